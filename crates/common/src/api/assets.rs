@@ -18,6 +18,24 @@ pub struct CLIMajorVersion {
     pub deprecation_date: Option<String>,
 }
 
+/// The checksums of the binaries published for a CLI release, keyed by `<os>-<arch>`
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CLIReleaseManifest {
+    pub version: String,
+    pub binaries: HashMap<String, CLIReleaseBinary>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CLIReleaseBinary {
+    pub sha256: String,
+}
+
+impl CLIReleaseManifest {
+    pub fn binary(&self, target: &str) -> Option<&CLIReleaseBinary> {
+        self.binaries.get(target)
+    }
+}
+
 pub struct AssetsClient {
     inner: GenericApiClient,
 }
@@ -28,8 +46,7 @@ impl ApiClient for AssetsClient {
     }
 
     fn base_url(&self) -> String {
-        let domain = std::env::var("EV_DOMAIN").unwrap_or_else(|_| String::from("evervault.com"));
-        format!("https://cli.{}", domain)
+        crate::endpoint::current().cli_assets_url()
     }
 
     fn auth(&self) -> &AuthMode {
@@ -81,4 +98,13 @@ impl AssetsClient {
             .handle_json_response::<CLIVersions>()
             .await
     }
+
+    pub async fn get_cli_release_manifest(&self, version: &str) -> ApiResult<CLIReleaseManifest> {
+        let manifest_url = format!("{}/{version}/manifest.json", self.base_url());
+        self.get(&manifest_url)
+            .send()
+            .await
+            .handle_json_response::<CLIReleaseManifest>()
+            .await
+    }
 }
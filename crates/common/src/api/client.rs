@@ -1,4 +1,4 @@
-use super::AuthMode;
+use super::{compat, rate_limit, AuthMode};
 use async_trait::async_trait;
 use reqwest::{Client, RequestBuilder, Response, StatusCode};
 use reqwest::{Error, Result as ReqwestResult};
@@ -58,16 +58,11 @@ pub trait ApiClient {
     fn client(&self) -> &Client;
 
     fn base_url(&self) -> String {
-        let domain = std::env::var("EV_DOMAIN").unwrap_or_else(|_| String::from("evervault.com"));
-        format!("https://api.{}", domain)
+        crate::endpoint::api_base_url()
     }
 
     fn keys_url(&self) -> String {
-        if self.base_url().contains("evervault.com") {
-            "https://keys.evervault.com".to_string()
-        } else {
-            "https://keys.evervault.io".to_string()
-        }
+        crate::endpoint::current().keys_url()
     }
 
     fn user_agent(&self) -> String {
@@ -109,11 +104,12 @@ pub trait ApiClient {
         request_builder = request_builder
             .header(reqwest::header::USER_AGENT, self.user_agent())
             .header(reqwest::header::ACCEPT, self.accept());
+        request_builder = compat::negotiate(request_builder);
 
         match &self.auth() {
             AuthMode::NoAuth => request_builder,
             AuthMode::ApiKey(api_key) => request_builder.header("api-key", api_key),
-            AuthMode::BearerAuth(token) => request_builder.bearer_auth(token),
+            AuthMode::BearerAuth(token) => request_builder.bearer_auth(token.access_token()),
             AuthMode::BasicAuth((app_uuid, api_key)) => {
                 request_builder.basic_auth(app_uuid, Some(api_key))
             }
@@ -131,7 +127,7 @@ pub trait HandleResponse {
 #[async_trait]
 impl HandleResponse for ReqwestResult<Response> {
     async fn handle_json_response<T: DeserializeOwned>(self) -> ApiResult<T> {
-        match self {
+        match self.inspect(compat::record_response) {
             Ok(res) if res.status().is_success() => res
                 .json()
                 .await
@@ -142,20 +138,26 @@ impl HandleResponse for ReqwestResult<Response> {
     }
 
     async fn handle_text_response(self) -> ApiResult<String> {
-        match self {
+        match self.inspect(compat::record_response) {
             Ok(res) if res.status().is_success() => res
                 .text()
                 .await
                 .map_err(|e| ApiError::new(ApiErrorKind::ParsingError(e.to_string()))),
-            Ok(res) => Err(res.status().into()),
+            Ok(res) => Err(ApiError::get_error_detais_from_res(res).await),
             Err(e) => Err(e.into()),
         }
     }
 
+    // The body isn't read here, so only the status and request id are available
     fn handle_no_op_response(self) -> ApiResult<()> {
-        match self {
+        match self.inspect(compat::record_response) {
             Ok(res) if res.status().is_success() => Ok(()),
-            Ok(res) => Err(res.status().into()),
+            Ok(res) => {
+                Err(
+                    ApiError::from_status(res.status().as_u16(), request_id_from_res(&res), None)
+                        .with_retry_after(rate_limit::retry_after(&res)),
+                )
+            }
             Err(e) => Err(e.into()),
         }
     }
@@ -169,13 +171,19 @@ pub enum ApiErrorKind {
     Internal,
     Forbidden,
     Conflict,
+    QuotaExceeded,
+    NameTaken,
+    VersionUnsupported,
+    /// The API rejected the request with a 429, with the seconds to wait from its Retry-After
+    RateLimited(Option<u64>),
     Unknown(Option<Error>),
     ParsingError(String),
 }
 
 pub struct ApiError {
     pub kind: ApiErrorKind,
-    pub details: Option<ApiErrorDetails>,
+    pub details: Option<Box<ApiErrorDetails>>,
+    pub request_id: Option<String>,
 }
 
 pub type ApiResult<T> = core::result::Result<T, ApiError>;
@@ -193,7 +201,10 @@ impl crate::CliError for ApiError {
             ApiErrorKind::Unauthorized => exitcode::NOUSER,
             ApiErrorKind::Internal | ApiErrorKind::ParsingError(_) => exitcode::SOFTWARE,
             ApiErrorKind::Forbidden => exitcode::NOPERM,
-            ApiErrorKind::Conflict => exitcode::DATAERR,
+            ApiErrorKind::Conflict | ApiErrorKind::NameTaken => exitcode::DATAERR,
+            ApiErrorKind::QuotaExceeded => exitcode::UNAVAILABLE,
+            ApiErrorKind::VersionUnsupported => exitcode::PROTOCOL,
+            ApiErrorKind::RateLimited(_) => exitcode::TEMPFAIL,
             ApiErrorKind::Unknown(_) => exitcode::UNAVAILABLE,
         }
     }
@@ -207,6 +218,19 @@ impl ApiErrorKind {
             Self::Forbidden => "403: Forbidden".to_owned(),
             Self::NotFound => "404: Not Found".to_owned(),
             Self::Conflict => "409: Conflict".to_owned(),
+            Self::QuotaExceeded => "Your plan's quota for this resource has been reached".to_owned(),
+            Self::NameTaken => "The name is already taken".to_owned(),
+            Self::VersionUnsupported => {
+                "This version of the CLI is no longer supported by the API. Run ev update to upgrade."
+                    .to_owned()
+            }
+            Self::RateLimited(retry_after) => {
+                let retry = match retry_after {
+                    Some(seconds) => format!("Try again in {seconds}s."),
+                    None => "Try again shortly.".to_owned(),
+                };
+                format!("429: The Evervault API is rate limiting requests from your team, which can happen when many deploys run at once. {retry}")
+            }
             Self::Internal => "500: Internal Server Error".to_owned(),
             Self::Unknown(e) => format!("An unexpected error occured: {:?}", e),
             Self::ParsingError(e) => {
@@ -223,7 +247,10 @@ impl From<ApiErrorKind> for exitcode::ExitCode {
             ApiErrorKind::Unauthorized => exitcode::NOUSER,
             ApiErrorKind::Internal | ApiErrorKind::ParsingError(_) => exitcode::SOFTWARE,
             ApiErrorKind::Forbidden => exitcode::NOPERM,
-            ApiErrorKind::Conflict => exitcode::DATAERR,
+            ApiErrorKind::Conflict | ApiErrorKind::NameTaken => exitcode::DATAERR,
+            ApiErrorKind::QuotaExceeded => exitcode::UNAVAILABLE,
+            ApiErrorKind::VersionUnsupported => exitcode::PROTOCOL,
+            ApiErrorKind::RateLimited(_) => exitcode::TEMPFAIL,
             ApiErrorKind::Unknown(_) => exitcode::UNAVAILABLE,
         }
     }
@@ -231,7 +258,7 @@ impl From<ApiErrorKind> for exitcode::ExitCode {
 
 impl From<StatusCode> for ApiError {
     fn from(status: StatusCode) -> Self {
-        Self::new(Self::get_error_from_status(status.into()))
+        Self::from_status(status.into(), None, None)
     }
 }
 
@@ -243,11 +270,16 @@ impl From<Error> for ApiError {
 
 impl std::fmt::Display for ApiError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match &self.details {
-            Some(details) => {
-                write!(f, "{}", details.title)
+        // The time remaining is only given by the kind, so it's shown over the body's message
+        match self.details.as_ref().and_then(|details| details.message()) {
+            Some(message) if !matches!(self.kind, ApiErrorKind::RateLimited(_)) => {
+                write!(f, "{message}")?
             }
-            None => self.kind.fmt(f),
+            _ => self.kind.fmt(f)?,
+        }
+        match self.request_id.as_deref() {
+            Some(request_id) => write!(f, " (request id: {request_id})"),
+            None => Ok(()),
         }
     }
 }
@@ -260,13 +292,42 @@ impl std::fmt::Debug for ApiError {
 
 impl std::error::Error for ApiError {}
 
-#[derive(serde::Deserialize, Clone, Debug)]
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// An error body returned by the API. Problem details (`title` and `detail`) and plain
+/// `message` bodies are both accepted.
+#[derive(serde::Deserialize, Clone, Debug, Default)]
 #[serde(rename_all = "snake_case")]
 pub struct ApiErrorDetails {
+    #[serde(default)]
     pub status: Option<u16>,
-    pub title: String,
-    pub detail: String,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub detail: Option<String>,
+    #[serde(default)]
+    pub message: Option<String>,
+    #[serde(default)]
     pub code: Option<String>,
+    #[serde(default, alias = "requestId")]
+    pub request_id: Option<String>,
+}
+
+impl ApiErrorDetails {
+    pub fn message(&self) -> Option<&str> {
+        self.title
+            .as_deref()
+            .or(self.message.as_deref())
+            .or(self.detail.as_deref())
+            .filter(|message| !message.is_empty())
+    }
+}
+
+fn request_id_from_res(res: &Response) -> Option<String> {
+    res.headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
 }
 
 impl ApiError {
@@ -274,6 +335,53 @@ impl ApiError {
         Self {
             kind,
             details: None,
+            request_id: None,
+        }
+    }
+
+    pub fn request_id(&self) -> Option<&str> {
+        self.request_id.as_deref()
+    }
+
+    pub fn code(&self) -> Option<&str> {
+        self.details
+            .as_ref()
+            .and_then(|details| details.code.as_deref())
+    }
+
+    /// Builds an error from a failed response, preferring the domain error given by the body's
+    /// code over the status code
+    pub fn from_status(
+        status: u16,
+        request_id: Option<String>,
+        details: Option<ApiErrorDetails>,
+    ) -> Self {
+        let kind = details
+            .as_ref()
+            .and_then(|details| details.code.as_deref())
+            .and_then(Self::get_error_from_code)
+            .unwrap_or_else(|| Self::get_error_from_status(status));
+        let request_id = request_id.or_else(|| {
+            details
+                .as_ref()
+                .and_then(|details| details.request_id.clone())
+        });
+        Self {
+            kind,
+            details: details.map(Box::new),
+            request_id,
+        }
+    }
+
+    pub fn get_error_from_code(code: &str) -> Option<ApiErrorKind> {
+        match code.to_ascii_lowercase().replace('_', "-").as_str() {
+            "quota-exceeded" | "limit-exceeded" => Some(ApiErrorKind::QuotaExceeded),
+            "name-taken" | "name-already-exists" | "already-exists" => {
+                Some(ApiErrorKind::NameTaken)
+            }
+            "version-unsupported" | "unsupported-version" => Some(ApiErrorKind::VersionUnsupported),
+            "rate-limited" | "too-many-requests" => Some(ApiErrorKind::RateLimited(None)),
+            _ => None,
         }
     }
 
@@ -283,16 +391,118 @@ impl ApiError {
             401 => ApiErrorKind::Unauthorized,
             403 => ApiErrorKind::Forbidden,
             404 => ApiErrorKind::NotFound,
+            406 => ApiErrorKind::VersionUnsupported,
             409 => ApiErrorKind::Conflict,
+            429 => ApiErrorKind::RateLimited(None),
             500 => ApiErrorKind::Internal,
             _ => ApiErrorKind::Unknown(None),
         }
     }
 
+    // A rate limited error carries the Retry-After of its response, so users know how long to wait
+    fn with_retry_after(mut self, retry_after: Option<Duration>) -> Self {
+        if let ApiErrorKind::RateLimited(seconds) = &mut self.kind {
+            *seconds = retry_after.map(|delay| delay.as_secs());
+        }
+        self
+    }
+
     pub async fn get_error_detais_from_res(res: Response) -> ApiError {
-        let mut api_error: ApiError = res.status().into();
-        api_error.details = res.json::<ApiErrorDetails>().await.ok();
+        let status = res.status().as_u16();
+        let request_id = request_id_from_res(&res);
+        let retry_after = rate_limit::retry_after(&res);
+        let details = res.json::<ApiErrorDetails>().await.ok();
+        Self::from_status(status, request_id, details).with_retry_after(retry_after)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn details(body: serde_json::Value) -> ApiErrorDetails {
+        serde_json::from_value(body).unwrap()
+    }
+
+    #[test]
+    fn test_problem_details_are_displayed_with_request_id() {
+        let error = ApiError::from_status(
+            400,
+            Some("req_123".into()),
+            Some(details(serde_json::json!({
+                "status": 400,
+                "title": "Invalid Enclave name",
+                "detail": "Names may only contain letters, numbers and dashes"
+            }))),
+        );
+        assert!(matches!(error.kind, ApiErrorKind::BadRequest));
+        assert_eq!(
+            error.to_string(),
+            "Invalid Enclave name (request id: req_123)"
+        );
+    }
+
+    #[test]
+    fn test_code_takes_precedence_over_status() {
+        let error = ApiError::from_status(
+            409,
+            None,
+            Some(details(serde_json::json!({
+                "code": "NAME_TAKEN",
+                "message": "An Enclave named hello already exists",
+                "requestId": "req_456"
+            }))),
+        );
+        assert!(matches!(error.kind, ApiErrorKind::NameTaken));
+        assert_eq!(error.request_id(), Some("req_456"));
+        assert_eq!(error.code(), Some("NAME_TAKEN"));
+        assert_eq!(
+            error.to_string(),
+            "An Enclave named hello already exists (request id: req_456)"
+        );
+    }
+
+    #[test]
+    fn test_domain_error_codes() {
+        assert!(matches!(
+            ApiError::get_error_from_code("quota-exceeded"),
+            Some(ApiErrorKind::QuotaExceeded)
+        ));
+        assert!(matches!(
+            ApiError::get_error_from_code("unsupported_version"),
+            Some(ApiErrorKind::VersionUnsupported)
+        ));
+        assert!(ApiError::get_error_from_code("something-else").is_none());
+    }
+
+    #[test]
+    fn test_rate_limited_errors_show_time_remaining() {
+        let error = ApiError::from_status(
+            429,
+            Some("req_429".into()),
+            Some(details(
+                serde_json::json!({ "message": "Too Many Requests" }),
+            )),
+        )
+        .with_retry_after(Some(Duration::from_secs(30)));
+        assert!(matches!(error.kind, ApiErrorKind::RateLimited(Some(30))));
+        assert_eq!(crate::CliError::exitcode(&error), exitcode::TEMPFAIL);
+        let message = error.to_string();
+        assert!(message.contains("rate limiting requests from your team"));
+        assert!(message.contains("Try again in 30s."));
+        assert!(message.ends_with("(request id: req_429)"));
+
+        let without_retry_after = ApiError::from_status(429, None, None).with_retry_after(None);
+        assert!(without_retry_after
+            .to_string()
+            .contains("Try again shortly."));
+    }
 
-        api_error
+    #[test]
+    fn test_status_without_details() {
+        let error = ApiError::from_status(406, Some("req_789".into()), None);
+        assert!(matches!(error.kind, ApiErrorKind::VersionUnsupported));
+        assert_eq!(crate::CliError::exitcode(&error), exitcode::PROTOCOL);
+        assert!(error.to_string().ends_with("(request id: req_789)"));
     }
 }
@@ -0,0 +1,268 @@
+use reqwest::header::HeaderMap;
+use reqwest::{RequestBuilder, Response};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+/// The newest response schema this CLI can parse. Sent as Accept-Version so the API answers with
+/// a schema no newer than this, even after it has moved on.
+pub const SCHEMA_VERSION: u32 = 2;
+const ACCEPT_VERSION_HEADER: &str = "accept-version";
+/// The schema version the API answered with
+const API_VERSION_HEADER: &str = "api-version";
+/// Sent with the features this CLI supports, and returned with the features the API supports
+const CAPABILITIES_HEADER: &str = "x-evervault-capabilities";
+/// Set by the API on responses from endpoints it's going to remove, with the date they stop
+/// working in Sunset
+const DEPRECATION_HEADER: &str = "deprecation";
+const SUNSET_HEADER: &str = "sunset";
+
+/// API features the CLI depends on, with the first response schema to include them
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Feature {
+    ErrorCodes,
+    BuildSteps,
+    ReplicaRollover,
+    MultipartUploads,
+}
+
+impl Feature {
+    pub const ALL: [Feature; 4] = [
+        Self::ErrorCodes,
+        Self::BuildSteps,
+        Self::ReplicaRollover,
+        Self::MultipartUploads,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::ErrorCodes => "error-codes",
+            Self::BuildSteps => "build-steps",
+            Self::ReplicaRollover => "replica-rollover",
+            Self::MultipartUploads => "multipart-uploads",
+        }
+    }
+
+    pub fn min_schema_version(&self) -> u32 {
+        match self {
+            Self::ErrorCodes => 1,
+            Self::BuildSteps | Self::ReplicaRollover | Self::MultipartUploads => 2,
+        }
+    }
+}
+
+fn capabilities() -> String {
+    Feature::ALL
+        .iter()
+        .map(Feature::name)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Adds the schema version and capabilities this CLI supports to a request
+pub fn negotiate(request_builder: RequestBuilder) -> RequestBuilder {
+    request_builder
+        .header(ACCEPT_VERSION_HEADER, SCHEMA_VERSION.to_string())
+        .header(CAPABILITIES_HEADER, capabilities())
+}
+
+/// What the API said it supports in its most recent response
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Negotiated {
+    pub api_version: Option<u32>,
+    pub capabilities: Option<Vec<String>>,
+    pub sunset: Option<String>,
+    pub deprecated: bool,
+}
+
+impl Negotiated {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+        };
+        Self {
+            api_version: header(API_VERSION_HEADER).and_then(|version| version.parse().ok()),
+            capabilities: header(CAPABILITIES_HEADER).map(|capabilities| {
+                capabilities
+                    .split(',')
+                    .map(|capability| capability.trim().to_ascii_lowercase())
+                    .filter(|capability| !capability.is_empty())
+                    .collect()
+            }),
+            sunset: header(SUNSET_HEADER).map(str::to_string),
+            deprecated: header(DEPRECATION_HEADER).is_some_and(|value| value != "false"),
+        }
+    }
+
+    /// Whether the API supports a feature. APIs which don't list their capabilities are assumed
+    /// to support everything in the schema version they answered with. None when the API gave
+    /// neither.
+    pub fn supports(&self, feature: Feature) -> Option<bool> {
+        match (&self.capabilities, self.api_version) {
+            (Some(capabilities), _) => Some(capabilities.iter().any(|c| c == feature.name())),
+            (None, Some(api_version)) => Some(api_version >= feature.min_schema_version()),
+            (None, None) => None,
+        }
+    }
+
+    pub fn report(&self) -> CompatReport {
+        CompatReport {
+            cli_version: env!("CARGO_PKG_VERSION").to_string(),
+            cli_schema_version: SCHEMA_VERSION,
+            api_schema_version: self.api_version,
+            features: Feature::ALL
+                .iter()
+                .map(|feature| FeatureSupport {
+                    name: feature.name().to_string(),
+                    min_schema_version: feature.min_schema_version(),
+                    supported: self.supports(*feature),
+                })
+                .collect(),
+        }
+    }
+}
+
+static NEGOTIATED: Mutex<Option<Negotiated>> = Mutex::new(None);
+static DEPRECATION_WARNED: AtomicBool = AtomicBool::new(false);
+
+/// Records what the API negotiated in a response, warning once per process when it has
+/// deprecated an endpoint the CLI used
+pub fn record_response(response: &Response) {
+    let negotiated = Negotiated::from_headers(response.headers());
+    if negotiated.deprecated && !DEPRECATION_WARNED.swap(true, Ordering::Relaxed) {
+        match negotiated.sunset.as_deref() {
+            Some(sunset) => log::warn!("This version of the CLI uses an API which is deprecated and will stop working on {sunset}. Run ev update to upgrade."),
+            None => log::warn!("This version of the CLI uses an API which is deprecated. Run ev update to upgrade."),
+        }
+    }
+    if negotiated.api_version.is_none() && negotiated.capabilities.is_none() {
+        return;
+    }
+    if let Some(api_version) = negotiated.api_version.filter(|v| *v < SCHEMA_VERSION) {
+        log::debug!("The API answered with schema version {api_version}, older than this CLI's {SCHEMA_VERSION}. Features it doesn't support will be skipped.");
+    }
+    *NEGOTIATED.lock().expect("Negotiated schema lock poisoned") = Some(negotiated);
+}
+
+/// What the API negotiated in the most recent response, if it has answered any
+pub fn negotiated() -> Option<Negotiated> {
+    NEGOTIATED
+        .lock()
+        .expect("Negotiated schema lock poisoned")
+        .clone()
+}
+
+/// Whether a feature can be used with the API. Features are assumed supported until the API says
+/// otherwise, so an API which predates negotiation behaves as it always has.
+pub fn is_supported(feature: Feature) -> bool {
+    negotiated()
+        .and_then(|negotiated| negotiated.supports(feature))
+        .unwrap_or(true)
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeatureSupport {
+    pub name: String,
+    pub min_schema_version: u32,
+    /// None when the API didn't say which features it supports
+    pub supported: Option<bool>,
+}
+
+/// The features this CLI and the API both support
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompatReport {
+    pub cli_version: String,
+    pub cli_schema_version: u32,
+    pub api_schema_version: Option<u32>,
+    pub features: Vec<FeatureSupport>,
+}
+
+/// A response field the API has deprecated. It's read while the API still sends it, but missing
+/// or null values are accepted so responses keep parsing once the API drops it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Deprecated<T>(Option<T>);
+
+impl<T> Default for Deprecated<T> {
+    fn default() -> Self {
+        Self(None)
+    }
+}
+
+impl<T> From<Option<T>> for Deprecated<T> {
+    fn from(value: Option<T>) -> Self {
+        Self(value)
+    }
+}
+
+impl<T> Deprecated<T> {
+    pub fn get(&self) -> Option<&T> {
+        self.0.as_ref()
+    }
+
+    pub fn into_inner(self) -> Option<T> {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn headers(values: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in values {
+            headers.insert(*name, HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    #[test]
+    fn test_negotiated_from_headers() {
+        let negotiated = Negotiated::from_headers(&headers(&[
+            ("api-version", "1"),
+            ("deprecation", "true"),
+            ("sunset", "Sat, 01 Nov 2026 00:00:00 GMT"),
+        ]));
+        assert_eq!(negotiated.api_version, Some(1));
+        assert!(negotiated.deprecated);
+        assert_eq!(negotiated.supports(Feature::ErrorCodes), Some(true));
+        assert_eq!(negotiated.supports(Feature::BuildSteps), Some(false));
+
+        // Listed capabilities take precedence over the schema version
+        let negotiated = Negotiated::from_headers(&headers(&[
+            ("api-version", "2"),
+            ("x-evervault-capabilities", "error-codes, Build-Steps"),
+        ]));
+        assert_eq!(negotiated.supports(Feature::BuildSteps), Some(true));
+        assert_eq!(negotiated.supports(Feature::MultipartUploads), Some(false));
+
+        let negotiated = Negotiated::from_headers(&HeaderMap::new());
+        assert_eq!(negotiated.supports(Feature::ErrorCodes), None);
+        let report = negotiated.report();
+        assert_eq!(report.features.len(), Feature::ALL.len());
+        assert!(report.features.iter().all(|f| f.supported.is_none()));
+    }
+
+    #[test]
+    fn test_deprecated_fields() {
+        #[derive(Deserialize)]
+        struct Body {
+            #[serde(default)]
+            old: Deprecated<String>,
+        }
+        let present: Body = serde_json::from_str(r#"{"old":"value"}"#).unwrap();
+        assert_eq!(present.old.get().map(String::as_str), Some("value"));
+        let null: Body = serde_json::from_str(r#"{"old":null}"#).unwrap();
+        assert_eq!(null.old.get(), None);
+        let missing: Body = serde_json::from_str("{}").unwrap();
+        assert_eq!(missing.old.into_inner(), None);
+    }
+}
@@ -20,6 +20,53 @@ pub struct RuntimeVersion {
 pub struct RuntimeMajorVersion {
     pub latest: String,
     pub installer: String,
+    /// The release being trialled before it's promoted to latest, if there is one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub candidate: Option<RuntimeRelease>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct RuntimeRelease {
+    pub latest: String,
+    pub installer: String,
+}
+
+/// Which data plane and installer releases an Enclave is built with
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum ReleaseChannel {
+    #[default]
+    Stable,
+    Candidate,
+}
+
+impl ReleaseChannel {
+    pub fn is_stable(&self) -> bool {
+        matches!(self, Self::Stable)
+    }
+}
+
+impl std::fmt::Display for ReleaseChannel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Stable => write!(f, "stable"),
+            Self::Candidate => write!(f, "candidate"),
+        }
+    }
+}
+
+impl RuntimeMajorVersion {
+    /// The data plane and installer versions for the channel. The candidate channel follows stable
+    /// while there's no release candidate.
+    pub fn release(&self, channel: ReleaseChannel) -> RuntimeRelease {
+        match (channel, self.candidate.as_ref()) {
+            (ReleaseChannel::Candidate, Some(candidate)) => candidate.clone(),
+            _ => RuntimeRelease {
+                latest: self.latest.clone(),
+                installer: self.installer.clone(),
+            },
+        }
+    }
 }
 
 pub struct EnclaveAssetsClient {
@@ -32,8 +79,7 @@ impl ApiClient for EnclaveAssetsClient {
     }
 
     fn base_url(&self) -> String {
-        let domain = std::env::var("EV_DOMAIN").unwrap_or_else(|_| String::from("evervault.com"));
-        format!("https://enclave-build-assets.{}", domain)
+        crate::endpoint::current().enclave_assets_url()
     }
 
     fn auth(&self) -> &AuthMode {
@@ -71,6 +117,16 @@ impl EnclaveAssetsClient {
             .map(|version| version.installer)
     }
 
+    /// Requests a build asset, returning the response so large assets can be streamed
+    pub async fn get_asset(&self, path: &str) -> ApiResult<reqwest::Response> {
+        let asset_url = format!("{}/{path}", self.base_url());
+        match self.get(&asset_url).send().await {
+            Ok(res) if res.status().is_success() => Ok(res),
+            Ok(res) => Err(ApiError::get_error_detais_from_res(res).await),
+            Err(e) => Err(e.into()),
+        }
+    }
+
     pub async fn get_runtime_versions(&self) -> ApiResult<RuntimeMajorVersion> {
         let runtime_major_version = get_runtime_major_version();
         let data_plane_version = format!("{}/runtime/versions", self.base_url());
@@ -86,3 +142,33 @@ impl EnclaveAssetsClient {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_release_for_channel() {
+        let versions: RuntimeMajorVersion = serde_json::from_str(
+            r#"{"latest": "1.2.0", "installer": "abc", "candidate": {"latest": "1.3.0-rc.1", "installer": "def"}}"#,
+        )
+        .unwrap();
+        assert_eq!(versions.release(ReleaseChannel::Stable).latest, "1.2.0");
+        assert_eq!(
+            versions.release(ReleaseChannel::Candidate),
+            RuntimeRelease {
+                latest: "1.3.0-rc.1".into(),
+                installer: "def".into()
+            }
+        );
+
+        let without_candidate: RuntimeMajorVersion =
+            serde_json::from_str(r#"{"latest": "1.2.0", "installer": "abc"}"#).unwrap();
+        assert_eq!(
+            without_candidate
+                .release(ReleaseChannel::Candidate)
+                .installer,
+            "abc"
+        );
+    }
+}
@@ -1,8 +1,11 @@
 pub mod assets;
 pub mod client;
+pub mod compat;
 pub mod enclave_assets;
 pub mod function;
+pub mod oauth;
 pub mod papi;
+pub mod rate_limit;
 pub use reqwest::Client;
 
 pub type BasicAuth = (String, String);
@@ -11,6 +14,6 @@ pub type BasicAuth = (String, String);
 pub enum AuthMode {
     NoAuth,
     ApiKey(String),
-    BearerAuth(String),
+    BearerAuth(oauth::BearerToken),
     BasicAuth(BasicAuth),
 }
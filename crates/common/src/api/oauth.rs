@@ -0,0 +1,313 @@
+use super::client::{ApiClient, ApiClientError, GenericApiClient};
+use super::AuthMode;
+use reqwest::Client;
+use serde::Deserialize;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// The OAuth client the CLI authenticates as
+pub const CLIENT_ID: &str = "evervault-cli";
+const DEVICE_CODE_GRANT: &str = "urn:ietf:params:oauth:grant-type:device_code";
+const REFRESH_TOKEN_GRANT: &str = "refresh_token";
+// Polling is slowed by this much each time the server asks, per RFC 8628
+const SLOW_DOWN_INCREMENT: Duration = Duration::from_secs(5);
+// Access tokens are refreshed this long before they expire, so in-flight requests never use an
+// expired token
+const REFRESH_MARGIN: Duration = Duration::from_secs(60);
+// Servers which don't say when access tokens expire are assumed to issue hour long tokens
+const DEFAULT_TOKEN_LIFETIME: Duration = Duration::from_secs(3600);
+
+#[derive(Debug, Error)]
+pub enum OAuthError {
+    #[error("The login code expired before it was approved. Run ev login again.")]
+    ExpiredDeviceCode,
+    #[error("The login request was denied")]
+    AccessDenied,
+    #[error("The login session has expired or been revoked. Run ev login again.")]
+    InvalidGrant,
+    #[error("The login request was rejected - {0}")]
+    Rejected(String),
+    #[error("An error occurred contacting the Evervault API - {0}")]
+    Request(#[from] reqwest::Error),
+}
+
+impl crate::CliError for OAuthError {
+    fn exitcode(&self) -> exitcode::ExitCode {
+        match self {
+            Self::ExpiredDeviceCode | Self::InvalidGrant => exitcode::NOUSER,
+            Self::AccessDenied => exitcode::NOPERM,
+            Self::Rejected(_) => exitcode::PROTOCOL,
+            Self::Request(_) => exitcode::UNAVAILABLE,
+        }
+    }
+}
+
+/// A pending device login, which the user approves by visiting the verification uri and
+/// entering the user code
+#[derive(Clone, Debug, Deserialize)]
+pub struct DeviceAuthorization {
+    pub device_code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    /// The verification uri with the user code filled in, for browsers opened by the CLI
+    pub verification_uri_complete: Option<String>,
+    pub expires_in: u64,
+    #[serde(default = "default_poll_interval")]
+    pub interval: u64,
+}
+
+fn default_poll_interval() -> u64 {
+    5
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub refresh_token: Option<String>,
+    pub expires_in: Option<u64>,
+}
+
+impl TokenResponse {
+    fn lifetime(&self) -> Duration {
+        self.expires_in
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_TOKEN_LIFETIME)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenErrorResponse {
+    error: String,
+    error_description: Option<String>,
+}
+
+// The outcome of a single token request. Pending logins aren't errors while polling.
+enum TokenPoll {
+    Issued(TokenResponse),
+    Pending,
+    SlowDown,
+}
+
+impl TokenErrorResponse {
+    fn into_poll_result(self) -> Result<TokenPoll, OAuthError> {
+        match self.error.as_str() {
+            "authorization_pending" => Ok(TokenPoll::Pending),
+            "slow_down" => Ok(TokenPoll::SlowDown),
+            "expired_token" => Err(OAuthError::ExpiredDeviceCode),
+            "access_denied" => Err(OAuthError::AccessDenied),
+            "invalid_grant" => Err(OAuthError::InvalidGrant),
+            _ => Err(OAuthError::Rejected(
+                self.error_description.unwrap_or(self.error),
+            )),
+        }
+    }
+}
+
+/// Client for the OAuth device authorization flow (RFC 8628) used by `ev login`
+#[derive(Clone, Default)]
+pub struct OAuthClient {
+    inner: GenericApiClient,
+}
+
+impl ApiClient for OAuthClient {
+    fn auth(&self) -> &AuthMode {
+        self.inner.auth()
+    }
+
+    fn update_auth(&mut self, _: AuthMode) -> Result<(), ApiClientError> {
+        Err(ApiClientError::AuthModeNotSupported)
+    }
+
+    fn client(&self) -> &Client {
+        self.inner.client()
+    }
+
+    fn base_url(&self) -> String {
+        format!("{}/oauth", crate::endpoint::api_base_url())
+    }
+}
+
+impl OAuthClient {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn request_device_authorization(&self) -> Result<DeviceAuthorization, OAuthError> {
+        let response = self
+            .post(&format!("{}/device/code", self.base_url()))
+            .form(&[("client_id", CLIENT_ID)])
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(OAuthError::Rejected(response.status().to_string()));
+        }
+        Ok(response.json().await?)
+    }
+
+    async fn request_token(&self, params: &[(&str, &str)]) -> Result<TokenPoll, OAuthError> {
+        let response = self
+            .post(&format!("{}/token", self.base_url()))
+            .form(params)
+            .send()
+            .await?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(TokenPoll::Issued(response.json().await?));
+        }
+        match response.json::<TokenErrorResponse>().await {
+            Ok(error) => error.into_poll_result(),
+            Err(_) => Err(OAuthError::Rejected(status.to_string())),
+        }
+    }
+
+    /// Polls until the user approves or denies the login, or the device code expires
+    pub async fn poll_for_token(
+        &self,
+        authorization: &DeviceAuthorization,
+    ) -> Result<TokenResponse, OAuthError> {
+        let deadline = Instant::now() + Duration::from_secs(authorization.expires_in);
+        let mut interval = Duration::from_secs(authorization.interval.max(1));
+        let params = [
+            ("grant_type", DEVICE_CODE_GRANT),
+            ("device_code", authorization.device_code.as_str()),
+            ("client_id", CLIENT_ID),
+        ];
+        loop {
+            tokio::time::sleep(interval).await;
+            if Instant::now() >= deadline {
+                return Err(OAuthError::ExpiredDeviceCode);
+            }
+            match self.request_token(&params).await? {
+                TokenPoll::Issued(tokens) => return Ok(tokens),
+                TokenPoll::Pending => {}
+                TokenPoll::SlowDown => interval += SLOW_DOWN_INCREMENT,
+            }
+        }
+    }
+
+    pub async fn refresh(&self, refresh_token: &str) -> Result<TokenResponse, OAuthError> {
+        let params = [
+            ("grant_type", REFRESH_TOKEN_GRANT),
+            ("refresh_token", refresh_token),
+            ("client_id", CLIENT_ID),
+        ];
+        match self.request_token(&params).await? {
+            TokenPoll::Issued(tokens) => Ok(tokens),
+            TokenPoll::Pending | TokenPoll::SlowDown => Err(OAuthError::Rejected(
+                "the refresh request was not completed".to_string(),
+            )),
+        }
+    }
+}
+
+/// An access token for bearer auth. Tokens from a login session are refreshed in the background
+/// before they expire, so long running commands such as deploys keep working.
+#[derive(Clone)]
+pub struct BearerToken {
+    access_token: Arc<RwLock<String>>,
+}
+
+impl BearerToken {
+    pub fn new(access_token: String) -> Self {
+        Self {
+            access_token: Arc::new(RwLock::new(access_token)),
+        }
+    }
+
+    /// Starts refreshing the session's access token before it expires. `on_refresh` is called
+    /// with each new refresh token the server issues, so it can be persisted.
+    pub fn refreshing<F>(tokens: TokenResponse, refresh_token: String, on_refresh: F) -> Self
+    where
+        F: Fn(&str) + Send + Sync + 'static,
+    {
+        let token = Self::new(tokens.access_token.clone());
+        let access_token = token.access_token.clone();
+        tokio::spawn(async move {
+            let client = OAuthClient::new();
+            let mut refresh_token = refresh_token;
+            let mut lifetime = tokens.lifetime();
+            loop {
+                tokio::time::sleep(lifetime.saturating_sub(REFRESH_MARGIN)).await;
+                let tokens = match client.refresh(&refresh_token).await {
+                    Ok(tokens) => tokens,
+                    Err(e) => {
+                        log::warn!("Failed to refresh the login session - {e}");
+                        return;
+                    }
+                };
+                if let Some(rotated) = tokens.refresh_token.as_deref() {
+                    on_refresh(rotated);
+                    refresh_token = rotated.to_string();
+                }
+                lifetime = tokens.lifetime();
+                *access_token.write().expect("Bearer token lock poisoned") = tokens.access_token;
+                log::debug!("Refreshed the login session's access token");
+            }
+        });
+        token
+    }
+
+    pub fn access_token(&self) -> String {
+        self.access_token
+            .read()
+            .expect("Bearer token lock poisoned")
+            .clone()
+    }
+}
+
+impl std::fmt::Debug for BearerToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "BearerToken(..)")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn token_error(error: &str) -> TokenErrorResponse {
+        TokenErrorResponse {
+            error: error.to_string(),
+            error_description: None,
+        }
+    }
+
+    #[test]
+    fn test_token_error_responses() {
+        assert!(matches!(
+            token_error("authorization_pending").into_poll_result(),
+            Ok(TokenPoll::Pending)
+        ));
+        assert!(matches!(
+            token_error("slow_down").into_poll_result(),
+            Ok(TokenPoll::SlowDown)
+        ));
+        assert!(matches!(
+            token_error("expired_token").into_poll_result(),
+            Err(OAuthError::ExpiredDeviceCode)
+        ));
+        assert!(matches!(
+            token_error("invalid_grant").into_poll_result(),
+            Err(OAuthError::InvalidGrant)
+        ));
+        let described = TokenErrorResponse {
+            error: "invalid_client".to_string(),
+            error_description: Some("Unknown client".to_string()),
+        };
+        assert!(matches!(
+            described.into_poll_result(),
+            Err(OAuthError::Rejected(reason)) if reason == "Unknown client"
+        ));
+    }
+
+    #[test]
+    fn test_device_authorization_defaults() {
+        let authorization: DeviceAuthorization = serde_json::from_str(
+            r#"{"device_code": "dc", "user_code": "ABCD-EFGH", "verification_uri": "https://app.evervault.com/device", "expires_in": 900}"#,
+        )
+        .unwrap();
+        assert_eq!(authorization.interval, 5);
+        assert!(authorization.verification_uri_complete.is_none());
+    }
+}
@@ -1,6 +1,7 @@
 use std::fs::File;
 
 use self::client::{ApiError, ApiErrorKind, ApiResult, HandleResponse};
+use self::rate_limit::RateLimitedRequest;
 use crate::function::{
     CreateFunctionResponse, Function, FunctionDeployment, FunctionDeploymentCredentials,
     GetFunctionEnvironmentResponse, GetFunctionResponse,
@@ -27,8 +28,7 @@ impl ApiClient for EvApiClient {
     }
 
     fn base_url(&self) -> String {
-        let domain = std::env::var("EV_DOMAIN").unwrap_or_else(|_| String::from("evervault.com"));
-        format!("https://api.{}", domain)
+        crate::endpoint::api_base_url()
     }
 
     fn auth(&self) -> &AuthMode {
@@ -114,7 +114,7 @@ impl EvApi for EvApiClient {
                 authentication: relay.authentication.clone(),
                 routes: relay.routes.clone(),
             })
-            .send()
+            .send_rate_limited()
             .await
             .handle_json_response()
             .await
@@ -124,7 +124,7 @@ impl EvApi for EvApiClient {
         let create_relay_url = format!("{}/relays", self.base_url());
         self.post(&create_relay_url)
             .json(&relay)
-            .send()
+            .send_rate_limited()
             .await
             .handle_json_response()
             .await
@@ -135,7 +135,7 @@ impl EvApi for EvApiClient {
             "https://github.com/evervault/template-{}-hello-function/archive/master.zip",
             lang
         );
-        match self.client().get(&url).send().await {
+        match self.client().get(&url).send_rate_limited().await {
             Ok(res) if res.status().is_success() => {
                 let mut tmpfile = tempfile::tempfile().unwrap();
                 let bytes = res
@@ -161,7 +161,7 @@ impl EvApi for EvApiClient {
 
         self.get(&url)
             .header("api-key", &self.api_key)
-            .send()
+            .send_rate_limited()
             .await
             .handle_json_response::<GetFunctionResponse>()
             .await
@@ -180,7 +180,7 @@ impl EvApi for EvApiClient {
 
         self.get(&url)
             .header("api-key", &self.api_key)
-            .send()
+            .send_rate_limited()
             .await
             .handle_json_response()
             .await
@@ -197,7 +197,7 @@ impl EvApi for EvApiClient {
             .json(&json!({
                 "name": function_name,
             }))
-            .send()
+            .send_rate_limited()
             .await
             .handle_json_response::<CreateFunctionResponse>()
             .await
@@ -218,7 +218,7 @@ impl EvApi for EvApiClient {
                     .to_string(),
             )
             .body(function)
-            .send()
+            .send_rate_limited()
             .await
             .handle_no_op_response()
     }
@@ -237,7 +237,7 @@ impl EvApi for EvApiClient {
 
         self.get(&url)
             .header("api-key", &self.api_key)
-            .send()
+            .send_rate_limited()
             .await
             .handle_json_response::<FunctionDeployment>()
             .await
@@ -248,7 +248,7 @@ impl EvApi for EvApiClient {
 
         self.delete(&url)
             .header("api-key", &self.api_key)
-            .send()
+            .send_rate_limited()
             .await
             .handle_no_op_response()
     }
@@ -267,7 +267,7 @@ impl EvApi for EvApiClient {
 
         self.get(&url)
             .header("api-key", &self.api_key)
-            .send()
+            .send_rate_limited()
             .await
             .handle_json_response()
             .await
@@ -282,7 +282,7 @@ impl EvApi for EvApiClient {
 
         self.get(&url)
             .header("api-key", &self.api_key)
-            .send()
+            .send_rate_limited()
             .await
             .handle_json_response::<GetFunctionEnvironmentResponse>()
             .await
@@ -311,7 +311,7 @@ impl EvApi for EvApiClient {
         self.put(&url)
             .header("api-key", &self.api_key)
             .json(&body)
-            .send()
+            .send_rate_limited()
             .await
             .handle_json_response()
             .await
@@ -331,7 +331,7 @@ impl EvApi for EvApiClient {
 
         self.delete(&url)
             .header("api-key", &self.api_key)
-            .send()
+            .send_rate_limited()
             .await
             .handle_json_response()
             .await
@@ -348,7 +348,7 @@ impl EvApi for EvApiClient {
         self.post(&url)
             .json(&json!({ "payload": payload, "async": is_async }))
             .header("content-type", "application/json")
-            .send()
+            .send_rate_limited()
             .await
             .handle_json_response()
             .await
@@ -360,7 +360,7 @@ impl EvApi for EvApiClient {
         self.post(&url)
             .header("content-type", "application/json")
             .json(&value)
-            .send()
+            .send_rate_limited()
             .await
             .handle_json_response()
             .await
@@ -372,7 +372,7 @@ impl EvApi for EvApiClient {
         self.post(&url)
             .header("content-type", "application/json")
             .json(&value)
-            .send()
+            .send_rate_limited()
             .await
             .handle_json_response()
             .await
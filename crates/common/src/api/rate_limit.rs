@@ -0,0 +1,199 @@
+use async_trait::async_trait;
+use reqwest::header::RETRY_AFTER;
+use reqwest::{RequestBuilder, Response, Result as ReqwestResult, StatusCode};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Sustained requests per second allowed to the Evervault API. Set to 0 to disable rate limiting.
+pub const RATE_LIMIT_ENV_VAR: &str = "EV_API_RATE_LIMIT";
+/// Number of requests which can be made in a burst before the sustained rate applies
+pub const RATE_LIMIT_BURST_ENV_VAR: &str = "EV_API_RATE_LIMIT_BURST";
+const DEFAULT_REQUESTS_PER_SECOND: f64 = 5.0;
+const DEFAULT_BURST: f64 = 10.0;
+// Requests which are rejected with a 429 are retried after the server's Retry-After
+const MAX_RATE_LIMITED_RETRIES: u32 = 3;
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(5);
+
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+    paused_until: Option<Instant>,
+    retry_after_hint: Option<Duration>,
+}
+
+/// A token bucket shared by every request made to the API from this process
+pub struct RateLimiter {
+    requests_per_second: f64,
+    burst: f64,
+    bucket: Mutex<Bucket>,
+}
+
+impl RateLimiter {
+    pub fn new(requests_per_second: f64, burst: f64) -> Self {
+        let burst = burst.max(1.0);
+        Self {
+            requests_per_second,
+            burst,
+            bucket: Mutex::new(Bucket {
+                tokens: burst,
+                last_refill: Instant::now(),
+                paused_until: None,
+                retry_after_hint: None,
+            }),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let read_var = |name: &str, default: f64| {
+            std::env::var(name)
+                .ok()
+                .and_then(|value| value.parse::<f64>().ok())
+                .filter(|value| value.is_finite() && *value >= 0.0)
+                .unwrap_or(default)
+        };
+        Self::new(
+            read_var(RATE_LIMIT_ENV_VAR, DEFAULT_REQUESTS_PER_SECOND),
+            read_var(RATE_LIMIT_BURST_ENV_VAR, DEFAULT_BURST),
+        )
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.requests_per_second > 0.0
+    }
+
+    // Takes a token if one is available, otherwise returns how long to wait before trying again
+    fn try_acquire(&self, now: Instant) -> Result<(), Duration> {
+        let mut bucket = self.bucket.lock().expect("Rate limiter lock poisoned");
+        if let Some(paused_until) = bucket.paused_until.filter(|until| *until > now) {
+            return Err(paused_until - now);
+        }
+        if !self.is_enabled() {
+            return Ok(());
+        }
+
+        let elapsed = now.saturating_duration_since(bucket.last_refill);
+        bucket.tokens =
+            (bucket.tokens + elapsed.as_secs_f64() * self.requests_per_second).min(self.burst);
+        bucket.last_refill = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - bucket.tokens) / self.requests_per_second,
+            ))
+        }
+    }
+
+    /// Waits until a request can be made without exceeding the rate limit
+    pub async fn acquire(&self) {
+        while let Err(wait) = self.try_acquire(Instant::now()) {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Holds back all requests until `retry_after` has passed, and records it as a hint for
+    /// polling loops
+    pub fn pause(&self, retry_after: Duration) {
+        let mut bucket = self.bucket.lock().expect("Rate limiter lock poisoned");
+        bucket.paused_until = Some(Instant::now() + retry_after);
+        bucket.retry_after_hint = Some(retry_after);
+    }
+
+    /// The most recent Retry-After received from the API, if it hasn't already been taken
+    pub fn take_retry_after_hint(&self) -> Option<Duration> {
+        self.bucket
+            .lock()
+            .expect("Rate limiter lock poisoned")
+            .retry_after_hint
+            .take()
+    }
+}
+
+pub fn global() -> &'static RateLimiter {
+    static RATE_LIMITER: OnceLock<RateLimiter> = OnceLock::new();
+    RATE_LIMITER.get_or_init(RateLimiter::from_env)
+}
+
+/// The delay the API asked for in a response's Retry-After header, in seconds
+pub fn retry_after(response: &Response) -> Option<Duration> {
+    response
+        .headers()
+        .get(RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .map(Duration::from_secs)
+}
+
+#[async_trait]
+pub trait RateLimitedRequest {
+    /// Sends the request once the global rate limiter allows it, retrying requests which are
+    /// rejected with a 429 after the delay requested by the API
+    async fn send_rate_limited(self) -> ReqwestResult<Response>;
+}
+
+#[async_trait]
+impl RateLimitedRequest for RequestBuilder {
+    async fn send_rate_limited(self) -> ReqwestResult<Response> {
+        let limiter = global();
+        let mut request = self;
+        let mut attempts = 0;
+        loop {
+            limiter.acquire().await;
+            // streamed bodies can't be cloned, so those requests are only sent once
+            let retry = request.try_clone();
+            let response = request.send().await?;
+            if response.status() != StatusCode::TOO_MANY_REQUESTS {
+                return Ok(response);
+            }
+            // Requests which won't be retried still pause the others, so polling loops back off
+            let retry_after = retry_after(&response).unwrap_or(DEFAULT_RETRY_AFTER);
+            limiter.pause(retry_after);
+            match retry {
+                Some(retry) if attempts < MAX_RATE_LIMITED_RETRIES => {
+                    log::debug!(
+                        "Rate limited by the Evervault API, retrying in {}s",
+                        retry_after.as_secs()
+                    );
+                    attempts += 1;
+                    request = retry;
+                }
+                _ => return Ok(response),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket() {
+        let limiter = RateLimiter::new(2.0, 2.0);
+        let now = Instant::now();
+        assert!(limiter.try_acquire(now).is_ok());
+        assert!(limiter.try_acquire(now).is_ok());
+        let wait = limiter.try_acquire(now).unwrap_err();
+        assert_eq!(wait, Duration::from_millis(500));
+
+        // tokens refill at the sustained rate
+        assert!(limiter
+            .try_acquire(now + Duration::from_millis(500))
+            .is_ok());
+
+        limiter.pause(Duration::from_secs(30));
+        assert!(limiter.try_acquire(Instant::now()).is_err());
+        assert_eq!(
+            limiter.take_retry_after_hint(),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(limiter.take_retry_after_hint(), None);
+
+        let disabled = RateLimiter::new(0.0, 1.0);
+        for _ in 0..100 {
+            assert!(disabled.try_acquire(now).is_ok());
+        }
+    }
+}
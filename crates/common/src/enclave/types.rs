@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+use thiserror::Error;
 
 // Tracking which FS elements have been created during signing
 #[derive(Debug, PartialEq)]
@@ -49,6 +50,9 @@ impl CleanUpMode {
     }
 }
 
+// The hash algorithm nitro-cli reports for the PCRs it measures
+const HASH_ALGORITHM: &str = "Sha384 { ... }";
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct EIFMeasurements {
     #[serde(rename = "HashAlgorithm")]
@@ -60,6 +64,14 @@ pub struct EIFMeasurements {
 }
 
 impl EIFMeasurements {
+    pub fn new(pcrs: PCRs, signature: Option<String>) -> Self {
+        Self {
+            hash_algorithm: HASH_ALGORITHM.to_string(),
+            pcrs,
+            signature,
+        }
+    }
+
     pub fn pcrs(&self) -> &PCRs {
         &self.pcrs
     }
@@ -73,36 +85,143 @@ impl EIFMeasurements {
     }
 }
 
+// Every PCR nitro-cli measures is a SHA-384 digest, hex encoded
+const PCR_HEX_LEN: usize = 96;
+
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum PcrError {
+    #[error("PCRs are {PCR_HEX_LEN} hex characters, but {0:?} has {1}")]
+    InvalidLength(String, usize),
+    #[error("PCRs are hex encoded, but {0:?} isn't")]
+    InvalidHex(String),
+}
+
+/// A PCR value, normalized to lowercase hex so values which only differ in case or surrounding
+/// whitespace are equal
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq, Hash)]
+#[serde(try_from = "String", into = "String")]
+pub struct Pcr(String);
+
+impl Pcr {
+    pub fn parse(value: &str) -> Result<Self, PcrError> {
+        let value = value.trim();
+        if !value.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(PcrError::InvalidHex(value.to_string()));
+        }
+        if value.len() != PCR_HEX_LEN {
+            return Err(PcrError::InvalidLength(value.to_string(), value.len()));
+        }
+        Ok(Self(value.to_ascii_lowercase()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::str::FromStr for Pcr {
+    type Err = PcrError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Self::parse(value)
+    }
+}
+
+impl TryFrom<String> for Pcr {
+    type Error = PcrError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::parse(&value)
+    }
+}
+
+impl From<Pcr> for String {
+    fn from(pcr: Pcr) -> Self {
+        pcr.0
+    }
+}
+
+impl std::ops::Deref for Pcr {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for Pcr {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for Pcr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
 // Isolated PCRs from remainder of the measures to use in API requests
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
 pub struct PCRs {
     #[serde(rename = "PCR0")]
-    pub pcr0: String,
+    pub pcr0: Pcr,
     #[serde(rename = "PCR1")]
-    pub pcr1: String,
+    pub pcr1: Pcr,
     #[serde(rename = "PCR2")]
-    pub pcr2: String,
+    pub pcr2: Pcr,
     #[serde(rename = "PCR8")]
-    pub pcr8: Option<String>,
+    pub pcr8: Option<Pcr>,
+}
+
+/// A PCR which differs between two sets of PCRs, by name such as `PCR0`
+#[derive(Debug, PartialEq, Eq)]
+pub struct PcrDifference<'a> {
+    pub name: &'static str,
+    pub left: Option<&'a Pcr>,
+    pub right: Option<&'a Pcr>,
+}
+
+impl PCRs {
+    /// Each PCR by name, in index order. PCR8 is only present for signed EIFs.
+    pub fn named(&self) -> [(&'static str, Option<&Pcr>); 4] {
+        [
+            ("PCR0", Some(&self.pcr0)),
+            ("PCR1", Some(&self.pcr1)),
+            ("PCR2", Some(&self.pcr2)),
+            ("PCR8", self.pcr8.as_ref()),
+        ]
+    }
+
+    /// The PCRs which differ from `other`'s, with `self`'s on the left
+    pub fn differences<'a>(&'a self, other: &'a PCRs) -> Vec<PcrDifference<'a>> {
+        self.named()
+            .into_iter()
+            .zip(other.named())
+            .filter(|((_, left), (_, right))| left != right)
+            .map(|((name, left), (_, right))| PcrDifference { name, left, right })
+            .collect()
+    }
 }
 
 #[cfg(feature = "pcr_signature")]
 impl pcr_sign::PCRProvider for PCRs {
     fn pcr0(&self) -> &str {
-        &self.pcr0
+        self.pcr0.as_str()
     }
 
     fn pcr1(&self) -> &str {
-        &self.pcr1
+        self.pcr1.as_str()
     }
 
     fn pcr2(&self) -> &str {
-        &self.pcr2
+        self.pcr2.as_str()
     }
 
     fn pcr8(&self) -> &str {
         self.pcr8
-            .as_deref()
+            .as_ref()
+            .map(Pcr::as_str)
             .expect("Failed to access PCR8 on built enclave. Required for PCRs to be signed.")
     }
 }
@@ -153,7 +272,10 @@ pub struct DescribeEif {
     #[serde(flatten)]
     pub measurements: EnclaveBuildOutput,
     is_signed: bool,
-    signing_certificate: EnclaveSigningCertificate,
+    // Unsigned EIFs have no signing certificate to describe or check
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    signing_certificate: Option<EnclaveSigningCertificate>,
+    #[serde(default)]
     signature_check: bool,
     metadata: EnclaveMetadata,
 }
@@ -184,3 +306,70 @@ pub struct EnclaveSigningCertificateIssuer {
 pub struct EnclaveMetadata {
     build_time: String,
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn pcrs(pcr0: &str, pcr8: Option<&str>) -> PCRs {
+        PCRs {
+            pcr0: Pcr::parse(&pcr0.repeat(48)).unwrap(),
+            pcr1: Pcr::parse(&"11".repeat(48)).unwrap(),
+            pcr2: Pcr::parse(&"22".repeat(48)).unwrap(),
+            pcr8: pcr8.map(|pcr8| Pcr::parse(&pcr8.repeat(48)).unwrap()),
+        }
+    }
+
+    #[test]
+    fn test_parse_pcr() {
+        let lowercase = Pcr::parse(&"ab".repeat(48)).unwrap();
+        assert_eq!(
+            lowercase,
+            format!(" {}\n", "AB".repeat(48)).parse().unwrap()
+        );
+        assert_eq!(lowercase.to_string(), "ab".repeat(48));
+
+        assert_eq!(
+            Pcr::parse("abc"),
+            Err(PcrError::InvalidLength("abc".into(), 3))
+        );
+        assert_eq!(
+            Pcr::parse(&"zz".repeat(48)),
+            Err(PcrError::InvalidHex("zz".repeat(48)))
+        );
+    }
+
+    #[test]
+    fn test_pcrs_serde_round_trip() {
+        let described = format!(
+            r#"{{"HashAlgorithm":"Sha384 {{ ... }}","PCR0":"{}","PCR1":"{}","PCR2":"{}","PCR8":"{}"}}"#,
+            "AA".repeat(48),
+            "11".repeat(48),
+            "22".repeat(48),
+            "88".repeat(48)
+        );
+        let measurements: EIFMeasurements = serde_json::from_str(&described).unwrap();
+        assert_eq!(measurements.pcrs(), &pcrs("aa", Some("88")));
+
+        let serialized = serde_json::to_string(&measurements).unwrap();
+        let round_tripped: EIFMeasurements = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(round_tripped.pcrs(), measurements.pcrs());
+        assert!(serialized.contains(&"aa".repeat(48)));
+
+        let invalid = described.replace(&"11".repeat(48), "11");
+        assert!(serde_json::from_str::<EIFMeasurements>(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_pcr_differences() {
+        let (built, signed) = (pcrs("aa", None), pcrs("bb", Some("88")));
+        assert!(built.differences(&built.clone()).is_empty());
+        let names: Vec<&str> = built
+            .differences(&signed)
+            .iter()
+            .map(|difference| difference.name)
+            .collect();
+        assert_eq!(names, ["PCR0", "PCR8"]);
+        assert_eq!(built.differences(&signed)[1].left, None);
+    }
+}
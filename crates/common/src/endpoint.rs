@@ -0,0 +1,166 @@
+use std::str::FromStr;
+use std::sync::OnceLock;
+use thiserror::Error;
+
+const PRODUCTION_DOMAIN: &str = "evervault.com";
+const STAGING_DOMAIN: &str = "evervault.io";
+
+static SELECTED_ENDPOINT: OnceLock<Endpoint> = OnceLock::new();
+
+#[derive(Debug, Error)]
+pub enum EndpointError {
+    #[error("Invalid endpoint \"{0}\". Use production, staging, or the domain of a custom environment such as evervault.test.")]
+    InvalidEndpoint(String),
+}
+
+impl crate::CliError for EndpointError {
+    fn exitcode(&self) -> exitcode::ExitCode {
+        exitcode::USAGE
+    }
+}
+
+/// The Evervault environment the CLI talks to. Each environment is served from a single domain,
+/// which the API, CLI assets and Enclave build assets are all subdomains of.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Endpoint {
+    Production,
+    Staging,
+    Custom(String),
+}
+
+impl FromStr for Endpoint {
+    type Err = EndpointError;
+
+    fn from_str(endpoint: &str) -> Result<Self, Self::Err> {
+        let endpoint = endpoint.trim().to_ascii_lowercase();
+        match endpoint.as_str() {
+            "production" | "prod" => Ok(Self::Production),
+            "staging" => Ok(Self::Staging),
+            domain if !domain.is_empty() && !domain.contains(['/', ':', ' ']) => {
+                Ok(Self::from_domain(domain))
+            }
+            _ => Err(EndpointError::InvalidEndpoint(endpoint)),
+        }
+    }
+}
+
+impl std::fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Production => write!(f, "production"),
+            Self::Staging => write!(f, "staging"),
+            Self::Custom(domain) => write!(f, "{domain}"),
+        }
+    }
+}
+
+impl Endpoint {
+    pub fn from_domain(domain: &str) -> Self {
+        match domain {
+            PRODUCTION_DOMAIN => Self::Production,
+            STAGING_DOMAIN => Self::Staging,
+            domain => Self::Custom(domain.to_string()),
+        }
+    }
+
+    pub fn domain(&self) -> &str {
+        match self {
+            Self::Production => PRODUCTION_DOMAIN,
+            Self::Staging => STAGING_DOMAIN,
+            Self::Custom(domain) => domain,
+        }
+    }
+
+    pub fn is_production(&self) -> bool {
+        matches!(self, Self::Production)
+    }
+
+    pub fn api_url(&self) -> String {
+        format!("https://api.{}", self.domain())
+    }
+
+    pub fn cli_assets_url(&self) -> String {
+        format!("https://cli.{}", self.domain())
+    }
+
+    pub fn enclave_assets_url(&self) -> String {
+        format!("https://enclave-build-assets.{}", self.domain())
+    }
+
+    /// Only production has its own keys service, other environments share the staging one
+    pub fn keys_url(&self) -> String {
+        match self {
+            Self::Production => "https://keys.evervault.com".to_string(),
+            _ => "https://keys.evervault.io".to_string(),
+        }
+    }
+
+    /// The domain deployed Enclaves are served under
+    pub fn enclave_domain(&self) -> &'static str {
+        match self {
+            Self::Staging => "evervault.dev",
+            _ => "evervault.com",
+        }
+    }
+}
+
+/// Selects the endpoint for the rest of the process. Only the first selection applies.
+pub fn select_endpoint(endpoint: Endpoint) {
+    let _ = SELECTED_ENDPOINT.set(endpoint);
+}
+
+/// The selected endpoint, falling back to the domain in `EV_DOMAIN` and then production
+pub fn current() -> Endpoint {
+    SELECTED_ENDPOINT.get().cloned().unwrap_or_else(|| {
+        std::env::var("EV_DOMAIN")
+            .map(|domain| Endpoint::from_domain(&domain))
+            .unwrap_or(Endpoint::Production)
+    })
+}
+
+/// The API's base URL, which `EV_API_URL` overrides for APIs served outside the endpoint's domain
+pub fn api_base_url() -> String {
+    match std::env::var("EV_API_URL") {
+        Ok(api_url) if !api_url.trim().is_empty() => api_url.trim().trim_end_matches('/').into(),
+        _ => current().api_url(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_endpoint() {
+        assert_eq!(
+            Endpoint::from_str("Production").unwrap(),
+            Endpoint::Production
+        );
+        assert_eq!(
+            Endpoint::from_str("evervault.io").unwrap(),
+            Endpoint::Staging
+        );
+        let custom = Endpoint::from_str("evervault.test").unwrap();
+        assert_eq!(custom, Endpoint::Custom("evervault.test".into()));
+        assert_eq!(custom.api_url(), "https://api.evervault.test");
+        assert_eq!(
+            custom.enclave_assets_url(),
+            "https://enclave-build-assets.evervault.test"
+        );
+        for invalid in ["", "https://api.evervault.test"] {
+            assert!(Endpoint::from_str(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_staging_endpoints() {
+        let staging = Endpoint::Staging;
+        assert_eq!(staging.cli_assets_url(), "https://cli.evervault.io");
+        assert_eq!(staging.keys_url(), "https://keys.evervault.io");
+        assert_eq!(staging.enclave_domain(), "evervault.dev");
+        assert_eq!(
+            Endpoint::Production.keys_url(),
+            "https://keys.evervault.com"
+        );
+    }
+}
@@ -1,7 +1,10 @@
 pub mod api;
 pub mod enclave;
+pub mod endpoint;
 pub mod function;
 pub mod relay;
+pub mod shell;
+pub mod table;
 pub trait CliError {
     fn exitcode(&self) -> exitcode::ExitCode;
 }
@@ -0,0 +1,82 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+fn shell() -> Command {
+    if cfg!(target_os = "windows") {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C");
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c");
+        cmd
+    }
+}
+
+/// Runs a user-defined command in the platform's shell and captures its output. `input` is
+/// written to the command's stdin from a separate thread, so a command which writes its output
+/// before reading all of its input can't deadlock against a full pipe.
+pub fn run_command(
+    command: &str,
+    envs: &[(&str, &str)],
+    input: Option<&[u8]>,
+) -> std::io::Result<Output> {
+    let mut child = shell()
+        .arg(command)
+        .envs(envs.iter().copied())
+        .stdin(match input {
+            Some(_) => Stdio::piped(),
+            None => Stdio::null(),
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    let writer = match (input, child.stdin.take()) {
+        (Some(input), Some(mut stdin)) => {
+            let input = input.to_vec();
+            // A command which exits without reading its input closes the pipe, which isn't an error
+            Some(std::thread::spawn(move || match stdin.write_all(&input) {
+                Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => Err(e),
+                _ => Ok(()),
+            }))
+        }
+        _ => None,
+    };
+
+    let output = child.wait_with_output()?;
+    if let Some(writer) = writer {
+        writer
+            .join()
+            .expect("Failed to join the thread writing the command's input")?;
+    }
+    Ok(output)
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_run_command_with_input() {
+        let output = run_command("cat", &[], Some(b"hello")).unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"hello");
+    }
+
+    #[test]
+    fn test_run_command_writes_output_before_reading_input() {
+        let input = vec![b'a'; 1024 * 1024];
+        let output = run_command("head -c 1048576 /dev/zero; wc -c", &[], Some(&input)).unwrap();
+        assert!(output.status.success());
+        let (zeros, count) = output.stdout.split_at(1024 * 1024);
+        assert!(zeros.iter().all(|byte| *byte == 0));
+        assert_eq!(String::from_utf8_lossy(count).trim(), "1048576");
+    }
+
+    #[test]
+    fn test_run_command_with_env() {
+        let output = run_command("echo $EV_APP_UUID", &[("EV_APP_UUID", "app_123")], None).unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "app_123");
+    }
+}
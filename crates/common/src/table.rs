@@ -0,0 +1,177 @@
+use crate::CliError;
+use std::cmp::Ordering;
+use std::fmt::Write;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum TableError {
+    #[error("Unknown column `{column}`. Available columns: {available}")]
+    UnknownColumn { column: String, available: String },
+}
+
+impl CliError for TableError {
+    fn exitcode(&self) -> exitcode::ExitCode {
+        match self {
+            Self::UnknownColumn { .. } => exitcode::USAGE,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Column {
+    /// Short lowercase name used to select and sort by the column
+    key: String,
+    title: String,
+}
+
+/// Plain text table output for list-style commands. Columns are addressed by a short key, so they
+/// can be selected and sorted from the command line regardless of how their titles are formatted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Table {
+    columns: Vec<Column>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    /// Creates an empty table from `(key, title)` pairs
+    pub fn new<'a>(columns: impl IntoIterator<Item = (&'a str, &'a str)>) -> Self {
+        Self {
+            columns: columns
+                .into_iter()
+                .map(|(key, title)| Column {
+                    key: key.to_string(),
+                    title: title.to_string(),
+                })
+                .collect(),
+            rows: vec![],
+        }
+    }
+
+    pub fn push_row(&mut self, row: Vec<String>) {
+        debug_assert_eq!(row.len(), self.columns.len());
+        self.rows.push(row);
+    }
+
+    pub fn rows(&self) -> &[Vec<String>] {
+        &self.rows
+    }
+
+    fn column_index(&self, key: &str) -> Result<usize, TableError> {
+        self.columns
+            .iter()
+            .position(|column| column.key.eq_ignore_ascii_case(key.trim()))
+            .ok_or_else(|| TableError::UnknownColumn {
+                column: key.trim().to_string(),
+                available: self
+                    .columns
+                    .iter()
+                    .map(|column| column.key.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            })
+    }
+
+    /// Keeps only the given columns, in the order given
+    pub fn select_columns<S: AsRef<str>>(&mut self, keys: &[S]) -> Result<(), TableError> {
+        let indices = keys
+            .iter()
+            .map(|key| self.column_index(key.as_ref()))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.columns = indices.iter().map(|i| self.columns[*i].clone()).collect();
+        self.rows = self
+            .rows
+            .iter()
+            .map(|row| indices.iter().map(|i| row[*i].clone()).collect())
+            .collect();
+        Ok(())
+    }
+
+    /// Sorts rows by a column in ascending order. Cells which are both numbers are compared
+    /// numerically, and everything else is compared as text.
+    pub fn sort_by(&mut self, key: &str) -> Result<(), TableError> {
+        let index = self.column_index(key)?;
+        self.rows
+            .sort_by(|a, b| compare_cells(&a[index], &b[index]));
+        Ok(())
+    }
+
+    pub fn render(&self, include_header: bool) -> String {
+        let titles: Vec<String> = self
+            .columns
+            .iter()
+            .map(|column| column.title.clone())
+            .collect();
+        let header = include_header.then_some(&titles);
+
+        let widths: Vec<usize> = (0..self.columns.len())
+            .map(|column| {
+                header
+                    .into_iter()
+                    .chain(self.rows.iter())
+                    .map(|row| row[column].chars().count())
+                    .max()
+                    .unwrap_or_default()
+            })
+            .collect();
+
+        let mut output = String::new();
+        for row in header.into_iter().chain(self.rows.iter()) {
+            let line = row
+                .iter()
+                .zip(widths.iter())
+                .map(|(cell, width)| format!("{cell:<width$}"))
+                .collect::<Vec<_>>()
+                .join("  ");
+            writeln!(output, "{}", line.trim_end()).unwrap();
+        }
+        output
+    }
+}
+
+fn compare_cells(a: &str, b: &str) -> Ordering {
+    match (a.parse::<f64>(), b.parse::<f64>()) {
+        (Ok(a), Ok(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+        _ => a.cmp(b),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn get_table() -> Table {
+        let mut table = Table::new([("name", "NAME"), ("requests", "REQUESTS")]);
+        table.push_row(vec!["hello-enclave".into(), "120".into()]);
+        table.push_row(vec!["api".into(), "9".into()]);
+        table
+    }
+
+    #[test]
+    fn test_render_table() {
+        let table = get_table();
+        assert_eq!(
+            table.render(true),
+            "NAME           REQUESTS\nhello-enclave  120\napi            9\n"
+        );
+        assert_eq!(
+            table.render(false),
+            "hello-enclave  120\napi            9\n"
+        );
+    }
+
+    #[test]
+    fn test_select_and_sort_columns() {
+        let mut table = get_table();
+        table.sort_by("REQUESTS").unwrap();
+        assert_eq!(table.rows()[0][0], "api");
+        table.sort_by("name").unwrap();
+        assert_eq!(table.rows()[0][0], "api");
+
+        table.select_columns(&["requests", "name"]).unwrap();
+        assert_eq!(table.render(true).lines().next(), Some("REQUESTS  NAME"));
+        assert!(matches!(
+            table.select_columns(&["cpu"]),
+            Err(TableError::UnknownColumn { .. })
+        ));
+    }
+}
@@ -0,0 +1,313 @@
+//! Derives `CmdOutput` for the message and error enums returned by ev-cli commands, so each
+//! variant declares its code, exit code and JSON data next to its message.
+//!
+//! ```ignore
+//! #[derive(Debug, thiserror::Error, CliMessage)]
+//! #[cli(code = "enclaves/rename-failed")]
+//! pub enum RenameError {
+//!     #[error("Failed to read the Enclave config — {0}")]
+//!     #[cli(code = "enclaves/config-error")]
+//!     Config(#[cli(exitcode)] EnclaveConfigError),
+//!     #[error("{0} is already taken")]
+//!     #[cli(exitcode = exitcode::DATAERR)]
+//!     NameTaken(#[cli(data)] String),
+//! }
+//! ```
+//!
+//! A variant's code and exit code fall back to the ones on the enum. A field marked
+//! `#[cli(exitcode)]` gives the exit code from its `CliError` impl, and a field marked
+//! `#[cli(data)]` is serialized as the output's data, unless it serializes to null.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Ident, Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Attribute, Data, DeriveInput, Expr, Fields, LitStr};
+
+#[derive(Default)]
+struct OutputAttrs {
+    code: Option<LitStr>,
+    exitcode: Option<Expr>,
+}
+
+impl OutputAttrs {
+    fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut output_attrs = Self::default();
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("cli")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("code") {
+                    output_attrs.code = Some(meta.value()?.parse()?);
+                    Ok(())
+                } else if meta.path.is_ident("exitcode") {
+                    output_attrs.exitcode = Some(meta.value()?.parse()?);
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `code` or `exitcode`"))
+                }
+            })?;
+        }
+        Ok(output_attrs)
+    }
+}
+
+#[derive(Default)]
+struct FieldAttrs {
+    exitcode: bool,
+    data: bool,
+}
+
+impl FieldAttrs {
+    fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut field_attrs = Self::default();
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("cli")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("exitcode") {
+                    field_attrs.exitcode = true;
+                    Ok(())
+                } else if meta.path.is_ident("data") {
+                    field_attrs.data = true;
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `exitcode` or `data`"))
+                }
+            })?;
+        }
+        Ok(field_attrs)
+    }
+}
+
+#[proc_macro_derive(CliMessage, attributes(cli))]
+pub fn derive_cli_message(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let Data::Enum(data) = &input.data else {
+        return Err(syn::Error::new(
+            Span::call_site(),
+            "CliMessage can only be derived for enums",
+        ));
+    };
+    let defaults = OutputAttrs::parse(&input.attrs)?;
+
+    let mut code_arms = vec![];
+    let mut exitcode_arms = vec![];
+    let mut data_arms = vec![];
+    for variant in data.variants.iter() {
+        let variant_ident = &variant.ident;
+        let attrs = OutputAttrs::parse(&variant.attrs)?;
+
+        let bindings: Vec<Ident> = variant
+            .fields
+            .iter()
+            .enumerate()
+            .map(|(index, field)| {
+                field
+                    .ident
+                    .clone()
+                    .unwrap_or_else(|| format_ident!("field_{index}"))
+            })
+            .collect();
+        let pattern = match &variant.fields {
+            Fields::Named(_) => quote!(Self::#variant_ident { #(#bindings),* }),
+            Fields::Unnamed(_) => quote!(Self::#variant_ident(#(#bindings),*)),
+            Fields::Unit => quote!(Self::#variant_ident),
+        };
+
+        let mut exitcode_field = None;
+        let mut data_field = None;
+        for (field, binding) in variant.fields.iter().zip(bindings.iter()) {
+            let field_attrs = FieldAttrs::parse(&field.attrs)?;
+            if field_attrs.exitcode {
+                exitcode_field = Some(binding);
+            }
+            if field_attrs.data {
+                data_field = Some(binding);
+            }
+        }
+
+        let code = attrs
+            .code
+            .or_else(|| defaults.code.clone())
+            .ok_or_else(|| {
+                syn::Error::new_spanned(
+                    variant_ident,
+                    "missing #[cli(code = \"...\")] on the variant or enum",
+                )
+            })?;
+        code_arms.push(quote! {
+            #[allow(unused_variables)]
+            #pattern => #code.to_string(),
+        });
+
+        let exitcode = match (attrs.exitcode, exitcode_field) {
+            (Some(exitcode), _) => quote!(#exitcode),
+            (None, Some(field)) => quote!(::common::CliError::exitcode(#field)),
+            (None, None) => match defaults.exitcode.as_ref() {
+                Some(exitcode) => quote!(#exitcode),
+                None => {
+                    return Err(syn::Error::new_spanned(
+                        variant_ident,
+                        "missing #[cli(exitcode = ...)] on the variant or enum, or a #[cli(exitcode)] field",
+                    ))
+                }
+            },
+        };
+        exitcode_arms.push(quote! {
+            #[allow(unused_variables)]
+            #pattern => #exitcode,
+        });
+
+        let data = match data_field {
+            Some(field) => quote! {
+                ::serde_json::to_value(#field)
+                    .ok()
+                    .filter(|data| !data.is_null())
+            },
+            None => quote!(None),
+        };
+        data_arms.push(quote! {
+            #[allow(unused_variables)]
+            #pattern => #data,
+        });
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics crate::CmdOutput for #ident #ty_generics #where_clause {
+            fn code(&self) -> String {
+                match self {
+                    #(#code_arms)*
+                }
+            }
+
+            fn exitcode(&self) -> crate::errors::ExitCode {
+                match self {
+                    #(#exitcode_arms)*
+                }
+            }
+
+            fn data(&self) -> Option<::serde_json::Value> {
+                match self {
+                    #(#data_arms)*
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use syn::parse_quote;
+
+    fn expand_to_string(input: DeriveInput) -> String {
+        expand(input).unwrap().to_string()
+    }
+
+    fn expand_error(input: DeriveInput) -> String {
+        match expand(input) {
+            Ok(tokens) => panic!("Expected an error, expanded to {tokens}"),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_expand_variant_attributes() {
+        let input: DeriveInput = parse_quote! {
+            #[cli(code = "generic/error", exitcode = exitcode::SOFTWARE)]
+            enum RenameError {
+                #[cli(code = "enclaves/config-error")]
+                Config(#[cli(exitcode)] EnclaveConfigError),
+                #[cli(exitcode = exitcode::DATAERR)]
+                NameTaken { #[cli(data)] name: String, suggestion: String },
+                Unknown,
+            }
+        };
+        let expected = quote! {
+            impl crate::CmdOutput for RenameError {
+                fn code(&self) -> String {
+                    match self {
+                        #[allow(unused_variables)]
+                        Self::Config(field_0) => "enclaves/config-error".to_string(),
+                        #[allow(unused_variables)]
+                        Self::NameTaken { name, suggestion } => "generic/error".to_string(),
+                        #[allow(unused_variables)]
+                        Self::Unknown => "generic/error".to_string(),
+                    }
+                }
+
+                fn exitcode(&self) -> crate::errors::ExitCode {
+                    match self {
+                        #[allow(unused_variables)]
+                        Self::Config(field_0) => ::common::CliError::exitcode(field_0),
+                        #[allow(unused_variables)]
+                        Self::NameTaken { name, suggestion } => exitcode::DATAERR,
+                        #[allow(unused_variables)]
+                        Self::Unknown => exitcode::SOFTWARE,
+                    }
+                }
+
+                fn data(&self) -> Option<::serde_json::Value> {
+                    match self {
+                        #[allow(unused_variables)]
+                        Self::Config(field_0) => None,
+                        #[allow(unused_variables)]
+                        Self::NameTaken { name, suggestion } => ::serde_json::to_value(name)
+                            .ok()
+                            .filter(|data| !data.is_null()),
+                        #[allow(unused_variables)]
+                        Self::Unknown => None,
+                    }
+                }
+            }
+        };
+        assert_eq!(expand_to_string(input), expected.to_string());
+    }
+
+    #[test]
+    fn test_expand_variant_exitcode_overrides_field() {
+        let input: DeriveInput = parse_quote! {
+            #[cli(code = "generic/error")]
+            enum DeployError {
+                #[cli(exitcode = exitcode::UNAVAILABLE)]
+                Api(#[cli(exitcode)] ApiError),
+            }
+        };
+        let expanded = expand_to_string(input);
+        assert!(expanded.contains(&quote!(Self::Api(field_0) => exitcode::UNAVAILABLE).to_string()));
+        assert!(!expanded.contains("CliError"));
+    }
+
+    #[test]
+    fn test_expand_errors() {
+        let missing_code = expand_error(parse_quote! {
+            #[cli(exitcode = exitcode::OK)]
+            enum Message { Done }
+        });
+        assert_eq!(
+            missing_code,
+            "missing #[cli(code = \"...\")] on the variant or enum"
+        );
+
+        let missing_exitcode = expand_error(parse_quote! {
+            #[cli(code = "generic/success")]
+            enum Message { Done }
+        });
+        assert!(missing_exitcode.starts_with("missing #[cli(exitcode = ...)]"));
+
+        let unknown_attribute = expand_error(parse_quote! {
+            #[cli(code = "generic/success", exitcode = exitcode::OK)]
+            enum Message { Done(#[cli(message)] String) }
+        });
+        assert_eq!(unknown_attribute, "expected `exitcode` or `data`");
+
+        let not_an_enum = expand_error(parse_quote! {
+            struct Message;
+        });
+        assert_eq!(not_an_enum, "CliMessage can only be derived for enums");
+    }
+}
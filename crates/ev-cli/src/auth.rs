@@ -1,23 +1,215 @@
-pub fn get_auth() -> (String, String) {
-    match (std::env::var("EV_APP_UUID"), std::env::var("EV_API_KEY")) {
-        (Ok(app_uuid), Ok(api_key)) => (app_uuid, api_key),
-        (Err(_), Err(_)) => {
-            log::error!(
-                "No App UUID or API key found. Make sure you have correctly set the \
-                     EV_APP_UUID and EV_API_KEY environment variables. See \
-                     https://docs.evervault.com/sdks/cli for more information."
-            );
-            std::process::exit(crate::errors::NOUSER);
+use common::api::oauth::{BearerToken, OAuthClient};
+use common::api::AuthMode;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+use thiserror::Error;
+
+use crate::config::{CliConfig, CliConfigError};
+use crate::BaseArgs;
+use clap::Parser;
+
+const DEFAULT_KEYCHAIN_SERVICE: &str = "evervault-cli";
+
+// The session started from the refresh token stored by `ev login`, when no API key is configured
+static LOGIN_SESSION: OnceLock<BearerToken> = OnceLock::new();
+
+/// Source of the API key used to authenticate with the Evervault API. Configured in the
+/// `[credentials]` table of `~/.evervault/config`.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+#[serde(tag = "provider", rename_all = "lowercase")]
+pub enum CredentialProvider {
+    /// Read the API key from the EV_API_KEY environment variable
+    #[default]
+    Env,
+    /// Read the API key from the OS keychain (macOS Keychain, Windows Credential Manager or
+    /// Secret Service), stored against the App UUID
+    Keychain { service: Option<String> },
+    /// Run a user-defined command and read the API key from its stdout
+    Exec { command: String },
+}
+
+#[derive(Debug, Error)]
+pub enum CredentialError {
+    #[error(
+        "No API Key found. Make sure you have correctly set the EV_API_KEY \
+        environment variable, or run ev login. See https://docs.evervault.com/sdks/cli \
+        for more information."
+    )]
+    MissingEnvVar,
+    #[error("Failed to read the API key from the OS keychain (service {0}) - {1}")]
+    Keychain(String, keyring::Error),
+    #[error("Failed to run the credential command `{0}` - {1}")]
+    ExecFailed(String, std::io::Error),
+    #[error("The credential command `{0}` exited unsuccessfully - {1}")]
+    ExecExited(String, String),
+    #[error("The credential command `{0}` did not output an API key")]
+    ExecEmptyOutput(String),
+}
+
+impl CredentialProvider {
+    pub fn resolve_api_key(&self, app_uuid: &str) -> Result<String, CredentialError> {
+        match self {
+            Self::Env => std::env::var("EV_API_KEY").map_err(|_| CredentialError::MissingEnvVar),
+            Self::Keychain { service } => {
+                let service = service.as_deref().unwrap_or(DEFAULT_KEYCHAIN_SERVICE);
+                keyring::Entry::new(service, app_uuid)
+                    .and_then(|entry| entry.get_password())
+                    .map_err(|e| CredentialError::Keychain(service.to_string(), e))
+            }
+            Self::Exec { command } => run_credential_command(command, app_uuid),
         }
-        (_, Err(_)) => {
-            log::error!(
-                "No API Key found. Make sure you have correctly set the EV_API_KEY \
-                     environment variable. See https://docs.evervault.com/sdks/cli for more \
-                     information."
-            );
-            std::process::exit(crate::errors::NOUSER);
+    }
+}
+
+fn run_credential_command(command: &str, app_uuid: &str) -> Result<String, CredentialError> {
+    let output = common::shell::run_command(command, &[("EV_APP_UUID", app_uuid)], None)
+        .map_err(|e| CredentialError::ExecFailed(command.to_string(), e))?;
+
+    if !output.status.success() {
+        return Err(CredentialError::ExecExited(
+            command.to_string(),
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+
+    let api_key = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if api_key.is_empty() {
+        return Err(CredentialError::ExecEmptyOutput(command.to_string()));
+    }
+    Ok(api_key)
+}
+
+impl std::fmt::Display for CredentialProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Env => write!(f, "env (EV_API_KEY)"),
+            Self::Keychain { service } => write!(
+                f,
+                "keychain (service {})",
+                service.as_deref().unwrap_or(DEFAULT_KEYCHAIN_SERVICE)
+            ),
+            Self::Exec { command } => write!(f, "exec (`{command}`)"),
+        }
+    }
+}
+
+// The keychain account login sessions are stored under, one per endpoint
+fn session_keychain_entry() -> keyring::Result<keyring::Entry> {
+    let account = format!("oauth:{}", common::endpoint::current().domain());
+    keyring::Entry::new(DEFAULT_KEYCHAIN_SERVICE, &account)
+}
+
+pub fn store_refresh_token(refresh_token: &str) -> keyring::Result<()> {
+    session_keychain_entry()?.set_password(refresh_token)
+}
+
+fn load_refresh_token() -> Option<String> {
+    session_keychain_entry()
+        .and_then(|entry| entry.get_password())
+        .map_err(|e| log::debug!("No login session found in the OS keychain - {e}"))
+        .ok()
+}
+
+/// Removes the stored login session, returning whether there was one
+pub fn delete_refresh_token() -> keyring::Result<bool> {
+    match session_keychain_entry()?.delete_password() {
+        Ok(()) => Ok(true),
+        Err(keyring::Error::NoEntry) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Exchanges the stored refresh token for an access token which is kept fresh for the rest of
+/// the process
+async fn start_login_session() -> Option<BearerToken> {
+    let refresh_token = load_refresh_token()?;
+    let tokens = match OAuthClient::new().refresh(&refresh_token).await {
+        Ok(tokens) => tokens,
+        Err(e) => {
+            log::warn!("Failed to resume the login session - {e}");
+            return None;
         }
-        (Err(_), _) => {
+    };
+    let refresh_token = match tokens.refresh_token.clone() {
+        Some(rotated) => {
+            persist_rotated_refresh_token(&rotated);
+            rotated
+        }
+        None => refresh_token,
+    };
+    Some(BearerToken::refreshing(
+        tokens,
+        refresh_token,
+        persist_rotated_refresh_token,
+    ))
+}
+
+fn persist_rotated_refresh_token(refresh_token: &str) {
+    if let Err(e) = store_refresh_token(refresh_token) {
+        log::warn!("Failed to save the refreshed login session to the OS keychain - {e}");
+    }
+}
+
+/// The auth for Enclave API requests: the login session when one is in use, otherwise the API key
+pub fn api_auth_mode(api_key: String) -> AuthMode {
+    match LOGIN_SESSION.get() {
+        Some(session) => AuthMode::BearerAuth(session.clone()),
+        None => AuthMode::ApiKey(api_key),
+    }
+}
+
+/// The API key to send to an Enclave which uses API key auth. Enclaves only accept API keys, so
+/// there's none to send when authenticating with the login session.
+pub fn enclave_api_key(api_key: String) -> Option<String> {
+    LOGIN_SESSION.get().is_none().then_some(api_key)
+}
+
+/// An API key given with --api-key or EV_API_KEY
+fn explicit_api_key() -> Option<String> {
+    BaseArgs::parse().api_key
+}
+
+/// Describes where the API key would be read from, without resolving it
+pub fn describe_api_key_source() -> Result<String, CliConfigError> {
+    if explicit_api_key().is_some() {
+        return Ok(CredentialProvider::Env.to_string());
+    }
+    Ok(match CliConfig::load()?.credentials {
+        Some(provider) => provider.to_string(),
+        None if load_refresh_token().is_some() => "login session (ev login)".to_string(),
+        None => format!("{} - not set", CredentialProvider::Env),
+    })
+}
+
+/// Whether an API key or login session is configured, without resolving either
+pub fn has_credentials() -> bool {
+    explicit_api_key().is_some()
+        || CliConfig::load().is_ok_and(|config| config.credentials.is_some())
+        || load_refresh_token().is_some()
+}
+
+/// The auth for requests made while completing arguments in the shell, when it can be resolved
+/// without prompting or refreshing the login session. Command line flags aren't parsed while
+/// completing, so an API key is only read from EV_API_KEY or the configured credential provider.
+pub fn completion_auth_mode() -> Option<AuthMode> {
+    if let Ok(api_key) = std::env::var("EV_API_KEY") {
+        return Some(AuthMode::ApiKey(api_key));
+    }
+    let app_uuid = std::env::var("EV_APP_UUID").ok()?;
+    let provider = CliConfig::load().ok()?.credentials?;
+    provider
+        .resolve_api_key(&app_uuid)
+        .map(AuthMode::ApiKey)
+        .ok()
+}
+
+/// Resolves the App UUID and API key, exiting when either is missing. When `allow_session` is
+/// set and no API key is configured, the session stored by `ev login` is used instead, and the
+/// returned API key is empty - build the request auth with [api_auth_mode].
+pub async fn get_auth(allow_session: bool) -> (String, String) {
+    let app_uuid = match std::env::var("EV_APP_UUID") {
+        Ok(app_uuid) => app_uuid,
+        Err(_) => {
             log::error!(
                 "No App UUID found. Make sure you have correctly set the EV_APP_UUID \
                      environment variable. See https://docs.evervault.com/sdks/cli for more \
@@ -25,5 +217,88 @@ pub fn get_auth() -> (String, String) {
             );
             std::process::exit(crate::errors::NOUSER);
         }
+    };
+
+    // An explicitly set API key always takes precedence over the configured provider
+    if let Some(api_key) = explicit_api_key() {
+        return (app_uuid, api_key);
+    }
+
+    let provider = match CliConfig::load() {
+        Ok(config) => config.credentials,
+        Err(e) => {
+            log::error!("{e}");
+            std::process::exit(crate::errors::CONFIG);
+        }
+    };
+
+    // A login session is only used when no credential provider has been configured
+    if provider.is_none() && allow_session {
+        if let Some(session) = start_login_session().await {
+            log::debug!("Authenticating with the login session");
+            let _ = LOGIN_SESSION.set(session);
+            return (app_uuid, String::new());
+        }
+    }
+    let provider = provider.unwrap_or_default();
+
+    match provider.resolve_api_key(&app_uuid) {
+        Ok(api_key) => (app_uuid, api_key),
+        Err(e) => {
+            log::error!("{e}");
+            std::process::exit(crate::errors::NOUSER);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_credential_providers() {
+        let config = CliConfig::parse(
+            r#"
+[credentials]
+provider = "exec"
+command = "vault kv get -field=api_key secret/evervault"
+"#,
+            "config",
+        )
+        .unwrap();
+        assert_eq!(
+            config.credentials,
+            Some(CredentialProvider::Exec {
+                command: "vault kv get -field=api_key secret/evervault".into()
+            })
+        );
+
+        let config =
+            CliConfig::parse("[credentials]\nprovider = \"keychain\"\n", "config").unwrap();
+        assert_eq!(
+            config.credentials,
+            Some(CredentialProvider::Keychain { service: None })
+        );
+
+        let config = CliConfig::parse("", "config").unwrap();
+        assert_eq!(config.credentials, None);
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn test_exec_provider_reads_stdout() {
+        let provider = CredentialProvider::Exec {
+            command: "echo \"ev:key:$EV_APP_UUID\"".into(),
+        };
+        let api_key = provider.resolve_api_key("app_123").unwrap();
+        assert_eq!(api_key, "ev:key:app_123");
+
+        let failing = CredentialProvider::Exec {
+            command: "exit 1".into(),
+        };
+        assert!(matches!(
+            failing.resolve_api_key("app_123"),
+            Err(CredentialError::ExecExited(_, _))
+        ));
     }
 }
@@ -0,0 +1,188 @@
+use clap::Parser;
+use ev_cli_derive::CliMessage;
+use ev_enclave::config::EnclaveConfig;
+use ev_enclave::state::load_cached_enclave;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::auth::{describe_api_key_source, has_credentials};
+use crate::config::CliConfigError;
+use crate::context::{load_last_command, LastCommand};
+use crate::tty::outputs_json;
+use crate::BaseArgs;
+
+const DEFAULT_CONFIG_PATH: &str = "./enclave.toml";
+
+/// What `ev` shows when run without a command: who it would act as, the Enclave in the current
+/// directory, and where to go next.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Dashboard {
+    endpoint: String,
+    api_key_source: String,
+    app_uuid: Option<String>,
+    enclave: Option<DashboardEnclave>,
+    last_command: Option<LastCommand>,
+    next_commands: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DashboardEnclave {
+    name: String,
+    uuid: Option<String>,
+    /// The Enclave's state from the local state cache or the cached list of Enclaves, when it could be read
+    state: Option<String>,
+}
+
+#[derive(Debug, Error, CliMessage)]
+pub enum DashboardError {
+    #[error("{0}")]
+    #[cli(code = "generic/config-error", exitcode = exitcode::CONFIG)]
+    ApiKeySource(#[from] CliConfigError),
+}
+
+#[derive(Debug, strum_macros::Display, CliMessage)]
+#[cli(code = "generic/success", exitcode = exitcode::OK)]
+pub enum DashboardMessage {
+    #[strum(to_string = "{summary}")]
+    Context {
+        summary: String,
+        #[cli(data)]
+        dashboard: Option<Dashboard>,
+    },
+}
+
+fn next_commands(
+    has_credentials: bool,
+    enclave: Option<&DashboardEnclave>,
+    last_command: Option<&LastCommand>,
+) -> Vec<String> {
+    let mut commands = vec![];
+    if !has_credentials {
+        commands.push("ev login".to_string());
+    }
+    match enclave {
+        None => commands.push("ev enclave init".to_string()),
+        Some(DashboardEnclave { uuid: None, .. }) => commands.push("ev enclave init".to_string()),
+        Some(_) => commands.extend([
+            "ev enclave deploy".to_string(),
+            "ev enclave status".to_string(),
+            "ev enclave logs --follow".to_string(),
+        ]),
+    }
+    // Offer to pick up where the user left off, unless it's already suggested
+    if let Some(last_command) = last_command {
+        if !commands.contains(&last_command.command) {
+            commands.push(last_command.command.clone());
+        }
+    }
+    commands.push("ev --help".to_string());
+    commands
+}
+
+impl Dashboard {
+    fn summary(&self) -> String {
+        let display = |value: &Option<String>| value.clone().unwrap_or_else(|| "-".to_string());
+        let mut lines = vec![
+            format!("Endpoint: {}", self.endpoint),
+            format!("API key: {}", self.api_key_source),
+            format!("App: {}", display(&self.app_uuid)),
+        ];
+        lines.push(match &self.enclave {
+            Some(enclave) => format!(
+                "Enclave: {} ({}) - {}",
+                enclave.name,
+                display(&enclave.uuid),
+                enclave
+                    .state
+                    .clone()
+                    .unwrap_or_else(|| "state unknown".to_string())
+            ),
+            None => format!("Enclave: no {DEFAULT_CONFIG_PATH} in this directory"),
+        });
+        if let Some(last_command) = &self.last_command {
+            lines.push(format!(
+                "Last command: {} at {}",
+                last_command.command, last_command.ran_at
+            ));
+        }
+        lines.push(String::new());
+        lines.push("Try:".to_string());
+        lines.extend(
+            self.next_commands
+                .iter()
+                .map(|command| format!("  {command}")),
+        );
+        lines.join("\n")
+    }
+}
+
+pub fn run() -> Result<DashboardMessage, DashboardError> {
+    let api_key_source = describe_api_key_source()?;
+    let has_credentials = has_credentials();
+    let enclave = EnclaveConfig::try_from_filepath(DEFAULT_CONFIG_PATH)
+        .ok()
+        .map(|config| {
+            // The state cached by earlier commands avoids a request. Otherwise, Enclaves are only
+            // listed with credentials which don't need a prompt or refresh.
+            let state = config.uuid.as_deref().and_then(|uuid| {
+                load_cached_enclave(DEFAULT_CONFIG_PATH, uuid)
+                    .map(|enclave| enclave.state.to_string())
+                    .or_else(|| {
+                        crate::completion::list_enclaves()
+                            .into_iter()
+                            .find(|enclave| enclave.uuid == uuid)
+                            .map(|enclave| enclave.state.to_string())
+                    })
+            });
+            DashboardEnclave {
+                name: config.name,
+                uuid: config.uuid,
+                state,
+            }
+        });
+    let last_command = load_last_command();
+
+    let dashboard = Dashboard {
+        endpoint: common::endpoint::current().to_string(),
+        api_key_source,
+        app_uuid: std::env::var("EV_APP_UUID").ok(),
+        next_commands: next_commands(has_credentials, enclave.as_ref(), last_command.as_ref()),
+        enclave,
+        last_command,
+    };
+    Ok(DashboardMessage::Context {
+        summary: dashboard.summary(),
+        dashboard: outputs_json(BaseArgs::parse().json).then_some(dashboard),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_next_commands() {
+        assert_eq!(
+            next_commands(false, None, None),
+            vec!["ev login", "ev enclave init", "ev --help"]
+        );
+
+        let enclave = DashboardEnclave {
+            name: "hello".to_string(),
+            uuid: Some("enclave_123".to_string()),
+            state: Some("active".to_string()),
+        };
+        let last_command = LastCommand {
+            command: "ev enclave env".to_string(),
+            enclave_uuid: None,
+            directory: None,
+            ran_at: "2024-01-01T00:00:00Z".to_string(),
+        };
+        let commands = next_commands(true, Some(&enclave), Some(&last_command));
+        assert_eq!(commands.first().unwrap(), "ev enclave deploy");
+        assert!(commands.contains(&"ev enclave env".to_string()));
+        assert_eq!(commands.last().unwrap(), "ev --help");
+    }
+}
@@ -1,66 +1,253 @@
 use attestation_doc_validation::attestation_doc::PCRs;
 use attestation_doc_validation::PCRProvider;
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use common::api::BasicAuth;
+use ev_cli_derive::CliMessage;
 use ev_enclave::attest::attest_connection_to_enclave;
-use ev_enclave::config::EnclaveConfig;
+use ev_enclave::attest::error::{AttestCommandError, TrustStoreError};
+use ev_enclave::attest::export::{ExportFormat, TrustedEnclaveConfig};
+use ev_enclave::attest::fixtures::generate_fixtures;
+use ev_enclave::attest::trust::TrustStore;
+use ev_enclave::config::{EnclaveConfig, EnclaveConfigError};
 use ev_enclave::describe::describe_eif;
+use ev_enclave::describe::error::DescribeError;
+use ev_enclave::enclave::{EIFMeasurements, NitroCliImageError};
+use std::path::Path;
+use thiserror::Error;
+
+use crate::config::trust_store_directory;
 
 /// Validate the attestation doc provided by an Enclave
 #[derive(Debug, Parser)]
-#[command(name = "attest", about)]
+#[command(name = "attest", about, args_conflicts_with_subcommands = true)]
 pub struct AttestArgs {
+    #[command(subcommand)]
+    pub action: Option<AttestCommands>,
     /// Path to enclave.toml config file
-    #[arg(short = 'c', long = "config", default_value = "./enclave.toml")]
+    #[arg(
+        short = 'c',
+        long = "config",
+        default_value = "./enclave.toml",
+        env = "EV_ENCLAVE_CONFIG"
+    )]
     pub config: String,
     /// Path to EIF file. When included, the attestation measures returned from the Enclave will be compared to the measures of the EIF.
-    #[arg(long = "eif-path")]
+    #[arg(long = "eif-path", env = "EV_EIF_PATH")]
     pub eif_path: Option<String>,
 }
 
-macro_rules! unwrap_or_exit_with_error {
-    ($res:expr) => {
-        match $res {
-            Ok(val) => val,
-            Err(e) => {
-                log::error!("{}", e.to_string());
-                return exitcode::SOFTWARE;
-            }
-        }
-    };
+#[derive(Debug, Subcommand)]
+pub enum AttestCommands {
+    /// Generate sample attestation docs and signed PCRs from a local build, for testing attestation verification in client libraries
+    #[command()]
+    Fixtures(FixturesArgs),
+    /// Export the Enclave's domain, PCRs and PCR signature for clients to pin, as Terraform or JSON
+    #[command()]
+    Export(ExportArgs),
+}
+
+#[derive(Debug, Parser)]
+#[command(name = "fixtures", about)]
+pub struct FixturesArgs {
+    /// Path to enclave.toml config file
+    #[arg(
+        short = 'c',
+        long = "config",
+        default_value = "./enclave.toml",
+        env = "EV_ENCLAVE_CONFIG"
+    )]
+    pub config: String,
+    /// Path to EIF file. When included, the fixtures are derived from the measures of the EIF instead of the attestation section of the config.
+    #[arg(long = "eif-path", env = "EV_EIF_PATH")]
+    pub eif_path: Option<String>,
+    /// Directory to write the fixtures to
+    #[arg(short = 'o', long = "out", default_value = "./attestation-fixtures")]
+    pub out_dir: String,
+}
+
+#[derive(Debug, Parser)]
+#[command(name = "export", about)]
+pub struct ExportArgs {
+    /// Path to enclave.toml config file
+    #[arg(
+        short = 'c',
+        long = "config",
+        default_value = "./enclave.toml",
+        env = "EV_ENCLAVE_CONFIG"
+    )]
+    pub config: String,
+    /// Path to EIF file. When included, the PCRs are taken from the EIF instead of the attestation section of the config.
+    #[arg(long = "eif-path", env = "EV_EIF_PATH")]
+    pub eif_path: Option<String>,
+    /// Format to export the Enclave's trusted config in
+    #[arg(long = "format", value_enum, default_value_t = ExportFormat::Json)]
+    pub format: ExportFormat,
+    /// File to write the export to. Defaults to stdout.
+    #[arg(short = 'o', long = "out")]
+    pub out: Option<String>,
+}
+
+#[derive(Debug, Error, CliMessage)]
+pub enum AttestError {
+    #[error("{0}")]
+    #[cli(code = "enclaves/config-error")]
+    Config(
+        #[from]
+        #[cli(exitcode)]
+        EnclaveConfigError,
+    ),
+    #[error("{0}")]
+    #[cli(code = "enclaves/nitro-cli-error")]
+    NitroCli(
+        #[from]
+        #[cli(exitcode)]
+        NitroCliImageError,
+    ),
+    #[error("{0}")]
+    #[cli(code = "enclaves/describe-error")]
+    Describe(
+        #[from]
+        #[cli(exitcode)]
+        DescribeError,
+    ),
+    #[error("{0}")]
+    #[cli(code = "enclaves/trust-store-error")]
+    TrustStore(
+        #[from]
+        #[cli(exitcode)]
+        TrustStoreError,
+    ),
+    #[error("Failed to attest Enclave - {0}")]
+    #[cli(code = "enclaves/attestation-failed", exitcode = exitcode::SOFTWARE)]
+    Attestation(AttestCommandError),
+    #[error("{0}")]
+    #[cli(code = "enclaves/fixtures-error", exitcode = exitcode::SOFTWARE)]
+    Fixtures(AttestCommandError),
+    #[error("Failed to write the export to {0} - {1}")]
+    #[cli(code = "generic/io-error", exitcode = exitcode::IOERR)]
+    WriteExport(String, std::io::Error),
 }
 
-pub async fn run(attest_args: AttestArgs, _: BasicAuth) -> i32 {
-    let config = unwrap_or_exit_with_error!(EnclaveConfig::try_from_filepath(&attest_args.config));
-    let domain = unwrap_or_exit_with_error!(config.get_enclave_domain());
+#[derive(Debug, strum_macros::Display, CliMessage)]
+#[cli(code = "generic/success", exitcode = exitcode::OK)]
+pub enum AttestMessage {
+    #[strum(
+        to_string = "Attestation successful!\n\nhttps://{domain} returned a signed attestation doc which had PCRs:\n\n{pcrs}"
+    )]
+    Attested { domain: String, pcrs: String },
+    #[strum(
+        to_string = "Attestation fixtures written to {out_dir}. Trust root.pem in your client tests, and see manifest.json for the expected result of each fixture:\n{paths}"
+    )]
+    FixturesWritten { out_dir: String, paths: String },
+    #[strum(to_string = "Trusted config for {name} written to {out}")]
+    ExportWritten { name: String, out: String },
+    #[strum(to_string = "{0}")]
+    Exported(String),
+}
 
-    let expected_pcrs = if let Some(eif_path) = attest_args.eif_path {
-        let description = unwrap_or_exit_with_error!(describe_eif(&eif_path, false, false));
-        description.measurements.measurements().clone()
+fn get_expected_measurements(
+    config: &EnclaveConfig,
+    eif_path: Option<&str>,
+) -> Result<EIFMeasurements, AttestError> {
+    if let Some(eif_path) = eif_path {
+        let nitro_cli = config.nitro_cli_image()?;
+        let description = describe_eif(eif_path, &nitro_cli, false, false)?;
+        Ok(description.measurements.measurements().clone())
     } else {
-        unwrap_or_exit_with_error!(config.get_attestation()).clone()
-    };
+        Ok(config.get_attestation().cloned()?)
+    }
+}
 
-    let expected_pcrs = PCRs {
-        pcr_0: expected_pcrs.pcrs().pcr0.clone(),
-        pcr_1: expected_pcrs.pcrs().pcr1.clone(),
-        pcr_2: expected_pcrs.pcrs().pcr2.clone(),
-        pcr_8: expected_pcrs
+/// The PCRs an Enclave is expected to attest to, from its EIF when given or the attestation section of its config
+pub fn get_expected_pcrs(
+    config: &EnclaveConfig,
+    eif_path: Option<&str>,
+) -> Result<PCRs, AttestError> {
+    let measurements = get_expected_measurements(config, eif_path)?;
+    Ok(PCRs {
+        pcr_0: measurements.pcrs().pcr0.to_string(),
+        pcr_1: measurements.pcrs().pcr1.to_string(),
+        pcr_2: measurements.pcrs().pcr2.to_string(),
+        pcr_8: measurements
             .pcrs()
             .pcr8
             .as_ref()
             .expect("When PCRs are set in the toml file, PCR8 should always be present")
-            .clone(),
+            .to_string(),
+    })
+}
+
+/// Loads the root of trust pinned with `ev enclave trust fetch`, warning when it is missing or expiring
+pub fn load_pinned_trust_store() -> Result<Option<TrustStore>, TrustStoreError> {
+    let store = match trust_store_directory() {
+        Some(directory) => TrustStore::load(&directory)?,
+        None => None,
     };
+    match store.as_ref() {
+        Some(store) => store
+            .expiry_warnings(chrono::Utc::now().timestamp())
+            .iter()
+            .for_each(|warning| log::warn!("{warning}")),
+        None => log::warn!("No attestation root of trust has been pinned, so the AWS Nitro root bundled with the CLI will be trusted. Run `ev enclave trust fetch` to pin the root and Evervault intermediates."),
+    }
+    Ok(store)
+}
 
-    match attest_connection_to_enclave(&domain, expected_pcrs.clone()).await {
-        Ok(_) => {
-            log::info!("Attestation successful!\n\nhttps://{} returned a signed attestation doc which had PCRs:\n\n{}", domain, expected_pcrs.to_string());
-            exitcode::OK
+pub async fn run(attest_args: AttestArgs, _: BasicAuth) -> Result<AttestMessage, AttestError> {
+    match attest_args.action {
+        Some(AttestCommands::Fixtures(fixtures_args)) => {
+            return generate_attestation_fixtures(fixtures_args)
         }
-        Err(e) => {
-            log::error!("Failed to attest Enclave - {e}");
-            exitcode::SOFTWARE
+        Some(AttestCommands::Export(export_args)) => return export_trusted_config(export_args),
+        None => {}
+    }
+
+    let config = EnclaveConfig::try_from_filepath(&attest_args.config)?;
+    let domain = config.get_enclave_domain()?;
+    let expected_pcrs = get_expected_pcrs(&config, attest_args.eif_path.as_deref())?;
+    let trust_store = load_pinned_trust_store()?;
+
+    attest_connection_to_enclave(&domain, expected_pcrs.clone(), trust_store)
+        .await
+        .map_err(AttestError::Attestation)?;
+    Ok(AttestMessage::Attested {
+        domain,
+        pcrs: expected_pcrs.to_string(),
+    })
+}
+
+fn generate_attestation_fixtures(
+    fixtures_args: FixturesArgs,
+) -> Result<AttestMessage, AttestError> {
+    let config = EnclaveConfig::try_from_filepath(&fixtures_args.config)?;
+    let measurements = get_expected_measurements(&config, fixtures_args.eif_path.as_deref())?;
+
+    let paths = generate_fixtures(&measurements, Path::new(&fixtures_args.out_dir))
+        .map_err(AttestError::Fixtures)?;
+    Ok(AttestMessage::FixturesWritten {
+        out_dir: fixtures_args.out_dir,
+        paths: paths
+            .iter()
+            .map(|path| format!("  {}", path.display()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+    })
+}
+
+fn export_trusted_config(export_args: ExportArgs) -> Result<AttestMessage, AttestError> {
+    let config = EnclaveConfig::try_from_filepath(&export_args.config)?;
+    let measurements = get_expected_measurements(&config, export_args.eif_path.as_deref())?;
+    let trusted_config = TrustedEnclaveConfig::new(&config, &measurements)?;
+    let rendered = trusted_config.render(export_args.format);
+
+    match export_args.out {
+        Some(out) => {
+            std::fs::write(&out, rendered).map_err(|e| AttestError::WriteExport(out.clone(), e))?;
+            Ok(AttestMessage::ExportWritten {
+                name: trusted_config.name,
+                out,
+            })
         }
+        None => Ok(AttestMessage::Exported(rendered.trim_end().to_string())),
     }
 }
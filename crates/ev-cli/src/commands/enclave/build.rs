@@ -1,39 +1,74 @@
+use clap::builder::BoolishValueParser;
 use clap::Parser;
-use common::CliError;
-use ev_enclave::build::build_enclave_image_file;
-use ev_enclave::common::prepare_build_args;
-use ev_enclave::config::{read_and_validate_config, BuildTimeConfig};
+use ev_cli_derive::CliMessage;
+use ev_enclave::build::error::BuildError as ImageBuildError;
+use ev_enclave::build::port::resolve_service_port;
+use ev_enclave::build::runtime::resolve_runtime_digests;
+use ev_enclave::build::signature::{sign_attestation, write_pcr_signature_bundle};
+use ev_enclave::build::watch::{wait_for_change, BuildDiff, ContextSnapshot, UserImageBuild};
+use ev_enclave::build::{
+    build_enclave_image_file, build_from_scratch, EV_USER_DOCKERFILE_PATH, STDIN_DOCKERFILE_PATH,
+};
+use ev_enclave::common::{prepare_build_args, resolve_output_path};
+use ev_enclave::config::{read_and_validate_config, BuildTimeConfig, EnclaveConfigError};
 use ev_enclave::docker::command::get_source_date_epoch;
-use ev_enclave::version::get_runtime_and_installer_version;
+use ev_enclave::docker::history::DockerfileHistory;
+use ev_enclave::docker::remote::{use_remote_builder, RemoteBuilderError};
+use ev_enclave::enclave::BuiltEnclave;
+use ev_enclave::enclave::EnclaveSigningInfo;
+use ev_enclave::version::{get_runtime_versions, RuntimeVersions, VersionError};
+use ev_enclave::workspace::{Workspace, WorkspaceError};
+use std::path::Path;
+use thiserror::Error;
 
+use crate::config::dockerfile_cache_directory;
+use crate::workspace::{report_member, MemberPaths, WorkspaceArgs, WorkspaceSummary};
 use crate::BaseArgs;
 
 /// Build an Enclave from a Dockerfile
-#[derive(Parser, Debug)]
+#[derive(Clone, Parser, Debug)]
 #[command(name = "build", about)]
 pub struct BuildArgs {
-    /// Path to enclave.toml config file. This can be generated using the init command
-    #[arg(short = 'c', long = "config", default_value = "./enclave.toml")]
+    /// Path to enclave.toml config file. This can be generated using the init command. Use - to read the config from stdin.
+    #[arg(
+        short = 'c',
+        long = "config",
+        default_value = "./enclave.toml",
+        env = "EV_ENCLAVE_CONFIG"
+    )]
     pub config: String,
 
-    /// Path to Dockerfile for Enclave. Will override any dockerfile specified in the .toml file.
-    #[arg(short = 'f', long = "file")]
+    /// Path to write the updated Enclave config, including its attestation measurements, to. Defaults to the config path, unless the config is read from stdin with `--config -`.
+    #[arg(
+        long = "config-output",
+        env = "EV_CONFIG_OUTPUT",
+        conflicts_with = "all"
+    )]
+    pub config_output: Option<String>,
+
+    /// Path to Dockerfile for Enclave. Will override any dockerfile specified in the .toml file. Use - to read the Dockerfile from stdin.
+    #[arg(short = 'f', long = "file", env = "EV_DOCKERFILE")]
     pub dockerfile: Option<String>,
 
     /// Path to use for Docker context. Defaults to the current directory.
-    #[arg(default_value = ".")]
+    #[arg(default_value = ".", env = "EV_CONTEXT_PATH")]
     pub context_path: String,
 
     /// Certificate used to sign the Enclave image file
-    #[arg(long = "signing-cert")]
+    #[arg(long = "signing-cert", env = "EV_SIGNING_CERT")]
     pub certificate: Option<String>,
 
     /// Private key used to sign the Enclave image file
-    #[arg(long = "private-key")]
+    #[arg(long = "private-key", env = "EV_PRIVATE_KEY")]
     pub private_key: Option<String>,
 
     /// Path to directory where the processed dockerfile and Enclave will be saved
-    #[arg(short = 'o', long = "output", default_value = ".")]
+    #[arg(
+        short = 'o',
+        long = "output",
+        default_value = ".",
+        env = "EV_OUTPUT_DIR"
+    )]
     pub output_dir: String,
 
     /// Build time arguments to provide to docker
@@ -41,11 +76,11 @@ pub struct BuildArgs {
     pub docker_build_args: Vec<String>,
 
     /// Path to an Enclave dockerfile to build from existing
-    #[arg(long = "from-existing")]
+    #[arg(long = "from-existing", conflicts_with = "all")]
     pub from_existing: Option<String>,
 
     /// Deterministic builds
-    #[arg(long = "reproducible")]
+    #[arg(long = "reproducible", env = "EV_REPRODUCIBLE", value_parser = BoolishValueParser::new())]
     pub reproducible: bool,
 
     /// Enables forwarding proxy protocol when TLS Termination is disabled
@@ -53,8 +88,39 @@ pub struct BuildArgs {
     pub forward_proxy_protocol: bool,
 
     /// Disables the use of cache during the image builds
-    #[arg(long = "no-cache")]
+    #[arg(long = "no-cache", env = "EV_NO_CACHE", value_parser = BoolishValueParser::new())]
     pub no_cache: bool,
+
+    /// Fail the build if the docker build context exceeds this size, in megabytes
+    #[arg(long = "max-context-size", env = "EV_MAX_CONTEXT_SIZE")]
+    pub max_context_size: Option<u64>,
+
+    /// Version of the Nitro CLI used to convert the image to an EIF. Will override any nitro_cli_version specified in the .toml file.
+    #[arg(long = "nitro-cli-version", env = "EV_NITRO_CLI_VERSION")]
+    pub nitro_cli_version: Option<String>,
+
+    /// Platform to build the Enclave's image for, such as linux/amd64/v3. Enclaves run linux/amd64 images. Will override any platform specified in the .toml file.
+    #[arg(long = "platform", env = "EV_PLATFORM")]
+    pub platform: Option<String>,
+
+    /// Docker context to run the build on, such as a remote x86_64 host. Use this for reproducible builds from machines which aren't x86_64, such as Apple Silicon Macs. Defaults to the remote_builder in the CLI config.
+    #[arg(long = "remote-builder", env = "EV_REMOTE_BUILDER")]
+    pub remote_builder: Option<String>,
+
+    /// Write the signed PCRs of the built Enclave to this path as JSON, so they can be hosted for clients
+    #[arg(long = "pcr-output", env = "EV_PCR_OUTPUT", conflicts_with = "all")]
+    pub pcr_output: Option<String>,
+
+    /// Sign the attestation block written to the Enclave config with the signing key, so changes to its PCRs can be detected with ev enclave config verify
+    #[arg(long = "sign-attestation", env = "EV_SIGN_ATTESTATION", value_parser = BoolishValueParser::new())]
+    pub sign_attestation: bool,
+
+    /// Watch the Dockerfile and build context for changes, rebuilding the user image on each change and reporting whether the Enclave's PCRs would change. The image isn't converted to an EIF.
+    #[arg(long = "watch", conflicts_with_all = ["all", "from_existing", "pcr_output"])]
+    pub watch: bool,
+
+    #[command(flatten)]
+    pub workspace_args: WorkspaceArgs,
 }
 
 impl BuildTimeConfig for BuildArgs {
@@ -69,75 +135,378 @@ impl BuildTimeConfig for BuildArgs {
     fn private_key(&self) -> Option<&str> {
         self.private_key.as_deref()
     }
+
+    fn nitro_cli_version(&self) -> Option<&str> {
+        self.nitro_cli_version.as_deref()
+    }
+
+    fn platform(&self) -> Option<&str> {
+        self.platform.as_deref()
+    }
+}
+
+#[derive(Debug, Error, CliMessage)]
+pub enum BuildError {
+    #[error("{0}")]
+    #[cli(code = "enclaves/remote-builder-error")]
+    RemoteBuilder(
+        #[from]
+        #[cli(exitcode)]
+        RemoteBuilderError,
+    ),
+    #[error("{0}")]
+    #[cli(code = "enclaves/workspace-error")]
+    Workspace(
+        #[from]
+        #[cli(exitcode)]
+        WorkspaceError,
+    ),
+    #[error("Failed to retrieve the latest data plane and installer versions - {0}")]
+    #[cli(code = "enclaves/version-error")]
+    Versions(
+        #[from]
+        #[cli(exitcode)]
+        VersionError,
+    ),
+    #[error("Failed to read Enclave config from file system — {0}")]
+    #[cli(code = "enclaves/config-error")]
+    Config(
+        #[from]
+        #[cli(exitcode)]
+        EnclaveConfigError,
+    ),
+    #[error("An error occurred while building your Enclave — {0}")]
+    #[cli(code = "enclaves/build-error")]
+    Build(
+        #[from]
+        #[cli(exitcode)]
+        ImageBuildError,
+    ),
+    #[error("Failed to write the PCR signature bundle — {0}")]
+    #[cli(code = "enclaves/pcr-signature-error")]
+    PcrSignature(#[cli(exitcode)] ImageBuildError),
+    #[error("Failed to create output directory {0} — {1}")]
+    #[cli(code = "generic/io-error", exitcode = exitcode::IOERR)]
+    OutputDirectory(String, std::io::Error),
+    #[error("--watch can't be used when the Dockerfile is read from stdin, as it can't be watched for changes")]
+    #[cli(code = "enclaves/watch-error", exitcode = exitcode::USAGE)]
+    WatchStdinDockerfile,
+    #[error("{0}")]
+    #[cli(code = "enclaves/workspace-failed")]
+    WorkspaceFailed(#[cli(exitcode, data)] WorkspaceSummary),
 }
 
-pub async fn run(build_args: BuildArgs) -> exitcode::ExitCode {
+#[derive(Debug, strum_macros::Display, CliMessage)]
+#[cli(code = "generic/success", exitcode = exitcode::OK)]
+pub enum BuildMessage {
+    #[strum(to_string = "EIF built successfully")]
+    Built(#[cli(data)] serde_json::Value),
+    #[strum(to_string = "{0}")]
+    WorkspaceBuilt(#[cli(data)] WorkspaceSummary),
+}
+
+/// Sends docker commands to the remote builder given on the command line, or in the CLI config
+pub fn select_remote_builder(remote_builder: Option<&str>) -> Result<(), RemoteBuilderError> {
+    let remote_builder = remote_builder.map(String::from).or_else(|| {
+        crate::config::CliConfig::load()
+            .map_err(|e| log::debug!("Failed to read the remote builder from the CLI config - {e}"))
+            .ok()
+            .and_then(|cli_config| cli_config.remote_builder)
+    });
+    let Some(remote_builder) = remote_builder else {
+        return Ok(());
+    };
+    use_remote_builder(&remote_builder)
+}
+
+pub async fn run(build_args: BuildArgs) -> Result<BuildMessage, BuildError> {
     let base_args = BaseArgs::parse();
+    crate::support::capture_build_log();
+    select_remote_builder(build_args.remote_builder.as_deref())?;
 
-    let (mut enclave_config, validated_config) =
-        match read_and_validate_config(&build_args.config, &build_args) {
-            Ok(config) => config,
-            Err(e) => {
-                log::error!("Failed to read Enclave config from file system — {e}");
-                return e.exitcode();
-            }
+    let workspace = match build_args.workspace_args.all {
+        true => Some(build_args.workspace_args.load_workspace()?),
+        false => None,
+    };
+
+    let versions = get_runtime_versions(build_args.from_existing.clone()).await?;
+
+    if let Some(workspace) = workspace {
+        let summary = build_workspace(
+            &workspace,
+            &build_args,
+            &versions,
+            base_args.verbose,
+            base_args.json,
+        )
+        .await?;
+        return match summary.succeeded() {
+            true => Ok(BuildMessage::WorkspaceBuilt(summary)),
+            false => Err(BuildError::WorkspaceFailed(summary)),
         };
+    }
+
+    if build_args.watch {
+        return watch_user_image(&build_args, &versions, base_args.verbose).await;
+    }
+
+    let (built_enclave, egress_destinations) =
+        build_enclave(&build_args, &versions, base_args.verbose).await?;
 
+    // Write Enclave measures to stdout
+    Ok(BuildMessage::Built(serde_json::json!({
+        "enclaveMeasurements": built_enclave.measurements(),
+        "egressDestinations": egress_destinations,
+        "timings": ev_enclave::instrumentation::timings()
+    })))
+}
+
+// Members are built one at a time, as builds share the same intermediate image names. They reuse
+// the same data plane and installer releases, and docker's layer cache.
+async fn build_workspace(
+    workspace: &Workspace,
+    build_args: &BuildArgs,
+    versions: &RuntimeVersions,
+    verbose: bool,
+    json: bool,
+) -> Result<WorkspaceSummary, BuildError> {
+    let mut reports = vec![];
+    for member in workspace.members() {
+        let paths = MemberPaths::from(member);
+        let output_dir = if build_args.output_dir == "." {
+            paths.context_path.clone()
+        } else {
+            let output_dir = std::path::Path::new(&build_args.output_dir).join(&member.path);
+            std::fs::create_dir_all(&output_dir)
+                .map_err(|e| BuildError::OutputDirectory(output_dir.display().to_string(), e))?;
+            output_dir.display().to_string()
+        };
+        let member_args = BuildArgs {
+            config: paths.config,
+            dockerfile: Some(paths.dockerfile),
+            context_path: paths.context_path,
+            certificate: paths.certificate,
+            private_key: paths.private_key,
+            output_dir,
+            ..build_args.clone()
+        };
+        let report = report_member(member, async {
+            build_enclave(&member_args, versions, verbose)
+                .await
+                .map(|(built_enclave, _)| {
+                    Some(built_enclave.measurements().pcrs().pcr0.to_string())
+                })
+        })
+        .await;
+        reports.push(report);
+    }
+    Ok(WorkspaceSummary::new(reports, json))
+}
+
+// Rebuilds only the user image on each change, as EIF conversion is the slowest stage of a build.
+// Runs until interrupted, and a failed rebuild is reported without ending the watch.
+async fn watch_user_image(
+    build_args: &BuildArgs,
+    versions: &RuntimeVersions,
+    verbose: bool,
+) -> Result<BuildMessage, BuildError> {
+    if build_args.dockerfile.as_deref() == Some(STDIN_DOCKERFILE_PATH) {
+        return Err(BuildError::WatchStdinDockerfile);
+    }
+    // The processed Dockerfile is an intermediate of the watch, so it's kept out of the output dir
+    let output_path = resolve_output_path(None::<&str>).map_err(ImageBuildError::from)?;
+    // A fixed timestamp keeps docker's layer cache, so unchanged images keep the same ID
+    let timestamp = get_source_date_epoch();
+    let context_path = Path::new(&build_args.context_path);
+    let config_path = Path::new(&build_args.config);
+
+    let mut previous_build: Option<UserImageBuild> = None;
+    loop {
+        // The config may change which Dockerfile is built, so it's resolved again on each change
+        let dockerfile_path = read_and_validate_config(&build_args.config, build_args)
+            .ok()
+            .map(|(_, validated_config)| validated_config.dockerfile().to_string())
+            .or_else(|| build_args.dockerfile.clone());
+        let mut watched_paths = vec![config_path];
+        watched_paths.extend(dockerfile_path.as_deref().map(Path::new));
+        let snapshot = ContextSnapshot::capture(context_path, &watched_paths)
+            .map_err(ImageBuildError::FailedToWatchContext)?;
+
+        match build_user_image(
+            build_args,
+            versions,
+            verbose,
+            output_path.path(),
+            timestamp.clone(),
+        )
+        .await
+        {
+            Ok(user_image) => {
+                match previous_build.as_ref() {
+                    Some(previous) => log::info!("{}", BuildDiff::new(previous, &user_image)),
+                    None => log::info!("User image built"),
+                }
+                previous_build = Some(user_image);
+            }
+            Err(e) => log::error!("{e}"),
+        }
+
+        log::info!(
+            "Watching {} for changes. Press Ctrl+C to stop.",
+            context_path.display()
+        );
+        let (_, changed_paths) = wait_for_change(context_path, &watched_paths, &snapshot)
+            .await
+            .map_err(BuildError::Build)?;
+        log::info!("Changed: {}", changed_paths.join(", "));
+    }
+}
+
+async fn build_user_image(
+    build_args: &BuildArgs,
+    versions: &RuntimeVersions,
+    verbose: bool,
+    output_path: &Path,
+    timestamp: String,
+) -> Result<UserImageBuild, BuildError> {
+    let (enclave_config, validated_config) =
+        read_and_validate_config(&build_args.config, build_args)?;
     let formatted_args = prepare_build_args(&build_args.docker_build_args);
     let borrowed_args = formatted_args
         .as_ref()
         .map(|args| args.iter().map(AsRef::as_ref).collect());
-
     let (data_plane_version, installer_version) =
-        match get_runtime_and_installer_version(build_args.from_existing.clone()).await {
-            Ok(versions) => versions,
-            Err(e) => {
-                log::error!(
-                    "Failed to retrieve the latest data plane and installer versions - {e:?}"
-                );
-                return e.exitcode();
-            }
-        };
+        versions.resolve(enclave_config.runtime_channel());
+
+    build_from_scratch(
+        &validated_config,
+        Path::new(&build_args.context_path),
+        verbose,
+        borrowed_args,
+        data_plane_version,
+        installer_version,
+        output_path,
+        timestamp,
+        build_args.reproducible,
+        build_args.no_cache,
+    )
+    .await?;
+    Ok(UserImageBuild::read(output_path)?)
+}
+
+// Kept so `ev enclave dockerfile diff` can explain PCR changes after the CLI is upgraded
+fn cache_generated_dockerfile(build_args: &BuildArgs) {
+    let Some(directory) = dockerfile_cache_directory() else {
+        return;
+    };
+    let dockerfile_path = Path::new(&build_args.output_dir).join(EV_USER_DOCKERFILE_PATH);
+    match std::fs::read_to_string(&dockerfile_path) {
+        Ok(dockerfile) => DockerfileHistory::new(directory, Path::new(&build_args.config))
+            .store(env!("CARGO_PKG_VERSION"), &dockerfile),
+        Err(e) => log::debug!("Failed to read the generated Dockerfile to cache it - {e}"),
+    }
+}
+
+async fn build_enclave(
+    build_args: &BuildArgs,
+    versions: &RuntimeVersions,
+    verbose: bool,
+) -> Result<(BuiltEnclave, Vec<String>), BuildError> {
+    let (mut enclave_config, mut validated_config) =
+        read_and_validate_config(&build_args.config, build_args)?;
+
+    // The presets are expanded during validation, so the literal destinations are recorded
+    let egress_destinations = validated_config.egress().allowed_destinations();
+    if !enclave_config.egress.presets.is_empty() {
+        log::info!(
+            "Egress presets {} expanded to: {}",
+            enclave_config.egress.presets.join(", "),
+            egress_destinations.join(", ")
+        );
+    }
+
+    // Recorded in the enclave.toml with the rest of the build's results, so it's only asked once
+    if build_args.from_existing.is_none() {
+        if let Some(port) =
+            resolve_service_port(&validated_config, &build_args.context_path).await?
+        {
+            enclave_config.set_port(port);
+            validated_config.port = Some(port);
+        }
+    }
+
+    let formatted_args = prepare_build_args(&build_args.docker_build_args);
+    let borrowed_args = formatted_args
+        .as_ref()
+        .map(|args| args.iter().map(AsRef::as_ref).collect());
 
     let timestamp = get_source_date_epoch();
+    let (data_plane_version, installer_version) =
+        versions.resolve(enclave_config.runtime_channel());
 
-    let from_existing = build_args.from_existing;
-    let built_enclave = match build_enclave_image_file(
+    let runtime_digests = resolve_runtime_digests(
+        &validated_config,
+        &data_plane_version,
+        &installer_version,
+        enclave_config.runtime.as_ref(),
+    )
+    .await?;
+
+    let from_existing = build_args.from_existing.clone();
+    let (built_enclave, _) = build_enclave_image_file(
         &validated_config,
         &build_args.context_path,
         Some(&build_args.output_dir),
-        base_args.verbose,
+        verbose,
         borrowed_args,
-        data_plane_version,
-        installer_version,
+        data_plane_version.clone(),
+        installer_version.clone(),
         timestamp,
         from_existing,
         build_args.reproducible,
         build_args.no_cache,
+        build_args.max_context_size,
     )
-    .await
-    {
-        Ok((built_enclave, _)) => built_enclave,
-        Err(e) => {
-            log::error!("An error occurred while building your Enclave — {e}");
-            return e.exitcode();
-        }
-    };
+    .await?;
+
+    if let Some(pcr_output) = build_args.pcr_output.as_deref() {
+        EnclaveSigningInfo::try_from(validated_config.signing_info())
+            .map_err(Into::into)
+            .and_then(|signing_info| {
+                write_pcr_signature_bundle(
+                    built_enclave.measurements(),
+                    &signing_info,
+                    std::path::Path::new(pcr_output),
+                )
+            })
+            .map_err(BuildError::PcrSignature)?;
+        log::info!("Signed PCRs written to {pcr_output}");
+    }
 
     enclave_config.set_attestation(built_enclave.measurements());
-    ev_enclave::common::save_enclave_config(&enclave_config, &build_args.config);
+    if build_args.sign_attestation {
+        let signing_info = EnclaveSigningInfo::try_from(validated_config.signing_info())
+            .map_err(ImageBuildError::from)?;
+        let signature = sign_attestation(built_enclave.measurements(), &signing_info)?;
+        enclave_config.set_attestation_signature(signature);
+    }
+    enclave_config.set_runtime_versions(&data_plane_version, &installer_version);
+    enclave_config.set_runtime_digests(&runtime_digests);
+    ev_enclave::common::save_enclave_config(
+        &enclave_config,
+        build_args
+            .config_output
+            .as_deref()
+            .unwrap_or(&build_args.config),
+    );
+
+    if build_args.from_existing.is_none() {
+        cache_generated_dockerfile(build_args);
+    }
 
     if enclave_config.debug {
         ev_enclave::common::log_debug_mode_attestation_warning();
     }
 
-    // Write Enclave measures to stdout
-    let success_msg = serde_json::json!({
-        "status": "success",
-        "message": "EIF built successfully",
-        "enclaveMeasurements": built_enclave.measurements()
-    });
-
-    println!("{}", serde_json::to_string_pretty(&success_msg).unwrap());
-    exitcode::OK
+    Ok((built_enclave, egress_destinations))
 }
@@ -1,10 +1,13 @@
-use atty::Stream;
 use clap::{Parser, Subcommand};
 use common::api::BasicAuth;
-use common::CliError;
+use ev_cli_derive::CliMessage;
 use ev_enclave::cert::{self, DistinguishedName};
-use ev_enclave::config::EnclaveConfig;
-use exitcode::DATAERR;
+use ev_enclave::config::{EnclaveConfig, EnclaveConfigError};
+use evervault_api_client::enclave::{CreateEnclaveSigningCertRefResponse, EnclaveSigningCert};
+use thiserror::Error;
+
+use crate::tty::outputs_json;
+use crate::BaseArgs;
 
 /// Manage Enclave signing certificates
 #[derive(Debug, Parser)]
@@ -22,9 +25,24 @@ pub enum CertCommands {
     /// Upload a Enclave signing certificate's metadata to Evervault
     #[command()]
     Upload(UploadCertArgs),
+    /// List the signing certificates registered with Evervault
+    #[command()]
+    List,
     /// Lock a Enclave to specific signing certificate. Enclave deployment will fail if the signing certificate is not the one specified.
+    #[cfg(feature = "tty")]
     #[command()]
     Lock(LockCertArgs),
+    /// Connect to an Enclave and inspect its attested TLS certificate, including the attestation doc embedded in it
+    #[cfg(not(target_os = "windows"))]
+    #[command()]
+    Inspect(InspectCertArgs),
+}
+
+#[derive(Parser, Debug)]
+#[command(name = "inspect", about)]
+pub struct InspectCertArgs {
+    /// URL or hostname of the Enclave, e.g. https://my-enclave.app-123.enclave.evervault.com
+    pub target: String,
 }
 
 #[derive(Parser, Debug)]
@@ -54,7 +72,7 @@ pub struct NewCertArgs {
 #[derive(Parser, Debug)]
 #[command(name = "upload", about)]
 pub struct UploadCertArgs {
-    /// Path to directory where the signing cert will be saved
+    /// Path to the signing cert to upload. Defaults to the cert in enclave.toml.
     #[arg(short = 'p', long = "cert_path")]
     pub cert_path: Option<String>,
 
@@ -63,7 +81,12 @@ pub struct UploadCertArgs {
     pub name: String,
 
     /// Path to enclave.toml config file
-    #[arg(short = 'c', long = "config", default_value = "./enclave.toml")]
+    #[arg(
+        short = 'c',
+        long = "config",
+        default_value = "./enclave.toml",
+        env = "EV_ENCLAVE_CONFIG"
+    )]
     pub config: String,
 }
 
@@ -71,117 +94,252 @@ pub struct UploadCertArgs {
 #[command(name = "lock", about)]
 pub struct LockCertArgs {
     /// Path to enclave.toml config file
-    #[arg(short = 'c', long = "config", default_value = "./enclave.toml")]
+    #[arg(
+        short = 'c',
+        long = "config",
+        default_value = "./enclave.toml",
+        env = "EV_ENCLAVE_CONFIG"
+    )]
     pub config: String,
 }
 
-pub async fn run(cert_args: CertArgs, (_, api_key): BasicAuth) -> exitcode::ExitCode {
+#[derive(Debug, Error, CliMessage)]
+pub enum CertError {
+    #[error("{0}")]
+    #[cli(code = "enclaves/invalid-subject")]
+    Subject(#[cli(exitcode)] cert::CertError),
+    #[error("An error occurred while generating your cert - {0}")]
+    #[cli(code = "enclaves/cert-error")]
+    Generate(#[cli(exitcode)] cert::CertError),
+    #[error("An error occurred while reading enclave.toml - {0}")]
+    #[cli(code = "enclaves/config-error")]
+    Config(
+        #[from]
+        #[cli(exitcode)]
+        EnclaveConfigError,
+    ),
+    #[error("No signing info found in enclave.toml")]
+    #[cli(code = "enclaves/missing-signing-info", exitcode = exitcode::DATAERR)]
+    MissingSigningInfo,
+    #[error("No Enclave details found in enclave.toml")]
+    #[cli(code = "enclaves/missing-uuid", exitcode = exitcode::DATAERR)]
+    #[cfg_attr(not(feature = "tty"), allow(dead_code))]
+    MissingEnclaveUuid,
+    #[error("An error occurred while generating PCR8 for your cert - {0}")]
+    #[cli(code = "enclaves/cert-upload-error")]
+    Upload(#[cli(exitcode)] cert::CertError),
+    #[error("An error occurred while retrieving your signing certs - {0}")]
+    #[cli(code = "enclaves/api-error")]
+    List(#[cli(exitcode)] cert::CertError),
+    #[error("Failed to lock the Enclave to certs - {0}")]
+    #[cli(code = "enclaves/cert-lock-error")]
+    #[cfg_attr(not(feature = "tty"), allow(dead_code))]
+    Lock(#[cli(exitcode)] cert::CertError),
+    #[error("Failed to inspect the certificate presented by {0} - {1}")]
+    #[cli(code = "enclaves/cert-inspect-error", exitcode = exitcode::UNAVAILABLE)]
+    Inspect(String, String),
+}
+
+#[derive(Debug, strum_macros::Display, CliMessage)]
+#[cli(code = "generic/success", exitcode = exitcode::OK)]
+pub enum CertMessage {
+    #[strum(
+        to_string = "Signing cert successfully generated...\n> Certificate saved to {certificate}\n> Key saved to {private_key}"
+    )]
+    Created {
+        certificate: String,
+        private_key: String,
+        #[cli(data)]
+        paths: serde_json::Value,
+    },
+    #[strum(to_string = "{summary}\nCertificate metadata uploaded to Evervault")]
+    Uploaded {
+        summary: String,
+        #[cli(data)]
+        cert_ref: Option<CreateEnclaveSigningCertRefResponse>,
+    },
+    #[strum(
+        to_string = "No signing certs registered. Upload a cert using `ev enclave cert upload`."
+    )]
+    NoCerts,
+    #[strum(to_string = "{summary}")]
+    Listed {
+        summary: String,
+        #[cli(data)]
+        certs: Option<Vec<EnclaveSigningCert>>,
+    },
+    #[strum(to_string = "{0}")]
+    #[cfg_attr(not(feature = "tty"), allow(dead_code))]
+    Locked(String),
+    #[strum(to_string = "Close one! Update Cancelled.")]
+    #[cli(code = "generic/cancelled")]
+    #[cfg_attr(not(feature = "tty"), allow(dead_code))]
+    LockCancelled,
+    #[strum(to_string = "{summary}")]
+    Inspected {
+        summary: String,
+        #[cli(data)]
+        inspection: Option<serde_json::Value>,
+    },
+}
+
+pub async fn run(cert_args: CertArgs, (_, api_key): BasicAuth) -> Result<CertMessage, CertError> {
+    let json = outputs_json(BaseArgs::parse().json);
     match cert_args.action {
         CertCommands::New(new_args) => {
-            let distinguished_name =
-                match try_resolve_distinguished_name(new_args.subject.as_deref()) {
-                    Ok(distinguished_name) => distinguished_name,
-                    Err(e) => {
-                        log::error!("{e}");
-                        return e.exitcode();
-                    }
-                };
+            let distinguished_name = try_resolve_distinguished_name(new_args.subject.as_deref())
+                .map_err(CertError::Subject)?;
             let output_path = std::path::Path::new(&new_args.output_dir);
 
             let desired_lifetime =
                 cert::DesiredLifetime::new(new_args.days, new_args.weeks, new_args.years);
 
             let (cert_path, key_path) =
-                match cert::create_new_cert(output_path, distinguished_name, desired_lifetime) {
-                    Ok(paths) => paths,
-                    Err(e) => {
-                        log::error!("An error occurred while generating your cert - {e}");
-                        return e.exitcode();
-                    }
-                };
-
-            if atty::is(Stream::Stdout) {
-                log::info!("Signing cert successfully generated...");
-                log::info!("> Certificate saved to {}", cert_path.display());
-                log::info!("> Key saved to {}", key_path.display());
-            } else {
-                let success_msg = serde_json::json!({
-                    "status": "success",
-                    "output": {
+                cert::create_new_cert(output_path, distinguished_name, desired_lifetime)
+                    .map_err(CertError::Generate)?;
+
+            Ok(CertMessage::Created {
+                certificate: cert_path.display().to_string(),
+                private_key: key_path.display().to_string(),
+                paths: match json {
+                    true => serde_json::json!({
                         "certificate": cert_path,
                         "privateKey": key_path
-                    }
-                });
-                println!("{}", serde_json::to_string(&success_msg).unwrap());
-            };
+                    }),
+                    false => serde_json::Value::Null,
+                },
+            })
         }
         CertCommands::Upload(upload_args) => {
             let cert_path = match upload_args.cert_path {
                 Some(cert_path) => cert_path,
-                None => match EnclaveConfig::try_from_filepath(&upload_args.config) {
-                    Ok(enclave_config) => match enclave_config.signing {
-                        Some(signing_info) if signing_info.cert.is_some() => {
-                            signing_info.cert.unwrap()
-                        }
-                        _ => {
-                            log::error!("No signing info found in enclave.toml");
-                            return DATAERR;
-                        }
-                    },
-                    Err(e) => {
-                        log::error!("An error occurred while reading enclave.toml - {e}");
-                        return e.exitcode();
-                    }
-                },
+                None => EnclaveConfig::try_from_filepath(&upload_args.config)?
+                    .signing
+                    .and_then(|signing_info| signing_info.cert)
+                    .ok_or(CertError::MissingSigningInfo)?,
             };
 
-            let cert_ref =
-                match cert::upload_new_cert_ref(&cert_path, &api_key, upload_args.name).await {
-                    Ok(pcr8) => pcr8,
-                    Err(e) => {
-                        log::error!("An error occurred while generating PCR8 for your cert - {e}");
-                        return e.exitcode();
-                    }
-                };
-
-            if atty::is(Stream::Stdout) {
-                log::info!("PCR8: {}", cert_ref.cert_hash());
-                log::info!("Not Before: {}", cert_ref.not_before());
-                log::info!("Not After: {}", cert_ref.not_after());
-                log::info!("Certificate metadata uploaded to Evervault");
-            } else {
-                let success_msg = serde_json::json!({
-                    "status": "success",
-                    "output": cert_ref,
-                });
-                println!("{}", serde_json::to_string(&success_msg).unwrap());
-            };
+            let cert_ref = cert::upload_new_cert_ref(
+                &cert_path,
+                crate::auth::api_auth_mode(api_key.clone()),
+                upload_args.name,
+            )
+            .await
+            .map_err(CertError::Upload)?;
+
+            Ok(CertMessage::Uploaded {
+                summary: format!(
+                    "PCR8: {}\nNot Before: {}\nNot After: {}",
+                    cert_ref.cert_hash(),
+                    cert_ref.not_before(),
+                    cert_ref.not_after()
+                ),
+                cert_ref: json.then_some(cert_ref),
+            })
         }
-        CertCommands::Lock(lock_cert_args) => {
-            let (enclave_uuid, enclave_name) =
-                match EnclaveConfig::try_from_filepath(&lock_cert_args.config) {
-                    Ok(enclave_config) => match (enclave_config.uuid, enclave_config.name) {
-                        (Some(uuid), name) => (uuid, name),
-                        _ => {
-                            log::error!("No Enclave details found in enclave.toml");
-                            return DATAERR;
-                        }
-                    },
-                    Err(_) => {
-                        log::error!("Failed to load Enclave configuration");
-                        return DATAERR;
-                    }
-                };
-
-            if let Err(e) =
-                cert::lock_enclave_to_certs(&api_key, &enclave_uuid, &enclave_name).await
-            {
-                return e.exitcode();
+        CertCommands::List => {
+            let certs = cert::list_cert_refs(crate::auth::api_auth_mode(api_key.clone()))
+                .await
+                .map_err(CertError::List)?;
+
+            if certs.is_empty() && !json {
+                return Ok(CertMessage::NoCerts);
             }
+            Ok(CertMessage::Listed {
+                summary: certs
+                    .iter()
+                    .map(cert::format_cert_summary)
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                certs: json.then_some(certs),
+            })
         }
+        #[cfg(feature = "tty")]
+        CertCommands::Lock(lock_cert_args) => {
+            let enclave_config = EnclaveConfig::try_from_filepath(&lock_cert_args.config)?;
+            let enclave_uuid = enclave_config.uuid.ok_or(CertError::MissingEnclaveUuid)?;
+            let enclave_name = enclave_config.name;
+
+            let locked = cert::lock_enclave_to_certs(
+                crate::auth::api_auth_mode(api_key.clone()),
+                &enclave_uuid,
+                &enclave_name,
+            )
+            .await
+            .map_err(CertError::Lock)?;
+            Ok(match locked {
+                None => CertMessage::LockCancelled,
+                Some(0) => CertMessage::Locked(format!(
+                    "Enclave {enclave_name} successfully unlocked from all certs!"
+                )),
+                Some(1) => CertMessage::Locked(format!(
+                    "Enclave {enclave_name} successfully locked to 1 cert!"
+                )),
+                Some(count) => CertMessage::Locked(format!(
+                    "Enclave {enclave_name} successfully locked to {count} certs!"
+                )),
+            })
+        }
+        #[cfg(not(target_os = "windows"))]
+        CertCommands::Inspect(inspect_args) => inspect_cert(&inspect_args.target, json).await,
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+async fn inspect_cert(target: &str, json: bool) -> Result<CertMessage, CertError> {
+    let inspection = ev_enclave::attest::inspect::inspect_certificate(target)
+        .await
+        .map_err(|e| CertError::Inspect(target.to_string(), e.to_string()))?;
+
+    let mut summary = format!(
+        "Certificate chain presented by {}:{}",
+        inspection.host, inspection.port
+    );
+    for (index, cert) in inspection.chain.iter().enumerate() {
+        summary.push_str(&format!(
+            "\n\n[{index}] {}\n  Issuer: {}\n  Serial: {}\n  SHA-256: {}\n  Valid: {} to {} ({} days remaining)\n  CA: {}",
+            cert.subject,
+            cert.issuer,
+            cert.serial,
+            cert.fingerprint,
+            cert.not_before,
+            cert.not_after,
+            cert.days_until_expiry,
+            cert.is_ca
+        ));
+        cert.subject_alt_names
+            .iter()
+            .for_each(|name| summary.push_str(&format!("\n  SAN: {name}")));
     }
 
-    exitcode::OK
+    if let Some(doc) = inspection.attestation_doc.as_ref() {
+        summary.push_str(&format!(
+            "\n\nAttestation doc (from the leaf certificate's SAN):\n  Module: {}\n  Timestamp: {}\n  Valid: {}",
+            doc.module_id,
+            doc.timestamp,
+            doc.valid
+        ));
+        doc.pcrs
+            .iter()
+            .for_each(|(name, value)| summary.push_str(&format!("\n  {name}: {value}")));
+        let display = |value: &Option<String>| value.clone().unwrap_or_else(|| "-".to_string());
+        summary.push_str(&format!(
+            "\n  User data: {}\n  Nonce: {}\n  Public key: {}",
+            display(&doc.user_data),
+            display(&doc.nonce),
+            display(&doc.public_key)
+        ));
+    }
+    inspection
+        .warnings
+        .iter()
+        .for_each(|warning| log::warn!("{warning}"));
+    Ok(CertMessage::Inspected {
+        summary,
+        inspection: json
+            .then(|| serde_json::to_value(&inspection).ok())
+            .flatten(),
+    })
 }
 
 fn try_resolve_distinguished_name(
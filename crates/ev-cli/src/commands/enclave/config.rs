@@ -0,0 +1,175 @@
+use clap::{Parser, Subcommand};
+use common::table::TableError;
+use ev_cli_derive::CliMessage;
+use ev_enclave::build::signature::{verify_attestation_signature, AttestationSignatureError};
+use ev_enclave::config::{EnclaveConfig, EnclaveConfigError};
+use ev_enclave::lint::{audit_config, exceeds_threshold, findings_table, Finding, Severity};
+use std::path::Path;
+use thiserror::Error;
+
+use crate::table::TableArgs;
+use crate::BaseArgs;
+
+/// Check an Enclave's config
+#[derive(Debug, Parser)]
+#[command(name = "config", about)]
+pub struct ConfigArgs {
+    #[command(subcommand)]
+    pub action: ConfigCommands,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommands {
+    /// Flag risky settings in an Enclave's config, such as debug mode, disabled API key auth and wildcard egress
+    #[command()]
+    Audit(AuditConfigArgs),
+    /// Check the attestation block of an Enclave's config is signed by its signing cert, to detect manual changes to its PCRs
+    #[command()]
+    Verify(VerifyConfigArgs),
+}
+
+#[derive(Debug, Parser)]
+#[command(name = "audit", about)]
+pub struct AuditConfigArgs {
+    /// Path to enclave.toml config file
+    #[arg(
+        short = 'c',
+        long = "config",
+        default_value = "./enclave.toml",
+        env = "EV_ENCLAVE_CONFIG"
+    )]
+    pub config: String,
+
+    /// Exit non-zero when any finding is at least this severe
+    #[arg(long = "fail-on", value_enum)]
+    pub fail_on: Option<Severity>,
+
+    #[command(flatten)]
+    pub table_args: TableArgs,
+}
+
+#[derive(Debug, Parser)]
+#[command(name = "verify", about)]
+pub struct VerifyConfigArgs {
+    /// Path to enclave.toml config file
+    #[arg(
+        short = 'c',
+        long = "config",
+        default_value = "./enclave.toml",
+        env = "EV_ENCLAVE_CONFIG"
+    )]
+    pub config: String,
+
+    /// Certificate the attestation block should be signed by. This is required rather than read from the .toml file, as anyone able to edit the PCRs could also swap its signing cert, so CI should pin a cert kept outside the repo.
+    #[arg(long = "signing-cert", env = "EV_SIGNING_CERT")]
+    pub certificate: String,
+}
+
+#[derive(Debug, Error, CliMessage)]
+pub enum ConfigError {
+    #[error("{0}")]
+    #[cli(code = "enclaves/config-error")]
+    Config(
+        #[from]
+        #[cli(exitcode)]
+        EnclaveConfigError,
+    ),
+    #[error("{0}")]
+    #[cli(code = "generic/table-error")]
+    Table(
+        #[from]
+        #[cli(exitcode)]
+        TableError,
+    ),
+    #[error("{table}Found settings with {threshold} severity or above")]
+    #[cli(code = "enclaves/risky-config", exitcode = exitcode::CONFIG)]
+    RiskyFindings {
+        threshold: Severity,
+        table: String,
+        #[cli(data)]
+        findings: Option<Vec<Finding>>,
+    },
+    #[error("{0}")]
+    #[cli(code = "enclaves/attestation-signature-error")]
+    AttestationSignature(
+        #[from]
+        #[cli(exitcode)]
+        AttestationSignatureError,
+    ),
+}
+
+#[derive(Debug, strum_macros::Display, CliMessage)]
+#[cli(code = "generic/success", exitcode = exitcode::OK)]
+pub enum ConfigMessage {
+    #[strum(to_string = "No risky settings found in {config}")]
+    NoFindings { config: String },
+    #[strum(to_string = "{0}")]
+    Table(String),
+    #[strum(to_string = "Found {count} risky settings")]
+    Findings {
+        count: usize,
+        #[cli(data)]
+        findings: Vec<Finding>,
+    },
+    #[strum(to_string = "The attestation block in {config} is signed by {cert}")]
+    Verified { config: String, cert: String },
+}
+
+pub fn run(config_args: ConfigArgs) -> Result<ConfigMessage, ConfigError> {
+    match config_args.action {
+        ConfigCommands::Audit(audit_args) => audit(audit_args),
+        ConfigCommands::Verify(verify_args) => verify(verify_args),
+    }
+}
+
+fn verify(verify_args: VerifyConfigArgs) -> Result<ConfigMessage, ConfigError> {
+    let config = EnclaveConfig::try_from_filepath(&verify_args.config)?;
+    let cert = verify_args.certificate;
+    verify_attestation_signature(
+        config.attestation.as_ref(),
+        config.attestation_signature.as_ref(),
+        Path::new(&cert),
+    )?;
+    Ok(ConfigMessage::Verified {
+        config: verify_args.config,
+        cert,
+    })
+}
+
+fn audit(audit_args: AuditConfigArgs) -> Result<ConfigMessage, ConfigError> {
+    let config = EnclaveConfig::try_from_filepath(&audit_args.config)?;
+
+    let findings = audit_config(&config);
+    let failed_threshold = audit_args
+        .fail_on
+        .filter(|threshold| exceeds_threshold(&findings, *threshold));
+
+    if !audit_args.table_args.use_table(BaseArgs::parse().json) {
+        return match failed_threshold {
+            Some(threshold) => Err(ConfigError::RiskyFindings {
+                threshold,
+                table: String::new(),
+                findings: Some(findings),
+            }),
+            None => Ok(ConfigMessage::Findings {
+                count: findings.len(),
+                findings,
+            }),
+        };
+    }
+
+    if findings.is_empty() {
+        return Ok(ConfigMessage::NoFindings {
+            config: audit_args.config,
+        });
+    }
+    let rendered = audit_args.table_args.render(findings_table(&findings))?;
+    match failed_threshold {
+        Some(threshold) => Err(ConfigError::RiskyFindings {
+            threshold,
+            table: rendered,
+            findings: None,
+        }),
+        None => Ok(ConfigMessage::Table(rendered.trim_end().to_string())),
+    }
+}
@@ -0,0 +1,69 @@
+use clap::{Parser, Subcommand};
+use ev_cli_derive::CliMessage;
+use ev_enclave::debug::{self, DebugError as EnclaveDebugError};
+use thiserror::Error;
+
+/// Debug Enclaves running on this host
+#[derive(Debug, Parser)]
+#[command(name = "debug", about)]
+pub struct DebugArgs {
+    #[command(subcommand)]
+    pub action: DebugCommands,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum DebugCommands {
+    #[command()]
+    Shell(DebugShellArgs),
+}
+
+/// Attach to the console of a debug-mode Enclave run on this host with nitro-cli, and show where to look when it fails to boot. Consoles are read-only.
+#[derive(Debug, Parser)]
+#[command(name = "shell", about)]
+pub struct DebugShellArgs {
+    /// ID of the Enclave to attach to, from nitro-cli describe-enclaves. Defaults to the only debug-mode Enclave running.
+    #[arg(long = "enclave-id")]
+    pub enclave_id: Option<String>,
+}
+
+#[derive(Debug, Error, CliMessage)]
+pub enum DebugError {
+    #[error("{0}")]
+    #[cli(code = "enclaves/debug-error")]
+    Debug(
+        #[from]
+        #[cli(exitcode)]
+        EnclaveDebugError,
+    ),
+    #[error("The console of Enclave {enclave_id} exited with code {exit_code}")]
+    #[cli(code = "enclaves/debug-console-failed", exitcode = *exit_code)]
+    Console { enclave_id: String, exit_code: i32 },
+}
+
+#[derive(Debug, strum_macros::Display, CliMessage)]
+#[cli(code = "generic/success", exitcode = exitcode::OK)]
+pub enum DebugMessage {
+    #[strum(to_string = "Detached from the console of Enclave {0}")]
+    Detached(String),
+}
+
+pub fn run(debug_args: &DebugArgs) -> Result<DebugMessage, DebugError> {
+    let DebugCommands::Shell(shell_args) = &debug_args.action;
+    let enclave =
+        debug::select_debug_enclave(debug::running_enclaves()?, shell_args.enclave_id.as_deref())?;
+
+    log::info!("{}", debug::boot_sequence_guide());
+    log::info!(
+        "Attaching to the console of Enclave {}. Press Ctrl+C to detach.",
+        enclave.id
+    );
+    let status = debug::attach_console(&enclave.id)?;
+    // Detaching with Ctrl+C kills the console without an exit code
+    match status.code() {
+        Some(exit_code) if exit_code != 0 => Err(DebugError::Console {
+            enclave_id: enclave.id,
+            exit_code,
+        }),
+        _ => Ok(DebugMessage::Detached(enclave.id)),
+    }
+}
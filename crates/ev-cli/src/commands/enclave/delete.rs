@@ -1,19 +1,50 @@
+use crate::completion::enclave_uuid_completer;
 use clap::Parser;
-use common::{api::BasicAuth, CliError};
-use ev_enclave::delete::delete_enclave;
+use common::api::client::ApiError;
+use common::api::BasicAuth;
+use common::table::Table;
+use common::CliError;
+use ev_cli_derive::CliMessage;
+use ev_enclave::audit::{append_audit_record, AuditAction, AuditError, AuditRecord};
+use ev_enclave::config::EnclaveConfig;
+use ev_enclave::delete::{
+    delete_enclave, delete_enclaves, export_enclave_state, DeleteError as EnclaveDeleteError,
+    EnclaveSelector,
+};
+use ev_enclave::enclave::EnclaveSigningInfo;
+use ev_enclave::prompt::{self, PromptError};
+use evervault_api_client::enclave::{EnclaveApi, EnclaveClient, EnclaveState};
+use thiserror::Error;
 
 /// Delete an Enclave from a toml file.
 #[derive(Debug, Parser)]
 #[command(name = "delete", about)]
 pub struct DeleteArgs {
     /// Path to enclave.toml config file
-    #[arg(short = 'c', long = "config", default_value = "./enclave.toml")]
+    #[arg(
+        short = 'c',
+        long = "config",
+        default_value = "./enclave.toml",
+        env = "EV_ENCLAVE_CONFIG"
+    )]
     pub config: String,
 
     /// Uuid of the Enclave to delete
-    #[arg(long = "enclave-uuid")]
+    #[arg(long = "enclave-uuid", env = "EV_ENCLAVE_UUID", add = enclave_uuid_completer(), conflicts_with = "all")]
     pub enclave_uuid: Option<String>,
 
+    /// Delete every Enclave in the App, or every Enclave matching --name-prefix
+    #[arg(long)]
+    pub all: bool,
+
+    /// Only delete Enclaves whose names start with this prefix. Requires --all.
+    #[arg(long = "name-prefix", requires = "all")]
+    pub name_prefix: Option<String>,
+
+    /// List the Enclaves which would be deleted, without deleting them. Requires --all.
+    #[arg(long = "dry-run", requires = "all")]
+    pub dry_run: bool,
+
     /// Perform the Enclave deletion in the background
     #[arg(long)]
     pub background: bool,
@@ -21,52 +52,252 @@ pub struct DeleteArgs {
     /// Prevent confirmation dialogue and proceed with deletion. Use with caution.
     #[arg(long)]
     pub force: bool,
+
+    /// Append a record of the deletion to this JSON lines file, signed with the Enclave's signing key if available
+    #[arg(long = "audit-log", env = "EV_AUDIT_LOG")]
+    pub audit_log: Option<String>,
+
+    /// Before deleting, write the Enclave's config, last deployment's PCRs, env var names and domains to this JSON file. The Enclave isn't deleted if the export fails.
+    #[arg(long = "export-state", conflicts_with = "all")]
+    pub export_state: Option<std::path::PathBuf>,
 }
 
-fn should_continue() -> Result<bool, exitcode::ExitCode> {
-    dialoguer::Confirm::new()
-        .with_prompt("Are you sure you want to delete this Enclave?")
-        .default(false)
-        .interact()
-        .map_err(|_| {
-            log::error!("An error occurred while attempting to confirm this Enclave delete.");
-            exitcode::IOERR
-        })
+#[derive(Debug, Error, CliMessage)]
+pub enum DeleteError {
+    #[error("An error occurred while attempting to confirm this Enclave delete — {0}")]
+    #[cli(code = "enclaves/prompt-error")]
+    Prompt(
+        #[from]
+        #[cli(exitcode)]
+        PromptError,
+    ),
+    #[error("{0}")]
+    #[cli(code = "enclaves/delete-error")]
+    Delete(
+        #[from]
+        #[cli(exitcode)]
+        EnclaveDeleteError,
+    ),
+    #[error("{0}")]
+    #[cli(code = "enclaves/audit-error")]
+    Audit(
+        #[from]
+        #[cli(exitcode)]
+        AuditError,
+    ),
+    #[error("An error occurred while retrieving your Enclaves — {0}")]
+    #[cli(code = "enclaves/list-error")]
+    List(#[cli(exitcode)] ApiError),
+    #[error("Marked {deleted} of {total} Enclave(s) for deletion.")]
+    #[cli(code = "enclaves/delete-error", exitcode = *exitcode)]
+    BulkDeleteFailed {
+        deleted: usize,
+        total: usize,
+        exitcode: exitcode::ExitCode,
+    },
+}
+
+#[derive(Debug, strum_macros::Display, CliMessage)]
+#[cli(code = "generic/success", exitcode = exitcode::OK)]
+pub enum DeleteMessage {
+    #[strum(to_string = "Deletion was successful")]
+    Deleted,
+    #[strum(to_string = "Enclave successfully marked for deletion.")]
+    MarkedForDeletion,
+    #[strum(to_string = "No Enclaves matched, nothing to delete.")]
+    NoneMatched,
+    #[strum(to_string = "Dry run, no Enclaves were deleted.")]
+    DryRun,
+    #[strum(to_string = "Marked {deleted} of {total} Enclave(s) for deletion.")]
+    BulkDeleted { deleted: usize, total: usize },
+    #[strum(to_string = "Phew! Exiting early...")]
+    #[cli(code = "generic/cancelled")]
+    Cancelled,
 }
 
-pub async fn run(delete_args: DeleteArgs, (_, api_key): BasicAuth) -> exitcode::ExitCode {
-    if !delete_args.force {
-        let should_delete = match should_continue() {
-            Ok(should_delete) => should_delete,
-            Err(e) => return e,
+pub async fn run(
+    delete_args: DeleteArgs,
+    (_, api_key): BasicAuth,
+) -> Result<DeleteMessage, DeleteError> {
+    if delete_args.all {
+        let selector = EnclaveSelector {
+            name_prefix: delete_args.name_prefix.clone(),
+            states: vec![EnclaveState::Pending, EnclaveState::Active],
+            older_than: None,
         };
+        let options = BulkDeleteOptions {
+            dry_run: delete_args.dry_run,
+            force: delete_args.force,
+            audit_log: delete_args.audit_log.as_deref(),
+        };
+        return delete_in_bulk(&api_key, &selector, options).await;
+    }
 
-        if !should_delete {
-            log::info!("Phew! Exiting early...");
-            return exitcode::OK;
-        }
+    if !delete_args.force
+        && !prompt::confirm("Are you sure you want to delete this Enclave?", false)?
+    {
+        return Ok(DeleteMessage::Cancelled);
+    }
+
+    if let Some(export_path) = delete_args.export_state.as_deref() {
+        export_enclave_state(
+            delete_args.config.as_str(),
+            delete_args.enclave_uuid.as_deref(),
+            crate::auth::api_auth_mode(api_key.clone()),
+            export_path,
+        )
+        .await?;
+        log::info!("Exported the Enclave's state to {}", export_path.display());
     }
 
-    match delete_enclave(
+    let delete_result = delete_enclave(
         delete_args.config.as_str(),
         delete_args.enclave_uuid.as_deref(),
-        api_key.as_str(),
+        crate::auth::api_auth_mode(api_key.clone()),
         delete_args.background,
     )
-    .await
-    {
-        Ok(_) => {
-            if delete_args.background {
-                log::info!("Enclave successfully marked for deletion.");
-            } else {
-                log::info!("Deletion was successful");
+    .await;
+
+    if let Some(audit_log) = delete_args.audit_log.as_deref() {
+        // the deletion's outcome takes precedence over a failure to record it
+        if let Err(e) = write_audit_record(&delete_args, audit_log, &delete_result) {
+            if delete_result.is_ok() {
+                return Err(e.into());
             }
-        }
-        Err(e) => {
             log::error!("{e}");
-            return e.exitcode();
         }
+    }
+
+    delete_result?;
+    if delete_args.background {
+        Ok(DeleteMessage::MarkedForDeletion)
+    } else {
+        Ok(DeleteMessage::Deleted)
+    }
+}
+
+fn write_audit_record(
+    delete_args: &DeleteArgs,
+    audit_log: &str,
+    delete_result: &Result<(), EnclaveDeleteError>,
+) -> Result<(), AuditError> {
+    // the config is optional when deleting by uuid, but provides the PCRs and signing key if present
+    let config = EnclaveConfig::try_from_filepath(&delete_args.config).ok();
+    let Some(enclave_uuid) = delete_args
+        .enclave_uuid
+        .clone()
+        .or_else(|| config.as_ref().and_then(|config| config.uuid.clone()))
+    else {
+        log::warn!("Skipping audit log record as the Enclave uuid couldn't be resolved");
+        return Ok(());
     };
+    let signing_info = config
+        .as_ref()
+        .and_then(|config| config.cert().zip(config.key()))
+        .map(|(cert, key)| EnclaveSigningInfo::new(cert.into(), key.into()))
+        .filter(|signing_info| signing_info.cert().exists() && signing_info.key().exists());
 
-    exitcode::OK
+    let record = AuditRecord::new(AuditAction::Delete, &enclave_uuid, delete_result).with_pcrs(
+        config
+            .as_ref()
+            .and_then(|config| config.attestation.as_ref())
+            .map(|attestation| attestation.pcrs()),
+    );
+    append_audit_record(
+        std::path::Path::new(audit_log),
+        record,
+        signing_info.as_ref(),
+    )
+    .map(|_| ())
+}
+
+pub(super) struct BulkDeleteOptions<'a> {
+    pub dry_run: bool,
+    pub force: bool,
+    pub audit_log: Option<&'a str>,
+}
+
+/// Deletes every Enclave matched by the selector, after listing them and asking for confirmation
+pub(super) async fn delete_in_bulk(
+    api_key: &str,
+    selector: &EnclaveSelector,
+    options: BulkDeleteOptions<'_>,
+) -> Result<DeleteMessage, DeleteError> {
+    let enclave_client = EnclaveClient::new(crate::auth::api_auth_mode(api_key.to_string()));
+    let enclaves = enclave_client
+        .get_enclaves()
+        .await
+        .map_err(DeleteError::List)?;
+
+    let selected = selector.select(enclaves.enclaves(), chrono::Utc::now());
+    if selected.is_empty() {
+        return Ok(DeleteMessage::NoneMatched);
+    }
+
+    let mut table = Table::new([
+        ("name", "NAME"),
+        ("uuid", "UUID"),
+        ("state", "STATE"),
+        ("updated", "UPDATED"),
+    ]);
+    for enclave in &selected {
+        table.push_row(vec![
+            enclave.name.clone(),
+            enclave.uuid.clone(),
+            enclave.state.to_string(),
+            enclave.updated_at.clone(),
+        ]);
+    }
+    log::info!(
+        "{} Enclave(s) matched:\n{}",
+        selected.len(),
+        table.render(true).trim_end()
+    );
+
+    if options.dry_run {
+        return Ok(DeleteMessage::DryRun);
+    }
+
+    if !options.force {
+        let prompt = format!(
+            "Are you sure you want to delete these {} Enclaves?",
+            selected.len()
+        );
+        if !prompt::confirm(&prompt, false)? {
+            return Ok(DeleteMessage::Cancelled);
+        }
+    }
+
+    let enclave_uuids = selected
+        .iter()
+        .map(|enclave| enclave.uuid.clone())
+        .collect();
+    let results = delete_enclaves(&enclave_client, enclave_uuids).await;
+
+    let mut code = exitcode::OK;
+    for (enclave_uuid, result) in &results {
+        match result {
+            Ok(_) => log::info!("Enclave {enclave_uuid} marked for deletion."),
+            Err(e) => {
+                log::error!("Failed to delete Enclave {enclave_uuid} — {e}");
+                code = e.exitcode();
+            }
+        }
+        if let Some(audit_log) = options.audit_log {
+            let record = AuditRecord::new(AuditAction::Delete, enclave_uuid, result);
+            append_audit_record(std::path::Path::new(audit_log), record, None)?;
+        }
+    }
+
+    let total = results.len();
+    let deleted = results.iter().filter(|(_, result)| result.is_ok()).count();
+    if code == exitcode::OK {
+        Ok(DeleteMessage::BulkDeleted { deleted, total })
+    } else {
+        Err(DeleteError::BulkDeleteFailed {
+            deleted,
+            total,
+            exitcode: code,
+        })
+    }
 }
@@ -1,49 +1,86 @@
-use atty::Stream;
-use clap::Parser;
-use common::api::enclave_assets::EnclaveAssetsClient;
-use common::api::AuthMode;
-use common::api::{client::ApiErrorKind, BasicAuth};
-use common::CliError;
+use clap::builder::BoolishValueParser;
+use clap::{Args, Parser};
+use common::api::client::{ApiError, ApiErrorKind};
+use common::api::BasicAuth;
+use ev_cli_derive::CliMessage;
 use ev_enclave::{
-    api::enclave::EnclaveApi,
+    audit::{append_audit_record, AuditAction, AuditError, AuditRecord},
     build::build_enclave_image_file,
+    build::error::BuildError,
+    build::runtime::{resolve_runtime_digests, RuntimeDigests},
     common::prepare_build_args,
     common::OutputPath,
-    config::{read_and_validate_config, BuildTimeConfig, ValidatedEnclaveBuildConfig},
-    deploy::{deploy_eif, get_eif},
+    config::{
+        read_and_validate_config, BuildTimeConfig, EnclaveConfig, EnclaveConfigError, EnclaveSize,
+        RuntimeSettings, ValidatedEnclaveBuildConfig,
+    },
+    deploy::{
+        deploy_eif, get_eif, parse_rate_limit, resolve_size, validate_strategy,
+        DeployError as EnclaveDeployError, ExpectedPcrs, RemotePcrMismatch, UploadOptions,
+        MAX_UPLOAD_CONCURRENCY,
+    },
+    deployments::{rollback_deployment, RollbackReport},
     docker::command::get_source_date_epoch,
-    enclave::EIFMeasurements,
+    docker::remote::RemoteBuilderError,
+    enclave::{EIFMeasurements, EnclaveSigningInfo, Pcr},
+    env::missing_remote_env_vars,
+    policy::{self, PolicyError},
+    prompt::{self, PromptError},
+    smoke::{run_smoke_test, SmokeError, SmokeReport, SmokeTest},
+    state::refresh_cached_enclave,
+    version::{get_runtime_versions, RuntimeVersions, VersionError},
+    workspace::{Workspace, WorkspaceError},
 };
-use exitcode::ExitCode;
+use evervault_api_client::enclave::{DeployStrategy, EnclaveApi, EnclaveClient};
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::{Mutex, Semaphore};
 
+use crate::tty::outputs_json;
+use crate::workspace::{report_member, MemberPaths, WorkspaceArgs, WorkspaceSummary};
 use crate::BaseArgs;
 
 /// Deploy an Enclave from a toml file.
-#[derive(Debug, Parser)]
+#[derive(Clone, Debug, Parser)]
 #[command(name = "deploy", about)]
 pub struct DeployArgs {
-    /// Path to enclave.toml config file
-    #[arg(short = 'c', long = "config", default_value = "./enclave.toml")]
+    /// Path to enclave.toml config file. Use - to read the config from stdin.
+    #[arg(
+        short = 'c',
+        long = "config",
+        default_value = "./enclave.toml",
+        env = "EV_ENCLAVE_CONFIG"
+    )]
     pub config: String,
 
+    /// Path to write the updated Enclave config, including its attestation measurements, to. Defaults to the config path, unless the config is read from stdin with `--config -`.
+    #[arg(
+        long = "config-output",
+        env = "EV_CONFIG_OUTPUT",
+        conflicts_with = "all"
+    )]
+    pub config_output: Option<String>,
+
     /// Path to Dockerfile for Enclave. Will override any dockerfile specified in the .toml file.
-    #[arg(short = 'f', long = "file")]
+    #[arg(short = 'f', long = "file", env = "EV_DOCKERFILE")]
     pub dockerfile: Option<String>,
 
     /// Path to EIF for Enclave. Will not build if EIF is provided.
-    #[arg(long = "eif-path")]
+    #[arg(long = "eif-path", env = "EV_EIF_PATH", conflicts_with = "all")]
     pub eif_path: Option<String>,
 
     /// Path to use for docker context
-    #[arg(default_value = ".")]
+    #[arg(default_value = ".", env = "EV_CONTEXT_PATH")]
     pub context_path: String,
 
     /// Certificate used to sign the Enclave image file
-    #[arg(long = "signing-cert")]
+    #[arg(long = "signing-cert", env = "EV_SIGNING_CERT")]
     pub certificate: Option<String>,
 
     /// Private key used to sign the Enclave image file
-    #[arg(long = "private-key")]
+    #[arg(long = "private-key", env = "EV_PRIVATE_KEY")]
     pub private_key: Option<String>,
 
     /// Build time arguments to provide to docker
@@ -51,20 +88,145 @@ pub struct DeployArgs {
     pub docker_build_args: Vec<String>,
 
     /// Path to an Enclave dockerfile to build from existing
-    #[arg(long = "from-existing")]
+    #[arg(long = "from-existing", conflicts_with = "all")]
     pub from_existing: Option<String>,
 
     /// Deterministic builds
-    #[arg(long = "reproducible")]
+    #[arg(long = "reproducible", env = "EV_REPRODUCIBLE", value_parser = BoolishValueParser::new())]
     pub reproducible: bool,
 
     /// Healthcheck path exposed by your service
-    #[arg(long = "healthcheck")]
+    #[arg(long = "healthcheck", env = "EV_HEALTHCHECK")]
     pub healthcheck: Option<String>,
 
     /// Disables the use of cache during the image builds
-    #[arg(long = "no-cache")]
+    #[arg(long = "no-cache", env = "EV_NO_CACHE", value_parser = BoolishValueParser::new())]
     pub no_cache: bool,
+
+    /// Docker context to run the build on, such as a remote x86_64 host. Use this for reproducible builds from machines which aren't x86_64, such as Apple Silicon Macs. Defaults to the remote_builder in the CLI config.
+    #[arg(long = "remote-builder", env = "EV_REMOTE_BUILDER")]
+    pub remote_builder: Option<String>,
+
+    /// Fail the build if the docker build context exceeds this size, in megabytes
+    #[arg(long = "max-context-size", env = "EV_MAX_CONTEXT_SIZE")]
+    pub max_context_size: Option<u64>,
+
+    /// Version of the Nitro CLI used to convert the image to an EIF. Will override any nitro_cli_version specified in the .toml file.
+    #[arg(long = "nitro-cli-version", env = "EV_NITRO_CLI_VERSION")]
+    pub nitro_cli_version: Option<String>,
+
+    /// Platform to build the Enclave's image for, such as linux/amd64/v3. Enclaves run linux/amd64 images. Will override any platform specified in the .toml file.
+    #[arg(long = "platform", env = "EV_PLATFORM")]
+    pub platform: Option<String>,
+
+    /// Whether to fail or warn when the PCRs of the Enclave built on Evervault don't match the local build
+    #[arg(long = "on-pcr-mismatch", value_enum, default_value_t = RemotePcrMismatch::Fail, env = "EV_ON_PCR_MISMATCH")]
+    pub on_pcr_mismatch: RemotePcrMismatch,
+
+    /// JSON file of PCRs the built Enclave must match before it's uploaded, such as the output of ev enclave attest export
+    #[arg(
+        long = "expected-pcrs",
+        env = "EV_EXPECTED_PCRS",
+        conflicts_with = "all"
+    )]
+    pub expected_pcrs: Option<String>,
+
+    /// PCR0 the built Enclave must match. Overrides the PCR0 in --expected-pcrs.
+    #[arg(long = "expected-pcr0", conflicts_with = "all")]
+    pub expected_pcr0: Option<Pcr>,
+
+    /// PCR1 the built Enclave must match. Overrides the PCR1 in --expected-pcrs.
+    #[arg(long = "expected-pcr1", conflicts_with = "all")]
+    pub expected_pcr1: Option<Pcr>,
+
+    /// PCR2 the built Enclave must match. Overrides the PCR2 in --expected-pcrs.
+    #[arg(long = "expected-pcr2", conflicts_with = "all")]
+    pub expected_pcr2: Option<Pcr>,
+
+    /// PCR8 the built Enclave must match. Overrides the PCR8 in --expected-pcrs.
+    #[arg(long = "expected-pcr8", conflicts_with = "all")]
+    pub expected_pcr8: Option<Pcr>,
+
+    /// Path to a policy file to evaluate before deploying. Defaults to policy.toml alongside the Enclave config, if present.
+    #[arg(long = "policy", env = "EV_POLICY")]
+    pub policy: Option<String>,
+
+    /// Append a record of the deployment to this JSON lines file, signed with the Enclave's signing key
+    #[arg(long = "audit-log", env = "EV_AUDIT_LOG")]
+    pub audit_log: Option<String>,
+
+    /// Replace the running replicas without dropping connections. Rolling needs at least 2 replicas, blue-green needs capacity for a second full set.
+    #[arg(long = "strategy", value_enum, env = "EV_DEPLOY_STRATEGY")]
+    pub strategy: Option<DeployStrategy>,
+
+    /// Warn before building when the Dockerfile's entrypoint reads environment variables which aren't set on the Enclave. Only variable names are compared.
+    #[arg(long = "diff-env", env = "EV_DIFF_ENV", value_parser = BoolishValueParser::new())]
+    pub diff_env: bool,
+
+    /// Size preset reserving CPUs and memory for each instance, see ev enclave sizes list. Will override any size specified in the .toml file.
+    #[arg(long = "size", value_enum, env = "EV_SIZE")]
+    pub size: Option<EnclaveSize>,
+
+    /// Path to send requests to once the deployment completes, such as your healthcheck. The deploy fails unless every request returns 200.
+    #[arg(long = "verify", conflicts_with = "all")]
+    pub verify: Option<String>,
+
+    /// Attest the Enclave against the measurements of this build before sending the verification requests
+    #[cfg(not(target_os = "windows"))]
+    #[arg(long = "verify-attest", requires = "verify")]
+    pub verify_attest: bool,
+
+    /// Redeploy the Enclave's previous successful deployment if verification fails
+    #[arg(long = "auto-rollback", requires = "verify")]
+    pub auto_rollback: bool,
+
+    #[command(flatten)]
+    pub upload_args: UploadArgs,
+
+    #[command(flatten)]
+    pub workspace_args: WorkspaceArgs,
+
+    /// Number of workspace members to deploy at once. Images are still built one at a time.
+    #[arg(long = "parallel", default_value_t = 1, requires = "all", value_parser = clap::value_parser!(u16).range(1..))]
+    pub parallel: u16,
+}
+
+/// Options for uploading the Enclave to Evervault
+#[derive(Clone, Debug, Default, Args)]
+pub struct UploadArgs {
+    /// Most bytes per second to upload the Enclave at, such as 500K or 10M, so deploys don't saturate constrained uplinks
+    #[arg(long = "upload-rate-limit", env = "EV_UPLOAD_RATE_LIMIT", value_parser = parse_rate_limit)]
+    pub upload_rate_limit: Option<u64>,
+
+    /// Number of parts to upload the Enclave in at once, for faster uploads on fast connections
+    #[arg(long = "upload-concurrency", env = "EV_UPLOAD_CONCURRENCY", default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..=MAX_UPLOAD_CONCURRENCY as i64))]
+    pub upload_concurrency: u32,
+}
+
+impl UploadArgs {
+    pub fn options(&self) -> UploadOptions {
+        UploadOptions {
+            rate_limit: self.upload_rate_limit,
+            concurrency: self.upload_concurrency,
+        }
+    }
+}
+
+impl DeployArgs {
+    /// The PCRs the build has been approved to ship, if any were given
+    fn expected_pcrs(&self) -> Result<Option<ExpectedPcrs>, EnclaveDeployError> {
+        let from_file = match self.expected_pcrs.as_deref() {
+            Some(path) => ExpectedPcrs::from_file(std::path::Path::new(path))?,
+            None => ExpectedPcrs::default(),
+        };
+        let expected = from_file.merge(ExpectedPcrs {
+            pcr0: self.expected_pcr0.clone(),
+            pcr1: self.expected_pcr1.clone(),
+            pcr2: self.expected_pcr2.clone(),
+            pcr8: self.expected_pcr8.clone(),
+        });
+        Ok((!expected.is_empty()).then_some(expected))
+    }
 }
 
 impl BuildTimeConfig for DeployArgs {
@@ -79,34 +241,466 @@ impl BuildTimeConfig for DeployArgs {
     fn private_key(&self) -> Option<&str> {
         self.private_key.as_deref()
     }
+
+    fn nitro_cli_version(&self) -> Option<&str> {
+        self.nitro_cli_version.as_deref()
+    }
+
+    fn platform(&self) -> Option<&str> {
+        self.platform.as_deref()
+    }
+
+    fn size(&self) -> Option<EnclaveSize> {
+        self.size
+    }
 }
 
-pub async fn run(deploy_args: DeployArgs, (_, api_key): BasicAuth) -> exitcode::ExitCode {
+#[derive(Debug, Error, CliMessage)]
+pub enum DeployError {
+    #[error("{0}")]
+    #[cli(code = "enclaves/remote-builder-error")]
+    RemoteBuilder(
+        #[from]
+        #[cli(exitcode)]
+        RemoteBuilderError,
+    ),
+    #[error("{0}")]
+    #[cli(code = "enclaves/workspace-error")]
+    Workspace(
+        #[from]
+        #[cli(exitcode)]
+        WorkspaceError,
+    ),
+    #[error("Failed to get data plane and installer versions – {0}")]
+    #[cli(code = "enclaves/version-error")]
+    Versions(
+        #[from]
+        #[cli(exitcode)]
+        VersionError,
+    ),
+    #[error("Failed to validate Enclave config - {0}")]
+    #[cli(code = "enclaves/config-error")]
+    Config(
+        #[from]
+        #[cli(exitcode)]
+        EnclaveConfigError,
+    ),
+    #[error("Failed to retrieve Enclave details from Evervault API – {0}")]
+    #[cli(code = "enclaves/api-error")]
+    EnclaveDetails(#[cli(exitcode)] ApiError),
+    #[error("Failed to load Enclave scaling config - {0}")]
+    #[cli(code = "enclaves/api-error")]
+    ScalingConfig(#[cli(exitcode)] ApiError),
+    #[error("{0}")]
+    #[cli(code = "enclaves/build-error")]
+    RuntimeDigests(#[cli(exitcode)] BuildError),
+    #[error("Failed to build EIF - {0}")]
+    #[cli(code = "enclaves/build-error")]
+    Build(#[cli(exitcode)] BuildError),
+    #[error("{0}")]
+    #[cli(code = "enclaves/policy-error")]
+    Policy(
+        #[from]
+        #[cli(exitcode)]
+        PolicyError,
+    ),
+    #[error("{0}")]
+    #[cli(code = "enclaves/deploy-error")]
+    Deploy(
+        #[from]
+        #[cli(exitcode)]
+        EnclaveDeployError,
+    ),
+    #[error("{0}")]
+    #[cli(code = "enclaves/prompt-error")]
+    Prompt(
+        #[from]
+        #[cli(exitcode)]
+        PromptError,
+    ),
+    #[error("Deployment cancelled. The built Enclave has been kept in {0}")]
+    #[cli(code = "generic/cancelled", exitcode = exitcode::OK)]
+    PcrChangesDeclined(String),
+    #[error("{0}")]
+    #[cli(code = "enclaves/audit-error")]
+    Audit(
+        #[from]
+        #[cli(exitcode)]
+        AuditError,
+    ),
+    #[error("{0}")]
+    #[cli(code = "enclaves/smoke-error")]
+    Smoke(
+        #[from]
+        #[cli(exitcode)]
+        SmokeError,
+    ),
+    #[error("--verify sends requests to the Enclave, which uses API key auth that the ev login session can't be used for. Set EV_API_KEY or pass --api-key to verify the deployment.")]
+    #[cli(code = "enclaves/api-key-required", exitcode = exitcode::NOUSER)]
+    VerifyApiKeyRequired,
+    #[error("{message}")]
+    #[cli(code = "enclaves/verification-failed", exitcode = exitcode::UNAVAILABLE)]
+    VerificationFailed {
+        message: String,
+        #[cli(data)]
+        data: Option<serde_json::Value>,
+    },
+    #[error("A workspace deployment failed unexpectedly — {0}")]
+    #[cli(code = "enclaves/workspace-error", exitcode = exitcode::SOFTWARE)]
+    WorkspaceTask(tokio::task::JoinError),
+    #[error("{0}")]
+    #[cli(code = "enclaves/workspace-failed")]
+    WorkspaceFailed(#[cli(exitcode, data)] WorkspaceSummary),
+}
+
+#[derive(Debug, strum_macros::Display, CliMessage)]
+#[cli(code = "generic/success", exitcode = exitcode::OK)]
+pub enum DeployMessage {
+    #[strum(to_string = "Your Enclave is now available at https://{domain}")]
+    Deployed {
+        domain: String,
+        #[cli(data)]
+        data: Option<serde_json::Value>,
+    },
+    #[strum(to_string = "{0}")]
+    WorkspaceDeployed(#[cli(data)] WorkspaceSummary),
+}
+
+pub async fn run(
+    deploy_args: DeployArgs,
+    (_, api_key): BasicAuth,
+) -> Result<DeployMessage, DeployError> {
     let base_args = BaseArgs::parse();
-    let (mut enclave_config, validated_config) =
-        match read_and_validate_config(&deploy_args.config, &deploy_args) {
-            Ok(configs) => configs,
-            Err(e) => {
-                log::error!("Failed to validate Enclave config - {e}");
-                return e.exitcode();
-            }
+    super::build::select_remote_builder(deploy_args.remote_builder.as_deref())?;
+    if base_args.json {
+        ev_enclave::progress::enable_json_events();
+    }
+
+    let workspace = match deploy_args.workspace_args.all {
+        true => Some(deploy_args.workspace_args.load_workspace()?),
+        false => None,
+    };
+
+    let versions = get_runtime_versions(None).await?;
+
+    if let Some(workspace) = workspace {
+        let summary =
+            deploy_workspace(&workspace, deploy_args, api_key, versions, &base_args).await?;
+        return match summary.succeeded() {
+            true => Ok(DeployMessage::WorkspaceDeployed(summary)),
+            false => Err(DeployError::WorkspaceFailed(summary)),
         };
+    }
 
-    let enclave_api = ev_enclave::api::enclave::EnclaveClient::new(AuthMode::ApiKey(api_key));
+    let (deployed, verification) = deploy_and_verify(
+        &deploy_args,
+        &api_key,
+        versions,
+        &base_args,
+        &DeployPreparation::default(),
+    )
+    .await?;
 
-    let enclave = match enclave_api
-        .get_enclave(validated_config.enclave_uuid())
-        .await
+    if outputs_json(base_args.json) {
+        let data = serde_json::json!({
+            "status": "success",
+            "enclaveDomain": deployed.domain,
+            "deploymentUuid": deployed.deployment_uuid,
+            "measurements": &deployed.measurements,
+            "verification": verification,
+            "timings": ev_enclave::instrumentation::timings()
+        });
+        return Ok(DeployMessage::Deployed {
+            domain: deployed.domain,
+            data: Some(data),
+        });
+    }
+    log::info!("Run ev enclave snippets --lang node|python|go for client code which attests the deployed Enclave.");
+    Ok(DeployMessage::Deployed {
+        domain: deployed.domain,
+        data: None,
+    })
+}
+
+/// Deploys a single Enclave, verifies the deployment when --verify is set, and saves its
+/// measurements to the enclave.toml once the deployed build is settled
+pub(super) async fn deploy_and_verify(
+    deploy_args: &DeployArgs,
+    api_key: &str,
+    versions: RuntimeVersions,
+    base_args: &BaseArgs,
+    preparation: &DeployPreparation<'_>,
+) -> Result<(DeployedEnclave, Option<Verification>), DeployError> {
+    let deployed = deploy_enclave(
+        deploy_args,
+        api_key,
+        versions,
+        base_args.verbose,
+        &Mutex::new(()),
+        preparation,
+    )
+    .await?;
+
+    // The next status command starts from the deployed state rather than waiting on the API
+    let enclave_api = EnclaveClient::new(crate::auth::api_auth_mode(api_key.to_string()));
+    if let Err(e) =
+        refresh_cached_enclave(&enclave_api, &deploy_args.config, &deployed.enclave_uuid).await
     {
-        Ok(enclave) => enclave,
-        Err(e) => {
-            log::error!(
-                "Failed to retrieve Enclave details from Evervault API – {}",
-                e
-            );
-            return e.exitcode();
+        log::debug!("{e}");
+    }
+
+    let verification = match deploy_args.verify.as_deref() {
+        Some(path) => Some(verify_deployment(deploy_args, path, &deployed, api_key).await?),
+        None => None,
+    };
+
+    if let Some(verification) = verification.as_ref().filter(|v| !v.passed) {
+        let (rollback, rollback_error) = match deploy_args.auto_rollback {
+            true => match rollback_deployment(
+                enclave_api,
+                &deployed.enclave_uuid,
+                &deployed.deployment_uuid,
+            )
+            .await
+            {
+                Ok(rollback) => (Some(rollback), None),
+                Err(e) => (None, Some(e.to_string())),
+            },
+            false => (None, None),
+        };
+        // A rolled back deployment runs the previous build, whose PCRs are still in the enclave.toml
+        if rollback.is_none() {
+            save_deployed_config(deploy_args, &deployed);
+        }
+        let message = verification_failure_message(
+            verification,
+            rollback.as_ref(),
+            rollback_error.as_deref(),
+        );
+        let data = outputs_json(base_args.json).then(|| {
+            serde_json::json!({
+                "status": "failed",
+                "enclaveDomain": deployed.domain,
+                "deploymentUuid": deployed.deployment_uuid,
+                "measurements": &deployed.measurements,
+                "verification": verification,
+                "rollback": rollback,
+                "rollbackError": rollback_error,
+            })
+        });
+        return Err(DeployError::VerificationFailed { message, data });
+    }
+    save_deployed_config(deploy_args, &deployed);
+    Ok((deployed, verification))
+}
+
+// Up to --parallel members are deployed at once. Their images are built one at a time, as builds
+// share the same intermediate image names, while uploads and rollouts overlap.
+async fn deploy_workspace(
+    workspace: &Workspace,
+    deploy_args: DeployArgs,
+    api_key: String,
+    versions: RuntimeVersions,
+    base_args: &BaseArgs,
+) -> Result<WorkspaceSummary, DeployError> {
+    let verbose = base_args.verbose;
+    let build_lock = Arc::new(Mutex::new(()));
+    let slots = Arc::new(Semaphore::new(deploy_args.parallel.into()));
+    let tasks: Vec<_> = workspace
+        .members()
+        .iter()
+        .cloned()
+        .map(|member| {
+            let paths = MemberPaths::from(&member);
+            let member_args = DeployArgs {
+                config: paths.config,
+                dockerfile: Some(paths.dockerfile),
+                context_path: paths.context_path,
+                certificate: paths.certificate,
+                private_key: paths.private_key,
+                ..deploy_args.clone()
+            };
+            let (api_key, versions) = (api_key.clone(), versions.clone());
+            let (build_lock, slots) = (build_lock.clone(), slots.clone());
+            tokio::spawn(async move {
+                let _slot = slots
+                    .acquire()
+                    .await
+                    .expect("Infallible - semaphore is never closed");
+                report_member(&member, async {
+                    let deployed = deploy_enclave(
+                        &member_args,
+                        &api_key,
+                        versions,
+                        verbose,
+                        &build_lock,
+                        &DeployPreparation::default(),
+                    )
+                    .await?;
+                    save_deployed_config(&member_args, &deployed);
+                    Ok::<_, DeployError>(Some(format!("https://{}", deployed.domain)))
+                })
+                .await
+            })
+        })
+        .collect();
+
+    let mut reports = vec![];
+    for task in tasks {
+        reports.push(task.await.map_err(DeployError::WorkspaceTask)?);
+    }
+    Ok(WorkspaceSummary::new(reports, base_args.json))
+}
+
+pub(super) struct DeployedEnclave {
+    enclave_uuid: String,
+    pub deployment_uuid: String,
+    pub domain: String,
+    pub measurements: EIFMeasurements,
+    config: EnclaveConfig,
+}
+
+/// Steps `ship` adds to preparing the Enclave before it's deployed
+#[derive(Debug, Default)]
+pub(super) struct DeployPreparation<'a> {
+    /// Directory to keep the build's artifacts in, instead of a temporary directory
+    pub output_dir: Option<&'a str>,
+    /// Confirm changes to the PCRs in the enclave.toml before deploying the new build
+    pub confirm_pcr_changes: bool,
+}
+
+const VERIFY_REQUESTS: u32 = 10;
+const VERIFY_TIMEOUT_SECONDS: u64 = 10;
+
+/// The checks run against a deployment once it completes
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct Verification {
+    passed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    attestation_error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    report: Option<SmokeReport>,
+}
+
+async fn verify_deployment(
+    deploy_args: &DeployArgs,
+    path: &str,
+    deployed: &DeployedEnclave,
+    api_key: &str,
+) -> Result<Verification, DeployError> {
+    #[cfg(not(target_os = "windows"))]
+    let attested = deploy_args.verify_attest;
+    #[cfg(target_os = "windows")]
+    let attested = false;
+    // the deployed config holds this build's measurements, so the new deployment is attested against them
+    #[cfg(not(target_os = "windows"))]
+    if attested {
+        if let Err(e) = super::smoke::attest_enclave(None, &deployed.config, &deployed.domain).await
+        {
+            log::error!("{e}");
+            return Ok(Verification {
+                passed: false,
+                attestation_error: Some(e.to_string()),
+                report: None,
+            });
         }
+    }
+
+    let smoke_test = SmokeTest {
+        base_url: format!("https://{}", deployed.domain),
+        path: path.to_string(),
+        expected_status: 200,
+        requests: VERIFY_REQUESTS,
+        timeout: Duration::from_secs(VERIFY_TIMEOUT_SECONDS),
+        api_key: deployed
+            .config
+            .api_key_auth
+            .then(|| crate::auth::enclave_api_key(api_key.to_string()))
+            .flatten(),
     };
+    log::info!(
+        "Verifying the deployment with {} requests to {}...",
+        smoke_test.requests,
+        smoke_test.url()
+    );
+    let outcomes = run_smoke_test(&smoke_test).await?;
+    let report = SmokeReport::new(
+        smoke_test.url(),
+        smoke_test.expected_status,
+        &outcomes,
+        attested,
+    );
+    for failure in report.failures.iter() {
+        log::error!("Request {} failed - {}", failure.request, failure.reason);
+    }
+    Ok(Verification {
+        passed: report.passed,
+        attestation_error: None,
+        report: Some(report),
+    })
+}
+
+fn verification_failure_message(
+    verification: &Verification,
+    rollback: Option<&RollbackReport>,
+    rollback_error: Option<&str>,
+) -> String {
+    let reason = match (
+        verification.attestation_error.as_deref(),
+        verification.report.as_ref(),
+    ) {
+        (Some(attestation_error), _) => attestation_error.to_string(),
+        (None, Some(report)) => format!(
+            "{} of {} requests to {} failed",
+            report.failures.len(),
+            report.requests,
+            report.url
+        ),
+        (None, None) => "no checks ran".to_string(),
+    };
+    let outcome = match (rollback, rollback_error) {
+        (Some(rollback), _) => format!(
+            "Rolled back to version {} in deployment {}.",
+            rollback.to_version, rollback.deployment_uuid
+        ),
+        (None, Some(rollback_error)) => format!("The rollback failed — {rollback_error}"),
+        (None, None) => "The deployment was left in place. Use --auto-rollback to restore the previous deployment when verification fails.".to_string(),
+    };
+    format!("Deployment verification failed: {reason}. {outcome}")
+}
+
+async fn deploy_enclave(
+    deploy_args: &DeployArgs,
+    api_key: &str,
+    versions: RuntimeVersions,
+    verbose: bool,
+    build_lock: &Mutex<()>,
+    preparation: &DeployPreparation<'_>,
+) -> Result<DeployedEnclave, DeployError> {
+    let (mut enclave_config, validated_config) =
+        read_and_validate_config(&deploy_args.config, deploy_args)?;
+    // Checked before deploying, as a verification which can't authenticate would fail the deployment
+    if deploy_args.verify.is_some()
+        && enclave_config.api_key_auth
+        && crate::auth::enclave_api_key(api_key.to_string()).is_none()
+    {
+        return Err(DeployError::VerifyApiKeyRequired);
+    }
+    let (data_plane_version, installer_version) =
+        versions.resolve(enclave_config.runtime_channel());
+
+    let expected_pcrs = deploy_args.expected_pcrs()?;
+
+    // Without a response cache, so the deployment starts from the Enclave's current state
+    let enclave_api = evervault_api_client::enclave::EnclaveClient::new(
+        crate::auth::api_auth_mode(api_key.to_string()),
+    );
+
+    let enclave = enclave_api
+        .get_enclave(validated_config.enclave_uuid())
+        .await
+        .map_err(DeployError::EnclaveDetails)?;
 
     let enclave_scaling_config = match enclave_api
         .get_scaling_config(validated_config.enclave_uuid())
@@ -114,10 +708,7 @@ pub async fn run(deploy_args: DeployArgs, (_, api_key): BasicAuth) -> exitcode::
     {
         Ok(scaling_config) => Some(scaling_config),
         Err(e) if matches!(e.kind, ApiErrorKind::NotFound) => None,
-        Err(e) => {
-            log::error!("Failed to load Enclave scaling config - {e}");
-            return e.exitcode();
-        }
+        Err(e) => return Err(DeployError::ScalingConfig(e)),
     };
 
     let local_replicas = validated_config
@@ -143,6 +734,24 @@ pub async fn run(deploy_args: DeployArgs, (_, api_key): BasicAuth) -> exitcode::
         log::warn!("Remote scaling config differs from local config. This deployment will apply the local config.\n\nCurrent remote replica count: {remote_replicas}\nLocal replica count: {local_replicas_count}\n");
     }
 
+    if let Some(strategy) = deploy_args.strategy {
+        // Enclaves without a scaling config run a single replica
+        let desired_replicas = local_replicas
+            .or_else(|| {
+                enclave_scaling_config
+                    .as_ref()
+                    .map(|config| config.desired_replicas())
+            })
+            .unwrap_or(1);
+        validate_strategy(strategy, desired_replicas, enclave_scaling_config.as_ref())?;
+    }
+
+    if deploy_args.diff_env {
+        warn_on_missing_env(&enclave_api, &validated_config).await;
+    }
+
+    let resources = resolve_size(&enclave_api, &validated_config).await?;
+
     let timestamp = get_source_date_epoch();
 
     let formatted_args = prepare_build_args(&deploy_args.docker_build_args);
@@ -150,21 +759,14 @@ pub async fn run(deploy_args: DeployArgs, (_, api_key): BasicAuth) -> exitcode::
         .as_ref()
         .map(|args| args.iter().map(AsRef::as_ref).collect());
 
-    let (data_plane_version, installer_version) = match get_data_plane_and_installer_version().await
-    {
-        Ok(versions) => versions,
-        Err(e) => {
-            log::error!("Failed to get data plane and installer versions – {e}");
-            return e;
-        }
-    };
-
-    let from_existing = deploy_args.from_existing;
-    let (eif_measurements, output_path) = match resolve_eif(
+    let from_existing = deploy_args.from_existing.clone();
+    let build_guard = build_lock.lock().await;
+    let resolved_eif = resolve_eif(
         &validated_config,
         &deploy_args.context_path,
         deploy_args.eif_path.as_deref(),
-        base_args.verbose,
+        preparation.output_dir,
+        verbose,
         build_args,
         from_existing,
         timestamp,
@@ -172,12 +774,36 @@ pub async fn run(deploy_args: DeployArgs, (_, api_key): BasicAuth) -> exitcode::
         installer_version.clone(),
         deploy_args.reproducible,
         deploy_args.no_cache,
+        deploy_args.max_context_size,
+        enclave_config.runtime.as_ref(),
     )
-    .await
-    {
-        Ok(eif_info) => eif_info,
-        Err(e) => return e,
-    };
+    .await;
+    drop(build_guard);
+    let (eif_measurements, output_path, runtime_digests) = resolved_eif?;
+
+    if let Some(expected_pcrs) = expected_pcrs {
+        expected_pcrs.verify(eif_measurements.pcrs())?;
+        log::info!("The built Enclave matches the expected PCRs.");
+    }
+
+    if preparation.confirm_pcr_changes {
+        if let Some(previous_measurements) = enclave_config.attestation.as_ref() {
+            if !confirm_pcr_changes(previous_measurements, &eif_measurements)? {
+                return Err(DeployError::PcrChangesDeclined(
+                    output_path.path().display().to_string(),
+                ));
+            }
+        }
+    }
+
+    let policy_path =
+        policy::resolve_policy_path(deploy_args.policy.as_deref(), &deploy_args.config);
+    let policy_input = policy::PolicyInput::new(
+        &validated_config,
+        enclave_config.attestation.as_ref(),
+        &eif_measurements,
+    );
+    policy::enforce_policy(policy_path.as_deref(), &policy_input)?;
 
     if enclave_config.debug {
         ev_enclave::common::log_debug_mode_attestation_warning();
@@ -190,36 +816,119 @@ pub async fn run(deploy_args: DeployArgs, (_, api_key): BasicAuth) -> exitcode::
     );
 
     enclave_config.set_attestation(&eif_measurements);
-    ev_enclave::common::save_enclave_config(&enclave_config, &deploy_args.config);
-
-    if let Err(e) = deploy_eif(
+    enclave_config.set_runtime_versions(&data_plane_version, &installer_version);
+    if let Some(runtime_digests) = runtime_digests.as_ref() {
+        enclave_config.set_runtime_digests(runtime_digests);
+    }
+    let deploy_result = deploy_eif(
         &validated_config,
         enclave_api,
         output_path,
         &eif_measurements,
         data_plane_version,
         installer_version,
+        runtime_digests.as_ref(),
+        deploy_args.on_pcr_mismatch,
+        deploy_args.strategy,
+        resources.as_ref(),
+        deploy_args.upload_args.options(),
     )
-    .await
-    {
-        log::error!("{e}");
-        return e.exitcode();
-    };
+    .await;
 
-    if atty::is(Stream::Stdout) {
-        log::info!(
-            "Your Enclave is now available at https://{}",
-            enclave.domain()
-        );
-    } else {
-        let success_msg = serde_json::json!({
-            "status": "success",
-            "enclaveDomain": enclave.domain(),
-            "measurements": &eif_measurements
-        });
-        println!("{}", serde_json::to_string(&success_msg).unwrap());
-    };
-    exitcode::OK
+    if let Some(audit_log) = deploy_args.audit_log.as_deref() {
+        let record = AuditRecord::new(
+            AuditAction::Deploy,
+            validated_config.enclave_uuid(),
+            &deploy_result,
+        )
+        .with_deployment_uuid(deploy_result.as_ref().ok().cloned())
+        .with_pcrs(Some(eif_measurements.pcrs()));
+        let signing_info = EnclaveSigningInfo::try_from(validated_config.signing_info()).ok();
+        // the deployment's outcome takes precedence over a failure to record it
+        if let Err(e) = append_audit_record(
+            std::path::Path::new(audit_log),
+            record,
+            signing_info.as_ref(),
+        ) {
+            if deploy_result.is_ok() {
+                return Err(e.into());
+            }
+            log::error!("{e}");
+        }
+    }
+
+    let deployment_uuid = deploy_result?;
+
+    Ok(DeployedEnclave {
+        enclave_uuid: validated_config.enclave_uuid().to_string(),
+        deployment_uuid,
+        domain: enclave.domain().to_string(),
+        measurements: eif_measurements,
+        config: enclave_config,
+    })
+}
+
+// The enclave.toml is only updated with the deployed build's measurements once it's running, so a
+// failed deployment leaves it attesting the build which is still deployed
+fn save_deployed_config(deploy_args: &DeployArgs, deployed: &DeployedEnclave) {
+    ev_enclave::common::save_enclave_config(
+        &deployed.config,
+        deploy_args
+            .config_output
+            .as_deref()
+            .unwrap_or(&deploy_args.config),
+    );
+}
+
+// A missing variable usually leaves the Enclave restarting as soon as it boots, so it's flagged
+// before the build rather than after the deployment fails
+async fn warn_on_missing_env<T: EnclaveApi>(
+    enclave_api: &T,
+    validated_config: &ValidatedEnclaveBuildConfig,
+) {
+    match missing_remote_env_vars(enclave_api, validated_config).await {
+        Ok(missing) if missing.is_empty() => {
+            log::info!("Every environment variable the Dockerfile references is set on the Enclave.")
+        }
+        Ok(missing) => log::warn!(
+            "The Dockerfile references environment variables which aren't set on the Enclave: {}. The Enclave may fail to start without them. Add them with ev enclave env add --key <name> --value <value>.",
+            missing.join(", ")
+        ),
+        Err(e) => log::warn!("Failed to compare the Enclave's environment with the Dockerfile - {e}"),
+    }
+}
+
+/// Logs any differences between the previously deployed PCRs and the newly built PCRs, and
+/// confirms that the user wants to continue. With --yes, the changes are accepted without a prompt.
+fn confirm_pcr_changes(
+    previous: &EIFMeasurements,
+    built: &EIFMeasurements,
+) -> Result<bool, PromptError> {
+    let changes: Vec<String> = previous
+        .pcrs()
+        .differences(built.pcrs())
+        .into_iter()
+        .map(|difference| {
+            format!(
+                "{}:\n  - {}\n  + {}",
+                difference.name,
+                difference.left.map_or("none", |pcr| pcr.as_str()),
+                difference.right.map_or("none", |pcr| pcr.as_str())
+            )
+        })
+        .collect();
+
+    if changes.is_empty() {
+        log::info!("The PCRs of the built Enclave match the PCRs in your enclave.toml.");
+        return Ok(true);
+    }
+
+    log::warn!(
+        "The PCRs of the built Enclave differ from the PCRs in your enclave.toml. Any clients attesting this Enclave will need to be updated.\n\n{}\n",
+        changes.join("\n")
+    );
+
+    prompt::confirm("Deploy the Enclave with the new PCRs?", false)
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -227,6 +936,7 @@ async fn resolve_eif(
     validated_config: &ValidatedEnclaveBuildConfig,
     context_path: &str,
     eif_path: Option<&str>,
+    output_dir: Option<&str>,
     verbose: bool,
     build_args: Option<Vec<&str>>,
     from_existing: Option<String>,
@@ -235,12 +945,12 @@ async fn resolve_eif(
     installer_version: String,
     reproducible: bool,
     no_cache: bool,
-) -> Result<(EIFMeasurements, OutputPath), exitcode::ExitCode> {
+    max_context_size: Option<u64>,
+    pinned_runtime: Option<&RuntimeSettings>,
+) -> Result<(EIFMeasurements, OutputPath, Option<RuntimeDigests>), DeployError> {
     if let Some(path) = eif_path {
-        let (mut measurements, output_path) = get_eif(path, verbose, no_cache).map_err(|e| {
-            log::error!("{e}");
-            e.exitcode()
-        })?;
+        let (mut measurements, output_path) =
+            get_eif(path, validated_config.nitro_cli_image(), verbose, no_cache)?;
 
         /*
          * We cannot guarantee that the signing key pair of the provided EIF are present when it is being uploaded.
@@ -271,12 +981,20 @@ async fn resolve_eif(
             );
         }
 
-        Ok((measurements, output_path))
+        Ok((measurements, output_path, None))
     } else {
+        let runtime_digests = resolve_runtime_digests(
+            validated_config,
+            &data_plane_version,
+            &installer_version,
+            pinned_runtime,
+        )
+        .await
+        .map_err(DeployError::RuntimeDigests)?;
         let (built_enclave, output_path) = build_enclave_image_file(
             validated_config,
             context_path,
-            None,
+            output_dir,
             verbose,
             build_args,
             data_plane_version,
@@ -285,34 +1003,50 @@ async fn resolve_eif(
             from_existing,
             reproducible,
             no_cache,
+            max_context_size,
         )
         .await
-        .map_err(|build_err| {
-            log::error!("Failed to build EIF - {build_err}");
-            build_err.exitcode()
-        })?;
-        Ok((built_enclave.measurements().to_owned(), output_path))
+        .map_err(DeployError::Build)?;
+        Ok((
+            built_enclave.measurements().to_owned(),
+            output_path,
+            Some(runtime_digests),
+        ))
     }
 }
 
-async fn get_data_plane_and_installer_version() -> Result<(String, String), ExitCode> {
-    let enclave_build_assets_client = EnclaveAssetsClient::new();
-    let data_plane_version = match enclave_build_assets_client.get_data_plane_version().await {
-        Ok(version) => version,
-        Err(e) => {
-            log::error!("Failed to retrieve the latest data plane version - {e:?}");
-            return Err(e.exitcode());
-        }
-    };
-    let installer_version = match enclave_build_assets_client
-        .get_runtime_installer_version()
-        .await
-    {
-        Ok(version) => version,
-        Err(e) => {
-            log::error!("Failed to retrieve the latest installer version - {e:?}");
-            return Err(e.exitcode());
-        }
-    };
-    Ok((data_plane_version, installer_version))
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_verification_failure_message() {
+        let verification = Verification {
+            passed: false,
+            attestation_error: None,
+            report: Some(SmokeReport::new(
+                "https://enclave.app.evervault.com/health".into(),
+                200,
+                &[ev_enclave::smoke::RequestOutcome {
+                    latency: Duration::from_millis(5),
+                    result: Ok(503),
+                }],
+                false,
+            )),
+        };
+        let rollback = RollbackReport {
+            from_deployment_uuid: "deployment_2".into(),
+            to_deployment_uuid: "deployment_1".into(),
+            to_version: 1,
+            deployment_uuid: "deployment_3".into(),
+        };
+        assert_eq!(
+            verification_failure_message(&verification, Some(&rollback), None),
+            "Deployment verification failed: 1 of 1 requests to https://enclave.app.evervault.com/health failed. Rolled back to version 1 in deployment deployment_3."
+        );
+        assert!(
+            verification_failure_message(&verification, None, Some("no previous deployment"))
+                .ends_with("The rollback failed — no previous deployment")
+        );
+    }
 }
@@ -0,0 +1,107 @@
+use clap::{Parser, Subcommand};
+use common::api::BasicAuth;
+use ev_cli_derive::CliMessage;
+use ev_enclave::common::resolve_enclave_uuid;
+use ev_enclave::deployments::{self, DeploymentsError as EnclaveDeploymentsError};
+use ev_enclave::prompt::{self, PromptError};
+use evervault_api_client::enclave::{EnclaveClient, EnclaveDeployment};
+use thiserror::Error;
+
+use crate::completion::enclave_uuid_completer;
+use crate::BaseArgs;
+
+/// Manage an Enclave's deployments
+#[derive(Debug, Parser)]
+#[command(name = "deployments", about)]
+pub struct DeploymentsArgs {
+    #[command(subcommand)]
+    action: DeploymentsCommands,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum DeploymentsCommands {
+    #[command()]
+    Cancel(CancelDeploymentArgs),
+}
+
+/// Cancel a deployment which is queued or in progress, such as one superseded by a newer deploy
+#[derive(Debug, Parser)]
+#[command(name = "cancel", about)]
+pub struct CancelDeploymentArgs {
+    /// Uuid of the deployment to cancel
+    pub deployment_uuid: String,
+
+    /// Path to enclave.toml config file
+    #[arg(
+        short = 'c',
+        long = "config",
+        default_value = "./enclave.toml",
+        env = "EV_ENCLAVE_CONFIG"
+    )]
+    pub config: String,
+
+    /// Uuid of the Enclave the deployment belongs to
+    #[arg(long = "enclave-uuid", env = "EV_ENCLAVE_UUID", add = enclave_uuid_completer())]
+    pub enclave_uuid: Option<String>,
+
+    /// Prevent confirmation dialogue and proceed with cancelling the deployment
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(Debug, Error, CliMessage)]
+pub enum DeploymentsError {
+    #[error("{0}")]
+    #[cli(code = "enclaves/deployments-error")]
+    Deployments(
+        #[from]
+        #[cli(exitcode)]
+        EnclaveDeploymentsError,
+    ),
+    #[error("An error occurred while attempting to confirm this cancellation — {0}")]
+    #[cli(code = "enclaves/prompt-error")]
+    Prompt(
+        #[from]
+        #[cli(exitcode)]
+        PromptError,
+    ),
+}
+
+#[derive(Debug, strum_macros::Display, CliMessage)]
+#[cli(code = "generic/success", exitcode = exitcode::OK)]
+pub enum DeploymentsMessage {
+    #[strum(to_string = "Deployment {uuid} cancelled")]
+    Cancelled {
+        uuid: String,
+        #[cli(data)]
+        deployment: Option<EnclaveDeployment>,
+    },
+    #[strum(to_string = "Phew! Exiting early...")]
+    #[cli(code = "generic/cancelled")]
+    Aborted,
+}
+
+pub async fn run(
+    deployments_args: DeploymentsArgs,
+    (_, api_key): BasicAuth,
+) -> Result<DeploymentsMessage, DeploymentsError> {
+    let enclave_api = EnclaveClient::new(crate::auth::api_auth_mode(api_key));
+    let DeploymentsCommands::Cancel(cancel_args) = deployments_args.action;
+    let enclave_uuid =
+        resolve_enclave_uuid(cancel_args.enclave_uuid.as_deref(), &cancel_args.config)
+            .map_err(EnclaveDeploymentsError::from)?
+            .ok_or(EnclaveDeploymentsError::MissingUuid)?;
+
+    let prompt = format!("Cancel deployment {}?", cancel_args.deployment_uuid);
+    if !cancel_args.force && !prompt::confirm(&prompt, false)? {
+        return Ok(DeploymentsMessage::Aborted);
+    }
+
+    let deployment =
+        deployments::cancel_deployment(&enclave_api, &enclave_uuid, &cancel_args.deployment_uuid)
+            .await?;
+    Ok(DeploymentsMessage::Cancelled {
+        uuid: cancel_args.deployment_uuid,
+        deployment: BaseArgs::parse().json.then_some(deployment),
+    })
+}
@@ -1,6 +1,13 @@
 use clap::Parser;
-use common::CliError;
-use ev_enclave::describe::describe_eif;
+use common::api::BasicAuth;
+use ev_cli_derive::CliMessage;
+use ev_enclave::describe::error::DescribeError as EnclaveDescribeError;
+use ev_enclave::describe::remote::{describe_remote, DeploymentProvenance};
+use ev_enclave::describe::{describe_eif, describe_eifs_in_dir};
+use ev_enclave::enclave::{DescribeEif, NitroCliImage, NitroCliImageError};
+use evervault_api_client::enclave::EnclaveClient;
+use std::collections::BTreeMap;
+use thiserror::Error;
 
 use crate::BaseArgs;
 
@@ -12,26 +19,115 @@ pub struct DescribeArgs {
     #[arg(default_value = "./enclave.eif")]
     pub eif_path: String,
 
+    /// Describe every EIF in this directory concurrently, printing a map of file name to PCRs
+    #[arg(long = "batch", conflicts_with = "eif_path")]
+    pub batch: Option<String>,
+
+    /// Describe the deployment currently serving the Enclave in the config instead of a local EIF, including the git hash and source date epoch it was built from
+    #[arg(long = "remote", conflicts_with_all = ["eif_path", "batch"])]
+    pub remote: bool,
+
+    /// Path to enclave.toml config file, used with --remote
+    #[arg(
+        short = 'c',
+        long = "config",
+        default_value = "./enclave.toml",
+        env = "EV_ENCLAVE_CONFIG"
+    )]
+    pub config: String,
+
     /// Disables the use of cache during the image builds
     #[arg(long = "no-cache")]
     pub no_cache: bool,
+
+    /// A prebuilt image with nitro-cli on its path to describe the EIF with, optionally pinned with @sha256:<digest>
+    #[arg(long = "nitro-cli-image", env = "EV_NITRO_CLI_IMAGE")]
+    pub nitro_cli_image: Option<String>,
+
+    /// Version of the Nitro CLI to describe the EIF with
+    #[arg(long = "nitro-cli-version", env = "EV_NITRO_CLI_VERSION")]
+    pub nitro_cli_version: Option<String>,
+}
+
+#[derive(Debug, Error, CliMessage)]
+pub enum DescribeError {
+    #[error("{0}")]
+    #[cli(code = "enclaves/nitro-cli-image-error")]
+    NitroCli(
+        #[from]
+        #[cli(exitcode)]
+        NitroCliImageError,
+    ),
+    #[error("{0}")]
+    #[cli(code = "enclaves/describe-error")]
+    Describe(
+        #[from]
+        #[cli(exitcode)]
+        EnclaveDescribeError,
+    ),
+}
+
+#[derive(Debug, strum_macros::Display, CliMessage)]
+#[cli(code = "generic/success", exitcode = exitcode::OK)]
+pub enum DescribeMessage {
+    #[strum(to_string = "Described the deployment serving the Enclave")]
+    Remote {
+        #[cli(data)]
+        provenance: DeploymentProvenance,
+    },
+    #[strum(to_string = "Described the EIFs in {batch_dir}")]
+    Batch {
+        batch_dir: String,
+        #[cli(data)]
+        descriptions: BTreeMap<String, DescribeEif>,
+    },
+    #[strum(to_string = "Described {eif_path}")]
+    Eif {
+        eif_path: String,
+        #[cli(data)]
+        description: DescribeEif,
+    },
 }
 
-pub async fn run(describe_args: DescribeArgs) -> exitcode::ExitCode {
+pub async fn run(
+    describe_args: DescribeArgs,
+    (_, api_key): BasicAuth,
+) -> Result<DescribeMessage, DescribeError> {
     let base_args = BaseArgs::parse();
 
-    let description = match describe_eif(
+    if describe_args.remote {
+        let enclave_api = EnclaveClient::new(crate::auth::api_auth_mode(api_key));
+        let provenance = describe_remote(&describe_args.config, &enclave_api).await?;
+        return Ok(DescribeMessage::Remote { provenance });
+    }
+
+    let nitro_cli = NitroCliImage::new(
+        describe_args.nitro_cli_image.as_deref(),
+        describe_args.nitro_cli_version.as_deref(),
+    )?;
+
+    if let Some(batch_dir) = describe_args.batch {
+        let descriptions = describe_eifs_in_dir(
+            &batch_dir,
+            &nitro_cli,
+            base_args.verbose,
+            describe_args.no_cache,
+        )
+        .await?;
+        return Ok(DescribeMessage::Batch {
+            batch_dir,
+            descriptions,
+        });
+    }
+
+    let description = describe_eif(
         &describe_args.eif_path,
+        &nitro_cli,
         base_args.verbose,
         describe_args.no_cache,
-    ) {
-        Ok(measurements) => measurements,
-        Err(e) => {
-            log::error!("{e}");
-            return e.exitcode();
-        }
-    };
-
-    println!("{}", serde_json::to_string_pretty(&description).unwrap());
-    exitcode::OK
+    )?;
+    Ok(DescribeMessage::Eif {
+        eif_path: describe_args.eif_path,
+        description,
+    })
 }
@@ -0,0 +1,84 @@
+use clap::{Parser, Subcommand};
+use common::table::TableError;
+use ev_cli_derive::CliMessage;
+use ev_enclave::diagnose::{CheckStatus, HostProbe, HostReport};
+use thiserror::Error;
+
+use crate::table::TableArgs;
+use crate::BaseArgs;
+
+/// Check the environment Enclaves are built for and run in
+#[derive(Debug, Parser)]
+#[command(name = "diagnose", about)]
+pub struct DiagnoseArgs {
+    #[command(subcommand)]
+    pub action: DiagnoseCommands,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum DiagnoseCommands {
+    /// Check this EC2 host is ready to run Enclaves: the allocator service, reserved hugepages and CPUs, vsock and nitro-cli
+    #[command()]
+    Host(DiagnoseHostArgs),
+}
+
+#[derive(Debug, Parser)]
+#[command(name = "host", about)]
+pub struct DiagnoseHostArgs {
+    #[command(flatten)]
+    pub table_args: TableArgs,
+}
+
+#[derive(Debug, Error, CliMessage)]
+pub enum DiagnoseError {
+    #[error("{0}")]
+    #[cli(code = "generic/table-error")]
+    Table(
+        #[from]
+        #[cli(exitcode)]
+        TableError,
+    ),
+    #[error("{table}{failures} checks failed, so this host can't run Enclaves yet.")]
+    #[cli(code = "enclaves/host-not-ready", exitcode = exitcode::UNAVAILABLE)]
+    NotReady {
+        failures: usize,
+        table: String,
+        #[cli(data)]
+        report: Option<HostReport>,
+    },
+}
+
+#[derive(Debug, strum_macros::Display, CliMessage)]
+#[cli(code = "generic/success", exitcode = exitcode::OK)]
+pub enum DiagnoseMessage {
+    #[strum(to_string = "{table}This host is ready to run Enclaves.")]
+    Ready {
+        table: String,
+        #[cli(data)]
+        report: Option<HostReport>,
+    },
+}
+
+pub fn run(diagnose_args: DiagnoseArgs) -> Result<DiagnoseMessage, DiagnoseError> {
+    let DiagnoseCommands::Host(host_args) = diagnose_args.action;
+    let report = HostProbe::default().diagnose();
+    let failures = report
+        .checks
+        .iter()
+        .filter(|check| check.status == CheckStatus::Fail)
+        .count();
+
+    let (table, report) = if host_args.table_args.use_table(BaseArgs::parse().json) {
+        (host_args.table_args.render(report.table())?, None)
+    } else {
+        (String::new(), Some(report))
+    };
+    match failures {
+        0 => Ok(DiagnoseMessage::Ready { table, report }),
+        _ => Err(DiagnoseError::NotReady {
+            failures,
+            table,
+            report,
+        }),
+    }
+}
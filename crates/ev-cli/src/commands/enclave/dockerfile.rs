@@ -0,0 +1,312 @@
+use clap::{Parser, Subcommand};
+use ev_cli_derive::CliMessage;
+use ev_enclave::build::{error::BuildError, synthesize_dockerfile};
+use ev_enclave::config::{read_and_validate_config, BuildTimeConfig, EnclaveConfigError};
+use ev_enclave::docker::error::DockerError;
+use ev_enclave::docker::history::DockerfileHistory;
+use ev_enclave::docker::{diff::word_diff, format::format_dockerfile, parse::DockerfileDecoder};
+use ev_enclave::version::{get_runtime_versions, VersionError};
+use std::path::Path;
+use thiserror::Error;
+
+use crate::config::dockerfile_cache_directory;
+
+/// Manage the Dockerfile used to build an Enclave
+#[derive(Debug, Parser)]
+#[command(name = "dockerfile", about)]
+pub struct DockerfileArgs {
+    #[command(subcommand)]
+    action: DockerfileCommands,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum DockerfileCommands {
+    /// Rewrite a Dockerfile into canonical form, so formatting-only edits don't change the Enclave's PCRs
+    #[command()]
+    Fmt(FmtArgs),
+    /// Print the Dockerfile a build would generate from your Dockerfile, including the directives Evervault injects, without building it
+    #[command()]
+    Print(PrintArgs),
+    /// Show how the Dockerfile this CLI generates differs from one generated by a previous CLI release, to explain PCR changes after upgrading
+    #[command()]
+    Diff(DiffArgs),
+}
+
+#[derive(Debug, Parser)]
+#[command(name = "fmt", about)]
+pub struct FmtArgs {
+    /// Path to the Dockerfile to format
+    #[arg(default_value = "./Dockerfile")]
+    pub dockerfile: String,
+
+    /// Exit with a non-zero code if the Dockerfile is not in canonical form, instead of rewriting it
+    #[arg(long = "check")]
+    pub check: bool,
+}
+
+#[derive(Debug, Parser)]
+#[command(name = "print", about)]
+pub struct PrintArgs {
+    /// Path to enclave.toml config file
+    #[arg(
+        short = 'c',
+        long = "config",
+        default_value = "./enclave.toml",
+        env = "EV_ENCLAVE_CONFIG"
+    )]
+    pub config: String,
+
+    /// Path to Dockerfile for Enclave. Will override any dockerfile specified in the .toml file.
+    #[arg(short = 'f', long = "file", env = "EV_DOCKERFILE")]
+    pub dockerfile: Option<String>,
+
+    /// Path to write the generated Dockerfile to. Defaults to stdout.
+    #[arg(short = 'o', long = "output")]
+    pub output: Option<String>,
+
+    /// Use the data plane and installer releases the next build would resolve, instead of the versions recorded in the [runtime] section of the enclave.toml by the last build
+    #[arg(long = "resolved-versions")]
+    pub resolved_versions: bool,
+
+    /// Generate the Dockerfile used for reproducible builds
+    #[arg(long = "reproducible")]
+    pub reproducible: bool,
+}
+
+impl BuildTimeConfig for PrintArgs {
+    fn dockerfile(&self) -> Option<&str> {
+        self.dockerfile.as_deref()
+    }
+}
+
+#[derive(Debug, Parser)]
+#[command(name = "diff", about)]
+pub struct DiffArgs {
+    /// Path to enclave.toml config file
+    #[arg(
+        short = 'c',
+        long = "config",
+        default_value = "./enclave.toml",
+        env = "EV_ENCLAVE_CONFIG"
+    )]
+    pub config: String,
+
+    /// Path to Dockerfile for Enclave. Will override any dockerfile specified in the .toml file.
+    #[arg(short = 'f', long = "file", env = "EV_DOCKERFILE")]
+    pub dockerfile: Option<String>,
+
+    /// The CLI version whose Dockerfile to compare against, cached when that version last built this Enclave, or the path to a previously generated Dockerfile
+    #[arg(long = "against")]
+    pub against: String,
+
+    /// Use the data plane and installer releases the next build would resolve, instead of the versions recorded in the [runtime] section of the enclave.toml by the last build
+    #[arg(long = "resolved-versions")]
+    pub resolved_versions: bool,
+
+    /// Generate the Dockerfile used for reproducible builds
+    #[arg(long = "reproducible")]
+    pub reproducible: bool,
+}
+
+impl BuildTimeConfig for DiffArgs {
+    fn dockerfile(&self) -> Option<&str> {
+        self.dockerfile.as_deref()
+    }
+}
+
+#[derive(Debug, Error, CliMessage)]
+pub enum DockerfileError {
+    #[error("Failed to read the Dockerfile at {0} - {1}")]
+    #[cli(code = "generic/io-error", exitcode = exitcode::NOINPUT)]
+    Read(String, std::io::Error),
+    #[error("Failed to parse the Dockerfile at {0} - {1}")]
+    #[cli(code = "enclaves/dockerfile-parse-error", exitcode = exitcode::DATAERR)]
+    Parse(String, DockerError),
+    #[error("{0} is not in canonical form. Run `ev enclave dockerfile fmt {0}` to format it.")]
+    #[cli(code = "enclaves/dockerfile-unformatted", exitcode = exitcode::DATAERR)]
+    Unformatted(String),
+    #[error("Failed to write the Dockerfile at {0} - {1}")]
+    #[cli(code = "generic/io-error", exitcode = exitcode::IOERR)]
+    Write(String, std::io::Error),
+    #[error("Failed to read Enclave config from file system — {0}")]
+    #[cli(code = "enclaves/config-error")]
+    Config(
+        #[from]
+        #[cli(exitcode)]
+        EnclaveConfigError,
+    ),
+    #[error("Failed to retrieve the latest data plane and installer versions - {0}")]
+    #[cli(code = "enclaves/version-error")]
+    Versions(
+        #[from]
+        #[cli(exitcode)]
+        VersionError,
+    ),
+    #[error("Couldn't find a home directory to read cached Dockerfiles from")]
+    #[cli(code = "enclaves/dockerfile-not-cached", exitcode = exitcode::CONFIG)]
+    NoHomeDirectory,
+    #[error("No Dockerfile generated by CLI version {0} is cached for this Enclave. {1}")]
+    #[cli(code = "enclaves/dockerfile-not-cached", exitcode = exitcode::NOINPUT)]
+    NotCached(String, String),
+    #[error("Failed to generate the Enclave Dockerfile — {0}")]
+    #[cli(code = "enclaves/build-error")]
+    Generate(
+        #[from]
+        #[cli(exitcode)]
+        BuildError,
+    ),
+}
+
+#[derive(Debug, strum_macros::Display, CliMessage)]
+#[cli(code = "generic/success", exitcode = exitcode::OK)]
+pub enum DockerfileMessage {
+    #[strum(to_string = "{0} is already formatted.")]
+    AlreadyFormatted(String),
+    #[strum(to_string = "Formatted {0}")]
+    Formatted(String),
+    #[strum(to_string = "Generated Dockerfile written to {0}")]
+    Written(String),
+    #[strum(to_string = "{0}")]
+    Generated(String),
+    #[strum(to_string = "The generated Dockerfile is unchanged from {0}")]
+    Unchanged(String),
+    #[strum(to_string = "Changes to the generated Dockerfile since {0}:\n{1}")]
+    Diff(String, String),
+}
+
+pub async fn run(dockerfile_args: DockerfileArgs) -> Result<DockerfileMessage, DockerfileError> {
+    match dockerfile_args.action {
+        DockerfileCommands::Fmt(fmt_args) => format(fmt_args).await,
+        DockerfileCommands::Print(print_args) => print(print_args).await,
+        DockerfileCommands::Diff(diff_args) => diff(diff_args).await,
+    }
+}
+
+async fn format(fmt_args: FmtArgs) -> Result<DockerfileMessage, DockerfileError> {
+    let dockerfile = fmt_args.dockerfile;
+    let contents = tokio::fs::read(&dockerfile)
+        .await
+        .map_err(|e| DockerfileError::Read(dockerfile.clone(), e))?;
+
+    let directives = DockerfileDecoder::decode_dockerfile_from_src(contents.as_slice())
+        .await
+        .map_err(|e| DockerfileError::Parse(dockerfile.clone(), e))?;
+
+    let formatted = format_dockerfile(&directives);
+    if formatted.as_bytes() == contents.as_slice() {
+        return Ok(DockerfileMessage::AlreadyFormatted(dockerfile));
+    }
+
+    if fmt_args.check {
+        return Err(DockerfileError::Unformatted(dockerfile));
+    }
+
+    if let Err(e) = tokio::fs::write(&dockerfile, formatted).await {
+        return Err(DockerfileError::Write(dockerfile, e));
+    }
+    Ok(DockerfileMessage::Formatted(dockerfile))
+}
+
+async fn generate_dockerfile<A: BuildTimeConfig>(
+    config_path: &str,
+    args: &A,
+    resolved_versions: bool,
+    reproducible: bool,
+) -> Result<String, DockerfileError> {
+    let (enclave_config, validated_config) = read_and_validate_config(config_path, args)?;
+
+    // The recorded versions are the ones in the last built EIF, so its Dockerfile can be reviewed
+    let recorded_versions = enclave_config.runtime.as_ref().and_then(|runtime| {
+        runtime
+            .data_plane_version
+            .clone()
+            .zip(runtime.installer_version.clone())
+    });
+    let (data_plane_version, installer_version) = match recorded_versions {
+        Some(versions) if !resolved_versions => versions,
+        _ => get_runtime_versions(None)
+            .await?
+            .resolve(enclave_config.runtime_channel()),
+    };
+    log::debug!("Using data plane {data_plane_version} and installer {installer_version}");
+
+    let directives = synthesize_dockerfile(
+        &validated_config,
+        data_plane_version,
+        installer_version,
+        reproducible,
+    )
+    .await?;
+    Ok(directives
+        .iter()
+        .map(|directive| format!("{directive}\n"))
+        .collect())
+}
+
+async fn print(print_args: PrintArgs) -> Result<DockerfileMessage, DockerfileError> {
+    let generated = generate_dockerfile(
+        &print_args.config,
+        &print_args,
+        print_args.resolved_versions,
+        print_args.reproducible,
+    )
+    .await?;
+
+    match print_args.output {
+        Some(output) => match tokio::fs::write(&output, generated).await {
+            Ok(_) => Ok(DockerfileMessage::Written(output)),
+            Err(e) => Err(DockerfileError::Write(output, e)),
+        },
+        None => Ok(DockerfileMessage::Generated(
+            generated.trim_end().to_string(),
+        )),
+    }
+}
+
+// A path to an existing file is read as a Dockerfile, anything else is looked up as a CLI version
+fn read_previous_dockerfile(
+    against: &str,
+    config_path: &str,
+) -> Result<(String, String), DockerfileError> {
+    if Path::new(against).is_file() {
+        let dockerfile = std::fs::read_to_string(against)
+            .map_err(|e| DockerfileError::Read(against.to_string(), e))?;
+        return Ok((against.to_string(), dockerfile));
+    }
+
+    let cli_version = against.trim_start_matches('v');
+    let directory = dockerfile_cache_directory().ok_or(DockerfileError::NoHomeDirectory)?;
+    let history = DockerfileHistory::new(directory, Path::new(config_path));
+    match history.load(cli_version) {
+        Some(dockerfile) => Ok((format!("CLI version {cli_version}"), dockerfile)),
+        None => {
+            let versions = history.versions();
+            let hint = if versions.is_empty() {
+                "No versions are cached yet. A version's Dockerfile is cached when it builds this Enclave.".to_string()
+            } else {
+                format!("Cached versions: {}", versions.join(", "))
+            };
+            Err(DockerfileError::NotCached(cli_version.to_string(), hint))
+        }
+    }
+}
+
+async fn diff(diff_args: DiffArgs) -> Result<DockerfileMessage, DockerfileError> {
+    let (previous_label, previous) =
+        read_previous_dockerfile(&diff_args.against, &diff_args.config)?;
+    let generated = generate_dockerfile(
+        &diff_args.config,
+        &diff_args,
+        diff_args.resolved_versions,
+        diff_args.reproducible,
+    )
+    .await?;
+
+    if previous.trim_end() == generated.trim_end() {
+        return Ok(DockerfileMessage::Unchanged(previous_label));
+    }
+    Ok(DockerfileMessage::Diff(
+        previous_label,
+        word_diff(&previous, &generated),
+    ))
+}
@@ -0,0 +1,239 @@
+use clap::{Args, Parser, Subcommand};
+use common::api::BasicAuth;
+use common::table::{Table, TableError};
+use ev_cli_derive::CliMessage;
+use ev_enclave::common::resolve_enclave_uuid;
+use ev_enclave::domains::{self, format_dns_guidance, DomainsError as EnclaveDomainsError};
+use ev_enclave::progress::get_tracker;
+use ev_enclave::prompt::{self, PromptError};
+use evervault_api_client::enclave::{CustomDomain, EnclaveClient};
+use thiserror::Error;
+
+use crate::completion::enclave_uuid_completer;
+use crate::table::TableArgs;
+use crate::BaseArgs;
+
+/// Manage the custom domains an Enclave is served on
+#[derive(Debug, Parser)]
+#[command(name = "domains", about)]
+pub struct DomainsArgs {
+    #[command(subcommand)]
+    action: DomainsCommands,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum DomainsCommands {
+    #[command()]
+    Add(AddDomainArgs),
+    #[command()]
+    List(ListDomainsArgs),
+    #[command()]
+    Remove(RemoveDomainArgs),
+}
+
+/// Serve the Enclave on a custom domain, printing the DNS records to create for it
+#[derive(Debug, Parser)]
+#[command(name = "add", about)]
+pub struct AddDomainArgs {
+    /// Domain to serve the Enclave on, e.g. api.example.com
+    pub domain: String,
+
+    /// Wait for the DNS records to be verified and the domain to serve the Enclave
+    #[arg(long)]
+    pub wait: bool,
+
+    #[command(flatten)]
+    pub enclave: EnclaveSelector,
+}
+
+/// List the custom domains of the Enclave, and their verification status
+#[derive(Debug, Parser)]
+#[command(name = "list", about)]
+pub struct ListDomainsArgs {
+    #[command(flatten)]
+    pub enclave: EnclaveSelector,
+
+    #[command(flatten)]
+    pub table_args: TableArgs,
+}
+
+/// Stop serving the Enclave on a custom domain
+#[derive(Debug, Parser)]
+#[command(name = "remove", about)]
+pub struct RemoveDomainArgs {
+    /// Custom domain to remove
+    pub domain: String,
+
+    /// Prevent confirmation dialogue and proceed with removing the domain
+    #[arg(long)]
+    pub force: bool,
+
+    #[command(flatten)]
+    pub enclave: EnclaveSelector,
+}
+
+/// Options selecting the Enclave to manage the domains of
+#[derive(Debug, Args)]
+pub struct EnclaveSelector {
+    /// Path to enclave.toml config file
+    #[arg(
+        short = 'c',
+        long = "config",
+        default_value = "./enclave.toml",
+        env = "EV_ENCLAVE_CONFIG"
+    )]
+    pub config: String,
+
+    /// Uuid of the Enclave to manage the domains of
+    #[arg(long = "enclave-uuid", env = "EV_ENCLAVE_UUID", add = enclave_uuid_completer())]
+    pub enclave_uuid: Option<String>,
+}
+
+impl EnclaveSelector {
+    fn resolve_uuid(&self) -> Result<String, EnclaveDomainsError> {
+        resolve_enclave_uuid(self.enclave_uuid.as_deref(), &self.config)?
+            .ok_or(EnclaveDomainsError::MissingUuid)
+    }
+}
+
+#[derive(Debug, Error, CliMessage)]
+pub enum DomainsError {
+    #[error("{0}")]
+    #[cli(code = "enclaves/domains-error")]
+    Domains(
+        #[from]
+        #[cli(exitcode)]
+        EnclaveDomainsError,
+    ),
+    #[error("An error occurred while attempting to confirm this domain removal — {0}")]
+    #[cli(code = "enclaves/prompt-error")]
+    Prompt(
+        #[from]
+        #[cli(exitcode)]
+        PromptError,
+    ),
+    #[error("{0}")]
+    #[cli(code = "generic/table-error")]
+    Table(
+        #[from]
+        #[cli(exitcode)]
+        TableError,
+    ),
+}
+
+#[derive(Debug, strum_macros::Display, CliMessage)]
+#[cli(code = "generic/success", exitcode = exitcode::OK)]
+pub enum DomainsMessage {
+    #[strum(
+        to_string = "{guidance}\nThe domain will serve the Enclave once its DNS records are verified. Check its status with ev enclave domains list."
+    )]
+    Added {
+        guidance: String,
+        #[cli(data)]
+        domain: Option<CustomDomain>,
+    },
+    #[strum(to_string = "{domain} is serving the Enclave")]
+    Active {
+        domain: String,
+        #[cli(data)]
+        custom_domain: Option<CustomDomain>,
+    },
+    #[strum(to_string = "{0}")]
+    Table(String),
+    #[strum(to_string = "Retrieved the Enclave's custom domains")]
+    Domains(#[cli(data)] Vec<CustomDomain>),
+    #[strum(to_string = "No custom domains found. Add one with ev enclave domains add.")]
+    NoDomains,
+    #[strum(
+        to_string = "{0} removed. The Enclave is no longer served on it, so its DNS records can be deleted."
+    )]
+    Removed(String),
+    #[strum(to_string = "Phew! Exiting early...")]
+    #[cli(code = "generic/cancelled")]
+    Cancelled,
+}
+
+pub async fn run(
+    domains_args: DomainsArgs,
+    (_, api_key): BasicAuth,
+) -> Result<DomainsMessage, DomainsError> {
+    let enclave_api = EnclaveClient::new(crate::auth::api_auth_mode(api_key));
+    let json = BaseArgs::parse().json;
+    match domains_args.action {
+        DomainsCommands::Add(add_args) => {
+            let enclave_uuid = add_args.enclave.resolve_uuid()?;
+            let custom_domain =
+                domains::add_domain(&enclave_api, &enclave_uuid, &add_args.domain).await?;
+            if !add_args.wait {
+                return Ok(DomainsMessage::Added {
+                    guidance: format_dns_guidance(&custom_domain),
+                    domain: json.then_some(custom_domain),
+                });
+            }
+
+            log::info!("{}", format_dns_guidance(&custom_domain));
+            let progress_bar = get_tracker("Verifying DNS records...", None);
+            let custom_domain = domains::watch_domain(
+                enclave_api,
+                &enclave_uuid,
+                &custom_domain.domain,
+                progress_bar,
+            )
+            .await?;
+            Ok(DomainsMessage::Active {
+                domain: custom_domain.domain.clone(),
+                custom_domain: json.then_some(custom_domain),
+            })
+        }
+        DomainsCommands::List(list_args) => {
+            let enclave_uuid = list_args.enclave.resolve_uuid()?;
+            let custom_domains = domains::list_domains(&enclave_api, &enclave_uuid).await?;
+            if !list_args.table_args.use_table(json) {
+                return Ok(DomainsMessage::Domains(custom_domains));
+            }
+            if custom_domains.is_empty() {
+                return Ok(DomainsMessage::NoDomains);
+            }
+            let rendered = list_args
+                .table_args
+                .render(domains_table(&custom_domains))?;
+            Ok(DomainsMessage::Table(rendered.trim_end().to_string()))
+        }
+        DomainsCommands::Remove(remove_args) => {
+            let enclave_uuid = remove_args.enclave.resolve_uuid()?;
+            let prompt = format!("Stop serving the Enclave on {}?", remove_args.domain);
+            if !remove_args.force && !prompt::confirm(&prompt, false)? {
+                return Ok(DomainsMessage::Cancelled);
+            }
+            domains::remove_domain(&enclave_api, &enclave_uuid, &remove_args.domain).await?;
+            Ok(DomainsMessage::Removed(remove_args.domain))
+        }
+    }
+}
+
+fn domains_table(custom_domains: &[CustomDomain]) -> Table {
+    let mut table = Table::new([
+        ("domain", "DOMAIN"),
+        ("status", "STATUS"),
+        ("records", "DNS RECORDS"),
+        ("created", "CREATED"),
+    ]);
+    for custom_domain in custom_domains {
+        let records = custom_domain
+            .dns_records
+            .iter()
+            .map(|record| format!("{} {} {}", record.record_type, record.name, record.value))
+            .collect::<Vec<_>>()
+            .join(", ");
+        table.push_row(vec![
+            custom_domain.domain.clone(),
+            custom_domain.status.to_string(),
+            records,
+            custom_domain
+                .created_at
+                .clone()
+                .unwrap_or_else(|| "-".to_string()),
+        ]);
+    }
+    table
+}
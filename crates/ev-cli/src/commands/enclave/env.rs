@@ -1,8 +1,15 @@
 use clap::{Parser, Subcommand};
 
-use common::api::{papi::EvApiClient, AuthMode, BasicAuth};
+use common::api::{papi::EvApiClient, BasicAuth};
+use common::table::{Table, TableError};
+use ev_cli_derive::CliMessage;
+use thiserror::Error;
 
-use ev_enclave::{api::enclave::EnclaveClient, env};
+use ev_enclave::env::{self, EnvError as EnclaveEnvError};
+use evervault_api_client::enclave::{EnclaveClient, EnclaveEnv, EnclaveEnvHistory};
+
+use crate::table::TableArgs;
+use crate::BaseArgs;
 
 /// Manage Enclave environment
 #[derive(Debug, Parser)]
@@ -20,6 +27,12 @@ pub enum EnvCommands {
     Delete(DeleteEnvArgs),
     #[command()]
     Get(GetEnvArgs),
+    #[command()]
+    Sync(SyncEnvArgs),
+    #[command()]
+    History(HistoryEnvArgs),
+    #[command()]
+    Mtls(MtlsEnvArgs),
 }
 
 /// Add Enclave environment variable
@@ -39,7 +52,12 @@ pub struct AddEnvArgs {
     pub is_secret: bool,
 
     /// Path to enclave.toml config file
-    #[clap(short = 'c', long = "config", default_value = "./enclave.toml")]
+    #[clap(
+        short = 'c',
+        long = "config",
+        default_value = "./enclave.toml",
+        env = "EV_ENCLAVE_CONFIG"
+    )]
     pub config: String,
 }
 
@@ -52,7 +70,12 @@ pub struct DeleteEnvArgs {
     pub name: String,
 
     /// Path to enclave.toml config file
-    #[clap(short = 'c', long = "config", default_value = "./enclave.toml")]
+    #[clap(
+        short = 'c',
+        long = "config",
+        default_value = "./enclave.toml",
+        env = "EV_ENCLAVE_CONFIG"
+    )]
     pub config: String,
 }
 
@@ -61,13 +84,129 @@ pub struct DeleteEnvArgs {
 #[clap(name = "env", about)]
 pub struct GetEnvArgs {
     /// Path to enclave.toml config file
-    #[clap(short = 'c', long = "config", default_value = "./enclave.toml")]
+    #[clap(
+        short = 'c',
+        long = "config",
+        default_value = "./enclave.toml",
+        env = "EV_ENCLAVE_CONFIG"
+    )]
     pub config: String,
+
+    #[command(flatten)]
+    pub table_args: TableArgs,
 }
 
-pub async fn run(env_args: EnvArgs, (app_uuid, api_key): BasicAuth) -> exitcode::ExitCode {
+/// Replace the Enclave's environment with the [env] section of enclave.toml
+#[derive(Debug, Parser)]
+#[clap(name = "env", about)]
+pub struct SyncEnvArgs {
+    /// Path to enclave.toml config file
+    #[clap(
+        short = 'c',
+        long = "config",
+        default_value = "./enclave.toml",
+        env = "EV_ENCLAVE_CONFIG"
+    )]
+    pub config: String,
+}
+
+/// Show when the Enclave's environment variables were added, updated or deleted, and by whom
+#[derive(Debug, Parser)]
+#[clap(name = "env", about)]
+pub struct HistoryEnvArgs {
+    /// Only show changes to this environment variable
+    #[clap(long = "key")]
+    pub name: Option<String>,
+
+    /// The maximum number of changes to show, most recent first
+    #[clap(long = "limit")]
+    pub limit: Option<usize>,
+
+    /// Path to enclave.toml config file
+    #[clap(
+        short = 'c',
+        long = "config",
+        default_value = "./enclave.toml",
+        env = "EV_ENCLAVE_CONFIG"
+    )]
+    pub config: String,
+
+    #[command(flatten)]
+    pub table_args: TableArgs,
+}
+
+/// Encrypt the egress mTLS client certificate and key in the [egress.mtls] section of enclave.toml and add them to the Enclave's environment
+#[derive(Debug, Parser)]
+#[clap(name = "env", about)]
+pub struct MtlsEnvArgs {
+    /// Path to enclave.toml config file
+    #[clap(
+        short = 'c',
+        long = "config",
+        default_value = "./enclave.toml",
+        env = "EV_ENCLAVE_CONFIG"
+    )]
+    pub config: String,
+}
+
+#[derive(Debug, Error, CliMessage)]
+pub enum EnvError {
+    #[error("Error updating environment {0}")]
+    #[cli(code = "enclaves/env-error", exitcode = exitcode::SOFTWARE)]
+    Update(EnclaveEnvError),
+    #[error("Error getting environment history {0}")]
+    #[cli(code = "enclaves/env-error", exitcode = exitcode::SOFTWARE)]
+    History(EnclaveEnvError),
+    #[error("{0}")]
+    #[cli(code = "generic/table-error")]
+    Table(
+        #[from]
+        #[cli(exitcode)]
+        TableError,
+    ),
+}
+
+#[derive(Debug, strum_macros::Display, CliMessage)]
+#[cli(code = "generic/success", exitcode = exitcode::OK)]
+pub enum EnvMessage {
+    #[strum(to_string = "Environment updated successfully")]
+    Updated,
+    #[strum(to_string = "{0}")]
+    Table(String),
+    #[strum(to_string = "Retrieved the Enclave's environment")]
+    Env(#[cli(data)] EnclaveEnv),
+    #[strum(to_string = "No environment changes found")]
+    NoChanges,
+    #[strum(to_string = "Retrieved the Enclave's environment history")]
+    History(#[cli(data)] EnclaveEnvHistory),
+    #[strum(
+        to_string = "Added the egress mTLS credentials as {added}. Deploy the Enclave for the data plane to use them."
+    )]
+    MtlsProvisioned { added: String },
+}
+
+pub async fn run(
+    env_args: EnvArgs,
+    (app_uuid, api_key): BasicAuth,
+) -> Result<EnvMessage, EnvError> {
     let api_client = EvApiClient::new((app_uuid, api_key.clone()));
-    let enclave_api = EnclaveClient::new(AuthMode::ApiKey(api_key));
+    let enclave_api = EnclaveClient::new(crate::auth::api_auth_mode(api_key));
+    if let EnvCommands::History(history_args) = env_args.action {
+        return run_history(enclave_api, history_args).await;
+    }
+    if let EnvCommands::Mtls(mtls_args) = env_args.action {
+        let added = env::provision_egress_mtls(enclave_api, api_client, mtls_args.config)
+            .await
+            .map_err(EnvError::Update)?;
+        return Ok(EnvMessage::MtlsProvisioned {
+            added: added.join(" and "),
+        });
+    }
+    let table_args = match &env_args.action {
+        EnvCommands::Get(get_args) => Some(get_args.table_args.clone()),
+        _ => None,
+    }
+    .filter(|table_args| table_args.use_table(BaseArgs::parse().json));
 
     let result = match env_args.action {
         EnvCommands::Add(add_args) => {
@@ -85,22 +224,73 @@ pub async fn run(env_args: EnvArgs, (app_uuid, api_key): BasicAuth) -> exitcode:
             env::delete_env_var(enclave_api, delete_args.config, delete_args.name).await
         }
         EnvCommands::Get(get_args) => env::get_env_vars(enclave_api, get_args.config).await,
+        EnvCommands::Sync(sync_args) => {
+            env::sync_env_vars(enclave_api, api_client, sync_args.config).await
+        }
+        EnvCommands::History(_) | EnvCommands::Mtls(_) => {
+            unreachable!("History and mtls are handled before other env commands")
+        }
     };
 
-    match result {
-        Ok(None) => {
-            log::info!("Environment updated successfully");
-            exitcode::OK
+    match (result.map_err(EnvError::Update)?, table_args) {
+        (None, _) => Ok(EnvMessage::Updated),
+        (Some(env), Some(table_args)) => {
+            let rendered = table_args.render(env_table(&env))?;
+            Ok(EnvMessage::Table(rendered.trim_end().to_string()))
         }
-        Ok(Some(env)) => {
-            let success_msg = serde_json::json!(env);
-            println!("{}", serde_json::to_string_pretty(&success_msg).unwrap());
+        (Some(env), None) => Ok(EnvMessage::Env(env)),
+    }
+}
 
-            exitcode::OK
-        }
-        Err(err) => {
-            log::error!("Error updating environment {err}");
-            exitcode::SOFTWARE
-        }
+fn env_table(env: &EnclaveEnv) -> Table {
+    let mut table = Table::new([("name", "NAME"), ("value", "VALUE")]);
+    for secret in &env.secrets {
+        table.push_row(vec![secret.name.clone(), secret.secret.clone()]);
+    }
+    table
+}
+
+async fn run_history(
+    enclave_api: EnclaveClient,
+    history_args: HistoryEnvArgs,
+) -> Result<EnvMessage, EnvError> {
+    let history = env::get_env_history(
+        enclave_api,
+        history_args.config,
+        history_args.name,
+        history_args.limit,
+    )
+    .await
+    .map_err(EnvError::History)?;
+
+    if !history_args.table_args.use_table(BaseArgs::parse().json) {
+        return Ok(EnvMessage::History(history));
+    }
+    if history.changes.is_empty() {
+        return Ok(EnvMessage::NoChanges);
+    }
+    let rendered = history_args
+        .table_args
+        .render(env_history_table(&history))?;
+    Ok(EnvMessage::Table(rendered.trim_end().to_string()))
+}
+
+fn env_history_table(history: &EnclaveEnvHistory) -> Table {
+    let mut table = Table::new([
+        ("changed", "CHANGED"),
+        ("action", "ACTION"),
+        ("name", "NAME"),
+        ("secret", "SECRET"),
+        ("by", "BY"),
+    ]);
+    for change in &history.changes {
+        table.push_row(vec![
+            change.changed_at.clone(),
+            change.action.to_string(),
+            change.name.clone(),
+            change.is_secret.to_string(),
+            change.changed_by.clone().unwrap_or_else(|| "-".to_string()),
+        ]);
     }
+    table
 }
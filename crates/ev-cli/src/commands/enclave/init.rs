@@ -1,11 +1,21 @@
 use clap::{ArgGroup, Parser};
+use common::api::client::{ApiError, ApiErrorKind};
 use common::api::BasicAuth;
-use common::{api::AuthMode, CliError};
-use ev_enclave::api::enclave::{Enclave, EnclaveApi};
-use ev_enclave::cert::{create_new_cert, DesiredLifetime, DistinguishedName};
+use ev_cli_derive::CliMessage;
+use ev_enclave::cert::{
+    create_new_cert, get_cert_validity_period, key_matches_cert, CertError, DesiredLifetime,
+    DistinguishedName,
+};
 use ev_enclave::config::{
-    default_dockerfile, EgressSettings, EnclaveConfig, ScalingSettings, SigningInfo,
+    default_dockerfile, EgressSettings, EnclaveConfig, EnclaveType, NetworkProtocol,
+    NetworkSettings, ScalingSettings, SigningInfo,
 };
+use ev_enclave::prompt::{self, PromptError};
+use ev_enclave::rename::validate_enclave_name;
+use evervault_api_client::enclave::{Enclave, EnclaveApi, EnclaveState};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
 
 /// Initialize an Enclave.toml in the current directory
 #[derive(Debug, Parser)]
@@ -81,9 +91,21 @@ pub struct InitArgs {
     #[arg(long = "healthcheck")]
     pub healthcheck: Option<String>,
 
+    /// Initialize a job Enclave, which runs its process to completion instead of serving requests
+    #[arg(long = "job")]
+    pub job: bool,
+
+    /// The protocol your service speaks. Services using tcp must also disable TLS termination.
+    #[arg(long = "protocol", value_enum)]
+    pub protocol: Option<NetworkProtocol>,
+
     /// The desired number of instances for your Enclave to use. Default is 2.
     #[arg(long = "desired-replicas")]
     pub desired_replicas: Option<u32>,
+
+    /// Create a new Enclave and signing credentials, instead of resuming a previous init of an Enclave with the same name
+    #[arg(long = "force-new")]
+    pub force_new: bool,
 }
 
 impl std::convert::From<InitArgs> for EnclaveConfig {
@@ -94,11 +116,17 @@ impl std::convert::From<InitArgs> for EnclaveConfig {
             Some(SigningInfo {
                 cert: val.cert_path,
                 key: val.key_path,
+                next: None,
             })
         };
 
         EnclaveConfig {
             name: val.enclave_name,
+            enclave_type: if val.job {
+                EnclaveType::Job
+            } else {
+                EnclaveType::Service
+            },
             uuid: None,
             app_uuid: None,
             team_uuid: None,
@@ -109,15 +137,25 @@ impl std::convert::From<InitArgs> for EnclaveConfig {
             scaling: val
                 .desired_replicas
                 .map(|desired_replicas| ScalingSettings { desired_replicas }),
+            network: val.protocol.map(|protocol| NetworkSettings {
+                protocol,
+                port: None,
+            }),
+            build: None,
+            dataplane: None,
+            runtime: None,
             dockerfile: val.dockerfile.unwrap_or_else(default_dockerfile), // need to manually set default dockerfile
             signing: signing_info,
             attestation: None,
+            attestation_signature: None,
+            env: None,
             tls_termination: !val.disable_tls_termination,
             api_key_auth: !val.disable_api_key_auth,
             trx_logging: !val.trx_logging_disabled,
             forward_proxy_protocol: val.forward_proxy_protocol,
             trusted_headers: convert_comma_list(val.trusted_headers).unwrap_or_default(),
             healthcheck: val.healthcheck,
+            size: None,
         }
     }
 }
@@ -126,72 +164,292 @@ fn convert_comma_list(maybe_str: Option<String>) -> Option<Vec<String>> {
     maybe_str.map(|str| str.split(',').map(|value| value.to_string()).collect())
 }
 
-pub async fn run(init_args: InitArgs, (_, api_key): BasicAuth) -> exitcode::ExitCode {
-    let enclave_client =
-        ev_enclave::api::enclave::EnclaveClient::new(AuthMode::ApiKey(api_key.clone()));
+// An Enclave which was created by an init that failed before it finished writing the enclave.toml.
+// Deployed Enclaves aren't resumed, as linking to one is a decision for the user.
+fn find_resumable_enclave<'a>(enclaves: &'a [Enclave], name: &str) -> Option<&'a Enclave> {
+    enclaves
+        .iter()
+        .find(|enclave| enclave.name == name && enclave.state == EnclaveState::Pending)
+}
+
+// An Enclave which already holds a name, and so blocks creating another Enclave with it
+fn find_named_enclave<'a>(enclaves: &'a [Enclave], name: &str) -> Option<&'a Enclave> {
+    enclaves
+        .iter()
+        .find(|enclave| enclave.name == name && enclave.state != EnclaveState::Deleted)
+}
+
+/// The Enclave holding a name, so scripts running init non-interactively can tell a taken name
+/// apart from other failures
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NameTaken {
+    name: String,
+    existing_enclave_uuid: Option<String>,
+}
+
+impl std::fmt::Display for NameTaken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.existing_enclave_uuid.as_deref() {
+            Some(uuid) => write!(
+                f,
+                "An Enclave named {} already exists in this App ({uuid}).",
+                self.name
+            ),
+            None => write!(
+                f,
+                "An Enclave named {} already exists in this App.",
+                self.name
+            ),
+        }
+    }
+}
+
+impl NameTaken {
+    fn new(name: &str, existing_enclave: Option<&Enclave>) -> Self {
+        Self {
+            name: name.to_string(),
+            existing_enclave_uuid: existing_enclave.map(|enclave| enclave.uuid.clone()),
+        }
+    }
+}
+
+#[derive(Debug, Error, CliMessage)]
+pub enum InitError {
+    #[error("Error creating Enclave record — {0}")]
+    #[cli(code = "enclaves/api-error")]
+    Create(#[cli(exitcode)] ApiError),
+    #[error(
+        "{0} Pass a different --name, or run init interactively to link the enclave.toml to it."
+    )]
+    #[cli(code = "enclaves/name-taken", exitcode = exitcode::DATAERR)]
+    NameTaken(#[cli(data)] NameTaken),
+    #[error("{0}")]
+    #[cli(code = "enclaves/prompt-error")]
+    Prompt(
+        #[from]
+        #[cli(exitcode)]
+        PromptError,
+    ),
+    #[error("Failed to generate Enclave signing credentials - {0}")]
+    #[cli(code = "enclaves/cert-error")]
+    GenerateCredentials(#[cli(exitcode)] CertError),
+    #[error("Error serializing enclave.toml — {0:?}")]
+    #[cli(code = "enclaves/config-error", exitcode = exitcode::SOFTWARE)]
+    Serialize(toml::ser::Error),
+    #[error("Error writing enclave.toml — {0:?}")]
+    #[cli(code = "generic/io-error", exitcode = exitcode::IOERR)]
+    Write(std::io::Error),
+}
+
+#[derive(Debug, strum_macros::Display, CliMessage)]
+#[cli(code = "generic/success", exitcode = exitcode::OK)]
+pub enum InitMessage {
+    #[strum(
+        to_string = "Enclave.toml has already been initialized for this Enclave. You can now deploy an Enclave using the deploy command"
+    )]
+    AlreadyInitialized,
+    #[strum(
+        to_string = "Enclave.toml initialized successfully. You can now deploy an Enclave using the deploy command"
+    )]
+    Initialized,
+    #[strum(to_string = "Phew! Exiting early...")]
+    #[cli(code = "generic/cancelled")]
+    Cancelled,
+}
 
-    let create_enclave_request = ev_enclave::api::enclave::CreateEnclaveRequest::new(
-        init_args.enclave_name.clone(),
-        init_args.is_time_bound,
+pub async fn run(
+    mut init_args: InitArgs,
+    (_, api_key): BasicAuth,
+) -> Result<InitMessage, InitError> {
+    let enclave_client = evervault_api_client::enclave::EnclaveClient::new(
+        crate::auth::api_auth_mode(api_key.clone()),
     );
-    let created_enclave = match enclave_client.create_enclave(create_enclave_request).await {
-        Ok(enclave_ref) => enclave_ref,
-        Err(e) => {
-            log::error!("Error creating Enclave record — {e:?}");
-            return e.exitcode();
+
+    if !init_args.force_new {
+        match enclave_client.get_enclaves().await {
+            Ok(response) => {
+                let enclaves = response.enclaves();
+                if let Some(existing_enclave) =
+                    find_resumable_enclave(enclaves, &init_args.enclave_name)
+                {
+                    log::info!(
+                        "Found existing Enclave {} ({}), resuming init. Use --force-new to create a new Enclave instead.",
+                        existing_enclave.name,
+                        existing_enclave.uuid
+                    );
+                    return init_local_config(init_args, existing_enclave.clone()).await;
+                }
+                if let Some(existing_enclave) =
+                    find_named_enclave(enclaves, &init_args.enclave_name)
+                {
+                    match resolve_name_taken(
+                        &init_args.enclave_name,
+                        Some(existing_enclave.clone()),
+                    )? {
+                        NameTakenResolution::Adopt(existing_enclave) => {
+                            return init_local_config(init_args, existing_enclave).await
+                        }
+                        NameTakenResolution::Rename(name) => init_args.enclave_name = name,
+                        NameTakenResolution::Cancel => return Ok(InitMessage::Cancelled),
+                    }
+                }
+            }
+            Err(e) => log::debug!("Failed to check for an existing Enclave to resume — {e}"),
+        }
+    }
+
+    let created_enclave = loop {
+        let create_enclave_request = evervault_api_client::enclave::CreateEnclaveRequest::new(
+            init_args.enclave_name.clone(),
+            init_args.is_time_bound,
+        );
+        match enclave_client.create_enclave(create_enclave_request).await {
+            Ok(enclave_ref) => break enclave_ref,
+            Err(e) if matches!(e.kind, ApiErrorKind::NameTaken | ApiErrorKind::Conflict) => {
+                let existing_enclave = match enclave_client.get_enclaves().await {
+                    Ok(response) => {
+                        find_named_enclave(response.enclaves(), &init_args.enclave_name).cloned()
+                    }
+                    Err(e) => {
+                        log::debug!("Failed to look up the Enclave using this name — {e}");
+                        None
+                    }
+                };
+                match resolve_name_taken(&init_args.enclave_name, existing_enclave)? {
+                    NameTakenResolution::Adopt(existing_enclave) => break existing_enclave,
+                    NameTakenResolution::Rename(name) => init_args.enclave_name = name,
+                    NameTakenResolution::Cancel => return Ok(InitMessage::Cancelled),
+                }
+            }
+            Err(e) => return Err(InitError::Create(e)),
         }
     };
 
     init_local_config(init_args, created_enclave).await
 }
 
-async fn init_local_config(init_args: InitArgs, created_enclave: Enclave) -> exitcode::ExitCode {
+enum NameTakenResolution {
+    Adopt(Enclave),
+    Rename(String),
+    Cancel,
+}
+
+// Lets the user link the enclave.toml to the Enclave which holds the name, or pick another name.
+// Without a terminal to ask on, init fails instead of guessing.
+fn resolve_name_taken(
+    name: &str,
+    existing_enclave: Option<Enclave>,
+) -> Result<NameTakenResolution, InitError> {
+    let name_taken = NameTaken::new(name, existing_enclave.as_ref());
+    if !prompt::can_prompt() {
+        return Err(InitError::NameTaken(name_taken));
+    }
+
+    log::warn!("{name_taken}");
+    // An Enclave being deleted can't be linked to, but its name can't be reused until it's gone
+    let adoptable_enclave = existing_enclave
+        .filter(|enclave| matches!(enclave.state, EnclaveState::Pending | EnclaveState::Active));
+    let mut options = vec!["Choose a different name".to_string(), "Cancel".to_string()];
+    if let Some(adoptable_enclave) = adoptable_enclave.as_ref() {
+        options.insert(
+            0,
+            format!(
+                "Link the enclave.toml to the existing Enclave {} ({})",
+                adoptable_enclave.name, adoptable_enclave.uuid
+            ),
+        );
+    }
+    let choice = prompt::select("How would you like to continue?", &options, 0)?;
+
+    match (adoptable_enclave, choice) {
+        (Some(adoptable_enclave), 0) => Ok(NameTakenResolution::Adopt(adoptable_enclave)),
+        (_, choice) if choice == options.len() - 2 => loop {
+            let new_name = prompt::input("New Enclave name")?;
+            match validate_enclave_name(&new_name) {
+                Ok(()) if new_name != name => break Ok(NameTakenResolution::Rename(new_name)),
+                Ok(()) => log::error!("{name} is already taken, choose a different name."),
+                Err(e) => log::error!("{e}"),
+            }
+        },
+        _ => Ok(NameTakenResolution::Cancel),
+    }
+}
+
+// Signing credentials generated by an init which failed before writing the enclave.toml
+fn find_generated_signing_credentials(output_path: &Path) -> Option<(PathBuf, PathBuf)> {
+    let cert_path = output_path.join("cert.pem");
+    let key_path = output_path.join("key.pem");
+    let key_exists = std::fs::metadata(&key_path).is_ok_and(|metadata| metadata.len() > 0);
+    if !key_exists || get_cert_validity_period(&cert_path).is_err() {
+        return None;
+    }
+    match key_matches_cert(&cert_path, &key_path) {
+        Ok(true) => Some((cert_path, key_path)),
+        Ok(false) => {
+            log::warn!("The key.pem in {} doesn't match its cert.pem, so new signing credentials will be generated.", output_path.display());
+            None
+        }
+        Err(e) => {
+            log::warn!("Failed to check the signing credentials in {} — {e}. New signing credentials will be generated.", output_path.display());
+            None
+        }
+    }
+}
+
+async fn init_local_config(
+    init_args: InitArgs,
+    created_enclave: Enclave,
+) -> Result<InitMessage, InitError> {
     let output_dir = init_args.output_dir.clone();
-    let output_path = std::path::Path::new(output_dir.as_str());
+    let output_path = Path::new(output_dir.as_str());
     let config_path = output_path.join("enclave.toml");
+    let force_new = init_args.force_new;
+
+    if !force_new {
+        let existing_config = EnclaveConfig::try_from_filepath(&config_path.to_string_lossy());
+        if existing_config
+            .is_ok_and(|config| config.uuid.as_deref() == Some(created_enclave.uuid()))
+        {
+            return Ok(InitMessage::AlreadyInitialized);
+        }
+    }
 
     let mut initial_config: EnclaveConfig = init_args.into();
     initial_config.annotate(created_enclave);
 
-    if initial_config.signing.is_none() {
+    let generated_credentials = if force_new {
+        None
+    } else {
+        find_generated_signing_credentials(output_path)
+    };
+
+    if let (None, Some((cert_path, key_path))) = (&initial_config.signing, generated_credentials) {
+        log::info!("Reusing the signing credentials generated by a previous init");
+        initial_config.set_cert(format!("{}", cert_path.display()));
+        initial_config.set_key(format!("{}", key_path.display()));
+    } else if initial_config.signing.is_none() {
         log::info!("Generating signing credentials for enclave");
-        match create_new_cert(
+        let (cert_path, key_path) = create_new_cert(
             output_path,
             DistinguishedName::default(),
             DesiredLifetime::default(),
-        ) {
-            Ok((cert_path, key_path)) => {
-                initial_config.set_cert(format!("{}", cert_path.display()));
-                initial_config.set_key(format!("{}", key_path.display()));
-            }
-            Err(e) => {
-                log::error!("Failed to generate Enclave signing credentials - {e}");
-                return e.exitcode();
-            }
-        }
+        )
+        .map_err(InitError::GenerateCredentials)?;
+        initial_config.set_cert(format!("{}", cert_path.display()));
+        initial_config.set_key(format!("{}", key_path.display()));
     }
 
-    let serialized_config = match toml::ser::to_vec(&initial_config) {
-        Ok(bytes) => bytes,
-        Err(e) => {
-            log::error!("Error serializing enclave.toml — {:?}", e);
-            return exitcode::SOFTWARE;
-        }
-    };
-
-    if let Err(e) = std::fs::write(config_path, serialized_config) {
-        log::error!("Error writing enclave.toml — {:?}", e);
-        exitcode::IOERR
-    } else {
-        log::info!("Enclave.toml initialized successfully. You can now deploy an Enclave using the deploy command");
-        exitcode::OK
-    }
+    let serialized_config = toml::ser::to_vec(&initial_config).map_err(InitError::Serialize)?;
+    std::fs::write(config_path, serialized_config).map_err(InitError::Write)?;
+    Ok(InitMessage::Initialized)
 }
 
 #[cfg(test)]
 mod init_tests {
     use super::*;
-    use ev_enclave::api::enclave::EnclaveState;
+    use crate::CmdOutput;
+    use evervault_api_client::enclave::EnclaveState;
 
     use std::fs::read;
     use tempfile::TempDir;
@@ -226,8 +484,11 @@ mod init_tests {
             forward_proxy_protocol: false,
             trusted_headers: Some("X-Evervault-*".to_string()),
             healthcheck: None,
+            job: false,
+            protocol: None,
+            force_new: false,
         };
-        init_local_config(init_args, sample_enclave).await;
+        init_local_config(init_args, sample_enclave).await.unwrap();
         let config_path = output_dir.path().join("enclave.toml");
         assert!(config_path.exists());
         let config_content = String::from_utf8(read(config_path).unwrap()).unwrap();
@@ -257,4 +518,129 @@ keyPath = "./key.pem"
 "#;
         assert_eq!(config_content, expected_config_content);
     }
+
+    fn sample_enclave(name: &str, uuid: &str, state: EnclaveState) -> Enclave {
+        Enclave {
+            uuid: uuid.into(),
+            name: name.into(),
+            team_uuid: "team_1234".into(),
+            app_uuid: "app_1234".into(),
+            domain: "hello.com".into(),
+            state,
+            created_at: "00:00:00".into(),
+            updated_at: "00:00:00".into(),
+        }
+    }
+
+    #[test]
+    fn find_resumable_enclave_test() {
+        let enclaves = vec![
+            sample_enclave("hello", "enclave_deleted", EnclaveState::Deleted),
+            sample_enclave("goodbye", "enclave_other", EnclaveState::Pending),
+            sample_enclave("deployed", "enclave_active", EnclaveState::Active),
+            sample_enclave("hello", "enclave_pending", EnclaveState::Pending),
+        ];
+        let resumable = find_resumable_enclave(&enclaves, "hello").unwrap();
+        assert_eq!(resumable.uuid, "enclave_pending");
+        assert!(find_resumable_enclave(&enclaves, "missing").is_none());
+        assert!(find_resumable_enclave(&enclaves, "deployed").is_none());
+    }
+
+    #[test]
+    fn resolve_name_taken_fails_without_a_terminal() {
+        let existing_enclave = sample_enclave("hello", "enclave_active", EnclaveState::Active);
+        assert!(matches!(
+            resolve_name_taken("hello", Some(existing_enclave)),
+            Err(InitError::NameTaken(NameTaken { existing_enclave_uuid: Some(uuid), .. })) if uuid == "enclave_active"
+        ));
+    }
+
+    #[test]
+    fn name_taken_error_test() {
+        let enclaves = vec![
+            sample_enclave("hello", "enclave_deleted", EnclaveState::Deleted),
+            sample_enclave("hello", "enclave_deleting", EnclaveState::Deleting),
+        ];
+        let existing_enclave = find_named_enclave(&enclaves, "hello");
+        assert_eq!(existing_enclave.unwrap().uuid, "enclave_deleting");
+
+        let error = InitError::NameTaken(NameTaken::new("hello", existing_enclave));
+        assert_eq!(error.code(), "enclaves/name-taken");
+        assert_eq!(error.exitcode(), exitcode::DATAERR);
+        assert_eq!(
+            error.to_string(),
+            "An Enclave named hello already exists in this App (enclave_deleting). Pass a different --name, or run init interactively to link the enclave.toml to it."
+        );
+        assert_eq!(
+            error.data().unwrap()["existingEnclaveUuid"],
+            "enclave_deleting"
+        );
+        let error = InitError::NameTaken(NameTaken::new("hello", None));
+        assert!(error.data().unwrap()["existingEnclaveUuid"].is_null());
+    }
+
+    #[tokio::test]
+    async fn init_local_config_resumes_with_generated_credentials() {
+        let output_dir = TempDir::new().unwrap();
+        let output_path = output_dir.path();
+        let (cert_path, _) =
+            create_new_cert(output_path, Default::default(), Default::default()).unwrap();
+        let generated_cert = read(&cert_path).unwrap();
+
+        let init_args = InitArgs::parse_from([
+            "init",
+            "--name",
+            "hello",
+            "--output",
+            output_path.to_str().unwrap(),
+        ]);
+        let enclave = sample_enclave("hello", "enclave_pending", EnclaveState::Pending);
+        assert!(matches!(
+            init_local_config(init_args, enclave.clone()).await,
+            Ok(InitMessage::Initialized)
+        ));
+        assert_eq!(read(&cert_path).unwrap(), generated_cert);
+
+        let config =
+            EnclaveConfig::try_from_filepath(output_path.join("enclave.toml").to_str().unwrap())
+                .unwrap();
+        assert_eq!(config.uuid.as_deref(), Some("enclave_pending"));
+        assert_eq!(config.cert(), Some(cert_path.to_str().unwrap()));
+
+        let init_args = InitArgs::parse_from([
+            "init",
+            "--name",
+            "hello",
+            "--output",
+            output_path.to_str().unwrap(),
+            "--force-new",
+        ]);
+        assert!(init_local_config(init_args, enclave).await.is_ok());
+        assert_ne!(read(&cert_path).unwrap(), generated_cert);
+    }
+
+    #[tokio::test]
+    async fn init_local_config_replaces_mismatched_credentials() {
+        let output_dir = TempDir::new().unwrap();
+        let other_dir = TempDir::new().unwrap();
+        let output_path = output_dir.path();
+        let (cert_path, key_path) =
+            create_new_cert(output_path, Default::default(), Default::default()).unwrap();
+        let (_, other_key_path) =
+            create_new_cert(other_dir.path(), Default::default(), Default::default()).unwrap();
+        std::fs::copy(other_key_path, &key_path).unwrap();
+        let mismatched_cert = read(&cert_path).unwrap();
+
+        let init_args = InitArgs::parse_from([
+            "init",
+            "--name",
+            "hello",
+            "--output",
+            output_path.to_str().unwrap(),
+        ]);
+        let enclave = sample_enclave("hello", "enclave_pending", EnclaveState::Pending);
+        assert!(init_local_config(init_args, enclave).await.is_ok());
+        assert_ne!(read(&cert_path).unwrap(), mismatched_cert);
+        assert!(key_matches_cert(&cert_path, &key_path).unwrap());
+    }
 }
@@ -1,9 +1,18 @@
+use crate::completion::enclave_uuid_completer;
+use crate::config::api_cache_directory;
+use crate::table::TableArgs;
+use crate::BaseArgs;
 use clap::Parser;
+use common::api::client::ApiError;
 use common::api::BasicAuth;
-use common::{api::AuthMode, CliError};
-use ev_enclave::api;
-use ev_enclave::api::enclave::EnclaveApi;
-use ev_enclave::config::{read_and_validate_config, BuildTimeConfig};
+use common::table::{Table, TableError};
+use ev_cli_derive::CliMessage;
+use ev_enclave::config::{read_and_validate_config, BuildTimeConfig, EnclaveConfigError};
+use evervault_api_client::cache::ResponseCache;
+use evervault_api_client::enclave::{
+    EnclaveApi, EnclaveClient, GetEnclaveResponse, GetEnclavesResponse,
+};
+use thiserror::Error;
 
 /// List your Enclaves and Deployments
 #[derive(Debug, Parser)]
@@ -12,6 +21,13 @@ pub struct List {
     /// The resource to list
     #[command(subcommand)]
     resource: ListCommands,
+
+    /// Always fetch from the API, bypassing the local response cache
+    #[arg(long = "no-cache", global = true)]
+    no_cache: bool,
+
+    #[command(flatten)]
+    table_args: TableArgs,
 }
 
 /// The supported list commands
@@ -29,70 +45,152 @@ pub enum ListCommands {
 #[derive(Debug, Parser)]
 pub struct DeploymentArgs {
     /// The Enclave uuid to get deployments for
-    #[arg(long = "enclave-uuid")]
+    #[arg(long = "enclave-uuid", env = "EV_ENCLAVE_UUID", add = enclave_uuid_completer())]
     enclave_uuid: Option<String>,
 
     /// The file containing the Enclave config
-    #[arg(short = 'c', long = "config", default_value = "./enclave.toml")]
+    #[arg(
+        short = 'c',
+        long = "config",
+        default_value = "./enclave.toml",
+        env = "EV_ENCLAVE_CONFIG"
+    )]
     config: String,
 }
 impl BuildTimeConfig for DeploymentArgs {}
 
-pub async fn run(list_action: List, (_, api_key): BasicAuth) -> exitcode::ExitCode {
-    let auth = AuthMode::ApiKey(api_key);
+#[derive(Debug, Error, CliMessage)]
+pub enum ListError {
+    #[error("No Enclave uuid provided, and failed to parse the Enclave config - {0}")]
+    #[cli(code = "enclaves/config-error")]
+    Config(
+        #[from]
+        #[cli(exitcode)]
+        EnclaveConfigError,
+    ),
+    #[error("An error occurred while retrieving your Enclaves — {0}")]
+    #[cli(code = "enclaves/api-error")]
+    Api(
+        #[from]
+        #[cli(exitcode)]
+        ApiError,
+    ),
+    #[error("{0}")]
+    #[cli(code = "generic/table-error")]
+    Table(
+        #[from]
+        #[cli(exitcode)]
+        TableError,
+    ),
+}
+
+#[derive(Debug, strum_macros::Display, CliMessage)]
+#[cli(code = "generic/success", exitcode = exitcode::OK)]
+pub enum ListMessage {
+    #[strum(to_string = "{0}")]
+    Table(String),
+    #[strum(to_string = "Retrieved your Enclaves")]
+    Enclaves(#[cli(data)] GetEnclavesResponse),
+    #[strum(to_string = "Retrieved the Enclave's Deployments")]
+    Deployments(#[cli(data)] Box<GetEnclaveResponse>),
+}
 
-    let enclave_client = api::enclave::EnclaveClient::new(auth);
+pub async fn run(list_action: List, (_, api_key): BasicAuth) -> Result<ListMessage, ListError> {
+    let auth = crate::auth::api_auth_mode(api_key);
+
+    let mut enclave_client = EnclaveClient::new(auth);
+    if !list_action.no_cache {
+        if let Some(directory) = api_cache_directory() {
+            enclave_client = enclave_client.with_response_cache(ResponseCache::new(directory));
+        }
+    }
 
     match list_action.resource {
-        ListCommands::Enclaves => list_enclaves(&enclave_client).await,
+        ListCommands::Enclaves => list_enclaves(&enclave_client, &list_action.table_args).await,
         ListCommands::Deployments(deployment_args) => {
-            list_deployments(&enclave_client, deployment_args).await
+            list_deployments(&enclave_client, deployment_args, &list_action.table_args).await
         }
     }
 }
 
-async fn list_enclaves(enclave_client: &api::enclave::EnclaveClient) -> exitcode::ExitCode {
-    let enclaves = match enclave_client.get_enclaves().await {
-        Ok(enclaves) => enclaves,
-        Err(e) => {
-            log::error!("An error occurred while retrieving your Enclaves — {:?}", e);
-            return e.exitcode();
-        }
-    };
+fn enclaves_table(enclaves: &GetEnclavesResponse) -> Table {
+    let mut table = Table::new([
+        ("name", "NAME"),
+        ("uuid", "UUID"),
+        ("state", "STATE"),
+        ("domain", "DOMAIN"),
+        ("created", "CREATED"),
+    ]);
+    for enclave in enclaves.enclaves() {
+        table.push_row(vec![
+            enclave.name.clone(),
+            enclave.uuid.clone(),
+            enclave.state.to_string(),
+            enclave.domain.clone(),
+            enclave.created_at.clone(),
+        ]);
+    }
+    table
+}
+
+fn deployments_table(enclave: &GetEnclaveResponse) -> Table {
+    let mut table = Table::new([
+        ("uuid", "UUID"),
+        ("version", "VERSION"),
+        ("status", "STATUS"),
+        ("debug", "DEBUG"),
+        ("started", "STARTED"),
+        ("completed", "COMPLETED"),
+    ]);
+    for deployment in &enclave.deployments {
+        let optional = |value: &Option<String>| value.clone().unwrap_or_else(|| "-".to_string());
+        table.push_row(vec![
+            deployment.deployment.uuid.clone(),
+            deployment.version.version.to_string(),
+            deployment.version.build_status.to_string(),
+            deployment.deployment.debug_mode.to_string(),
+            optional(&deployment.deployment.started_at),
+            optional(&deployment.deployment.completed_at),
+        ]);
+    }
+    table
+}
 
-    let serialized_enclaves = serde_json::to_string_pretty(&enclaves).unwrap();
-    println!("{}", serialized_enclaves);
-    exitcode::OK
+fn render_table(table_args: &TableArgs, table: Table) -> Result<ListMessage, ListError> {
+    let rendered = table_args.render(table)?;
+    Ok(ListMessage::Table(rendered.trim_end().to_string()))
+}
+
+async fn list_enclaves(
+    enclave_client: &EnclaveClient,
+    table_args: &TableArgs,
+) -> Result<ListMessage, ListError> {
+    let enclaves = enclave_client.get_enclaves().await?;
+
+    if !table_args.use_table(BaseArgs::parse().json) {
+        return Ok(ListMessage::Enclaves(enclaves));
+    }
+    render_table(table_args, enclaves_table(&enclaves))
 }
 
 async fn list_deployments(
-    enclave_client: &api::enclave::EnclaveClient,
+    enclave_client: &EnclaveClient,
     deployment_args: DeploymentArgs,
-) -> exitcode::ExitCode {
-    let enclave_uuid = if let Some(uuid) = deployment_args.enclave_uuid.clone() {
-        uuid
-    } else {
-        match read_and_validate_config(&deployment_args.config, &deployment_args) {
-            Ok((_, validated_config)) => validated_config.enclave_uuid().to_string(),
-            Err(e) => {
-                log::error!(
-                    "No Enclave uuid provided, and failed to parse the Enclave config - {}",
-                    e
-                );
-                return e.exitcode();
-            }
+    table_args: &TableArgs,
+) -> Result<ListMessage, ListError> {
+    let enclave_uuid = match deployment_args.enclave_uuid.clone() {
+        Some(uuid) => uuid,
+        None => {
+            let (_, validated_config) =
+                read_and_validate_config(&deployment_args.config, &deployment_args)?;
+            validated_config.enclave_uuid().to_string()
         }
     };
 
-    let enclave = match enclave_client.get_enclave(&enclave_uuid).await {
-        Ok(enclave) => enclave,
-        Err(e) => {
-            log::error!("An error occurred while retrieving your Enclaves — {:?}", e);
-            return e.exitcode();
-        }
-    };
+    let enclave = enclave_client.get_enclave(&enclave_uuid).await?;
 
-    let serialized_deployments = serde_json::to_string_pretty(&enclave).unwrap();
-    println!("{}", serialized_deployments);
-    exitcode::OK
+    if !table_args.use_table(BaseArgs::parse().json) {
+        return Ok(ListMessage::Deployments(Box::new(enclave)));
+    }
+    render_table(table_args, deployments_table(&enclave))
 }
@@ -1,20 +1,40 @@
-use clap::Parser;
-use common::{
-    api::{AuthMode, BasicAuth},
-    CliError,
+use clap::{Parser, Subcommand};
+use common::api::BasicAuth;
+use ev_cli_derive::CliMessage;
+use ev_enclave::{
+    config::{EnclaveConfig, EnclaveConfigError},
+    logs::{
+        export_logs, follow_logs, format_log_event, get_logs, parse_rotation_size, parse_since,
+        search_logs, use_log_colors, LogExporter, LogSearch, LogSourceFilter,
+        LogsError as EnclaveLogsError, RotationPolicy,
+    },
 };
-use ev_enclave::{api::enclave::EnclaveClient, config::EnclaveConfig, logs::get_logs};
+use evervault_api_client::enclave::{EnclaveClient, LogEvent};
+use std::path::PathBuf;
+use thiserror::Error;
+
+use crate::completion::enclave_uuid_completer;
+use crate::tty::outputs_json;
+use crate::BaseArgs;
 
 /// Pull the logs for an Enclave
 #[derive(Debug, Parser)]
-#[command(name = "logs", about)]
+#[command(name = "logs", about, args_conflicts_with_subcommands = true)]
 pub struct LogArgs {
+    #[command(subcommand)]
+    pub action: Option<LogsCommands>,
+
     /// Uuid of the Enclave show logs for. If not supplied, the CLI will look for a local enclave.toml
-    #[arg(long = "enclave-uuid")]
+    #[arg(long = "enclave-uuid", env = "EV_ENCLAVE_UUID", add = enclave_uuid_completer())]
     pub enclave_uuid: Option<String>,
 
     /// Path to the toml file containing the Enclave's config
-    #[arg(short = 'c', long = "config", default_value = "./enclave.toml")]
+    #[arg(
+        short = 'c',
+        long = "config",
+        default_value = "./enclave.toml",
+        env = "EV_ENCLAVE_CONFIG"
+    )]
     pub config: String,
 
     /// The start time in epoch milliseconds
@@ -22,47 +42,248 @@ pub struct LogArgs {
     pub start_time: Option<String>,
 
     /// The end time in epoch milliseconds
-    #[arg(long = "end-time")]
+    #[arg(long = "end-time", conflicts_with = "follow")]
     pub end_time: Option<String>,
+
+    /// Keep polling for new logs until interrupted
+    #[arg(long)]
+    pub follow: bool,
+
+    /// Write the logs to a file instead of the pager. The format is chosen from the extension: .ndjson, .jsonl or .csv
+    #[arg(long = "export")]
+    pub export: Option<PathBuf>,
+
+    /// Start a new export file once the current one reaches this size, e.g. 10MB
+    #[arg(long = "rotate-size", requires = "export")]
+    pub rotate_size: Option<String>,
+
+    /// The number of rotated export files to keep
+    #[arg(long = "max-files", default_value_t = 5, requires = "rotate_size")]
+    pub max_files: usize,
+
+    /// Only show logs written by the data plane or by your app. Logs without a source are shown as your app's.
+    #[arg(long = "source", value_enum, default_value_t = LogSourceFilter::All)]
+    pub source: LogSourceFilter,
 }
 
-pub async fn run(log_args: LogArgs, (_, api_key): BasicAuth) -> i32 {
-    log::info!("Note: each query will return a maximum of 500 logs, if logs are missing reduce the time range");
+#[derive(Debug, Subcommand)]
+pub enum LogsCommands {
+    #[command()]
+    Search(SearchLogsArgs),
+}
 
-    let enclave_client = EnclaveClient::new(AuthMode::ApiKey(api_key));
-
-    let enclave_uuid = match log_args.enclave_uuid.clone() {
-        Some(enclave_uuid) => enclave_uuid,
-        None => {
-            let enclave_uuid = match EnclaveConfig::try_from_filepath(&log_args.config) {
-                Ok(config) => config.uuid,
-                Err(e) => {
-                    log::error!("An error occurred while resolving your Enclave toml.\n\nPlease make sure you have a enclave.toml file in the current directory, or have supplied a path with the --config flag.");
-                    return e.exitcode();
-                }
-            };
-            match enclave_uuid {
-                Some(uuid) => uuid,
-                None => {
-                    log::error!("Enclave uuid is missing from toml");
-                    return exitcode::DATAERR;
-                }
-            }
-        }
+/// Search an Enclave's logs for a message, without exporting them
+#[derive(Debug, Parser)]
+#[command(name = "search", about)]
+pub struct SearchLogsArgs {
+    /// Uuid of the Enclave to search the logs of. If not supplied, the CLI will look for a local enclave.toml
+    #[arg(long = "enclave-uuid", env = "EV_ENCLAVE_UUID", add = enclave_uuid_completer())]
+    pub enclave_uuid: Option<String>,
+
+    /// Path to the toml file containing the Enclave's config
+    #[arg(
+        short = 'c',
+        long = "config",
+        default_value = "./enclave.toml",
+        env = "EV_ENCLAVE_CONFIG"
+    )]
+    pub config: String,
+
+    /// The text to search for. Matching ignores case.
+    #[arg(long = "query")]
+    pub query: String,
+
+    /// How far back to search, e.g. 30m, 24h or 7d
+    #[arg(long = "since", default_value = "1h")]
+    pub since: String,
+
+    /// Number of log queries to run at once
+    #[arg(long = "concurrency", default_value_t = 4, value_parser = clap::value_parser!(u16).range(1..))]
+    pub concurrency: u16,
+
+    /// Only search logs written by the data plane or by your app
+    #[arg(long = "source", value_enum, default_value_t = LogSourceFilter::All)]
+    pub source: LogSourceFilter,
+}
+
+#[derive(Debug, Error, CliMessage)]
+pub enum LogsError {
+    #[error("An error occurred while resolving your Enclave toml — {0}\n\nPlease make sure you have a enclave.toml file in the current directory, or have supplied a path with the --config flag.")]
+    #[cli(code = "enclaves/config-error")]
+    Config(
+        #[from]
+        #[cli(exitcode)]
+        EnclaveConfigError,
+    ),
+    #[error("Enclave uuid is missing from toml")]
+    #[cli(code = "enclaves/config-error", exitcode = exitcode::DATAERR)]
+    MissingEnclaveUuid,
+    #[error("An error occurred while fetching logs: {0}")]
+    #[cli(code = "enclaves/logs-error")]
+    Fetch(
+        #[from]
+        #[cli(exitcode)]
+        EnclaveLogsError,
+    ),
+}
+
+#[derive(Debug, strum_macros::Display, CliMessage)]
+#[cli(code = "generic/success", exitcode = exitcode::OK)]
+pub enum LogsMessage {
+    #[strum(to_string = "Fetched the logs for Enclave {0}")]
+    Fetched(String),
+    #[strum(to_string = "Exported {count} logs to {path}")]
+    Exported { count: usize, path: String },
+    #[strum(to_string = "Found {count} logs matching \"{query}\"")]
+    Searched { count: usize, query: String },
+    #[strum(to_string = "Retrieved the logs matching \"{query}\"")]
+    Matches {
+        query: String,
+        #[cli(data)]
+        events: Vec<LogEvent>,
+    },
+}
+
+fn create_exporter(log_args: &LogArgs) -> Result<Option<LogExporter>, EnclaveLogsError> {
+    let Some(export_path) = log_args.export.as_deref() else {
+        return Ok(None);
+    };
+    let rotation = RotationPolicy {
+        max_bytes: log_args
+            .rotate_size
+            .as_deref()
+            .map(parse_rotation_size)
+            .transpose()?,
+        max_files: log_args.max_files,
     };
+    LogExporter::create(export_path, rotation).map(Some)
+}
 
-    match get_logs(
-        log_args.start_time,
-        log_args.end_time,
-        enclave_uuid,
-        enclave_client,
-    )
-    .await
-    {
-        Ok(_) => exitcode::OK,
-        Err(err) => {
-            log::error!("An error occurred while fetching logs: {err}");
-            err.exitcode()
+async fn fetch_logs(
+    log_args: LogArgs,
+    enclave_uuid: String,
+    enclave_client: EnclaveClient,
+) -> Result<LogsMessage, EnclaveLogsError> {
+    let exporter = create_exporter(&log_args)?;
+    match (exporter, log_args.follow) {
+        (None, false) => {
+            get_logs(
+                log_args.start_time,
+                log_args.end_time,
+                enclave_uuid.clone(),
+                enclave_client,
+                log_args.source,
+            )
+            .await?;
+            Ok(LogsMessage::Fetched(enclave_uuid))
+        }
+        (None, true) => {
+            log::info!("Following logs, press Ctrl-C to stop");
+            let colored = use_log_colors();
+            follow_logs(
+                log_args.start_time,
+                &enclave_uuid,
+                &enclave_client,
+                |events| {
+                    events
+                        .iter()
+                        .filter(|event| log_args.source.matches(event))
+                        .filter_map(|event| format_log_event(event, colored))
+                        .for_each(|log_event| println!("{log_event}"));
+                    Ok(())
+                },
+            )
+            .await?;
+            Ok(LogsMessage::Fetched(enclave_uuid))
         }
+        (Some(mut exporter), false) => {
+            let exported = export_logs(
+                log_args.start_time,
+                log_args.end_time,
+                &enclave_uuid,
+                &enclave_client,
+                &mut exporter,
+                log_args.source,
+            )
+            .await?;
+            Ok(LogsMessage::Exported {
+                count: exported,
+                path: exporter.path().display().to_string(),
+            })
+        }
+        (Some(mut exporter), true) => {
+            log::info!(
+                "Exporting logs to {} until interrupted, press Ctrl-C to stop",
+                exporter.path().display()
+            );
+            follow_logs(
+                log_args.start_time,
+                &enclave_uuid,
+                &enclave_client,
+                |events| exporter.write_events(&log_args.source.filter(events)),
+            )
+            .await?;
+            Ok(LogsMessage::Exported {
+                count: exporter.events_written(),
+                path: exporter.path().display().to_string(),
+            })
+        }
+    }
+}
+
+async fn search(
+    search_args: SearchLogsArgs,
+    enclave_uuid: String,
+    enclave_client: EnclaveClient,
+) -> Result<LogsMessage, EnclaveLogsError> {
+    let since = parse_since(&search_args.since)?;
+    let search = LogSearch::new(&search_args.query)?;
+    let matches = search_logs(
+        &enclave_client,
+        &enclave_uuid,
+        &search,
+        since,
+        search_args.concurrency.into(),
+    )
+    .await?;
+    let matches = search_args.source.filter(&matches);
+
+    if outputs_json(BaseArgs::parse().json) {
+        return Ok(LogsMessage::Matches {
+            query: search_args.query,
+            events: matches,
+        });
+    }
+    let colored = use_log_colors();
+    matches
+        .iter()
+        .filter_map(|event| format_log_event(event, colored))
+        .for_each(|log_event| println!("{}", search.highlight(&log_event)));
+    Ok(LogsMessage::Searched {
+        count: matches.len(),
+        query: search_args.query,
+    })
+}
+
+fn resolve_enclave_uuid(enclave_uuid: Option<String>, config: &str) -> Result<String, LogsError> {
+    match enclave_uuid {
+        Some(enclave_uuid) => Ok(enclave_uuid),
+        None => EnclaveConfig::try_from_filepath(config)?
+            .uuid
+            .ok_or(LogsError::MissingEnclaveUuid),
     }
 }
+
+pub async fn run(log_args: LogArgs, (_, api_key): BasicAuth) -> Result<LogsMessage, LogsError> {
+    let enclave_client = EnclaveClient::new(crate::auth::api_auth_mode(api_key));
+
+    if let Some(LogsCommands::Search(search_args)) = log_args.action {
+        let enclave_uuid =
+            resolve_enclave_uuid(search_args.enclave_uuid.clone(), &search_args.config)?;
+        return Ok(search(search_args, enclave_uuid, enclave_client).await?);
+    }
+
+    log::info!("Note: each query will return a maximum of 500 logs, if logs are missing reduce the time range");
+    let enclave_uuid = resolve_enclave_uuid(log_args.enclave_uuid.clone(), &log_args.config)?;
+    Ok(fetch_logs(log_args, enclave_uuid, enclave_client).await?)
+}
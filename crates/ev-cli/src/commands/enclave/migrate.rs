@@ -1,5 +1,8 @@
 use clap::Parser;
-use ev_enclave::migrate::migrate_toml;
+use ev_cli_derive::CliMessage;
+use ev_enclave::migrate::{migrate_toml, MigrateError as EnclaveMigrateError};
+use thiserror::Error;
+
 /// Migrate an Enclave toml from v0 to v1
 #[derive(Parser, Debug)]
 #[command(name = "migrate", about)]
@@ -13,26 +16,34 @@ pub struct MigrateArgs {
     pub output: Option<String>,
 }
 
-pub async fn run(args: MigrateArgs) -> exitcode::ExitCode {
-    let serialized_config = match migrate_toml(&args.config) {
-        Ok(bytes) => bytes,
-        Err(e) => {
-            log::error!("{}", e);
-            return exitcode::SOFTWARE;
-        }
-    };
+#[derive(Debug, Error, CliMessage)]
+pub enum MigrateError {
+    #[error("{0}")]
+    #[cli(code = "enclaves/migrate-error", exitcode = exitcode::SOFTWARE)]
+    Migrate(#[from] EnclaveMigrateError),
+    #[error("Error writing enclave.toml — {0}")]
+    #[cli(code = "generic/io-error", exitcode = exitcode::IOERR)]
+    Write(std::io::Error),
+}
+
+#[derive(Debug, strum_macros::Display, CliMessage)]
+#[cli(code = "generic/success", exitcode = exitcode::OK)]
+pub enum MigrateMessage {
+    #[strum(
+        to_string = "Enclave.toml migrated successfully. You can now deploy a V1 Enclave using the deploy command"
+    )]
+    Migrated,
+}
+
+pub async fn run(args: MigrateArgs) -> Result<MigrateMessage, MigrateError> {
+    let serialized_config = migrate_toml(&args.config)?;
 
     let output_path = match args.output {
         Some(path) => path,
         None => args.config.clone(),
     };
-    if let Err(e) = std::fs::write(&output_path, serialized_config) {
-        log::error!("Error writing enclave.toml — {}", e);
-        exitcode::IOERR
-    } else {
-        log::info!("Enclave.toml migrated successfully. You can now deploy a V1 Enclave using the deploy command");
-        exitcode::OK
-    }
+    std::fs::write(&output_path, serialized_config).map_err(MigrateError::Write)?;
+    Ok(MigrateMessage::Migrated)
 }
 
 #[cfg(test)]
@@ -51,7 +62,7 @@ mod migrate_tests {
             config: "../../fixtures/v0.cage.toml".into(),
             output: Some(output_file.to_str().unwrap().to_string()),
         };
-        run(args).await;
+        run(args).await.unwrap();
         assert!(output_file.exists());
         let config_content = String::from_utf8(read(output_file).unwrap()).unwrap();
         let expected_config_content = r#"version = 1
@@ -1,19 +1,41 @@
+use crate::run_cmd;
 use clap::Parser;
 use common::api::BasicAuth;
 #[cfg(not(target_os = "windows"))]
 pub mod attest;
 pub mod build;
 pub mod cert;
+pub mod config;
+pub mod debug;
 pub mod delete;
 pub mod deploy;
+pub mod deployments;
 pub mod describe;
+pub mod diagnose;
+pub mod dockerfile;
+pub mod domains;
 pub mod env;
 pub mod init;
 pub mod list;
 pub mod logs;
 pub mod migrate;
+pub mod nitro;
+pub mod pcrs;
+pub mod prune;
+pub mod rename;
 pub mod restart;
+pub mod run_job;
 pub mod scale;
+pub mod ship;
+pub mod sizes;
+pub mod smoke;
+pub mod snippets;
+pub mod stats;
+pub mod status;
+pub mod test;
+#[cfg(not(target_os = "windows"))]
+pub mod trust;
+pub mod which;
 
 #[derive(Parser, Debug)]
 #[command(name = "enclave")]
@@ -28,35 +50,83 @@ pub enum EnclaveCommand {
     Attest(attest::AttestArgs),
     Build(build::BuildArgs),
     Describe(describe::DescribeArgs),
+    Diagnose(diagnose::DiagnoseArgs),
+    Dockerfile(dockerfile::DockerfileArgs),
+    Domains(domains::DomainsArgs),
     Migrate(migrate::MigrateArgs),
+    Nitro(nitro::NitroArgs),
     Cert(cert::CertArgs),
+    Config(config::ConfigArgs),
+    Debug(debug::DebugArgs),
     Delete(delete::DeleteArgs),
-    Deploy(deploy::DeployArgs),
+    Deploy(Box<deploy::DeployArgs>),
+    Deployments(deployments::DeploymentsArgs),
     Init(init::InitArgs),
     List(list::List),
     Logs(logs::LogArgs),
+    Pcrs(pcrs::PcrsArgs),
+    Prune(prune::PruneArgs),
+    Rename(rename::RenameArgs),
     Restart(restart::RestartArgs),
+    RunJob(run_job::RunJobArgs),
     Scale(scale::ScaleArgs),
+    Ship(Box<ship::ShipArgs>),
+    Sizes(sizes::SizesArgs),
+    Smoke(smoke::SmokeArgs),
+    Snippets(snippets::SnippetsArgs),
+    Stats(stats::StatsArgs),
+    Status(status::StatusArgs),
+    Test(test::TestArgs),
+    #[cfg(not(target_os = "windows"))]
+    Trust(trust::TrustArgs),
     Env(env::EnvArgs),
+    Which(which::WhichArgs),
 }
 
 pub async fn run(enclave_args: EnclaveArgs, auth: BasicAuth) {
-    let exitcode = match enclave_args.action {
+    match enclave_args.action {
         #[cfg(not(target_os = "windows"))]
-        EnclaveCommand::Attest(attest_args) => attest::run(attest_args, auth).await,
-        EnclaveCommand::Build(build_args) => build::run(build_args).await,
-        EnclaveCommand::Describe(describe_args) => describe::run(describe_args).await,
-        EnclaveCommand::Migrate(migrate_args) => migrate::run(migrate_args).await,
-        EnclaveCommand::Cert(cert_args) => cert::run(cert_args, auth).await,
-        EnclaveCommand::Delete(delete_args) => delete::run(delete_args, auth).await,
-        EnclaveCommand::Deploy(deploy_args) => deploy::run(deploy_args, auth).await,
-        EnclaveCommand::Init(init_args) => init::run(init_args, auth).await,
-        EnclaveCommand::List(list_args) => list::run(list_args, auth).await,
-        EnclaveCommand::Logs(log_args) => logs::run(log_args, auth).await,
-        EnclaveCommand::Restart(restart_args) => restart::run(restart_args, auth).await,
-        EnclaveCommand::Scale(scale_args) => scale::run(scale_args, auth).await,
-        EnclaveCommand::Env(env_args) => env::run(env_args, auth).await,
-    };
-
-    std::process::exit(exitcode);
+        EnclaveCommand::Attest(attest_args) => run_cmd(attest::run(attest_args, auth).await),
+        EnclaveCommand::Build(build_args) => run_cmd(build::run(build_args).await),
+        EnclaveCommand::Describe(describe_args) => {
+            run_cmd(describe::run(describe_args, auth).await)
+        }
+        EnclaveCommand::Diagnose(diagnose_args) => run_cmd(diagnose::run(diagnose_args)),
+        EnclaveCommand::Dockerfile(dockerfile_args) => {
+            run_cmd(dockerfile::run(dockerfile_args).await)
+        }
+        EnclaveCommand::Domains(domains_args) => run_cmd(domains::run(domains_args, auth).await),
+        EnclaveCommand::Migrate(migrate_args) => run_cmd(migrate::run(migrate_args).await),
+        EnclaveCommand::Nitro(nitro_args) => run_cmd(nitro::run(&nitro_args)),
+        EnclaveCommand::Cert(cert_args) => run_cmd(cert::run(cert_args, auth).await),
+        EnclaveCommand::Config(config_args) => run_cmd(config::run(config_args)),
+        EnclaveCommand::Debug(debug_args) => run_cmd(debug::run(&debug_args)),
+        EnclaveCommand::Delete(delete_args) => run_cmd(delete::run(delete_args, auth).await),
+        EnclaveCommand::Deploy(deploy_args) => run_cmd(deploy::run(*deploy_args, auth).await),
+        EnclaveCommand::Deployments(deployments_args) => {
+            run_cmd(deployments::run(deployments_args, auth).await)
+        }
+        EnclaveCommand::Init(init_args) => run_cmd(init::run(init_args, auth).await),
+        EnclaveCommand::List(list_args) => run_cmd(list::run(list_args, auth).await),
+        EnclaveCommand::Logs(log_args) => run_cmd(logs::run(log_args, auth).await),
+        EnclaveCommand::Pcrs(pcrs_args) => run_cmd(pcrs::run(pcrs_args, auth).await),
+        EnclaveCommand::Prune(prune_args) => run_cmd(prune::run(prune_args, auth).await),
+        EnclaveCommand::Rename(rename_args) => run_cmd(rename::run(rename_args, auth).await),
+        EnclaveCommand::Restart(restart_args) => run_cmd(restart::run(restart_args, auth).await),
+        EnclaveCommand::RunJob(run_job_args) => run_cmd(run_job::run(run_job_args, auth).await),
+        EnclaveCommand::Scale(scale_args) => run_cmd(scale::run(scale_args, auth).await),
+        EnclaveCommand::Ship(ship_args) => run_cmd(ship::run(*ship_args, auth).await),
+        EnclaveCommand::Sizes(sizes_args) => run_cmd(sizes::run(sizes_args, auth).await),
+        EnclaveCommand::Smoke(smoke_args) => run_cmd(smoke::run(smoke_args, auth).await),
+        EnclaveCommand::Snippets(snippets_args) => {
+            run_cmd(snippets::run(snippets_args, auth).await)
+        }
+        EnclaveCommand::Stats(stats_args) => run_cmd(stats::run(stats_args, auth).await),
+        EnclaveCommand::Status(status_args) => run_cmd(status::run(status_args, auth).await),
+        EnclaveCommand::Test(test_args) => run_cmd(test::run(test_args).await),
+        #[cfg(not(target_os = "windows"))]
+        EnclaveCommand::Trust(trust_args) => run_cmd(trust::run(trust_args).await),
+        EnclaveCommand::Env(env_args) => run_cmd(env::run(env_args, auth).await),
+        EnclaveCommand::Which(which_args) => run_cmd(which::run(&which_args)),
+    }
 }
@@ -0,0 +1,97 @@
+use clap::Parser;
+use ev_cli_derive::CliMessage;
+use ev_enclave::enclave::{NitroCliImage, NitroCliImageError};
+use ev_enclave::nitro::{run_nitro_cli, NitroError as EnclaveNitroError};
+use thiserror::Error;
+
+use crate::BaseArgs;
+
+/// Run nitro-cli commands the CLI doesn't wrap in the CLI's managed Nitro CLI image, without installing nitro-cli locally
+#[derive(Debug, Parser)]
+#[command(name = "nitro", about)]
+pub struct NitroArgs {
+    /// Directory to mount as nitro-cli's working directory, so relative paths in its arguments resolve against it
+    #[arg(
+        short = 'o',
+        long = "output",
+        default_value = ".",
+        env = "EV_OUTPUT_DIR"
+    )]
+    pub output_dir: String,
+
+    /// A prebuilt image with nitro-cli on its path to run, optionally pinned with @sha256:<digest>
+    #[arg(long = "nitro-cli-image", env = "EV_NITRO_CLI_IMAGE")]
+    pub nitro_cli_image: Option<String>,
+
+    /// Version of the Nitro CLI to run
+    #[arg(long = "nitro-cli-version", env = "EV_NITRO_CLI_VERSION")]
+    pub nitro_cli_version: Option<String>,
+
+    /// Disables the use of cache when building the Nitro CLI image
+    #[arg(long = "no-cache")]
+    pub no_cache: bool,
+
+    /// The nitro-cli arguments, given after --, such as `-- describe-eif --eif-path enclave.eif`
+    #[arg(last = true, required = true)]
+    pub nitro_cli_args: Vec<String>,
+}
+
+#[derive(Debug, Error, CliMessage)]
+pub enum NitroError {
+    #[error("{0}")]
+    #[cli(code = "enclaves/nitro-cli-image-error")]
+    NitroCliImage(
+        #[from]
+        #[cli(exitcode)]
+        NitroCliImageError,
+    ),
+    #[error("{0}")]
+    #[cli(code = "enclaves/nitro-error")]
+    Nitro(
+        #[from]
+        #[cli(exitcode)]
+        EnclaveNitroError,
+    ),
+    // nitro-cli's own exit code is passed on, so scripts can branch on it
+    #[error("nitro-cli exited with code {exit_code}{output}")]
+    #[cli(code = "enclaves/nitro-cli-failed", exitcode = *exit_code)]
+    Failed {
+        exit_code: exitcode::ExitCode,
+        output: String,
+    },
+}
+
+#[derive(Debug, strum_macros::Display, CliMessage)]
+#[cli(code = "generic/success", exitcode = exitcode::OK)]
+pub enum NitroMessage {
+    #[strum(to_string = "{0}")]
+    Output(String),
+}
+
+pub fn run(nitro_args: &NitroArgs) -> Result<NitroMessage, NitroError> {
+    let nitro_cli = NitroCliImage::new(
+        nitro_args.nitro_cli_image.as_deref(),
+        nitro_args.nitro_cli_version.as_deref(),
+    )?;
+    let output = run_nitro_cli(
+        &nitro_args.output_dir,
+        &nitro_cli,
+        &nitro_args.nitro_cli_args,
+        BaseArgs::parse().verbose,
+        nitro_args.no_cache,
+    )?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout)
+        .trim_end()
+        .to_string();
+    if output.status.success() {
+        return Ok(NitroMessage::Output(stdout));
+    }
+    Err(NitroError::Failed {
+        exit_code: output.status.code().unwrap_or(exitcode::SOFTWARE),
+        output: match stdout.is_empty() {
+            true => stdout,
+            false => format!("\n{stdout}"),
+        },
+    })
+}
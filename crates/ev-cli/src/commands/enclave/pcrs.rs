@@ -0,0 +1,95 @@
+use clap::{Parser, Subcommand};
+use common::api::BasicAuth;
+use ev_cli_derive::CliMessage;
+use ev_enclave::pcrs::{pull_pcrs, PcrsError as EnclavePcrsError, PulledPcrs};
+use evervault_api_client::enclave::EnclaveClient;
+use thiserror::Error;
+
+use crate::BaseArgs;
+
+/// Manage the PCRs pinned in the attestation section of the enclave.toml
+#[derive(Debug, Parser)]
+#[command(name = "pcrs", about)]
+pub struct PcrsArgs {
+    #[command(subcommand)]
+    action: PcrsCommands,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum PcrsCommands {
+    /// Write the PCRs of the Enclave's latest deployment, and their signature when it was deployed with one, into the enclave.toml
+    #[command()]
+    Pull(PullPcrsArgs),
+}
+
+#[derive(Debug, Parser)]
+#[command(name = "pull", about)]
+pub struct PullPcrsArgs {
+    /// Path to enclave.toml config file
+    #[arg(
+        short = 'c',
+        long = "config",
+        default_value = "./enclave.toml",
+        env = "EV_ENCLAVE_CONFIG"
+    )]
+    pub config: String,
+
+    /// Path to write the updated Enclave config to. Defaults to the config path, unless the config is read from stdin with `--config -`.
+    #[arg(long = "config-output", env = "EV_CONFIG_OUTPUT")]
+    pub config_output: Option<String>,
+}
+
+#[derive(Debug, Error, CliMessage)]
+pub enum PcrsError {
+    #[error("Failed to pull PCRs — {0}")]
+    #[cli(code = "enclaves/pcrs-error")]
+    Pull(
+        #[from]
+        #[cli(exitcode)]
+        EnclavePcrsError,
+    ),
+}
+
+#[derive(Debug, strum_macros::Display, CliMessage)]
+#[cli(code = "generic/success", exitcode = exitcode::OK)]
+pub enum PcrsMessage {
+    #[strum(
+        to_string = "Attestation section updated with the PCRs of Enclave {uuid}'s latest deployment"
+    )]
+    Updated {
+        uuid: String,
+        #[cli(data)]
+        pulled: Option<PulledPcrs>,
+    },
+    #[strum(to_string = "Attestation section already matches Enclave {uuid}'s latest deployment")]
+    Unchanged {
+        uuid: String,
+        #[cli(data)]
+        pulled: Option<PulledPcrs>,
+    },
+}
+
+pub async fn run(pcrs_args: PcrsArgs, (_, api_key): BasicAuth) -> Result<PcrsMessage, PcrsError> {
+    let PcrsCommands::Pull(pull_args) = pcrs_args.action;
+    let enclave_api = EnclaveClient::new(crate::auth::api_auth_mode(api_key));
+    let pulled = pull_pcrs(
+        &pull_args.config,
+        pull_args
+            .config_output
+            .as_deref()
+            .unwrap_or(&pull_args.config),
+        &enclave_api,
+    )
+    .await?;
+
+    if pulled.measurements.signature().is_none() {
+        log::info!("The latest deployment wasn't signed, so no PCR signature was written");
+    }
+    let uuid = pulled.uuid.clone();
+    let changed = pulled.changed;
+    let pulled = BaseArgs::parse().json.then_some(pulled);
+    match changed {
+        true => Ok(PcrsMessage::Updated { uuid, pulled }),
+        false => Ok(PcrsMessage::Unchanged { uuid, pulled }),
+    }
+}
@@ -0,0 +1,52 @@
+use clap::Parser;
+use common::api::BasicAuth;
+use ev_enclave::delete::{parse_age, EnclaveSelector};
+use evervault_api_client::enclave::EnclaveState;
+
+use super::delete::{delete_in_bulk, BulkDeleteOptions, DeleteError, DeleteMessage};
+
+/// Delete Enclaves in bulk by state and age, e.g. to clean up after CI runs
+#[derive(Debug, Parser)]
+#[command(name = "prune", about)]
+pub struct PruneArgs {
+    /// Comma separated list of Enclave states to prune
+    #[arg(long = "status", value_enum, value_delimiter = ',', required = true)]
+    pub states: Vec<EnclaveState>,
+
+    /// Only prune Enclaves which haven't been updated within this period, e.g. 12h, 30d or 2w
+    #[arg(long = "older-than", value_parser = parse_age)]
+    pub older_than: Option<chrono::Duration>,
+
+    /// Only prune Enclaves whose names start with this prefix
+    #[arg(long = "name-prefix")]
+    pub name_prefix: Option<String>,
+
+    /// List the Enclaves which would be pruned, without deleting them
+    #[arg(long = "dry-run")]
+    pub dry_run: bool,
+
+    /// Prevent confirmation dialogue and proceed with deletion. Use with caution.
+    #[arg(long)]
+    pub force: bool,
+
+    /// Append a record of each deletion to this JSON lines file
+    #[arg(long = "audit-log", env = "EV_AUDIT_LOG")]
+    pub audit_log: Option<String>,
+}
+
+pub async fn run(
+    prune_args: PruneArgs,
+    (_, api_key): BasicAuth,
+) -> Result<DeleteMessage, DeleteError> {
+    let selector = EnclaveSelector {
+        name_prefix: prune_args.name_prefix,
+        states: prune_args.states,
+        older_than: prune_args.older_than,
+    };
+    let options = BulkDeleteOptions {
+        dry_run: prune_args.dry_run,
+        force: prune_args.force,
+        audit_log: prune_args.audit_log.as_deref(),
+    };
+    delete_in_bulk(&api_key, &selector, options).await
+}
@@ -0,0 +1,115 @@
+use crate::completion::enclave_uuid_completer;
+use crate::BaseArgs;
+use clap::Parser;
+use common::api::BasicAuth;
+use ev_cli_derive::CliMessage;
+use ev_enclave::prompt::{self, PromptError};
+use ev_enclave::rename::{
+    rename_enclave, validate_enclave_name, RenameError as EnclaveRenameError, RenamedEnclave,
+};
+use evervault_api_client::enclave::EnclaveClient;
+use thiserror::Error;
+
+/// Rename an Enclave, updating its domain and the name in the enclave.toml
+#[derive(Debug, Parser)]
+#[command(name = "rename", about)]
+pub struct RenameArgs {
+    /// Path to enclave.toml config file
+    #[arg(
+        short = 'c',
+        long = "config",
+        default_value = "./enclave.toml",
+        env = "EV_ENCLAVE_CONFIG"
+    )]
+    pub config: String,
+
+    /// Uuid of the Enclave to rename
+    #[arg(long = "enclave-uuid", env = "EV_ENCLAVE_UUID", add = enclave_uuid_completer())]
+    pub enclave_uuid: Option<String>,
+
+    /// The new name for the Enclave
+    #[arg(long = "name")]
+    pub name: String,
+
+    /// Prevent confirmation dialogue and proceed with the rename
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(Debug, Error, CliMessage)]
+pub enum RenameError {
+    #[error("{0}")]
+    #[cli(code = "enclaves/invalid-name")]
+    InvalidName(#[cli(exitcode)] EnclaveRenameError),
+    #[error("An error occurred while attempting to confirm this Enclave rename — {0}")]
+    #[cli(code = "enclaves/prompt-error")]
+    Prompt(
+        #[from]
+        #[cli(exitcode)]
+        PromptError,
+    ),
+    #[error("Failed to rename Enclave — {0}")]
+    #[cli(code = "enclaves/rename-error")]
+    Rename(#[cli(exitcode)] EnclaveRenameError),
+}
+
+#[derive(Debug, strum_macros::Display, CliMessage)]
+#[cli(code = "generic/success", exitcode = exitcode::OK)]
+pub enum RenameMessage {
+    #[strum(to_string = "Enclave {previous_name} renamed to {name}. It's now served at {domain}")]
+    Renamed {
+        previous_name: String,
+        name: String,
+        domain: String,
+        #[cli(data)]
+        renamed: Option<RenamedEnclave>,
+    },
+    #[strum(to_string = "Phew! Exiting early...")]
+    #[cli(code = "generic/cancelled")]
+    Cancelled,
+}
+
+pub async fn run(
+    rename_args: RenameArgs,
+    (_, api_key): BasicAuth,
+) -> Result<RenameMessage, RenameError> {
+    // Reject invalid names before asking to confirm a rename which can't succeed
+    validate_enclave_name(&rename_args.name).map_err(RenameError::InvalidName)?;
+
+    if !rename_args.force {
+        let prompt_text = "Renaming an Enclave changes its domain, and requests to the old domain will stop reaching it. Are you sure you want to rename this Enclave?";
+        if !prompt::confirm(prompt_text, false)? {
+            return Ok(RenameMessage::Cancelled);
+        }
+    }
+
+    let enclave_api = EnclaveClient::new(crate::auth::api_auth_mode(api_key));
+    let renamed = rename_enclave(
+        &rename_args.config,
+        rename_args.enclave_uuid.as_deref(),
+        &rename_args.name,
+        &enclave_api,
+    )
+    .await
+    .map_err(RenameError::Rename)?;
+
+    log::warn!(
+        "{} will stop resolving. Update any clients, DNS records or exported attestation configs which still use it.",
+        renamed.previous_domain
+    );
+    if renamed.config_updated {
+        log::info!("Updated the Enclave name in {}", rename_args.config);
+    } else {
+        log::warn!(
+            "{} isn't linked to this Enclave, so it wasn't updated. Set name = \"{}\" in the Enclave's enclave.toml before deploying it again.",
+            rename_args.config,
+            renamed.name
+        );
+    }
+    Ok(RenameMessage::Renamed {
+        previous_name: renamed.previous_name.clone(),
+        name: renamed.name.clone(),
+        domain: renamed.domain.clone(),
+        renamed: BaseArgs::parse().json.then_some(renamed),
+    })
+}
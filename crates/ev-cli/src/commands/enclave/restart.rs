@@ -1,25 +1,30 @@
+use crate::completion::enclave_uuid_completer;
 use clap::Parser;
-use common::{
-    api::{AuthMode, BasicAuth},
-    CliError,
-};
+use common::api::BasicAuth;
+use ev_cli_derive::CliMessage;
 use ev_enclave::{
-    api::enclave::EnclaveClient,
-    deploy::{timed_operation, watch_deployment, DEPLOY_WATCH_TIMEOUT_SECONDS},
+    deploy::{timed_operation, watch_deployment, DeployError, DEPLOY_WATCH_TIMEOUT_SECONDS},
     progress::get_tracker,
-    restart::restart_enclave,
+    restart::{restart_enclave, RestartError as EnclaveRestartError},
 };
+use evervault_api_client::enclave::EnclaveClient;
+use thiserror::Error;
 
 /// Restart the Enclave deployment
 #[derive(Debug, Parser)]
 #[command(name = "restart", about)]
 pub struct RestartArgs {
     /// Path to enclave.toml config file
-    #[arg(short = 'c', long = "config", default_value = "./enclave.toml")]
+    #[arg(
+        short = 'c',
+        long = "config",
+        default_value = "./enclave.toml",
+        env = "EV_ENCLAVE_CONFIG"
+    )]
     pub config: String,
 
     /// Uuid of the Enclave who's deployment to restart
-    #[arg(long = "enclave-uuid")]
+    #[arg(long = "enclave-uuid", env = "EV_ENCLAVE_UUID", add = enclave_uuid_completer())]
     pub enclave_uuid: Option<String>,
 
     /// Perform the Enclave restart in the background
@@ -27,29 +32,51 @@ pub struct RestartArgs {
     pub background: bool,
 }
 
-pub async fn run(restart_args: RestartArgs, (_, api_key): BasicAuth) -> i32 {
-    let enclave_api = EnclaveClient::new(AuthMode::ApiKey(api_key.to_string()));
+#[derive(Debug, Error, CliMessage)]
+pub enum RestartError {
+    #[error("{0}")]
+    #[cli(code = "enclaves/restart-error")]
+    Restart(
+        #[from]
+        #[cli(exitcode)]
+        EnclaveRestartError,
+    ),
+    #[error("{0}")]
+    #[cli(code = "enclaves/deploy-error")]
+    Deployment(
+        #[from]
+        #[cli(exitcode)]
+        DeployError,
+    ),
+}
+
+#[derive(Debug, strum_macros::Display, CliMessage)]
+#[cli(code = "generic/success", exitcode = exitcode::OK)]
+pub enum RestartMessage {
+    #[strum(
+        to_string = "Enclave restarting. You can observe the restart progress in the Enclaves Dashboard"
+    )]
+    Restarting,
+    #[strum(to_string = "Enclave restarted successfully")]
+    Restarted,
+}
+
+pub async fn run(
+    restart_args: RestartArgs,
+    (_, api_key): BasicAuth,
+) -> Result<RestartMessage, RestartError> {
+    let enclave_api = EnclaveClient::new(crate::auth::api_auth_mode(api_key.to_string()));
 
-    let new_deployment = match restart_enclave(
+    let new_deployment = restart_enclave(
         restart_args.config.as_str(),
         restart_args.enclave_uuid.as_deref(),
         &enclave_api,
         restart_args.background,
     )
-    .await
-    {
-        Ok(depl) => depl,
-        Err(e) => {
-            log::error!("{}", e);
-            return e.exitcode();
-        }
-    };
+    .await?;
 
     if restart_args.background {
-        println!(
-            "Enclave restarting. You can observe the restart progress in the Enclaves Dashboard"
-        );
-        return exitcode::OK;
+        return Ok(RestartMessage::Restarting);
     }
 
     let progress_bar = get_tracker(
@@ -57,7 +84,7 @@ pub async fn run(restart_args: RestartArgs, (_, api_key): BasicAuth) -> i32 {
         None,
     );
 
-    match timed_operation(
+    timed_operation(
         "Enclave Deployment",
         DEPLOY_WATCH_TIMEOUT_SECONDS,
         watch_deployment(
@@ -67,12 +94,6 @@ pub async fn run(restart_args: RestartArgs, (_, api_key): BasicAuth) -> i32 {
             progress_bar,
         ),
     )
-    .await
-    {
-        Ok(_) => exitcode::OK,
-        Err(e) => {
-            log::error!("{}", e);
-            e.exitcode()
-        }
-    }
+    .await??;
+    Ok(RestartMessage::Restarted)
 }
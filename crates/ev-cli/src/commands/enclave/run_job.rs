@@ -0,0 +1,94 @@
+use crate::completion::enclave_uuid_completer;
+use crate::BaseArgs;
+use clap::Parser;
+use common::api::BasicAuth;
+use ev_cli_derive::CliMessage;
+use ev_enclave::job::{run_job, watch_job, JobError};
+use ev_enclave::progress::get_tracker;
+use evervault_api_client::enclave::{EnclaveClient, JobExecution};
+use thiserror::Error;
+
+/// Start an execution of a job Enclave, which runs until its process exits
+#[derive(Debug, Parser)]
+#[command(name = "run-job", about)]
+pub struct RunJobArgs {
+    /// Path to enclave.toml config file
+    #[arg(
+        short = 'c',
+        long = "config",
+        default_value = "./enclave.toml",
+        env = "EV_ENCLAVE_CONFIG"
+    )]
+    pub config: String,
+
+    /// Uuid of the job Enclave to run
+    #[arg(long = "enclave-uuid", env = "EV_ENCLAVE_UUID", add = enclave_uuid_completer())]
+    pub enclave_uuid: Option<String>,
+
+    /// Wait for the job to exit, failing if it exits with a non-zero code
+    #[arg(long)]
+    pub wait: bool,
+}
+
+#[derive(Debug, Error, CliMessage)]
+pub enum RunJobError {
+    #[error("Failed to start job — {0}")]
+    #[cli(code = "enclaves/job-error")]
+    Start(#[cli(exitcode)] JobError),
+    #[error("{0}")]
+    #[cli(code = "enclaves/job-error")]
+    Watch(#[cli(exitcode)] JobError),
+}
+
+#[derive(Debug, strum_macros::Display, CliMessage)]
+#[cli(code = "generic/success", exitcode = exitcode::OK)]
+pub enum RunJobMessage {
+    #[strum(to_string = "Job {uuid} started. Follow its output with ev enclave logs.")]
+    Started {
+        uuid: String,
+        #[cli(data)]
+        execution: Option<JobExecution>,
+    },
+    #[strum(to_string = "Job {uuid} finished")]
+    Finished {
+        uuid: String,
+        #[cli(data)]
+        execution: Option<JobExecution>,
+    },
+}
+
+pub async fn run(
+    run_job_args: RunJobArgs,
+    (_, api_key): BasicAuth,
+) -> Result<RunJobMessage, RunJobError> {
+    let enclave_api = EnclaveClient::new(crate::auth::api_auth_mode(api_key));
+
+    let execution = run_job(
+        &run_job_args.config,
+        run_job_args.enclave_uuid.as_deref(),
+        &enclave_api,
+    )
+    .await
+    .map_err(RunJobError::Start)?;
+
+    if !run_job_args.wait {
+        return Ok(RunJobMessage::Started {
+            uuid: execution.uuid.clone(),
+            execution: BaseArgs::parse().json.then_some(execution),
+        });
+    }
+
+    let progress_bar = get_tracker("Running job...", None);
+    let execution = watch_job(
+        enclave_api,
+        &execution.enclave_uuid,
+        &execution.uuid,
+        progress_bar,
+    )
+    .await
+    .map_err(RunJobError::Watch)?;
+    Ok(RunJobMessage::Finished {
+        uuid: execution.uuid.clone(),
+        execution: BaseArgs::parse().json.then_some(execution),
+    })
+}
@@ -1,33 +1,42 @@
+use crate::completion::enclave_uuid_completer;
 use clap::Parser;
-use common::{
-    api::{AuthMode, BasicAuth},
-    CliError,
-};
+use common::api::{client::ApiError, BasicAuth};
+use ev_cli_derive::CliMessage;
 use ev_enclave::{
-    api::enclave::{EnclaveApi, EnclaveClient},
     config::EnclaveConfig,
     config::{self, ScalingSettings},
+    state::update_cached_enclave,
 };
+use evervault_api_client::enclave::{EnclaveApi, EnclaveClient, EnclaveScalingConfig};
 use thiserror::Error;
 
-#[derive(Debug, Error)]
+#[derive(Debug, Error, CliMessage)]
 pub enum ScaleError {
     #[error("No Enclave Uuid given. You can provide one by using either the --enclave-uuid flag, or using the --config flag to point to an Enclave.toml")]
+    #[cli(code = "enclaves/config-error", exitcode = exitcode::CONFIG)]
     MissingUuid,
     #[error("An error occurred parsing the Enclave config - {0}")]
-    ConfigError(#[from] config::EnclaveConfigError),
-    #[error("An error occurred contacting the API — {0}")]
-    ApiError(#[from] common::api::client::ApiError),
+    #[cli(code = "enclaves/config-error")]
+    ConfigError(
+        #[from]
+        #[cli(exitcode)]
+        config::EnclaveConfigError,
+    ),
+    #[error("Failed to read the scaling config for {0} - {1}")]
+    #[cli(code = "enclaves/api-error")]
+    ReadScalingConfig(String, #[cli(exitcode)] ApiError),
+    #[error("Failed to update the scaling config for {0} - {1}")]
+    #[cli(code = "enclaves/api-error")]
+    UpdateScalingConfig(String, #[cli(exitcode)] ApiError),
 }
 
-impl CliError for ScaleError {
-    fn exitcode(&self) -> exitcode::ExitCode {
-        match self {
-            Self::MissingUuid => exitcode::CONFIG,
-            Self::ConfigError(inner) => inner.exitcode(),
-            Self::ApiError(inner) => inner.exitcode(),
-        }
-    }
+#[derive(Debug, strum_macros::Display, CliMessage)]
+#[cli(code = "generic/success", exitcode = exitcode::OK)]
+pub enum ScaleMessage {
+    #[strum(to_string = "Enclave scaling config updated successfully")]
+    Updated(#[cli(data)] EnclaveScalingConfig),
+    #[strum(to_string = "Retrieved the Enclave's scaling config")]
+    Retrieved(#[cli(data)] EnclaveScalingConfig),
 }
 
 /// Update your Enclave's Scaling config
@@ -35,11 +44,16 @@ impl CliError for ScaleError {
 #[command(name = "scale", about)]
 pub struct ScaleArgs {
     /// Path to enclave.toml config file
-    #[arg(short = 'c', long = "config", default_value = "./enclave.toml")]
+    #[arg(
+        short = 'c',
+        long = "config",
+        default_value = "./enclave.toml",
+        env = "EV_ENCLAVE_CONFIG"
+    )]
     pub config: String,
 
     /// Uuid of the Enclave to scale
-    #[arg(long = "enclave-uuid")]
+    #[arg(long = "enclave-uuid", env = "EV_ENCLAVE_UUID", add = enclave_uuid_completer())]
     pub enclave_uuid: Option<String>,
 
     /// Number of replicas to run for this Enclave. If unset, the command will read the current scaling config from the Evervault API.
@@ -51,57 +65,30 @@ pub struct ScaleArgs {
     pub sync: bool,
 }
 
-pub async fn run(args: ScaleArgs, (_, api_key): BasicAuth) -> i32 {
-    let enclave_api = EnclaveClient::new(AuthMode::ApiKey(api_key.to_string()));
+pub async fn run(args: ScaleArgs, (_, api_key): BasicAuth) -> Result<ScaleMessage, ScaleError> {
+    let enclave_api = EnclaveClient::new(crate::auth::api_auth_mode(api_key.to_string()));
 
     let enclave_config = EnclaveConfig::try_from_filepath(&args.config);
-    let enclave_uuid = match args.enclave_uuid.as_deref() {
-        Some(enclave_uuid) => Ok(enclave_uuid),
-        None => match enclave_config.as_ref() {
-            Ok(enclave_config) => enclave_config
-                .uuid
-                .as_deref()
-                .ok_or(ScaleError::MissingUuid),
-            Err(e) => {
-                log::error!("Failed to resolve Enclave config - {e:?}");
-                return e.exitcode();
-            }
+    let enclave_uuid = match args.enclave_uuid.clone() {
+        Some(enclave_uuid) => enclave_uuid,
+        None => match enclave_config {
+            Ok(ref enclave_config) => enclave_config.uuid.clone().ok_or(ScaleError::MissingUuid)?,
+            Err(e) => return Err(e.into()),
         },
     };
 
-    let enclave_uuid = match enclave_uuid {
-        Ok(enclave_uuid) => enclave_uuid,
-        Err(e) => {
-            log::error!("{e:?}");
-            return e.exitcode();
-        }
-    };
-
-    let scaling_config_result = match args.desired_replicas {
+    let scaling_config = match args.desired_replicas {
         Some(new_desired_replicas) => {
             log::info!("Updating desired replicas to {new_desired_replicas}");
             enclave_api
-                .update_scaling_config(enclave_uuid, new_desired_replicas.into())
+                .update_scaling_config(&enclave_uuid, new_desired_replicas.into())
                 .await
+                .map_err(|e| ScaleError::UpdateScalingConfig(enclave_uuid.clone(), e))?
         }
-        None => enclave_api.get_scaling_config(enclave_uuid).await,
-    };
-
-    let scaling_config = match scaling_config_result {
-        Ok(result) if args.desired_replicas.is_some() => {
-            log::info!("Enclave scaling config updated successfully");
-            result
-        }
-        Ok(result) => result,
-        Err(e) => {
-            let action = if args.desired_replicas.is_some() {
-                "update"
-            } else {
-                "read"
-            };
-            log::error!("Failed to {action} the scaling config for {enclave_uuid} - {e:?}");
-            return e.exitcode();
-        }
+        None => enclave_api
+            .get_scaling_config(&enclave_uuid)
+            .await
+            .map_err(|e| ScaleError::ReadScalingConfig(enclave_uuid.clone(), e))?,
     };
 
     if let Ok(mut config) = enclave_config {
@@ -120,18 +107,15 @@ pub async fn run(args: ScaleArgs, (_, api_key): BasicAuth) -> i32 {
         }
     }
 
-    if atty::is(atty::Stream::Stdout) {
-        println!(
-            "{}",
-            serde_json::to_string_pretty(&scaling_config)
-                .expect("Failed to serialize scaling config")
-        );
-    } else {
-        println!(
-            "{}",
-            serde_json::to_string(&scaling_config).expect("Failed to serialize scaling config")
-        );
+    let desired_replicas = scaling_config.desired_replicas();
+    if let Err(e) = update_cached_enclave(&args.config, &enclave_uuid, |enclave| {
+        enclave.desired_replicas = Some(desired_replicas)
+    }) {
+        log::debug!("{e}");
     }
 
-    exitcode::OK
+    match args.desired_replicas {
+        Some(_) => Ok(ScaleMessage::Updated(scaling_config)),
+        None => Ok(ScaleMessage::Retrieved(scaling_config)),
+    }
 }
@@ -0,0 +1,175 @@
+use clap::Parser;
+use common::api::BasicAuth;
+use ev_cli_derive::CliMessage;
+use ev_enclave::{
+    docker::remote::RemoteBuilderError, enclave::EIFMeasurements, version::get_runtime_versions,
+};
+use thiserror::Error;
+
+use super::deploy::{deploy_and_verify, DeployArgs, DeployError, DeployPreparation};
+use crate::tty::outputs_json;
+use crate::{errors, BaseArgs, CmdOutput};
+use common::CliError;
+
+/// Build, deploy and attest an Enclave in a single step
+#[derive(Debug, Parser)]
+#[command(name = "ship", about)]
+pub struct ShipArgs {
+    #[command(flatten)]
+    pub deploy_args: DeployArgs,
+
+    /// Path to directory where the processed dockerfile, Enclave and zipped upload will be kept
+    #[arg(
+        short = 'o',
+        long = "output",
+        default_value = ".",
+        env = "EV_OUTPUT_DIR"
+    )]
+    pub output_dir: String,
+
+    /// Skip attesting the Enclave once it has been deployed
+    #[arg(long = "skip-attestation")]
+    pub skip_attestation: bool,
+}
+
+#[derive(Debug, Error)]
+pub enum ShipError {
+    #[error("{0}")]
+    RemoteBuilder(#[from] RemoteBuilderError),
+    #[error("ship deploys a single Enclave. Use ev enclave deploy --all to deploy a workspace.")]
+    Workspace,
+    #[error("{0}")]
+    Deploy(#[from] DeployError),
+    #[error("{0}")]
+    TrustStore(String),
+    #[error("The Enclave was deployed, but failed to attest - {0}")]
+    Attestation(String),
+}
+
+// Deploy errors keep their own codes and data, so ship fails the same way deploy does
+impl CmdOutput for ShipError {
+    fn exitcode(&self) -> errors::ExitCode {
+        match self {
+            Self::RemoteBuilder(e) => e.exitcode(),
+            Self::Workspace => errors::USAGE,
+            Self::Deploy(e) => e.exitcode(),
+            Self::TrustStore(_) | Self::Attestation(_) => errors::SOFTWARE,
+        }
+    }
+
+    fn code(&self) -> String {
+        match self {
+            Self::RemoteBuilder(_) => "enclaves/remote-builder-error".to_string(),
+            Self::Workspace => "enclaves/workspace-error".to_string(),
+            Self::Deploy(e) => e.code(),
+            Self::TrustStore(_) => "enclaves/trust-store-error".to_string(),
+            Self::Attestation(_) => "enclaves/attestation-error".to_string(),
+        }
+    }
+
+    fn data(&self) -> Option<serde_json::Value> {
+        match self {
+            Self::Deploy(e) => e.data(),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, strum_macros::Display, CliMessage)]
+#[cli(code = "generic/success", exitcode = exitcode::OK)]
+pub enum ShipMessage {
+    #[strum(to_string = "Your Enclave is now available at https://{domain}")]
+    Shipped {
+        domain: String,
+        #[cli(data)]
+        data: Option<serde_json::Value>,
+    },
+    #[strum(to_string = "Deployment cancelled. The built Enclave has been kept in {0}")]
+    #[cli(code = "generic/cancelled")]
+    Cancelled(String),
+}
+
+pub async fn run(ship_args: ShipArgs, (_, api_key): BasicAuth) -> Result<ShipMessage, ShipError> {
+    let base_args = BaseArgs::parse();
+    let deploy_args = &ship_args.deploy_args;
+    if deploy_args.workspace_args.all {
+        return Err(ShipError::Workspace);
+    }
+    super::build::select_remote_builder(deploy_args.remote_builder.as_deref())?;
+    if base_args.json {
+        ev_enclave::progress::enable_json_events();
+    }
+
+    let versions = get_runtime_versions(None)
+        .await
+        .map_err(DeployError::from)?;
+    let preparation = DeployPreparation {
+        output_dir: Some(&ship_args.output_dir),
+        confirm_pcr_changes: true,
+    };
+    let (deployed, verification) =
+        match deploy_and_verify(deploy_args, &api_key, versions, &base_args, &preparation).await {
+            Ok(deployed) => deployed,
+            Err(DeployError::PcrChangesDeclined(output_path)) => {
+                return Ok(ShipMessage::Cancelled(output_path))
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+    let attested = if ship_args.skip_attestation {
+        false
+    } else {
+        attest_deployment(&deployed.domain, &deployed.measurements).await?
+    };
+
+    let data = outputs_json(base_args.json).then(|| {
+        serde_json::json!({
+            "status": "success",
+            "enclaveDomain": deployed.domain,
+            "deploymentUuid": deployed.deployment_uuid,
+            "measurements": &deployed.measurements,
+            "verification": verification,
+            "attested": attested,
+            "timings": ev_enclave::instrumentation::timings()
+        })
+    });
+    Ok(ShipMessage::Shipped {
+        domain: deployed.domain,
+        data,
+    })
+}
+
+#[cfg(not(target_os = "windows"))]
+async fn attest_deployment(
+    domain: &str,
+    measurements: &EIFMeasurements,
+) -> Result<bool, ShipError> {
+    use attestation_doc_validation::attestation_doc::PCRs;
+
+    let pcrs = measurements.pcrs();
+    let Some(pcr8) = pcrs.pcr8.as_ref() else {
+        log::warn!("The built Enclave has no PCR8, skipping post-deploy attestation.");
+        return Ok(false);
+    };
+    let expected_pcrs = PCRs {
+        pcr_0: pcrs.pcr0.to_string(),
+        pcr_1: pcrs.pcr1.to_string(),
+        pcr_2: pcrs.pcr2.to_string(),
+        pcr_8: pcr8.to_string(),
+    };
+
+    let trust_store = super::attest::load_pinned_trust_store()
+        .map_err(|e| ShipError::TrustStore(e.to_string()))?;
+
+    ev_enclave::attest::attest_connection_to_enclave(domain, expected_pcrs, trust_store)
+        .await
+        .map_err(|e| ShipError::Attestation(e.to_string()))?;
+    log::info!("Attestation successful! https://{domain} returned a signed attestation doc matching the deployed PCRs.");
+    Ok(true)
+}
+
+#[cfg(target_os = "windows")]
+async fn attest_deployment(_: &str, _: &EIFMeasurements) -> Result<bool, ShipError> {
+    log::warn!("Post-deploy attestation is not supported on Windows, skipping.");
+    Ok(false)
+}
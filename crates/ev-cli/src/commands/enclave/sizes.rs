@@ -0,0 +1,90 @@
+use clap::{Parser, Subcommand};
+use common::api::{client::ApiError, BasicAuth};
+use common::table::{Table, TableError};
+use ev_cli_derive::CliMessage;
+use evervault_api_client::enclave::{EnclaveApi, EnclaveClient, EnclaveSizes};
+use thiserror::Error;
+
+use crate::table::TableArgs;
+use crate::BaseArgs;
+
+/// Manage Enclave size presets
+#[derive(Debug, Parser)]
+#[command(name = "sizes", about)]
+pub struct SizesArgs {
+    #[command(subcommand)]
+    action: SizesCommands,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum SizesCommands {
+    #[command()]
+    List(ListSizesArgs),
+}
+
+/// List the CPUs and memory reserved by each size, as set with --size or size in enclave.toml
+#[derive(Debug, Parser)]
+#[command(name = "list", about)]
+pub struct ListSizesArgs {
+    #[command(flatten)]
+    pub table_args: TableArgs,
+}
+
+#[derive(Debug, Error, CliMessage)]
+pub enum SizesError {
+    #[error("Failed to retrieve Enclave sizes from Evervault API – {0}")]
+    #[cli(code = "enclaves/api-error")]
+    Api(
+        #[from]
+        #[cli(exitcode)]
+        ApiError,
+    ),
+    #[error("{0}")]
+    #[cli(code = "generic/table-error")]
+    Table(
+        #[from]
+        #[cli(exitcode)]
+        TableError,
+    ),
+}
+
+#[derive(Debug, strum_macros::Display, CliMessage)]
+#[cli(code = "generic/success", exitcode = exitcode::OK)]
+pub enum SizesMessage {
+    #[strum(to_string = "{0}")]
+    Table(String),
+    #[strum(to_string = "Retrieved the Enclave sizes")]
+    Sizes(#[cli(data)] EnclaveSizes),
+}
+
+pub async fn run(
+    sizes_args: SizesArgs,
+    (_, api_key): BasicAuth,
+) -> Result<SizesMessage, SizesError> {
+    let enclave_api = EnclaveClient::new(crate::auth::api_auth_mode(api_key));
+    let SizesCommands::List(list_args) = sizes_args.action;
+    let sizes = enclave_api.get_enclave_sizes().await?;
+    if !list_args.table_args.use_table(BaseArgs::parse().json) {
+        return Ok(SizesMessage::Sizes(sizes));
+    }
+    let rendered = list_args.table_args.render(sizes_table(&sizes))?;
+    Ok(SizesMessage::Table(rendered.trim_end().to_string()))
+}
+
+fn sizes_table(sizes: &EnclaveSizes) -> Table {
+    let mut table = Table::new([
+        ("size", "SIZE"),
+        ("cpus", "CPUS"),
+        ("memory", "MEMORY (MiB)"),
+        ("description", "DESCRIPTION"),
+    ]);
+    for definition in &sizes.sizes {
+        table.push_row(vec![
+            definition.size.clone(),
+            definition.cpus.to_string(),
+            definition.memory_mib.to_string(),
+            definition.description.clone().unwrap_or_default(),
+        ]);
+    }
+    table
+}
@@ -0,0 +1,191 @@
+use clap::Parser;
+use common::api::BasicAuth;
+use ev_cli_derive::CliMessage;
+use ev_enclave::config::{EnclaveConfig, EnclaveConfigError};
+use ev_enclave::smoke::{run_smoke_test, SmokeError as EnclaveSmokeError, SmokeReport, SmokeTest};
+use std::time::Duration;
+use thiserror::Error;
+
+use crate::tty::outputs_json;
+use crate::BaseArgs;
+
+/// Send requests to a deployed Enclave and check it responds as expected, exiting non-zero on failure
+#[derive(Debug, Parser)]
+#[command(name = "smoke", about)]
+pub struct SmokeArgs {
+    /// Path to enclave.toml config file
+    #[arg(
+        short = 'c',
+        long = "config",
+        default_value = "./enclave.toml",
+        env = "EV_ENCLAVE_CONFIG"
+    )]
+    pub config: String,
+
+    /// The path to send requests to
+    #[arg(long = "path", default_value = "/")]
+    pub path: String,
+
+    /// The HTTP status every request is expected to return
+    #[arg(long = "expect-status", default_value_t = 200)]
+    pub expect_status: u16,
+
+    /// The number of requests to send
+    #[arg(short = 'n', long = "requests", default_value_t = 10, value_parser = clap::value_parser!(u32).range(1..))]
+    pub requests: u32,
+
+    /// Seconds to wait for each request before counting it as a failure
+    #[arg(long = "timeout", default_value_t = 10)]
+    pub timeout: u64,
+
+    /// Attest the Enclave before sending requests, using the PCRs in the attestation section of the config
+    #[cfg(not(target_os = "windows"))]
+    #[arg(long = "attest")]
+    pub attest: bool,
+
+    /// Path to EIF file. When included with --attest, the Enclave is attested against the measures of the EIF.
+    #[cfg(not(target_os = "windows"))]
+    #[arg(long = "eif-path", env = "EV_EIF_PATH", requires = "attest")]
+    pub eif_path: Option<String>,
+}
+
+#[derive(Debug, Error, CliMessage)]
+pub enum SmokeError {
+    #[error("An error occurred while resolving your Enclave toml — {0}\n\nPlease make sure you have a enclave.toml file in the current directory, or have supplied a path with the --config flag.")]
+    #[cli(code = "enclaves/config-error")]
+    Config(#[cli(exitcode)] EnclaveConfigError),
+    #[error("{0}")]
+    #[cli(code = "enclaves/config-error")]
+    Domain(#[cli(exitcode)] EnclaveConfigError),
+    #[error("Failed to attest Enclave - {0}")]
+    #[cli(code = "enclaves/attestation-error", exitcode = exitcode::SOFTWARE)]
+    Attestation(String),
+    #[error("{0}")]
+    #[cli(code = "enclaves/smoke-error")]
+    Smoke(
+        #[from]
+        #[cli(exitcode)]
+        EnclaveSmokeError,
+    ),
+    #[error("The Enclave uses API key auth, which the ev login session can't be used for. Set EV_API_KEY or pass --api-key to send requests to it.")]
+    #[cli(code = "enclaves/api-key-required", exitcode = exitcode::NOUSER)]
+    ApiKeyRequired,
+    #[error("Smoke test failed: {failed} of {requests} requests failed")]
+    #[cli(code = "enclaves/smoke-failed", exitcode = exitcode::UNAVAILABLE)]
+    Failed {
+        failed: usize,
+        requests: u32,
+        #[cli(data)]
+        report: Option<SmokeReport>,
+    },
+}
+
+#[derive(Debug, strum_macros::Display, CliMessage)]
+#[cli(code = "generic/success", exitcode = exitcode::OK)]
+pub enum SmokeMessage {
+    #[strum(to_string = "Smoke test passed: {requests} requests returned {expected_status}")]
+    Passed {
+        requests: u32,
+        expected_status: u16,
+        #[cli(data)]
+        report: Option<SmokeReport>,
+    },
+}
+
+#[cfg(not(target_os = "windows"))]
+pub(super) async fn attest_enclave(
+    eif_path: Option<&str>,
+    config: &EnclaveConfig,
+    domain: &str,
+) -> Result<(), SmokeError> {
+    use super::attest::{get_expected_pcrs, load_pinned_trust_store};
+    use ev_enclave::attest::attest_connection_to_enclave;
+
+    let attestation = async {
+        let expected_pcrs = get_expected_pcrs(config, eif_path).map_err(|e| e.to_string())?;
+        let trust_store = load_pinned_trust_store().map_err(|e| e.to_string())?;
+        attest_connection_to_enclave(domain, expected_pcrs, trust_store)
+            .await
+            .map_err(|e| e.to_string())
+    };
+    attestation.await.map_err(SmokeError::Attestation)?;
+    log::info!("Attested https://{domain}");
+    Ok(())
+}
+
+pub async fn run(
+    smoke_args: SmokeArgs,
+    (_, api_key): BasicAuth,
+) -> Result<SmokeMessage, SmokeError> {
+    let config =
+        EnclaveConfig::try_from_filepath(&smoke_args.config).map_err(SmokeError::Config)?;
+    let domain = config.get_enclave_domain().map_err(SmokeError::Domain)?;
+
+    #[cfg(not(target_os = "windows"))]
+    let attested = smoke_args.attest;
+    #[cfg(target_os = "windows")]
+    let attested = false;
+    #[cfg(not(target_os = "windows"))]
+    if attested {
+        attest_enclave(smoke_args.eif_path.as_deref(), &config, &domain).await?;
+    }
+
+    let api_key = match config.api_key_auth {
+        true => Some(crate::auth::enclave_api_key(api_key).ok_or(SmokeError::ApiKeyRequired)?),
+        false => None,
+    };
+
+    let smoke_test = SmokeTest {
+        base_url: format!("https://{domain}"),
+        path: smoke_args.path,
+        expected_status: smoke_args.expect_status,
+        requests: smoke_args.requests,
+        timeout: Duration::from_secs(smoke_args.timeout),
+        api_key,
+    };
+    log::info!(
+        "Sending {} requests to {}...",
+        smoke_test.requests,
+        smoke_test.url()
+    );
+    let outcomes = run_smoke_test(&smoke_test).await?;
+    let report = SmokeReport::new(
+        smoke_test.url(),
+        smoke_test.expected_status,
+        &outcomes,
+        attested,
+    );
+
+    let (passed, failed, requests) = (report.passed, report.failures.len(), report.requests);
+    let report = if outputs_json(BaseArgs::parse().json) {
+        Some(report)
+    } else {
+        if let Some(latency) = report.latency.as_ref() {
+            log::info!(
+                "Latency: p50 {}ms, p90 {}ms, p99 {}ms, max {}ms",
+                latency.p50,
+                latency.p90,
+                latency.p99,
+                latency.max
+            );
+        }
+        for failure in report.failures.iter() {
+            log::error!("Request {} failed - {}", failure.request, failure.reason);
+        }
+        None
+    };
+
+    if passed {
+        Ok(SmokeMessage::Passed {
+            requests,
+            expected_status: smoke_test.expected_status,
+            report,
+        })
+    } else {
+        Err(SmokeError::Failed {
+            failed,
+            requests,
+            report,
+        })
+    }
+}
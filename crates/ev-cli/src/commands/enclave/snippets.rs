@@ -0,0 +1,85 @@
+use clap::Parser;
+use common::api::BasicAuth;
+use ev_cli_derive::CliMessage;
+use ev_enclave::config::{EnclaveConfig, EnclaveConfigError};
+use ev_enclave::snippets::{SnippetError, SnippetLanguage, SnippetTarget};
+use evervault_api_client::enclave::EnclaveClient;
+use thiserror::Error;
+
+/// Generate client code which connects to the Enclave and attests it against its PCRs
+#[derive(Debug, Parser)]
+#[command(name = "snippets", about)]
+pub struct SnippetsArgs {
+    /// Path to enclave.toml config file
+    #[arg(
+        short = 'c',
+        long = "config",
+        default_value = "./enclave.toml",
+        env = "EV_ENCLAVE_CONFIG"
+    )]
+    pub config: String,
+
+    /// Language of the Evervault SDK the snippet uses
+    #[arg(long = "lang", value_enum)]
+    pub lang: SnippetLanguage,
+
+    /// Pin the PCRs of the Enclave's latest deployment, instead of the attestation section of the config
+    #[arg(long = "from-api")]
+    pub from_api: bool,
+
+    /// File to write the snippet to. Defaults to stdout.
+    #[arg(short = 'o', long = "out")]
+    pub out: Option<String>,
+}
+
+#[derive(Debug, Error, CliMessage)]
+pub enum SnippetsError {
+    #[error("{0}")]
+    #[cli(code = "enclaves/config-error")]
+    Config(
+        #[from]
+        #[cli(exitcode)]
+        EnclaveConfigError,
+    ),
+    #[error("Failed to generate snippet — {0}")]
+    #[cli(code = "enclaves/snippet-error")]
+    Snippet(
+        #[from]
+        #[cli(exitcode)]
+        SnippetError,
+    ),
+    #[error("Failed to write the snippet to {0} - {1}")]
+    #[cli(code = "generic/io-error", exitcode = exitcode::IOERR)]
+    Write(String, std::io::Error),
+}
+
+#[derive(Debug, strum_macros::Display, CliMessage)]
+#[cli(code = "generic/success", exitcode = exitcode::OK)]
+pub enum SnippetsMessage {
+    #[strum(to_string = "Client snippet for {name} written to {out}")]
+    Written { name: String, out: String },
+    #[strum(to_string = "{0}")]
+    Snippet(String),
+}
+
+pub async fn run(
+    snippets_args: SnippetsArgs,
+    (_, api_key): BasicAuth,
+) -> Result<SnippetsMessage, SnippetsError> {
+    let config = EnclaveConfig::try_from_filepath(&snippets_args.config)?;
+
+    let enclave_api = EnclaveClient::new(crate::auth::api_auth_mode(api_key));
+    let target = SnippetTarget::resolve(&config, &enclave_api, snippets_args.from_api).await?;
+    let snippet = target.render(snippets_args.lang);
+
+    match snippets_args.out {
+        Some(out) => match std::fs::write(&out, snippet) {
+            Ok(_) => Ok(SnippetsMessage::Written {
+                name: target.name,
+                out,
+            }),
+            Err(e) => Err(SnippetsError::Write(out, e)),
+        },
+        None => Ok(SnippetsMessage::Snippet(snippet.trim_end().to_string())),
+    }
+}
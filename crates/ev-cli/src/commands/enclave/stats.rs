@@ -0,0 +1,136 @@
+use clap::{Parser, ValueEnum};
+use common::api::BasicAuth;
+use common::table::TableError;
+use ev_cli_derive::CliMessage;
+use ev_enclave::{
+    config::{EnclaveConfig, EnclaveConfigError},
+    stats,
+};
+use evervault_api_client::enclave::{EnclaveClient, EnclaveMetrics};
+use thiserror::Error;
+
+use crate::completion::enclave_uuid_completer;
+use crate::table::TableArgs;
+use crate::BaseArgs;
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum StatsFormat {
+    Table,
+    Sparkline,
+    Json,
+}
+
+/// Show CPU, memory, request and restart metrics for each replica of an Enclave
+#[derive(Debug, Parser)]
+#[command(name = "stats", about)]
+pub struct StatsArgs {
+    /// Uuid of the Enclave to show metrics for. If not supplied, the CLI will look for a local enclave.toml
+    #[arg(long = "enclave-uuid", env = "EV_ENCLAVE_UUID", add = enclave_uuid_completer())]
+    pub enclave_uuid: Option<String>,
+
+    /// Path to the toml file containing the Enclave's config
+    #[arg(
+        short = 'c',
+        long = "config",
+        default_value = "./enclave.toml",
+        env = "EV_ENCLAVE_CONFIG"
+    )]
+    pub config: String,
+
+    /// The window of time to show metrics for
+    #[arg(short = 'w', long = "window", default_value = "1h", value_parser = stats::METRIC_WINDOWS)]
+    pub window: String,
+
+    /// The output format. Defaults to a table in interactive terminals, and JSON otherwise.
+    #[arg(long = "format", value_enum)]
+    pub format: Option<StatsFormat>,
+
+    #[command(flatten)]
+    pub table_args: TableArgs,
+}
+
+#[derive(Debug, Error, CliMessage)]
+pub enum StatsError {
+    #[error("An error occurred while resolving your Enclave toml — {0}\n\nPlease make sure you have a enclave.toml file in the current directory, or have supplied a path with the --config flag.")]
+    #[cli(code = "enclaves/config-error")]
+    Config(
+        #[from]
+        #[cli(exitcode)]
+        EnclaveConfigError,
+    ),
+    #[error("Enclave uuid is missing from toml")]
+    #[cli(code = "enclaves/config-error", exitcode = exitcode::DATAERR)]
+    MissingEnclaveUuid,
+    #[error("An error occurred while fetching metrics: {0}")]
+    #[cli(code = "enclaves/stats-error")]
+    Metrics(
+        #[from]
+        #[cli(exitcode)]
+        stats::StatsError,
+    ),
+    #[error("{0}")]
+    #[cli(code = "generic/table-error")]
+    Table(
+        #[from]
+        #[cli(exitcode)]
+        TableError,
+    ),
+}
+
+#[derive(Debug, strum_macros::Display, CliMessage)]
+#[cli(code = "generic/success", exitcode = exitcode::OK)]
+pub enum StatsMessage {
+    #[strum(to_string = "Retrieved metrics for the last {window}")]
+    Metrics {
+        window: String,
+        #[cli(data)]
+        metrics: EnclaveMetrics,
+    },
+    #[strum(to_string = "No metrics found for the last {0}")]
+    NoMetrics(String),
+    #[strum(to_string = "{0}")]
+    Rendered(String),
+}
+
+pub async fn run(
+    stats_args: StatsArgs,
+    (_, api_key): BasicAuth,
+) -> Result<StatsMessage, StatsError> {
+    let enclave_uuid = match stats_args.enclave_uuid {
+        Some(enclave_uuid) => enclave_uuid,
+        None => EnclaveConfig::try_from_filepath(&stats_args.config)?
+            .uuid
+            .ok_or(StatsError::MissingEnclaveUuid)?,
+    };
+
+    let enclave_client = EnclaveClient::new(crate::auth::api_auth_mode(api_key));
+    let metrics = stats::get_metrics(&enclave_client, &enclave_uuid, &stats_args.window).await?;
+
+    let format =
+        stats_args
+            .format
+            .unwrap_or(if stats_args.table_args.use_table(BaseArgs::parse().json) {
+                StatsFormat::Table
+            } else {
+                StatsFormat::Json
+            });
+
+    let rendered = match format {
+        StatsFormat::Json => {
+            return Ok(StatsMessage::Metrics {
+                window: metrics.window().to_string(),
+                metrics,
+            })
+        }
+        _ if metrics.replicas().is_empty() => {
+            return Ok(StatsMessage::NoMetrics(metrics.window().to_string()))
+        }
+        StatsFormat::Table => stats_args
+            .table_args
+            .render(stats::metrics_table(&metrics))?,
+        StatsFormat::Sparkline => stats_args
+            .table_args
+            .render(stats::sparklines_table(&metrics))?,
+    };
+    Ok(StatsMessage::Rendered(rendered.trim_end().to_string()))
+}
@@ -0,0 +1,138 @@
+use clap::Parser;
+use common::api::BasicAuth;
+use ev_cli_derive::CliMessage;
+use ev_enclave::common::resolve_enclave_uuid;
+use ev_enclave::config::EnclaveConfigError;
+use ev_enclave::state::{
+    load_cached_enclave, refresh_cached_enclave, CachedEnclave, StateError as EnclaveStateError,
+};
+use evervault_api_client::enclave::EnclaveClient;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::completion::enclave_uuid_completer;
+use crate::tty::outputs_json;
+use crate::BaseArgs;
+
+/// Show an Enclave's domain, last deployment and scaling, starting from the state cached by earlier commands while it's refreshed
+#[derive(Debug, Parser)]
+#[command(name = "status", about)]
+pub struct StatusArgs {
+    /// Path to enclave.toml config file
+    #[arg(
+        short = 'c',
+        long = "config",
+        default_value = "./enclave.toml",
+        env = "EV_ENCLAVE_CONFIG"
+    )]
+    pub config: String,
+
+    /// Uuid of the Enclave, overriding the uuid in the config
+    #[arg(long = "enclave-uuid", env = "EV_ENCLAVE_UUID", add = enclave_uuid_completer())]
+    pub enclave_uuid: Option<String>,
+
+    /// Only show the cached state, without contacting the Evervault API
+    #[arg(long = "cached")]
+    pub cached: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnclaveStatus {
+    #[serde(flatten)]
+    enclave: CachedEnclave,
+    /// Whether the state is from the cache rather than a fetch by this command
+    stale: bool,
+}
+
+#[derive(Debug, Error, CliMessage)]
+pub enum StatusError {
+    #[error("{0}")]
+    #[cli(code = "enclaves/config-error")]
+    Config(
+        #[from]
+        #[cli(exitcode)]
+        EnclaveConfigError,
+    ),
+    #[error("No Enclave Uuid given. You can provide one by using either the --enclave-uuid flag, or using the --config flag to point to an Enclave.toml")]
+    #[cli(code = "enclaves/config-error", exitcode = exitcode::CONFIG)]
+    MissingUuid,
+    #[error("No state is cached for Enclave {0}. Run this command without --cached to fetch it.")]
+    #[cli(code = "enclaves/state-error", exitcode = exitcode::NOINPUT)]
+    NotCached(String),
+    #[error("{0}")]
+    #[cli(code = "enclaves/state-error")]
+    State(
+        #[from]
+        #[cli(exitcode)]
+        EnclaveStateError,
+    ),
+}
+
+#[derive(Debug, strum_macros::Display, CliMessage)]
+#[cli(code = "generic/success", exitcode = exitcode::OK)]
+pub enum StatusMessage {
+    #[strum(to_string = "{summary}")]
+    Status {
+        summary: String,
+        #[cli(data)]
+        status: Option<EnclaveStatus>,
+    },
+}
+
+fn status_message(enclave: CachedEnclave, stale: bool) -> StatusMessage {
+    let mut summary = enclave.summary();
+    if stale {
+        let age = enclave
+            .age()
+            .unwrap_or_else(|| "an unknown time".to_string());
+        summary.push_str(&format!("\n(cached {age} ago)"));
+    }
+    StatusMessage::Status {
+        summary,
+        status: outputs_json(BaseArgs::parse().json).then_some(EnclaveStatus { enclave, stale }),
+    }
+}
+
+pub async fn run(
+    status_args: StatusArgs,
+    (_, api_key): BasicAuth,
+) -> Result<StatusMessage, StatusError> {
+    let enclave_uuid =
+        resolve_enclave_uuid(status_args.enclave_uuid.as_deref(), &status_args.config)?
+            .ok_or(StatusError::MissingUuid)?;
+    let cached = load_cached_enclave(&status_args.config, &enclave_uuid);
+
+    if status_args.cached {
+        let cached = cached.ok_or(StatusError::NotCached(enclave_uuid))?;
+        return Ok(status_message(cached, true));
+    }
+
+    // The refresh starts before the cached state is shown, so slow connections don't delay either
+    let config_path = status_args.config.clone();
+    let refresh = tokio::spawn(async move {
+        let enclave_api = EnclaveClient::new(crate::auth::api_auth_mode(api_key));
+        refresh_cached_enclave(&enclave_api, &config_path, &enclave_uuid).await
+    });
+    if let (Some(cached), false) = (cached.as_ref(), outputs_json(BaseArgs::parse().json)) {
+        let age = cached
+            .age()
+            .unwrap_or_else(|| "an unknown time".to_string());
+        log::info!("{}\n(cached {age} ago, refreshing...)\n", cached.summary());
+    }
+
+    match refresh
+        .await
+        .expect("Infallible - the refresh task doesn't panic")
+    {
+        Ok(enclave) => Ok(status_message(enclave, false)),
+        // Cached state is still worth showing when the API can't be reached
+        Err(e) => match cached {
+            Some(cached) => {
+                log::warn!("{e}. Showing the cached state instead.");
+                Ok(status_message(cached, true))
+            }
+            None => Err(e.into()),
+        },
+    }
+}
@@ -0,0 +1,142 @@
+use clap::builder::BoolishValueParser;
+use clap::Parser;
+use ev_cli_derive::CliMessage;
+use ev_enclave::common::prepare_build_args;
+use ev_enclave::config::{read_and_validate_config, BuildTimeConfig, EnclaveConfigError};
+use ev_enclave::harness::{run_harness, HarnessError, TestHarness, TestReport};
+use ev_enclave::version::{get_runtime_versions, VersionError};
+use std::time::Duration;
+use thiserror::Error;
+
+use crate::BaseArgs;
+
+/// Build an Enclave's image without converting it to an EIF, and run a test command against it alongside the services in a compose file
+#[derive(Clone, Parser, Debug)]
+#[command(name = "test", about)]
+pub struct TestArgs {
+    /// Path to enclave.toml config file
+    #[arg(
+        short = 'c',
+        long = "config",
+        default_value = "./enclave.toml",
+        env = "EV_ENCLAVE_CONFIG"
+    )]
+    pub config: String,
+
+    /// Path to Dockerfile for Enclave. Will override any dockerfile specified in the .toml file.
+    #[arg(short = 'f', long = "file", env = "EV_DOCKERFILE")]
+    pub dockerfile: Option<String>,
+
+    /// Path to use for Docker context. Defaults to the current directory.
+    #[arg(default_value = ".", env = "EV_CONTEXT_PATH")]
+    pub context_path: String,
+
+    /// Compose file defining the services the tests depend on. The Enclave is added to it as the `enclave` service.
+    #[arg(long = "compose", default_value = "./docker-compose.test.yml")]
+    pub compose: String,
+
+    /// Build time arguments to provide to docker
+    #[arg(long = "build-arg")]
+    pub docker_build_args: Vec<String>,
+
+    /// Disables the use of cache during the image builds
+    #[arg(long = "no-cache", env = "EV_NO_CACHE", value_parser = BoolishValueParser::new())]
+    pub no_cache: bool,
+
+    /// Seconds to wait for the Enclave's service to accept connections before running the tests
+    #[arg(long = "startup-timeout", default_value_t = 60)]
+    pub startup_timeout: u64,
+
+    /// The test command to run once the Enclave is up, given after --. The Enclave's address is passed to it in EV_ENCLAVE_URL.
+    #[arg(last = true, required = true)]
+    pub command: Vec<String>,
+}
+
+impl BuildTimeConfig for TestArgs {
+    fn dockerfile(&self) -> Option<&str> {
+        self.dockerfile.as_deref()
+    }
+}
+
+#[derive(Debug, Error, CliMessage)]
+pub enum TestError {
+    #[error("Failed to read Enclave config from file system — {0}")]
+    #[cli(code = "enclaves/config-error")]
+    Config(
+        #[from]
+        #[cli(exitcode)]
+        EnclaveConfigError,
+    ),
+    #[error("Failed to retrieve the latest data plane and installer versions - {0}")]
+    #[cli(code = "enclaves/version-error")]
+    Versions(
+        #[from]
+        #[cli(exitcode)]
+        VersionError,
+    ),
+    #[error("Failed to run the Enclave's tests — {0}")]
+    #[cli(code = "enclaves/test-error")]
+    Harness(
+        #[from]
+        #[cli(exitcode)]
+        HarnessError,
+    ),
+    // The test command's own exit code is passed on, so CI shows how the tests failed
+    #[error("Tests failed against the Enclave's image")]
+    #[cli(code = "enclaves/tests-failed", exitcode = *exit_code)]
+    Failed {
+        exit_code: exitcode::ExitCode,
+        #[cli(data)]
+        report: Option<TestReport>,
+    },
+}
+
+#[derive(Debug, strum_macros::Display, CliMessage)]
+#[cli(code = "generic/success", exitcode = exitcode::OK)]
+pub enum TestMessage {
+    #[strum(to_string = "Tests passed against the Enclave's image")]
+    Passed(#[cli(data)] Option<TestReport>),
+}
+
+pub async fn run(test_args: TestArgs) -> Result<TestMessage, TestError> {
+    let base_args = BaseArgs::parse();
+    let (enclave_config, validated_config) =
+        read_and_validate_config(&test_args.config, &test_args)?;
+
+    let (data_plane_version, installer_version) = get_runtime_versions(None)
+        .await?
+        .resolve(enclave_config.runtime_channel());
+
+    let formatted_args = prepare_build_args(&test_args.docker_build_args);
+    let borrowed_args = formatted_args
+        .as_ref()
+        .map(|args| args.iter().map(AsRef::as_ref).collect());
+
+    let harness = TestHarness {
+        compose_file: test_args.compose.clone(),
+        command: test_args.command.clone(),
+        startup_timeout: Duration::from_secs(test_args.startup_timeout),
+        verbose: base_args.verbose,
+    };
+    let report = run_harness(
+        &validated_config,
+        &test_args.context_path,
+        &harness,
+        borrowed_args,
+        data_plane_version,
+        installer_version,
+        test_args.no_cache,
+    )
+    .await?;
+
+    let (passed, exit_code) = (report.passed, report.exit_code);
+    let report = base_args.json.then_some(report);
+    if passed {
+        Ok(TestMessage::Passed(report))
+    } else {
+        Err(TestError::Failed {
+            exit_code: exit_code.unwrap_or(exitcode::SOFTWARE),
+            report,
+        })
+    }
+}
@@ -0,0 +1,108 @@
+use atty::Stream;
+use clap::{Parser, Subcommand};
+use ev_cli_derive::CliMessage;
+use ev_enclave::attest::error::TrustStoreError;
+use ev_enclave::attest::trust::{fetch_trust_store, TrustStore};
+use thiserror::Error;
+
+use crate::config::trust_store_directory;
+
+/// Manage the pinned attestation root of trust used by `ev enclave attest`
+#[derive(Debug, Parser)]
+#[command(name = "trust", about)]
+pub struct TrustArgs {
+    #[command(subcommand)]
+    pub action: TrustCommands,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum TrustCommands {
+    /// Download the AWS Nitro root and Evervault intermediates, verify them, and pin them locally
+    #[command()]
+    Fetch,
+    /// Show the pinned certificates and when they expire
+    #[command()]
+    Show,
+}
+
+#[derive(Debug, Error, CliMessage)]
+pub enum TrustError {
+    #[error("Couldn't find a home directory to store the pinned certificates in")]
+    #[cli(code = "enclaves/trust-store-error", exitcode = exitcode::CONFIG)]
+    NoHomeDirectory,
+    #[error("No root of trust has been pinned. Run `ev enclave trust fetch` to pin one.")]
+    #[cli(code = "enclaves/trust-store-missing", exitcode = exitcode::NOINPUT)]
+    NotPinned,
+    #[error("{0}")]
+    #[cli(code = "enclaves/trust-store-error")]
+    TrustStore(
+        #[from]
+        #[cli(exitcode)]
+        TrustStoreError,
+    ),
+}
+
+#[derive(Debug, strum_macros::Display, CliMessage)]
+#[cli(code = "generic/success", exitcode = exitcode::OK)]
+pub enum TrustMessage {
+    #[strum(to_string = "Pinned the attestation root of trust in {directory}\n{summary}")]
+    Fetched {
+        directory: String,
+        summary: String,
+        #[cli(data)]
+        store: Option<TrustStore>,
+    },
+    #[strum(to_string = "{summary}")]
+    Shown {
+        summary: String,
+        #[cli(data)]
+        store: Option<TrustStore>,
+    },
+}
+
+fn summarize_trust_store(store: &TrustStore) -> String {
+    let not_after = |timestamp: i64| {
+        chrono::DateTime::from_timestamp(timestamp, 0)
+            .map(|date| date.format("%Y-%m-%d").to_string())
+            .unwrap_or_else(|| timestamp.to_string())
+    };
+    let mut lines = vec![format!(
+        "Root: {} (expires {})\n  {}",
+        store.root.subject,
+        not_after(store.root.not_after),
+        store.root.fingerprint
+    )];
+    for intermediate in store.intermediates.iter() {
+        lines.push(format!(
+            "Intermediate: {} (expires {})\n  {}",
+            intermediate.subject,
+            not_after(intermediate.not_after),
+            intermediate.fingerprint
+        ));
+    }
+    lines.join("\n")
+}
+
+pub async fn run(trust_args: TrustArgs) -> Result<TrustMessage, TrustError> {
+    let directory = trust_store_directory().ok_or(TrustError::NoHomeDirectory)?;
+
+    let store = match trust_args.action {
+        TrustCommands::Fetch => fetch_trust_store(&directory).await?,
+        TrustCommands::Show => TrustStore::load(&directory)?.ok_or(TrustError::NotPinned)?,
+    };
+    store
+        .expiry_warnings(chrono::Utc::now().timestamp())
+        .iter()
+        .for_each(|warning| log::warn!("{warning}"));
+
+    let summary = summarize_trust_store(&store);
+    let store = (!atty::is(Stream::Stdout)).then_some(store);
+    match trust_args.action {
+        TrustCommands::Fetch => Ok(TrustMessage::Fetched {
+            directory: directory.display().to_string(),
+            summary,
+            store,
+        }),
+        TrustCommands::Show => Ok(TrustMessage::Shown { summary, store }),
+    }
+}
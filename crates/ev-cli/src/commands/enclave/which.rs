@@ -0,0 +1,185 @@
+use atty::Stream;
+use clap::Parser;
+use common::api::client::ApiClient;
+use common::api::AuthMode;
+use ev_cli_derive::CliMessage;
+use ev_enclave::config::EnclaveConfig;
+use evervault_api_client::enclave::EnclaveClient;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::auth::describe_api_key_source;
+use crate::completion::enclave_uuid_completer;
+use crate::config::CliConfigError;
+
+const DEFAULT_CONFIG_PATH: &str = "./enclave.toml";
+
+/// Show which Enclave config, credentials and API the other commands would use
+#[derive(Debug, Parser)]
+#[command(name = "which", about)]
+pub struct WhichArgs {
+    /// Path to enclave.toml config file
+    #[arg(
+        short = 'c',
+        long = "config",
+        default_value = DEFAULT_CONFIG_PATH,
+        env = "EV_ENCLAVE_CONFIG"
+    )]
+    pub config: String,
+
+    /// Uuid of the Enclave, overriding the uuid in the config
+    #[arg(long = "enclave-uuid", env = "EV_ENCLAVE_UUID", add = enclave_uuid_completer())]
+    pub enclave_uuid: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvedTarget {
+    config_path: String,
+    config_source: &'static str,
+    config_found: bool,
+    api_key_source: String,
+    api_base_url: String,
+    app_uuid: Option<String>,
+    enclave_name: Option<String>,
+    enclave_uuid: Option<String>,
+    team_uuid: Option<String>,
+    warnings: Vec<String>,
+}
+
+fn value_source(value: &str, env_var: &str, default: &str) -> &'static str {
+    if std::env::var(env_var).is_ok_and(|env_value| env_value == value) {
+        "env"
+    } else if value == default {
+        "default"
+    } else {
+        "flag"
+    }
+}
+
+fn target_warnings(app_uuid: Option<&str>, config: Option<&EnclaveConfig>) -> Vec<String> {
+    let mut warnings = vec![];
+    if app_uuid.is_none() {
+        warnings.push("EV_APP_UUID is not set".to_string());
+    }
+    match config {
+        None => warnings.push("No Enclave config was found".to_string()),
+        Some(config) => {
+            if let (Some(app_uuid), Some(config_app_uuid)) = (app_uuid, config.app_uuid.as_deref())
+            {
+                if app_uuid != config_app_uuid {
+                    warnings.push(format!("EV_APP_UUID ({app_uuid}) doesn't match the app_uuid in the Enclave config ({config_app_uuid}). Commands will fail or act on a different App."));
+                }
+            }
+        }
+    }
+    warnings
+}
+
+#[derive(Debug, Error, CliMessage)]
+pub enum WhichError {
+    #[error("{0}")]
+    #[cli(code = "generic/config-error", exitcode = exitcode::CONFIG)]
+    ApiKeySource(#[from] CliConfigError),
+}
+
+#[derive(Debug, strum_macros::Display, CliMessage)]
+#[cli(code = "generic/success", exitcode = exitcode::OK)]
+pub enum WhichMessage {
+    #[strum(to_string = "{summary}")]
+    Resolved {
+        summary: String,
+        #[cli(data)]
+        target: Option<ResolvedTarget>,
+    },
+}
+
+impl ResolvedTarget {
+    fn summary(&self) -> String {
+        let display = |value: &Option<String>| value.clone().unwrap_or_else(|| "-".to_string());
+        [
+            format!(
+                "Config: {} ({}{})",
+                self.config_path,
+                self.config_source,
+                if self.config_found { "" } else { ", not found" }
+            ),
+            format!("API key: {}", self.api_key_source),
+            format!("API: {}", self.api_base_url),
+            format!("App: {}", display(&self.app_uuid)),
+            format!("Team: {}", display(&self.team_uuid)),
+            format!(
+                "Enclave: {} ({})",
+                display(&self.enclave_name),
+                display(&self.enclave_uuid)
+            ),
+        ]
+        .join("\n")
+    }
+}
+
+pub fn run(which_args: &WhichArgs) -> Result<WhichMessage, WhichError> {
+    let config_path = std::path::Path::new(&which_args.config);
+    let config = EnclaveConfig::try_from_filepath(&which_args.config).ok();
+    let app_uuid = std::env::var("EV_APP_UUID").ok();
+
+    let api_key_source = describe_api_key_source()?;
+
+    let enclave_uuid = which_args
+        .enclave_uuid
+        .clone()
+        .or_else(|| config.as_ref().and_then(|config| config.uuid.clone()));
+
+    let target = ResolvedTarget {
+        config_path: config_path
+            .canonicalize()
+            .unwrap_or_else(|_| config_path.to_path_buf())
+            .display()
+            .to_string(),
+        config_source: value_source(&which_args.config, "EV_ENCLAVE_CONFIG", DEFAULT_CONFIG_PATH),
+        config_found: config.is_some(),
+        api_key_source,
+        api_base_url: EnclaveClient::new(AuthMode::NoAuth).base_url(),
+        warnings: target_warnings(app_uuid.as_deref(), config.as_ref()),
+        app_uuid,
+        enclave_name: config.as_ref().map(|config| config.name.clone()),
+        enclave_uuid,
+        team_uuid: config.as_ref().and_then(|config| config.team_uuid.clone()),
+    };
+
+    target
+        .warnings
+        .iter()
+        .for_each(|warning| log::warn!("{warning}"));
+    Ok(WhichMessage::Resolved {
+        summary: target.summary(),
+        target: (!atty::is(Stream::Stdout)).then_some(target),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_target_warnings() {
+        let config: EnclaveConfig = toml::from_str(
+            r#"
+version = 1
+name = "hello"
+app_uuid = "app_123"
+debug = false
+
+[egress]
+enabled = false
+"#,
+        )
+        .unwrap();
+
+        assert!(target_warnings(Some("app_123"), Some(&config)).is_empty());
+        let warnings = target_warnings(Some("app_456"), Some(&config));
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("app_456"));
+        assert_eq!(target_warnings(None, None).len(), 2);
+    }
+}
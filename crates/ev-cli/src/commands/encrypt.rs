@@ -2,17 +2,33 @@ use crate::{errors, CmdOutput};
 use clap::Parser;
 use common::api::{client::ApiError, papi::EvApiClient};
 use common::api::{papi::EvApi, BasicAuth};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use sha2::Digest;
+use std::future::Future;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use thiserror::Error;
 
+/// The file written alongside the encrypted files, listing what a decrypt step should restore
+pub const MANIFEST_FILE_NAME: &str = "manifest.json";
+const MANIFEST_VERSION: u8 = 1;
+
 /// Encrypt data using the Evervault API
 #[derive(Debug, Parser)]
 #[command(name = "encrypt", about)]
 pub struct EncryptArgs {
-    #[arg(short, long, num_args(0..))]
+    #[arg(short, long, num_args(0..), required_unless_present = "dir", conflicts_with = "dir")]
     ///A JSON value or file to be encrypted. This can be any valid JSON value: Objects, Arrays, Numbers, Boolean or Strings (strings should be enclosed in double quotes).
-    data: String,
+    data: Option<String>,
+
+    /// A directory of files to encrypt, such as secrets to provision into an Enclave. Each file is encrypted into --out with a manifest.json listing them, so --dir can't contain its own top-level manifest.json.
+    #[arg(long, requires = "out")]
+    dir: Option<PathBuf>,
+
+    /// The directory to write the encrypted files and their manifest to. It can't be inside --dir.
+    #[arg(long, requires = "dir")]
+    out: Option<PathBuf>,
 }
 
 #[derive(Error, Debug)]
@@ -21,17 +37,40 @@ pub enum EncryptError {
     ApiError(#[from] ApiError),
     #[error("Failed to serialize data. Data can be any valid JSON value: Objects, Arrays, Numbers, Boolean or Strings (strings should be enclosed in double quotes): {0}")]
     Se(#[from] serde_json::Error),
+    #[error("Failed to access {path} - {source}")]
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("The output directory {0} can't be inside the directory being encrypted")]
+    OutputInsideSource(String),
+    #[error("{0} has a name which isn't valid UTF-8, so it can't be listed in the manifest")]
+    InvalidFileName(String),
+    #[error("{0} would be overwritten by the generated manifest. Rename it or move it into a subdirectory")]
+    ReservedFileName(String),
+    #[error("The API returned a value which wasn't an encrypted string for {0}")]
+    UnexpectedResponse(String),
 }
 
 impl CmdOutput for EncryptError {
     fn exitcode(&self) -> i32 {
-        errors::SOFTWARE
+        match self {
+            EncryptError::Io { .. } => errors::IOERR,
+            EncryptError::OutputInsideSource(_) => errors::USAGE,
+            EncryptError::InvalidFileName(_) | EncryptError::ReservedFileName(_) => errors::DATAERR,
+            _ => errors::SOFTWARE,
+        }
     }
 
     fn code(&self) -> String {
         match self {
             EncryptError::ApiError(_) => "generic/api-error",
             EncryptError::Se(_) => "generic/serialization-error",
+            EncryptError::Io { .. } => "generic/io-error",
+            EncryptError::OutputInsideSource(_) => "encrypt/output-inside-source",
+            EncryptError::InvalidFileName(_) => "encrypt/invalid-file-name",
+            EncryptError::ReservedFileName(_) => "encrypt/reserved-file-name",
+            EncryptError::UnexpectedResponse(_) => "encrypt/unexpected-response",
         }
         .to_string()
     }
@@ -45,6 +84,12 @@ impl CmdOutput for EncryptError {
 pub enum EncryptMessage {
     #[strum(to_string = "")]
     Success { value: Value },
+    #[strum(to_string = "Encrypted {count} files into {out}")]
+    EncryptedDirectory {
+        count: usize,
+        out: String,
+        manifest: SecretsManifest,
+    },
 }
 
 impl CmdOutput for EncryptMessage {
@@ -55,6 +100,7 @@ impl CmdOutput for EncryptMessage {
     fn code(&self) -> String {
         match self {
             EncryptMessage::Success { .. } => "generic/success",
+            EncryptMessage::EncryptedDirectory { .. } => "encrypt/directory-encrypted",
         }
         .to_string()
     }
@@ -62,14 +108,228 @@ impl CmdOutput for EncryptMessage {
     fn data(&self) -> Option<serde_json::Value> {
         match self {
             EncryptMessage::Success { value } => Some(value.clone()),
+            EncryptMessage::EncryptedDirectory { manifest, .. } => {
+                serde_json::to_value(manifest).ok()
+            }
+        }
+    }
+}
+
+/// Lists the files encrypted from a directory. Each file's contents are base64 encoded and
+/// encrypted as a string, then written to the same relative path in the output directory.
+///
+/// To provision the files, a step inside the Enclave reads each `path` from the output
+/// directory, decrypts it with the Enclave's decrypt endpoint (`http://127.0.0.1:9999/decrypt`),
+/// base64 decodes the result and checks it against `sha256` before writing it out.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SecretsManifest {
+    pub version: u8,
+    pub encoding: String,
+    pub files: Vec<ManifestEntry>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestEntry {
+    /// The file's path relative to the encrypted directory, separated by `/`
+    pub path: String,
+    /// The hex encoded SHA-256 of the file's plaintext contents
+    pub sha256: String,
+    pub size: u64,
+}
+
+fn io_error(path: &Path) -> impl FnOnce(std::io::Error) -> EncryptError + '_ {
+    move |source| EncryptError::Io {
+        path: path.display().to_string(),
+        source,
+    }
+}
+
+/// Finds the regular files beneath the directory, sorted by their relative path. Symlinks are
+/// skipped, so files outside the directory are never encrypted by accident.
+fn collect_files(dir: &Path) -> Result<Vec<(String, PathBuf)>, EncryptError> {
+    let mut files = Vec::new();
+    let mut pending = vec![(String::new(), dir.to_path_buf())];
+    while let Some((relative_dir, current)) = pending.pop() {
+        for entry in std::fs::read_dir(&current).map_err(io_error(&current))? {
+            let entry = entry.map_err(io_error(&current))?;
+            let path = entry.path();
+            let name = entry
+                .file_name()
+                .into_string()
+                .map_err(|_| EncryptError::InvalidFileName(path.display().to_string()))?;
+            let relative_path = if relative_dir.is_empty() {
+                name
+            } else {
+                format!("{relative_dir}/{name}")
+            };
+            let metadata = path.symlink_metadata().map_err(io_error(&path))?;
+            if metadata.is_dir() {
+                pending.push((relative_path, path));
+            } else if metadata.is_file() {
+                files.push((relative_path, path));
+            } else {
+                log::warn!("Skipping {}, as it isn't a regular file", path.display());
+            }
         }
     }
+    files.sort();
+    Ok(files)
+}
+
+/// Resolves `out` to an absolute path without creating it, by canonicalizing its deepest existing ancestor
+fn resolve_output(out: &Path) -> Result<PathBuf, EncryptError> {
+    let cwd = std::env::current_dir().map_err(io_error(Path::new(".")))?;
+    let absolute = cwd.join(out);
+    let mut missing = Vec::new();
+    let mut existing = absolute.as_path();
+    while !existing.exists() {
+        match (existing.file_name(), existing.parent()) {
+            (Some(name), Some(parent)) => {
+                missing.push(name);
+                existing = parent;
+            }
+            _ => break,
+        }
+    }
+    let mut resolved = existing.canonicalize().map_err(io_error(existing))?;
+    resolved.extend(missing.into_iter().rev());
+    Ok(resolved)
+}
+
+/// Encrypts each file in `dir` into `out` using `encrypt`, and writes the manifest listing them
+pub async fn encrypt_directory<F, Fut>(
+    dir: &Path,
+    out: &Path,
+    encrypt: F,
+) -> Result<SecretsManifest, EncryptError>
+where
+    F: Fn(Value) -> Fut,
+    Fut: Future<Output = Result<Value, ApiError>>,
+{
+    let canonical_dir = dir.canonicalize().map_err(io_error(dir))?;
+    if resolve_output(out)?.starts_with(&canonical_dir) {
+        return Err(EncryptError::OutputInsideSource(out.display().to_string()));
+    }
+    let files = collect_files(dir)?;
+    if let Some((_, path)) = files
+        .iter()
+        .find(|(relative_path, _)| relative_path == MANIFEST_FILE_NAME)
+    {
+        return Err(EncryptError::ReservedFileName(path.display().to_string()));
+    }
+    std::fs::create_dir_all(out).map_err(io_error(out))?;
+
+    let mut manifest = SecretsManifest {
+        version: MANIFEST_VERSION,
+        encoding: "base64".to_string(),
+        files: Vec::new(),
+    };
+    for (relative_path, path) in files {
+        let contents = std::fs::read(&path).map_err(io_error(&path))?;
+        let encrypted = match encrypt(Value::String(base64::encode(&contents))).await? {
+            Value::String(encrypted) => encrypted,
+            _ => return Err(EncryptError::UnexpectedResponse(relative_path)),
+        };
+
+        let destination = out.join(&relative_path);
+        if let Some(parent) = destination.parent() {
+            std::fs::create_dir_all(parent).map_err(io_error(parent))?;
+        }
+        std::fs::write(&destination, encrypted).map_err(io_error(&destination))?;
+        manifest.files.push(ManifestEntry {
+            path: relative_path,
+            sha256: hex::encode(sha2::Sha256::digest(&contents)),
+            size: contents.len() as u64,
+        });
+    }
+
+    let manifest_path = out.join(MANIFEST_FILE_NAME);
+    let manifest_contents = serde_json::to_vec_pretty(&manifest)?;
+    std::fs::write(&manifest_path, manifest_contents).map_err(io_error(&manifest_path))?;
+    Ok(manifest)
 }
 
 pub async fn run(args: EncryptArgs, auth: BasicAuth) -> Result<EncryptMessage, EncryptError> {
     let api_client = EvApiClient::new(auth);
 
-    let encrypted = api_client.encrypt(Value::from_str(&args.data)?).await?;
+    if let (Some(dir), Some(out)) = (args.dir.as_deref(), args.out.as_deref()) {
+        let manifest = encrypt_directory(dir, out, |value| api_client.encrypt(value)).await?;
+        return Ok(EncryptMessage::EncryptedDirectory {
+            count: manifest.files.len(),
+            out: out.display().to_string(),
+            manifest,
+        });
+    }
+
+    let data = args.data.unwrap_or_default();
+    let encrypted = api_client.encrypt(Value::from_str(&data)?).await?;
 
     Ok(EncryptMessage::Success { value: encrypted })
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    async fn fake_encrypt(value: Value) -> Result<Value, ApiError> {
+        Ok(Value::String(format!(
+            "ev:encrypted:{}",
+            value.as_str().unwrap()
+        )))
+    }
+
+    #[tokio::test]
+    async fn test_encrypt_directory() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("db")).unwrap();
+        std::fs::write(dir.path().join("db/password.txt"), "hunter2").unwrap();
+        std::fs::write(dir.path().join("api.key"), [0u8, 159, 146, 150]).unwrap();
+        let out = tempfile::TempDir::new().unwrap();
+
+        let manifest = encrypt_directory(dir.path(), out.path(), fake_encrypt)
+            .await
+            .unwrap();
+        let paths: Vec<_> = manifest
+            .files
+            .iter()
+            .map(|file| file.path.as_str())
+            .collect();
+        assert_eq!(paths, vec!["api.key", "db/password.txt"]);
+        assert_eq!(
+            manifest.files[1].sha256,
+            hex::encode(sha2::Sha256::digest(b"hunter2"))
+        );
+
+        let encrypted = std::fs::read_to_string(out.path().join("db/password.txt")).unwrap();
+        assert_eq!(
+            encrypted,
+            format!("ev:encrypted:{}", base64::encode("hunter2"))
+        );
+        let written: SecretsManifest =
+            serde_json::from_slice(&std::fs::read(out.path().join(MANIFEST_FILE_NAME)).unwrap())
+                .unwrap();
+        assert_eq!(written, manifest);
+    }
+
+    #[tokio::test]
+    async fn test_encrypt_directory_rejects_output_inside_source() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let result = encrypt_directory(dir.path(), &dir.path().join("out"), fake_encrypt).await;
+        assert!(matches!(result, Err(EncryptError::OutputInsideSource(_))));
+        assert!(!dir.path().join("out").exists());
+    }
+
+    #[tokio::test]
+    async fn test_encrypt_directory_rejects_manifest_file_name() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join(MANIFEST_FILE_NAME), "{}").unwrap();
+        std::fs::create_dir_all(dir.path().join("nested")).unwrap();
+        std::fs::write(dir.path().join("nested").join(MANIFEST_FILE_NAME), "{}").unwrap();
+        let out = tempfile::TempDir::new().unwrap();
+        let result = encrypt_directory(dir.path(), &out.path().join("out"), fake_encrypt).await;
+        assert!(matches!(result, Err(EncryptError::ReservedFileName(_))));
+        assert!(!out.path().join("out").exists());
+    }
+}
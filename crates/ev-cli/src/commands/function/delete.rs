@@ -100,7 +100,7 @@ pub async fn run(args: DeleteArgs, auth: BasicAuth) -> Result<DeleteMessage, Del
     let target_function = resolve_function_by_name_or_pwd(args.name, &api_client).await?;
 
     if !args.force {
-        if std::io::stdout().is_terminal() {
+        if std::io::stdout().is_terminal() || ev_enclave::prompt::is_non_interactive() {
             let confirm = interact::confirm(
                 DeletePrompt::AreYouSure {
                     function_name: target_function.clone().name,
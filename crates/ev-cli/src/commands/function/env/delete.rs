@@ -101,11 +101,12 @@ pub async fn run(args: DeleteEnvArgs, auth: BasicAuth) -> Result<DeleteEnvMessag
     let function = resolve_function_by_name_or_pwd(args.name, &api_client).await?;
 
     if !args.force {
-        let confirmed = if std::io::stdout().is_terminal() {
-            interact::confirm(DeleteEnvPrompt::Confirm, false)
-        } else {
-            return Err(DeleteEnvError::MustForce);
-        };
+        let confirmed =
+            if std::io::stdout().is_terminal() || ev_enclave::prompt::is_non_interactive() {
+                interact::confirm(DeleteEnvPrompt::Confirm, false)
+            } else {
+                return Err(DeleteEnvError::MustForce);
+            };
 
         if !confirmed {
             return Err(DeleteEnvError::Aborted);
@@ -1,5 +1,5 @@
-use crate::theme::CliTheme;
-use dialoguer::{Confirm, Input, Select};
+use common::CliError;
+#[cfg(feature = "tty")]
 use indicatif::{ProgressBar, ProgressStyle};
 
 pub mod validators {
@@ -57,18 +57,26 @@ pub mod validators {
     }
 }
 
+/// Answers the prompt without showing it when running with --yes, exiting if it needs input which
+/// can't be given.
+fn auto_answer<T>(prompt: &str, default: Option<T>) -> Option<T> {
+    match ev_enclave::prompt::auto_answer(prompt, default) {
+        Ok(answer) => answer,
+        Err(e) => {
+            log::error!("{e}");
+            std::process::exit(e.exitcode());
+        }
+    }
+}
+
 pub fn input<T>(prompt: T, allow_empty: bool) -> String
 where
     T: std::fmt::Display,
 {
-    let theme = CliTheme::default();
-    let mut input: Input<String> = Input::with_theme(&theme);
-
-    match input
-        .with_prompt(prompt.to_string())
-        .allow_empty(allow_empty)
-        .interact()
-    {
+    if let Some(answer) = auto_answer(&prompt.to_string(), allow_empty.then(String::new)) {
+        return answer;
+    }
+    match dialog::input(prompt.to_string(), allow_empty) {
         Ok(input) => input,
         Err(e) => {
             eprintln!("Error reading user input : {}", e);
@@ -85,24 +93,20 @@ pub fn validated_input<T>(
 where
     T: std::fmt::Display,
 {
-    let theme = CliTheme::default();
-    let mut input: Input<String> = Input::with_theme(&theme);
-
-    input
-        .with_prompt(prompt.to_string())
-        .allow_empty(allow_empty)
-        .validate_with(validator)
-        .interact()
+    if let Some(answer) = auto_answer(&prompt.to_string(), allow_empty.then(String::new)) {
+        return Ok(answer);
+    }
+    dialog::validated_input(prompt.to_string(), allow_empty, validator)
 }
 
 pub fn select<T>(options: &Vec<String>, default: usize, prompt: T) -> Option<usize>
 where
     T: std::fmt::Display,
 {
-    let theme = CliTheme::default();
-    let mut select_obj = Select::with_theme(&theme);
-    select_obj.with_prompt(prompt.to_string());
-    select_obj.items(options).default(default).interact().ok()
+    if let Some(answer) = auto_answer(&prompt.to_string(), Some(default)) {
+        return Some(answer);
+    }
+    dialog::select(prompt.to_string(), options, default)
 }
 
 pub fn preset_input<S, T>(prompt: S, preset: T) -> Option<String>
@@ -110,27 +114,107 @@ where
     S: std::fmt::Display,
     T: std::fmt::Display,
 {
-    let theme = CliTheme::default();
-    let mut input: Input<String> = Input::with_theme(&theme);
-
-    input
-        .with_prompt(prompt.to_string())
-        .default(preset.to_string())
-        .interact()
-        .ok()
+    if let Some(answer) = auto_answer(&prompt.to_string(), Some(preset.to_string())) {
+        return Some(answer);
+    }
+    dialog::preset_input(prompt.to_string(), preset.to_string())
 }
 
 pub fn confirm<S>(prompt: S, default: bool) -> bool
 where
     S: std::fmt::Display,
 {
-    Confirm::with_theme(&CliTheme::default())
-        .with_prompt(prompt.to_string())
-        .wait_for_newline(false)
-        .default(default)
-        .show_default(true)
-        .interact()
-        .unwrap_or(default)
+    if let Some(answer) = auto_answer(&prompt.to_string(), Some(true)) {
+        return answer;
+    }
+    dialog::confirm(prompt.to_string(), default)
+}
+
+#[cfg(feature = "tty")]
+mod dialog {
+    use super::validators;
+    use crate::theme::CliTheme;
+    use dialoguer::{Confirm, Input, Select};
+
+    pub fn input(prompt: String, allow_empty: bool) -> Result<String, std::io::Error> {
+        let theme = CliTheme::default();
+        let mut input: Input<String> = Input::with_theme(&theme);
+        input
+            .with_prompt(prompt)
+            .allow_empty(allow_empty)
+            .interact()
+    }
+
+    pub fn validated_input(
+        prompt: String,
+        allow_empty: bool,
+        validator: Box<validators::GenericValidator>,
+    ) -> Result<String, std::io::Error> {
+        let theme = CliTheme::default();
+        let mut input: Input<String> = Input::with_theme(&theme);
+
+        input
+            .with_prompt(prompt)
+            .allow_empty(allow_empty)
+            .validate_with(validator)
+            .interact()
+    }
+
+    pub fn select(prompt: String, options: &[String], default: usize) -> Option<usize> {
+        let theme = CliTheme::default();
+        let mut select_obj = Select::with_theme(&theme);
+        select_obj.with_prompt(prompt);
+        select_obj.items(options).default(default).interact().ok()
+    }
+
+    pub fn preset_input(prompt: String, preset: String) -> Option<String> {
+        let theme = CliTheme::default();
+        let mut input: Input<String> = Input::with_theme(&theme);
+
+        input.with_prompt(prompt).default(preset).interact().ok()
+    }
+
+    pub fn confirm(prompt: String, default: bool) -> bool {
+        Confirm::with_theme(&CliTheme::default())
+            .with_prompt(prompt)
+            .wait_for_newline(false)
+            .default(default)
+            .show_default(true)
+            .interact()
+            .unwrap_or(default)
+    }
+}
+
+// Builds without a terminal answer every prompt in auto_answer, or exit there
+#[cfg(not(feature = "tty"))]
+mod dialog {
+    use super::validators;
+
+    const UNREACHABLE: &str = "prompts are answered by auto_answer in builds without a terminal";
+
+    pub fn input(_prompt: String, _allow_empty: bool) -> Result<String, std::io::Error> {
+        unreachable!("{UNREACHABLE}")
+    }
+
+    pub fn validated_input(
+        _prompt: String,
+        _allow_empty: bool,
+        _validator: Box<validators::GenericValidator>,
+    ) -> Result<String, std::io::Error> {
+        unreachable!("{UNREACHABLE}")
+    }
+
+    pub fn select(_prompt: String, _options: &[String], _default: usize) -> Option<usize> {
+        unreachable!("{UNREACHABLE}")
+    }
+
+    pub fn preset_input(_prompt: String, _preset: String) -> Option<String> {
+        unreachable!("{UNREACHABLE}")
+    }
+
+    pub fn confirm(_prompt: String, _default: bool) -> bool {
+        unreachable!("{UNREACHABLE}")
+    }
 }
 
 /// To make quiet mode integration more simple
@@ -139,10 +223,12 @@ where
 ///
 /// There may be an argument for implementing this using Deref
 /// To coerce OptPB to a PB silently, but unsure
+#[cfg(feature = "tty")]
 pub struct OptionalProgressBar {
     bar: Option<ProgressBar>,
 }
 
+#[cfg(feature = "tty")]
 impl OptionalProgressBar {
     pub fn new_spinner(quiet: bool) -> Self {
         Self {
@@ -181,6 +267,7 @@ impl OptionalProgressBar {
     }
 }
 
+#[cfg(feature = "tty")]
 pub fn start_spinner(msg: &str, quiet: bool) -> OptionalProgressBar {
     let pb = OptionalProgressBar::new_spinner(quiet);
     pb.enable_steady_tick(core::time::Duration::from_millis(200));
@@ -194,3 +281,31 @@ pub fn start_spinner(msg: &str, quiet: bool) -> OptionalProgressBar {
     pb.set_message(msg.into());
     pb
 }
+
+/// Builds without a terminal log the spinner's messages as status lines instead
+#[cfg(not(feature = "tty"))]
+pub struct OptionalProgressBar {
+    enabled: bool,
+}
+
+#[cfg(not(feature = "tty"))]
+impl OptionalProgressBar {
+    pub fn finish_with_message(&self, msg: String) {
+        self.set_message(msg)
+    }
+
+    pub fn set_message(&self, msg: String) {
+        if self.enabled {
+            log::info!("{msg}");
+        }
+    }
+
+    pub fn finish(&self) {}
+}
+
+#[cfg(not(feature = "tty"))]
+pub fn start_spinner(msg: &str, quiet: bool) -> OptionalProgressBar {
+    let pb = OptionalProgressBar { enabled: quiet };
+    pb.set_message(msg.into());
+    pb
+}
@@ -0,0 +1,91 @@
+use crate::{errors, CmdOutput};
+use clap::Parser;
+use common::api::oauth::{OAuthClient, OAuthError};
+use common::CliError;
+use thiserror::Error;
+
+/// Log in to Evervault in the browser, storing a session in the OS keychain which Enclave
+/// commands use in place of an API key
+#[derive(Debug, Parser)]
+#[command(name = "login", about)]
+pub struct LoginArgs {}
+
+#[derive(Error, Debug)]
+pub enum LoginError {
+    #[error(transparent)]
+    OAuth(#[from] OAuthError),
+    #[error("The login was approved, but no refresh token was issued for the CLI")]
+    MissingRefreshToken,
+    #[error("Failed to save the login session to the OS keychain - {0}")]
+    Keychain(#[from] keyring::Error),
+}
+
+impl CmdOutput for LoginError {
+    fn exitcode(&self) -> errors::ExitCode {
+        match self {
+            Self::OAuth(e) => e.exitcode(),
+            Self::MissingRefreshToken => errors::PROTOCOL,
+            Self::Keychain(_) => errors::OSERR,
+        }
+    }
+
+    fn code(&self) -> String {
+        match self {
+            Self::OAuth(_) | Self::MissingRefreshToken => "generic/login-error",
+            Self::Keychain(_) => "generic/keychain-error",
+        }
+        .to_string()
+    }
+
+    fn data(&self) -> Option<serde_json::Value> {
+        None
+    }
+}
+
+#[derive(strum_macros::Display)]
+pub enum LoginMessage {
+    #[strum(to_string = "Logged in to {domain}")]
+    LoggedIn { domain: String },
+}
+
+impl CmdOutput for LoginMessage {
+    fn exitcode(&self) -> errors::ExitCode {
+        errors::OK
+    }
+
+    fn code(&self) -> String {
+        "generic/success".to_string()
+    }
+
+    fn data(&self) -> Option<serde_json::Value> {
+        None
+    }
+}
+
+pub async fn run(_: LoginArgs) -> Result<LoginMessage, LoginError> {
+    let client = OAuthClient::new();
+    let authorization = client.request_device_authorization().await?;
+
+    match authorization.verification_uri_complete.as_deref() {
+        Some(uri) => log::info!(
+            "To log in, open {uri} and confirm the code {}",
+            authorization.user_code
+        ),
+        None => log::info!(
+            "To log in, open {} and enter the code {}",
+            authorization.verification_uri,
+            authorization.user_code
+        ),
+    }
+    log::info!("Waiting for the login to be approved...");
+
+    let tokens = client.poll_for_token(&authorization).await?;
+    let refresh_token = tokens
+        .refresh_token
+        .ok_or(LoginError::MissingRefreshToken)?;
+    crate::auth::store_refresh_token(&refresh_token)?;
+
+    Ok(LoginMessage::LoggedIn {
+        domain: common::endpoint::current().domain().to_string(),
+    })
+}
@@ -0,0 +1,58 @@
+use crate::{errors, CmdOutput};
+use clap::Parser;
+use thiserror::Error;
+
+/// Remove the login session stored by `ev login` from the OS keychain
+#[derive(Debug, Parser)]
+#[command(name = "logout", about)]
+pub struct LogoutArgs {}
+
+#[derive(Error, Debug)]
+pub enum LogoutError {
+    #[error("Failed to remove the login session from the OS keychain - {0}")]
+    Keychain(#[from] keyring::Error),
+}
+
+impl CmdOutput for LogoutError {
+    fn exitcode(&self) -> errors::ExitCode {
+        errors::OSERR
+    }
+
+    fn code(&self) -> String {
+        "generic/keychain-error".to_string()
+    }
+
+    fn data(&self) -> Option<serde_json::Value> {
+        None
+    }
+}
+
+#[derive(strum_macros::Display)]
+pub enum LogoutMessage {
+    #[strum(to_string = "Logged out")]
+    LoggedOut,
+    #[strum(to_string = "Not logged in")]
+    NotLoggedIn,
+}
+
+impl CmdOutput for LogoutMessage {
+    fn exitcode(&self) -> errors::ExitCode {
+        errors::OK
+    }
+
+    fn code(&self) -> String {
+        "generic/success".to_string()
+    }
+
+    fn data(&self) -> Option<serde_json::Value> {
+        None
+    }
+}
+
+pub async fn run(_: LogoutArgs) -> Result<LogoutMessage, LogoutError> {
+    if crate::auth::delete_refresh_token()? {
+        Ok(LogoutMessage::LoggedOut)
+    } else {
+        Ok(LogoutMessage::NotLoggedIn)
+    }
+}
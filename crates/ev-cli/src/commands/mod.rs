@@ -1,47 +1,90 @@
 use self::{
     decrypt::DecryptArgs, enclave::EnclaveArgs, encrypt::EncryptArgs, function::FunctionArgs,
-    relay::RelayArgs, update::UpdateArgs,
+    login::LoginArgs, logout::LogoutArgs, relay::RelayArgs, support_bundle::SupportBundleArgs,
+    update::UpdateArgs, verify_install::VerifyInstallArgs,
 };
 use super::run_cmd;
 use crate::{print_and_exit, BaseArgs};
 use clap::Parser;
 
+mod dashboard;
 mod decrypt;
 mod enclave;
 mod encrypt;
 mod function;
 mod interact;
+mod login;
+mod logout;
 mod relay;
+mod support_bundle;
 mod update;
+mod verify_install;
 
 #[derive(Parser, Debug)]
 pub enum Command {
-    Enclave(EnclaveArgs),
+    Enclave(Box<EnclaveArgs>),
     Relay(RelayArgs),
     Function(FunctionArgs),
     Update(UpdateArgs),
+    Login(LoginArgs),
+    Logout(LogoutArgs),
     Encrypt(EncryptArgs),
     Decrypt(DecryptArgs),
+    VerifyInstall(VerifyInstallArgs),
+    SupportBundle(SupportBundleArgs),
 }
 
 pub async fn run(base_args: BaseArgs) {
+    // Without a command, show a starting point rather than clap's usage error
+    let Some(command) = base_args.command else {
+        run_cmd(dashboard::run());
+    };
+    // Verifying an install shouldn't be blocked by the version check, as it's run on old versions
+    if let Command::VerifyInstall(verify_args) = command {
+        run_cmd(verify_install::run(verify_args).await);
+    }
+    // Nor should gathering diagnostics, which is often needed on an outdated install
+    if let Command::SupportBundle(support_args) = command {
+        run_cmd(support_bundle::run(support_args).await);
+    }
+
     if let Ok(Some(version_msg)) = crate::version::check_version().await {
         print_and_exit(version_msg, true);
     };
 
-    match base_args.command {
+    match command {
         Command::Update(update_args) => run_cmd(update::run(update_args).await),
+        Command::Login(login_args) => run_cmd(login::run(login_args).await),
+        Command::Logout(logout_args) => run_cmd(logout::run(logout_args).await),
         _ => {}
     }
 
-    let auth = crate::get_auth();
+    // `enclave which` reports on the credentials in effect, so must run without them, and
+    // `enclave nitro` and `enclave debug` only run nitro-cli locally
+    if let Command::Enclave(enclave_args) = &command {
+        match &enclave_args.action {
+            enclave::EnclaveCommand::Which(which_args) => run_cmd(enclave::which::run(which_args)),
+            enclave::EnclaveCommand::Nitro(nitro_args) => run_cmd(enclave::nitro::run(nitro_args)),
+            enclave::EnclaveCommand::Debug(debug_args) => run_cmd(enclave::debug::run(debug_args)),
+            _ => {}
+        }
+    }
+
+    // Login sessions authenticate Enclave commands only, the other commands need an API key
+    let auth = crate::get_auth(matches!(command, Command::Enclave(_))).await;
 
-    match base_args.command {
-        Command::Enclave(enclave_args) => enclave::run(enclave_args, auth).await,
+    match command {
+        Command::Enclave(enclave_args) => enclave::run(*enclave_args, auth).await,
         Command::Relay(relay_args) => relay::run(relay_args, auth).await,
         Command::Function(function_args) => function::run(function_args, auth).await,
         Command::Encrypt(encrypt_args) => run_cmd(encrypt::run(encrypt_args, auth).await),
         Command::Decrypt(decrypt_args) => run_cmd(decrypt::run(decrypt_args, auth).await),
-        Command::Update(_) => unreachable!("infallible: matched previously"),
+        Command::Update(_)
+        | Command::Login(_)
+        | Command::Logout(_)
+        | Command::VerifyInstall(_)
+        | Command::SupportBundle(_) => {
+            unreachable!("infallible: matched previously")
+        }
     }
 }
@@ -0,0 +1,138 @@
+use crate::config::support_directory;
+use crate::support::{LAST_BUILD_LOG, RECENT_ERRORS};
+use crate::{errors, BaseArgs, CmdOutput};
+use clap::Parser;
+use common::CliError;
+use ev_enclave::support::{SupportBundle, SupportBundleError, SystemReport};
+use std::path::Path;
+
+/// Gather sanitized diagnostics into a tarball to attach to support tickets. API keys, secrets and environment values are redacted.
+#[derive(Debug, Parser)]
+#[command(name = "support-bundle", about)]
+pub struct SupportBundleArgs {
+    /// Path to enclave.toml config file
+    #[arg(
+        short = 'c',
+        long = "config",
+        default_value = "./enclave.toml",
+        env = "EV_ENCLAVE_CONFIG"
+    )]
+    pub config: String,
+
+    /// Path to write the bundle to. Defaults to ev-support-bundle-<timestamp>.tar.gz in the current directory.
+    #[arg(short = 'o', long = "output")]
+    pub output: Option<String>,
+}
+
+impl CmdOutput for SupportBundleError {
+    fn exitcode(&self) -> errors::ExitCode {
+        CliError::exitcode(self)
+    }
+
+    fn code(&self) -> String {
+        match self {
+            Self::InvalidConfig(_) => "enclaves/config-error",
+            Self::Write(..) => "generic/io-error",
+        }
+        .to_string()
+    }
+
+    fn data(&self) -> Option<serde_json::Value> {
+        None
+    }
+}
+
+pub struct SupportBundleMessage {
+    path: String,
+    files: Vec<String>,
+    skipped: Vec<String>,
+}
+
+impl std::fmt::Display for SupportBundleMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Support bundle written to {} with {}. Attach it to your support ticket.",
+            self.path,
+            self.files.join(", ")
+        )?;
+        for reason in &self.skipped {
+            write!(f, "\n{reason}")?;
+        }
+        Ok(())
+    }
+}
+
+impl CmdOutput for SupportBundleMessage {
+    fn exitcode(&self) -> errors::ExitCode {
+        errors::OK
+    }
+
+    fn code(&self) -> String {
+        "generic/success".to_string()
+    }
+
+    fn data(&self) -> Option<serde_json::Value> {
+        BaseArgs::parse().json.then(|| {
+            serde_json::json!({
+                "path": self.path,
+                "files": self.files,
+                "skipped": self.skipped,
+            })
+        })
+    }
+}
+
+fn add_recorded(bundle: &mut SupportBundle, name: &str, missing: &str) {
+    let recorded = support_directory().and_then(|dir| std::fs::read_to_string(dir.join(name)).ok());
+    match recorded {
+        Some(contents) => bundle.add_text(name, &contents),
+        None => bundle.skip(missing),
+    }
+}
+
+pub async fn run(
+    support_args: SupportBundleArgs,
+) -> Result<SupportBundleMessage, SupportBundleError> {
+    let mut bundle = SupportBundle::new();
+    bundle.add_json(
+        "system.json",
+        &SystemReport::collect(env!("CARGO_PKG_VERSION")),
+    );
+
+    // A config which can't be parsed can't be redacted, so it's left out rather than shared as is
+    match std::fs::read_to_string(&support_args.config) {
+        Ok(contents) => {
+            if let Err(e) = bundle.add_config("enclave.toml", &contents) {
+                bundle.skip(format!("{} was left out - {e}", support_args.config));
+            }
+        }
+        Err(e) => bundle.skip(format!(
+            "No Enclave config was read from {} - {e}",
+            support_args.config
+        )),
+    }
+    add_recorded(
+        &mut bundle,
+        LAST_BUILD_LOG,
+        "No build log has been recorded. Run `ev enclave build` to record one.",
+    );
+    add_recorded(
+        &mut bundle,
+        RECENT_ERRORS,
+        "No errors have been recorded by this CLI.",
+    );
+
+    let path = support_args.output.unwrap_or_else(|| {
+        format!(
+            "ev-support-bundle-{}.tar.gz",
+            chrono::Utc::now().format("%Y%m%dT%H%M%SZ")
+        )
+    });
+    bundle.write(Path::new(&path))?;
+    Ok(SupportBundleMessage {
+        files: bundle.file_names().into_iter().map(String::from).collect(),
+        skipped: bundle.skipped().to_vec(),
+        path,
+    })
+}
@@ -0,0 +1,296 @@
+use crate::{errors, CmdOutput};
+use clap::Parser;
+use common::api::client::{ApiClient, ApiError, GenericApiClient};
+use common::api::{assets::AssetsClient, compat};
+use sha2::{Digest, Sha256};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Check the installed CLI binary matches the checksum published for its release
+#[derive(Debug, Parser)]
+#[command(name = "verify-install", about)]
+pub struct VerifyInstallArgs {
+    /// Report which features this CLI and the Evervault API both support, instead of verifying the binary
+    #[arg(long = "api-compat")]
+    pub api_compat: bool,
+}
+
+#[derive(Error, Debug)]
+pub enum VerifyInstallError {
+    #[error("Failed to find the installed CLI binary - {0}")]
+    LocateBinary(std::io::Error),
+    #[error("Failed to read the installed CLI binary at {} - {source}", path.display())]
+    ReadBinary {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Failed to fetch the release manifest for version {version} - {source}")]
+    FetchManifest { version: String, source: ApiError },
+    #[error("The release manifest for version {version} has no binary for {target}, so this install can't be verified")]
+    UnknownTarget { version: String, target: String },
+    #[error("The CLI binary at {} has been replaced with version {latest}, but the running CLI is version {version}. The update didn't complete, run ev update to finish it.", path.display())]
+    PartialUpdate {
+        path: PathBuf,
+        version: String,
+        latest: String,
+    },
+    #[error("The CLI binary at {} doesn't match the published release for version {version}. It may have been tampered with or corrupted, reinstall it from https://evervault.com/docs/cli.", path.display())]
+    ChecksumMismatch {
+        path: PathBuf,
+        version: String,
+        expected: String,
+        actual: String,
+    },
+    #[error("Failed to reach the Evervault API to check compatibility - {0}")]
+    ApiCompat(ApiError),
+}
+
+impl CmdOutput for VerifyInstallError {
+    fn exitcode(&self) -> i32 {
+        match self {
+            Self::LocateBinary(_) | Self::ReadBinary { .. } => errors::IOERR,
+            Self::FetchManifest { .. } => errors::SOFTWARE,
+            Self::UnknownTarget { .. } | Self::ApiCompat(_) => errors::UNAVAILABLE,
+            Self::PartialUpdate { .. } | Self::ChecksumMismatch { .. } => errors::DATAERR,
+        }
+    }
+
+    fn code(&self) -> String {
+        match self {
+            Self::LocateBinary(_) | Self::ReadBinary { .. } => "generic/io-error",
+            Self::FetchManifest { .. } | Self::ApiCompat(_) => "generic/api-error",
+            Self::UnknownTarget { .. } => "verify-install/unknown-target",
+            Self::PartialUpdate { .. } => "verify-install/partial-update",
+            Self::ChecksumMismatch { .. } => "verify-install/checksum-mismatch",
+        }
+        .to_string()
+    }
+
+    fn data(&self) -> Option<serde_json::Value> {
+        match self {
+            Self::ChecksumMismatch {
+                path,
+                version,
+                expected,
+                actual,
+            } => Some(serde_json::json!({
+                "path": path,
+                "version": version,
+                "expected": expected,
+                "actual": actual,
+            })),
+            _ => None,
+        }
+    }
+}
+
+#[derive(strum_macros::Display)]
+pub enum VerifyInstallMessage {
+    #[strum(
+        to_string = "The CLI binary at {path} matches the published release for version {version}"
+    )]
+    Verified {
+        path: String,
+        version: String,
+        sha256: String,
+    },
+    #[strum(to_string = "{summary}")]
+    ApiCompat {
+        summary: String,
+        report: compat::CompatReport,
+    },
+}
+
+impl CmdOutput for VerifyInstallMessage {
+    fn exitcode(&self) -> i32 {
+        errors::OK
+    }
+
+    fn code(&self) -> String {
+        match self {
+            Self::Verified { .. } => "verify-install/verified",
+            Self::ApiCompat { .. } => "verify-install/api-compat",
+        }
+        .to_string()
+    }
+
+    fn data(&self) -> Option<serde_json::Value> {
+        match self {
+            Self::Verified {
+                path,
+                version,
+                sha256,
+            } => Some(serde_json::json!({
+                "path": path,
+                "version": version,
+                "sha256": sha256,
+            })),
+            Self::ApiCompat { report, .. } => serde_json::to_value(report).ok(),
+        }
+    }
+}
+
+fn describe_compat(report: &compat::CompatReport) -> String {
+    let Some(api_schema_version) = report.api_schema_version else {
+        return format!(
+            "The API didn't report its schema version, so CLI {} assumes it supports all {} features",
+            report.cli_version,
+            report.features.len()
+        );
+    };
+    let unsupported: Vec<&str> = report
+        .features
+        .iter()
+        .filter(|feature| feature.supported == Some(false))
+        .map(|feature| feature.name.as_str())
+        .collect();
+    let summary = format!(
+        "CLI {} (schema v{}) and the API (schema v{api_schema_version}) support {} of {} features",
+        report.cli_version,
+        report.cli_schema_version,
+        report.features.len() - unsupported.len(),
+        report.features.len()
+    );
+    if unsupported.is_empty() {
+        summary
+    } else {
+        format!("{summary}. Unsupported: {}", unsupported.join(", "))
+    }
+}
+
+// The API negotiates its schema on every response, including ones it rejects for lacking
+// credentials, so this works without logging in
+async fn api_compat() -> Result<VerifyInstallMessage, VerifyInstallError> {
+    let api_client = GenericApiClient::default();
+    let response = api_client
+        .get(&api_client.base_url())
+        .send()
+        .await
+        .map_err(|e| VerifyInstallError::ApiCompat(e.into()))?;
+    compat::record_response(&response);
+    let report = compat::negotiated().unwrap_or_default().report();
+    Ok(VerifyInstallMessage::ApiCompat {
+        summary: describe_compat(&report),
+        report,
+    })
+}
+
+/// The key for this platform's binary in a release manifest, e.g. linux-x86_64
+fn release_target() -> String {
+    format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH)
+}
+
+fn hash_binary(path: &Path) -> std::io::Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+async fn published_checksum(
+    assets_client: &AssetsClient,
+    version: &str,
+) -> Result<String, VerifyInstallError> {
+    let manifest = assets_client
+        .get_cli_release_manifest(version)
+        .await
+        .map_err(|source| VerifyInstallError::FetchManifest {
+            version: version.to_string(),
+            source,
+        })?;
+    manifest
+        .binary(&release_target())
+        .map(|binary| binary.sha256.to_ascii_lowercase())
+        .ok_or_else(|| VerifyInstallError::UnknownTarget {
+            version: version.to_string(),
+            target: release_target(),
+        })
+}
+
+pub async fn run(args: VerifyInstallArgs) -> Result<VerifyInstallMessage, VerifyInstallError> {
+    if args.api_compat {
+        return api_compat().await;
+    }
+
+    let path = std::env::current_exe().map_err(VerifyInstallError::LocateBinary)?;
+    let actual = hash_binary(&path).map_err(|source| VerifyInstallError::ReadBinary {
+        path: path.clone(),
+        source,
+    })?;
+    log::debug!("SHA-256 of {}: {actual}", path.display());
+
+    let version = env!("CARGO_PKG_VERSION");
+    let assets_client = AssetsClient::new();
+    let expected = published_checksum(&assets_client, version).await?;
+    if actual == expected {
+        return Ok(VerifyInstallMessage::Verified {
+            path: path.display().to_string(),
+            version: version.to_string(),
+            sha256: actual,
+        });
+    }
+
+    // An update replaces the binary on disk while this process keeps running the old one, so a
+    // binary matching the latest release means an update was interrupted rather than tampering.
+    if let Ok(latest) = assets_client.get_latest_cli_version().await {
+        let latest = latest.trim().to_string();
+        if latest != version
+            && published_checksum(&assets_client, &latest)
+                .await
+                .is_ok_and(|latest_checksum| latest_checksum == actual)
+        {
+            return Err(VerifyInstallError::PartialUpdate {
+                path,
+                version: version.to_string(),
+                latest,
+            });
+        }
+    }
+
+    Err(VerifyInstallError::ChecksumMismatch {
+        path,
+        version: version.to_string(),
+        expected,
+        actual,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_hash_binary() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, b"ev").unwrap();
+        assert_eq!(
+            hash_binary(file.path()).unwrap(),
+            "67d35554c1f60638d6d72a0edc045450a841f7efabe0f9843b7e653499094538"
+        );
+    }
+
+    #[test]
+    fn test_describe_compat() {
+        let mut negotiated = compat::Negotiated {
+            api_version: Some(1),
+            ..Default::default()
+        };
+        let summary = describe_compat(&negotiated.report());
+        assert!(summary.ends_with(
+            "(schema v2) and the API (schema v1) support 1 of 4 features. Unsupported: build-steps, replica-rollover, multipart-uploads"
+        ));
+
+        negotiated.api_version = None;
+        assert!(
+            describe_compat(&negotiated.report()).ends_with("assumes it supports all 4 features")
+        );
+    }
+}
@@ -0,0 +1,103 @@
+use clap_complete::engine::{ArgValueCompleter, CompletionCandidate};
+use evervault_api_client::cache::ResponseCache;
+use evervault_api_client::enclave::{Enclave, EnclaveApi, EnclaveClient};
+use std::ffi::OsStr;
+use std::time::Duration;
+
+use crate::config::{api_cache_directory, CliConfig};
+
+// Completions are requested on every tab, so Enclaves are listed at most once every few minutes
+const COMPLETION_CACHE_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// Completes `--enclave-uuid` with the uuids of the app's Enclaves. Typing the start of an
+/// Enclave's name also completes its uuid.
+pub fn enclave_uuid_completer() -> ArgValueCompleter {
+    ArgValueCompleter::new(|current: &OsStr| enclave_uuid_candidates(&list_enclaves(), current))
+}
+
+fn enclave_uuid_candidates(enclaves: &[Enclave], current: &OsStr) -> Vec<CompletionCandidate> {
+    let Some(current) = current.to_str() else {
+        return vec![];
+    };
+    let mut matching: Vec<&Enclave> = enclaves
+        .iter()
+        .filter(|enclave| enclave.uuid.starts_with(current) || enclave.name.starts_with(current))
+        .collect();
+    matching.sort_by(|a, b| a.name.cmp(&b.name));
+    matching
+        .into_iter()
+        .map(|enclave| {
+            CompletionCandidate::new(&enclave.uuid).help(Some(enclave.name.clone().into()))
+        })
+        .collect()
+}
+
+// Completions can't report errors, so Enclaves which can't be listed complete to nothing
+pub(crate) fn list_enclaves() -> Vec<Enclave> {
+    let Some(auth) = crate::auth::completion_auth_mode() else {
+        return vec![];
+    };
+    let endpoint = std::env::var("EV_ENDPOINT")
+        .ok()
+        .and_then(|endpoint| endpoint.parse().ok())
+        .or_else(|| CliConfig::load().ok()?.endpoint);
+    if let Some(endpoint) = endpoint {
+        common::endpoint::select_endpoint(endpoint);
+    }
+
+    let mut enclave_client = EnclaveClient::new(auth);
+    if let Some(directory) = api_cache_directory() {
+        enclave_client = enclave_client
+            .with_response_cache(ResponseCache::new(directory).with_ttl(COMPLETION_CACHE_TTL));
+    }
+    // Completers are called from within the CLI's runtime, so the request runs on its own thread
+    std::thread::spawn(move || {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .ok()?;
+        runtime.block_on(enclave_client.get_enclaves()).ok()
+    })
+    .join()
+    .ok()
+    .flatten()
+    .map(|response| response.enclaves().clone())
+    .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn enclave(uuid: &str, name: &str) -> Enclave {
+        serde_json::from_value(serde_json::json!({
+            "uuid": uuid,
+            "name": name,
+            "teamUuid": "team_123",
+            "appUuid": "app_123",
+            "domain": format!("{name}.app-123.enclave.evervault.com"),
+            "state": "active",
+            "createdAt": "2024-01-01T00:00:00Z",
+            "updatedAt": "2024-01-01T00:00:00Z",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_enclave_uuid_candidates() {
+        let enclaves = vec![
+            enclave("enclave_b2", "payments"),
+            enclave("enclave_a1", "hello"),
+        ];
+        let values = |current: &str| -> Vec<String> {
+            enclave_uuid_candidates(&enclaves, OsStr::new(current))
+                .iter()
+                .map(|candidate| candidate.get_value().to_string_lossy().to_string())
+                .collect()
+        };
+        assert_eq!(values(""), vec!["enclave_a1", "enclave_b2"]);
+        assert_eq!(values("enclave_b"), vec!["enclave_b2"]);
+        assert_eq!(values("pay"), vec!["enclave_b2"]);
+        assert!(values("missing").is_empty());
+    }
+}
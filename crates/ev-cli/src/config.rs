@@ -0,0 +1,105 @@
+use common::endpoint::Endpoint;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use thiserror::Error;
+
+const CLI_CONFIG_DIRECTORY: &str = ".evervault";
+const CLI_CONFIG_FILENAME: &str = "config";
+
+#[derive(Debug, Error)]
+pub enum CliConfigError {
+    #[error("Failed to read the CLI config at {0} - {1}")]
+    Io(String, std::io::Error),
+    #[error("Failed to parse the CLI config at {0} - {1}")]
+    Parse(String, toml::de::Error),
+}
+
+/// Global CLI configuration, read from `~/.evervault/config`
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct CliConfig {
+    #[serde(default)]
+    pub credentials: Option<crate::auth::CredentialProvider>,
+    /// The Evervault environment to use when --endpoint isn't given
+    #[serde(
+        default,
+        deserialize_with = "deserialize_endpoint",
+        serialize_with = "serialize_endpoint",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub endpoint: Option<Endpoint>,
+    /// The docker context Enclave builds run on when --remote-builder isn't given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote_builder: Option<String>,
+}
+
+fn deserialize_endpoint<'de, D>(deserializer: D) -> Result<Option<Endpoint>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Option::<String>::deserialize(deserializer)?
+        .map(|endpoint| endpoint.parse().map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+fn serialize_endpoint<S>(endpoint: &Option<Endpoint>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    endpoint
+        .as_ref()
+        .map(ToString::to_string)
+        .serialize(serializer)
+}
+
+impl CliConfig {
+    pub fn parse(contents: &str, path: &str) -> Result<Self, CliConfigError> {
+        toml::from_str(contents).map_err(|e| CliConfigError::Parse(path.to_string(), e))
+    }
+
+    /// Load the global CLI config, falling back to the default config if no file exists
+    pub fn load() -> Result<Self, CliConfigError> {
+        let Some(path) = cli_config_path() else {
+            return Ok(Self::default());
+        };
+        let path_str = path.display().to_string();
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => Self::parse(&contents, &path_str),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(CliConfigError::Io(path_str, e)),
+        }
+    }
+}
+
+pub fn cli_config_directory() -> Option<PathBuf> {
+    let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+    Some(PathBuf::from(home).join(CLI_CONFIG_DIRECTORY))
+}
+
+/// Where the attestation root of trust is pinned by `ev enclave trust fetch`
+pub fn trust_store_directory() -> Option<PathBuf> {
+    cli_config_directory().map(|dir| dir.join("trust"))
+}
+
+/// Where diagnostics for `ev support-bundle` are recorded, such as the last build's log
+pub fn support_directory() -> Option<PathBuf> {
+    cli_config_directory().map(|dir| dir.join("support"))
+}
+
+/// Where the Dockerfiles generated by each CLI version are cached for `ev enclave dockerfile diff`
+pub fn dockerfile_cache_directory() -> Option<PathBuf> {
+    cli_config_directory().map(|dir| dir.join("cache/dockerfiles"))
+}
+
+/// Where read-only API responses are cached for `ev enclave list` and shell completions
+pub fn api_cache_directory() -> Option<PathBuf> {
+    cli_config_directory().map(|dir| dir.join("cache/api"))
+}
+
+/// Where the last command run is recorded, for the dashboard shown when `ev` is run on its own
+pub fn last_command_path() -> Option<PathBuf> {
+    cli_config_directory().map(|dir| dir.join("last-command.json"))
+}
+
+pub fn cli_config_path() -> Option<PathBuf> {
+    cli_config_directory().map(|dir| dir.join(CLI_CONFIG_FILENAME))
+}
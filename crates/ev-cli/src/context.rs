@@ -0,0 +1,101 @@
+use clap::ArgMatches;
+use serde::{Deserialize, Serialize};
+
+use crate::config::last_command_path;
+
+/// The last command run, recorded so `ev` on its own can show where the user left off. Only the
+/// subcommands and Enclave uuid are kept, as other arguments can contain secrets.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct LastCommand {
+    pub command: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enclave_uuid: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub directory: Option<String>,
+    pub ran_at: String,
+}
+
+fn last_command_from_matches(matches: &ArgMatches) -> Option<LastCommand> {
+    let mut command = vec!["ev".to_string()];
+    let mut leaf = matches;
+    while let Some((name, sub_matches)) = leaf.subcommand() {
+        command.push(name.to_string());
+        leaf = sub_matches;
+    }
+    if command.len() == 1 {
+        return None;
+    }
+    // Commands without an --enclave-uuid arg report it as undefined rather than unset
+    let enclave_uuid = leaf
+        .try_get_one::<String>("enclave_uuid")
+        .ok()
+        .flatten()
+        .cloned();
+    Some(LastCommand {
+        command: command.join(" "),
+        enclave_uuid,
+        directory: std::env::current_dir()
+            .ok()
+            .map(|dir| dir.display().to_string()),
+        ran_at: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+    })
+}
+
+/// Records the command being run. Failing to record it never fails the command.
+pub fn record_last_command(matches: &ArgMatches) {
+    let (Some(path), Some(last_command)) =
+        (last_command_path(), last_command_from_matches(matches))
+    else {
+        return;
+    };
+    let result = path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| {
+            let contents =
+                serde_json::to_vec(&last_command).expect("Failed to serialize the last command");
+            std::fs::write(&path, contents)
+        });
+    if let Err(e) = result {
+        log::debug!("Failed to record the last command - {e}");
+    }
+}
+
+pub fn load_last_command() -> Option<LastCommand> {
+    let contents = std::fs::read_to_string(last_command_path()?).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::BaseArgs;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_last_command_from_matches() {
+        let matches = BaseArgs::command()
+            .try_get_matches_from([
+                "ev",
+                "enclave",
+                "logs",
+                "--enclave-uuid",
+                "enclave_123",
+                "--api-key",
+                "secret",
+            ])
+            .unwrap();
+        let last_command = last_command_from_matches(&matches).unwrap();
+        assert_eq!(last_command.command, "ev enclave logs");
+        assert_eq!(last_command.enclave_uuid.as_deref(), Some("enclave_123"));
+        assert!(!serde_json::to_string(&last_command)
+            .unwrap()
+            .contains("secret"));
+
+        let matches = BaseArgs::command()
+            .try_get_matches_from(["ev", "--json"])
+            .unwrap();
+        assert!(last_command_from_matches(&matches).is_none());
+    }
+}
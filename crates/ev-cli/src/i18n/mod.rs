@@ -0,0 +1,132 @@
+//! Translations of user-facing messages, with the locale selected by EV_LANG. Messages are looked
+//! up by their code, which is never translated, so scripts relying on codes behave the same in
+//! every locale. Messages without a translation are shown in English.
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+const MESSAGE_PLACEHOLDER: &str = "{message}";
+
+static CATALOG: OnceLock<Option<Catalog>> = OnceLock::new();
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Locale {
+    En,
+    De,
+    Es,
+    Fr,
+}
+
+impl Locale {
+    /// Parses a locale such as `de`, `de-AT` or `de_DE.UTF-8`, by its language alone
+    pub fn parse(value: &str) -> Option<Self> {
+        let language = value
+            .split(['_', '-', '.'])
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+        match language.as_str() {
+            "en" => Some(Self::En),
+            "de" => Some(Self::De),
+            "es" => Some(Self::Es),
+            "fr" => Some(Self::Fr),
+            _ => None,
+        }
+    }
+
+    fn from_env() -> Self {
+        match std::env::var("EV_LANG") {
+            Ok(value) => Self::parse(&value).unwrap_or_else(|| {
+                log::debug!("No messages are available for EV_LANG={value}, using English");
+                Self::En
+            }),
+            Err(_) => Self::En,
+        }
+    }
+
+    fn catalog_source(&self) -> Option<&'static str> {
+        match self {
+            Self::En => None,
+            Self::De => Some(include_str!("de.toml")),
+            Self::Es => Some(include_str!("es.toml")),
+            Self::Fr => Some(include_str!("fr.toml")),
+        }
+    }
+}
+
+/// Message templates for a locale, keyed by message code. `{message}` in a template is replaced
+/// with the English message, which carries details such as paths and API errors.
+#[derive(Debug)]
+pub struct Catalog {
+    templates: HashMap<String, String>,
+}
+
+impl Catalog {
+    pub fn load(locale: Locale) -> Option<Self> {
+        let templates = toml::from_str(locale.catalog_source()?)
+            .expect("Infallible - bundled message catalogs are valid");
+        Some(Self { templates })
+    }
+
+    pub fn translate(&self, code: &str, message: &str) -> Option<String> {
+        self.templates
+            .get(code)
+            .map(|template| template.replace(MESSAGE_PLACEHOLDER, message))
+    }
+}
+
+/// The message in the locale selected by EV_LANG, or as is when it has no translation
+pub fn localize(code: &str, message: String) -> String {
+    CATALOG
+        .get_or_init(|| Catalog::load(Locale::from_env()))
+        .as_ref()
+        .and_then(|catalog| catalog.translate(code, &message))
+        .unwrap_or(message)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_locale() {
+        assert_eq!(Locale::parse("de"), Some(Locale::De));
+        assert_eq!(Locale::parse("fr-CA"), Some(Locale::Fr));
+        assert_eq!(Locale::parse("es_ES.UTF-8"), Some(Locale::Es));
+        assert_eq!(Locale::parse("EN"), Some(Locale::En));
+        assert_eq!(Locale::parse("pt_BR"), None);
+        assert_eq!(Locale::parse(""), None);
+    }
+
+    #[test]
+    fn test_catalogs_cover_the_same_codes() {
+        assert!(Catalog::load(Locale::En).is_none());
+        let catalogs: Vec<Catalog> = [Locale::De, Locale::Es, Locale::Fr]
+            .into_iter()
+            .map(|locale| Catalog::load(locale).unwrap())
+            .collect();
+        let mut codes: Vec<&String> = catalogs[0].templates.keys().collect();
+        codes.sort();
+        for catalog in &catalogs {
+            let mut catalog_codes: Vec<&String> = catalog.templates.keys().collect();
+            catalog_codes.sort();
+            assert_eq!(catalog_codes, codes);
+            for (code, template) in &catalog.templates {
+                assert!(code.starts_with("enclaves/"), "{code}");
+                assert!(template.contains(MESSAGE_PLACEHOLDER), "{code}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_translate() {
+        let catalog = Catalog::load(Locale::De).unwrap();
+        assert_eq!(
+            catalog.translate("enclaves/config-error", "Failed to read ./enclave.toml"),
+            Some(
+                "Die Enclave-Konfiguration ist ungültig — Failed to read ./enclave.toml"
+                    .to_string()
+            )
+        );
+        assert_eq!(catalog.translate("generic/success", "Done"), None);
+    }
+}
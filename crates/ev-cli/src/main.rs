@@ -1,6 +1,7 @@
 use atty::Stream;
-use clap::Parser;
+use clap::{CommandFactory, Parser};
 use commands::Command;
+use common::endpoint::Endpoint;
 use env_logger::fmt::Formatter;
 use env_logger::{Builder, Env};
 use human_panic::setup_panic;
@@ -10,13 +11,21 @@ use std::io::{IsTerminal, Write};
 
 mod auth;
 mod commands;
+mod completion;
+mod config;
+mod context;
 mod errors;
 mod fs;
 mod function;
+mod i18n;
 mod relay;
+mod support;
+mod table;
+#[cfg(feature = "tty")]
 mod theme;
 mod tty;
 mod version;
+mod workspace;
 
 pub use auth::get_auth;
 
@@ -40,11 +49,15 @@ where
     T: CmdOutput,
 {
     let base_args = BaseArgs::parse();
+    if is_error {
+        support::record_error(&output.code(), output.exitcode());
+    }
 
+    let message = i18n::localize(&output.code(), output.to_string());
     let msg = if base_args.json {
-        fmt_json(&output, is_error)
+        fmt_json(&output, message, is_error)
     } else {
-        output.to_string()
+        message
     };
 
     // when a we have json data to display in a non-json output, print it to stdout and print
@@ -63,12 +76,12 @@ where
     std::process::exit(output.exitcode());
 }
 
-fn fmt_json<T>(output: &T, is_error: bool) -> String
+fn fmt_json<T>(output: &T, message: String, is_error: bool) -> String
 where
     T: CmdOutput,
 {
     let mut json = serde_json::json!({
-        "message": output.to_string(),
+        "message": message,
         "code": output.code(),
         "is_error": is_error
     });
@@ -97,12 +110,37 @@ pub struct BaseArgs {
     #[clap(long, global = true)]
     pub json: bool,
 
+    /// Write progress to stdout as newline delimited JSON events instead of drawing progress bars
+    #[clap(long = "progress-json", global = true, env = "EV_PROGRESS_JSON")]
+    pub progress_json: bool,
+
+    /// Accept confirmation prompts automatically and fail when a prompt has no default, instead of waiting for input
+    #[clap(short = 'y', long = "yes", global = true, env = "EV_NONINTERACTIVE")]
+    pub yes: bool,
+
+    /// API key to authenticate with. Takes precedence over the credentials in ~/.evervault/config and the session from ev login.
+    #[clap(
+        long = "api-key",
+        global = true,
+        env = "EV_API_KEY",
+        hide_env_values = true
+    )]
+    pub api_key: Option<String>,
+
+    /// Evervault environment to use: production, staging, or the domain of a custom environment. Defaults to the endpoint in ~/.evervault/config.
+    #[clap(long = "endpoint", global = true, env = "EV_ENDPOINT")]
+    pub endpoint: Option<Endpoint>,
+
+    /// Shows the current context and suggested commands when not given
     #[clap(subcommand)]
-    pub command: Command,
+    pub command: Option<Command>,
 }
 
 #[tokio::main]
 async fn main() {
+    // Answers completion requests from the shell scripts printed by `COMPLETE=<shell> ev`
+    clap_complete::CompleteEnv::with_factory(|| BaseArgs::command().name("ev")).complete();
+
     // Use human panic to give nicer error logs in the case of a runtime panic
     setup_panic!(Metadata {
         name: env!("CARGO_PKG_NAME").into(),
@@ -113,10 +151,37 @@ async fn main() {
 
     let base_args: BaseArgs = BaseArgs::parse();
     setup_logger(base_args.verbose);
+    if base_args.progress_json {
+        ev_enclave::progress::enable_progress_json();
+    }
+    if base_args.yes {
+        ev_enclave::prompt::enable_non_interactive();
+    }
+    select_endpoint(base_args.endpoint.clone());
+    if let Ok(matches) = BaseArgs::command().try_get_matches() {
+        context::record_last_command(&matches);
+    }
+    #[cfg(feature = "sentry")]
     setup_sentry();
     commands::run(base_args).await;
 }
 
+// The endpoint given on the command line takes precedence over the CLI config, and EV_DOMAIN is
+// used when neither sets one.
+fn select_endpoint(endpoint: Option<Endpoint>) {
+    let endpoint = endpoint.or_else(|| match config::CliConfig::load() {
+        Ok(cli_config) => cli_config.endpoint,
+        Err(e) => {
+            log::debug!("Failed to read the endpoint from the CLI config - {e}");
+            None
+        }
+    });
+    if let Some(endpoint) = endpoint {
+        log::debug!("Using the {endpoint} endpoint");
+        common::endpoint::select_endpoint(endpoint);
+    }
+}
+
 fn setup_logger(verbose_logging: bool) {
     let env = Env::new()
         .filter_or("EV_LOG", "INFO")
@@ -124,6 +189,7 @@ fn setup_logger(verbose_logging: bool) {
     let mut builder = Builder::from_env(env);
 
     let log_formatter = |buf: &mut Formatter, record: &Record| {
+        support::capture_log_line(record);
         // If stderr is being piped elsewhere, add timestamps and remove colors
         if atty::isnt(Stream::Stderr) {
             let timestamp = buf.timestamp_millis();
@@ -156,6 +222,7 @@ fn setup_logger(verbose_logging: bool) {
     builder.format(log_formatter).init();
 }
 
+#[cfg(feature = "sentry")]
 fn setup_sentry() {
     if cfg!(not(debug_assertions)) {
         let _ = sentry::init((
@@ -167,3 +234,64 @@ fn setup_sentry() {
         ));
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ev_cli_derive::CliMessage;
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("the config is invalid")]
+    struct ConfigError;
+
+    impl common::CliError for ConfigError {
+        fn exitcode(&self) -> exitcode::ExitCode {
+            exitcode::CONFIG
+        }
+    }
+
+    #[derive(Debug, thiserror::Error, CliMessage)]
+    #[cli(code = "test/error", exitcode = exitcode::SOFTWARE)]
+    enum TestError {
+        #[error("Failed to read the config — {0}")]
+        #[cli(code = "test/config-error")]
+        Config(#[cli(exitcode)] ConfigError),
+        #[error("{name} is already taken")]
+        #[cli(exitcode = exitcode::DATAERR)]
+        NameTaken {
+            #[cli(data)]
+            name: String,
+        },
+        #[error("Nothing to report")]
+        Empty(#[cli(data)] Option<String>),
+        #[error("Something went wrong")]
+        Unknown,
+    }
+
+    #[test]
+    fn test_derived_cli_message() {
+        let config = TestError::Config(ConfigError);
+        assert_eq!(
+            config.to_string(),
+            "Failed to read the config — the config is invalid"
+        );
+        assert_eq!(config.code(), "test/config-error");
+        assert_eq!(config.exitcode(), exitcode::CONFIG);
+        assert_eq!(config.data(), None);
+
+        let name_taken = TestError::NameTaken {
+            name: "hello".into(),
+        };
+        assert_eq!(name_taken.to_string(), "hello is already taken");
+        assert_eq!(name_taken.code(), "test/error");
+        assert_eq!(name_taken.exitcode(), exitcode::DATAERR);
+        assert_eq!(name_taken.data(), Some(serde_json::json!("hello")));
+
+        // data which serializes to null is omitted
+        assert_eq!(TestError::Empty(None).data(), None);
+
+        let unknown = TestError::Unknown;
+        assert_eq!(unknown.code(), "test/error");
+        assert_eq!(unknown.exitcode(), exitcode::SOFTWARE);
+    }
+}
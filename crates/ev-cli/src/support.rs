@@ -0,0 +1,113 @@
+//! Diagnostics recorded as the CLI runs, so `ev support-bundle` can include them. Recording is
+//! best effort and never fails a command.
+use crate::config::support_directory;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::Write;
+use std::sync::Mutex;
+
+pub const LAST_BUILD_LOG: &str = "last-build.log";
+pub const RECENT_ERRORS: &str = "recent-errors.jsonl";
+const MAX_RECENT_ERRORS: usize = 20;
+
+static CAPTURED_LOG: Mutex<Option<File>> = Mutex::new(None);
+
+/// An error the CLI exited with. Only the code is kept, as messages can include user data.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordedError {
+    pub timestamp: String,
+    pub cli_version: String,
+    pub code: String,
+    pub exitcode: i32,
+}
+
+/// Copies the log lines of the rest of this command to `~/.evervault/support/last-build.log`,
+/// replacing the previous build's log
+pub fn capture_build_log() {
+    let Some(directory) = support_directory() else {
+        return;
+    };
+    let file = std::fs::create_dir_all(&directory)
+        .and_then(|_| File::create(directory.join(LAST_BUILD_LOG)));
+    match (file, CAPTURED_LOG.lock()) {
+        (Ok(file), Ok(mut captured)) => *captured = Some(file),
+        (Err(e), _) => log::debug!("Failed to create the build log - {e}"),
+        (_, Err(_)) => {}
+    }
+}
+
+pub fn capture_log_line(record: &log::Record) {
+    if let Ok(mut captured) = CAPTURED_LOG.lock() {
+        if let Some(file) = captured.as_mut() {
+            let timestamp = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+            let _ = writeln!(
+                file,
+                "[{timestamp} {}] {}",
+                record.metadata().level(),
+                record.args()
+            );
+        }
+    }
+}
+
+// Keeps the most recent errors, oldest first, one JSON object per line
+fn append_error(recorded: &str, error: RecordedError) -> String {
+    let mut lines: Vec<&str> = recorded.lines().filter(|line| !line.is_empty()).collect();
+    let serialized = serde_json::to_string(&error).expect("Infallible - serializable error");
+    lines.push(&serialized);
+    let keep_from = lines.len().saturating_sub(MAX_RECENT_ERRORS);
+    lines[keep_from..]
+        .iter()
+        .map(|line| format!("{line}\n"))
+        .collect()
+}
+
+pub fn record_error(code: &str, exitcode: i32) {
+    let Some(directory) = support_directory() else {
+        return;
+    };
+    let path = directory.join(RECENT_ERRORS);
+    let recorded = std::fs::read_to_string(&path).unwrap_or_default();
+    let error = RecordedError {
+        timestamp: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        cli_version: env!("CARGO_PKG_VERSION").to_string(),
+        code: code.to_string(),
+        exitcode,
+    };
+    let result = std::fs::create_dir_all(&directory)
+        .and_then(|_| std::fs::write(&path, append_error(&recorded, error)));
+    if let Err(e) = result {
+        log::debug!("Failed to record the error for support bundles - {e}");
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn error(code: &str) -> RecordedError {
+        RecordedError {
+            timestamp: "2024-05-01T12:00:00Z".to_string(),
+            cli_version: "4.1.2".to_string(),
+            code: code.to_string(),
+            exitcode: 1,
+        }
+    }
+
+    #[test]
+    fn test_append_error_keeps_most_recent() {
+        let recorded = (0..MAX_RECENT_ERRORS).fold(String::new(), |recorded, index| {
+            append_error(&recorded, error(&format!("generic/error-{index}")))
+        });
+        let recorded = append_error(&recorded, error("generic/api-error"));
+
+        let codes: Vec<String> = recorded
+            .lines()
+            .map(|line| serde_json::from_str::<RecordedError>(line).unwrap().code)
+            .collect();
+        assert_eq!(codes.len(), MAX_RECENT_ERRORS);
+        assert_eq!(codes.first().unwrap(), "generic/error-1");
+        assert_eq!(codes.last().unwrap(), "generic/api-error");
+    }
+}
@@ -0,0 +1,94 @@
+use atty::Stream;
+use clap::{Args, ValueEnum};
+use common::table::{Table, TableError};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Table,
+    Json,
+}
+
+/// Options shared by commands which print a table
+#[derive(Clone, Debug, Default, Args)]
+pub struct TableArgs {
+    /// The output format. Defaults to a table in interactive terminals, and JSON otherwise.
+    #[arg(long = "output", value_enum, global = true)]
+    pub output: Option<OutputFormat>,
+
+    /// Comma separated list of columns to show, in order
+    #[arg(long = "columns", value_delimiter = ',', global = true)]
+    pub columns: Vec<String>,
+
+    /// Column to sort rows by
+    #[arg(long = "sort-by", global = true)]
+    pub sort_by: Option<String>,
+
+    /// Omit the header row, for scripting
+    #[arg(long = "no-header", global = true)]
+    pub no_header: bool,
+}
+
+impl TableArgs {
+    fn is_set(&self) -> bool {
+        !self.columns.is_empty() || self.sort_by.is_some() || self.no_header
+    }
+
+    /// Tables are printed to terminals, or when any table option is given. Otherwise, and whenever
+    /// --json or --output json is passed, commands print JSON.
+    pub fn use_table(&self, json: bool) -> bool {
+        match self.output {
+            _ if json => false,
+            Some(output) => output == OutputFormat::Table,
+            None => self.is_set() || atty::is(Stream::Stdout),
+        }
+    }
+
+    pub fn render(&self, mut table: Table) -> Result<String, TableError> {
+        if let Some(sort_by) = self.sort_by.as_deref() {
+            table.sort_by(sort_by)?;
+        }
+        if !self.columns.is_empty() {
+            table.select_columns(&self.columns)?;
+        }
+        Ok(table.render(!self.no_header))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_render_with_table_args() {
+        let mut table = Table::new([("name", "NAME"), ("state", "STATE"), ("uuid", "UUID")]);
+        table.push_row(vec!["web".into(), "active".into(), "enclave_2".into()]);
+        table.push_row(vec!["api".into(), "pending".into(), "enclave_1".into()]);
+
+        // rows can be sorted by a column which isn't selected
+        let args = TableArgs {
+            columns: vec!["uuid".into(), "state".into()],
+            sort_by: Some("name".into()),
+            no_header: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            args.render(table.clone()).unwrap(),
+            "enclave_1  pending\nenclave_2  active\n"
+        );
+        assert!(args.use_table(false));
+        assert!(!args.use_table(true));
+
+        let args = TableArgs {
+            output: Some(OutputFormat::Json),
+            no_header: true,
+            ..Default::default()
+        };
+        assert!(!args.use_table(false));
+
+        let args = TableArgs {
+            sort_by: Some("created".into()),
+            ..Default::default()
+        };
+        assert!(args.render(table).is_err());
+    }
+}
@@ -1,5 +1,6 @@
 use atty::Stream;
 
+#[cfg(feature = "tty")]
 #[derive(Debug)]
 pub struct AttyReport {
     pub stdout: bool,
@@ -7,6 +8,7 @@ pub struct AttyReport {
     pub stdin: bool,
 }
 
+#[cfg(feature = "tty")]
 impl AttyReport {
     fn is_stdout_atty() -> bool {
         atty::is(Stream::Stdout)
@@ -21,6 +23,7 @@ impl AttyReport {
     }
 }
 
+#[cfg(feature = "tty")]
 impl Default for AttyReport {
     fn default() -> Self {
         Self {
@@ -30,3 +33,9 @@ impl Default for AttyReport {
         }
     }
 }
+
+/// Commands output their data as JSON when --json is passed or stdout isn't a terminal, and only
+/// a summary for people otherwise
+pub fn outputs_json(json: bool) -> bool {
+    json || !atty::is(Stream::Stdout)
+}
@@ -0,0 +1,147 @@
+use atty::Stream;
+use clap::Args;
+use common::CliError;
+use ev_enclave::workspace::{
+    combined_exitcode, summary_table, MemberReport, Workspace, WorkspaceError, WorkspaceMember,
+};
+use serde::{Serialize, Serializer};
+use std::time::Instant;
+
+use crate::CmdOutput;
+
+/// Options for running a command against every Enclave in an evervault.toml workspace
+#[derive(Clone, Debug, Default, Args)]
+pub struct WorkspaceArgs {
+    /// Run against every member of the evervault.toml workspace
+    #[arg(long = "all")]
+    pub all: bool,
+
+    /// Path to the evervault.toml workspace file. Defaults to the nearest one in the current directory or its parents.
+    #[arg(long = "workspace", env = "EV_WORKSPACE")]
+    pub workspace: Option<String>,
+}
+
+impl WorkspaceArgs {
+    pub fn load_workspace(&self) -> Result<Workspace, WorkspaceError> {
+        match self.workspace.as_deref() {
+            Some(path) => Workspace::try_from_filepath(std::path::Path::new(path)),
+            None => std::env::current_dir()
+                .map_err(|e| WorkspaceError::Io(".".into(), e))
+                .and_then(|current_dir| Workspace::discover(&current_dir)),
+        }
+    }
+}
+
+/// Paths from a member's enclave.toml, resolved against the member's directory so commands can
+/// run from anywhere in the workspace
+pub struct MemberPaths {
+    pub config: String,
+    pub context_path: String,
+    pub dockerfile: String,
+    pub certificate: Option<String>,
+    pub private_key: Option<String>,
+}
+
+impl From<&WorkspaceMember> for MemberPaths {
+    fn from(member: &WorkspaceMember) -> Self {
+        Self {
+            config: member.config_path.display().to_string(),
+            context_path: member.directory.display().to_string(),
+            dockerfile: member.resolve_path(member.config.dockerfile()),
+            certificate: member.config.cert().map(|cert| member.resolve_path(cert)),
+            private_key: member.config.key().map(|key| member.resolve_path(key)),
+        }
+    }
+}
+
+/// Times a command run against a workspace member, and records its outcome
+pub async fn report_member<F, E>(member: &WorkspaceMember, run: F) -> MemberReport
+where
+    F: std::future::Future<Output = Result<Option<String>, E>>,
+    E: CmdOutput,
+{
+    log::info!("[{}] Starting...", member.path);
+    let started_at = Instant::now();
+    let result = run.await;
+    let (exit_code, detail) = match result {
+        Ok(detail) => (exitcode::OK, detail),
+        Err(e) => {
+            log::error!("[{}] {e}", member.path);
+            (e.exitcode(), None)
+        }
+    };
+    MemberReport {
+        member: member.path.clone(),
+        enclave_name: member.name().to_string(),
+        exit_code,
+        elapsed_seconds: started_at.elapsed().as_secs(),
+        detail,
+    }
+}
+
+/// The combined report of a run across the workspace. It's shown as a table in terminals, and
+/// serialized as the command's data otherwise.
+#[derive(Debug)]
+pub struct WorkspaceSummary {
+    reports: Vec<MemberReport>,
+    table: bool,
+}
+
+impl WorkspaceSummary {
+    pub fn new(reports: Vec<MemberReport>, json: bool) -> Self {
+        Self {
+            reports,
+            table: !json && atty::is(Stream::Stdout),
+        }
+    }
+
+    pub fn succeeded(&self) -> bool {
+        self.reports.iter().all(MemberReport::succeeded)
+    }
+}
+
+impl CliError for WorkspaceSummary {
+    fn exitcode(&self) -> exitcode::ExitCode {
+        combined_exitcode(&self.reports)
+    }
+}
+
+impl std::fmt::Display for WorkspaceSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let failures = self
+            .reports
+            .iter()
+            .filter(|report| !report.succeeded())
+            .count();
+        if self.table {
+            writeln!(
+                f,
+                "{}",
+                summary_table(&self.reports).render(true).trim_end()
+            )?;
+        }
+        if failures > 0 {
+            write!(
+                f,
+                "{failures} of {} workspace members failed",
+                self.reports.len()
+            )
+        } else {
+            write!(f, "All {} workspace members succeeded", self.reports.len())
+        }
+    }
+}
+
+// The table already shows every member, so no data is printed alongside it
+impl Serialize for WorkspaceSummary {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.table {
+            return serializer.serialize_none();
+        }
+        let summary = serde_json::json!({
+            "status": if self.succeeded() { "success" } else { "failed" },
+            "members": self.reports,
+        });
+        summary.serialize(serializer)
+    }
+}
@@ -1,3 +1,56 @@
-pub mod enclave;
+//! The Enclaves API client is maintained in the `evervault-api-client` crate, and re-exported
+//! here along with the conversions from the CLI's config.
+use crate::config::ValidatedEnclaveBuildConfig;
+use enclave::{DeploymentSettings, SigningRotation};
 
-pub use reqwest::Client;
+pub use evervault_api_client::{cache, enclave, Client};
+
+impl From<&ValidatedEnclaveBuildConfig> for DeploymentSettings {
+    fn from(config: &ValidatedEnclaveBuildConfig) -> Self {
+        Self {
+            debug_mode: config.debug,
+            egress_enabled: config.egress.enabled,
+            egress_domains: config.egress.destinations.clone(),
+            trusted_headers: config.trusted_headers().to_vec(),
+            not_before: config.signing.not_before(),
+            not_after: config.signing.not_after(),
+            signing_rotation: config.signing.next.as_ref().map(|next| SigningRotation {
+                next_pcr8: next.pcr8.clone(),
+                not_before: next.cert_validity_period.not_before.clone(),
+                not_after: next.cert_validity_period.not_after.clone(),
+            }),
+            healthcheck: config.healthcheck().map(String::from),
+            protocol: config.protocol(),
+            desired_replicas: config
+                .scaling
+                .as_ref()
+                .map(|scaling| scaling.desired_replicas),
+            enclave_type: config.enclave_type(),
+            restart_policy: config.restart_policy(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::RestartPolicy;
+
+    #[test]
+    fn test_deployment_settings_from_config() {
+        let mut config = crate::build::test::get_config(false);
+        config.restart_policy = RestartPolicy::OnFailure;
+        let settings = DeploymentSettings::from(&config);
+        assert_eq!(settings.debug_mode, config.debug);
+        assert_eq!(settings.trusted_headers, config.trusted_headers());
+        assert_eq!(settings.not_after, config.signing.not_after());
+        assert_eq!(settings.restart_policy, RestartPolicy::OnFailure);
+        assert_eq!(
+            settings.desired_replicas,
+            config
+                .scaling
+                .as_ref()
+                .map(|scaling| scaling.desired_replicas)
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use common::CliError;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -22,6 +23,48 @@ pub enum AttestCommandError {
     InvalidHostname(#[from] tokio_rustls::rustls::client::InvalidDnsNameError),
     #[error(transparent)]
     DNSLookupFailure(#[from] tokio::time::error::Elapsed),
+    #[error("Failed to generate attestation fixtures - {0}")]
+    FixtureGenerationError(String),
     #[error(transparent)]
     X509CertError(#[from] x509_parser::error::X509Error),
+    #[error(transparent)]
+    TrustStore(#[from] TrustStoreError),
+    #[error("Couldn't connect to {0} - {1}")]
+    InvalidTarget(String, String),
+    #[error("The server didn't present a certificate")]
+    NoCertificatePresented,
+}
+
+#[derive(Debug, Error)]
+pub enum TrustStoreError {
+    #[error("Failed to download {0} - {1}")]
+    Download(String, reqwest::Error),
+    #[error("Failed to access the trust store at {0} - {1}")]
+    Io(String, std::io::Error),
+    #[error("Failed to parse the trust store manifest at {0} - {1}")]
+    InvalidManifest(String, serde_json::Error),
+    #[error("Invalid certificate - {0}")]
+    InvalidCertificate(String),
+    #[error("The downloaded AWS Nitro root has fingerprint {0}, which doesn't match the fingerprint published by AWS")]
+    RootFingerprintMismatch(String),
+    #[error("The certificate {0} has expired")]
+    ExpiredCertificate(String),
+    #[error("The attestation doc was signed by a root with fingerprint {0}, which doesn't match the pinned AWS Nitro root")]
+    UnpinnedRoot(String),
+    #[error("The Enclave's certificate wasn't issued by a pinned Evervault intermediate. Run `ev enclave trust fetch` if the intermediates have been rotated.")]
+    UnpinnedIntermediate,
+}
+
+impl CliError for TrustStoreError {
+    fn exitcode(&self) -> exitcode::ExitCode {
+        match self {
+            Self::Download(_, _) => exitcode::UNAVAILABLE,
+            Self::Io(_, _) => exitcode::IOERR,
+            Self::InvalidManifest(_, _) => exitcode::CONFIG,
+            Self::InvalidCertificate(_) | Self::ExpiredCertificate(_) => exitcode::DATAERR,
+            Self::RootFingerprintMismatch(_)
+            | Self::UnpinnedRoot(_)
+            | Self::UnpinnedIntermediate => exitcode::NOPERM,
+        }
+    }
 }
@@ -0,0 +1,155 @@
+use crate::config::{EnclaveConfig, EnclaveConfigError};
+use crate::enclave::{EIFMeasurements, PCRs};
+use serde::Serialize;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// A locals block which Terraform modules can reference directly
+    Terraform,
+    #[default]
+    Json,
+}
+
+/// Everything a client needs to pin an Enclave: where to reach it and the measurements it should
+/// attest to.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrustedEnclaveConfig {
+    pub name: String,
+    pub uuid: String,
+    pub app_uuid: String,
+    pub domain: String,
+    pub pcrs: PCRs,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pcrs_signature: Option<String>,
+}
+
+impl TrustedEnclaveConfig {
+    pub fn new(
+        config: &EnclaveConfig,
+        measurements: &EIFMeasurements,
+    ) -> Result<Self, EnclaveConfigError> {
+        Ok(Self {
+            name: config.name.clone(),
+            uuid: config
+                .uuid
+                .clone()
+                .ok_or_else(|| EnclaveConfigError::MissingField("uuid".into()))?,
+            app_uuid: config
+                .app_uuid
+                .clone()
+                .ok_or_else(|| EnclaveConfigError::MissingField("app_uuid".into()))?,
+            domain: config.get_enclave_domain()?,
+            pcrs: measurements.pcrs().clone(),
+            pcrs_signature: measurements.signature().map(String::from),
+        })
+    }
+
+    pub fn render(&self, format: ExportFormat) -> String {
+        match format {
+            ExportFormat::Json => serde_json::to_string_pretty(self).unwrap(),
+            ExportFormat::Terraform => self.to_terraform(),
+        }
+    }
+
+    /// The local is named after the Enclave, so exports for several Enclaves can share a module
+    fn terraform_local_name(&self) -> String {
+        let name: String = self
+            .name
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() {
+                    c.to_ascii_lowercase()
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        format!("evervault_enclave_{name}")
+    }
+
+    fn to_terraform(&self) -> String {
+        let optional = |value: Option<&str>| value.map(hcl_string).unwrap_or("null".into());
+        format!(
+            r#"# Generated by `ev enclave attest export`. Export again after each deploy to keep clients pinned to the deployed Enclave.
+locals {{
+  {local} = {{
+    name     = {name}
+    uuid     = {uuid}
+    app_uuid = {app_uuid}
+    domain   = {domain}
+    pcrs = {{
+      pcr0 = {pcr0}
+      pcr1 = {pcr1}
+      pcr2 = {pcr2}
+      pcr8 = {pcr8}
+    }}
+    pcrs_signature = {signature}
+  }}
+}}
+"#,
+            local = self.terraform_local_name(),
+            name = hcl_string(&self.name),
+            uuid = hcl_string(&self.uuid),
+            app_uuid = hcl_string(&self.app_uuid),
+            domain = hcl_string(&self.domain),
+            pcr0 = hcl_string(&self.pcrs.pcr0),
+            pcr1 = hcl_string(&self.pcrs.pcr1),
+            pcr2 = hcl_string(&self.pcrs.pcr2),
+            pcr8 = optional(self.pcrs.pcr8.as_deref()),
+            signature = optional(self.pcrs_signature.as_deref()),
+        )
+    }
+}
+
+// Quotes a string for HCL, escaping template sequences so values are never interpolated
+fn hcl_string(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace("${", "$${")
+        .replace("%{", "%%{");
+    format!("\"{escaped}\"")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::pcr;
+
+    fn trusted_config() -> TrustedEnclaveConfig {
+        TrustedEnclaveConfig {
+            name: "payments-api".into(),
+            uuid: "enclave_123".into(),
+            app_uuid: "app_123".into(),
+            domain: "payments-api.app-123.enclave.evervault.com".into(),
+            pcrs: PCRs {
+                pcr0: pcr("00"),
+                pcr1: pcr("11"),
+                pcr2: pcr("22"),
+                pcr8: None,
+            },
+            pcrs_signature: None,
+        }
+    }
+
+    #[test]
+    fn test_export_terraform() {
+        let rendered = trusted_config().render(ExportFormat::Terraform);
+        assert!(rendered.contains("  evervault_enclave_payments_api = {\n"));
+        assert!(
+            rendered.contains("    domain   = \"payments-api.app-123.enclave.evervault.com\"\n")
+        );
+        assert!(rendered.contains("      pcr8 = null\n"));
+        assert_eq!(hcl_string("a\"${b}"), r#""a\"$${b}""#);
+    }
+
+    #[test]
+    fn test_export_json() {
+        let rendered: serde_json::Value =
+            serde_json::from_str(&trusted_config().render(ExportFormat::Json)).unwrap();
+        assert_eq!(rendered["appUuid"], "app_123");
+        assert_eq!(rendered["pcrs"]["PCR0"], "00".repeat(48));
+        assert!(rendered.get("pcrsSignature").is_none());
+    }
+}
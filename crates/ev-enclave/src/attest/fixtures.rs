@@ -0,0 +1,528 @@
+use super::error::AttestCommandError;
+use crate::enclave::{EIFMeasurements, Pcr};
+use chrono::{DateTime, Datelike, Duration, Utc};
+use elliptic_curve::pkcs8::DecodePrivateKey;
+use pcr_sign::{EcdsaSig, PCRProvider, Signer, SigningKey};
+use rcgen::{BasicConstraints, Certificate, CertificateParams, DnType, IsCa};
+use serde::Serialize;
+use serde_cbor::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+const MODULE_ID: &str = "i-00000000000000000-enc0000000000000000";
+const PCR_LENGTH: usize = 48;
+const PCR_COUNT: i128 = 16;
+// COSE algorithm identifier for ECDSA with SHA-384
+const COSE_ALG_ES384: i128 = -35;
+const ATTESTATION_DOCS_DIRECTORY: &str = "attestation-docs";
+const SIGNED_PCRS_DIRECTORY: &str = "signed-pcrs";
+
+/// The PCRs of the local build, which every fixture is derived from
+#[derive(Clone, Debug, Serialize)]
+pub struct FixturePcrs {
+    #[serde(rename = "PCR0")]
+    pcr0: String,
+    #[serde(rename = "PCR1")]
+    pcr1: String,
+    #[serde(rename = "PCR2")]
+    pcr2: String,
+    #[serde(rename = "PCR8")]
+    pcr8: String,
+}
+
+impl From<&EIFMeasurements> for FixturePcrs {
+    fn from(measurements: &EIFMeasurements) -> Self {
+        let pcrs = measurements.pcrs();
+        Self {
+            pcr0: pcrs.pcr0.to_string(),
+            pcr1: pcrs.pcr1.to_string(),
+            pcr2: pcrs.pcr2.to_string(),
+            pcr8: pcrs
+                .pcr8
+                .as_ref()
+                .map_or_else(|| hex::encode([0u8; PCR_LENGTH]), Pcr::to_string),
+        }
+    }
+}
+
+impl PCRProvider for FixturePcrs {
+    fn pcr0(&self) -> &str {
+        &self.pcr0
+    }
+
+    fn pcr1(&self) -> &str {
+        &self.pcr1
+    }
+
+    fn pcr2(&self) -> &str {
+        &self.pcr2
+    }
+
+    fn pcr8(&self) -> &str {
+        &self.pcr8
+    }
+}
+
+impl FixturePcrs {
+    fn tampered(&self) -> Self {
+        let mut tampered = self.clone();
+        let mut pcr0 = hex::decode(&self.pcr0).unwrap_or_else(|_| vec![0u8; PCR_LENGTH]);
+        if let Some(byte) = pcr0.first_mut() {
+            *byte ^= 0xff;
+        }
+        tampered.pcr0 = hex::encode(pcr0);
+        tampered
+    }
+
+    fn to_cbor(&self) -> Result<Value, AttestCommandError> {
+        let mut pcrs = BTreeMap::new();
+        for index in 0..PCR_COUNT {
+            let value = match index {
+                0 => hex::decode(&self.pcr0)?,
+                1 => hex::decode(&self.pcr1)?,
+                2 => hex::decode(&self.pcr2)?,
+                8 => hex::decode(&self.pcr8)?,
+                _ => vec![0u8; PCR_LENGTH],
+            };
+            pcrs.insert(Value::Integer(index), Value::Bytes(value));
+        }
+        Ok(Value::Map(pcrs))
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FixtureVector {
+    name: &'static str,
+    path: String,
+    expected_valid: bool,
+    description: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FixtureManifest {
+    pcrs: FixturePcrs,
+    root_certificate: String,
+    attestation_docs: Vec<FixtureVector>,
+    signed_pcrs: Vec<FixtureVector>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SignedPcrsFixture<'a> {
+    pcrs: &'a FixturePcrs,
+    signature: String,
+    certificate: String,
+}
+
+struct TestCertificate {
+    cert: Certificate,
+    der: Vec<u8>,
+}
+
+impl TestCertificate {
+    fn generate(
+        common_name: &str,
+        issuer: Option<&TestCertificate>,
+        not_before: DateTime<Utc>,
+        not_after: DateTime<Utc>,
+    ) -> Result<Self, AttestCommandError> {
+        let mut params = CertificateParams::new(vec![]);
+        params.alg = &rcgen::PKCS_ECDSA_P384_SHA384;
+        params
+            .distinguished_name
+            .push(DnType::CommonName, common_name);
+        params.not_before = rcgen::date_time_ymd(
+            not_before.year(),
+            not_before.month() as u8,
+            not_before.day() as u8,
+        );
+        params.not_after = rcgen::date_time_ymd(
+            not_after.year(),
+            not_after.month() as u8,
+            not_after.day() as u8,
+        );
+        if issuer.is_none() {
+            params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        }
+
+        let cert = Certificate::from_params(params).map_err(fixture_error)?;
+        let der = match issuer {
+            Some(issuer) => cert.serialize_der_with_signer(&issuer.cert),
+            None => cert.serialize_der(),
+        }
+        .map_err(fixture_error)?;
+        Ok(Self { cert, der })
+    }
+
+    fn signing_key(&self) -> Result<SigningKey, AttestCommandError> {
+        SigningKey::from_pkcs8_der(&self.cert.serialize_private_key_der()).map_err(fixture_error)
+    }
+
+    fn pem(&self) -> String {
+        pem_encode(&self.der)
+    }
+}
+
+fn fixture_error<E: std::fmt::Display>(e: E) -> AttestCommandError {
+    AttestCommandError::FixtureGenerationError(e.to_string())
+}
+
+fn pem_encode(der: &[u8]) -> String {
+    let encoded = base64::encode(der);
+    let lines = encoded
+        .as_bytes()
+        .chunks(64)
+        .map(|chunk| String::from_utf8_lossy(chunk).to_string())
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!("-----BEGIN CERTIFICATE-----\n{lines}\n-----END CERTIFICATE-----\n")
+}
+
+fn to_cbor_bytes(value: &Value) -> Result<Vec<u8>, AttestCommandError> {
+    serde_cbor::to_vec(value).map_err(fixture_error)
+}
+
+fn attestation_doc_payload(
+    pcrs: &FixturePcrs,
+    certificate: &TestCertificate,
+    root: &TestCertificate,
+    timestamp: DateTime<Utc>,
+) -> Result<Vec<u8>, AttestCommandError> {
+    let text = |value: &str| Value::Text(value.to_string());
+    let document = BTreeMap::from([
+        (text("module_id"), text(MODULE_ID)),
+        (text("digest"), text("SHA384")),
+        (
+            text("timestamp"),
+            Value::Integer(timestamp.timestamp_millis() as i128),
+        ),
+        (text("pcrs"), pcrs.to_cbor()?),
+        (text("certificate"), Value::Bytes(certificate.der.clone())),
+        (
+            text("cabundle"),
+            Value::Array(vec![Value::Bytes(root.der.clone())]),
+        ),
+        (text("public_key"), Value::Null),
+        (text("user_data"), Value::Null),
+        (text("nonce"), Value::Null),
+    ]);
+    to_cbor_bytes(&Value::Map(document))
+}
+
+fn cose_protected_header() -> Result<Vec<u8>, AttestCommandError> {
+    to_cbor_bytes(&Value::Map(BTreeMap::from([(
+        Value::Integer(1),
+        Value::Integer(COSE_ALG_ES384),
+    )])))
+}
+
+fn cose_signature(
+    protected: &[u8],
+    payload: &[u8],
+    signing_key: &SigningKey,
+) -> Result<Vec<u8>, AttestCommandError> {
+    let signature_structure = to_cbor_bytes(&Value::Array(vec![
+        Value::Text("Signature1".to_string()),
+        Value::Bytes(protected.to_vec()),
+        Value::Bytes(vec![]),
+        Value::Bytes(payload.to_vec()),
+    ]))?;
+    let signature: EcdsaSig = signing_key.sign(&signature_structure);
+    Ok(signature.to_bytes().to_vec())
+}
+
+fn cose_sign1(
+    protected: Vec<u8>,
+    payload: Vec<u8>,
+    signature: Vec<u8>,
+) -> Result<Vec<u8>, AttestCommandError> {
+    to_cbor_bytes(&Value::Array(vec![
+        Value::Bytes(protected),
+        Value::Map(BTreeMap::new()),
+        Value::Bytes(payload),
+        Value::Bytes(signature),
+    ]))
+}
+
+fn write_fixture(
+    out_dir: &Path,
+    relative_path: &str,
+    contents: &[u8],
+) -> Result<String, AttestCommandError> {
+    let path = out_dir.join(relative_path);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, contents)?;
+    Ok(relative_path.to_string())
+}
+
+/// Generates attestation documents and signed PCR payloads for the given measurements, signed by a
+/// throwaway root CA written alongside them. Client libraries can trust that root in their tests to
+/// verify the valid vectors, and check that the expired and tampered vectors are rejected.
+pub fn generate_fixtures(
+    measurements: &EIFMeasurements,
+    out_dir: &Path,
+) -> Result<Vec<PathBuf>, AttestCommandError> {
+    let pcrs = FixturePcrs::from(measurements);
+    let now = Utc::now();
+
+    let root = TestCertificate::generate(
+        "Evervault Attestation Fixtures Root",
+        None,
+        now - Duration::days(1),
+        now + Duration::days(365),
+    )?;
+    let enclave_cert = TestCertificate::generate(
+        MODULE_ID,
+        Some(&root),
+        now - Duration::days(1),
+        now + Duration::days(30),
+    )?;
+    let expired_enclave_cert = TestCertificate::generate(
+        MODULE_ID,
+        Some(&root),
+        now - Duration::days(30),
+        now - Duration::days(2),
+    )?;
+
+    let root_certificate = write_fixture(out_dir, "root.pem", root.pem().as_bytes())?;
+    let protected = cose_protected_header()?;
+
+    let valid_payload = attestation_doc_payload(&pcrs, &enclave_cert, &root, now)?;
+    let valid_signature = cose_signature(&protected, &valid_payload, &enclave_cert.signing_key()?)?;
+    let expired_payload = attestation_doc_payload(&pcrs, &expired_enclave_cert, &root, now)?;
+    let expired_signature = cose_signature(
+        &protected,
+        &expired_payload,
+        &expired_enclave_cert.signing_key()?,
+    )?;
+    // the signature covers the original PCRs, so verifying the tampered document must fail
+    let tampered_payload = attestation_doc_payload(&pcrs.tampered(), &enclave_cert, &root, now)?;
+
+    let attestation_docs = vec![
+        FixtureVector {
+            name: "valid",
+            path: write_fixture(
+                out_dir,
+                &format!("{ATTESTATION_DOCS_DIRECTORY}/valid.cbor"),
+                &cose_sign1(protected.clone(), valid_payload, valid_signature.clone())?,
+            )?,
+            expected_valid: true,
+            description: "Signed by a certificate chaining to root.pem, with the PCRs of the build",
+        },
+        FixtureVector {
+            name: "expired",
+            path: write_fixture(
+                out_dir,
+                &format!("{ATTESTATION_DOCS_DIRECTORY}/expired.cbor"),
+                &cose_sign1(protected.clone(), expired_payload, expired_signature)?,
+            )?,
+            expected_valid: false,
+            description: "Signed by a certificate which expired before the document's timestamp",
+        },
+        FixtureVector {
+            name: "tampered",
+            path: write_fixture(
+                out_dir,
+                &format!("{ATTESTATION_DOCS_DIRECTORY}/tampered.cbor"),
+                &cose_sign1(protected, tampered_payload, valid_signature)?,
+            )?,
+            expected_valid: false,
+            description: "PCR0 was modified after the document was signed",
+        },
+    ];
+
+    let signing_cert = TestCertificate::generate(
+        "Evervault PCR Signing Fixture",
+        Some(&root),
+        now - Duration::days(1),
+        now + Duration::days(30),
+    )?;
+    let expired_signing_cert = TestCertificate::generate(
+        "Evervault PCR Signing Fixture",
+        Some(&root),
+        now - Duration::days(30),
+        now - Duration::days(2),
+    )?;
+    let sign_pcrs = |cert: &TestCertificate| -> Result<String, AttestCommandError> {
+        Ok(pcr_sign::Signature::new(
+            pcr_sign::SignatureVersion::default(),
+            &pcrs,
+            cert.signing_key()?,
+        )
+        .sign())
+    };
+    let signed_pcrs_fixture = |pcrs: &FixturePcrs, signature: String, cert: &TestCertificate| {
+        serde_json::to_vec_pretty(&SignedPcrsFixture {
+            pcrs,
+            signature,
+            certificate: cert.pem(),
+        })
+        .map_err(fixture_error)
+    };
+
+    let tampered_pcrs = pcrs.tampered();
+    let signed_pcrs = vec![
+        FixtureVector {
+            name: "valid",
+            path: write_fixture(
+                out_dir,
+                &format!("{SIGNED_PCRS_DIRECTORY}/valid.json"),
+                &signed_pcrs_fixture(&pcrs, sign_pcrs(&signing_cert)?, &signing_cert)?,
+            )?,
+            expected_valid: true,
+            description: "PCRs of the build signed by a certificate chaining to root.pem",
+        },
+        FixtureVector {
+            name: "expired",
+            path: write_fixture(
+                out_dir,
+                &format!("{SIGNED_PCRS_DIRECTORY}/expired.json"),
+                &signed_pcrs_fixture(
+                    &pcrs,
+                    sign_pcrs(&expired_signing_cert)?,
+                    &expired_signing_cert,
+                )?,
+            )?,
+            expected_valid: false,
+            description: "PCRs of the build signed by an expired certificate",
+        },
+        FixtureVector {
+            name: "tampered",
+            path: write_fixture(
+                out_dir,
+                &format!("{SIGNED_PCRS_DIRECTORY}/tampered.json"),
+                &signed_pcrs_fixture(&tampered_pcrs, sign_pcrs(&signing_cert)?, &signing_cert)?,
+            )?,
+            expected_valid: false,
+            description: "PCR0 was modified after the PCRs were signed",
+        },
+    ];
+
+    let manifest = FixtureManifest {
+        pcrs,
+        root_certificate,
+        attestation_docs,
+        signed_pcrs,
+    };
+    let manifest_path = write_fixture(
+        out_dir,
+        "manifest.json",
+        &serde_json::to_vec_pretty(&manifest).map_err(fixture_error)?,
+    )?;
+
+    Ok(std::iter::once(&manifest_path)
+        .chain(std::iter::once(&manifest.root_certificate))
+        .chain(manifest.attestation_docs.iter().map(|vector| &vector.path))
+        .chain(manifest.signed_pcrs.iter().map(|vector| &vector.path))
+        .map(|path| out_dir.join(path))
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pcr_sign::{_Verifier, Verifier, VerifyingKey};
+    use tempfile::TempDir;
+    use x509_parser::prelude::{parse_x509_pem, FromDer, X509Certificate};
+
+    fn get_measurements() -> EIFMeasurements {
+        serde_json::from_value(serde_json::json!({
+            "HashAlgorithm": "Sha384 { ... }",
+            "PCR0": "aa".repeat(PCR_LENGTH),
+            "PCR1": "bb".repeat(PCR_LENGTH),
+            "PCR2": "cc".repeat(PCR_LENGTH),
+            "PCR8": "dd".repeat(PCR_LENGTH)
+        }))
+        .unwrap()
+    }
+
+    fn verify_attestation_doc(path: &Path) -> bool {
+        let cose: Vec<Value> = serde_cbor::from_slice(&std::fs::read(path).unwrap()).unwrap();
+        let [Value::Bytes(protected), _, Value::Bytes(payload), Value::Bytes(signature)] =
+            &cose[..]
+        else {
+            panic!("Unexpected COSE structure");
+        };
+        let document: BTreeMap<String, Value> = serde_cbor::from_slice(payload).unwrap();
+        let Some(Value::Bytes(certificate)) = document.get("certificate") else {
+            panic!("Attestation doc is missing its certificate");
+        };
+        let (_, certificate) = X509Certificate::from_der(certificate).unwrap();
+        if !certificate.validity().is_valid() {
+            return false;
+        }
+        let verifying_key =
+            VerifyingKey::from_sec1_bytes(&certificate.public_key().subject_public_key.data)
+                .unwrap();
+        let signature_structure = to_cbor_bytes(&Value::Array(vec![
+            Value::Text("Signature1".to_string()),
+            Value::Bytes(protected.clone()),
+            Value::Bytes(vec![]),
+            Value::Bytes(payload.clone()),
+        ]))
+        .unwrap();
+        let signature = EcdsaSig::from_slice(signature).unwrap();
+        verifying_key
+            .verify(&signature_structure, &signature)
+            .is_ok()
+    }
+
+    fn verify_signed_pcrs(path: &Path) -> bool {
+        let fixture: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(path).unwrap()).unwrap();
+        let pcr = |name: &str| fixture["pcrs"][name].as_str().unwrap().to_string();
+        let pcrs = FixturePcrs {
+            pcr0: pcr("PCR0"),
+            pcr1: pcr("PCR1"),
+            pcr2: pcr("PCR2"),
+            pcr8: pcr("PCR8"),
+        };
+        let (_, pem) = parse_x509_pem(fixture["certificate"].as_str().unwrap().as_bytes()).unwrap();
+        let certificate = pem.parse_x509().unwrap();
+        if !certificate.validity().is_valid() {
+            return false;
+        }
+        let verifying_key =
+            VerifyingKey::from_sec1_bytes(&certificate.public_key().subject_public_key.data)
+                .unwrap();
+        Verifier::new(fixture["signature"].as_str().unwrap(), &pcrs, verifying_key)
+            .try_verify()
+            .is_ok()
+    }
+
+    #[test]
+    fn test_generate_fixtures() {
+        let out_dir = TempDir::new().unwrap();
+        let paths = generate_fixtures(&get_measurements(), out_dir.path()).unwrap();
+        assert_eq!(paths.len(), 8);
+        assert!(paths.iter().all(|path| path.exists()));
+
+        let manifest: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(out_dir.path().join("manifest.json")).unwrap())
+                .unwrap();
+        assert_eq!(manifest["pcrs"]["PCR0"], "aa".repeat(PCR_LENGTH));
+
+        for vector in manifest["attestationDocs"].as_array().unwrap() {
+            let path = out_dir.path().join(vector["path"].as_str().unwrap());
+            assert_eq!(
+                verify_attestation_doc(&path),
+                vector["expectedValid"].as_bool().unwrap(),
+                "attestation doc {}",
+                vector["name"]
+            );
+        }
+        for vector in manifest["signedPcrs"].as_array().unwrap() {
+            let path = out_dir.path().join(vector["path"].as_str().unwrap());
+            assert_eq!(
+                verify_signed_pcrs(&path),
+                vector["expectedValid"].as_bool().unwrap(),
+                "signed PCRs {}",
+                vector["name"]
+            );
+        }
+    }
+}
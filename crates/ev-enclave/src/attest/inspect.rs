@@ -0,0 +1,323 @@
+use super::error::AttestCommandError;
+use super::trust::fingerprint;
+use attestation_doc_validation::attestation_doc::decode_attestation_document;
+use attestation_doc_validation::cert::extract_signed_cose_sign_1_from_certificate;
+use attestation_doc_validation::validate_attestation_doc_in_cert;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use tokio_rustls::rustls::{
+    client::{ClientConfig, ServerCertVerified, ServerCertVerifier},
+    Certificate, RootCertStore,
+};
+use x509_parser::{certificate::X509Certificate, extensions::GeneralName, prelude::FromDer};
+
+/// Certs expiring within this many days are flagged as close to expiry
+const EXPIRY_WARNING_DAYS: i64 = 7;
+/// SAN labels longer than this are summarised rather than printed in full, as they hold the
+/// hex encoded attestation doc
+const MAX_SAN_LABEL_LENGTH: usize = 63;
+/// The PCRs Evervault Enclaves are attested against
+const ATTESTED_PCRS: [usize; 4] = [0, 1, 2, 8];
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CertificateSummary {
+    pub subject: String,
+    pub issuer: String,
+    pub serial: String,
+    pub fingerprint: String,
+    pub not_before: String,
+    pub not_after: String,
+    pub days_until_expiry: i64,
+    pub is_ca: bool,
+    pub subject_alt_names: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttestationDocSummary {
+    pub module_id: String,
+    pub timestamp: String,
+    pub pcrs: BTreeMap<String, String>,
+    pub user_data: Option<String>,
+    pub nonce: Option<String>,
+    pub public_key: Option<String>,
+    /// Whether the doc is signed by the AWS Nitro root and bound to the presented cert's key
+    pub valid: bool,
+    pub validation_error: Option<String>,
+}
+
+/// The certificate chain presented by an Enclave, and the attestation doc embedded in its leaf cert
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CertInspection {
+    pub host: String,
+    pub port: u16,
+    pub chain: Vec<CertificateSummary>,
+    pub attestation_doc: Option<AttestationDocSummary>,
+    pub attestation_doc_error: Option<String>,
+    pub warnings: Vec<String>,
+}
+
+/// Accepts any cert chain, recording it so it can be inspected after the handshake
+#[derive(Default)]
+struct ChainRecorder {
+    chain: Mutex<Vec<Vec<u8>>>,
+}
+
+impl ServerCertVerifier for ChainRecorder {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        intermediates: &[Certificate],
+        _server_name: &tokio_rustls::rustls::ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: std::time::SystemTime,
+    ) -> Result<ServerCertVerified, tokio_rustls::rustls::Error> {
+        let chain = std::iter::once(end_entity)
+            .chain(intermediates.iter())
+            .map(|cert| cert.0.clone())
+            .collect();
+        *self.chain.lock().expect("Chain lock poisoned") = chain;
+        Ok(ServerCertVerified::assertion())
+    }
+}
+
+/// Resolves the host and port to connect to from a URL or bare hostname
+pub fn parse_target(target: &str) -> Result<(String, u16), AttestCommandError> {
+    let url = if target.contains("://") {
+        target.to_string()
+    } else {
+        format!("https://{target}")
+    };
+    let url = reqwest::Url::parse(&url)
+        .map_err(|e| AttestCommandError::InvalidTarget(target.to_string(), e.to_string()))?;
+    let host = url.host_str().ok_or_else(|| {
+        AttestCommandError::InvalidTarget(target.to_string(), "no host was given".into())
+    })?;
+    Ok((host.to_string(), url.port_or_known_default().unwrap_or(443)))
+}
+
+async fn fetch_certificate_chain(
+    host: &str,
+    port: u16,
+) -> Result<Vec<Vec<u8>>, AttestCommandError> {
+    let destinations = tokio::time::timeout(
+        std::time::Duration::from_secs(10),
+        tokio::net::lookup_host((host, port)),
+    )
+    .await??
+    .collect::<Vec<_>>();
+    let stream = tokio::net::TcpStream::connect(&destinations[..]).await?;
+    let mut client_config = ClientConfig::builder()
+        .with_safe_defaults()
+        .with_root_certificates(RootCertStore::empty())
+        .with_no_client_auth();
+    let recorder = Arc::new(ChainRecorder::default());
+    client_config
+        .dangerous()
+        .set_certificate_verifier(recorder.clone());
+    let tls_connector: tokio_rustls::TlsConnector = Arc::new(client_config).into();
+    let mut connection = tls_connector.connect(host.try_into()?, stream).await?;
+    let (_io, session) = connection.get_mut();
+    session.send_close_notify();
+
+    let chain = recorder.chain.lock().expect("Chain lock poisoned").clone();
+    Ok(chain)
+}
+
+fn format_timestamp(timestamp: i64) -> String {
+    chrono::DateTime::from_timestamp(timestamp, 0)
+        .map(|date| date.to_rfc3339())
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+fn summarize_subject_alt_names(cert: &X509Certificate) -> Vec<String> {
+    let Ok(Some(subject_alt_names)) = cert.subject_alternative_name() else {
+        return vec![];
+    };
+    subject_alt_names
+        .value
+        .general_names
+        .iter()
+        .map(|name| match name {
+            GeneralName::DNSName(dns_name) => {
+                let (label, rest) = dns_name.split_once('.').unwrap_or((dns_name, ""));
+                if label.len() > MAX_SAN_LABEL_LENGTH {
+                    format!("<attestation doc, {} bytes>.{rest}", label.len() / 2)
+                } else {
+                    dns_name.to_string()
+                }
+            }
+            other => other.to_string(),
+        })
+        .collect()
+}
+
+pub fn summarize_certificate(cert: &X509Certificate, der: &[u8], now: i64) -> CertificateSummary {
+    let validity = cert.validity();
+    CertificateSummary {
+        subject: cert.subject().to_string(),
+        issuer: cert.issuer().to_string(),
+        serial: cert.raw_serial_as_string(),
+        fingerprint: fingerprint(der),
+        not_before: format_timestamp(validity.not_before.timestamp()),
+        not_after: format_timestamp(validity.not_after.timestamp()),
+        days_until_expiry: (validity.not_after.timestamp() - now) / (24 * 60 * 60),
+        is_ca: cert.is_ca(),
+        subject_alt_names: summarize_subject_alt_names(cert),
+    }
+}
+
+fn summarize_attestation_doc(cert: &X509Certificate) -> Result<AttestationDocSummary, String> {
+    let cose_sign_1 =
+        extract_signed_cose_sign_1_from_certificate(cert).map_err(|e| e.to_string())?;
+    let (_, attestation_doc) =
+        decode_attestation_document(&cose_sign_1).map_err(|e| e.to_string())?;
+    let validation_error = validate_attestation_doc_in_cert(cert)
+        .err()
+        .map(|e| e.to_string());
+    Ok(AttestationDocSummary {
+        module_id: attestation_doc.module_id.clone(),
+        timestamp: chrono::DateTime::from_timestamp_millis(attestation_doc.timestamp as i64)
+            .map(|date| date.to_rfc3339())
+            .unwrap_or_else(|| attestation_doc.timestamp.to_string()),
+        pcrs: ATTESTED_PCRS
+            .iter()
+            .filter_map(|index| {
+                let pcr = attestation_doc.pcrs.get(index)?;
+                Some((format!("PCR{index}"), hex::encode(pcr.as_slice())))
+            })
+            .collect(),
+        user_data: attestation_doc
+            .user_data
+            .as_ref()
+            .map(|data| hex::encode(data.as_slice())),
+        nonce: attestation_doc
+            .nonce
+            .as_ref()
+            .map(|nonce| hex::encode(nonce.as_slice())),
+        public_key: attestation_doc
+            .public_key
+            .as_ref()
+            .map(|key| hex::encode(key.as_slice())),
+        valid: validation_error.is_none(),
+        validation_error,
+    })
+}
+
+fn inspection_warnings(inspection: &CertInspection) -> Vec<String> {
+    let mut warnings = vec![];
+    if let Some(leaf) = inspection.chain.first() {
+        if leaf.days_until_expiry < 0 {
+            warnings.push(format!("The Enclave's certificate expired at {}. Clients will reject it until the Enclave rotates its cert.", leaf.not_after));
+        } else if leaf.days_until_expiry < EXPIRY_WARNING_DAYS {
+            warnings.push(format!(
+                "The Enclave's certificate expires in {} days, at {}",
+                leaf.days_until_expiry, leaf.not_after
+            ));
+        }
+    }
+    match (&inspection.attestation_doc, &inspection.attestation_doc_error) {
+        (Some(doc), _) if !doc.valid => warnings.push(format!(
+            "The embedded attestation doc is invalid, so clients will fail to attest this connection - {}",
+            doc.validation_error.as_deref().unwrap_or("unknown error")
+        )),
+        (None, Some(e)) => warnings.push(format!("No attestation doc could be read from the certificate's Subject Alt Names - {e}. Clients attesting via the SAN will fail, though clients fetching /.well-known/attestation are unaffected.")),
+        _ => {}
+    }
+    warnings
+}
+
+pub fn inspect_chain(
+    host: String,
+    port: u16,
+    chain: &[Vec<u8>],
+    now: i64,
+) -> Result<CertInspection, AttestCommandError> {
+    let parsed = chain
+        .iter()
+        .map(|der| X509Certificate::from_der(der).map(|(_, cert)| (cert, der)))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| AttestCommandError::X509CertError(e.into()))?;
+    let (leaf, _) = parsed
+        .first()
+        .ok_or(AttestCommandError::NoCertificatePresented)?;
+    let (attestation_doc, attestation_doc_error) = match summarize_attestation_doc(leaf) {
+        Ok(doc) => (Some(doc), None),
+        Err(e) => (None, Some(e)),
+    };
+    let mut inspection = CertInspection {
+        host,
+        port,
+        chain: parsed
+            .iter()
+            .map(|(cert, der)| summarize_certificate(cert, der, now))
+            .collect(),
+        attestation_doc,
+        attestation_doc_error,
+        warnings: vec![],
+    };
+    inspection.warnings = inspection_warnings(&inspection);
+    Ok(inspection)
+}
+
+/// Connects to an Enclave and inspects the attested TLS certificate it presents
+pub async fn inspect_certificate(target: &str) -> Result<CertInspection, AttestCommandError> {
+    let (host, port) = parse_target(target)?;
+    let chain = fetch_certificate_chain(&host, port).await?;
+    inspect_chain(host, port, &chain, chrono::Utc::now().timestamp())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rcgen::{Certificate, CertificateParams};
+
+    #[test]
+    fn test_parse_target() {
+        assert_eq!(
+            parse_target("https://my-enclave.app-123.enclave.evervault.com/hello").unwrap(),
+            ("my-enclave.app-123.enclave.evervault.com".to_string(), 443)
+        );
+        assert_eq!(
+            parse_target("my-enclave.app-123.enclave.evervault.com:8443").unwrap(),
+            ("my-enclave.app-123.enclave.evervault.com".to_string(), 8443)
+        );
+        assert!(parse_target("https://").is_err());
+    }
+
+    #[test]
+    fn test_inspect_chain_without_attestation_doc() {
+        let attestation_label = "ab".repeat(40);
+        let mut params = CertificateParams::new(vec![
+            "my-enclave.app-123.enclave.evervault.com".to_string(),
+            format!("{attestation_label}.my-enclave.app-123.enclave.evervault.com"),
+        ]);
+        params.not_before = rcgen::date_time_ymd(2023, 1, 1);
+        params.not_after = rcgen::date_time_ymd(2023, 11, 16);
+        let der = Certificate::from_params(params)
+            .unwrap()
+            .serialize_der()
+            .unwrap();
+
+        let inspection = inspect_chain("my-enclave".into(), 443, &[der], 1_700_000_000).unwrap();
+        let leaf = &inspection.chain[0];
+        assert_eq!(leaf.days_until_expiry, 1);
+        assert_eq!(
+            leaf.subject_alt_names[1],
+            "<attestation doc, 40 bytes>.my-enclave.app-123.enclave.evervault.com"
+        );
+        assert!(inspection.attestation_doc.is_none());
+        assert!(inspection.attestation_doc_error.is_some());
+        assert_eq!(inspection.warnings.len(), 2);
+        assert!(inspection.warnings[0].contains("expires in 1 days"));
+
+        assert!(matches!(
+            inspect_chain("my-enclave".into(), 443, &[], 1_700_000_000),
+            Err(AttestCommandError::NoCertificatePresented)
+        ));
+    }
+}
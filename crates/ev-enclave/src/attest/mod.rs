@@ -1,4 +1,9 @@
 pub mod error;
+pub mod export;
+#[cfg(feature = "pcr-sign")]
+pub mod fixtures;
+pub mod inspect;
+pub mod trust;
 
 use attestation_doc_validation::error::AttestationError;
 use attestation_doc_validation::validate_attestation_doc_against_cert;
@@ -12,6 +17,7 @@ use tokio_rustls::rustls::{
     client::{ClientConfig, ServerCertVerified, ServerCertVerifier},
     RootCertStore,
 };
+use trust::TrustStore;
 use x509_parser::{certificate::X509Certificate, prelude::FromDer};
 
 /**
@@ -22,6 +28,7 @@ struct SubjectAltNameAttestationValidator {
     context_sender: mpsc::Sender<Result<(), AttestationError>>,
     expected_pcrs: PCRs,
     attestation_doc: Vec<u8>,
+    trust_store: Option<TrustStore>,
 }
 
 macro_rules! to_rustls_general_error {
@@ -30,11 +37,34 @@ macro_rules! to_rustls_general_error {
     };
 }
 
+impl SubjectAltNameAttestationValidator {
+    fn verify_pinned_trust(
+        &self,
+        root: Option<&[u8]>,
+        certificate: &tokio_rustls::rustls::Certificate,
+        intermediates: &[tokio_rustls::rustls::Certificate],
+    ) -> Result<(), tokio_rustls::rustls::Error> {
+        let Some(trust_store) = self.trust_store.as_ref() else {
+            return Ok(());
+        };
+        trust_store
+            .verify_root(root)
+            .map_err(|e| to_rustls_general_error!(e))?;
+        if !trust_store.intermediates.is_empty() {
+            let chain: Vec<&[u8]> = intermediates.iter().map(|cert| cert.as_ref()).collect();
+            trust_store
+                .verify_intermediates(certificate.as_ref(), &chain)
+                .map_err(|e| to_rustls_general_error!(e))?;
+        }
+        Ok(())
+    }
+}
+
 impl ServerCertVerifier for SubjectAltNameAttestationValidator {
     fn verify_server_cert(
         &self,
         certificate: &tokio_rustls::rustls::Certificate,
-        _intermediates: &[tokio_rustls::rustls::Certificate],
+        intermediates: &[tokio_rustls::rustls::Certificate],
         _server_name: &tokio_rustls::rustls::ServerName,
         _scts: &mut dyn Iterator<Item = &[u8]>,
         _ocsp_response: &[u8],
@@ -45,6 +75,9 @@ impl ServerCertVerifier for SubjectAltNameAttestationValidator {
         let attestation_doc =
             validate_attestation_doc_against_cert(&certificate_parsed, &self.attestation_doc)
                 .map_err(|e| to_rustls_general_error!(e))?;
+        // The first cert in the CA bundle is the root the attestation doc's signature chains to
+        let root = attestation_doc.cabundle.first().map(|root| root.as_slice());
+        self.verify_pinned_trust(root, certificate, intermediates)?;
         let attestation_validation_result =
             validate_expected_pcrs(&attestation_doc, &self.expected_pcrs);
         let verification_result = match &attestation_validation_result {
@@ -62,6 +95,7 @@ impl ServerCertVerifier for SubjectAltNameAttestationValidator {
 pub async fn attest_connection_to_enclave(
     domain: &str,
     expected_pcrs: PCRs,
+    trust_store: Option<TrustStore>,
 ) -> Result<(), AttestCommandError> {
     let destinations = tokio::time::timeout(
         std::time::Duration::from_secs(10),
@@ -80,6 +114,7 @@ pub async fn attest_connection_to_enclave(
         context_sender: tx,
         expected_pcrs,
         attestation_doc,
+        trust_store,
     });
     client_config
         .dangerous()
@@ -131,6 +166,7 @@ mod attest_tests {
         attest_connection_to_enclave(
             "synthetic-cage.app-f5f084041a7e.cage.evervault.com",
             expected_pcrs,
+            None,
         )
         .await
         .unwrap();
@@ -147,6 +183,7 @@ mod attest_tests {
         let err = attest_connection_to_enclave(
             "synthetic-cage.app-f5f084041a7e.cage.evervault.com",
             expected_pcrs,
+            None,
         )
         .await
         .unwrap_err();
@@ -0,0 +1,437 @@
+use super::error::TrustStoreError;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::Read;
+use std::path::Path;
+use x509_parser::pem::Pem;
+use x509_parser::prelude::{FromDer, X509Certificate};
+
+pub const AWS_NITRO_ROOT_URL: &str =
+    "https://aws-nitro-enclaves.amazonaws.com/AWS_NitroEnclaves_Root-G1.zip";
+/// SHA-256 fingerprint of the AWS Nitro Enclaves Root-G1 certificate, as published by AWS
+const AWS_NITRO_ROOT_FINGERPRINT: &str =
+    "641a0321a3e244efe456463195d606317ed7cdcc3c1756e09893f3c68f79bb5b";
+const AWS_NITRO_ROOT_ZIP_ENTRY: &str = "root.pem";
+
+const ROOT_FILENAME: &str = "aws-nitro-root.pem";
+const INTERMEDIATES_FILENAME: &str = "evervault-intermediates.pem";
+const MANIFEST_FILENAME: &str = "manifest.json";
+/// Pinned certificates expiring within this many days are reported on every attestation
+pub const EXPIRY_WARNING_DAYS: i64 = 30;
+
+pub fn evervault_intermediates_url() -> String {
+    format!(
+        "{}/trust/intermediates.pem",
+        common::endpoint::current().enclave_assets_url()
+    )
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PinnedCertificate {
+    pub subject: String,
+    /// Hex encoded SHA-256 of the DER encoded certificate
+    pub fingerprint: String,
+    /// Seconds since the unix epoch
+    pub not_after: i64,
+}
+
+impl PinnedCertificate {
+    fn parse_pem_bundle(pem_bundle: &[u8]) -> Result<Vec<(Self, Vec<u8>)>, TrustStoreError> {
+        Pem::iter_from_buffer(pem_bundle)
+            .map(|pem| {
+                let pem = pem.map_err(|e| TrustStoreError::InvalidCertificate(e.to_string()))?;
+                let cert = pem
+                    .parse_x509()
+                    .map_err(|e| TrustStoreError::InvalidCertificate(e.to_string()))?;
+                if !cert.is_ca() {
+                    return Err(TrustStoreError::InvalidCertificate(format!(
+                        "{} is not a CA certificate",
+                        cert.subject()
+                    )));
+                }
+                let pinned = Self {
+                    subject: cert.subject().to_string(),
+                    fingerprint: fingerprint(&pem.contents),
+                    not_after: cert.validity().not_after.timestamp(),
+                };
+                Ok((pinned, pem.contents.clone()))
+            })
+            .collect()
+    }
+
+    fn expiry_warning(&self, now: i64) -> Option<String> {
+        let days_remaining = (self.not_after - now) / (24 * 60 * 60);
+        if self.not_after <= now {
+            Some(format!("The pinned certificate {} has expired. Run `ev enclave trust fetch` to refresh the trust store.", self.subject))
+        } else if days_remaining < EXPIRY_WARNING_DAYS {
+            Some(format!("The pinned certificate {} expires in {days_remaining} days. Run `ev enclave trust fetch` to refresh the trust store.", self.subject))
+        } else {
+            None
+        }
+    }
+}
+
+pub fn fingerprint(der: &[u8]) -> String {
+    hex::encode(Sha256::digest(der))
+}
+
+/// The attestation root of trust pinned with `ev enclave trust fetch`: the AWS Nitro Enclaves root
+/// which signs attestation docs, and the Evervault intermediates which issue Enclave TLS certs.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TrustStore {
+    pub fetched_at: i64,
+    pub root: PinnedCertificate,
+    pub intermediates: Vec<PinnedCertificate>,
+}
+
+impl TrustStore {
+    /// Verifies the downloaded certificates and builds a store from them. The root must match the
+    /// fingerprint published by AWS, and every intermediate must be a CA certificate.
+    pub fn from_downloads(
+        nitro_root_zip: &[u8],
+        intermediates_pem: &[u8],
+        now: i64,
+    ) -> Result<(Self, Vec<u8>), TrustStoreError> {
+        let root_pem = extract_root_pem(nitro_root_zip)?;
+        let (root, _) = PinnedCertificate::parse_pem_bundle(&root_pem)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| {
+                TrustStoreError::InvalidCertificate("the root bundle is empty".into())
+            })?;
+        if root.fingerprint != AWS_NITRO_ROOT_FINGERPRINT {
+            return Err(TrustStoreError::RootFingerprintMismatch(root.fingerprint));
+        }
+
+        let intermediates: Vec<PinnedCertificate> =
+            PinnedCertificate::parse_pem_bundle(intermediates_pem)?
+                .into_iter()
+                .map(|(intermediate, _)| intermediate)
+                .collect();
+        if intermediates.is_empty() {
+            return Err(TrustStoreError::InvalidCertificate(
+                "no Evervault intermediates were returned".into(),
+            ));
+        }
+        let store = Self {
+            fetched_at: now,
+            root,
+            intermediates,
+        };
+        if let Some(expired) = store.all_certificates().find(|cert| cert.not_after <= now) {
+            return Err(TrustStoreError::ExpiredCertificate(expired.subject.clone()));
+        }
+        Ok((store, root_pem))
+    }
+
+    fn all_certificates(&self) -> impl Iterator<Item = &PinnedCertificate> {
+        std::iter::once(&self.root).chain(self.intermediates.iter())
+    }
+
+    pub fn save(
+        &self,
+        directory: &Path,
+        root_pem: &[u8],
+        intermediates_pem: &[u8],
+    ) -> Result<(), TrustStoreError> {
+        let write = |filename: &str, contents: &[u8]| {
+            let path = directory.join(filename);
+            std::fs::write(&path, contents)
+                .map_err(|e| TrustStoreError::Io(path.display().to_string(), e))
+        };
+        std::fs::create_dir_all(directory)
+            .map_err(|e| TrustStoreError::Io(directory.display().to_string(), e))?;
+        write(ROOT_FILENAME, root_pem)?;
+        write(INTERMEDIATES_FILENAME, intermediates_pem)?;
+        write(
+            MANIFEST_FILENAME,
+            serde_json::to_string_pretty(self)
+                .expect("Infallible - trust store is serializable")
+                .as_bytes(),
+        )
+    }
+
+    /// Loads the pinned store from `directory`, or `None` if nothing has been pinned yet
+    pub fn load(directory: &Path) -> Result<Option<Self>, TrustStoreError> {
+        let path = directory.join(MANIFEST_FILENAME);
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .map(Some)
+                .map_err(|e| TrustStoreError::InvalidManifest(path.display().to_string(), e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(TrustStoreError::Io(path.display().to_string(), e)),
+        }
+    }
+
+    pub fn expiry_warnings(&self, now: i64) -> Vec<String> {
+        self.all_certificates()
+            .filter_map(|cert| cert.expiry_warning(now))
+            .collect()
+    }
+
+    /// Checks the root of an attestation doc's CA bundle is the pinned root
+    pub fn verify_root(&self, root_der: Option<&[u8]>) -> Result<(), TrustStoreError> {
+        let found = root_der.map(fingerprint).unwrap_or_default();
+        if found == self.root.fingerprint {
+            Ok(())
+        } else {
+            Err(TrustStoreError::UnpinnedRoot(found))
+        }
+    }
+
+    /// Checks the Enclave's TLS cert was issued through one of the pinned intermediates, by
+    /// following the signatures from `leaf` up the server's chain until a pinned cert is reached.
+    /// Intermediates are public, so a pinned cert only counts if it signed the cert below it.
+    pub fn verify_intermediates(
+        &self,
+        leaf: &[u8],
+        chain: &[&[u8]],
+    ) -> Result<(), TrustStoreError> {
+        let parse = |der| {
+            X509Certificate::from_der(der)
+                .map(|(_, cert)| cert)
+                .map_err(|e| TrustStoreError::InvalidCertificate(e.to_string()))
+        };
+        let leaf = parse(leaf)?;
+        let chain = chain
+            .iter()
+            .map(|der| Ok((fingerprint(der), parse(der)?)))
+            .collect::<Result<Vec<_>, TrustStoreError>>()?;
+
+        let mut current = &leaf;
+        // Each cert in the chain can only be passed once on the way up
+        for _ in 0..chain.len() {
+            let issuer = chain.iter().find(|(_, issuer)| {
+                issuer.subject() == current.issuer()
+                    && current.verify_signature(Some(issuer.public_key())).is_ok()
+            });
+            let Some((issuer_fingerprint, issuer)) = issuer else {
+                break;
+            };
+            if self
+                .intermediates
+                .iter()
+                .any(|intermediate| &intermediate.fingerprint == issuer_fingerprint)
+            {
+                return Ok(());
+            }
+            current = issuer;
+        }
+        Err(TrustStoreError::UnpinnedIntermediate)
+    }
+}
+
+async fn download(client: &reqwest::Client, url: &str) -> Result<Vec<u8>, TrustStoreError> {
+    let to_error = |e| TrustStoreError::Download(url.to_string(), e);
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(to_error)?;
+    let bytes = response.bytes().await.map_err(to_error)?;
+    Ok(bytes.to_vec())
+}
+
+/// Downloads the AWS Nitro root and Evervault intermediates, verifies them, and pins them in `directory`
+pub async fn fetch_trust_store(directory: &Path) -> Result<TrustStore, TrustStoreError> {
+    let client = reqwest::Client::new();
+    let nitro_root_zip = download(&client, AWS_NITRO_ROOT_URL).await?;
+    let intermediates_pem = download(&client, &evervault_intermediates_url()).await?;
+    let (store, root_pem) = TrustStore::from_downloads(
+        &nitro_root_zip,
+        &intermediates_pem,
+        chrono::Utc::now().timestamp(),
+    )?;
+    store.save(directory, &root_pem, &intermediates_pem)?;
+    Ok(store)
+}
+
+fn extract_root_pem(nitro_root_zip: &[u8]) -> Result<Vec<u8>, TrustStoreError> {
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(nitro_root_zip))
+        .map_err(|e| TrustStoreError::InvalidCertificate(e.to_string()))?;
+    let mut entry = archive
+        .by_name(AWS_NITRO_ROOT_ZIP_ENTRY)
+        .map_err(|e| TrustStoreError::InvalidCertificate(e.to_string()))?;
+    let mut root_pem = vec![];
+    entry
+        .read_to_end(&mut root_pem)
+        .map_err(|e| TrustStoreError::Io(AWS_NITRO_ROOT_ZIP_ENTRY.into(), e))?;
+    Ok(root_pem)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rcgen::{BasicConstraints, Certificate, CertificateParams, DnType, IsCa};
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    const AWS_NITRO_ROOT_PEM: &[u8] = include_bytes!("aws-nitro-root.pem");
+    const NOW: i64 = 1_700_000_000;
+
+    fn zip_root(root_pem: &[u8]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(vec![]));
+        let options =
+            zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        writer
+            .start_file(AWS_NITRO_ROOT_ZIP_ENTRY, options)
+            .unwrap();
+        writer.write_all(root_pem).unwrap();
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn generate_pem(common_name: &str, is_ca: bool, not_after_year: i32) -> String {
+        let mut params = CertificateParams::new(vec![]);
+        params
+            .distinguished_name
+            .push(DnType::CommonName, common_name);
+        params.not_before = rcgen::date_time_ymd(2020, 1, 1);
+        params.not_after = rcgen::date_time_ymd(not_after_year, 1, 1);
+        if is_ca {
+            params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        }
+        Certificate::from_params(params)
+            .unwrap()
+            .serialize_pem()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_trust_store_from_downloads() {
+        let intermediates = generate_pem("Evervault Intermediate", true, 2040);
+        let (store, root_pem) = TrustStore::from_downloads(
+            &zip_root(AWS_NITRO_ROOT_PEM),
+            intermediates.as_bytes(),
+            NOW,
+        )
+        .unwrap();
+        assert_eq!(root_pem, AWS_NITRO_ROOT_PEM);
+        assert_eq!(store.root.fingerprint, AWS_NITRO_ROOT_FINGERPRINT);
+        assert_eq!(store.intermediates.len(), 1);
+        assert!(store.expiry_warnings(NOW).is_empty());
+
+        let directory = TempDir::new().unwrap();
+        store
+            .save(directory.path(), &root_pem, intermediates.as_bytes())
+            .unwrap();
+        assert_eq!(TrustStore::load(directory.path()).unwrap(), Some(store));
+        assert_eq!(
+            TrustStore::load(&directory.path().join("missing")).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_trust_store_rejects_unexpected_certificates() {
+        let intermediates = generate_pem("Evervault Intermediate", true, 2040);
+        let other_root = generate_pem("Not AWS", true, 2040);
+        assert!(matches!(
+            TrustStore::from_downloads(
+                &zip_root(other_root.as_bytes()),
+                intermediates.as_bytes(),
+                NOW
+            ),
+            Err(TrustStoreError::RootFingerprintMismatch(_))
+        ));
+
+        let leaf = generate_pem("Evervault Leaf", false, 2040);
+        assert!(matches!(
+            TrustStore::from_downloads(&zip_root(AWS_NITRO_ROOT_PEM), leaf.as_bytes(), NOW),
+            Err(TrustStoreError::InvalidCertificate(_))
+        ));
+
+        let expired = generate_pem("Evervault Intermediate", true, 2021);
+        assert!(matches!(
+            TrustStore::from_downloads(&zip_root(AWS_NITRO_ROOT_PEM), expired.as_bytes(), NOW),
+            Err(TrustStoreError::ExpiredCertificate(_))
+        ));
+    }
+
+    #[test]
+    fn test_trust_store_expiry_warnings_and_verification() {
+        let intermediates = generate_pem("Evervault Intermediate", true, 2024);
+        let (store, _) = TrustStore::from_downloads(
+            &zip_root(AWS_NITRO_ROOT_PEM),
+            intermediates.as_bytes(),
+            NOW,
+        )
+        .unwrap();
+        let intermediate = &store.intermediates[0];
+        let warnings = store.expiry_warnings(intermediate.not_after - 10 * 24 * 60 * 60);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("expires in 10 days"));
+        assert!(store.expiry_warnings(intermediate.not_after)[0].contains("has expired"));
+
+        let (_, root) = x509_parser::pem::parse_x509_pem(AWS_NITRO_ROOT_PEM).unwrap();
+        assert!(store.verify_root(Some(&root.contents)).is_ok());
+        assert!(matches!(
+            store.verify_root(None),
+            Err(TrustStoreError::UnpinnedRoot(_))
+        ));
+    }
+
+    fn generate_ca(common_name: &str) -> Certificate {
+        let mut params = CertificateParams::new(vec![]);
+        params
+            .distinguished_name
+            .push(DnType::CommonName, common_name);
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        Certificate::from_params(params).unwrap()
+    }
+
+    fn generate_leaf_der(issuer: &Certificate) -> Vec<u8> {
+        let mut params = CertificateParams::new(vec!["enclave.evervault.com".into()]);
+        params
+            .distinguished_name
+            .push(DnType::CommonName, "Evervault Enclave");
+        Certificate::from_params(params)
+            .unwrap()
+            .serialize_der_with_signer(issuer)
+            .unwrap()
+    }
+
+    fn der(pem: &str) -> Vec<u8> {
+        x509_parser::pem::parse_x509_pem(pem.as_bytes())
+            .unwrap()
+            .1
+            .contents
+    }
+
+    #[test]
+    fn test_verify_intermediates() {
+        let pinned = generate_ca("Evervault Intermediate");
+        // Signatures are randomized, so the pinned cert is only serialized once
+        let pinned_pem = pinned.serialize_pem().unwrap();
+        let (store, _) =
+            TrustStore::from_downloads(&zip_root(AWS_NITRO_ROOT_PEM), pinned_pem.as_bytes(), NOW)
+                .unwrap();
+        let pinned_der = der(&pinned_pem);
+
+        let leaf = generate_leaf_der(&pinned);
+        assert!(store.verify_intermediates(&leaf, &[&pinned_der]).is_ok());
+
+        // The leaf is issued by an intermediate the pinned cert signed
+        let issuing = generate_ca("Evervault Issuing");
+        let issuing_der = issuing.serialize_der_with_signer(&pinned).unwrap();
+        let leaf = generate_leaf_der(&issuing);
+        assert!(store
+            .verify_intermediates(&leaf, &[&issuing_der, &pinned_der])
+            .is_ok());
+
+        // The pinned intermediate is in the chain, but didn't issue the leaf
+        let impostor = generate_ca("Evervault Intermediate");
+        let leaf = generate_leaf_der(&impostor);
+        let impostor_der = der(&impostor.serialize_pem().unwrap());
+        assert!(matches!(
+            store.verify_intermediates(&leaf, &[&impostor_der, &pinned_der]),
+            Err(TrustStoreError::UnpinnedIntermediate)
+        ));
+        assert!(matches!(
+            store.verify_intermediates(&leaf, &[&pinned_der]),
+            Err(TrustStoreError::UnpinnedIntermediate)
+        ));
+    }
+}
@@ -0,0 +1,182 @@
+use crate::build::signature::{read_signing_key, signing_cert_fingerprint};
+use crate::config::SigningInfoError;
+use crate::enclave::{EnclaveSigningInfo, PCRs};
+use common::CliError;
+use pcr_sign::{EcdsaSig, Signer};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum AuditError {
+    #[error("Failed to write to the audit log - {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("Failed to sign the audit record - {0}")]
+    SigningError(#[from] SigningInfoError),
+}
+
+impl CliError for AuditError {
+    fn exitcode(&self) -> exitcode::ExitCode {
+        match self {
+            Self::IoError(_) => exitcode::IOERR,
+            Self::SigningError(e) => e.exitcode(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditAction {
+    Deploy,
+    Delete,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditOutcome {
+    Success,
+    Failure,
+}
+
+/// A single line in the audit log. When a signing key is available, `signature` is a hex encoded
+/// DER ECDSA P-384 signature over the record serialized as JSON without its `signature` field.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditRecord {
+    pub action: AuditAction,
+    pub actor: String,
+    pub timestamp: String,
+    pub enclave_uuid: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deployment_uuid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pcrs: Option<PCRs>,
+    pub result: AuditOutcome,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signing_cert_fingerprint: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+// The local user running the command, as the API key doesn't identify a person
+fn current_actor() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+impl AuditRecord {
+    pub fn new<T, E: std::fmt::Display>(
+        action: AuditAction,
+        enclave_uuid: &str,
+        result: &Result<T, E>,
+    ) -> Self {
+        let (result, error) = match result {
+            Ok(_) => (AuditOutcome::Success, None),
+            Err(e) => (AuditOutcome::Failure, Some(e.to_string())),
+        };
+        Self {
+            action,
+            actor: current_actor(),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            enclave_uuid: enclave_uuid.to_string(),
+            deployment_uuid: None,
+            pcrs: None,
+            result,
+            error,
+            signing_cert_fingerprint: None,
+            signature: None,
+        }
+    }
+
+    pub fn with_deployment_uuid(mut self, deployment_uuid: Option<String>) -> Self {
+        self.deployment_uuid = deployment_uuid;
+        self
+    }
+
+    pub fn with_pcrs(mut self, pcrs: Option<&PCRs>) -> Self {
+        self.pcrs = pcrs.cloned();
+        self
+    }
+
+    fn sign(&mut self, signing_info: &EnclaveSigningInfo) -> Result<(), SigningInfoError> {
+        let signing_key = read_signing_key(signing_info.key())?;
+        self.signing_cert_fingerprint = Some(signing_cert_fingerprint(signing_info.cert())?);
+        self.signature = None;
+        let payload = serde_json::to_vec(self).expect("Failed to serialize audit record");
+        let signature: EcdsaSig = signing_key.sign(&payload);
+        self.signature = Some(hex::encode(signature.to_der().as_bytes()));
+        Ok(())
+    }
+}
+
+/// Appends the record to the JSON lines audit log at `path`, signing it if a signing key is given
+pub fn append_audit_record(
+    path: &Path,
+    mut record: AuditRecord,
+    signing_info: Option<&EnclaveSigningInfo>,
+) -> Result<AuditRecord, AuditError> {
+    if let Some(signing_info) = signing_info {
+        record.sign(signing_info)?;
+    }
+    let mut line = serde_json::to_string(&record).expect("Failed to serialize audit record");
+    line.push('\n');
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    file.write_all(line.as_bytes())?;
+    Ok(record)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pcr_sign::{_Verifier, VerifyingKey};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_append_signed_audit_records() {
+        let directory = TempDir::new().unwrap();
+        let mut params = rcgen::CertificateParams::new(vec![]);
+        params.alg = &rcgen::PKCS_ECDSA_P384_SHA384;
+        let cert = rcgen::Certificate::from_params(params).unwrap();
+        let cert_path = directory.path().join("cert.pem");
+        let key_path = directory.path().join("key.pem");
+        std::fs::write(&cert_path, cert.serialize_pem().unwrap()).unwrap();
+        std::fs::write(&key_path, cert.serialize_private_key_pem()).unwrap();
+        let signing_info = EnclaveSigningInfo::new(cert_path, key_path.clone());
+        let audit_log = directory.path().join("audit.jsonl");
+
+        let deployed: Result<(), String> = Ok(());
+        let record = AuditRecord::new(AuditAction::Deploy, "enclave_123", &deployed)
+            .with_deployment_uuid(Some("deployment_456".into()));
+        append_audit_record(&audit_log, record, Some(&signing_info)).unwrap();
+
+        let deleted: Result<(), String> = Err("404: Not Found".into());
+        let record = AuditRecord::new(AuditAction::Delete, "enclave_123", &deleted);
+        append_audit_record(&audit_log, record, None).unwrap();
+
+        let contents = std::fs::read_to_string(&audit_log).unwrap();
+        let records: Vec<AuditRecord> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].result, AuditOutcome::Success);
+        assert_eq!(records[1].result, AuditOutcome::Failure);
+        assert_eq!(records[1].error.as_deref(), Some("404: Not Found"));
+        assert!(records[1].signature.is_none());
+
+        let mut signed = records[0].clone();
+        let signature = hex::decode(signed.signature.take().unwrap()).unwrap();
+        let payload = serde_json::to_vec(&signed).unwrap();
+        let verifying_key = VerifyingKey::from(&read_signing_key(&key_path).unwrap());
+        assert!(verifying_key
+            .verify(&payload, &EcdsaSig::from_der(&signature).unwrap())
+            .is_ok());
+    }
+}
@@ -0,0 +1,176 @@
+use crate::docker::parse::Directive;
+use common::CliError;
+use thiserror::Error;
+use x509_parser::parse_x509_certificate;
+use x509_parser::pem::Pem;
+
+// Debian, Ubuntu and Alpine install certificates from here with update-ca-certificates
+const CA_CERTS_DIRECTORY: &str = "/usr/local/share/ca-certificates";
+const CA_CERT_PREFIX: &str = "evervault-extra-ca";
+
+#[derive(Debug, Error)]
+pub enum CaCertError {
+    #[error("Failed to read the CA certificate at {path} — {source}")]
+    Read {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("{0} doesn't contain any PEM encoded certificates.")]
+    NoCertificates(String),
+    #[error("{0} contains PEM blocks other than certificates, such as a private key. Only certificates can be added to the trust store.")]
+    UnexpectedPemBlock(String),
+    #[error("Failed to parse the certificate in {path} — {reason}")]
+    InvalidCertificate { path: String, reason: String },
+}
+
+impl CliError for CaCertError {
+    fn exitcode(&self) -> exitcode::ExitCode {
+        match self {
+            Self::Read { .. } => exitcode::NOINPUT,
+            _ => exitcode::DATAERR,
+        }
+    }
+}
+
+/// A CA certificate from `[build] extra_ca_certs`, checked to hold only certificates so nothing
+/// else from the file ends up in the image
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CaCertificate {
+    pub path: String,
+    pem: String,
+}
+
+impl CaCertificate {
+    pub fn load(path: &str) -> Result<Self, CaCertError> {
+        let contents = std::fs::read(path).map_err(|source| CaCertError::Read {
+            path: path.to_string(),
+            source,
+        })?;
+        Self::parse(path, &contents)
+    }
+
+    fn parse(path: &str, contents: &[u8]) -> Result<Self, CaCertError> {
+        let mut pem = String::new();
+        for block in Pem::iter_from_buffer(contents) {
+            let block = block.map_err(|e| CaCertError::InvalidCertificate {
+                path: path.to_string(),
+                reason: e.to_string(),
+            })?;
+            if block.label != "CERTIFICATE" {
+                return Err(CaCertError::UnexpectedPemBlock(path.to_string()));
+            }
+            let (_, certificate) = parse_x509_certificate(&block.contents).map_err(|e| {
+                CaCertError::InvalidCertificate {
+                    path: path.to_string(),
+                    reason: e.to_string(),
+                }
+            })?;
+            if !certificate.is_ca() {
+                log::warn!(
+                    "The certificate for {} in {path} isn't a CA certificate, so it can't be used to verify other certificates.",
+                    certificate.subject()
+                );
+            }
+            if !certificate.validity().is_valid() {
+                log::warn!(
+                    "The certificate for {} in {path} isn't currently valid, so TLS connections relying on it will fail.",
+                    certificate.subject()
+                );
+            }
+            pem.push_str(&encode_pem(&block.contents));
+        }
+        if pem.is_empty() {
+            return Err(CaCertError::NoCertificates(path.to_string()));
+        }
+        Ok(Self {
+            path: path.to_string(),
+            pem,
+        })
+    }
+}
+
+fn encode_pem(der: &[u8]) -> String {
+    let encoded = base64::encode(der);
+    let mut pem = String::from("-----BEGIN CERTIFICATE-----\n");
+    for line in encoded.as_bytes().chunks(64) {
+        pem.push_str(std::str::from_utf8(line).expect("infallible - base64 is ascii"));
+        pem.push('\n');
+    }
+    pem.push_str("-----END CERTIFICATE-----\n");
+    pem
+}
+
+/// Directives which write the certificates into the image and install them with whichever trust
+/// store tooling the base image has, falling back to appending them to the default bundle
+pub fn trust_store_directives(ca_certs: &[CaCertificate]) -> Vec<Directive> {
+    if ca_certs.is_empty() {
+        return vec![];
+    }
+    let mut directives = vec![Directive::new_run(format!("mkdir -p {CA_CERTS_DIRECTORY}"))];
+    for (index, ca_cert) in ca_certs.iter().enumerate() {
+        // Re-encoded PEM is only base64, dashes and spaces, so each line can be single quoted
+        let lines = ca_cert
+            .pem
+            .lines()
+            .map(|line| format!("'{line}'"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        directives.push(Directive::new_run(format!(
+            "printf '%s\\n' {lines} > {CA_CERTS_DIRECTORY}/{CA_CERT_PREFIX}-{index}.crt"
+        )));
+    }
+    let certs = format!("{CA_CERTS_DIRECTORY}/{CA_CERT_PREFIX}-*.crt");
+    directives.push(Directive::new_run(format!(
+        "if command -v update-ca-certificates >/dev/null 2>&1; then update-ca-certificates; \
+elif command -v update-ca-trust >/dev/null 2>&1; then cp {certs} /etc/pki/ca-trust/source/anchors/ && update-ca-trust extract; \
+else mkdir -p /etc/ssl/certs && cat {certs} >> /etc/ssl/certs/ca-certificates.crt; fi"
+    )));
+    directives
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn test_cert_pem() -> String {
+        let mut params = rcgen::CertificateParams::new(vec!["internal-root".to_string()]);
+        params.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Unconstrained);
+        rcgen::Certificate::from_params(params)
+            .unwrap()
+            .serialize_pem()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parse_ca_certificate() {
+        let pem = test_cert_pem();
+        let bundle = format!("# internal roots\n{pem}{pem}");
+        let ca_cert = CaCertificate::parse("./internal-root.pem", bundle.as_bytes()).unwrap();
+        assert_eq!(ca_cert.pem.matches("BEGIN CERTIFICATE").count(), 2);
+
+        let err = CaCertificate::parse("./empty.pem", b"not a certificate").unwrap_err();
+        assert!(matches!(err, CaCertError::NoCertificates(_)));
+
+        let key = rcgen::KeyPair::generate(&rcgen::PKCS_ECDSA_P256_SHA256)
+            .unwrap()
+            .serialize_pem();
+        let err = CaCertificate::parse("./key.pem", format!("{pem}{key}").as_bytes()).unwrap_err();
+        assert!(matches!(err, CaCertError::UnexpectedPemBlock(_)));
+    }
+
+    #[test]
+    fn test_trust_store_directives() {
+        assert!(trust_store_directives(&[]).is_empty());
+
+        let ca_cert =
+            CaCertificate::parse("./internal-root.pem", test_cert_pem().as_bytes()).unwrap();
+        let directives = trust_store_directives(&[ca_cert]);
+        assert_eq!(directives.len(), 3);
+        let write_cert = directives[1].to_string();
+        assert!(write_cert.starts_with("RUN printf '%s\\n' '-----BEGIN CERTIFICATE-----' "));
+        assert!(write_cert.ends_with(
+            "'-----END CERTIFICATE-----' > /usr/local/share/ca-certificates/evervault-extra-ca-0.crt"
+        ));
+        assert!(directives[2].to_string().contains("update-ca-certificates"));
+    }
+}
@@ -0,0 +1,313 @@
+use super::error::BuildError;
+use regex::Regex;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+
+const DOCKERIGNORE_FILENAME: &str = ".dockerignore";
+const BYTES_PER_MB: u64 = 1024 * 1024;
+/// Contexts larger than this are reported before building, as they slow down builds and bloat EIFs
+pub const CONTEXT_SIZE_WARNING_THRESHOLD_MB: u64 = 250;
+const MAX_REPORTED_PATHS: usize = 5;
+// Paths which are almost never needed at runtime and commonly end up in a build context by accident
+const COMMONLY_IGNORED_PATHS: [&str; 10] = [
+    ".git",
+    "node_modules",
+    "target",
+    ".venv",
+    "venv",
+    "__pycache__",
+    ".next",
+    "dist",
+    "coverage",
+    ".terraform",
+];
+
+struct IgnorePattern {
+    regex: Regex,
+    negated: bool,
+}
+
+/// The subset of `.dockerignore` semantics needed to size a build context: `*`, `?` and `**`
+/// wildcards, `!` exceptions, and patterns matching a directory excluding everything beneath it.
+pub struct DockerIgnore {
+    patterns: Vec<IgnorePattern>,
+}
+
+impl DockerIgnore {
+    pub fn parse(contents: &str) -> Self {
+        let patterns = contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| {
+                let (negated, pattern) = match line.strip_prefix('!') {
+                    Some(pattern) => (true, pattern.trim()),
+                    None => (false, line),
+                };
+                let pattern = pattern
+                    .trim_start_matches("./")
+                    .trim_start_matches('/')
+                    .trim_end_matches('/');
+                if pattern.is_empty() {
+                    return None;
+                }
+                Regex::new(&format!("^{}(/.*)?$", pattern_to_regex(pattern)))
+                    .ok()
+                    .map(|regex| IgnorePattern { regex, negated })
+            })
+            .collect();
+        Self { patterns }
+    }
+
+    pub(super) fn from_context(context_path: &Path) -> Self {
+        std::fs::read_to_string(context_path.join(DOCKERIGNORE_FILENAME))
+            .map(|contents| Self::parse(&contents))
+            .unwrap_or(Self { patterns: vec![] })
+    }
+
+    /// Whether the path, relative to the context root, is excluded. The last matching pattern wins.
+    pub fn is_excluded(&self, relative_path: &str) -> bool {
+        self.patterns
+            .iter()
+            .rev()
+            .find(|pattern| pattern.regex.is_match(relative_path))
+            .is_some_and(|pattern| !pattern.negated)
+    }
+
+    pub(super) fn has_exceptions(&self) -> bool {
+        self.patterns.iter().any(|pattern| pattern.negated)
+    }
+}
+
+fn pattern_to_regex(pattern: &str) -> String {
+    let mut regex = String::new();
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                // `**/` matches zero or more directories
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    regex.push_str("(.*/)?");
+                } else {
+                    regex.push_str(".*");
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex
+}
+
+/// Size of the files docker will send as the build context, broken down by top level path
+#[derive(Debug, Default)]
+pub struct ContextSize {
+    pub total_bytes: u64,
+    pub largest_paths: Vec<(String, u64)>,
+}
+
+pub fn measure_build_context(context_path: &Path) -> std::io::Result<ContextSize> {
+    let dockerignore = DockerIgnore::from_context(context_path);
+    let mut sizes: HashMap<String, u64> = HashMap::new();
+    measure_dir(context_path, "", &dockerignore, &mut sizes)?;
+
+    let mut largest_paths: Vec<(String, u64)> = sizes.into_iter().collect();
+    largest_paths.sort_by(|(a_path, a_size), (b_path, b_size)| {
+        b_size.cmp(a_size).then_with(|| a_path.cmp(b_path))
+    });
+    Ok(ContextSize {
+        total_bytes: largest_paths.iter().map(|(_, size)| size).sum(),
+        largest_paths,
+    })
+}
+
+fn measure_dir(
+    dir: &Path,
+    relative_dir: &str,
+    dockerignore: &DockerIgnore,
+    sizes: &mut HashMap<String, u64>,
+) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        let relative_path = if relative_dir.is_empty() {
+            name
+        } else {
+            format!("{relative_dir}/{name}")
+        };
+        // symlinks are sent as links, not followed
+        let metadata = entry.path().symlink_metadata()?;
+        let excluded = dockerignore.is_excluded(&relative_path);
+
+        if metadata.is_dir() {
+            // an exception pattern may re-include files beneath an excluded directory
+            if !excluded || dockerignore.has_exceptions() {
+                measure_dir(&entry.path(), &relative_path, dockerignore, sizes)?;
+            }
+        } else if !excluded {
+            let top_level = relative_path
+                .split('/')
+                .next()
+                .unwrap_or_default()
+                .to_string();
+            *sizes.entry(top_level).or_default() += metadata.len();
+        }
+    }
+    Ok(())
+}
+
+fn format_size(bytes: u64) -> String {
+    format!("{:.1}MB", bytes as f64 / BYTES_PER_MB as f64)
+}
+
+/// Suggests `.dockerignore` entries for the commonly ignored paths among the largest in the context
+pub fn suggest_dockerignore_entries(context_size: &ContextSize) -> Vec<String> {
+    context_size
+        .largest_paths
+        .iter()
+        .map(|(path, _)| path)
+        .filter(|path| COMMONLY_IGNORED_PATHS.contains(&path.as_str()))
+        .cloned()
+        .collect()
+}
+
+fn append_dockerignore_entries(context_path: &Path, entries: &[String]) -> std::io::Result<()> {
+    let dockerignore_path = context_path.join(DOCKERIGNORE_FILENAME);
+    let existing = std::fs::read_to_string(&dockerignore_path).unwrap_or_default();
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&dockerignore_path)?;
+    if !existing.is_empty() && !existing.ends_with('\n') {
+        writeln!(file)?;
+    }
+    for entry in entries {
+        writeln!(file, "{entry}")?;
+    }
+    Ok(())
+}
+
+/// Measures the build context before it is sent to docker, warning when it is unusually large and
+/// failing when it exceeds `max_size_mb`. In an interactive terminal, offers to add commonly
+/// ignored paths to the `.dockerignore`.
+pub fn check_build_context(
+    context_path: &Path,
+    max_size_mb: Option<u64>,
+) -> Result<(), BuildError> {
+    let context_size = match measure_build_context(context_path) {
+        Ok(context_size) => context_size,
+        Err(e) => {
+            log::debug!("Failed to measure the build context size - {e}");
+            return Ok(());
+        }
+    };
+    log::debug!(
+        "Build context size: {}",
+        format_size(context_size.total_bytes)
+    );
+
+    let threshold_mb = max_size_mb.unwrap_or(CONTEXT_SIZE_WARNING_THRESHOLD_MB);
+    if context_size.total_bytes <= threshold_mb * BYTES_PER_MB {
+        return Ok(());
+    }
+
+    let largest_paths = context_size
+        .largest_paths
+        .iter()
+        .take(MAX_REPORTED_PATHS)
+        .map(|(path, size)| format!("  {path} ({})", format_size(*size)))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let suggestions = suggest_dockerignore_entries(&context_size);
+
+    if max_size_mb.is_some() {
+        return Err(BuildError::ContextTooLarge {
+            size: format_size(context_size.total_bytes),
+            max_size: format_size(threshold_mb * BYTES_PER_MB),
+            largest_paths,
+        });
+    }
+
+    log::warn!(
+        "The build context at {} is {}, which will slow down your build and increase the size of your EIF. Largest paths:\n{largest_paths}",
+        context_path.display(),
+        format_size(context_size.total_bytes)
+    );
+    if suggestions.is_empty() {
+        log::warn!("Consider excluding files which aren't needed in your Enclave using a .dockerignore file.");
+        return Ok(());
+    }
+    log::warn!(
+        "Consider adding the following entries to your .dockerignore:\n{}",
+        suggestions
+            .iter()
+            .map(|entry| format!("  {entry}"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    );
+
+    // Only offered when someone can answer, so --yes never edits the .dockerignore unprompted
+    if crate::prompt::can_prompt() {
+        let confirmed = crate::prompt::confirm("Add these entries to your .dockerignore?", false)
+            .unwrap_or(false);
+        if confirmed {
+            append_dockerignore_entries(context_path, &suggestions)
+                .map_err(BuildError::FailedToUpdateDockerignore)?;
+            log::info!("Updated {DOCKERIGNORE_FILENAME}.");
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_dockerignore_matching() {
+        let dockerignore =
+            DockerIgnore::parse("# comment\nnode_modules/\n**/*.log\n*.md\n!README.md\n/tmp\n");
+        assert!(dockerignore.is_excluded("node_modules"));
+        assert!(dockerignore.is_excluded("node_modules/react/index.js"));
+        assert!(dockerignore.is_excluded("debug.log"));
+        assert!(dockerignore.is_excluded("logs/app/debug.log"));
+        assert!(dockerignore.is_excluded("CHANGELOG.md"));
+        assert!(!dockerignore.is_excluded("README.md"));
+        assert!(!dockerignore.is_excluded("docs/CHANGELOG.md"));
+        assert!(dockerignore.is_excluded("tmp/cache"));
+        assert!(!dockerignore.is_excluded("src/main.rs"));
+    }
+
+    #[test]
+    fn test_measure_build_context() {
+        let context = TempDir::new().unwrap();
+        let write_file = |path: &str, size: usize| {
+            let path = context.path().join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, vec![0u8; size]).unwrap();
+        };
+        write_file("node_modules/react/index.js", 300);
+        write_file(".git/objects/pack", 200);
+        write_file("src/main.js", 50);
+        write_file("debug.log", 1000);
+        std::fs::write(context.path().join(".dockerignore"), "*.log\n").unwrap();
+
+        let context_size = measure_build_context(context.path()).unwrap();
+        let paths: Vec<&str> = context_size
+            .largest_paths
+            .iter()
+            .map(|(path, _)| path.as_str())
+            .collect();
+        assert_eq!(paths, vec!["node_modules", ".git", "src", ".dockerignore"]);
+        assert_eq!(context_size.total_bytes, 300 + 200 + 50 + 6);
+        assert_eq!(
+            suggest_dockerignore_entries(&context_size),
+            vec!["node_modules".to_string(), ".git".to_string()]
+        );
+    }
+}
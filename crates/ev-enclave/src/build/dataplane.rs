@@ -0,0 +1,150 @@
+use super::error::BuildError;
+use crate::config::ValidatedEnclaveBuildConfig;
+use crate::egress::mtls::{MTLS_CERT_ENV_VAR, MTLS_KEY_ENV_VAR};
+use serde_json::{json, Value};
+
+/// Data plane features which are only available from a given release
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DataPlaneFeature {
+    Healthcheck,
+    ForwardProxyProtocol,
+    TcpProtocol,
+}
+
+impl DataPlaneFeature {
+    fn minimum_version(&self) -> semver::Version {
+        match self {
+            Self::ForwardProxyProtocol => semver::Version::new(1, 0, 0),
+            Self::Healthcheck => semver::Version::new(1, 1, 0),
+            Self::TcpProtocol => semver::Version::new(1, 2, 0),
+        }
+    }
+
+    fn config_key(&self) -> &'static str {
+        match self {
+            Self::Healthcheck => "dataplane.healthcheck",
+            Self::ForwardProxyProtocol => "dataplane.forward_proxy_protocol",
+            Self::TcpProtocol => "network.protocol = \"tcp\"",
+        }
+    }
+}
+
+/// The data plane features a build config relies on
+fn required_features(build_config: &ValidatedEnclaveBuildConfig) -> Vec<DataPlaneFeature> {
+    let mut features = vec![];
+    if build_config.healthcheck().is_some() {
+        features.push(DataPlaneFeature::Healthcheck);
+    }
+    if build_config.forward_proxy_protocol() {
+        features.push(DataPlaneFeature::ForwardProxyProtocol);
+    }
+    if !build_config.protocol().is_http() {
+        features.push(DataPlaneFeature::TcpProtocol);
+    }
+    features
+}
+
+/// Checks the data plane release supports every feature the config enables. Pre-release builds are
+/// checked as the release they lead up to, and unparseable versions aren't checked.
+pub fn check_supported_features(
+    build_config: &ValidatedEnclaveBuildConfig,
+    data_plane_version: &str,
+) -> Result<(), BuildError> {
+    let Ok(version) = semver::Version::parse(data_plane_version) else {
+        log::debug!("Skipping data plane feature checks for version {data_plane_version}");
+        return Ok(());
+    };
+    let release = semver::Version::new(version.major, version.minor, version.patch);
+    let unsupported = required_features(build_config)
+        .into_iter()
+        .find(|feature| release < feature.minimum_version());
+    match unsupported {
+        Some(feature) => Err(BuildError::UnsupportedDataPlaneFeature {
+            setting: feature.config_key().to_string(),
+            version: data_plane_version.to_string(),
+            minimum_version: feature.minimum_version().to_string(),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// The config read by the data plane at boot from /etc/dataplane-config.json
+pub fn dataplane_config(build_config: &ValidatedEnclaveBuildConfig) -> Value {
+    let mut dataplane_info = json!({
+        "api_key_auth":  &build_config.api_key_auth(),
+        "trx_logging_enabled": &build_config.trx_logging_enabled(),
+        "forward_proxy_protocol": build_config.forward_proxy_protocol(),
+        "trusted_headers": build_config.trusted_headers(),
+    });
+
+    let egress = build_config.egress();
+    if egress.is_enabled() {
+        dataplane_info["egress"] = json!({
+            "allow_list": &egress.clone().get_destinations()
+        });
+        // the data plane reads the credentials from the Enclave's environment at boot
+        if egress.mtls.is_some() {
+            dataplane_info["egress"]["mtls"] = json!({
+                "cert_env_var": MTLS_CERT_ENV_VAR,
+                "key_env_var": MTLS_KEY_ENV_VAR,
+            });
+        }
+    }
+
+    if let Some(healthcheck) = build_config.healthcheck() {
+        dataplane_info["healthcheck"] = json!(healthcheck);
+    }
+
+    // API key auth is enforced on HTTP requests, so can't apply to raw TCP traffic
+    if !build_config.protocol().is_http() {
+        dataplane_info["protocol"] = json!(build_config.protocol());
+        dataplane_info["api_key_auth"] = json!(false);
+    }
+    dataplane_info
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::build::test::get_config;
+    use crate::config::{EgressMtlsSettings, NetworkProtocol};
+
+    #[test]
+    fn test_check_supported_features() {
+        let mut config = get_config(false);
+        config.healthcheck = Some("/health".into());
+        assert!(check_supported_features(&config, "1.1.0").is_ok());
+        assert!(check_supported_features(&config, "1.1.0-beta-daac60").is_ok());
+        assert!(check_supported_features(&config, "not-a-version").is_ok());
+        assert!(matches!(
+            check_supported_features(&config, "1.0.4"),
+            Err(BuildError::UnsupportedDataPlaneFeature { minimum_version, .. }) if minimum_version == "1.1.0"
+        ));
+
+        config.healthcheck = None;
+        config.protocol = NetworkProtocol::Tcp;
+        assert!(check_supported_features(&config, "1.1.0").is_err());
+        assert!(dataplane_config(&config)["protocol"] == "tcp");
+    }
+
+    #[test]
+    fn test_dataplane_config_with_egress_mtls() {
+        let mut config = get_config(true);
+        assert!(dataplane_config(&config)["egress"].get("mtls").is_none());
+
+        config.egress.mtls = Some(EgressMtlsSettings {
+            cert_path: "./client.crt".into(),
+            key_path: "./client.key".into(),
+        });
+        let dataplane_config = dataplane_config(&config);
+        assert_eq!(dataplane_config["egress"]["allow_list"], "*");
+        assert_eq!(
+            dataplane_config["egress"]["mtls"]["cert_env_var"],
+            MTLS_CERT_ENV_VAR
+        );
+        assert_eq!(
+            dataplane_config["egress"]["mtls"]["key_env_var"],
+            MTLS_KEY_ENV_VAR
+        );
+    }
+}
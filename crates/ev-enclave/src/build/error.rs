@@ -1,5 +1,5 @@
 use crate::common::OutputPathError;
-use crate::config::SigningInfoError;
+use crate::config::{NetworkProtocol, SigningInfoError};
 use crate::docker::error::DockerError;
 use crate::enclave::error::EnclaveError;
 use common::CliError;
@@ -25,9 +25,54 @@ pub enum BuildError {
     DockerBuildError(String),
     #[error("An error occurred while converting your image to an Enclave — {0}")]
     EnclaveConversionError(String),
+    #[error("Your base image is missing commands required to boot the Enclave: {0}. The Enclave's startup scripts need /bin/sh and these commands on the PATH, so distroless or shell-less base images are not supported. Install them in your Dockerfile (e.g. with busybox) or use a base image such as alpine or debian-slim.")]
+    MissingBaseImageCommands(String),
+    #[error("The build context is {size}, which exceeds the maximum of {max_size}. Exclude files which aren't needed in your Enclave using a .dockerignore file. Largest paths:\n{largest_paths}")]
+    ContextTooLarge {
+        size: String,
+        max_size: String,
+        largest_paths: String,
+    },
+    #[error("Enclaves using the {0} protocol must EXPOSE the port their service listens on in the Dockerfile, or set port in the [network] section of their enclave.toml.")]
+    MissingExposedPort(NetworkProtocol),
+    #[error("{0} isn't a valid port. Ports must be a number between 1 and 65535.")]
+    InvalidServicePort(String),
+    #[error("Your Dockerfile can't be built reproducibly:\n{0}")]
+    NonDeterministicDockerfile(String),
+    #[error("Failed to read the processed Enclave dockerfile - {0}")]
+    FailedToReadEnclaveDockerfile(std::io::Error),
+    #[error("Failed to watch the build context for changes - {0}")]
+    FailedToWatchContext(std::io::Error),
+    #[error("Failed to update the .dockerignore file - {0}")]
+    FailedToUpdateDockerignore(std::io::Error),
+    #[error("The built Enclave has no PCR8, so its PCRs can't be signed. PCR8 is only present for signed EIFs.")]
+    MissingSigningCertPcr,
+    #[error("Failed to write the PCR signature bundle - {0}")]
+    FailedToWritePcrBundle(std::io::Error),
+    #[error("Enclaves without a supervisor can't set a USER in their Dockerfile, as su would run as PID 1 without forwarding signals to your service. Set supervisor = \"tini\" in the [build] section of your enclave.toml, or drop privileges in your entrypoint.")]
+    UnsupervisedUserSwitch,
+    #[error("Your service can't be started as USER {0}, as the Enclave's services start it with su, which needs the name of a user in the image. Set USER to the user's name, such as USER app, or drop privileges in your entrypoint.")]
+    UnsupportedServiceUser(String),
+    #[error("The {setting} setting requires data plane {minimum_version} or later, but this build uses {version}.")]
+    UnsupportedDataPlaneFeature {
+        setting: String,
+        version: String,
+        minimum_version: String,
+    },
+    #[error("Failed to fetch the build asset {path} — {reason}")]
+    RuntimeAssetError { path: String, reason: String },
+    #[error("The {asset} release {version} has digest {resolved}, but {pinned} is pinned in the [runtime] section of your enclave.toml. The release has changed since it was pinned, remove the pinned digest to build with it.")]
+    RuntimeDigestMismatch {
+        asset: String,
+        version: String,
+        pinned: String,
+        resolved: String,
+    },
     #[error(transparent)]
     EnclaveError(#[from] EnclaveError),
     #[error(transparent)]
+    PromptError(#[from] crate::prompt::PromptError),
+    #[error(transparent)]
     Utf8Error(#[from] std::str::Utf8Error),
 }
 
@@ -37,14 +82,29 @@ impl CliError for BuildError {
             Self::ContextPathDoesNotExist
             | Self::InvalidSigningInfo(_)
             | Self::DockerfileAccessError(_) => exitcode::NOINPUT,
-            Self::FailedToAccessOutputDir(_) | Self::FailedToWriteEnclaveDockerfile(_) => {
-                exitcode::IOERR
-            }
+            Self::FailedToAccessOutputDir(_)
+            | Self::FailedToWriteEnclaveDockerfile(_)
+            | Self::FailedToReadEnclaveDockerfile(_)
+            | Self::FailedToWatchContext(_)
+            | Self::FailedToUpdateDockerignore(_)
+            | Self::FailedToWritePcrBundle(_) => exitcode::IOERR,
+            Self::DockerError(DockerError::PlatformError(platform_err)) => platform_err.exitcode(),
             Self::DockerError(_) | Self::DockerBuildError(_) | Self::Utf8Error(_) => {
                 exitcode::SOFTWARE
             }
-            Self::EnclaveConversionError(_) => exitcode::SOFTWARE,
+            Self::EnclaveConversionError(_) | Self::MissingSigningCertPcr => exitcode::SOFTWARE,
+            Self::RuntimeAssetError { .. } => exitcode::UNAVAILABLE,
+            Self::MissingBaseImageCommands(_)
+            | Self::ContextTooLarge { .. }
+            | Self::NonDeterministicDockerfile(_)
+            | Self::MissingExposedPort(_)
+            | Self::InvalidServicePort(_)
+            | Self::UnsupervisedUserSwitch
+            | Self::UnsupportedServiceUser(_)
+            | Self::UnsupportedDataPlaneFeature { .. }
+            | Self::RuntimeDigestMismatch { .. } => exitcode::DATAERR,
             Self::EnclaveError(e) => e.exitcode(),
+            Self::PromptError(e) => e.exitcode(),
         }
     }
 }
@@ -1,17 +1,30 @@
+pub mod ca_certs;
+pub mod context;
+pub mod dataplane;
 pub mod error;
+pub mod port;
+pub mod runtime;
+#[cfg(feature = "pcr-sign")]
+pub mod signature;
+pub mod user;
+pub mod watch;
 use error::BuildError;
+use user::ServiceUser;
 
 use crate::common::{resolve_output_path, OutputPath};
-use crate::config::ValidatedEnclaveBuildConfig;
+use crate::config::{EnclaveType, RestartPolicy, Supervisor, ValidatedEnclaveBuildConfig};
+use crate::docker::credentials::{resolve_build_credentials, BuildCredentials};
+use crate::docker::determinism::{find_non_deterministic_patterns, Severity};
 use crate::docker::error::DockerError;
 use crate::docker::parse::{Directive, DockerfileDecoder, EnvVar, Mode};
-use crate::docker::utils::verify_docker_is_running;
+use crate::docker::platform::{check_base_image_platforms, Platform};
+use crate::docker::utils::{find_missing_image_commands, verify_docker_is_running};
 use crate::enclave;
+use crate::instrumentation::{self, Stage};
 
-use serde_json::json;
-use std::io::Write;
+use std::io::{Read, Write};
 use std::path::Path;
-use tokio::fs::File;
+use std::sync::OnceLock;
 use tokio::io::AsyncRead;
 
 #[cfg(feature = "pcr_signature")]
@@ -19,10 +32,10 @@ use crate::config::SigningInfoError;
 #[cfg(feature = "pcr_signature")]
 use elliptic_curve::{pkcs8::DecodePrivateKey, SecretKey};
 
-const EV_USER_DOCKERFILE_PATH: &str = "enclave.Dockerfile";
+pub const EV_USER_DOCKERFILE_PATH: &str = "enclave.Dockerfile";
 const INSTALLER_DIRECTORY: &str = "/opt/evervault";
-const USER_ENTRYPOINT_SERVICE_PATH: &str = "/etc/service/user-entrypoint";
-const DATA_PLANE_SERVICE_PATH: &str = "/etc/service/data-plane";
+pub(crate) const USER_ENTRYPOINT_SERVICE_PATH: &str = "/etc/service/user-entrypoint";
+pub(crate) const DATA_PLANE_SERVICE_PATH: &str = "/etc/service/data-plane";
 
 #[allow(clippy::too_many_arguments)]
 pub async fn build_enclave_image_file(
@@ -37,6 +50,7 @@ pub async fn build_enclave_image_file(
     from_existing: Option<String>,
     reproducible: bool,
     no_cache: bool,
+    max_context_size_mb: Option<u64>,
 ) -> Result<(enclave::BuiltEnclave, OutputPath), BuildError> {
     let context_path = Path::new(&context_path);
     if !context_path.exists() {
@@ -47,6 +61,8 @@ pub async fn build_enclave_image_file(
         return Err(BuildError::ContextPathDoesNotExist);
     }
 
+    context::check_build_context(context_path, max_context_size_mb)?;
+
     // temporary directory must remain in scope for the whole
     // function so it isn't deleted until all the builds are finished.
     let output_path = resolve_output_path(output_dir)?;
@@ -56,14 +72,18 @@ pub async fn build_enclave_image_file(
     match from_existing {
         Some(path) => {
             let user_dockerfile_path = output_path.path().join(path);
-            enclave::build_user_image(
-                &user_dockerfile_path,
-                context_path,
-                verbose,
-                docker_build_args,
-                timestamp,
-                no_cache,
-            )?;
+            instrumentation::time_stage(Stage::DockerBuild, || {
+                enclave::build_user_image(
+                    &user_dockerfile_path,
+                    context_path,
+                    verbose,
+                    docker_build_args,
+                    timestamp,
+                    no_cache,
+                    None,
+                    enclave_config.platform(),
+                )
+            })?;
         }
         None => {
             build_from_scratch(
@@ -86,11 +106,18 @@ pub async fn build_enclave_image_file(
         log::debug!("Building Nitro CLI image... {output_path}");
     }
 
-    enclave::build_nitro_cli_image(output_path.path(), Some(&signing_info), verbose, no_cache)?;
-    log::info!("Converting docker image to EIF...");
+    enclave::build_nitro_cli_image(
+        output_path.path(),
+        Some(&signing_info),
+        enclave_config.nitro_cli_image(),
+        verbose,
+        no_cache,
+    )?;
     #[allow(unused_mut)]
-    let mut built_enclave = enclave::run_conversion_to_enclave(output_path.path(), verbose)
-        .map_err(BuildError::from)?;
+    let mut built_enclave = instrumentation::time_stage(Stage::EifConversion, || {
+        enclave::run_conversion_to_enclave(output_path.path(), verbose)
+    })
+    .map_err(BuildError::from)?;
 
     #[cfg(feature = "pcr_signature")]
     {
@@ -117,6 +144,54 @@ pub async fn build_enclave_image_file(
     Ok((built_enclave, output_path))
 }
 
+/// Dockerfile path which reads the Dockerfile from stdin, for tools which generate Dockerfiles
+pub const STDIN_DOCKERFILE_PATH: &str = "-";
+
+// Stdin can only be read once, so the Dockerfile is kept for the stages which read it again
+static STDIN_DOCKERFILE: OnceLock<Vec<u8>> = OnceLock::new();
+
+fn read_stdin_dockerfile() -> Result<&'static [u8], std::io::Error> {
+    if let Some(content) = STDIN_DOCKERFILE.get() {
+        return Ok(content);
+    }
+    let mut content = Vec::new();
+    std::io::stdin().read_to_end(&mut content)?;
+    Ok(STDIN_DOCKERFILE.get_or_init(|| content))
+}
+
+/// Reads the Dockerfile at the given path, or from stdin when the path is `-`
+pub(crate) async fn read_dockerfile(dockerfile_path: &str) -> Result<Vec<u8>, BuildError> {
+    let access_error = || BuildError::DockerfileAccessError(dockerfile_path.to_string());
+    if dockerfile_path == STDIN_DOCKERFILE_PATH {
+        return read_stdin_dockerfile()
+            .map(<[u8]>::to_vec)
+            .map_err(|_| access_error());
+    }
+    tokio::fs::read(dockerfile_path)
+        .await
+        .map_err(|_| access_error())
+}
+
+/// Runs the Dockerfile processing stage on its own, returning the directives of the Dockerfile the
+/// build would generate, with the directives Evervault injects
+pub async fn synthesize_dockerfile(
+    enclave_config: &ValidatedEnclaveBuildConfig,
+    data_plane_version: String,
+    installer_version: String,
+    reproducible: bool,
+) -> Result<Vec<Directive>, BuildError> {
+    let dockerfile = read_dockerfile(enclave_config.dockerfile()).await?;
+
+    process_dockerfile(
+        enclave_config,
+        dockerfile.as_slice(),
+        data_plane_version,
+        installer_version,
+        reproducible,
+    )
+    .await
+}
+
 #[allow(clippy::too_many_arguments)]
 pub async fn build_from_scratch(
     enclave_config: &ValidatedEnclaveBuildConfig,
@@ -134,24 +209,14 @@ pub async fn build_from_scratch(
         return Err(DockerError::DaemonNotRunning.into());
     }
 
-    // read dockerfile
-    let dockerfile_path = Path::new(enclave_config.dockerfile());
-    if !dockerfile_path.exists() {
-        return Err(BuildError::DockerfileAccessError(
-            enclave_config.dockerfile().to_string(),
-        ));
-    }
-
-    let dockerfile = File::open(dockerfile_path)
-        .await
-        .map_err(|_| BuildError::DockerfileAccessError(enclave_config.dockerfile().to_string()))?;
-
-    let processed_dockerfile = process_dockerfile(
-        enclave_config,
-        dockerfile,
-        data_plane_version,
-        installer_version,
-        reproducible,
+    let processed_dockerfile = instrumentation::time_stage_async(
+        Stage::DockerfileProcessing,
+        synthesize_dockerfile(
+            enclave_config,
+            data_plane_version,
+            installer_version,
+            reproducible,
+        ),
     )
     .await?;
 
@@ -170,20 +235,100 @@ pub async fn build_from_scratch(
         user_dockerfile_path.display()
     );
 
+    // Kept in scope until the build finishes, as the resolved credentials are deleted on drop.
+    let build_credentials =
+        resolve_build_credentials(&processed_dockerfile).map_err(DockerError::from)?;
+    let docker_config_dir = build_credentials
+        .as_ref()
+        .map(BuildCredentials::docker_config_dir);
+
+    let platform = enclave_config.platform();
+    check_base_image_platforms(&processed_dockerfile, platform, docker_config_dir)
+        .map_err(DockerError::from)?;
+    if let Some(builder) = Platform::builder().filter(|builder| !builder.matches(platform)) {
+        log::info!("Building a {platform} image on a {builder} docker host. The build runs under emulation, so it may be slower than a native build.");
+        if reproducible {
+            log::warn!("Reproducible builds under emulation may not match builds on x86_64 hosts, so their PCRs can differ. Use --remote-builder to build on an x86_64 docker context instead.");
+        }
+    }
+
     log::info!("Building docker image...");
 
-    enclave::build_user_image(
-        &user_dockerfile_path,
-        context_path,
-        verbose,
-        docker_build_args,
-        timestamp,
-        no_cache,
-    )?;
+    instrumentation::time_stage(Stage::DockerBuild, || {
+        enclave::build_user_image(
+            &user_dockerfile_path,
+            context_path,
+            verbose,
+            docker_build_args,
+            timestamp,
+            no_cache,
+            docker_config_dir,
+            platform,
+        )
+    })?;
     log::debug!("User image built...");
+
+    // The generated boot scripts are run by the base image's shell at boot, so check they'll work
+    // now rather than failing in the Enclave.
+    let uses_custom_user = processed_dockerfile
+        .iter()
+        .filter(|directive| directive.is_user())
+        .count()
+        > 1;
+    let required_commands = required_boot_commands(
+        enclave_config.supervisor(),
+        enclave_config.egress.is_enabled(),
+        uses_custom_user,
+    );
+    let missing_commands = find_missing_image_commands(
+        &format!("{}:latest", enclave::EV_USER_IMAGE_NAME),
+        &required_commands,
+    )?;
+    if !missing_commands.is_empty() {
+        return Err(BuildError::MissingBaseImageCommands(
+            missing_commands.join(", "),
+        ));
+    }
+    Ok(())
+}
+
+// Builds are only reproducible if the commands in the Dockerfile are, so flag commands which
+// depend on when the build runs.
+fn check_reproducibility(directives: &[Directive]) -> Result<(), BuildError> {
+    let patterns = find_non_deterministic_patterns(directives);
+    for pattern in patterns.iter() {
+        log::warn!("Non-deterministic Dockerfile command {pattern}");
+    }
+    let errors: Vec<String> = patterns
+        .iter()
+        .filter(|pattern| pattern.severity == Severity::Error)
+        .map(|pattern| pattern.to_string())
+        .collect();
+    if !errors.is_empty() {
+        return Err(BuildError::NonDeterministicDockerfile(errors.join("\n")));
+    }
     Ok(())
 }
 
+/// Commands used by the generated bootstrap and service scripts
+fn required_boot_commands(
+    supervisor: Supervisor,
+    egress_enabled: bool,
+    uses_custom_user: bool,
+) -> Vec<&'static str> {
+    // `source` isn't probed, as the scripts load /etc/customer-env with `.` instead. It's a POSIX
+    // special builtin, so every /bin/sh has it, whereas `source` is missing from shells like dash.
+    let mut commands = vec!["grep", "sleep", "hostname", "ifconfig"];
+    commands.extend(supervisor.required_commands());
+    if egress_enabled {
+        commands.extend(["ip", "iptables"]);
+    }
+    if uses_custom_user {
+        commands.push("su");
+    }
+    commands
+}
+
 async fn process_dockerfile<R: AsyncRead + std::marker::Unpin>(
     build_config: &ValidatedEnclaveBuildConfig,
     dockerfile_src: R,
@@ -194,6 +339,10 @@ async fn process_dockerfile<R: AsyncRead + std::marker::Unpin>(
     // Decode dockerfile from file
     let instruction_set = DockerfileDecoder::decode_dockerfile_from_src(dockerfile_src).await?;
 
+    if reproducible {
+        check_reproducibility(&instruction_set)?;
+    }
+
     // Filter out unwanted directives
     let mut last_cmd = None;
     let mut last_entrypoint = None;
@@ -208,6 +357,11 @@ async fn process_dockerfile<R: AsyncRead + std::marker::Unpin>(
             Directive::Cmd { .. } => last_cmd = Some(directive.clone()),
             Directive::Entrypoint { .. } => last_entrypoint = Some(directive.clone()),
             Directive::Expose { port } => exposed_port = *port,
+            // A USER in an earlier stage doesn't carry over to the stages built from it
+            Directive::From { .. } => {
+                last_user = None;
+                return true;
+            }
             Directive::User(b) => {
                 if let Ok(user) = String::from_utf8(b.to_vec()) {
                     last_user = Some(user);
@@ -239,39 +393,55 @@ async fn process_dockerfile<R: AsyncRead + std::marker::Unpin>(
         return Err(directive_parse_error);
     }
 
+    let service_user = match last_user.as_deref() {
+        Some(user) => ServiceUser::from_directive(user)?,
+        None => None,
+    };
+    if let Some(user) = &service_user {
+        log::info!("Your Dockerfile switches to USER {}, but the Enclave's services need root at boot. The image will run as root, and your service will be started as {} by its run script.", user.name(), user.name());
+    }
+
+    let supervisor = build_config.supervisor();
+    check_supervisor_tradeoffs(supervisor, service_user.as_ref())?;
+
+    // An EXPOSE in the Dockerfile takes precedence over the port recorded in the enclave.toml
+    let service_port = exposed_port.or(build_config.port());
+
+    // The data plane can't infer the port of a raw TCP service from its traffic
+    if service_port.is_none() && !build_config.protocol().is_http() {
+        return Err(BuildError::MissingExposedPort(build_config.protocol()));
+    }
+
+    dataplane::check_supported_features(build_config, &data_plane_version)?;
+
     let wait_for_env = r#"while ! grep -q \"EV_INITIALIZED\" /etc/customer-env\n do echo \"Env not ready, sleeping user process for one second\"\n sleep 1\n done \n . /etc/customer-env\n"#;
     let user_service_builder =
         crate::docker::utils::create_combined_docker_entrypoint(last_entrypoint, last_cmd).map(
-            |entrypoint| build_user_service(entrypoint, wait_for_env, last_user, user_env_vars),
+            |entrypoint| {
+                build_user_service(
+                    entrypoint,
+                    wait_for_env,
+                    service_user.as_ref(),
+                    user_env_vars,
+                    supervisor,
+                    build_config.enclave_type(),
+                )
+            },
         )?;
 
-    let ev_domain = std::env::var("EV_DOMAIN").unwrap_or_else(|_| String::from("evervault.com"));
-
-    let data_plane_url = format!(
-        "https://enclave-build-assets.{}/runtime/{}/data-plane/{}",
-        ev_domain,
-        data_plane_version,
-        build_config.get_dataplane_feature_label()
-    );
+    let data_plane_url = runtime::asset_url(&runtime::data_plane_asset_path(
+        &data_plane_version,
+        build_config,
+    ));
 
     let mut data_plane_run_script =
         r#"echo \"Booting Evervault data plane...\"\nexec /opt/evervault/data-plane"#.to_string();
-    if let Some(port) = exposed_port {
+    if let Some(port) = service_port {
         data_plane_run_script = format!("{data_plane_run_script} {port}");
     }
 
-    let mut dataplane_info = json!({
-        "api_key_auth":  &build_config.api_key_auth(),
-        "trx_logging_enabled": &build_config.trx_logging_enabled(),
-        "forward_proxy_protocol": build_config.forward_proxy_protocol(),
-        "trusted_headers": build_config.trusted_headers(),
-    });
-
-    let egress = build_config.clone().egress;
+    let egress = build_config.egress();
     let egress_config = if egress.is_enabled() {
-        dataplane_info["egress"] = json!({
-            "allow_list": &egress.clone().get_destinations()
-        });
         r#"iptables -A OUTPUT -t nat -p tcp --dport 1:65535 ! -d 127.0.0.1  -j DNAT --to-destination 127.0.0.1:4444\nip route add default via 127.0.0.1 dev lo\niptables -t nat -A POSTROUTING -o lo -s 0.0.0.0 -j SNAT --to-source 127.0.0.1\n"#
     } else {
         ""
@@ -279,29 +449,31 @@ async fn process_dockerfile<R: AsyncRead + std::marker::Unpin>(
 
     let loopback_config = r#"ifconfig lo 127.0.0.1\n echo \"enclave.local\" > /etc/hostname \n echo \"127.0.0.1 enclave.local\" >> /etc/hosts \n hostname -F /etc/hostname \n"#;
 
-    let bootstrap_script = r#"echo \"Booting enclave...\"\nexec runsvdir /etc/service"#;
-
-    let bootstrap_script_content =
-        format!("{}{}{}", loopback_config, egress_config, bootstrap_script);
-
-    let installer_bundle_url = format!(
-        "https://enclave-build-assets.{}/installer/{}.tar.gz",
-        ev_domain, installer_version
+    let bootstrap_script_content = format!(
+        "{}{}{}",
+        loopback_config,
+        egress_config,
+        bootstrap_script(supervisor)
     );
+
+    let installer_bundle_url =
+        runtime::asset_url(&runtime::installer_asset_path(&installer_version));
     let installer_bundle = "runtime-dependencies.tar.gz";
     let installer_destination = format!("{INSTALLER_DIRECTORY}/{installer_bundle}");
 
-    if let Some(healthcheck) = build_config.healthcheck.as_deref() {
-        dataplane_info["healthcheck"] = json!(healthcheck);
-    }
-
     let dataplane_env = format!(
         "echo {} > /etc/dataplane-config.json",
-        dataplane_info.to_string().replace('"', "\\\"")
+        dataplane::dataplane_config(build_config)
+            .to_string()
+            .replace('"', "\\\"")
     );
 
-    let injected_directives = vec![
-        Directive::new_user("root"),
+    let injected_directives = [
+        vec![Directive::new_user("root")],
+        service_user.iter().map(ServiceUser::check_directive).collect(),
+        // install CA certificates before anything which could make TLS connections
+        ca_certs::trust_store_directives(build_config.extra_ca_certs()),
+        vec![
         // install dependencies
         Directive::new_run(format!("mkdir -p {INSTALLER_DIRECTORY}")),
         Directive::new_add(&installer_bundle_url, &installer_destination),
@@ -322,7 +494,12 @@ async fn process_dockerfile<R: AsyncRead + std::marker::Unpin>(
             format!("{DATA_PLANE_SERVICE_PATH}/run").as_str(),
             &[],
         ))
-    ];
+        ],
+        user_service_finish_script(build_config)
+            .into_iter()
+            .collect(),
+    ]
+    .concat();
 
     // add custom directives to end of dockerfile
     Ok([
@@ -348,6 +525,65 @@ async fn process_dockerfile<R: AsyncRead + std::marker::Unpin>(
     .concat())
 }
 
+// Without runit, nothing restarts the data plane or the user's service if they exit, and the
+// Enclave stops when the process running as PID 1 does.
+fn check_supervisor_tradeoffs(
+    supervisor: Supervisor,
+    service_user: Option<&ServiceUser>,
+) -> Result<(), BuildError> {
+    if supervisor.is_runit() {
+        return Ok(());
+    }
+    log::warn!("The {supervisor} supervisor doesn't restart the data plane or your service if they exit. The Enclave will stop when your service does.");
+    // su stays in the foreground as the parent of the service, so would run as PID 1 without
+    // forwarding signals or reaping zombies
+    if supervisor == Supervisor::None && service_user.is_some() {
+        return Err(BuildError::UnsupervisedUserSwitch);
+    }
+    Ok(())
+}
+
+/// runit runs a service's finish script each time its run script exits, with the exit code as the
+/// first argument, or -1 if it was killed by a signal. Services which shouldn't be restarted stop
+/// runsvdir from there, which stops the Enclave.
+fn user_service_finish_script(build_config: &ValidatedEnclaveBuildConfig) -> Option<Directive> {
+    // Jobs stop the supervisor themselves, and other supervisors never restart the service
+    if !build_config.supervisor().is_runit() || build_config.enclave_type().is_job() {
+        return None;
+    }
+    let policy = build_config.restart_policy();
+    let stop_enclave = format!(
+        r#"echo \"User service exited with code \$1. The restart policy is {policy}, so the Enclave is stopping.\"\nkill -HUP 1"#
+    );
+    let finish_script = match policy {
+        RestartPolicy::Always => return None,
+        RestartPolicy::OnFailure => format!(
+            r#"if [ \"\$1\" != \"0\" ]; then echo \"User service exited with code \$1, restarting it\"; exit 0; fi\n{stop_enclave}"#
+        ),
+        RestartPolicy::Never => stop_enclave,
+    };
+    Some(Directive::new_run(
+        crate::docker::utils::write_command_to_script(
+            &finish_script,
+            &format!("{USER_ENTRYPOINT_SERVICE_PATH}/finish"),
+            &[],
+        ),
+    ))
+}
+
+/// The final step of the bootstrap script, which hands PID 1 over to the supervisor
+fn bootstrap_script(supervisor: Supervisor) -> String {
+    let start_data_plane =
+        format!(r#"{DATA_PLANE_SERVICE_PATH}/run &\nexport EV_DATA_PLANE_PID=\$!\n"#);
+    let user_service_runner = format!("{USER_ENTRYPOINT_SERVICE_PATH}/run");
+    let start_services = match supervisor {
+        Supervisor::Runit => "exec runsvdir /etc/service".to_string(),
+        Supervisor::Tini => format!("{start_data_plane}exec tini -g -- {user_service_runner}"),
+        Supervisor::None => format!("{start_data_plane}exec {user_service_runner}"),
+    };
+    format!(r#"echo \"Booting enclave...\"\n{start_services}"#)
+}
+
 // TODO: remove when https://github.com/moby/buildkit/pull/4057 is released
 fn reproducible_build_directives(layer_name: Option<String>) -> Vec<Directive> {
     let repro_time = r#"find $( ls / | grep -E -v "^(dev|mnt|proc|sys)$" ) -xdev | xargs touch --date="@0" --no-dereference || true"#.to_string();
@@ -403,13 +639,35 @@ fn handle_multi_step_builds(
 pub fn build_user_service(
     entrypoint: String,
     wait_for_env: &str,
-    last_user: Option<String>,
+    service_user: Option<&ServiceUser>,
     user_env_vars: Vec<EnvVar>,
+    supervisor: Supervisor,
+    enclave_type: EnclaveType,
 ) -> Directive {
-    let exec_cmd = if let Some(last_user) = last_user {
-        format!("su {last_user} -c 'exec {entrypoint}'")
+    // Jobs run the entrypoint as a child of the script, so the script can stop the Enclave once it exits
+    let exec = if enclave_type.is_job() { "" } else { "exec " };
+    let exec_cmd = if let Some(service_user) = service_user {
+        service_user.switch_command(&entrypoint)
     } else {
-        format!("exec {entrypoint}")
+        format!("{exec}{entrypoint}")
+    };
+
+    // runsvdir would restart a job which exits, so it's told to stop supervising. Other
+    // supervisors exit along with the job.
+    let stop_supervisor = if supervisor.is_runit() {
+        "kill -HUP 1"
+    } else {
+        ""
+    };
+    let job_exit_cmds = if enclave_type.is_job() {
+        vec![
+            r#"EV_JOB_EXIT_CODE=\$?"#,
+            r#"echo \"Job exited with code \$EV_JOB_EXIT_CODE\""#,
+            stop_supervisor,
+            r#"exit \$EV_JOB_EXIT_CODE"#,
+        ]
+    } else {
+        vec![]
     };
 
     let env_cmd = if !user_env_vars.is_empty() {
@@ -425,17 +683,25 @@ pub fn build_user_service(
         "".to_string()
     };
 
-    let cmds = vec![
+    // Outside of runit, the bootstrap script passes on the pid of the data plane it started
+    let data_plane_check = if supervisor.is_runit() {
+        "SVDIR=/etc/service sv check data-plane || exit 1"
+    } else {
+        r#"kill -0 \"\$EV_DATA_PLANE_PID\" || exit 1"#
+    };
+
+    let mut cmds = vec![
         env_cmd.as_str(),
         "sleep 5",
         r#"echo \"Checking status of data-plane\""#,
-        "SVDIR=/etc/service sv check data-plane || exit 1",
+        data_plane_check,
         r#"echo \"Data-plane up and running\""#,
         wait_for_env,
         r#"echo \"Booting user service...\""#,
         "cd %s",
         exec_cmd.as_str(),
     ];
+    cmds.extend(job_exit_cmds);
 
     let entrypoint_script = cmds
         .into_iter()
@@ -454,11 +720,16 @@ pub fn build_user_service(
 }
 
 #[cfg(test)]
-mod test {
-    use super::process_dockerfile;
+pub(crate) mod test {
+    use super::error::BuildError;
+    use super::{process_dockerfile, required_boot_commands, synthesize_dockerfile};
     use crate::cert::CertValidityPeriod;
     use crate::config::EgressSettings;
+    use crate::config::EnclaveType;
+    use crate::config::NetworkProtocol;
+    use crate::config::RestartPolicy;
     use crate::config::ScalingSettings;
+    use crate::config::Supervisor;
     use crate::config::ValidatedEnclaveBuildConfig;
     use crate::config::ValidatedSigningInfo;
     use crate::docker;
@@ -467,9 +738,10 @@ mod test {
     use std::iter::zip;
     use tempfile::TempDir;
 
-    fn get_config(egress_enabled: bool) -> ValidatedEnclaveBuildConfig {
+    pub(crate) fn get_config(egress_enabled: bool) -> ValidatedEnclaveBuildConfig {
         ValidatedEnclaveBuildConfig {
             enclave_name: "test".into(),
+            enclave_type: EnclaveType::Service,
             enclave_uuid: "1234".into(),
             team_uuid: "teamid".into(),
             version: 1,
@@ -479,6 +751,8 @@ mod test {
             egress: EgressSettings {
                 enabled: egress_enabled,
                 destinations: None,
+                presets: vec![],
+                mtls: None,
             },
             scaling: Some(ScalingSettings {
                 desired_replicas: 2,
@@ -491,6 +765,7 @@ mod test {
                     not_before: "".into(),
                     not_after: "".into(),
                 },
+                next: None,
             },
             tls_termination: true,
             api_key_auth: true,
@@ -498,9 +773,279 @@ mod test {
             forward_proxy_protocol: false,
             trusted_headers: vec!["X-Evervault-*".to_string()],
             healthcheck: None,
+            protocol: NetworkProtocol::Http,
+            port: None,
+            nitro_cli_image: Default::default(),
+            supervisor: Supervisor::Runit,
+            restart_policy: RestartPolicy::Always,
+            size: None,
+            platform: Default::default(),
+            extra_ca_certs: vec![],
         }
     }
 
+    #[test]
+    fn test_required_boot_commands() {
+        let commands = required_boot_commands(Supervisor::Runit, false, false);
+        assert!(commands.contains(&"ifconfig"));
+        assert!(commands.contains(&"runsvdir"));
+        assert!(!commands.contains(&"iptables"));
+        assert!(!commands.contains(&"su"));
+
+        let commands = required_boot_commands(Supervisor::Runit, true, true);
+        assert!(commands.contains(&"iptables"));
+        assert!(commands.contains(&"ip"));
+        assert!(commands.contains(&"su"));
+
+        let commands = required_boot_commands(Supervisor::Tini, false, false);
+        assert!(commands.contains(&"tini"));
+        assert!(!commands.contains(&"sv"));
+    }
+
+    #[tokio::test]
+    async fn test_process_dockerfile_alternate_supervisors() {
+        let dockerfile = "FROM alpine\nENTRYPOINT [\"/server\"]";
+        let mut config = get_config(false);
+
+        config.supervisor = Supervisor::Tini;
+        let processed_file: Vec<String> = process_dockerfile(
+            &config,
+            dockerfile.as_bytes(),
+            "0.0.0".into(),
+            "abcdef".into(),
+            false,
+        )
+        .await
+        .unwrap()
+        .iter()
+        .map(|d| d.to_string())
+        .collect();
+        let bootstrap = processed_file
+            .iter()
+            .find(|directive| directive.contains("> /bootstrap"))
+            .unwrap();
+        assert!(bootstrap.contains(r#"/etc/service/data-plane/run &\nexport EV_DATA_PLANE_PID=\$!\nexec tini -g -- /etc/service/user-entrypoint/run\n"#));
+        assert!(!bootstrap.contains("runsvdir"));
+        let user_service = processed_file
+            .iter()
+            .find(|directive| directive.contains("> /etc/service/user-entrypoint/run"))
+            .unwrap();
+        assert!(user_service.contains(r#"kill -0 \"\$EV_DATA_PLANE_PID\" || exit 1"#));
+        assert!(!user_service.contains("sv check"));
+
+        config.supervisor = Supervisor::None;
+        let processed_file = process_dockerfile(
+            &config,
+            dockerfile.as_bytes(),
+            "0.0.0".into(),
+            "abcdef".into(),
+            false,
+        )
+        .await
+        .unwrap();
+        assert!(processed_file.iter().any(|directive| directive
+            .to_string()
+            .contains(r#"\nexec /etc/service/user-entrypoint/run\n"#)));
+
+        let dockerfile = "FROM alpine\nUSER app\nENTRYPOINT [\"/server\"]";
+        let result = process_dockerfile(
+            &config,
+            dockerfile.as_bytes(),
+            "0.0.0".into(),
+            "abcdef".into(),
+            false,
+        )
+        .await;
+        assert!(matches!(result, Err(BuildError::UnsupervisedUserSwitch)));
+    }
+
+    #[tokio::test]
+    async fn test_process_dockerfile_resets_user_between_stages() {
+        let dockerfile = "FROM node AS builder\nUSER node\nRUN npm ci\nFROM alpine\nCOPY --from=builder /app /app\nENTRYPOINT [\"/app/server\"]";
+        let processed_file = process_dockerfile(
+            &get_config(false),
+            dockerfile.as_bytes(),
+            "0.0.0".into(),
+            "abcdef".into(),
+            false,
+        )
+        .await
+        .unwrap();
+        assert!(!processed_file
+            .iter()
+            .any(|directive| directive.to_string().contains("su -s /bin/sh")));
+
+        let dockerfile = "FROM alpine\nUSER 1000\nENTRYPOINT [\"/server\"]";
+        let result = process_dockerfile(
+            &get_config(false),
+            dockerfile.as_bytes(),
+            "0.0.0".into(),
+            "abcdef".into(),
+            false,
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(BuildError::UnsupportedServiceUser(user)) if user == "1000"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_process_dockerfile_restart_policy() {
+        let dockerfile = "FROM alpine\nENTRYPOINT [\"/server\"]";
+        let mut config = get_config(false);
+        let finish_script = |processed_file: Vec<docker::parse::Directive>| {
+            processed_file
+                .iter()
+                .map(|directive| directive.to_string())
+                .find(|directive| directive.contains("> /etc/service/user-entrypoint/finish"))
+        };
+
+        // runit restarts the service by default, so no finish script is needed
+        let processed_file = process_dockerfile(
+            &config,
+            dockerfile.as_bytes(),
+            "0.0.0".into(),
+            "abcdef".into(),
+            false,
+        )
+        .await
+        .unwrap();
+        assert!(finish_script(processed_file).is_none());
+
+        config.restart_policy = RestartPolicy::OnFailure;
+        let processed_file = process_dockerfile(
+            &config,
+            dockerfile.as_bytes(),
+            "0.0.0".into(),
+            "abcdef".into(),
+            false,
+        )
+        .await
+        .unwrap();
+        let finish = finish_script(processed_file).unwrap();
+        assert!(finish.contains(r#"if [ \"\$1\" != \"0\" ]; then echo \"User service exited with code \$1, restarting it\"; exit 0; fi"#));
+        assert!(finish.contains("The restart policy is on-failure, so the Enclave is stopping."));
+        assert!(finish.contains(r#"\nkill -HUP 1\n"#));
+
+        config.restart_policy = RestartPolicy::Never;
+        let processed_file = process_dockerfile(
+            &config,
+            dockerfile.as_bytes(),
+            "0.0.0".into(),
+            "abcdef".into(),
+            false,
+        )
+        .await
+        .unwrap();
+        let finish = finish_script(processed_file).unwrap();
+        assert!(!finish.contains("restarting it"));
+        assert!(finish.contains("The restart policy is never, so the Enclave is stopping."));
+
+        // tini exits along with the service, so the policy is already honoured
+        config.supervisor = Supervisor::Tini;
+        let processed_file = process_dockerfile(
+            &config,
+            dockerfile.as_bytes(),
+            "0.0.0".into(),
+            "abcdef".into(),
+            false,
+        )
+        .await
+        .unwrap();
+        assert!(finish_script(processed_file).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_process_dockerfile_job() {
+        let dockerfile = "FROM alpine\nENTRYPOINT [\"/migrate\"]";
+        let mut config = get_config(false);
+        config.enclave_type = EnclaveType::Job;
+        let processed_file = process_dockerfile(
+            &config,
+            dockerfile.as_bytes(),
+            "0.0.0".into(),
+            "abcdef".into(),
+            false,
+        )
+        .await
+        .unwrap();
+        let user_service = processed_file
+            .iter()
+            .map(|directive| directive.to_string())
+            .find(|directive| directive.contains("> /etc/service/user-entrypoint/run"))
+            .unwrap();
+        assert!(user_service.contains(r#"\n/migrate\nEV_JOB_EXIT_CODE=\$?\n"#));
+        assert!(!user_service.contains("exec /migrate"));
+        assert!(user_service.contains(r#"kill -HUP 1\nexit \$EV_JOB_EXIT_CODE"#));
+    }
+
+    #[tokio::test]
+    async fn test_synthesize_dockerfile_from_stdin() {
+        super::STDIN_DOCKERFILE
+            .set(b"FROM alpine\nENTRYPOINT [\"/server\"]".to_vec())
+            .unwrap();
+        let mut config = get_config(false);
+        config.dockerfile = super::STDIN_DOCKERFILE_PATH.into();
+        let synthesize = || synthesize_dockerfile(&config, "0.0.0".into(), "abcdef".into(), false);
+        let processed_file = synthesize().await.unwrap();
+        assert!(processed_file
+            .iter()
+            .any(|directive| directive.to_string() == "FROM alpine"));
+        // Stages which read the Dockerfile again get the same content
+        assert_eq!(processed_file.len(), synthesize().await.unwrap().len());
+    }
+
+    #[tokio::test]
+    async fn test_process_dockerfile_tcp_protocol() {
+        let mut config = get_config(false);
+        config.tls_termination = false;
+        config.trx_logging_enabled = false;
+        config.trusted_headers = vec![];
+        config.protocol = NetworkProtocol::Tcp;
+
+        let dockerfile = "FROM alpine\nEXPOSE 50051\nENTRYPOINT [\"/grpc-server\"]";
+        let processed_file = process_dockerfile(
+            &config,
+            dockerfile.as_bytes(),
+            "1.2.0".into(),
+            "abcdef".into(),
+            false,
+        )
+        .await
+        .unwrap();
+        let processed_file: Vec<String> = processed_file.iter().map(|d| d.to_string()).collect();
+        assert!(processed_file.contains(&r#"RUN echo {\"api_key_auth\":false,\"forward_proxy_protocol\":false,\"protocol\":\"tcp\",\"trusted_headers\":[],\"trx_logging_enabled\":false} > /etc/dataplane-config.json"#.to_string()));
+        assert!(processed_file
+            .iter()
+            .any(|directive| directive.contains("exec /opt/evervault/data-plane 50051")));
+
+        let dockerfile = "FROM alpine\nENTRYPOINT [\"/grpc-server\"]";
+        let result = process_dockerfile(
+            &config,
+            dockerfile.as_bytes(),
+            "1.2.0".into(),
+            "abcdef".into(),
+            false,
+        )
+        .await;
+        assert!(matches!(result, Err(BuildError::MissingExposedPort(_))));
+
+        config.port = Some(9000);
+        let processed_file = process_dockerfile(
+            &config,
+            dockerfile.as_bytes(),
+            "1.2.0".into(),
+            "abcdef".into(),
+            false,
+        )
+        .await
+        .unwrap();
+        assert!(processed_file.iter().any(|directive| directive
+            .to_string()
+            .contains("exec /opt/evervault/data-plane 9000")));
+    }
+
     #[tokio::test]
     async fn test_process_dockerfile_reproducible() {
         let sample_dockerfile_contents = r#"FROM alpine
@@ -765,7 +1310,7 @@ ENTRYPOINT ["sh", "/hello-script"]"#;
         let mut config: ValidatedEnclaveBuildConfig = get_config(false);
         config.healthcheck = Some("/health".into());
 
-        let data_plane_version = "0.0.0".to_string();
+        let data_plane_version = "1.1.0".to_string();
         let installer_version = "abcdef".to_string();
         let processed_file = process_dockerfile(
             &config,
@@ -788,7 +1333,7 @@ RUN cd /opt/evervault ; tar -xzf runtime-dependencies.tar.gz ; sh ./installer.sh
 RUN echo {\"api_key_auth\":true,\"forward_proxy_protocol\":false,\"healthcheck\":\"/health\",\"trusted_headers\":[\"X-Evervault-*\"],\"trx_logging_enabled\":true} > /etc/dataplane-config.json
 RUN mkdir -p /etc/service/user-entrypoint
 RUN printf "#!/bin/sh\nsleep 5\necho \"Checking status of data-plane\"\nSVDIR=/etc/service sv check data-plane || exit 1\necho \"Data-plane up and running\"\nwhile ! grep -q \"EV_INITIALIZED\" /etc/customer-env\n do echo \"Env not ready, sleeping user process for one second\"\n sleep 1\n done \n . /etc/customer-env\n\necho \"Booting user service...\"\ncd %s\nexec sh /hello-script\n" "$PWD"  > /etc/service/user-entrypoint/run && chmod +x /etc/service/user-entrypoint/run
-ADD https://enclave-build-assets.evervault.com/runtime/0.0.0/data-plane/egress-disabled/tls-termination-enabled /opt/evervault/data-plane
+ADD https://enclave-build-assets.evervault.com/runtime/1.1.0/data-plane/egress-disabled/tls-termination-enabled /opt/evervault/data-plane
 RUN chmod +x /opt/evervault/data-plane
 RUN mkdir -p /etc/service/data-plane
 RUN printf "#!/bin/sh\necho \"Booting Evervault data plane...\"\nexec /opt/evervault/data-plane 3443\n" > /etc/service/data-plane/run && chmod +x /etc/service/data-plane/run
@@ -843,12 +1388,13 @@ USER someuser
 RUN touch /hello-script;\
     /bin/sh -c "echo -e '"'#!/bin/sh\nwhile true; do echo "hello"; sleep 2; done;\n'"' > /hello-script"
 USER root
+RUN id -u someuser > /dev/null 2>&1 || (echo "The user someuser set by USER in your Dockerfile doesn't exist in the image" && exit 1)
 RUN mkdir -p /opt/evervault
 ADD https://enclave-build-assets.evervault.com/installer/abcdef.tar.gz /opt/evervault/runtime-dependencies.tar.gz
 RUN cd /opt/evervault ; tar -xzf runtime-dependencies.tar.gz ; sh ./installer.sh ; rm runtime-dependencies.tar.gz
 RUN echo {\"api_key_auth\":true,\"forward_proxy_protocol\":false,\"trusted_headers\":[\"X-Evervault-*\"],\"trx_logging_enabled\":true} > /etc/dataplane-config.json
 RUN mkdir -p /etc/service/user-entrypoint
-RUN printf "#!/bin/sh\nsleep 5\necho \"Checking status of data-plane\"\nSVDIR=/etc/service sv check data-plane || exit 1\necho \"Data-plane up and running\"\nwhile ! grep -q \"EV_INITIALIZED\" /etc/customer-env\n do echo \"Env not ready, sleeping user process for one second\"\n sleep 1\n done \n . /etc/customer-env\n\necho \"Booting user service...\"\ncd %s\nsu someuser -c 'exec sh /hello-script'\n" "$PWD"  > /etc/service/user-entrypoint/run && chmod +x /etc/service/user-entrypoint/run
+RUN printf "#!/bin/sh\nsleep 5\necho \"Checking status of data-plane\"\nSVDIR=/etc/service sv check data-plane || exit 1\necho \"Data-plane up and running\"\nwhile ! grep -q \"EV_INITIALIZED\" /etc/customer-env\n do echo \"Env not ready, sleeping user process for one second\"\n sleep 1\n done \n . /etc/customer-env\n\necho \"Booting user service...\"\ncd %s\nsu -s /bin/sh someuser -c 'exec sh /hello-script'\n" "$PWD"  > /etc/service/user-entrypoint/run && chmod +x /etc/service/user-entrypoint/run
 ADD https://enclave-build-assets.evervault.com/runtime/0.0.0/data-plane/egress-disabled/tls-termination-enabled /opt/evervault/data-plane
 RUN chmod +x /opt/evervault/data-plane
 RUN mkdir -p /etc/service/data-plane
@@ -904,12 +1450,13 @@ USER someuser
 RUN touch /hello-script;\
     /bin/sh -c "echo -e '"'#!/bin/sh\nwhile true; do echo "hello"; sleep 2; done;\n'"' > /hello-script"
 USER root
+RUN id -u someuser > /dev/null 2>&1 || (echo "The user someuser set by USER in your Dockerfile doesn't exist in the image" && exit 1)
 RUN mkdir -p /opt/evervault
 ADD https://enclave-build-assets.evervault.com/installer/abcdef.tar.gz /opt/evervault/runtime-dependencies.tar.gz
 RUN cd /opt/evervault ; tar -xzf runtime-dependencies.tar.gz ; sh ./installer.sh ; rm runtime-dependencies.tar.gz
 RUN echo {\"api_key_auth\":true,\"egress\":{\"allow_list\":\"*\"},\"forward_proxy_protocol\":false,\"trusted_headers\":[\"X-Evervault-*\"],\"trx_logging_enabled\":true} > /etc/dataplane-config.json
 RUN mkdir -p /etc/service/user-entrypoint
-RUN printf "#!/bin/sh\nsleep 5\necho \"Checking status of data-plane\"\nSVDIR=/etc/service sv check data-plane || exit 1\necho \"Data-plane up and running\"\nwhile ! grep -q \"EV_INITIALIZED\" /etc/customer-env\n do echo \"Env not ready, sleeping user process for one second\"\n sleep 1\n done \n . /etc/customer-env\n\necho \"Booting user service...\"\ncd %s\nsu someuser -c 'exec sh /hello-script'\n" "$PWD"  > /etc/service/user-entrypoint/run && chmod +x /etc/service/user-entrypoint/run
+RUN printf "#!/bin/sh\nsleep 5\necho \"Checking status of data-plane\"\nSVDIR=/etc/service sv check data-plane || exit 1\necho \"Data-plane up and running\"\nwhile ! grep -q \"EV_INITIALIZED\" /etc/customer-env\n do echo \"Env not ready, sleeping user process for one second\"\n sleep 1\n done \n . /etc/customer-env\n\necho \"Booting user service...\"\ncd %s\nsu -s /bin/sh someuser -c 'exec sh /hello-script'\n" "$PWD"  > /etc/service/user-entrypoint/run && chmod +x /etc/service/user-entrypoint/run
 ADD https://enclave-build-assets.evervault.com/runtime/0.0.0/data-plane/egress-enabled/tls-termination-enabled /opt/evervault/data-plane
 RUN chmod +x /opt/evervault/data-plane
 RUN mkdir -p /etc/service/data-plane
@@ -0,0 +1,301 @@
+use super::error::BuildError;
+use super::read_dockerfile;
+use crate::config::ValidatedEnclaveBuildConfig;
+use crate::docker::parse::{Directive, DockerfileDecoder};
+use crate::prompt;
+use regex::Regex;
+use std::path::Path;
+
+const PORT_PROMPT: &str =
+    "Your Dockerfile doesn't EXPOSE a port. Which port does your service listen on?";
+
+/// A port the service in the build context looks like it listens on, and where it was found
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DetectedPort {
+    pub port: u16,
+    pub source: String,
+}
+
+fn parse_port(value: &str) -> Option<u16> {
+    value
+        .trim()
+        .trim_matches(|c| c == '"' || c == '\'')
+        .parse()
+        .ok()
+        .filter(|port| *port != 0)
+}
+
+// Matches the port flags of common servers, like `--port 3000`, `-p 3000`, `PORT=3000` and
+// `--bind 0.0.0.0:8000`
+fn find_port_flag(command: &str) -> Option<u16> {
+    let port_flag =
+        Regex::new(r"(?:--port[= ]+|-p\s+|\bPORT=|--bind[= ]+[\w.\-]*:|-b\s+[\w.\-]*:)(\d{1,5})\b")
+            .expect("infallible - valid regex");
+    port_flag
+        .captures(command)
+        .and_then(|captures| parse_port(&captures[1]))
+}
+
+fn read_context_file(context_path: &Path, path: &str) -> Option<String> {
+    std::fs::read_to_string(context_path.join(path)).ok()
+}
+
+fn detect_package_json_port(context_path: &Path) -> Vec<DetectedPort> {
+    let Some(package_json) = read_context_file(context_path, "package.json")
+        .and_then(|contents| serde_json::from_str::<serde_json::Value>(&contents).ok())
+    else {
+        return vec![];
+    };
+    let mut detected = vec![];
+    if let Some(scripts) = package_json["scripts"].as_object() {
+        // The start script is what's usually run in production, so it's checked first
+        let start = scripts.get_key_value("start");
+        let others = scripts.iter().filter(|(name, _)| name.as_str() != "start");
+        for (name, script) in start.into_iter().chain(others) {
+            if let Some(port) = script.as_str().and_then(find_port_flag) {
+                detected.push(DetectedPort {
+                    port,
+                    source: format!("the {name} script in package.json"),
+                });
+            }
+        }
+    }
+    let has_dependency = |name: &str| {
+        ["dependencies", "devDependencies"]
+            .iter()
+            .any(|section| package_json[section].get(name).is_some())
+    };
+    for (dependency, framework) in [("next", "Next.js"), ("nuxt", "Nuxt")] {
+        if has_dependency(dependency) {
+            detected.push(DetectedPort {
+                port: 3000,
+                source: format!("the default port for {framework}"),
+            });
+        }
+    }
+    detected
+}
+
+fn detect_framework_config_ports(context_path: &Path) -> Vec<DetectedPort> {
+    let mut detected = vec![];
+    let mut detect = |path: &str, port: Option<u16>| {
+        if let Some(port) = port {
+            detected.push(DetectedPort {
+                port,
+                source: path.to_string(),
+            });
+        }
+    };
+
+    let procfile = read_context_file(context_path, "Procfile");
+    detect(
+        "Procfile",
+        procfile.as_deref().and_then(|procfile| {
+            procfile
+                .lines()
+                .find_map(|line| line.strip_prefix("web:"))
+                .and_then(find_port_flag)
+        }),
+    );
+
+    let gunicorn_bind = Regex::new(r#"bind\s*=\s*\[?\s*["'][\w.\-]*:(\d{1,5})["']"#)
+        .expect("infallible - valid regex");
+    let gunicorn_config = read_context_file(context_path, "gunicorn.conf.py");
+    detect(
+        "gunicorn.conf.py",
+        gunicorn_config.as_deref().and_then(|config| {
+            gunicorn_bind
+                .captures(config)
+                .and_then(|captures| parse_port(&captures[1]))
+        }),
+    );
+
+    let spring_port = Regex::new(r"(?m)^\s*server\.port\s*[=:]\s*(\d{1,5})\s*$")
+        .expect("infallible - valid regex");
+    for path in [
+        "application.properties",
+        "src/main/resources/application.properties",
+    ] {
+        let properties = read_context_file(context_path, path);
+        detect(
+            path,
+            properties.as_deref().and_then(|properties| {
+                spring_port
+                    .captures(properties)
+                    .and_then(|captures| parse_port(&captures[1]))
+            }),
+        );
+    }
+
+    let puma_port = Regex::new(r"(?m)^\s*port\s+(?:ENV\.fetch\([^)]*\)\s*\{\s*)?(\d{1,5})")
+        .expect("infallible - valid regex");
+    if let Some(puma_config) = read_context_file(context_path, "config/puma.rb") {
+        let port = puma_port
+            .captures(&puma_config)
+            .and_then(|captures| parse_port(&captures[1]));
+        detect("config/puma.rb", port.or(Some(3000)));
+    }
+
+    if context_path.join("manage.py").is_file() {
+        detected.push(DetectedPort {
+            port: 8000,
+            source: "the default port for Django".to_string(),
+        });
+    }
+    detected
+}
+
+/// Looks for the port the service listens on in the Dockerfile's PORT variable, the package.json
+/// scripts, and the config files of common frameworks. Each port is returned once, from its most
+/// specific source.
+pub fn detect_ports(directives: &[Directive], context_path: &Path) -> Vec<DetectedPort> {
+    let env_port = directives
+        .iter()
+        .rev()
+        .find_map(|directive| match directive {
+            Directive::Env { vars } => vars.iter().rev().find(|var| var.key == "PORT"),
+            _ => None,
+        })
+        .and_then(|var| parse_port(&var.val))
+        .map(|port| DetectedPort {
+            port,
+            source: "ENV PORT in the Dockerfile".to_string(),
+        });
+
+    let mut detected: Vec<DetectedPort> = vec![];
+    let candidates = env_port
+        .into_iter()
+        .chain(detect_package_json_port(context_path))
+        .chain(detect_framework_config_ports(context_path));
+    for candidate in candidates {
+        if !detected.iter().any(|found| found.port == candidate.port) {
+            detected.push(candidate);
+        }
+    }
+    detected
+}
+
+fn choose_port(detected: &[DetectedPort]) -> Result<u16, BuildError> {
+    if !detected.is_empty() {
+        let mut items: Vec<String> = detected
+            .iter()
+            .map(|found| format!("{} (from {})", found.port, found.source))
+            .collect();
+        items.push("Another port".to_string());
+        let choice = prompt::select(PORT_PROMPT, &items, 0)?;
+        if let Some(found) = detected.get(choice) {
+            return Ok(found.port);
+        }
+    }
+    let answer = prompt::input(PORT_PROMPT)?;
+    parse_port(&answer).ok_or(BuildError::InvalidServicePort(answer))
+}
+
+// A missing Dockerfile is reported by the build itself, so it's treated as having no directives
+pub(crate) async fn read_dockerfile_directives(
+    dockerfile_path: &str,
+) -> Result<Vec<Directive>, BuildError> {
+    let Ok(dockerfile) = read_dockerfile(dockerfile_path).await else {
+        return Ok(vec![]);
+    };
+    Ok(DockerfileDecoder::decode_dockerfile_from_src(dockerfile.as_slice()).await?)
+}
+
+fn exposed_port(directives: &[Directive]) -> Option<u16> {
+    directives
+        .iter()
+        .rev()
+        .find_map(|directive| match directive {
+            Directive::Expose { port } => *port,
+            _ => None,
+        })
+}
+
+/// The port the Enclave's service listens on, from the Dockerfile's EXPOSE or the enclave.toml
+pub async fn service_port(
+    build_config: &ValidatedEnclaveBuildConfig,
+) -> Result<Option<u16>, BuildError> {
+    let directives = read_dockerfile_directives(build_config.dockerfile()).await?;
+    Ok(exposed_port(&directives).or(build_config.port()))
+}
+
+/// Finds the port the Enclave's service listens on when its Dockerfile doesn't EXPOSE one and its
+/// enclave.toml doesn't set one, asking when there's a terminal to ask on. Returns the port so it
+/// can be recorded in the enclave.toml, or None when nothing needs to be recorded.
+pub async fn resolve_service_port(
+    build_config: &ValidatedEnclaveBuildConfig,
+    context_path: &str,
+) -> Result<Option<u16>, BuildError> {
+    if build_config.port().is_some() {
+        return Ok(None);
+    }
+    let directives = read_dockerfile_directives(build_config.dockerfile()).await?;
+    if directives.is_empty() || exposed_port(&directives).is_some() {
+        return Ok(None);
+    }
+
+    let detected = detect_ports(&directives, Path::new(context_path));
+    if prompt::can_prompt() {
+        return choose_port(&detected).map(Some);
+    }
+    match detected.first() {
+        Some(found) => {
+            log::info!(
+                "Your Dockerfile doesn't EXPOSE a port, so the Enclave will forward traffic to port {} (from {}).",
+                found.port,
+                found.source
+            );
+            Ok(Some(found.port))
+        }
+        None => {
+            if build_config.protocol().is_http() {
+                log::warn!("Your Dockerfile doesn't EXPOSE a port, and the port your service listens on couldn't be detected. EXPOSE it in your Dockerfile, or set port in the [network] section of your enclave.toml.");
+            }
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_detect_ports() {
+        let context = TempDir::new().unwrap();
+        let write_file = |path: &str, contents: &str| {
+            let path = context.path().join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        };
+        write_file(
+            "package.json",
+            r#"{"scripts":{"dev":"next dev -p 4000","start":"next start --port 8080"},"dependencies":{"next":"14.0.0"}}"#,
+        );
+        write_file(
+            "Procfile",
+            "release: ./migrate\nweb: gunicorn app:app --bind 0.0.0.0:8080\n",
+        );
+        write_file("manage.py", "");
+
+        let dockerfile =
+            "FROM node:20\nENV NODE_ENV=production PORT=\"5000\"\nCMD [\"npm\", \"start\"]";
+        let directives = DockerfileDecoder::decode_dockerfile_from_src(dockerfile.as_bytes())
+            .await
+            .unwrap();
+
+        let detected = detect_ports(&directives, context.path());
+        let ports: Vec<u16> = detected.iter().map(|found| found.port).collect();
+        assert_eq!(ports, vec![5000, 8080, 4000, 3000, 8000]);
+        assert_eq!(detected[1].source, "the start script in package.json");
+        assert_eq!(detected[3].source, "the default port for Next.js");
+
+        assert!(detect_ports(&[], TempDir::new().unwrap().path()).is_empty());
+        assert_eq!(
+            find_port_flag("rails server -b 0.0.0.0 -p 3001"),
+            Some(3001)
+        );
+        assert_eq!(find_port_flag("node server.js"), None);
+    }
+}
@@ -0,0 +1,175 @@
+use super::error::BuildError;
+use crate::config::{RuntimeSettings, ValidatedEnclaveBuildConfig};
+use common::api::enclave_assets::EnclaveAssetsClient;
+use sha2::{Digest, Sha256};
+
+pub use crate::api::enclave::RuntimeDigests;
+
+pub fn data_plane_asset_path(
+    data_plane_version: &str,
+    build_config: &ValidatedEnclaveBuildConfig,
+) -> String {
+    format!(
+        "runtime/{}/data-plane/{}",
+        data_plane_version,
+        build_config.get_dataplane_feature_label()
+    )
+}
+
+pub fn installer_asset_path(installer_version: &str) -> String {
+    format!("installer/{installer_version}.tar.gz")
+}
+
+/// The URL docker adds a build asset from
+pub fn asset_url(path: &str) -> String {
+    format!(
+        "{}/{path}",
+        common::endpoint::current().enclave_assets_url()
+    )
+}
+
+async fn fetch_asset_digest(
+    client: &EnclaveAssetsClient,
+    path: &str,
+) -> Result<String, BuildError> {
+    let mut response =
+        client
+            .get_asset(path)
+            .await
+            .map_err(|source| BuildError::RuntimeAssetError {
+                path: path.to_string(),
+                reason: source.to_string(),
+            })?;
+    let mut hasher = Sha256::new();
+    while let Some(chunk) =
+        response
+            .chunk()
+            .await
+            .map_err(|source| BuildError::RuntimeAssetError {
+                path: path.to_string(),
+                reason: source.to_string(),
+            })?
+    {
+        hasher.update(&chunk);
+    }
+    Ok(format!("sha256:{}", hex::encode(hasher.finalize())))
+}
+
+/// Checks the digests against those recorded for the same releases in the [runtime] section, so
+/// a release which has changed since the Enclave was last built fails the build.
+pub fn check_pinned_digests(
+    pinned: Option<&RuntimeSettings>,
+    data_plane_version: &str,
+    installer_version: &str,
+    digests: &RuntimeDigests,
+) -> Result<(), BuildError> {
+    let Some(pinned) = pinned else {
+        return Ok(());
+    };
+    let mismatch = |asset: &str, version: &str, pinned: &str, resolved: &str| {
+        BuildError::RuntimeDigestMismatch {
+            asset: asset.to_string(),
+            version: version.to_string(),
+            pinned: pinned.to_string(),
+            resolved: resolved.to_string(),
+        }
+    };
+
+    let same_data_plane = pinned.data_plane_version.as_deref() == Some(data_plane_version)
+        && pinned.data_plane_variant.as_deref() == Some(&digests.data_plane_variant);
+    if let Some(pinned_digest) = pinned.data_plane_digest.as_deref() {
+        if same_data_plane && pinned_digest != digests.data_plane {
+            return Err(mismatch(
+                "data plane",
+                data_plane_version,
+                pinned_digest,
+                &digests.data_plane,
+            ));
+        }
+    }
+
+    let same_installer = pinned.installer_version.as_deref() == Some(installer_version);
+    if let Some(pinned_digest) = pinned.installer_digest.as_deref() {
+        if same_installer && pinned_digest != digests.installer {
+            return Err(mismatch(
+                "installer",
+                installer_version,
+                pinned_digest,
+                &digests.installer,
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Fetches the data plane and installer the build will add, returning their digests once they've
+/// been checked against the digests pinned in the config.
+pub async fn resolve_runtime_digests(
+    build_config: &ValidatedEnclaveBuildConfig,
+    data_plane_version: &str,
+    installer_version: &str,
+    pinned: Option<&RuntimeSettings>,
+) -> Result<RuntimeDigests, BuildError> {
+    let client = EnclaveAssetsClient::new();
+    let digests = RuntimeDigests {
+        data_plane_variant: build_config.get_dataplane_feature_label(),
+        data_plane: fetch_asset_digest(
+            &client,
+            &data_plane_asset_path(data_plane_version, build_config),
+        )
+        .await?,
+        installer: fetch_asset_digest(&client, &installer_asset_path(installer_version)).await?,
+    };
+    log::debug!(
+        "Data plane {data_plane_version} digest: {}, installer {installer_version} digest: {}",
+        digests.data_plane,
+        digests.installer
+    );
+    check_pinned_digests(pinned, data_plane_version, installer_version, &digests)?;
+    Ok(digests)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn digests() -> RuntimeDigests {
+        RuntimeDigests {
+            data_plane_variant: "egress-disabled/tls-termination-enabled".into(),
+            data_plane: "sha256:aaaa".into(),
+            installer: "sha256:bbbb".into(),
+        }
+    }
+
+    #[test]
+    fn test_check_pinned_digests() {
+        let pinned = RuntimeSettings {
+            data_plane_version: Some("1.2.0".into()),
+            data_plane_variant: Some("egress-disabled/tls-termination-enabled".into()),
+            data_plane_digest: Some("sha256:aaaa".into()),
+            installer_version: Some("abc123".into()),
+            installer_digest: Some("sha256:bbbb".into()),
+            ..Default::default()
+        };
+        assert!(check_pinned_digests(Some(&pinned), "1.2.0", "abc123", &digests()).is_ok());
+        assert!(check_pinned_digests(None, "1.2.0", "abc123", &digests()).is_ok());
+
+        let republished = RuntimeDigests {
+            data_plane: "sha256:cccc".into(),
+            ..digests()
+        };
+        assert!(matches!(
+            check_pinned_digests(Some(&pinned), "1.2.0", "abc123", &republished),
+            Err(BuildError::RuntimeDigestMismatch { asset, pinned, .. })
+                if asset == "data plane" && pinned == "sha256:aaaa"
+        ));
+
+        // Digests recorded for other releases or data plane variants aren't compared
+        assert!(check_pinned_digests(Some(&pinned), "1.3.0", "abc123", &republished).is_ok());
+        let other_variant = RuntimeDigests {
+            data_plane_variant: "egress-enabled/tls-termination-enabled".into(),
+            ..republished
+        };
+        assert!(check_pinned_digests(Some(&pinned), "1.2.0", "abc123", &other_variant).is_ok());
+    }
+}
@@ -0,0 +1,304 @@
+use super::error::BuildError;
+use crate::config::{AttestationSignature, SigningInfoError};
+use crate::enclave::{EIFMeasurements, EnclaveSigningInfo};
+use common::enclave::types::PCRs;
+use common::CliError;
+use elliptic_curve::pkcs8::DecodePrivateKey;
+use pcr_sign::{PCRProvider, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use thiserror::Error;
+use x509_parser::prelude::parse_x509_pem;
+
+/// Signed PCRs for a built Enclave, in a form which can be hosted for clients to fetch and verify
+/// attestations against
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PcrSignatureBundle {
+    pub pcrs: PCRs,
+    /// Hex encoded signature over the PCRs, prefixed with the pcr-sign signature version
+    pub signature: String,
+    /// SHA-256 of the DER encoded signing cert
+    pub signing_cert_fingerprint: String,
+    pub timestamp: String,
+}
+
+// PCR8 is only present for signed EIFs, which every build produces
+struct SignablePcrs<'a> {
+    pcrs: &'a PCRs,
+    pcr8: &'a str,
+}
+
+impl<'a> PCRProvider for SignablePcrs<'a> {
+    fn pcr0(&self) -> &str {
+        &self.pcrs.pcr0
+    }
+
+    fn pcr1(&self) -> &str {
+        &self.pcrs.pcr1
+    }
+
+    fn pcr2(&self) -> &str {
+        &self.pcrs.pcr2
+    }
+
+    fn pcr8(&self) -> &str {
+        self.pcr8
+    }
+}
+
+pub(crate) fn read_signing_key(key_path: &Path) -> Result<SigningKey, SigningInfoError> {
+    let private_key = std::fs::read_to_string(key_path)?;
+    SigningKey::from_pkcs8_pem(&private_key).map_err(|e| SigningInfoError::InvalidKey {
+        curve: "p384r1".into(),
+        inner: e,
+    })
+}
+
+pub(crate) fn signing_cert_fingerprint(cert_path: &Path) -> Result<String, SigningInfoError> {
+    let cert_contents = std::fs::read(cert_path)?;
+    let (_, pem) =
+        parse_x509_pem(&cert_contents).map_err(|_| SigningInfoError::InvalidSigningCert)?;
+    Ok(hex::encode(Sha256::digest(&pem.contents)))
+}
+
+pub fn create_pcr_signature_bundle(
+    measurements: &EIFMeasurements,
+    signing_info: &EnclaveSigningInfo,
+) -> Result<PcrSignatureBundle, BuildError> {
+    let pcrs = measurements.pcrs();
+    let signing_key = read_signing_key(signing_info.key())?;
+    let signature = pcr_sign::Signature::new(
+        pcr_sign::SignatureVersion::default(),
+        &signable_pcrs(pcrs)?,
+        signing_key,
+    )
+    .sign();
+
+    Ok(PcrSignatureBundle {
+        pcrs: pcrs.clone(),
+        signature,
+        signing_cert_fingerprint: signing_cert_fingerprint(signing_info.cert())?,
+        timestamp: chrono::Utc::now().to_rfc3339(),
+    })
+}
+
+/// Signs the PCRs of a built Enclave and writes them to `output_path` as JSON
+pub fn write_pcr_signature_bundle(
+    measurements: &EIFMeasurements,
+    signing_info: &EnclaveSigningInfo,
+    output_path: &Path,
+) -> Result<PcrSignatureBundle, BuildError> {
+    let bundle = create_pcr_signature_bundle(measurements, signing_info)?;
+    let contents =
+        serde_json::to_string_pretty(&bundle).expect("Failed to serialize PCR signature bundle");
+    std::fs::write(output_path, contents).map_err(BuildError::FailedToWritePcrBundle)?;
+    Ok(bundle)
+}
+
+#[derive(Debug, Error)]
+pub enum AttestationSignatureError {
+    #[error(
+        "The Enclave config has no attestation block to verify. Run ev enclave build to add one."
+    )]
+    MissingAttestation,
+    #[error("The attestation block in the Enclave config isn't signed. Run ev enclave build with --sign-attestation to sign it.")]
+    Unsigned,
+    #[error("The attestation block was signed by the cert with fingerprint {signed_by}, not the given signing cert ({fingerprint}).")]
+    CertMismatch {
+        signed_by: String,
+        fingerprint: String,
+    },
+    #[error("The signing cert's public key isn't a P-384 key, so the attestation signature can't be checked.")]
+    InvalidPublicKey,
+    #[error("The attestation block doesn't match its signature, so its PCRs have been changed since it was signed — {0}")]
+    InvalidSignature(pcr_sign::SignatureVerificationError),
+    #[error(transparent)]
+    SigningInfo(#[from] SigningInfoError),
+}
+
+impl CliError for AttestationSignatureError {
+    fn exitcode(&self) -> exitcode::ExitCode {
+        match self {
+            Self::MissingAttestation | Self::Unsigned => exitcode::NOINPUT,
+            Self::CertMismatch { .. } | Self::InvalidPublicKey | Self::InvalidSignature(_) => {
+                exitcode::DATAERR
+            }
+            Self::SigningInfo(signing_err) => signing_err.exitcode(),
+        }
+    }
+}
+
+fn signable_pcrs(pcrs: &PCRs) -> Result<SignablePcrs<'_>, BuildError> {
+    let pcr8 = pcrs
+        .pcr8
+        .as_deref()
+        .ok_or(BuildError::MissingSigningCertPcr)?;
+    Ok(SignablePcrs { pcrs, pcr8 })
+}
+
+/// Signs the PCRs of an attestation block with the Enclave's signing key
+pub fn sign_attestation(
+    measurements: &EIFMeasurements,
+    signing_info: &EnclaveSigningInfo,
+) -> Result<AttestationSignature, BuildError> {
+    let bundle = create_pcr_signature_bundle(measurements, signing_info)?;
+    Ok(AttestationSignature {
+        signature: bundle.signature,
+        signing_cert_fingerprint: bundle.signing_cert_fingerprint,
+    })
+}
+
+fn read_verifying_key(cert_path: &Path) -> Result<VerifyingKey, AttestationSignatureError> {
+    let cert_contents = std::fs::read(cert_path).map_err(SigningInfoError::from)?;
+    let (_, pem) =
+        parse_x509_pem(&cert_contents).map_err(|_| SigningInfoError::InvalidSigningCert)?;
+    let cert = pem
+        .parse_x509()
+        .map_err(|_| SigningInfoError::InvalidSigningCert)?;
+    VerifyingKey::from_sec1_bytes(&cert.public_key().subject_public_key.data)
+        .map_err(|_| AttestationSignatureError::InvalidPublicKey)
+}
+
+/// Checks the attestation block of an enclave.toml was signed by the key of the given signing cert
+pub fn verify_attestation_signature(
+    measurements: Option<&EIFMeasurements>,
+    signature: Option<&AttestationSignature>,
+    cert_path: &Path,
+) -> Result<(), AttestationSignatureError> {
+    let measurements = measurements.ok_or(AttestationSignatureError::MissingAttestation)?;
+    let signature = signature.ok_or(AttestationSignatureError::Unsigned)?;
+
+    let fingerprint = signing_cert_fingerprint(cert_path)?;
+    if fingerprint != signature.signing_cert_fingerprint {
+        return Err(AttestationSignatureError::CertMismatch {
+            signed_by: signature.signing_cert_fingerprint.clone(),
+            fingerprint,
+        });
+    }
+
+    let verifying_key = read_verifying_key(cert_path)?;
+    // Unsigned EIFs have no PCR8, so their attestation blocks can't have been signed either
+    let pcrs =
+        signable_pcrs(measurements.pcrs()).map_err(|_| AttestationSignatureError::Unsigned)?;
+    Verifier::new(&signature.signature, &pcrs, verifying_key)
+        .try_verify()
+        .map_err(AttestationSignatureError::InvalidSignature)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    // Writes a self signed P-384 cert and its key, returning their paths and the DER encoded cert
+    fn write_signing_cert(directory: &TempDir, name: &str) -> (PathBuf, PathBuf, Vec<u8>) {
+        let mut params = rcgen::CertificateParams::new(vec![]);
+        params.alg = &rcgen::PKCS_ECDSA_P384_SHA384;
+        let cert = rcgen::Certificate::from_params(params).unwrap();
+        let cert_path = directory.path().join(format!("{name}-cert.pem"));
+        let key_path = directory.path().join(format!("{name}-key.pem"));
+        let cert_der = cert.serialize_der().unwrap();
+        let cert_pem = format!(
+            "-----BEGIN CERTIFICATE-----\n{}\n-----END CERTIFICATE-----\n",
+            base64::encode(&cert_der)
+        );
+        std::fs::write(&cert_path, cert_pem).unwrap();
+        std::fs::write(&key_path, cert.serialize_private_key_pem()).unwrap();
+        (cert_path, key_path, cert_der)
+    }
+
+    fn attestation_measurements(pcr0: &str) -> EIFMeasurements {
+        serde_json::from_value(serde_json::json!({
+            "HashAlgorithm": "Sha384 { ... }",
+            "PCR0": pcr0.repeat(48),
+            "PCR1": "bb".repeat(48),
+            "PCR2": "cc".repeat(48),
+            "PCR8": "dd".repeat(48)
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_write_pcr_signature_bundle() {
+        let directory = TempDir::new().unwrap();
+        let (cert_path, key_path, cert_der) = write_signing_cert(&directory, "signing");
+
+        let mut measurements_json = serde_json::json!({
+            "HashAlgorithm": "Sha384 { ... }",
+            "PCR0": "aa".repeat(48),
+            "PCR1": "bb".repeat(48),
+            "PCR2": "cc".repeat(48),
+            "PCR8": "dd".repeat(48)
+        });
+        let measurements: EIFMeasurements =
+            serde_json::from_value(measurements_json.clone()).unwrap();
+        let signing_info = EnclaveSigningInfo::new(cert_path, key_path.clone());
+        let output_path = directory.path().join("pcrs.json");
+
+        let bundle =
+            write_pcr_signature_bundle(&measurements, &signing_info, &output_path).unwrap();
+        let written: PcrSignatureBundle =
+            serde_json::from_slice(&std::fs::read(&output_path).unwrap()).unwrap();
+        assert_eq!(bundle, written);
+        assert!(written.signature.starts_with("01"));
+        assert_eq!(
+            written.signing_cert_fingerprint,
+            hex::encode(Sha256::digest(&cert_der))
+        );
+
+        let verifying_key = VerifyingKey::from(&read_signing_key(&key_path).unwrap());
+        let signed_pcrs = SignablePcrs {
+            pcrs: &written.pcrs,
+            pcr8: written.pcrs.pcr8.as_deref().unwrap(),
+        };
+        assert!(
+            Verifier::new(&written.signature, &signed_pcrs, verifying_key)
+                .try_verify()
+                .is_ok()
+        );
+
+        measurements_json.as_object_mut().unwrap().remove("PCR8");
+        let unsigned: EIFMeasurements = serde_json::from_value(measurements_json).unwrap();
+        assert!(matches!(
+            create_pcr_signature_bundle(&unsigned, &signing_info),
+            Err(BuildError::MissingSigningCertPcr)
+        ));
+    }
+
+    #[test]
+    fn test_verify_attestation_signature() {
+        let directory = TempDir::new().unwrap();
+        let (cert_path, key_path, _) = write_signing_cert(&directory, "signing");
+        let (other_cert_path, _, _) = write_signing_cert(&directory, "other");
+        let signing_info = EnclaveSigningInfo::new(cert_path.clone(), key_path);
+        let attestation = attestation_measurements("aa");
+        let signature = sign_attestation(&attestation, &signing_info).unwrap();
+
+        assert!(
+            verify_attestation_signature(Some(&attestation), Some(&signature), &cert_path).is_ok()
+        );
+        assert!(matches!(
+            verify_attestation_signature(
+                Some(&attestation_measurements("ee")),
+                Some(&signature),
+                &cert_path
+            ),
+            Err(AttestationSignatureError::InvalidSignature(_))
+        ));
+        assert!(matches!(
+            verify_attestation_signature(Some(&attestation), Some(&signature), &other_cert_path),
+            Err(AttestationSignatureError::CertMismatch { .. })
+        ));
+        assert!(matches!(
+            verify_attestation_signature(Some(&attestation), None, &cert_path),
+            Err(AttestationSignatureError::Unsigned)
+        ));
+        assert!(matches!(
+            verify_attestation_signature(None, Some(&signature), &cert_path),
+            Err(AttestationSignatureError::MissingAttestation)
+        ));
+    }
+}
@@ -0,0 +1,91 @@
+use super::error::BuildError;
+use crate::docker::parse::Directive;
+
+/// The user the final stage of the Dockerfile switches to. The injected services need root at
+/// boot, so the image is built to run as root and the user's service drops to this user instead.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ServiceUser {
+    name: String,
+}
+
+impl ServiceUser {
+    /// Parses the argument of a USER directive. Returns None for root, as there are no
+    /// privileges to drop.
+    pub fn from_directive(user: &str) -> Result<Option<Self>, BuildError> {
+        let user = user.trim();
+        let (name, group) = match user.split_once(':') {
+            Some((name, group)) => (name, Some(group)),
+            None => (user, None),
+        };
+        if name == "root" || name == "0" {
+            return Ok(None);
+        }
+        // su can only switch to a user by name, and build args aren't set when the Enclave boots
+        if name.is_empty()
+            || name.chars().all(|c| c.is_ascii_digit())
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+        {
+            return Err(BuildError::UnsupportedServiceUser(user.to_string()));
+        }
+        if let Some(group) = group {
+            log::warn!("Your Dockerfile sets USER {user}, but the group {group} can't be kept when your service is started as {name}. It will run with {name}'s primary group.");
+        }
+        Ok(Some(Self {
+            name: name.to_string(),
+        }))
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Starts the entrypoint as the user. The shell is set, as users created for services often
+    /// have a nologin shell which su would otherwise run.
+    pub fn switch_command(&self, entrypoint: &str) -> String {
+        format!("su -s /bin/sh {} -c 'exec {entrypoint}'", self.name)
+    }
+
+    /// Fails the Docker build if the user doesn't exist, rather than the service failing at boot
+    pub fn check_directive(&self) -> Directive {
+        let name = &self.name;
+        Directive::new_run(format!(
+            r#"id -u {name} > /dev/null 2>&1 || (echo "The user {name} set by USER in your Dockerfile doesn't exist in the image" && exit 1)"#
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_from_directive() {
+        let user = ServiceUser::from_directive("app").unwrap().unwrap();
+        assert_eq!(user.name(), "app");
+        assert_eq!(
+            user.switch_command("node server.js"),
+            "su -s /bin/sh app -c 'exec node server.js'"
+        );
+        assert_eq!(
+            ServiceUser::from_directive("app:staff")
+                .unwrap()
+                .unwrap()
+                .name(),
+            "app"
+        );
+        for root in ["root", "0", "root:root", "0:0"] {
+            assert!(ServiceUser::from_directive(root).unwrap().is_none());
+        }
+        for unsupported in ["1000", "1000:1000", "$APP_USER", "", "app user"] {
+            assert!(
+                matches!(
+                    ServiceUser::from_directive(unsupported),
+                    Err(BuildError::UnsupportedServiceUser(_))
+                ),
+                "{unsupported}"
+            );
+        }
+    }
+}
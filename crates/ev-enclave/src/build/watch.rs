@@ -0,0 +1,272 @@
+use super::context::DockerIgnore;
+use super::error::BuildError;
+use super::EV_USER_DOCKERFILE_PATH;
+use crate::docker::command::image_id;
+use crate::docker::diff::changed_lines;
+use crate::docker::error::DockerError;
+use crate::enclave::EV_USER_IMAGE_NAME;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+const SHORT_IMAGE_ID_LENGTH: usize = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileState {
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+/// The size and modification time of every file a user image build reads, used to detect changes
+/// between builds without a file system notification backend.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ContextSnapshot {
+    files: BTreeMap<String, FileState>,
+}
+
+impl ContextSnapshot {
+    /// Records the files docker would send as the build context, respecting the context's
+    /// `.dockerignore`, along with files the build reads from outside the context, such as the
+    /// Dockerfile and enclave.toml. Missing files are left out, so deleting one counts as a change.
+    pub fn capture(context_path: &Path, extra_paths: &[&Path]) -> std::io::Result<Self> {
+        let dockerignore = DockerIgnore::from_context(context_path);
+        let mut files = BTreeMap::new();
+        snapshot_dir(context_path, "", &dockerignore, &mut files)?;
+        for path in extra_paths {
+            if let Ok(metadata) = std::fs::metadata(path) {
+                files.insert(path.display().to_string(), FileState::from(&metadata));
+            }
+        }
+        Ok(Self { files })
+    }
+
+    /// Paths which were added, removed or modified in the next snapshot, in sorted order
+    pub fn changed_paths(&self, next: &Self) -> Vec<String> {
+        let mut changed: Vec<String> = next
+            .files
+            .iter()
+            .filter(|(path, state)| self.files.get(*path) != Some(state))
+            .map(|(path, _)| path.clone())
+            .collect();
+        changed.extend(
+            self.files
+                .keys()
+                .filter(|path| !next.files.contains_key(*path))
+                .cloned(),
+        );
+        changed.sort();
+        changed
+    }
+}
+
+impl From<&std::fs::Metadata> for FileState {
+    fn from(metadata: &std::fs::Metadata) -> Self {
+        Self {
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+        }
+    }
+}
+
+fn snapshot_dir(
+    dir: &Path,
+    relative_dir: &str,
+    dockerignore: &DockerIgnore,
+    files: &mut BTreeMap<String, FileState>,
+) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        let relative_path = if relative_dir.is_empty() {
+            name
+        } else {
+            format!("{relative_dir}/{name}")
+        };
+        let metadata = entry.path().symlink_metadata()?;
+        let excluded = dockerignore.is_excluded(&relative_path);
+
+        if metadata.is_dir() {
+            if !excluded || dockerignore.has_exceptions() {
+                snapshot_dir(&entry.path(), &relative_path, dockerignore, files)?;
+            }
+        } else if !excluded {
+            files.insert(relative_path, FileState::from(&metadata));
+        }
+    }
+    Ok(())
+}
+
+/// Polls the build's files until they change, returning the new snapshot and the changed paths.
+/// Editors often write a file in several steps, so the change is only reported once the files have
+/// stopped changing between polls.
+pub async fn wait_for_change(
+    context_path: &Path,
+    extra_paths: &[&Path],
+    previous: &ContextSnapshot,
+) -> Result<(ContextSnapshot, Vec<String>), BuildError> {
+    let capture = || {
+        ContextSnapshot::capture(context_path, extra_paths)
+            .map_err(BuildError::FailedToWatchContext)
+    };
+    let mut snapshot = loop {
+        tokio::time::sleep(POLL_INTERVAL).await;
+        let snapshot = capture()?;
+        if &snapshot != previous {
+            break snapshot;
+        }
+    };
+    loop {
+        tokio::time::sleep(POLL_INTERVAL).await;
+        let settled = capture()?;
+        if settled == snapshot {
+            break;
+        }
+        snapshot = settled;
+    }
+    let changed = previous.changed_paths(&snapshot);
+    Ok((snapshot, changed))
+}
+
+/// The parts of a user image build which determine the Enclave's PCRs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserImageBuild {
+    dockerfile_lines: Vec<String>,
+    image_id: String,
+}
+
+impl UserImageBuild {
+    pub fn new(dockerfile: &str, image_id: String) -> Self {
+        Self {
+            dockerfile_lines: dockerfile.lines().map(String::from).collect(),
+            image_id,
+        }
+    }
+
+    /// Reads the processed Dockerfile written to the output path by `build_from_scratch`, and the
+    /// ID of the user image built from it
+    pub fn read(output_path: &Path) -> Result<Self, BuildError> {
+        let dockerfile = std::fs::read_to_string(output_path.join(EV_USER_DOCKERFILE_PATH))
+            .map_err(BuildError::FailedToReadEnclaveDockerfile)?;
+        let image_id =
+            image_id(&format!("{EV_USER_IMAGE_NAME}:latest")).map_err(DockerError::from)?;
+        Ok(Self::new(&dockerfile, image_id))
+    }
+
+    fn short_image_id(&self) -> &str {
+        let id = self.image_id.trim_start_matches("sha256:");
+        &id[..id.len().min(SHORT_IMAGE_ID_LENGTH)]
+    }
+}
+
+/// How a rebuild of the user image differs from the previous build. The EIF is built from the user
+/// image, so the Enclave's PCRs only change when the image does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildDiff {
+    dockerfile_changes: Vec<String>,
+    previous_image: String,
+    image: String,
+}
+
+impl BuildDiff {
+    pub fn new(previous: &UserImageBuild, next: &UserImageBuild) -> Self {
+        Self {
+            dockerfile_changes: changed_lines(&previous.dockerfile_lines, &next.dockerfile_lines),
+            previous_image: previous.short_image_id().to_string(),
+            image: next.short_image_id().to_string(),
+        }
+    }
+
+    pub fn changes_pcrs(&self) -> bool {
+        self.previous_image != self.image
+    }
+}
+
+impl std::fmt::Display for BuildDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.changes_pcrs() {
+            return write!(
+                f,
+                "The user image is unchanged ({}), so the Enclave's PCRs will not change",
+                self.image
+            );
+        }
+        write!(
+            f,
+            "The user image changed ({} -> {}), so the Enclave's PCRs will change",
+            self.previous_image, self.image
+        )?;
+        if !self.dockerfile_changes.is_empty() {
+            write!(
+                f,
+                "\nProcessed Dockerfile changes:\n{}",
+                self.dockerfile_changes.join("\n")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_changed_paths() {
+        let context = tempfile::TempDir::new().unwrap();
+        std::fs::write(context.path().join(".dockerignore"), "logs\n").unwrap();
+        std::fs::write(context.path().join("main.py"), "print('hello')").unwrap();
+        std::fs::create_dir(context.path().join("logs")).unwrap();
+        let dockerfile = tempfile::NamedTempFile::new().unwrap();
+        let extra_paths = [dockerfile.path()];
+
+        let previous = ContextSnapshot::capture(context.path(), &extra_paths).unwrap();
+        std::fs::write(context.path().join("logs/build.log"), "ignored").unwrap();
+        assert_eq!(
+            previous,
+            ContextSnapshot::capture(context.path(), &extra_paths).unwrap()
+        );
+
+        std::fs::write(context.path().join("main.py"), "print('hello world')").unwrap();
+        std::fs::write(context.path().join("requirements.txt"), "flask").unwrap();
+        std::fs::write(dockerfile.path(), "FROM alpine").unwrap();
+        let next = ContextSnapshot::capture(context.path(), &extra_paths).unwrap();
+        assert_eq!(
+            previous.changed_paths(&next),
+            vec![
+                dockerfile.path().display().to_string(),
+                "main.py".to_string(),
+                "requirements.txt".to_string(),
+            ]
+        );
+
+        std::fs::remove_file(context.path().join("requirements.txt")).unwrap();
+        let removed = ContextSnapshot::capture(context.path(), &extra_paths).unwrap();
+        assert_eq!(next.changed_paths(&removed), vec!["requirements.txt"]);
+    }
+
+    #[test]
+    fn test_build_diff() {
+        let previous = UserImageBuild::new(
+            "FROM alpine\nRUN apk add curl\nENTRYPOINT [\"/start.sh\"]",
+            "sha256:aaaaaaaaaaaaaaaaaaaa".into(),
+        );
+        let next = UserImageBuild::new(
+            "FROM alpine\nRUN apk add curl jq\nENTRYPOINT [\"/start.sh\"]",
+            "sha256:bbbbbbbbbbbbbbbbbbbb".into(),
+        );
+        let diff = BuildDiff::new(&previous, &next);
+        assert!(diff.changes_pcrs());
+        assert_eq!(
+            diff.to_string(),
+            "The user image changed (aaaaaaaaaaaa -> bbbbbbbbbbbb), so the Enclave's PCRs will change\nProcessed Dockerfile changes:\n- RUN apk add curl\n+ RUN apk add curl jq"
+        );
+
+        let unchanged = BuildDiff::new(&previous, &previous);
+        assert!(!unchanged.changes_pcrs());
+        assert_eq!(
+            unchanged.to_string(),
+            "The user image is unchanged (aaaaaaaaaaaa), so the Enclave's PCRs will not change"
+        );
+    }
+}
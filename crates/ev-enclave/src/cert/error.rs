@@ -32,8 +32,12 @@ pub enum CertError {
     TimstampParseError(#[from] chrono::ParseError),
     #[error("No certs found for the current Enclave.")]
     NoCertsFound,
+    #[error("The cert is not valid UTF-8 encoded PEM")]
+    InvalidCertEncoding,
     #[error("Provided cert expiry is in the past: {0}")]
     CertExpiryIsInThePast(chrono::DateTime<Utc>),
+    #[error(transparent)]
+    PromptError(#[from] crate::prompt::PromptError),
 }
 
 impl CliError for CertError {
@@ -49,8 +53,10 @@ impl CliError for CertError {
             | Self::CertNotYetValid
             | Self::InvalidDate
             | Self::CertPathDoesNotExist(_)
+            | Self::InvalidCertEncoding
             | Self::TimstampParseError(_) => exitcode::DATAERR,
             Self::ApiError(inner) => inner.exitcode(),
+            Self::PromptError(inner) => inner.exitcode(),
             Self::NoCertsFound | Self::CertExpiryIsInThePast(_) => exitcode::USAGE,
         }
     }
@@ -1,6 +1,6 @@
+use crate::prompt;
 use aws_nitro_enclaves_image_format::defs::eif_hasher::EifHasher;
 use chrono::{DateTime, Datelike, Local, TimeZone, Utc};
-use dialoguer::{Confirm, MultiSelect};
 use itertools::Itertools;
 use rcgen::CertificateParams;
 use sha2::{Digest, Sha384};
@@ -127,7 +127,7 @@ pub fn get_cert_pcr(cert_path: &Path) -> Result<String, CertError> {
 
 pub async fn upload_new_cert_ref(
     cert_path: &str,
-    api_key: &str,
+    auth: AuthMode,
     name: String,
 ) -> Result<CreateEnclaveSigningCertRefResponse, CertError> {
     let path = std::path::Path::new(cert_path);
@@ -135,14 +135,18 @@ pub async fn upload_new_cert_ref(
     let pcr8 = get_cert_pcr(path)?;
     let validity_period = get_cert_validity_period(path)?;
 
-    let enclave_api = EnclaveClient::new(AuthMode::ApiKey(api_key.to_string()));
+    let enclave_api = EnclaveClient::new(auth);
+
+    let certificate = String::from_utf8(read_cert_bytes_from_fs(path)?)
+        .map_err(|_| CertError::InvalidCertEncoding)?;
 
     let payload = CreateEnclaveSigningCertRefRequest::new(
         pcr8.clone(),
         name,
         validity_period.not_before,
         validity_period.not_after,
-    );
+    )
+    .with_certificate(certificate);
 
     let cert_ref = match enclave_api.create_enclave_signing_cert_ref(payload).await {
         Ok(cert_ref) => cert_ref,
@@ -155,7 +159,45 @@ pub async fn upload_new_cert_ref(
     Ok(cert_ref)
 }
 
-fn format_cert_for_multi_select(cert: &EnclaveSigningCert) -> String {
+pub async fn list_cert_refs(auth: AuthMode) -> Result<Vec<EnclaveSigningCert>, CertError> {
+    let enclave_api = EnclaveClient::new(auth);
+    let response = enclave_api.get_signing_certs().await?;
+    Ok(response.certs)
+}
+
+/// Checks the PCR8 of a deployment against the signing certs registered with Evervault, returning a
+/// warning if the cert is unknown or outside of its validity period.
+pub fn signing_cert_warning(
+    registered_certs: &[EnclaveSigningCert],
+    pcr8: &str,
+    now: DateTime<Utc>,
+) -> Option<String> {
+    let Some(cert) = registered_certs
+        .iter()
+        .find(|cert| cert.cert_hash() == pcr8)
+    else {
+        return Some(format!("The Enclave is signed by a cert which has not been uploaded to Evervault (PCR8: {pcr8}). Run `ev enclave cert upload` to register it."));
+    };
+
+    let parse_time = |time: Option<String>| {
+        time.and_then(|time| DateTime::parse_from_rfc3339(&time).ok())
+            .map(|time| time.with_timezone(&Utc))
+    };
+    let name = cert.name().unwrap_or_else(|| cert.uuid().to_string());
+    if parse_time(cert.not_after()).is_some_and(|not_after| not_after < now) {
+        return Some(format!(
+            "The Enclave is signed by the cert {name}, which has expired."
+        ));
+    }
+    if parse_time(cert.not_before()).is_some_and(|not_before| not_before > now) {
+        return Some(format!(
+            "The Enclave is signed by the cert {name}, which is not yet valid."
+        ));
+    }
+    None
+}
+
+pub fn format_cert_summary(cert: &EnclaveSigningCert) -> String {
     let name = cert.name().unwrap_or_default();
     let cert_hash = cert.cert_hash();
     let not_after = cert
@@ -193,7 +235,7 @@ struct CertWithFormattedString {
 impl CertWithFormattedString {
     fn new(cert: &EnclaveSigningCert, locked: bool) -> Self {
         Self {
-            formatted: format_cert_for_multi_select(cert),
+            formatted: format_cert_summary(cert),
             cert: cert.clone(),
             locked,
         }
@@ -255,12 +297,14 @@ fn sort_certs_by_expiry(
     Ok(certs)
 }
 
+/// Asks which certs to lock the Enclave to, returning how many it was locked to, or None when the
+/// update was cancelled
 pub async fn lock_enclave_to_certs(
-    api_key: &str,
+    auth: AuthMode,
     enclave_uuid: &str,
     enclave_name: &str,
-) -> Result<(), CertError> {
-    let enclave_api = EnclaveClient::new(AuthMode::ApiKey(api_key.to_string()));
+) -> Result<Option<usize>, CertError> {
+    let enclave_api = EnclaveClient::new(auth);
 
     let certs_for_select = get_certs_for_selection(enclave_api.clone(), enclave_uuid).await?;
 
@@ -271,18 +315,14 @@ pub async fn lock_enclave_to_certs(
 
     let sorted_certs_for_select = sort_certs_by_expiry(certs_for_select)?;
 
-    let chosen: Vec<usize> = MultiSelect::new()
-        .with_prompt("Select Certs To Lock Enclave To. Press Space To Select, Enter To Confirm.\n Cert Name | PCR8 (Hash of cert) | Cert Expiry ")
-        .report(false)
-        .max_length(6)
-        .items_checked(
-            sorted_certs_for_select
-                .iter()
-                .map(|cert| (cert.formatted.as_str(), cert.locked))
-                .collect::<Vec<(&str, bool)>>()
-                .as_slice(),
-        )
-        .interact()?;
+    let select_prompt = "Select Certs To Lock Enclave To. Press Space To Select, Enter To Confirm.\n Cert Name | PCR8 (Hash of cert) | Cert Expiry ";
+    let chosen = prompt::multi_select(
+        select_prompt,
+        &sorted_certs_for_select
+            .iter()
+            .map(|cert| (cert.formatted.as_str(), cert.locked))
+            .collect::<Vec<(&str, bool)>>(),
+    )?;
 
     let chosen_cert_uuids = chosen
         .iter()
@@ -313,38 +353,18 @@ pub async fn lock_enclave_to_certs(
 
     log::info!("{}", msg);
     //Need to ask the user to confirm they want to continue
-    let confirmed = Confirm::new()
-        .with_prompt("Do you want to continue?")
-        .interact()?;
+    let confirmed = prompt::confirm("Do you want to continue?", false)?;
 
     if !confirmed {
         log::info!("Close one! Update Cancelled.");
-        return Ok(());
+        return Ok(None);
     }
 
-    if let Err(e) = enclave_api
+    enclave_api
         .update_enclave_locked_signing_certs(enclave_uuid, payload)
-        .await
-    {
-        log::error!("Error locking Enclave to certs - {e}");
-        return Err(CertError::ApiError(e));
-    };
+        .await?;
 
-    let final_msg = match amount_chosen {
-        0 => format!(
-            "Enclave {} successfully unlocked from all certs!",
-            enclave_name
-        ),
-        1 => format!("Enclave {} successfully locked to 1 cert!", enclave_name),
-        _ => format!(
-            "Enclave {} successfully locked to {} certs!",
-            enclave_name, amount_chosen
-        ),
-    };
-
-    log::info!("{}", final_msg);
-
-    Ok(())
+    Ok(Some(amount_chosen))
 }
 
 fn add_distinguished_name_to_cert_params(
@@ -428,6 +448,17 @@ pub fn get_cert_validity_period(path: &Path) -> Result<CertValidityPeriod, CertE
     extract_cert_validity_period_from_x509(&x509)
 }
 
+/// Whether the private key at `key_path` is the key the cert at `cert_path` was issued for
+pub fn key_matches_cert(cert_path: &Path, key_path: &Path) -> Result<bool, CertError> {
+    let cert_contents = read_cert_bytes_from_fs(cert_path)?;
+    let (_, pem) = parse_x509_pem(&cert_contents).map_err(CertError::PEMError)?;
+    let (_, x509) = parse_x509_certificate(&pem.contents).map_err(CertError::X509Error)?;
+
+    let key_pem = std::fs::read_to_string(key_path)?;
+    let key_pair = rcgen::KeyPair::from_pem(&key_pem)?;
+    Ok(key_pair.public_key_raw() == x509.public_key().subject_public_key.data.as_ref())
+}
+
 fn read_cert_bytes_from_fs(path: &Path) -> Result<Vec<u8>, CertError> {
     let cert_file = std::fs::File::open(path)?;
     let mut cert_reader = std::io::BufReader::new(cert_file);
@@ -515,6 +546,50 @@ impl<'a> std::convert::TryInto<DistinguishedName<'a>> for DnBuilder<'a> {
 mod test {
     use super::*;
 
+    #[test]
+    fn test_signing_cert_warning() {
+        let cert = EnclaveSigningCert::new(
+            Some("prod".into()),
+            "cert_123".into(),
+            "app_123".into(),
+            "abc123".into(),
+            Some("2024-01-01T00:00:00Z".into()),
+            Some("2025-01-01T00:00:00Z".into()),
+        );
+        let certs = vec![cert];
+        let during = Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap();
+        let after = Utc.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap();
+
+        assert_eq!(signing_cert_warning(&certs, "abc123", during), None);
+        assert!(signing_cert_warning(&certs, "abc123", after)
+            .unwrap()
+            .contains("expired"));
+        assert!(signing_cert_warning(&certs, "def456", during)
+            .unwrap()
+            .contains("not been uploaded"));
+    }
+
+    #[test]
+    fn test_key_matches_cert() {
+        let first = tempfile::TempDir::new().unwrap();
+        let second = tempfile::TempDir::new().unwrap();
+        let (cert_path, key_path) = create_new_cert(
+            first.path(),
+            DistinguishedName::default(),
+            DesiredLifetime::default(),
+        )
+        .unwrap();
+        let (_, other_key_path) = create_new_cert(
+            second.path(),
+            DistinguishedName::default(),
+            DesiredLifetime::default(),
+        )
+        .unwrap();
+
+        assert!(key_matches_cert(&cert_path, &key_path).unwrap());
+        assert!(!key_matches_cert(&cert_path, &other_key_path).unwrap());
+    }
+
     #[test]
     fn test_epoch_to_date() {
         let epoch: i64 = 1619196863;
@@ -72,6 +72,12 @@ pub fn resolve_output_path(
 }
 
 pub fn save_enclave_config(enclave_config: &EnclaveConfig, config_path: &str) {
+    if config_path == crate::config::STDIN_CONFIG_PATH {
+        log::info!(
+            "The Enclave config was read from stdin, so the updated config has not been saved"
+        );
+        return;
+    }
     if let Ok(serialized_config) = toml::ser::to_vec(&enclave_config) {
         match std::fs::write(config_path, serialized_config) {
             Ok(_) => log::debug!("Enclave config updated"),
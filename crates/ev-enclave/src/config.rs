@@ -1,16 +1,57 @@
+use std::io::Read;
 use std::path::Path;
-
-use crate::cert::{get_cert_validity_period, CertValidityPeriod};
-
-use super::enclave::{EIFMeasurements, EnclaveSigningInfo};
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use crate::build::ca_certs::{CaCertError, CaCertificate};
+use crate::build::runtime::RuntimeDigests;
+use crate::cert::{get_cert_pcr, get_cert_validity_period, CertValidityPeriod};
+
+use super::docker::error::PlatformError;
+use super::docker::platform::Platform;
+use super::egress::{expand_presets, EgressPresetError};
+use super::enclave::{EIFMeasurements, EnclaveSigningInfo, NitroCliImage, NitroCliImageError};
+use common::api::enclave_assets::ReleaseChannel;
 use common::CliError;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+// Settings which are sent to the API are defined by its client
+pub use crate::api::enclave::{EnclaveSize, EnclaveType, NetworkProtocol, RestartPolicy};
+
+/// Config path which reads the Enclave config from stdin, for pipelines which template configs
+pub const STDIN_CONFIG_PATH: &str = "-";
+
+// Stdin can only be read once, so the config is kept for commands which read it again
+static STDIN_CONFIG: OnceLock<Vec<u8>> = OnceLock::new();
+
+fn read_stdin_config() -> Result<&'static [u8], std::io::Error> {
+    if let Some(content) = STDIN_CONFIG.get() {
+        return Ok(content);
+    }
+    let mut content = Vec::new();
+    std::io::stdin().read_to_end(&mut content)?;
+    Ok(STDIN_CONFIG.get_or_init(|| content))
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct EgressSettings {
     pub enabled: bool,
     pub destinations: Option<Vec<String>>,
+    /// Named destination lists, such as "stripe", which are expanded into destinations at build time
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub presets: Vec<String>,
+    /// Client certificate the data plane presents to destinations which require mTLS
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mtls: Option<EgressMtlsSettings>,
+}
+
+/// Local paths of the egress client certificate and key. They're uploaded as secrets by
+/// `ev enclave env mtls` rather than built into the image.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct EgressMtlsSettings {
+    pub cert_path: String,
+    pub key_path: String,
 }
 
 impl EgressSettings {
@@ -24,12 +65,53 @@ impl EgressSettings {
         EgressSettings {
             enabled,
             destinations,
+            presets: vec![],
+            mtls: None,
         }
     }
 
     pub fn is_enabled(&self) -> bool {
         self.enabled
     }
+
+    /// Replaces the presets with the destinations they allow, so the data plane and the API only
+    /// ever see literal destinations.
+    pub fn expand_presets(&self) -> Result<EgressSettings, EgressPresetError> {
+        if self.presets.is_empty() {
+            return Ok(self.clone());
+        }
+        if !self.enabled {
+            return Err(EgressPresetError::EgressDisabled);
+        }
+        let destinations = self.destinations.as_deref().unwrap_or_default();
+        Ok(EgressSettings {
+            enabled: true,
+            destinations: Some(expand_presets(destinations, &self.presets)?),
+            presets: vec![],
+            mtls: self.mtls.clone(),
+        })
+    }
+
+    /// Whether egress is open to every destination, which is the case when no destinations or
+    /// presets are given.
+    pub fn allows_any_destination(&self) -> bool {
+        self.enabled
+            && match self.destinations.as_ref() {
+                Some(destinations) => destinations.iter().any(|destination| destination == "*"),
+                None => self.presets.is_empty(),
+            }
+    }
+
+    /// The destinations the Enclave can reach, which is empty when egress is disabled
+    pub fn allowed_destinations(&self) -> Vec<String> {
+        if !self.enabled {
+            return vec![];
+        }
+        self.destinations
+            .clone()
+            .unwrap_or_else(|| vec!["*".to_string()])
+    }
+
     pub fn get_destinations(self) -> String {
         self.destinations
             .map(|destination| destination.join(","))
@@ -42,6 +124,112 @@ pub struct ScalingSettings {
     pub desired_replicas: u32,
 }
 
+/// The process which runs as PID 1 in the Enclave, starting the data plane and the user's service
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Supervisor {
+    /// runsvdir supervises both processes, restarting them if they exit
+    #[default]
+    Runit,
+    /// tini forwards signals to both processes and reaps zombies, but doesn't restart them
+    Tini,
+    /// The user's service runs as PID 1, and must forward signals and reap zombies itself
+    None,
+}
+
+impl Supervisor {
+    pub fn is_runit(&self) -> bool {
+        matches!(self, Self::Runit)
+    }
+
+    /// Commands the supervisor needs on the PATH of the user's image
+    pub fn required_commands(&self) -> &'static [&'static str] {
+        match self {
+            Self::Runit => &["runsvdir", "sv"],
+            Self::Tini => &["tini"],
+            Self::None => &[],
+        }
+    }
+}
+
+impl std::fmt::Display for Supervisor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Runit => write!(f, "runit"),
+            Self::Tini => write!(f, "tini"),
+            Self::None => write!(f, "none"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct NetworkSettings {
+    #[serde(default)]
+    pub protocol: NetworkProtocol,
+    /// Port the service listens on, used when the Dockerfile doesn't EXPOSE one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+}
+
+/// Toggles for the data plane which runs alongside the user's service. These take precedence over
+/// the equivalent top-level settings, which are kept for existing configs.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct DataPlaneSettings {
+    /// Path the data plane polls to check the user's service is healthy
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub healthcheck: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trx_logging: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key_auth: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub forward_proxy_protocol: Option<bool>,
+}
+
+/// Controls how the Enclave image is built. By default, the latest Nitro CLI release is installed
+/// on amazonlinux:2 to convert the image to an EIF, and runit supervises the Enclave's processes.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct BuildSettings {
+    /// A prebuilt image with nitro-cli on its path, optionally pinned with @sha256:<digest>
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nitro_cli_image: Option<String>,
+    /// The version of aws-nitro-enclaves-cli to install
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nitro_cli_version: Option<String>,
+    /// The process which starts and supervises the data plane and the user's service
+    #[serde(default, skip_serializing_if = "Supervisor::is_runit")]
+    pub supervisor: Supervisor,
+    /// The platform the user's image is built for. Enclaves run linux/amd64 images, optionally
+    /// with a variant such as linux/amd64/v3.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub platform: Option<String>,
+    /// PEM encoded CA certificates to install into the image's system trust store, for calling
+    /// services which use a private CA
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra_ca_certs: Vec<String>,
+}
+
+/// Which data plane and installer releases the Enclave is built with. Builds record the versions
+/// they resolved here, so the runtime an Enclave was built with can be audited.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct RuntimeSettings {
+    #[serde(default, skip_serializing_if = "ReleaseChannel::is_stable")]
+    pub channel: ReleaseChannel,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data_plane_version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data_plane_variant: Option<String>,
+    /// Builds fail if the recorded release is republished with a different digest
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data_plane_digest: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub installer_version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub installer_digest: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub restart_policy: Option<RestartPolicy>,
+}
+
 impl Default for ScalingSettings {
     fn default() -> Self {
         ScalingSettings {
@@ -66,6 +254,17 @@ pub struct SigningInfo {
     pub cert: Option<String>,
     #[serde(rename = "keyPath")]
     pub key: Option<String>,
+    /// Cert and key pair which will replace the current pair at the next key rotation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next: Option<NextSigningInfo>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct NextSigningInfo {
+    #[serde(rename = "certPath")]
+    pub cert: String,
+    #[serde(rename = "keyPath")]
+    pub key: String,
 }
 
 impl SigningInfo {
@@ -88,6 +287,8 @@ pub enum SigningInfoError {
     SigningCertNotFound(String),
     #[error("Could not find signing key file at {0}")]
     SigningKeyNotFound(String),
+    #[error("Invalid next signing cert given at {0}.")]
+    InvalidNextSigningCert(String),
     #[error("Failed to access signing info: {0}")]
     FileSystemIOError(#[from] std::io::Error),
     #[error("Failed to interpret key for curve({curve}): {inner}")]
@@ -104,6 +305,7 @@ impl CliError for SigningInfoError {
             | Self::EmptySigningCert
             | Self::EmptySigningKey
             | Self::InvalidSigningCert
+            | Self::InvalidNextSigningCert(_)
             | Self::InvalidKey { .. } => exitcode::DATAERR,
             Self::FileSystemIOError(_) => exitcode::IOERR,
             Self::SigningCertNotFound(_) | Self::SigningKeyNotFound(_) => exitcode::NOINPUT,
@@ -116,6 +318,42 @@ pub struct ValidatedSigningInfo {
     pub cert: String,
     pub key: String,
     pub cert_validity_period: CertValidityPeriod,
+    pub next: Option<ValidatedNextSigningInfo>,
+}
+
+/// The upcoming signing cert, used to let clients pre-trust the PCR8 it will produce
+#[derive(Clone, Debug)]
+pub struct ValidatedNextSigningInfo {
+    pub cert: String,
+    pub key: String,
+    pub pcr8: String,
+    pub cert_validity_period: CertValidityPeriod,
+}
+
+impl std::convert::TryFrom<&NextSigningInfo> for ValidatedNextSigningInfo {
+    type Error = SigningInfoError;
+
+    fn try_from(next: &NextSigningInfo) -> Result<Self, Self::Error> {
+        let cert_path = Path::new(&next.cert);
+        if !cert_path.exists() {
+            return Err(SigningInfoError::SigningCertNotFound(next.cert.clone()));
+        }
+        if !Path::new(&next.key).exists() {
+            return Err(SigningInfoError::SigningKeyNotFound(next.key.clone()));
+        }
+
+        let cert_validity_period = get_cert_validity_period(cert_path)
+            .map_err(|_| SigningInfoError::InvalidNextSigningCert(next.cert.clone()))?;
+        let pcr8 = get_cert_pcr(cert_path)
+            .map_err(|_| SigningInfoError::InvalidNextSigningCert(next.cert.clone()))?;
+
+        Ok(Self {
+            cert: next.cert.clone(),
+            key: next.key.clone(),
+            pcr8,
+            cert_validity_period,
+        })
+    }
 }
 
 impl ValidatedSigningInfo {
@@ -155,10 +393,17 @@ impl std::convert::TryFrom<&SigningInfo> for ValidatedSigningInfo {
         let cert_validity_period = get_cert_validity_period(Path::new(&cert_path))
             .map_err(|_| Self::Error::EmptySigningCert)?;
 
+        let next = signing_info
+            .next
+            .as_ref()
+            .map(ValidatedNextSigningInfo::try_from)
+            .transpose()?;
+
         Ok(ValidatedSigningInfo {
             cert: cert_path,
             key: key_path,
             cert_validity_period,
+            next,
         })
     }
 }
@@ -193,10 +438,30 @@ pub enum EnclaveConfigError {
     MissingSigningInfo(#[from] SigningInfoError),
     #[error("Dockerfile is required and was not given.")]
     MissingDockerfile,
+    #[error("The Enclave config and Dockerfile can't both be read from stdin. Pass a path for one of them instead of -.")]
+    ConfigAndDockerfileFromStdin,
     #[error("{0} was not set in the toml.")]
     MissingField(String),
     #[error("TLS Termination must be enabled to enable Enclave logging.")]
     LoggingEnabledWithoutTLSTermination(),
+    #[error("TLS Termination must be disabled for Enclaves using the tcp protocol. Set tls_termination = false in your enclave.toml.")]
+    TlsTerminationWithTcpProtocol,
+    #[error("The {0} setting is only supported for Enclaves using the http protocol.")]
+    HttpSettingWithTcpProtocol(String),
+    #[error("restart_policy = \"{0}\" requires the runit supervisor, as {1} doesn't restart your service. Remove the restart_policy or set it to \"never\".")]
+    RestartPolicyWithoutRunit(RestartPolicy, Supervisor),
+    #[error("Jobs stop the Enclave when their process exits, so restart_policy = \"{0}\" isn't supported. Remove the restart_policy or set it to \"never\".")]
+    RestartPolicyForJob(RestartPolicy),
+    #[error(transparent)]
+    InvalidNitroCliImage(#[from] NitroCliImageError),
+    #[error(transparent)]
+    InvalidPlatform(#[from] PlatformError),
+    #[error(transparent)]
+    InvalidEgressPreset(#[from] EgressPresetError),
+    #[error(transparent)]
+    InvalidCaCert(#[from] CaCertError),
+    #[error("Egress must be enabled to use an mTLS client certificate. Set enabled = true in the [egress] section of your enclave.toml.")]
+    EgressMtlsWithoutEgress,
 }
 
 impl CliError for EnclaveConfigError {
@@ -205,9 +470,19 @@ impl CliError for EnclaveConfigError {
             Self::MissingConfigFile(_) | Self::FailedToAccessConfig(_) => exitcode::NOINPUT,
             Self::FailedToParseEnclaveConfig(_)
             | Self::MissingDockerfile
+            | Self::ConfigAndDockerfileFromStdin
             | Self::MissingField(_)
-            | Self::LoggingEnabledWithoutTLSTermination() => exitcode::DATAERR,
+            | Self::LoggingEnabledWithoutTLSTermination()
+            | Self::TlsTerminationWithTcpProtocol
+            | Self::HttpSettingWithTcpProtocol(_)
+            | Self::RestartPolicyWithoutRunit(..)
+            | Self::RestartPolicyForJob(_)
+            | Self::EgressMtlsWithoutEgress => exitcode::DATAERR,
             Self::MissingSigningInfo(signing_err) => signing_err.exitcode(),
+            Self::InvalidNitroCliImage(image_err) => image_err.exitcode(),
+            Self::InvalidPlatform(platform_err) => platform_err.exitcode(),
+            Self::InvalidEgressPreset(preset_err) => preset_err.exitcode(),
+            Self::InvalidCaCert(ca_cert_err) => ca_cert_err.exitcode(),
         }
     }
 }
@@ -220,10 +495,26 @@ pub fn default_true() -> bool {
     true
 }
 
+/// Signature over the PCRs of the attestation block in an enclave.toml, so changes to pinned PCRs
+/// which weren't made by a build with the signing key can be detected
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct AttestationSignature {
+    /// Hex encoded signature over the PCRs, prefixed with the pcr-sign signature version
+    pub signature: String,
+    /// SHA-256 of the DER encoded signing cert
+    pub signing_cert_fingerprint: String,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct EnclaveConfig {
     pub version: u8,
     pub name: String,
+    #[serde(
+        rename = "type",
+        default,
+        skip_serializing_if = "EnclaveType::is_service"
+    )]
+    pub enclave_type: EnclaveType,
     pub uuid: Option<String>,
     pub app_uuid: Option<String>,
     pub team_uuid: Option<String>,
@@ -242,11 +533,29 @@ pub struct EnclaveConfig {
     pub trusted_headers: Vec<String>,
     #[serde(default)]
     pub healthcheck: Option<String>,
+    /// Resources reserved for each instance. The API's default is used when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<EnclaveSize>,
     // Table configs
     pub egress: EgressSettings,
     pub scaling: Option<ScalingSettings>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network: Option<NetworkSettings>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build: Option<BuildSettings>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dataplane: Option<DataPlaneSettings>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub runtime: Option<RuntimeSettings>,
     pub signing: Option<SigningInfo>,
     pub attestation: Option<EIFMeasurements>,
+    /// Signature over the attestation PCRs by the signing key, checked with `ev enclave config verify`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attestation_signature: Option<AttestationSignature>,
+    /// Declarative environment, applied with `ev enclave env sync`. Values may reference other
+    /// variables (`{{ NAME }}`) or secrets from the local environment (`{{ secret:NAME }}`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env: Option<std::collections::BTreeMap<String, String>>,
 }
 
 // This type exists only to read V0 tomls and migrate to V1
@@ -278,6 +587,7 @@ impl std::convert::From<EnclaveConfigV0> for EnclaveConfig {
     fn from(value: EnclaveConfigV0) -> Self {
         EnclaveConfig {
             name: value.name,
+            enclave_type: EnclaveType::Service,
             uuid: value.uuid,
             app_uuid: value.app_uuid,
             version: 1,
@@ -290,10 +600,17 @@ impl std::convert::From<EnclaveConfigV0> for EnclaveConfig {
             forward_proxy_protocol: value.forward_proxy_protocol,
             trusted_headers: value.trusted_headers,
             healthcheck: value.healthcheck,
+            size: None,
             egress: value.egress,
             scaling: value.scaling,
+            network: None,
+            build: None,
+            dataplane: None,
+            runtime: None,
             signing: value.signing,
             attestation: value.attestation,
+            attestation_signature: None,
+            env: None,
         }
     }
 }
@@ -317,6 +634,7 @@ impl std::convert::AsRef<EnclaveConfig> for EnclaveConfig {
 pub struct ValidatedEnclaveBuildConfig {
     pub version: u8,
     pub enclave_name: String,
+    pub enclave_type: EnclaveType,
     pub enclave_uuid: String,
     pub app_uuid: String,
     pub team_uuid: String,
@@ -332,6 +650,14 @@ pub struct ValidatedEnclaveBuildConfig {
     pub forward_proxy_protocol: bool,
     pub trusted_headers: Vec<String>,
     pub healthcheck: Option<String>,
+    pub protocol: NetworkProtocol,
+    pub port: Option<u16>,
+    pub nitro_cli_image: NitroCliImage,
+    pub supervisor: Supervisor,
+    pub restart_policy: RestartPolicy,
+    pub size: Option<EnclaveSize>,
+    pub platform: Platform,
+    pub extra_ca_certs: Vec<CaCertificate>,
 }
 
 impl ValidatedEnclaveBuildConfig {
@@ -351,6 +677,10 @@ impl ValidatedEnclaveBuildConfig {
         &self.enclave_name
     }
 
+    pub fn enclave_type(&self) -> EnclaveType {
+        self.enclave_type
+    }
+
     pub fn enclave_uuid(&self) -> &str {
         &self.enclave_uuid
     }
@@ -400,6 +730,38 @@ impl ValidatedEnclaveBuildConfig {
     pub fn healthcheck(&self) -> Option<&str> {
         self.healthcheck.as_deref()
     }
+
+    pub fn protocol(&self) -> NetworkProtocol {
+        self.protocol
+    }
+
+    pub fn port(&self) -> Option<u16> {
+        self.port
+    }
+
+    pub fn nitro_cli_image(&self) -> &NitroCliImage {
+        &self.nitro_cli_image
+    }
+
+    pub fn supervisor(&self) -> Supervisor {
+        self.supervisor
+    }
+
+    pub fn restart_policy(&self) -> RestartPolicy {
+        self.restart_policy
+    }
+
+    pub fn size(&self) -> Option<EnclaveSize> {
+        self.size
+    }
+
+    pub fn platform(&self) -> &Platform {
+        &self.platform
+    }
+
+    pub fn extra_ca_certs(&self) -> &[CaCertificate] {
+        &self.extra_ca_certs
+    }
 }
 
 impl EnclaveConfig {
@@ -439,15 +801,121 @@ impl EnclaveConfig {
         self.signing = Some(info);
     }
 
+    // A signature over different PCRs no longer verifies, so it's dropped with them
     pub fn set_attestation(&mut self, measurements: &EIFMeasurements) {
+        if self
+            .attestation
+            .as_ref()
+            .is_some_and(|existing| existing.pcrs() != measurements.pcrs())
+        {
+            self.attestation_signature = None;
+        }
         self.attestation = Some(measurements.clone());
     }
 
+    pub fn set_attestation_signature(&mut self, signature: AttestationSignature) {
+        self.attestation_signature = Some(signature);
+    }
+
+    // A version given on the command line replaces any Nitro CLI source in the toml
+    pub fn set_nitro_cli_version(&mut self, version: String) {
+        let build_settings = self.build.get_or_insert_with(BuildSettings::default);
+        build_settings.nitro_cli_image = None;
+        build_settings.nitro_cli_version = Some(version);
+    }
+
+    pub fn nitro_cli_image(&self) -> Result<NitroCliImage, NitroCliImageError> {
+        let build_settings = self.build.clone().unwrap_or_default();
+        NitroCliImage::new(
+            build_settings.nitro_cli_image.as_deref(),
+            build_settings.nitro_cli_version.as_deref(),
+        )
+    }
+
+    pub fn set_platform(&mut self, platform: String) {
+        self.build
+            .get_or_insert_with(BuildSettings::default)
+            .platform = Some(platform);
+    }
+
+    pub fn platform(&self) -> Result<Platform, PlatformError> {
+        match self
+            .build
+            .as_ref()
+            .and_then(|build_settings| build_settings.platform.as_deref())
+        {
+            Some(platform) => Platform::from_str(platform)?.validate_target(),
+            None => Ok(Platform::default()),
+        }
+    }
+
+    pub fn runtime_channel(&self) -> ReleaseChannel {
+        self.runtime
+            .as_ref()
+            .map(|runtime| runtime.channel)
+            .unwrap_or_default()
+    }
+
+    /// The restart policy in the [runtime] section, or the supervisor's behaviour when it's unset.
+    /// Only runit restarts services, and jobs always stop the Enclave when their process exits.
+    pub fn restart_policy(
+        &self,
+        supervisor: Supervisor,
+    ) -> Result<RestartPolicy, EnclaveConfigError> {
+        let configured = self
+            .runtime
+            .as_ref()
+            .and_then(|runtime| runtime.restart_policy);
+        let restarts = supervisor.is_runit() && self.enclave_type.is_service();
+        match configured {
+            None if restarts => Ok(RestartPolicy::Always),
+            None | Some(RestartPolicy::Never) => Ok(RestartPolicy::Never),
+            Some(policy) if self.enclave_type.is_job() => {
+                Err(EnclaveConfigError::RestartPolicyForJob(policy))
+            }
+            Some(policy) if !supervisor.is_runit() => Err(
+                EnclaveConfigError::RestartPolicyWithoutRunit(policy, supervisor),
+            ),
+            Some(policy) => Ok(policy),
+        }
+    }
+
+    pub fn set_port(&mut self, port: u16) {
+        self.network
+            .get_or_insert_with(NetworkSettings::default)
+            .port = Some(port);
+    }
+
+    pub fn set_runtime_versions(&mut self, data_plane_version: &str, installer_version: &str) {
+        let runtime = self.runtime.get_or_insert_with(RuntimeSettings::default);
+        // Digests recorded for other releases would otherwise be checked against these ones
+        if runtime.data_plane_version.as_deref() != Some(data_plane_version) {
+            runtime.data_plane_variant = None;
+            runtime.data_plane_digest = None;
+        }
+        if runtime.installer_version.as_deref() != Some(installer_version) {
+            runtime.installer_digest = None;
+        }
+        runtime.data_plane_version = Some(data_plane_version.to_string());
+        runtime.installer_version = Some(installer_version.to_string());
+    }
+
+    pub fn set_runtime_digests(&mut self, digests: &RuntimeDigests) {
+        let runtime = self.runtime.get_or_insert_with(RuntimeSettings::default);
+        runtime.data_plane_variant = Some(digests.data_plane_variant.clone());
+        runtime.data_plane_digest = Some(digests.data_plane.clone());
+        runtime.installer_digest = Some(digests.installer.clone());
+    }
+
     pub fn set_scaling_config(&mut self, scaling_info: ScalingSettings) {
         self.scaling = Some(scaling_info);
     }
 
     pub fn try_from_filepath(path: &str) -> Result<Self, EnclaveConfigError> {
+        if path == STDIN_CONFIG_PATH {
+            return Ok(toml::de::from_slice(read_stdin_config()?)?);
+        }
+
         let config_path = std::path::Path::new(path);
         if !config_path.exists() {
             return Err(EnclaveConfigError::MissingConfigFile(path.to_string()));
@@ -461,14 +929,7 @@ impl EnclaveConfig {
         if self.uuid.is_none() {
             return Err(EnclaveConfigError::MissingField("enclave_uuid".to_string()));
         }
-        let base_domain = if std::env::var("EV_DOMAIN")
-            .unwrap_or_else(|_| String::from("evervault.com"))
-            == "evervault.io"
-        {
-            "evervault.dev"
-        } else {
-            "evervault.com"
-        };
+        let base_domain = common::endpoint::current().enclave_domain();
 
         Ok(format!(
             "{}.{}.enclave.{}",
@@ -510,32 +971,86 @@ impl std::convert::TryFrom<&EnclaveConfig> for ValidatedEnclaveBuildConfig {
             .clone()
             .ok_or_else(|| EnclaveConfigError::MissingField("Team uuid".into()))?;
 
-        let trx_logging_enabled = match (config.trx_logging, config.tls_termination) {
+        // Settings in the [dataplane] block take precedence over their top-level equivalents
+        let dataplane = config.dataplane.clone().unwrap_or_default();
+        let trx_logging = dataplane.trx_logging.unwrap_or(config.trx_logging);
+        let healthcheck = dataplane.healthcheck.or_else(|| config.healthcheck.clone());
+
+        let trx_logging_enabled = match (trx_logging, config.tls_termination) {
             (false, _) => Ok(false), // (logging disabled, _) = logging disabled
             (true, false) => Err(EnclaveConfigError::LoggingEnabledWithoutTLSTermination()), // (logging enabled, tls_termination disabled) = config error (Tls termination needed for logging)
             (true, true) => Ok(true), // (logging enabled, tls_termination enabled) = logging enabled
         }?;
 
+        let protocol = config
+            .network
+            .as_ref()
+            .map(|network| network.protocol)
+            .unwrap_or_default();
+        if !protocol.is_http() {
+            if config.tls_termination {
+                return Err(EnclaveConfigError::TlsTerminationWithTcpProtocol);
+            }
+            // Healthchecks and trusted headers are both HTTP features of the data plane
+            if healthcheck.is_some() {
+                return Err(EnclaveConfigError::HttpSettingWithTcpProtocol(
+                    "healthcheck".into(),
+                ));
+            }
+            if !config.trusted_headers.is_empty() {
+                return Err(EnclaveConfigError::HttpSettingWithTcpProtocol(
+                    "trusted_headers".into(),
+                ));
+            }
+        }
+
+        let supervisor = config
+            .build
+            .as_ref()
+            .map(|build_settings| build_settings.supervisor)
+            .unwrap_or_default();
+        let restart_policy = config.restart_policy(supervisor)?;
+
         let scaling_settings = config.scaling.clone();
 
+        if config.egress.mtls.is_some() && !config.egress.is_enabled() {
+            return Err(EnclaveConfigError::EgressMtlsWithoutEgress);
+        }
+
         Ok(ValidatedEnclaveBuildConfig {
             version: config.version,
             enclave_uuid,
             app_uuid,
             team_uuid,
             enclave_name: config.name.clone(),
+            enclave_type: config.enclave_type,
             debug: config.debug,
             dockerfile: config.dockerfile.clone(),
-            egress: config.egress.clone(),
+            egress: config.egress.expand_presets()?,
             signing: signing_info.try_into()?,
             scaling: scaling_settings,
             attestation: config.attestation.clone(),
             tls_termination: config.tls_termination,
-            api_key_auth: config.api_key_auth,
+            api_key_auth: dataplane.api_key_auth.unwrap_or(config.api_key_auth),
             trx_logging_enabled,
-            forward_proxy_protocol: config.forward_proxy_protocol,
+            forward_proxy_protocol: dataplane
+                .forward_proxy_protocol
+                .unwrap_or(config.forward_proxy_protocol),
             trusted_headers: config.trusted_headers.clone(),
-            healthcheck: config.healthcheck.clone(),
+            healthcheck,
+            protocol,
+            port: config.network.as_ref().and_then(|network| network.port),
+            nitro_cli_image: config.nitro_cli_image()?,
+            supervisor,
+            restart_policy,
+            size: config.size,
+            platform: config.platform()?,
+            extra_ca_certs: config
+                .build
+                .iter()
+                .flat_map(|build_settings| build_settings.extra_ca_certs.iter())
+                .map(|path| CaCertificate::load(path))
+                .collect::<Result<_, _>>()?,
         })
     }
 }
@@ -551,6 +1066,15 @@ pub trait BuildTimeConfig {
     fn private_key(&self) -> Option<&str> {
         None
     }
+    fn nitro_cli_version(&self) -> Option<&str> {
+        None
+    }
+    fn platform(&self) -> Option<&str> {
+        None
+    }
+    fn size(&self) -> Option<EnclaveSize> {
+        None
+    }
 
     // Return new copy of config to prevent args being written to toml file in err
     fn merge_with_config(&self, config: &EnclaveConfig) -> EnclaveConfig {
@@ -568,6 +1092,18 @@ pub trait BuildTimeConfig {
             merged_config.set_key(private_key.to_string());
         }
 
+        if let Some(nitro_cli_version) = self.nitro_cli_version() {
+            merged_config.set_nitro_cli_version(nitro_cli_version.to_string());
+        }
+
+        if let Some(platform) = self.platform() {
+            merged_config.set_platform(platform.to_string());
+        }
+
+        if let Some(size) = self.size() {
+            merged_config.size = Some(size);
+        }
+
         merged_config
     }
 }
@@ -579,6 +1115,11 @@ pub fn read_and_validate_config<B: BuildTimeConfig>(
     config_path: &str,
     args: &B,
 ) -> Result<(EnclaveConfig, ValidatedEnclaveBuildConfig), EnclaveConfigError> {
+    if config_path == STDIN_CONFIG_PATH
+        && args.dockerfile() == Some(crate::build::STDIN_DOCKERFILE_PATH)
+    {
+        return Err(EnclaveConfigError::ConfigAndDockerfileFromStdin);
+    }
     let enclave_config = EnclaveConfig::try_from_filepath(config_path)?;
     let merged_config = args.merge_with_config(&enclave_config);
     let validated_config: ValidatedEnclaveBuildConfig = merged_config.as_ref().try_into()?;
@@ -588,7 +1129,11 @@ pub fn read_and_validate_config<B: BuildTimeConfig>(
 
 #[cfg(test)]
 mod test {
-    use super::{BuildTimeConfig, EnclaveConfig};
+    use super::{
+        BuildSettings, BuildTimeConfig, EgressPresetError, EnclaveConfig, EnclaveConfigError,
+        EnclaveSize, EnclaveType, NetworkProtocol, NitroCliImage, ReleaseChannel, RestartPolicy,
+        RuntimeDigests, SigningInfo, Supervisor, ValidatedEnclaveBuildConfig, ValidatedSigningInfo,
+    };
 
     struct ExampleArgs {
         cert: String,
@@ -608,6 +1153,10 @@ mod test {
         fn private_key(&self) -> Option<&str> {
             Some(self.pk.as_str())
         }
+
+        fn size(&self) -> Option<EnclaveSize> {
+            Some(EnclaveSize::Large)
+        }
     }
 
     #[test]
@@ -615,6 +1164,7 @@ mod test {
         let config = EnclaveConfig {
             version: 1,
             name: "Enclave123".to_string(),
+            enclave_type: super::EnclaveType::Service,
             uuid: Some("abcdef123".to_string()),
             app_uuid: Some("abcdef321".to_string()),
             team_uuid: Some("team_abcdef456".to_string()),
@@ -624,17 +1174,26 @@ mod test {
             egress: super::EgressSettings {
                 enabled: false,
                 destinations: None,
+                presets: vec![],
+                mtls: None,
             },
             scaling: Some(super::ScalingSettings {
                 desired_replicas: 2,
             }),
+            network: None,
+            build: None,
+            dataplane: None,
+            runtime: None,
             signing: None,
             attestation: None,
+            attestation_signature: None,
+            env: None,
             api_key_auth: true,
             trx_logging: true,
             forward_proxy_protocol: false,
             trusted_headers: vec![],
             healthcheck: Some("/health".to_string()),
+            size: Some(EnclaveSize::Small),
         };
 
         let test_args = ExampleArgs {
@@ -648,5 +1207,348 @@ mod test {
         assert_eq!(merged.dockerfile(), test_args.dockerfile().unwrap());
         assert_eq!(merged.cert().unwrap(), test_args.certificate().unwrap());
         assert_eq!(merged.key().unwrap(), test_args.private_key().unwrap());
+        assert_eq!(merged.size, Some(EnclaveSize::Large));
+    }
+
+    #[test]
+    fn validate_tcp_protocol_settings() {
+        let config_toml = r#"
+version = 1
+name = "grpc-enclave"
+uuid = "1234"
+app_uuid = "4321"
+team_uuid = "teamid"
+debug = false
+tls_termination = false
+trx_logging = false
+
+[egress]
+enabled = false
+
+[network]
+protocol = "tcp"
+
+[signing]
+certPath = "../../fixtures/cert.pem"
+keyPath = "../../fixtures/key.pem"
+"#;
+        let config: EnclaveConfig = toml::from_str(config_toml).unwrap();
+        let validated = ValidatedEnclaveBuildConfig::try_from(&config).unwrap();
+        assert_eq!(validated.protocol(), NetworkProtocol::Tcp);
+
+        let mut tls_config = config.clone();
+        tls_config.tls_termination = true;
+        tls_config.trx_logging = false;
+        assert!(matches!(
+            ValidatedEnclaveBuildConfig::try_from(&tls_config),
+            Err(EnclaveConfigError::TlsTerminationWithTcpProtocol)
+        ));
+
+        let mut healthcheck_config = config.clone();
+        healthcheck_config.healthcheck = Some("/health".into());
+        assert!(matches!(
+            ValidatedEnclaveBuildConfig::try_from(&healthcheck_config),
+            Err(EnclaveConfigError::HttpSettingWithTcpProtocol(_))
+        ));
+    }
+
+    #[test]
+    fn dataplane_settings_override_top_level_settings() {
+        let config_toml = r#"
+version = 1
+name = "hello"
+uuid = "1234"
+app_uuid = "4321"
+team_uuid = "teamid"
+debug = false
+api_key_auth = true
+healthcheck = "/legacy-health"
+
+[dataplane]
+healthcheck = "/health"
+api_key_auth = false
+forward_proxy_protocol = true
+
+[egress]
+enabled = false
+
+[signing]
+certPath = "../../fixtures/cert.pem"
+keyPath = "../../fixtures/key.pem"
+"#;
+        let config: EnclaveConfig = toml::from_str(config_toml).unwrap();
+        let validated = ValidatedEnclaveBuildConfig::try_from(&config).unwrap();
+        assert_eq!(validated.healthcheck(), Some("/health"));
+        assert!(!validated.api_key_auth());
+        assert!(validated.forward_proxy_protocol());
+        // unset settings fall back to the top-level defaults
+        assert!(validated.trx_logging_enabled());
+
+        let mut logging_config = config.clone();
+        logging_config.tls_termination = false;
+        logging_config.dataplane.as_mut().unwrap().trx_logging = Some(true);
+        assert!(matches!(
+            ValidatedEnclaveBuildConfig::try_from(&logging_config),
+            Err(EnclaveConfigError::LoggingEnabledWithoutTLSTermination())
+        ));
+    }
+
+    #[test]
+    fn egress_presets_are_expanded_during_validation() {
+        let config_toml = r#"
+version = 1
+name = "hello"
+uuid = "1234"
+app_uuid = "4321"
+team_uuid = "teamid"
+debug = false
+
+[egress]
+enabled = true
+destinations = ["*.evervault.com"]
+presets = ["aws-s3"]
+
+[signing]
+certPath = "../../fixtures/cert.pem"
+keyPath = "../../fixtures/key.pem"
+"#;
+        let config: EnclaveConfig = toml::from_str(config_toml).unwrap();
+        let validated = ValidatedEnclaveBuildConfig::try_from(&config).unwrap();
+        assert_eq!(
+            validated.egress().allowed_destinations(),
+            vec!["*.evervault.com", "s3.amazonaws.com", "*.s3.amazonaws.com"]
+        );
+        assert!(validated.egress().presets.is_empty());
+
+        let mut unknown_config = config.clone();
+        unknown_config.egress.presets = vec!["aws-s4".into()];
+        assert!(matches!(
+            ValidatedEnclaveBuildConfig::try_from(&unknown_config),
+            Err(EnclaveConfigError::InvalidEgressPreset(
+                EgressPresetError::UnknownPreset(_)
+            ))
+        ));
+
+        let mut disabled_config = config.clone();
+        disabled_config.egress.enabled = false;
+        assert!(matches!(
+            ValidatedEnclaveBuildConfig::try_from(&disabled_config),
+            Err(EnclaveConfigError::InvalidEgressPreset(
+                EgressPresetError::EgressDisabled
+            ))
+        ));
+    }
+
+    #[test]
+    fn egress_mtls_is_validated_and_kept() {
+        let config_toml = r#"
+version = 1
+name = "hello"
+uuid = "1234"
+app_uuid = "4321"
+team_uuid = "teamid"
+debug = false
+
+[egress]
+enabled = true
+destinations = ["api.bank.com"]
+
+[egress.mtls]
+cert_path = "./client.crt"
+key_path = "./client.key"
+
+[signing]
+certPath = "../../fixtures/cert.pem"
+keyPath = "../../fixtures/key.pem"
+"#;
+        let config: EnclaveConfig = toml::from_str(config_toml).unwrap();
+        let validated = ValidatedEnclaveBuildConfig::try_from(&config).unwrap();
+        assert_eq!(
+            validated.egress().mtls,
+            Some(super::EgressMtlsSettings {
+                cert_path: "./client.crt".into(),
+                key_path: "./client.key".into(),
+            })
+        );
+
+        let mut disabled_config = config.clone();
+        disabled_config.egress.enabled = false;
+        assert!(matches!(
+            ValidatedEnclaveBuildConfig::try_from(&disabled_config),
+            Err(EnclaveConfigError::EgressMtlsWithoutEgress)
+        ));
+    }
+
+    #[test]
+    fn merge_nitro_cli_version_with_build_settings() {
+        let config: EnclaveConfig = toml::from_str(
+            r#"
+version = 1
+name = "hello"
+debug = false
+
+[egress]
+enabled = false
+
+[build]
+nitro_cli_image = "mirror.internal/nitro-cli:1.2.2"
+nitro_cli_version = "1.2.2"
+"#,
+        )
+        .unwrap();
+        assert!(config.nitro_cli_image().is_err());
+
+        assert_eq!(config.build.as_ref().unwrap().supervisor, Supervisor::Runit);
+
+        let mut merged = config.clone();
+        merged.set_nitro_cli_version("1.2.2".into());
+        assert_eq!(
+            merged.nitro_cli_image().unwrap(),
+            NitroCliImage::Version("1.2.2".into())
+        );
+    }
+
+    #[test]
+    fn record_runtime_versions_for_channel() {
+        let mut config: EnclaveConfig = toml::from_str(
+            r#"
+version = 1
+name = "hello"
+debug = false
+
+[egress]
+enabled = false
+
+[runtime]
+channel = "candidate"
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.runtime_channel(), ReleaseChannel::Candidate);
+
+        config.set_runtime_versions("1.3.0-rc.1", "abcdef");
+        let serialized = toml::to_string(&config).unwrap();
+        assert!(serialized.contains(
+            "[runtime]\nchannel = \"candidate\"\ndata_plane_version = \"1.3.0-rc.1\"\ninstaller_version = \"abcdef\"\n"
+        ));
+
+        config.set_runtime_digests(&RuntimeDigests {
+            data_plane_variant: "egress-disabled/tls-termination-enabled".into(),
+            data_plane: "sha256:aaaa".into(),
+            installer: "sha256:bbbb".into(),
+        });
+        // Recording a new data plane release drops the digest of the previous one
+        config.set_runtime_versions("1.3.0", "abcdef");
+        let runtime = config.runtime.as_ref().unwrap();
+        assert_eq!(runtime.data_plane_digest, None);
+        assert_eq!(runtime.installer_digest.as_deref(), Some("sha256:bbbb"));
+    }
+
+    #[test]
+    fn validate_signing_info_with_next_cert() {
+        let signing_info: SigningInfo = toml::from_str(
+            r#"
+certPath = "../../fixtures/cert.pem"
+keyPath = "../../fixtures/key.pem"
+
+[next]
+certPath = "../../fixtures/cert.pem"
+keyPath = "../../fixtures/key.pem"
+"#,
+        )
+        .unwrap();
+
+        let validated = ValidatedSigningInfo::try_from(&signing_info).unwrap();
+        let next = validated
+            .next
+            .expect("next signing info should be validated");
+        let expected_pcr8 =
+            crate::cert::get_cert_pcr(std::path::Path::new("../../fixtures/cert.pem")).unwrap();
+        assert_eq!(next.pcr8, expected_pcr8);
+
+        let missing_next: SigningInfo = toml::from_str(
+            r#"
+certPath = "../../fixtures/cert.pem"
+keyPath = "../../fixtures/key.pem"
+
+[next]
+certPath = "../../fixtures/missing-cert.pem"
+keyPath = "../../fixtures/key.pem"
+"#,
+        )
+        .unwrap();
+        assert!(ValidatedSigningInfo::try_from(&missing_next).is_err());
+    }
+
+    #[test]
+    fn restart_policy_is_validated_against_the_supervisor() {
+        let config_toml = r#"
+version = 1
+name = "hello"
+uuid = "1234"
+app_uuid = "4321"
+team_uuid = "teamid"
+debug = false
+
+[egress]
+enabled = false
+
+[signing]
+certPath = "../../fixtures/cert.pem"
+keyPath = "../../fixtures/key.pem"
+
+[runtime]
+restart_policy = "on-failure"
+"#;
+        let mut config: EnclaveConfig = toml::from_str(config_toml).unwrap();
+        let validated = ValidatedEnclaveBuildConfig::try_from(&config).unwrap();
+        assert_eq!(validated.restart_policy(), RestartPolicy::OnFailure);
+        assert!(toml::to_string(&config)
+            .unwrap()
+            .contains("restart_policy = \"on-failure\""));
+
+        config.enclave_type = EnclaveType::Job;
+        assert!(matches!(
+            ValidatedEnclaveBuildConfig::try_from(&config),
+            Err(EnclaveConfigError::RestartPolicyForJob(
+                RestartPolicy::OnFailure
+            ))
+        ));
+
+        config.enclave_type = EnclaveType::Service;
+        config.build = Some(BuildSettings {
+            supervisor: Supervisor::Tini,
+            ..Default::default()
+        });
+        assert!(matches!(
+            ValidatedEnclaveBuildConfig::try_from(&config),
+            Err(EnclaveConfigError::RestartPolicyWithoutRunit(
+                RestartPolicy::OnFailure,
+                Supervisor::Tini
+            ))
+        ));
+
+        // Without a policy, each supervisor keeps its existing behaviour
+        config.runtime.as_mut().unwrap().restart_policy = None;
+        let validated = ValidatedEnclaveBuildConfig::try_from(&config).unwrap();
+        assert_eq!(validated.restart_policy(), RestartPolicy::Never);
+        config.build = None;
+        let validated = ValidatedEnclaveBuildConfig::try_from(&config).unwrap();
+        assert_eq!(validated.restart_policy(), RestartPolicy::Always);
+    }
+
+    #[test]
+    fn test_config_from_stdin_is_read_once() {
+        let content = std::fs::read("./test.enclave.toml").unwrap();
+        super::STDIN_CONFIG.set(content).unwrap();
+        let config = EnclaveConfig::try_from_filepath(super::STDIN_CONFIG_PATH).unwrap();
+        let reread = EnclaveConfig::try_from_filepath(super::STDIN_CONFIG_PATH).unwrap();
+        assert_eq!(config.name, reread.name);
+        assert_eq!(
+            config.name,
+            EnclaveConfig::try_from_filepath("./test.enclave.toml")
+                .unwrap()
+                .name
+        );
     }
 }
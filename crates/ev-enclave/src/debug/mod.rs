@@ -0,0 +1,183 @@
+use crate::build::{DATA_PLANE_SERVICE_PATH, USER_ENTRYPOINT_SERVICE_PATH};
+use crate::enclave::EV_USER_IMAGE_NAME;
+use common::CliError;
+use serde::{Deserialize, Serialize};
+use std::process::{Command, ExitStatus};
+use thiserror::Error;
+
+const NITRO_CLI: &str = "nitro-cli";
+const DEBUG_MODE_FLAG: &str = "DEBUG_MODE";
+
+#[derive(Debug, Error)]
+pub enum DebugError {
+    #[error("nitro-cli couldn't be run - {0}. Debug shells attach to Enclaves run on this host with nitro-cli run-enclave --debug-mode, see ev enclave diagnose host.")]
+    NitroCliUnavailable(std::io::Error),
+    #[error("nitro-cli {command} failed - {stderr}")]
+    NitroCliFailed { command: String, stderr: String },
+    #[error("Failed to parse the output of nitro-cli describe-enclaves - {0}")]
+    Parse(#[from] serde_json::Error),
+    #[error("No Enclaves are running on this host. Start one with nitro-cli run-enclave --eif-path enclave.eif --debug-mode.")]
+    NoEnclaves,
+    #[error("No running Enclave has the ID {0}")]
+    EnclaveNotFound(String),
+    #[error("Enclave {0} wasn't run in debug mode, so its console can't be attached to. Run it with nitro-cli run-enclave --debug-mode.")]
+    NotDebugMode(String),
+    #[error("{0} debug-mode Enclaves are running on this host. Choose one with --enclave-id: {1}")]
+    MultipleEnclaves(usize, String),
+}
+
+impl CliError for DebugError {
+    fn exitcode(&self) -> exitcode::ExitCode {
+        match self {
+            Self::NitroCliUnavailable(_) => exitcode::UNAVAILABLE,
+            Self::NitroCliFailed { .. } => exitcode::SOFTWARE,
+            Self::Parse(_) => exitcode::DATAERR,
+            Self::NoEnclaves | Self::EnclaveNotFound(_) => exitcode::NOINPUT,
+            Self::NotDebugMode(_) | Self::MultipleEnclaves(..) => exitcode::USAGE,
+        }
+    }
+}
+
+/// An Enclave running on this host, as listed by `nitro-cli describe-enclaves`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RunningEnclave {
+    #[serde(rename = "EnclaveName", default)]
+    pub name: Option<String>,
+    #[serde(rename = "EnclaveID")]
+    pub id: String,
+    #[serde(rename = "EnclaveCID", default)]
+    pub cid: Option<u64>,
+    #[serde(rename = "State", default)]
+    pub state: String,
+    #[serde(rename = "Flags", default)]
+    pub flags: String,
+}
+
+impl RunningEnclave {
+    pub fn is_debug_mode(&self) -> bool {
+        self.flags == DEBUG_MODE_FLAG
+    }
+}
+
+pub fn running_enclaves() -> Result<Vec<RunningEnclave>, DebugError> {
+    let output = Command::new(NITRO_CLI)
+        .arg("describe-enclaves")
+        .output()
+        .map_err(DebugError::NitroCliUnavailable)?;
+    if !output.status.success() {
+        return Err(DebugError::NitroCliFailed {
+            command: "describe-enclaves".into(),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+    Ok(serde_json::from_slice(&output.stdout)?)
+}
+
+/// The Enclave with the given ID, or the only debug-mode Enclave when no ID is given
+pub fn select_debug_enclave(
+    enclaves: Vec<RunningEnclave>,
+    enclave_id: Option<&str>,
+) -> Result<RunningEnclave, DebugError> {
+    if enclaves.is_empty() {
+        return Err(DebugError::NoEnclaves);
+    }
+    let selected = match enclave_id {
+        Some(enclave_id) => enclaves
+            .into_iter()
+            .find(|enclave| enclave.id == enclave_id)
+            .ok_or_else(|| DebugError::EnclaveNotFound(enclave_id.to_string()))?,
+        None => {
+            let mut debug_enclaves: Vec<RunningEnclave> = enclaves
+                .iter()
+                .filter(|enclave| enclave.is_debug_mode())
+                .cloned()
+                .collect();
+            match debug_enclaves.len() {
+                0 => return Err(DebugError::NotDebugMode(enclaves[0].id.clone())),
+                1 => debug_enclaves.remove(0),
+                count => {
+                    let ids: Vec<&str> = debug_enclaves.iter().map(|e| e.id.as_str()).collect();
+                    return Err(DebugError::MultipleEnclaves(count, ids.join(", ")));
+                }
+            }
+        }
+    };
+    if !selected.is_debug_mode() {
+        return Err(DebugError::NotDebugMode(selected.id));
+    }
+    Ok(selected)
+}
+
+/// Streams the Enclave's console until it exits or the user interrupts. Consoles are read-only, so
+/// this shows the kernel's and services' output rather than accepting commands.
+pub fn attach_console(enclave_id: &str) -> Result<ExitStatus, DebugError> {
+    Command::new(NITRO_CLI)
+        .args(["console", "--enclave-id", enclave_id])
+        .status()
+        .map_err(DebugError::NitroCliUnavailable)
+}
+
+/// Where to look when an Enclave fails to boot, in the order it boots
+pub fn boot_sequence_guide() -> String {
+    format!(
+        "Enclaves boot in this order, and the console shows the output of each step:
+  1. The kernel boots from the EIF and unpacks the image's filesystem.
+  2. /bootstrap, the image's entrypoint, sets up the loopback interface and egress rules, then starts the services.
+  3. {DATA_PLANE_SERVICE_PATH}/run starts the data plane, logging \"Booting Evervault data plane...\".
+  4. {USER_ENTRYPOINT_SERVICE_PATH}/run starts your service, and {USER_ENTRYPOINT_SERVICE_PATH}/finish runs whenever it exits.
+To inspect these files as they are in the Enclave, open a shell in the last image built on this host:
+  docker run --rm -it --entrypoint /bin/sh {EV_USER_IMAGE_NAME}:latest"
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn running_enclave(id: &str, flags: &str) -> RunningEnclave {
+        RunningEnclave {
+            name: None,
+            id: id.into(),
+            cid: Some(16),
+            state: "RUNNING".into(),
+            flags: flags.into(),
+        }
+    }
+
+    #[test]
+    fn test_select_debug_enclave() {
+        let described: Vec<RunningEnclave> = serde_json::from_str(
+            r#"[{"EnclaveName":"hello","EnclaveID":"i-abc-enc1","ProcessID":7,"EnclaveCID":16,"NumberOfCPUs":2,"CPUIDs":[1,3],"MemoryMiB":512,"State":"RUNNING","Flags":"DEBUG_MODE"}]"#,
+        )
+        .unwrap();
+        let selected = select_debug_enclave(described, None).unwrap();
+        assert_eq!(selected.id, "i-abc-enc1");
+        assert_eq!(selected.name.as_deref(), Some("hello"));
+
+        assert!(matches!(
+            select_debug_enclave(vec![], None),
+            Err(DebugError::NoEnclaves)
+        ));
+        let enclaves = vec![
+            running_enclave("enc-1", "NONE"),
+            running_enclave("enc-2", DEBUG_MODE_FLAG),
+            running_enclave("enc-3", DEBUG_MODE_FLAG),
+        ];
+        assert!(matches!(
+            select_debug_enclave(enclaves.clone(), None),
+            Err(DebugError::MultipleEnclaves(2, ids)) if ids == "enc-2, enc-3"
+        ));
+        assert!(matches!(
+            select_debug_enclave(enclaves.clone(), Some("enc-1")),
+            Err(DebugError::NotDebugMode(id)) if id == "enc-1"
+        ));
+        assert!(matches!(
+            select_debug_enclave(enclaves.clone(), Some("enc-4")),
+            Err(DebugError::EnclaveNotFound(_))
+        ));
+        assert_eq!(
+            select_debug_enclave(enclaves, Some("enc-3")).unwrap().id,
+            "enc-3"
+        );
+    }
+}
@@ -11,15 +11,22 @@ pub enum DeleteError {
     IoError(#[from] std::io::Error),
     #[error("An error occurred contacting the API — {0}")]
     ApiError(#[from] common::api::client::ApiError),
+    #[error("Invalid age `{0}`. Expected a number followed by m, h, d or w, e.g. 30d")]
+    InvalidAge(String),
+    #[error("Failed to export the Enclave's state to {path}, so it wasn't deleted - {source}")]
+    ExportState {
+        path: String,
+        source: std::io::Error,
+    },
 }
 
 impl CliError for DeleteError {
     fn exitcode(&self) -> exitcode::ExitCode {
         match self {
             Self::EnclaveConfigError(config_err) => config_err.exitcode(),
-            Self::IoError(_) => exitcode::IOERR,
+            Self::IoError(_) | Self::ExportState { .. } => exitcode::IOERR,
             Self::ApiError(api_err) => api_err.exitcode(),
-            Self::MissingUuid => exitcode::DATAERR,
+            Self::MissingUuid | Self::InvalidAge(_) => exitcode::DATAERR,
         }
     }
 }
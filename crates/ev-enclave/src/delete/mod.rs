@@ -1,16 +1,118 @@
+use std::path::Path;
 use std::sync::Arc;
 
 use crate::api;
-use crate::api::enclave::EnclaveApi;
+use crate::api::enclave::{Enclave, EnclaveApi, EnclaveState};
+use crate::config::EnclaveConfig;
 use crate::progress::{get_tracker, poll_fn_and_report_status, ProgressLogger, StatusReport};
+use chrono::{DateTime, Duration, Utc};
 use common::api::AuthMode;
+use futures::StreamExt;
 mod error;
 pub use error::DeleteError;
+mod snapshot;
+pub use snapshot::{snapshot_enclave, write_snapshot, DeploymentSnapshot, EnclaveStateSnapshot};
+
+// Bulk deletes are sent concurrently, but bounded to stay well within the API's rate limits
+const MAX_CONCURRENT_DELETES: usize = 4;
+
+/// Parses an age such as `30d` into a duration. Supports minutes, hours, days and weeks.
+pub fn parse_age(value: &str) -> Result<Duration, DeleteError> {
+    let invalid = || DeleteError::InvalidAge(value.to_string());
+    let value = value.trim();
+    let (unit_index, unit) = value.char_indices().last().ok_or_else(invalid)?;
+    // Ages are unsigned, so a negative age can't select Enclaves updated in the future
+    let amount = value[..unit_index]
+        .parse::<u64>()
+        .ok()
+        .filter(|amount| *amount > 0)
+        .and_then(|amount| i64::try_from(amount).ok())
+        .ok_or_else(invalid)?;
+    let duration = match unit {
+        'm' => Duration::try_minutes(amount),
+        'h' => Duration::try_hours(amount),
+        'd' => Duration::try_days(amount),
+        'w' => Duration::try_weeks(amount),
+        _ => None,
+    };
+    duration.ok_or_else(invalid)
+}
+
+/// Criteria for choosing Enclaves to delete in bulk. Empty criteria match every Enclave.
+#[derive(Clone, Debug, Default)]
+pub struct EnclaveSelector {
+    pub name_prefix: Option<String>,
+    pub states: Vec<EnclaveState>,
+    /// Only match Enclaves which haven't been updated within this duration
+    pub older_than: Option<Duration>,
+}
+
+impl EnclaveSelector {
+    pub fn matches(&self, enclave: &Enclave, now: DateTime<Utc>) -> bool {
+        let name_matches = self
+            .name_prefix
+            .as_deref()
+            .is_none_or(|prefix| enclave.name.starts_with(prefix));
+        let state_matches = self.states.is_empty() || self.states.contains(&enclave.state);
+        // Enclaves with unparseable timestamps are never treated as old enough to delete
+        let age_matches = self.older_than.is_none_or(|older_than| {
+            DateTime::parse_from_rfc3339(&enclave.updated_at)
+                .is_ok_and(|updated_at| now.signed_duration_since(updated_at) > older_than)
+        });
+        name_matches && state_matches && age_matches
+    }
+
+    pub fn select<'a>(&self, enclaves: &'a [Enclave], now: DateTime<Utc>) -> Vec<&'a Enclave> {
+        enclaves
+            .iter()
+            .filter(|enclave| self.matches(enclave, now))
+            .collect()
+    }
+}
+
+/// Deletes each Enclave without waiting for the deletions to complete, returning the result for
+/// each uuid in the order they finished
+pub async fn delete_enclaves<T: EnclaveApi>(
+    enclave_api: &T,
+    enclave_uuids: Vec<String>,
+) -> Vec<(String, Result<(), DeleteError>)> {
+    futures::stream::iter(enclave_uuids)
+        .map(|enclave_uuid| async move {
+            let result = enclave_api
+                .delete_enclave(&enclave_uuid)
+                .await
+                .map(|_| ())
+                .map_err(DeleteError::from);
+            (enclave_uuid, result)
+        })
+        .buffer_unordered(MAX_CONCURRENT_DELETES)
+        .collect()
+        .await
+}
+
+/// Writes a snapshot of the Enclave's state to `path`, so there's a record of it once it's deleted
+pub async fn export_enclave_state(
+    config: &str,
+    enclave_uuid: Option<&str>,
+    auth: AuthMode,
+    path: &Path,
+) -> Result<(), DeleteError> {
+    let enclave_uuid = crate::common::resolve_enclave_uuid(enclave_uuid, config)?
+        .ok_or(DeleteError::MissingUuid)?;
+    // The config is only recorded if it belongs to the Enclave being deleted
+    let config = EnclaveConfig::try_from_filepath(config)
+        .ok()
+        .filter(|config| config.uuid.as_deref() == Some(enclave_uuid.as_str()));
+
+    let enclave_api = api::enclave::EnclaveClient::new(auth);
+    let snapshot = snapshot_enclave(&enclave_api, &enclave_uuid, config).await?;
+    write_snapshot(path, &snapshot)
+}
 
 pub async fn delete_enclave(
     config: &str,
     enclave_uuid: Option<&str>,
-    api_key: &str,
+    auth: AuthMode,
     background: bool,
 ) -> Result<(), DeleteError> {
     let maybe_enclave_uuid = crate::common::resolve_enclave_uuid(enclave_uuid, config)?;
@@ -19,7 +121,7 @@ pub async fn delete_enclave(
         _ => return Err(DeleteError::MissingUuid),
     };
 
-    let enclave_api = api::enclave::EnclaveClient::new(AuthMode::ApiKey(api_key.to_string()));
+    let enclave_api = api::enclave::EnclaveClient::new(auth);
 
     let deleted_enclave = match enclave_api.delete_enclave(&enclave_uuid).await {
         Ok(enclave_ref) => enclave_ref,
@@ -94,7 +196,7 @@ mod test {
             .expect_get_enclave()
             .times(3)
             .returning(move |_| Box::pin(std::future::ready(Ok(responses.next().unwrap()))));
-        let result = watch_deletion(mock_api, "abc".into(), NonTty).await;
+        let result = watch_deletion(mock_api, "abc".into(), NonTty::default()).await;
         assert!(result.is_ok());
     }
 
@@ -115,7 +217,7 @@ mod test {
             .expect_get_enclave()
             .times(5)
             .returning(move |_| Box::pin(std::future::ready(Err(responses.next().unwrap()))));
-        let result = watch_deletion(mock_api, "abc".into(), NonTty).await;
+        let result = watch_deletion(mock_api, "abc".into(), NonTty::default()).await;
         assert!(result.is_err());
     }
 
@@ -147,7 +249,97 @@ mod test {
             .expect_get_enclave()
             .times(4)
             .returning(move |_| Box::pin(std::future::ready(responses.next().unwrap())));
-        let result = watch_deletion(mock_api, "abc".into(), NonTty).await;
+        let result = watch_deletion(mock_api, "abc".into(), NonTty::default()).await;
         assert!(result.is_ok());
     }
+
+    fn sample_enclave(uuid: &str, name: &str, state: EnclaveState, updated_at: &str) -> Enclave {
+        Enclave {
+            uuid: uuid.into(),
+            name: name.into(),
+            team_uuid: "team".into(),
+            app_uuid: "app".into(),
+            domain: "enclave.com".into(),
+            state,
+            created_at: updated_at.into(),
+            updated_at: updated_at.into(),
+        }
+    }
+
+    #[test]
+    fn test_parse_age() {
+        assert_eq!(parse_age("30d").unwrap(), Duration::days(30));
+        assert_eq!(parse_age("12h").unwrap(), Duration::hours(12));
+        assert_eq!(parse_age("2w").unwrap(), Duration::weeks(2));
+        assert!(parse_age("30").is_err());
+        assert!(parse_age("d").is_err());
+        assert!(parse_age("").is_err());
+        assert!(parse_age("30é").is_err());
+        assert!(parse_age("99999999999999d").is_err());
+        assert!(parse_age("-1d").is_err());
+        assert!(parse_age("0d").is_err());
+    }
+
+    #[test]
+    fn test_select_enclaves() {
+        let now = DateTime::parse_from_rfc3339("2024-03-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let enclaves = vec![
+            sample_enclave(
+                "1",
+                "test-old",
+                EnclaveState::Deleted,
+                "2024-01-01T00:00:00Z",
+            ),
+            sample_enclave(
+                "2",
+                "test-new",
+                EnclaveState::Deleted,
+                "2024-02-28T00:00:00Z",
+            ),
+            sample_enclave("3", "prod", EnclaveState::Active, "2024-01-01T00:00:00Z"),
+            sample_enclave("4", "test-bad-date", EnclaveState::Deleted, "00:00:00"),
+        ];
+
+        let prune = EnclaveSelector {
+            states: vec![EnclaveState::Deleted],
+            older_than: Some(Duration::days(30)),
+            ..Default::default()
+        };
+        let selected: Vec<&str> = prune
+            .select(&enclaves, now)
+            .iter()
+            .map(|enclave| enclave.uuid.as_str())
+            .collect();
+        assert_eq!(selected, vec!["1"]);
+
+        let by_prefix = EnclaveSelector {
+            name_prefix: Some("test-".into()),
+            ..Default::default()
+        };
+        assert_eq!(by_prefix.select(&enclaves, now).len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_delete_enclaves_concurrently() {
+        let mut mock_api = MockEnclaveApi::new();
+        mock_api
+            .expect_delete_enclave()
+            .times(3)
+            .returning(move |uuid| {
+                let result = if uuid == "2" {
+                    Err(ApiError::new(common::api::client::ApiErrorKind::NotFound))
+                } else {
+                    Ok(sample_enclave(uuid, "test", EnclaveState::Deleting, ""))
+                };
+                Box::pin(std::future::ready(result))
+            });
+
+        let mut results =
+            delete_enclaves(&mock_api, vec!["1".into(), "2".into(), "3".into()]).await;
+        results.sort_by(|a, b| a.0.cmp(&b.0));
+        let outcomes: Vec<bool> = results.iter().map(|(_, result)| result.is_ok()).collect();
+        assert_eq!(outcomes, vec![true, false, true]);
+    }
 }
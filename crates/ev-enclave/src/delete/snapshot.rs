@@ -0,0 +1,156 @@
+use super::DeleteError;
+use crate::api::enclave::{Enclave, EnclaveApi};
+use crate::config::EnclaveConfig;
+use crate::enclave::PCRs;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// A record of an Enclave as it was before it was deleted, written by `--export-state`. Env vars
+/// are recorded by name only, so the snapshot never contains secrets.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnclaveStateSnapshot {
+    pub exported_at: String,
+    pub enclave: Enclave,
+    /// The enclave.toml the Enclave was deleted with, when one was found
+    pub config: Option<EnclaveConfig>,
+    pub last_deployment: Option<DeploymentSnapshot>,
+    pub env_var_names: Vec<String>,
+    pub domains: Vec<String>,
+}
+
+/// The most recent deployment which finished, and the measurements of the EIF it ran
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeploymentSnapshot {
+    pub deployment_uuid: String,
+    pub version: u16,
+    pub debug_mode: bool,
+    pub started_at: Option<String>,
+    pub completed_at: Option<String>,
+    pub pcrs: Option<PCRs>,
+    pub data_plane_version: Option<String>,
+    pub installer_version: Option<String>,
+    pub git_hash: Option<String>,
+}
+
+/// Collects the state of the Enclave from the API. Any request failing fails the snapshot, so an
+/// incomplete record is never mistaken for the full one.
+pub async fn snapshot_enclave<T: EnclaveApi>(
+    enclave_api: &T,
+    enclave_uuid: &str,
+    config: Option<EnclaveConfig>,
+) -> Result<EnclaveStateSnapshot, DeleteError> {
+    let enclave = enclave_api.get_enclave(enclave_uuid).await?;
+    let env = enclave_api
+        .get_enclave_env(enclave_uuid.to_string())
+        .await?;
+    let domains = enclave_api.get_custom_domains(enclave_uuid).await?;
+
+    let last_deployment = enclave
+        .latest_finished_deployment()
+        .map(|latest| DeploymentSnapshot {
+            deployment_uuid: latest.deployment.uuid.clone(),
+            version: latest.version.version,
+            debug_mode: latest.deployment.debug_mode,
+            started_at: latest.deployment.started_at.clone(),
+            completed_at: latest.deployment.completed_at.clone(),
+            pcrs: latest.version.pcrs.clone(),
+            data_plane_version: latest.version.data_plane_version.clone(),
+            installer_version: latest.version.installer_version.clone(),
+            git_hash: latest.version.git_hash.clone(),
+        });
+    let mut env_var_names: Vec<String> = env.secrets.into_iter().map(|env| env.name).collect();
+    env_var_names.sort();
+
+    Ok(EnclaveStateSnapshot {
+        exported_at: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        enclave: enclave.enclaves,
+        config,
+        last_deployment,
+        env_var_names,
+        domains: domains
+            .domains
+            .into_iter()
+            .map(|domain| domain.domain)
+            .collect(),
+    })
+}
+
+pub fn write_snapshot(path: &Path, snapshot: &EnclaveStateSnapshot) -> Result<(), DeleteError> {
+    let contents =
+        serde_json::to_vec_pretty(snapshot).expect("Failed to serialize Enclave state snapshot");
+    std::fs::write(path, contents).map_err(|source| DeleteError::ExportState {
+        path: path.display().to_string(),
+        source,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::api::enclave::{
+        BuildStatus, DeployStatus, DeploymentsForGetEnclave, EnclaveEnv, EnclaveState,
+        GetCustomDomainsResponse, MockEnclaveApi, Secret,
+    };
+    use crate::test_utils::{build_get_enclave_deployment, build_get_enclave_response};
+
+    #[tokio::test]
+    async fn test_snapshot_enclave() {
+        let mut finished = build_get_enclave_deployment(
+            BuildStatus::Ready,
+            DeployStatus::Ready,
+            Some("2024-01-01T00:00:00Z".into()),
+            Some("2024-01-01T00:05:00Z".into()),
+        );
+        finished.deployment.uuid = "deployment_1".into();
+        finished.enclave_version.pcrs = Some(PCRs {
+            pcr0: crate::test_utils::pcr("00"),
+            pcr1: crate::test_utils::pcr("11"),
+            pcr2: crate::test_utils::pcr("22"),
+            pcr8: None,
+        });
+        let deployments = vec![DeploymentsForGetEnclave {
+            deployment: finished.deployment,
+            version: finished.enclave_version,
+        }];
+
+        let mut mock_api = MockEnclaveApi::new();
+        mock_api.expect_get_enclave().returning(move |_| {
+            let response = build_get_enclave_response(EnclaveState::Active, deployments.clone());
+            Box::pin(std::future::ready(Ok(response)))
+        });
+        mock_api.expect_get_enclave_env().returning(|_| {
+            let secret = |name: &str| Secret {
+                name: name.into(),
+                secret: "ev:encrypted:value".into(),
+            };
+            let env = EnclaveEnv {
+                secrets: vec![secret("STRIPE_KEY"), secret("DATABASE_URL")],
+            };
+            Box::pin(std::future::ready(Ok(env)))
+        });
+        mock_api.expect_get_custom_domains().returning(|_| {
+            let domains: GetCustomDomainsResponse = serde_json::from_str(
+                r#"{"domains":[{"domain":"api.example.com","status":"active"}]}"#,
+            )
+            .unwrap();
+            Box::pin(std::future::ready(Ok(domains)))
+        });
+
+        let snapshot = snapshot_enclave(&mock_api, "abc", None).await.unwrap();
+        assert_eq!(snapshot.enclave.uuid, "abc");
+        assert_eq!(snapshot.env_var_names, vec!["DATABASE_URL", "STRIPE_KEY"]);
+        assert_eq!(snapshot.domains, vec!["api.example.com"]);
+        let last_deployment = snapshot.last_deployment.as_ref().unwrap();
+        assert_eq!(last_deployment.deployment_uuid, "deployment_1");
+        assert!(last_deployment.pcrs.is_some());
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("state.json");
+        write_snapshot(&path, &snapshot).unwrap();
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert!(written.contains("\"envVarNames\""));
+        assert!(!written.contains("ev:encrypted:value"));
+    }
+}
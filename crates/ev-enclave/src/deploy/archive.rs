@@ -0,0 +1,469 @@
+use crate::api::enclave::UploadFormat;
+use crate::enclave::ENCLAVE_FILENAME;
+use bytes::Bytes;
+use sha2::{Digest, Sha256, Sha384};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt};
+
+const ZSTD_COMPRESSION_LEVEL: i32 = 3;
+// Compressed EIFs smaller than this are kept in memory rather than spilled to a temp file
+const SPOOL_MEMORY_LIMIT: usize = 64 * 1024 * 1024;
+
+const LOCAL_FILE_HEADER_SIGNATURE: u32 = 0x04034b50;
+const CENTRAL_DIRECTORY_HEADER_SIGNATURE: u32 = 0x02014b50;
+const ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x06064b50;
+const ZIP64_END_OF_CENTRAL_DIRECTORY_LOCATOR_SIGNATURE: u32 = 0x07064b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x06054b50;
+const ZIP64_EXTRA_FIELD_ID: u16 = 0x0001;
+const ZIP_VERSION: u16 = 20;
+const ZIP64_VERSION: u16 = 45;
+// 1980-01-01 00:00:00, the earliest MS-DOS timestamp, so archives of the same EIF are identical
+const DOS_DATE: u16 = (1 << 5) | 1;
+const DOS_TIME: u16 = 0;
+
+pub type ArchiveReader = Box<dyn AsyncRead + Send + Unpin>;
+
+/// The size and digests of an EIF, computed together in a single read. The SHA-256 and SHA-384
+/// digests are sent with the deployment intent so the upload can be verified on Evervault, and
+/// the CRC-32 is reused for the zip's headers rather than reading the EIF again. The EIF's
+/// modification time is kept so a zip is never streamed from an EIF which changed since.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EifDigests {
+    pub size_bytes: u64,
+    pub sha256: String,
+    pub sha384: String,
+    crc32: u32,
+    modified: Option<SystemTime>,
+}
+
+impl EifDigests {
+    pub fn compute(output_path: &Path) -> std::io::Result<Self> {
+        let mut eif = std::fs::File::open(output_path.join(ENCLAVE_FILENAME))?;
+        let modified = eif.metadata()?.modified().ok();
+        let mut crc32 = crc32fast::Hasher::new();
+        let mut sha256 = Sha256::new();
+        let mut sha384 = Sha384::new();
+        let mut size_bytes = 0;
+        let mut buffer = vec![0; 1024 * 1024];
+        loop {
+            let read = eif.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            crc32.update(&buffer[..read]);
+            sha256.update(&buffer[..read]);
+            sha384.update(&buffer[..read]);
+            size_bytes += read as u64;
+        }
+        Ok(Self {
+            size_bytes,
+            sha256: hex::encode(sha256.finalize()),
+            sha384: hex::encode(sha384.finalize()),
+            crc32: crc32.finalize(),
+            modified,
+        })
+    }
+}
+
+/// The archive uploaded for a deployment. Archives are produced from the EIF as they're uploaded,
+/// rather than written alongside it, so a fresh reader can be taken for each upload attempt.
+pub enum UploadArchive {
+    /// A stored zip, with its headers generated around the EIF as it's read
+    Zip {
+        eif_path: PathBuf,
+        eif_modified: Option<SystemTime>,
+        header: Bytes,
+        trailer: Bytes,
+        len: u64,
+    },
+    /// zstd's output size isn't known until the EIF has been compressed, so it's spooled. Output
+    /// which stayed in memory is shared by the readers for each part rather than copied.
+    Compressed(Bytes),
+    /// zstd output which outgrew SPOOL_MEMORY_LIMIT, reopened for each reader
+    Spooled {
+        file: tempfile::NamedTempFile,
+        len: u64,
+    },
+}
+
+/// Compressed archive contents, held in memory until they outgrow SPOOL_MEMORY_LIMIT and then
+/// moved to a temp file, which is removed when it's dropped. The file is reopened for each
+/// reader, so parts of the archive can be read concurrently.
+pub enum Spool {
+    Memory(Vec<u8>),
+    Disk(tempfile::NamedTempFile),
+}
+
+impl Write for Spool {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if let Self::Memory(contents) = self {
+            if contents.len() + buf.len() > SPOOL_MEMORY_LIMIT {
+                let mut file = tempfile::NamedTempFile::new()?;
+                file.write_all(contents)?;
+                *self = Self::Disk(file);
+            }
+        }
+        match self {
+            Self::Memory(contents) => contents.write(buf),
+            Self::Disk(file) => file.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Self::Memory(_) => Ok(()),
+            Self::Disk(file) => file.flush(),
+        }
+    }
+}
+
+impl UploadArchive {
+    /// Creates the archive for an EIF whose digests have already been computed, so a zip can be
+    /// produced without reading the EIF again
+    pub fn new(
+        output_path: &Path,
+        format: UploadFormat,
+        digests: &EifDigests,
+    ) -> std::io::Result<Self> {
+        let eif_path = output_path.join(ENCLAVE_FILENAME);
+        match format {
+            UploadFormat::Zip => Ok(Self::stored_zip(eif_path, digests)),
+            UploadFormat::Zstd => Self::zstd(&eif_path),
+        }
+    }
+
+    fn stored_zip(eif_path: PathBuf, digests: &EifDigests) -> Self {
+        let eif_len = digests.size_bytes;
+        let (header, trailer) = stored_zip_records(
+            ENCLAVE_FILENAME,
+            eif_len,
+            digests.crc32,
+            needs_zip64(eif_len),
+        );
+        Self::Zip {
+            eif_path,
+            eif_modified: digests.modified,
+            len: header.len() as u64 + eif_len + trailer.len() as u64,
+            header: header.into(),
+            trailer: trailer.into(),
+        }
+    }
+
+    fn zstd(eif_path: &Path) -> std::io::Result<Self> {
+        let mut eif = std::fs::File::open(eif_path)?;
+        let mut encoder = zstd::Encoder::new(Spool::Memory(vec![]), ZSTD_COMPRESSION_LEVEL)?;
+        std::io::copy(&mut eif, &mut encoder)?;
+        let mut spool = encoder.finish()?;
+        spool.flush()?;
+        match spool {
+            Spool::Memory(contents) => Ok(Self::Compressed(contents.into())),
+            Spool::Disk(mut file) => {
+                let len = file.as_file_mut().seek(SeekFrom::End(0))?;
+                Ok(Self::Spooled { file, len })
+            }
+        }
+    }
+
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> u64 {
+        match self {
+            Self::Zip { len, .. } | Self::Spooled { len, .. } => *len,
+            Self::Compressed(contents) => contents.len() as u64,
+        }
+    }
+
+    /// A reader over `len` bytes of the archive, starting at `offset`
+    pub async fn part_reader(&self, offset: u64, len: u64) -> std::io::Result<ArchiveReader> {
+        Ok(Box::new(self.reader_from(offset, len).await?.take(len)))
+    }
+
+    async fn reader_from(&self, offset: u64, part_len: u64) -> std::io::Result<ArchiveReader> {
+        match self {
+            Self::Zip {
+                eif_path,
+                eif_modified,
+                header,
+                trailer,
+                len,
+            } => {
+                let header_len = header.len() as u64;
+                let eif_len = len - header_len - trailer.len() as u64;
+                let eif_offset = offset.saturating_sub(header_len).min(eif_len);
+                let trailer_offset = offset.saturating_sub(header_len + eif_len);
+                let mut eif = tokio::fs::File::open(eif_path).await?;
+                // The zip's headers hold the CRC-32 computed earlier, so they'd no longer match
+                let metadata = eif.metadata().await?;
+                if metadata.len() != eif_len || metadata.modified().ok() != *eif_modified {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!("{} changed while it was being uploaded", eif_path.display()),
+                    ));
+                }
+                eif.seek(SeekFrom::Start(eif_offset)).await?;
+                Ok(Box::new(AsyncReadExt::chain(
+                    AsyncReadExt::chain(remaining_from(header, offset, part_len), eif),
+                    remaining_from(trailer, trailer_offset, part_len),
+                )))
+            }
+            Self::Compressed(contents) => Ok(Box::new(remaining_from(contents, offset, part_len))),
+            Self::Spooled { file, .. } => {
+                let mut file = tokio::fs::File::open(file.path()).await?;
+                file.seek(SeekFrom::Start(offset)).await?;
+                Ok(Box::new(file))
+            }
+        }
+    }
+}
+
+// Up to `len` bytes of `buffer` from `offset`, sharing the buffer rather than copying it
+fn remaining_from(buffer: &Bytes, offset: u64, len: u64) -> std::io::Cursor<Bytes> {
+    let start = (offset as usize).min(buffer.len());
+    let end = start.saturating_add(len as usize).min(buffer.len());
+    std::io::Cursor::new(buffer.slice(start..end))
+}
+
+fn needs_zip64(len: u64) -> bool {
+    // Leave room for the local header, which comes before the central directory's offset
+    len >= u32::MAX as u64 - u16::MAX as u64
+}
+
+fn clamp_u32(value: u64) -> u32 {
+    value.min(u32::MAX as u64) as u32
+}
+
+fn put_u16(buffer: &mut Vec<u8>, value: u16) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(buffer: &mut Vec<u8>, value: u32) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u64(buffer: &mut Vec<u8>, value: u64) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+// The records of a zip holding a single uncompressed file: the local file header which precedes
+// the file's contents, and the central directory which follows them.
+fn stored_zip_records(name: &str, len: u64, crc: u32, zip64: bool) -> (Vec<u8>, Vec<u8>) {
+    let (version, size_field, extra) = if zip64 {
+        let mut extra = vec![];
+        put_u16(&mut extra, ZIP64_EXTRA_FIELD_ID);
+        put_u16(&mut extra, 16);
+        put_u64(&mut extra, len);
+        put_u64(&mut extra, len);
+        (ZIP64_VERSION, u32::MAX, extra)
+    } else {
+        (ZIP_VERSION, len as u32, vec![])
+    };
+
+    let mut header = vec![];
+    put_u32(&mut header, LOCAL_FILE_HEADER_SIGNATURE);
+    put_u16(&mut header, version);
+    put_u16(&mut header, 0); // flags
+    put_u16(&mut header, 0); // stored
+    put_u16(&mut header, DOS_TIME);
+    put_u16(&mut header, DOS_DATE);
+    put_u32(&mut header, crc);
+    put_u32(&mut header, size_field);
+    put_u32(&mut header, size_field);
+    put_u16(&mut header, name.len() as u16);
+    put_u16(&mut header, extra.len() as u16);
+    header.extend_from_slice(name.as_bytes());
+    header.extend_from_slice(&extra);
+
+    let mut trailer = vec![];
+    put_u32(&mut trailer, CENTRAL_DIRECTORY_HEADER_SIGNATURE);
+    put_u16(&mut trailer, version); // made by
+    put_u16(&mut trailer, version); // needed to extract
+    put_u16(&mut trailer, 0); // flags
+    put_u16(&mut trailer, 0); // stored
+    put_u16(&mut trailer, DOS_TIME);
+    put_u16(&mut trailer, DOS_DATE);
+    put_u32(&mut trailer, crc);
+    put_u32(&mut trailer, size_field);
+    put_u32(&mut trailer, size_field);
+    put_u16(&mut trailer, name.len() as u16);
+    put_u16(&mut trailer, extra.len() as u16);
+    put_u16(&mut trailer, 0); // comment length
+    put_u16(&mut trailer, 0); // disk number
+    put_u16(&mut trailer, 0); // internal attributes
+    put_u32(&mut trailer, 0); // external attributes
+    put_u32(&mut trailer, 0); // local header offset
+    trailer.extend_from_slice(name.as_bytes());
+    trailer.extend_from_slice(&extra);
+
+    let central_directory_offset = header.len() as u64 + len;
+    let central_directory_len = trailer.len() as u64;
+    if zip64 {
+        let zip64_end_offset = central_directory_offset + central_directory_len;
+        put_u32(&mut trailer, ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+        put_u64(&mut trailer, 44); // size of the remaining record
+        put_u16(&mut trailer, ZIP64_VERSION);
+        put_u16(&mut trailer, ZIP64_VERSION);
+        put_u32(&mut trailer, 0); // disk number
+        put_u32(&mut trailer, 0); // disk with the central directory
+        put_u64(&mut trailer, 1); // entries on this disk
+        put_u64(&mut trailer, 1); // total entries
+        put_u64(&mut trailer, central_directory_len);
+        put_u64(&mut trailer, central_directory_offset);
+
+        put_u32(
+            &mut trailer,
+            ZIP64_END_OF_CENTRAL_DIRECTORY_LOCATOR_SIGNATURE,
+        );
+        put_u32(&mut trailer, 0); // disk with the zip64 end of central directory
+        put_u64(&mut trailer, zip64_end_offset);
+        put_u32(&mut trailer, 1); // total disks
+    }
+
+    put_u32(&mut trailer, END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+    put_u16(&mut trailer, 0); // disk number
+    put_u16(&mut trailer, 0); // disk with the central directory
+    put_u16(&mut trailer, 1); // entries on this disk
+    put_u16(&mut trailer, 1); // total entries
+    put_u32(&mut trailer, clamp_u32(central_directory_len));
+    put_u32(&mut trailer, clamp_u32(central_directory_offset));
+    put_u16(&mut trailer, 0); // comment length
+
+    (header, trailer)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn write_eif(contents: &[u8]) -> tempfile::TempDir {
+        let output_dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(output_dir.path().join(ENCLAVE_FILENAME), contents).unwrap();
+        output_dir
+    }
+
+    fn new_archive(output_path: &Path, format: UploadFormat) -> UploadArchive {
+        let digests = EifDigests::compute(output_path).unwrap();
+        UploadArchive::new(output_path, format, &digests).unwrap()
+    }
+
+    async fn read_archive(archive: &UploadArchive) -> Vec<u8> {
+        let mut contents = vec![];
+        archive
+            .part_reader(0, archive.len())
+            .await
+            .unwrap()
+            .read_to_end(&mut contents)
+            .await
+            .unwrap();
+        contents
+    }
+
+    fn unzip(contents: Vec<u8>) -> Vec<u8> {
+        let mut zip = zip::ZipArchive::new(std::io::Cursor::new(contents)).unwrap();
+        assert_eq!(zip.len(), 1);
+        let mut file = zip.by_name(ENCLAVE_FILENAME).unwrap();
+        let mut eif = vec![];
+        file.read_to_end(&mut eif).unwrap();
+        eif
+    }
+
+    #[tokio::test]
+    async fn test_streamed_zip_round_trip() {
+        let eif_contents = b"not really an eif".repeat(1024);
+        let output_dir = write_eif(&eif_contents);
+
+        let archive = new_archive(output_dir.path(), UploadFormat::Zip);
+        let contents = read_archive(&archive).await;
+        assert_eq!(contents.len() as u64, archive.len());
+        assert_eq!(unzip(contents), eif_contents);
+        // nothing but the EIF is written to the output directory
+        assert_eq!(std::fs::read_dir(output_dir.path()).unwrap().count(), 1);
+
+        // each upload attempt reads the archive from the start
+        assert_eq!(read_archive(&archive).await.len() as u64, archive.len());
+    }
+
+    #[tokio::test]
+    async fn test_part_readers_cover_archive() {
+        let eif_contents = b"not really an eif".repeat(1024);
+        let output_dir = write_eif(&eif_contents);
+
+        for format in [UploadFormat::Zip, UploadFormat::Zstd] {
+            let archive = new_archive(output_dir.path(), format);
+            let whole = read_archive(&archive).await;
+            // Parts which start and end inside the zip's header, EIF and trailer
+            let part_len = 37;
+            let mut parts = vec![];
+            for offset in (0..archive.len()).step_by(part_len) {
+                archive
+                    .part_reader(offset, part_len as u64)
+                    .await
+                    .unwrap()
+                    .read_to_end(&mut parts)
+                    .await
+                    .unwrap();
+            }
+            assert_eq!(parts, whole);
+        }
+    }
+
+    #[test]
+    fn test_remaining_from_is_bounded_to_part() {
+        let buffer = Bytes::from_static(b"0123456789");
+        assert_eq!(remaining_from(&buffer, 2, 3).into_inner(), "234");
+        assert_eq!(remaining_from(&buffer, 8, 5).into_inner(), "89");
+        assert!(remaining_from(&buffer, 12, 5).into_inner().is_empty());
+    }
+
+    #[test]
+    fn test_eif_digests() {
+        let eif_contents = b"not really an eif".repeat(1024 * 1024);
+        let output_dir = write_eif(&eif_contents);
+
+        let digests = EifDigests::compute(output_dir.path()).unwrap();
+        assert_eq!(digests.size_bytes, eif_contents.len() as u64);
+        assert_eq!(digests.sha256, hex::encode(Sha256::digest(&eif_contents)));
+        assert_eq!(digests.sha384, hex::encode(Sha384::digest(&eif_contents)));
+        assert_eq!(digests.crc32, crc32fast::hash(&eif_contents));
+    }
+
+    #[tokio::test]
+    async fn test_zip_rejects_changed_eif() {
+        let eif_contents = b"not really an eif".repeat(1024);
+        let output_dir = write_eif(&eif_contents);
+        let archive = new_archive(output_dir.path(), UploadFormat::Zip);
+
+        std::fs::write(
+            output_dir.path().join(ENCLAVE_FILENAME),
+            b"a different eif".repeat(1024),
+        )
+        .unwrap();
+        let err = match archive.part_reader(0, archive.len()).await {
+            Ok(_) => panic!("Expected the changed EIF to be rejected"),
+            Err(err) => err,
+        };
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_zip64_records_round_trip() {
+        let eif_contents = b"a small eif with zip64 records".to_vec();
+        let crc = crc32fast::hash(&eif_contents);
+        let (header, trailer) =
+            stored_zip_records(ENCLAVE_FILENAME, eif_contents.len() as u64, crc, true);
+        let contents = [header, eif_contents.clone(), trailer].concat();
+        assert_eq!(unzip(contents), eif_contents);
+    }
+
+    #[tokio::test]
+    async fn test_zstd_archive_round_trip() {
+        let eif_contents = b"not really an eif".repeat(1024);
+        let output_dir = write_eif(&eif_contents);
+
+        let archive = new_archive(output_dir.path(), UploadFormat::Zstd);
+        let contents = read_archive(&archive).await;
+        assert_eq!(contents.len() as u64, archive.len());
+        assert!(archive.len() < eif_contents.len() as u64);
+        assert_eq!(zstd::decode_all(contents.as_slice()).unwrap(), eif_contents);
+    }
+}
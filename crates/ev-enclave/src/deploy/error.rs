@@ -22,12 +22,26 @@ pub enum DeployError {
     ApiError(#[from] common::api::client::ApiError),
     #[error("Enclave failed to upload - {0}")]
     UploadError(String),
-    #[error("Could not read the size of the Enclave EIF file {0}")]
-    EifSizeReadError(std::io::Error),
+    #[error("Could not read the Enclave EIF file {0}")]
+    EifReadError(std::io::Error),
     #[error("Could not deploy Enclave to Evervault Infrastructure")]
     DeploymentError,
     #[error("[{0}] Operation timed out after {1} seconds")]
     TimeoutError(String, u64),
+    #[error("The PCRs of the Enclave built on Evervault don't match your local build, so attestations against your local PCRs will fail. Deployment {0} was cancelled before release.\n{1}")]
+    RemotePcrMismatch(String, String),
+    #[error(transparent)]
+    InvalidDeploymentIntent(#[from] crate::api::enclave::DeploymentIntentError),
+    #[error("The {0} deployment strategy can't be used - {1}")]
+    InvalidStrategy(crate::api::enclave::DeployStrategy, String),
+    #[error(transparent)]
+    JobError(#[from] crate::job::JobError),
+    #[error("Invalid expected PCRs {0}")]
+    InvalidExpectedPcrs(String),
+    #[error("The built Enclave doesn't match the expected PCRs, so it wasn't deployed.\n{0}")]
+    ExpectedPcrMismatch(String),
+    #[error("The {0} size isn't available to your team. Available sizes: {1}")]
+    UnknownSize(crate::config::EnclaveSize, String),
 }
 
 impl CliError for DeployError {
@@ -37,12 +51,17 @@ impl CliError for DeployError {
             Self::BuildError(build_err) => build_err.exitcode(),
             Self::EnclaveConfigError(config_err) => config_err.exitcode(),
             Self::FailedToAccessOutputDir(output_err) => output_err.exitcode(),
-            Self::IoError(_) | Self::ZipError(_) | Self::EifSizeReadError(_) => exitcode::IOERR,
+            Self::IoError(_) | Self::ZipError(_) | Self::EifReadError(_) => exitcode::IOERR,
             Self::RequestError(_)
             | Self::UploadError(_)
             | Self::DeploymentError
             | Self::TimeoutError(..) => exitcode::TEMPFAIL,
             Self::ApiError(api_err) => api_err.exitcode(),
+            Self::RemotePcrMismatch(..) | Self::ExpectedPcrMismatch(_) => exitcode::DATAERR,
+            Self::InvalidExpectedPcrs(_) => exitcode::CONFIG,
+            Self::InvalidStrategy(..) | Self::UnknownSize(..) => exitcode::CONFIG,
+            Self::InvalidDeploymentIntent(intent_err) => intent_err.exitcode(),
+            Self::JobError(job_err) => job_err.exitcode(),
         }
     }
 }
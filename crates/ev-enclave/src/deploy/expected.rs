@@ -0,0 +1,164 @@
+use super::error::DeployError;
+use crate::enclave::{PCRs, Pcr};
+use serde::Deserialize;
+use std::path::Path;
+
+/// PCRs a deployment has been approved to ship. Only the PCRs which are set are checked, so an
+/// approval can pin the image measurements without pinning the signing cert, or vice versa.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ExpectedPcrs {
+    #[serde(rename = "PCR0")]
+    pub pcr0: Option<Pcr>,
+    #[serde(rename = "PCR1")]
+    pub pcr1: Option<Pcr>,
+    #[serde(rename = "PCR2")]
+    pub pcr2: Option<Pcr>,
+    #[serde(rename = "PCR8")]
+    pub pcr8: Option<Pcr>,
+}
+
+// Files can hold the PCRs alone, or be the output of `ev enclave attest export`
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ExpectedPcrsFile {
+    TrustedConfig { pcrs: ExpectedPcrs },
+    Pcrs(ExpectedPcrs),
+}
+
+impl ExpectedPcrs {
+    pub fn from_file(path: &Path) -> Result<Self, DeployError> {
+        let invalid = |reason: String| {
+            DeployError::InvalidExpectedPcrs(format!("{} - {reason}", path.display()))
+        };
+        let contents = std::fs::read_to_string(path).map_err(|e| invalid(e.to_string()))?;
+        let expected = match serde_json::from_str(&contents).map_err(|e| invalid(e.to_string()))? {
+            ExpectedPcrsFile::TrustedConfig { pcrs } | ExpectedPcrsFile::Pcrs(pcrs) => pcrs,
+        };
+        if expected.is_empty() {
+            return Err(invalid("no PCRs were found in the file".to_string()));
+        }
+        Ok(expected)
+    }
+
+    /// PCRs set in `overrides` replace those in `self`
+    pub fn merge(self, overrides: Self) -> Self {
+        Self {
+            pcr0: overrides.pcr0.or(self.pcr0),
+            pcr1: overrides.pcr1.or(self.pcr1),
+            pcr2: overrides.pcr2.or(self.pcr2),
+            pcr8: overrides.pcr8.or(self.pcr8),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// Fails when any expected PCR differs from the built EIF's
+    pub fn verify(&self, built: &PCRs) -> Result<(), DeployError> {
+        let expected = [
+            self.pcr0.as_ref(),
+            self.pcr1.as_ref(),
+            self.pcr2.as_ref(),
+            self.pcr8.as_ref(),
+        ];
+        let differences: Vec<String> = expected
+            .into_iter()
+            .zip(built.named())
+            .filter_map(|(expected, (name, built))| {
+                let expected = expected?;
+                (Some(expected) != built).then(|| {
+                    format!(
+                        "  {name}: expected {expected}, built {}",
+                        built.map_or("none", |built| built.as_str())
+                    )
+                })
+            })
+            .collect();
+        if differences.is_empty() {
+            Ok(())
+        } else {
+            Err(DeployError::ExpectedPcrMismatch(differences.join("\n")))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::pcr;
+
+    fn built_pcrs() -> PCRs {
+        PCRs {
+            pcr0: pcr("aa"),
+            pcr1: pcr("bb"),
+            pcr2: pcr("cc"),
+            pcr8: None,
+        }
+    }
+
+    #[test]
+    fn test_verify_expected_pcrs() {
+        let expected = ExpectedPcrs {
+            pcr0: Some(pcr("AA")),
+            pcr2: Some(pcr("cc")),
+            ..Default::default()
+        };
+        assert!(expected.verify(&built_pcrs()).is_ok());
+
+        let expected = expected.merge(ExpectedPcrs {
+            pcr2: Some(pcr("dd")),
+            pcr8: Some(pcr("ee")),
+            ..Default::default()
+        });
+        let mismatch = format!(
+            "  PCR2: expected {}, built {}\n  PCR8: expected {}, built none",
+            "dd".repeat(48),
+            "cc".repeat(48),
+            "ee".repeat(48)
+        );
+        assert!(matches!(
+            expected.verify(&built_pcrs()),
+            Err(DeployError::ExpectedPcrMismatch(differences)) if differences == mismatch
+        ));
+    }
+
+    #[test]
+    fn test_expected_pcrs_from_file() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let pcrs_path = dir.path().join("pcrs.json");
+        let (pcr0, pcr1, pcr2) = ("AA".repeat(48), "bb".repeat(48), "cc".repeat(48));
+        std::fs::write(
+            &pcrs_path,
+            format!(r#"{{"PCR0": "{pcr0}", "PCR1": " {pcr1} "}}"#),
+        )
+        .unwrap();
+        let expected = ExpectedPcrs::from_file(&pcrs_path).unwrap();
+        assert_eq!(expected.pcr0, Some(pcr("aa")));
+        assert_eq!(expected.pcr1, Some(pcr("bb")));
+        assert!(expected.pcr2.is_none());
+
+        let export_path = dir.path().join("export.json");
+        std::fs::write(
+            &export_path,
+            format!(
+                r#"{{"name": "hello", "pcrs": {{"PCR0": "{pcr0}", "PCR1": "{pcr1}", "PCR2": "{pcr2}", "PCR8": null}}}}"#
+            ),
+        )
+        .unwrap();
+        let expected = ExpectedPcrs::from_file(&export_path).unwrap();
+        assert_eq!(expected.pcr2, Some(pcr("cc")));
+
+        std::fs::write(&pcrs_path, format!(r#"{{"PCR9": "{pcr0}"}}"#)).unwrap();
+        assert!(matches!(
+            ExpectedPcrs::from_file(&pcrs_path),
+            Err(DeployError::InvalidExpectedPcrs(_))
+        ));
+        std::fs::write(&pcrs_path, r#"{"PCR0": "aa00"}"#).unwrap();
+        assert!(matches!(
+            ExpectedPcrs::from_file(&pcrs_path),
+            Err(DeployError::InvalidExpectedPcrs(_))
+        ));
+    }
+}
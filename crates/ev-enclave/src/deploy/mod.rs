@@ -1,27 +1,155 @@
-use crate::api;
-use crate::api::{enclave::CreateEnclaveDeploymentIntentRequest, enclave::EnclaveApi};
+use crate::api::enclave::{
+    BuildStep, BuildStepStatus, CreateEnclaveDeploymentIntentRequest, DeployStrategy, EnclaveApi,
+    EnclaveScalingConfig, EnclaveSizeDefinition, UploadFormat,
+};
+use crate::build::runtime::RuntimeDigests;
 use crate::common::{resolve_output_path, OutputPath};
 use crate::config::ValidatedEnclaveBuildConfig;
 use crate::describe::describe_eif;
-use crate::enclave::{EIFMeasurements, ENCLAVE_FILENAME};
-use crate::progress::{get_tracker, poll_fn_and_report_status, ProgressLogger, StatusReport};
-use std::io::Write;
+use crate::enclave::{EIFMeasurements, NitroCliImage, PCRs};
+use crate::instrumentation::{self, Stage};
+use crate::progress::{
+    get_tracker, poll_fn_and_report_status, ProgressLogger, ProgressStep, StatusReport,
+};
+use common::api::compat::{self, Feature};
 use std::sync::Arc;
+mod archive;
 mod error;
+mod expected;
+mod upload;
 use crate::docker::command::get_git_hash;
 use crate::docker::command::get_source_date_epoch;
-use async_stream::__private::AsyncStream;
-use error::DeployError;
-use reqwest::Body;
-use std::path::Path;
-use tokio::fs::File;
+use archive::{EifDigests, UploadArchive};
+pub use error::DeployError;
+pub use expected::ExpectedPcrs;
 use tokio::time::timeout;
-use tokio_stream::StreamExt;
-use tokio_util::codec::{BytesCodec, FramedRead};
+pub use upload::{parse_rate_limit, UploadOptions, MAX_UPLOAD_CONCURRENCY};
 
-const ENCLAVE_ZIP_FILENAME: &str = "enclave.zip";
 pub const DEPLOY_WATCH_TIMEOUT_SECONDS: u64 = 1200; //15 minutes
 
+async fn warn_on_unregistered_signing_cert<T: EnclaveApi>(enclave_api: &T, pcr8: &str) {
+    match enclave_api.get_signing_certs().await {
+        Ok(response) => {
+            if let Some(warning) =
+                crate::cert::signing_cert_warning(&response.certs, pcr8, chrono::Utc::now())
+            {
+                log::warn!("{warning}");
+            }
+        }
+        Err(e) => log::debug!("Failed to retrieve registered signing certs - {e}"),
+    }
+}
+
+/// What to do when the PCRs of the Enclave built on Evervault differ from the local build
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum RemotePcrMismatch {
+    #[default]
+    Fail,
+    Warn,
+}
+
+// PCR8 is only compared when both builds were signed
+fn pcr_differences(local: &PCRs, remote: &PCRs) -> Vec<String> {
+    local
+        .differences(remote)
+        .into_iter()
+        .filter_map(|difference| match (difference.left, difference.right) {
+            (Some(local), Some(remote)) => Some(format!(
+                "  {}: local {local}, remote {remote}",
+                difference.name
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+async fn verify_remote_pcrs<T: EnclaveApi>(
+    enclave_api: &T,
+    enclave_uuid: &str,
+    deployment_uuid: &str,
+    local_pcrs: &PCRs,
+    on_mismatch: RemotePcrMismatch,
+) -> Result<(), DeployError> {
+    let deployment = enclave_api
+        .get_enclave_deployment_by_uuid(enclave_uuid, deployment_uuid)
+        .await?;
+    let Some(remote_pcrs) = deployment.enclave_version.pcrs.as_ref() else {
+        log::debug!("The remote build didn't report its PCRs, skipping verification");
+        return Ok(());
+    };
+
+    let differences = pcr_differences(local_pcrs, remote_pcrs);
+    if differences.is_empty() {
+        log::info!("Verified the PCRs of the Enclave built on Evervault match your local build.");
+        return Ok(());
+    }
+    let differences = differences.join("\n");
+    match on_mismatch {
+        RemotePcrMismatch::Fail => {
+            // The deployment already exists, so it's cancelled to stop the mismatched build rolling out
+            if let Err(e) = enclave_api
+                .cancel_deployment(enclave_uuid, deployment_uuid)
+                .await
+            {
+                log::error!("Failed to cancel deployment {deployment_uuid} - {e}. Cancel it with ev enclave deployments cancel {deployment_uuid} before it's released.");
+            }
+            Err(DeployError::RemotePcrMismatch(
+                deployment_uuid.to_string(),
+                differences,
+            ))
+        }
+        RemotePcrMismatch::Warn => {
+            log::warn!("The PCRs of the Enclave built on Evervault don't match your local build. Clients must attest against the remote PCRs.\n{differences}");
+            Ok(())
+        }
+    }
+}
+
+/// Checks the Enclave's replica count allows the strategy to roll over without dropping
+/// connections. `desired_replicas` is the count the deployment will apply.
+pub fn validate_strategy(
+    strategy: DeployStrategy,
+    desired_replicas: u32,
+    scaling_config: Option<&EnclaveScalingConfig>,
+) -> Result<(), DeployError> {
+    match strategy {
+        DeployStrategy::Rolling if desired_replicas < 2 => Err(DeployError::InvalidStrategy(
+            strategy,
+            format!("it needs at least 2 replicas to keep serving traffic while each one is replaced, but the Enclave has {desired_replicas}. Set [scaling] desired_replicas in your enclave.toml, or use --strategy blue-green."),
+        )),
+        DeployStrategy::BlueGreen => match scaling_config {
+            Some(config) if config.available_instances() < desired_replicas => {
+                Err(DeployError::InvalidStrategy(
+                    strategy,
+                    format!("it starts {desired_replicas} new replicas alongside the old ones, but only {} more instances are available to your team.", config.available_instances()),
+                ))
+            }
+            _ => Ok(()),
+        },
+        DeployStrategy::Rolling => Ok(()),
+    }
+}
+
+/// Resolves the config's size into the resources it reserves, so an unknown size fails before
+/// the build rather than after the upload
+pub async fn resolve_size<T: EnclaveApi>(
+    enclave_api: &T,
+    validated_config: &ValidatedEnclaveBuildConfig,
+) -> Result<Option<EnclaveSizeDefinition>, DeployError> {
+    let Some(size) = validated_config.size() else {
+        return Ok(None);
+    };
+    let sizes = enclave_api.get_enclave_sizes().await?;
+    match sizes.resolve(size) {
+        Some(definition) => Ok(Some(definition.clone())),
+        None => {
+            let available: Vec<&str> = sizes.sizes.iter().map(|s| s.size.as_str()).collect();
+            Err(DeployError::UnknownSize(size, available.join(", ")))
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub async fn deploy_eif<T: EnclaveApi + Clone>(
     validated_config: &ValidatedEnclaveBuildConfig,
     enclave_api: T,
@@ -29,32 +157,41 @@ pub async fn deploy_eif<T: EnclaveApi + Clone>(
     eif_measurements: &EIFMeasurements,
     data_plane_version: String,
     installer_version: String,
-) -> Result<(), DeployError> {
-    let progress_bar = get_tracker("Zipping Enclave...", None);
-    create_zip_archive_for_eif(output_path.path())?;
-    progress_bar.finish_with_message("Enclave zipped.");
-
-    let zip_path = output_path.path().join(ENCLAVE_ZIP_FILENAME);
-    let zip_file = File::open(&zip_path).await?;
-    let zip_len_bytes = zip_file.metadata().await?.len();
-    let zip_upload_stream = create_zip_upload_stream(zip_file, zip_len_bytes);
+    runtime_digests: Option<&RuntimeDigests>,
+    on_pcr_mismatch: RemotePcrMismatch,
+    strategy: Option<DeployStrategy>,
+    resources: Option<&EnclaveSizeDefinition>,
+    upload_options: UploadOptions,
+) -> Result<String, DeployError> {
+    let eif_digests = EifDigests::compute(output_path.path()).map_err(DeployError::EifReadError)?;
+
+    if let Some(next) = validated_config.signing_info().next.as_ref() {
+        log::info!(
+            "Including upcoming signing key rotation in deployment. Next PCR8: {}",
+            next.pcr8
+        );
+    }
 
-    let eif_size_bytes = get_eif_size_bytes(output_path.path()).await?;
+    if let Some(pcr8) = eif_measurements.pcrs().pcr8.as_deref() {
+        warn_on_unregistered_signing_cert(&enclave_api, pcr8).await;
+    }
 
-    let enclave_deployment_intent_payload = CreateEnclaveDeploymentIntentRequest::new(
-        eif_measurements.pcrs(),
-        validated_config.clone(),
-        eif_size_bytes,
-        data_plane_version,
-        installer_version,
-        get_source_date_epoch(),
-        get_git_hash(),
-        validated_config
-            .scaling
-            .as_ref()
-            .map(|config| config.desired_replicas),
-        eif_measurements.signature().map(String::from),
-    );
+    // APIs without multipart uploads are sent the EIF in one request instead
+    let upload_parts = upload_options
+        .parts()
+        .filter(|_| compat::is_supported(Feature::MultipartUploads));
+    let enclave_deployment_intent_payload =
+        CreateEnclaveDeploymentIntentRequest::builder(validated_config, eif_measurements.pcrs())
+            .eif_size_bytes(eif_digests.size_bytes)
+            .eif_digests(&eif_digests.sha256, &eif_digests.sha384)
+            .runtime_versions(data_plane_version, installer_version)
+            .runtime_digests(runtime_digests)
+            .git_metadata(get_git_hash(), get_source_date_epoch())
+            .pcrs_signature(eif_measurements.signature().map(String::from))
+            .strategy(strategy)
+            .resources(resources)
+            .upload_parts(upload_parts)
+            .build()?;
 
     let deployment_intent = enclave_api
         .create_enclave_deployment_intent(
@@ -63,32 +200,40 @@ pub async fn deploy_eif<T: EnclaveApi + Clone>(
         )
         .await?;
 
-    let s3_upload_url = deployment_intent.signed_url();
-    let reqwest_client = api::Client::builder().build().unwrap();
-    let s3_response = reqwest_client
-        .put(s3_upload_url)
-        .header("Content-Type", "application/zip")
-        .header("Content-Length", zip_len_bytes)
-        .body(Body::wrap_stream(zip_upload_stream))
-        .send()
-        .await?;
-
-    tokio::fs::remove_file(zip_path).await?;
-
-    if s3_response.status().is_success() {
-        log::info!("Enclave uploaded to Evervault.");
-    } else {
-        return Err(DeployError::UploadError(s3_response.text().await?));
+    let upload_format = deployment_intent.upload_format();
+    let archive = match upload_format {
+        UploadFormat::Zip => {
+            let progress_bar = get_tracker("Zipping Enclave...", None);
+            let archive = UploadArchive::new(output_path.path(), upload_format, &eif_digests)?;
+            progress_bar.finish_with_message("Enclave zipped.");
+            archive
+        }
+        UploadFormat::Zstd => {
+            let progress_bar = get_tracker("Compressing Enclave...", None);
+            let archive = UploadArchive::new(output_path.path(), upload_format, &eif_digests)?;
+            progress_bar.finish_with_message("Enclave compressed.");
+            archive
+        }
     };
 
+    instrumentation::time_stage_async(
+        Stage::Upload,
+        upload::upload_archive(&enclave_api, &deployment_intent, &archive, upload_options),
+    )
+    .await?;
+    log::info!("Enclave uploaded to Evervault.");
+
     let progress_bar_for_build =
         get_tracker("Building Enclave Docker Image on Evervault Infra...", None);
 
-    let build_complete = watch_build(
-        enclave_api.clone(),
-        deployment_intent.enclave_uuid(),
-        deployment_intent.deployment_uuid(),
-        progress_bar_for_build,
+    let build_complete = instrumentation::time_stage_async(
+        Stage::RemoteBuild,
+        watch_build(
+            enclave_api.clone(),
+            deployment_intent.enclave_uuid(),
+            deployment_intent.deployment_uuid(),
+            progress_bar_for_build,
+        ),
     )
     .await?;
 
@@ -96,19 +241,34 @@ pub async fn deploy_eif<T: EnclaveApi + Clone>(
         return Err(DeployError::DeploymentError);
     }
 
+    verify_remote_pcrs(
+        &enclave_api,
+        deployment_intent.enclave_uuid(),
+        deployment_intent.deployment_uuid(),
+        eif_measurements.pcrs(),
+        on_pcr_mismatch,
+    )
+    .await?;
+
+    if let Some(strategy) = strategy {
+        log::info!("Replacing the current replicas with a {strategy} deployment.");
+    }
     let progress_bar_for_deploy = get_tracker(
         "Deploying Enclave into a Trusted Execution Environment...",
         None,
     );
 
-    let deployment_complete = timed_operation(
-        "Enclave Deployment",
-        DEPLOY_WATCH_TIMEOUT_SECONDS,
-        watch_deployment(
-            enclave_api,
-            deployment_intent.enclave_uuid(),
-            deployment_intent.deployment_uuid(),
-            progress_bar_for_deploy,
+    let deployment_complete = instrumentation::time_stage_async(
+        Stage::Deploy,
+        timed_operation(
+            "Enclave Deployment",
+            DEPLOY_WATCH_TIMEOUT_SECONDS,
+            watch_deployment(
+                enclave_api.clone(),
+                deployment_intent.enclave_uuid(),
+                deployment_intent.deployment_uuid(),
+                progress_bar_for_deploy,
+            ),
         ),
     )
     .await??;
@@ -117,9 +277,69 @@ pub async fn deploy_eif<T: EnclaveApi + Clone>(
         return Err(DeployError::DeploymentError);
     }
 
+    if validated_config.enclave_type().is_job() {
+        watch_deployed_job(
+            enclave_api,
+            deployment_intent.enclave_uuid(),
+            deployment_intent.deployment_uuid(),
+        )
+        .await?;
+    }
+
+    Ok(deployment_intent.deployment_uuid().to_string())
+}
+
+// Deploying a job Enclave starts its first execution, which is watched until the job exits
+async fn watch_deployed_job<T: EnclaveApi>(
+    enclave_api: T,
+    enclave_uuid: &str,
+    deployment_uuid: &str,
+) -> Result<(), DeployError> {
+    let deployment = enclave_api
+        .get_enclave_deployment_by_uuid(enclave_uuid, deployment_uuid)
+        .await?;
+    let Some(job_execution) = deployment.job_execution else {
+        log::info!(
+            "No job execution was started by this deployment. Run the job with ev enclave run-job."
+        );
+        return Ok(());
+    };
+
+    let progress_bar = get_tracker("Running job...", None);
+    crate::job::watch_job(enclave_api, enclave_uuid, &job_execution.uuid, progress_bar).await?;
     Ok(())
 }
 
+fn to_progress_step(step: &BuildStep) -> ProgressStep {
+    ProgressStep::new(
+        step.name.clone(),
+        step.status.to_string(),
+        step.started_at.clone(),
+        step.completed_at.clone(),
+    )
+}
+
+// Older API versions don't report queue positions, so a failed lookup leaves the status as it was
+async fn queue_status_report<T: EnclaveApi>(
+    enclave_api: &T,
+    enclave_uuid: &str,
+    deployment_uuid: &str,
+) -> StatusReport {
+    match enclave_api
+        .get_deployment_queue_position(enclave_uuid, deployment_uuid)
+        .await
+    {
+        Ok(queue_position) => queue_position
+            .describe()
+            .map(StatusReport::update)
+            .unwrap_or_else(StatusReport::no_op),
+        Err(e) => {
+            log::debug!("Failed to get the queue position of deployment {deployment_uuid} - {e}");
+            StatusReport::no_op()
+        }
+    }
+}
+
 async fn watch_build<T: EnclaveApi>(
     enclave_api: T,
     enclave_uuid: &str,
@@ -135,20 +355,33 @@ async fn watch_build<T: EnclaveApi>(
         let deployment_response = enclave_api
             .get_enclave_deployment_by_uuid(enclave_uuid, deployment_uuid)
             .await?;
-        if deployment_response.is_built() {
-            Ok(StatusReport::complete(
-                "Enclave built on Evervault!".to_string(),
-            ))
+        let build_steps = &deployment_response.enclave_version.build_steps;
+        let report = if deployment_response.is_built() {
+            StatusReport::complete("Enclave built on Evervault!".to_string())
         } else if deployment_response.is_failed() {
             let failure_msg = deployment_response
                 .get_failure_reason()
                 .unwrap_or_else(|| "An unknown error occurred".into());
-            Ok(StatusReport::Failed(format!(
-                "Enclave build failed - {failure_msg}"
-            )))
+            StatusReport::Failed(format!("Enclave build failed - {failure_msg}"))
+        } else if deployment_response.is_cancelled() {
+            StatusReport::Failed(format!("Deployment {deployment_uuid} was cancelled"))
+        } else if deployment_response.is_queued() {
+            queue_status_report(enclave_api.as_ref(), enclave_uuid, deployment_uuid).await
         } else {
-            Ok(StatusReport::no_op())
-        }
+            build_steps
+                .iter()
+                .position(|step| step.status == BuildStepStatus::Running)
+                .map(|index| {
+                    StatusReport::update(format!(
+                        "Building Enclave on Evervault Infra - {} (step {} of {})...",
+                        build_steps[index].name,
+                        index + 1,
+                        build_steps.len()
+                    ))
+                })
+                .unwrap_or_else(StatusReport::no_op)
+        };
+        Ok(report.with_steps(build_steps.iter().map(to_progress_step).collect()))
     }
 
     let get_deployment_args = vec![enclave_uuid.to_string(), deployment_uuid.to_string()];
@@ -179,6 +412,10 @@ pub async fn watch_deployment<T: EnclaveApi>(
 
         if deployment_response.is_finished() {
             Ok(StatusReport::complete("Enclave deployed!".to_string()))
+        } else if deployment_response.is_cancelled() {
+            Ok(StatusReport::Failed(format!(
+                "Deployment {deployment_uuid} was cancelled"
+            )))
         } else if deployment_response.is_failed() {
             let failure_msg = deployment_response
                 .get_failure_reason()
@@ -186,6 +423,8 @@ pub async fn watch_deployment<T: EnclaveApi>(
             Ok(StatusReport::Failed(format!(
                 "Enclave deployment failed - {failure_msg}"
             )))
+        } else if let Some(rollover) = deployment_response.get_replica_rollover() {
+            Ok(StatusReport::update(rollover.describe()))
         } else {
             let status_report = match deployment_response.get_detailed_status() {
                 Some(status) => StatusReport::update(status),
@@ -205,69 +444,19 @@ pub async fn watch_deployment<T: EnclaveApi>(
     .await
 }
 
-fn create_zip_archive_for_eif(output_path: &std::path::Path) -> zip::result::ZipResult<()> {
-    let zip_path = output_path.join(ENCLAVE_ZIP_FILENAME);
-    let zip_file = if !zip_path.exists() {
-        std::fs::File::create(&zip_path)?
-    } else {
-        std::fs::OpenOptions::new()
-            .read(true)
-            .write(true)
-            .open(&zip_path)?
-    };
-
-    let mut zip = zip::ZipWriter::new(zip_file);
-
-    let zip_opts =
-        zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
-
-    let eif_path = output_path.join(ENCLAVE_FILENAME);
-    zip.start_file(ENCLAVE_FILENAME, zip_opts)?;
-    let eif = std::fs::read(eif_path)?;
-    zip.write_all(eif.as_slice())?;
-
-    let _ = zip.finish()?;
-
-    Ok(())
-}
-
-fn create_zip_upload_stream(
-    zip_file: File,
-    zip_len_bytes: u64,
-) -> AsyncStream<Result<bytes::BytesMut, std::io::Error>, impl core::future::Future<Output = ()>> {
-    let mut stream = FramedRead::new(zip_file, BytesCodec::new());
-    let progress_bar = get_tracker("Uploading Enclave to Evervault", Some(zip_len_bytes));
-    async_stream::stream! {
-        let mut bytes_sent = 0;
-        while let Some(bytes) = stream.next().await {
-            progress_bar.set_position(bytes_sent);
-            if let Ok(bytes) = &bytes {
-                bytes_sent += bytes.len() as u64;
-            }
-            yield bytes;
-        }
-    }
-}
-
 pub fn get_eif<S: AsRef<str>>(
     eif_path: S,
+    nitro_cli: &NitroCliImage,
     verbose: bool,
     no_cache: bool,
 ) -> Result<(EIFMeasurements, OutputPath), DeployError> {
-    let eif = describe_eif(eif_path.as_ref(), verbose, no_cache)?;
+    let eif = describe_eif(eif_path.as_ref(), nitro_cli, verbose, no_cache)?;
     let output_path = resolve_output_path(None::<&str>)?;
     let output_p = format!("{}/enclave.eif", output_path.path().to_str().unwrap());
     std::fs::copy(eif_path.as_ref(), output_p)?;
     Ok((eif.measurements.measurements, output_path))
 }
 
-async fn get_eif_size_bytes(output_path: &Path) -> Result<u64, DeployError> {
-    match tokio::fs::metadata(output_path.join(ENCLAVE_FILENAME)).await {
-        Ok(metadata) => Ok(metadata.len()),
-        Err(err) => Err(DeployError::EifSizeReadError(err)),
-    }
-}
-
 pub async fn timed_operation<T: std::future::Future>(
     operation_name: &str,
     max_timeout_seconds: u64,
@@ -288,7 +477,9 @@ pub async fn timed_operation<T: std::future::Future>(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::api;
     use crate::api::enclave::MockEnclaveApi;
+    use crate::config::EnclaveSize;
     use crate::enclave::PCRs;
     use crate::progress::NonTty;
     use crate::test_utils;
@@ -362,6 +553,48 @@ mod tests {
         assert_eq!(correct_result, true);
     }
 
+    #[test]
+    fn test_validate_strategy() {
+        let scaling_config: EnclaveScalingConfig = serde_json::from_str(
+            r#"{"limits":{"maxInstances":10,"availableInstances":2},"config":{"desiredReplicas":3}}"#,
+        )
+        .unwrap();
+        assert!(validate_strategy(DeployStrategy::Rolling, 3, Some(&scaling_config)).is_ok());
+        assert!(matches!(
+            validate_strategy(DeployStrategy::Rolling, 1, None),
+            Err(DeployError::InvalidStrategy(DeployStrategy::Rolling, _))
+        ));
+        assert!(validate_strategy(DeployStrategy::BlueGreen, 2, Some(&scaling_config)).is_ok());
+        assert!(validate_strategy(DeployStrategy::BlueGreen, 3, None).is_ok());
+        assert!(matches!(
+            validate_strategy(DeployStrategy::BlueGreen, 3, Some(&scaling_config)),
+            Err(DeployError::InvalidStrategy(DeployStrategy::BlueGreen, _))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_resolve_size() {
+        let mut config = crate::build::test::get_config(false);
+        let mock_api = MockEnclaveApi::new();
+        assert!(resolve_size(&mock_api, &config).await.unwrap().is_none());
+
+        let mut mock_api = MockEnclaveApi::new();
+        mock_api.expect_get_enclave_sizes().times(2).returning(|| {
+            let sizes =
+                serde_json::from_str(r#"{"sizes":[{"size":"small","cpus":2,"memoryMib":4096}]}"#)
+                    .unwrap();
+            Box::pin(std::future::ready(Ok(sizes)))
+        });
+        config.size = Some(EnclaveSize::Small);
+        let resources = resolve_size(&mock_api, &config).await.unwrap().unwrap();
+        assert_eq!(resources.cpus, 2);
+        config.size = Some(EnclaveSize::Large);
+        assert!(matches!(
+            resolve_size(&mock_api, &config).await,
+            Err(DeployError::UnknownSize(EnclaveSize::Large, available)) if available == "small"
+        ));
+    }
+
     #[tokio::test]
     async fn test_watch_build() {
         let mut mock_api = MockEnclaveApi::new();
@@ -393,12 +626,147 @@ mod tests {
             .times(3)
             .returning(move |_, _| Box::pin(std::future::ready(Ok(responses.next().unwrap()))));
 
-        let result = watch_build(mock_api, "".into(), "".into(), NonTty)
+        let result = watch_build(mock_api, "".into(), "".into(), NonTty::default())
             .await
             .unwrap();
         assert!(result);
     }
 
+    #[tokio::test]
+    async fn test_watch_build_reports_queue_position() {
+        let mut mock_api = MockEnclaveApi::new();
+        let mut responses = vec![
+            test_utils::build_get_enclave_deployment(
+                api::enclave::BuildStatus::Pending,
+                api::enclave::DeployStatus::Pending,
+                None,
+                None,
+            ),
+            test_utils::build_get_enclave_deployment(
+                api::enclave::BuildStatus::Cancelled,
+                api::enclave::DeployStatus::Pending,
+                None,
+                None,
+            ),
+        ]
+        .into_iter();
+        mock_api
+            .expect_get_enclave_deployment_by_uuid()
+            .times(2)
+            .returning(move |_, _| Box::pin(std::future::ready(Ok(responses.next().unwrap()))));
+        mock_api
+            .expect_get_deployment_queue_position()
+            .times(1)
+            .returning(|_, _| {
+                Box::pin(std::future::ready(Ok(
+                    api::enclave::DeploymentQueuePosition {
+                        position: Some(3),
+                        queue_length: 7,
+                        blocking_deployment_uuid: Some("deployment_123".into()),
+                    },
+                )))
+            });
+
+        let result = watch_build(mock_api, "", "deployment_456", NonTty::default())
+            .await
+            .unwrap();
+        assert!(!result);
+    }
+
+    #[derive(Clone, Default)]
+    struct RecordingTracker {
+        steps: Arc<std::sync::Mutex<Vec<String>>>,
+    }
+
+    impl ProgressLogger for RecordingTracker {
+        fn set_message(&self, _message: &str) {}
+        fn finish_with_message(&self, _message: &str) {}
+        fn set_position(&self, _bytes: u64) {}
+        fn finish(&self) {}
+        fn report_step(&self, step: &ProgressStep) {
+            self.steps.lock().unwrap().push(step.describe());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_watch_build_reports_steps() {
+        let step = |name: &str, status, started_at: Option<&str>, completed_at: Option<&str>| {
+            api::enclave::BuildStep {
+                name: name.into(),
+                status,
+                started_at: started_at.map(String::from),
+                completed_at: completed_at.map(String::from),
+            }
+        };
+        let with_steps = |build_status, steps| {
+            let mut response = test_utils::build_get_enclave_deployment(
+                build_status,
+                api::enclave::DeployStatus::Pending,
+                None,
+                None,
+            );
+            response.enclave_version.build_steps = steps;
+            response
+        };
+        let mut responses = vec![
+            with_steps(
+                api::enclave::BuildStatus::Building,
+                vec![
+                    step(
+                        "queue",
+                        BuildStepStatus::Complete,
+                        Some("2024-01-01T00:00:00Z"),
+                        Some("2024-01-01T00:00:03Z"),
+                    ),
+                    step(
+                        "build",
+                        BuildStepStatus::Running,
+                        Some("2024-01-01T00:00:03Z"),
+                        None,
+                    ),
+                ],
+            ),
+            with_steps(
+                api::enclave::BuildStatus::Ready,
+                vec![
+                    step(
+                        "queue",
+                        BuildStepStatus::Complete,
+                        Some("2024-01-01T00:00:00Z"),
+                        Some("2024-01-01T00:00:03Z"),
+                    ),
+                    step(
+                        "build",
+                        BuildStepStatus::Complete,
+                        Some("2024-01-01T00:00:03Z"),
+                        Some("2024-01-01T00:01:05Z"),
+                    ),
+                ],
+            ),
+        ]
+        .into_iter();
+
+        let mut mock_api = MockEnclaveApi::new();
+        mock_api
+            .expect_get_enclave_deployment_by_uuid()
+            .times(2)
+            .returning(move |_, _| Box::pin(std::future::ready(Ok(responses.next().unwrap()))));
+
+        let tracker = RecordingTracker::default();
+        let result = watch_build(mock_api, "", "", tracker.clone())
+            .await
+            .unwrap();
+        assert!(result);
+        assert_eq!(
+            *tracker.steps.lock().unwrap(),
+            vec![
+                "queue complete in 3.0s".to_string(),
+                "build running".to_string(),
+                "build complete in 1m 2s".to_string(),
+            ]
+        );
+    }
+
     #[tokio::test]
     async fn test_watch_failed_build() {
         let mut mock_api = MockEnclaveApi::new();
@@ -430,7 +798,7 @@ mod tests {
             .times(3)
             .returning(move |_, _| Box::pin(std::future::ready(Ok(responses.next().unwrap()))));
 
-        let result = watch_build(mock_api, "".into(), "".into(), NonTty)
+        let result = watch_build(mock_api, "".into(), "".into(), NonTty::default())
             .await
             .unwrap();
         assert_eq!(result, false);
@@ -467,7 +835,7 @@ mod tests {
             .times(3)
             .returning(move |_, _| Box::pin(std::future::ready(Ok(responses.next().unwrap()))));
 
-        let result = watch_deployment(mock_api, "".into(), "".into(), NonTty)
+        let result = watch_deployment(mock_api, "".into(), "".into(), NonTty::default())
             .await
             .unwrap();
         assert!(result);
@@ -504,9 +872,77 @@ mod tests {
             .times(3)
             .returning(move |_, _| Box::pin(std::future::ready(Ok(responses.next().unwrap()))));
 
-        let result = watch_deployment(mock_api, "".into(), "".into(), NonTty)
+        let result = watch_deployment(mock_api, "".into(), "".into(), NonTty::default())
             .await
             .unwrap();
         assert_eq!(result, false);
     }
+
+    #[tokio::test]
+    async fn test_verify_remote_pcrs() {
+        let local_pcrs = PCRs {
+            pcr0: test_utils::pcr("00"),
+            pcr1: test_utils::pcr("11"),
+            pcr2: test_utils::pcr("22"),
+            pcr8: Some(test_utils::pcr("88")),
+        };
+        let mut remote_deployment = test_utils::build_get_enclave_deployment(
+            api::enclave::BuildStatus::Ready,
+            api::enclave::DeployStatus::Pending,
+            None,
+            None,
+        );
+        remote_deployment.enclave_version.pcrs = Some(PCRs {
+            pcr0: test_utils::pcr("ff"),
+            pcr8: None,
+            ..local_pcrs.clone()
+        });
+
+        let cancelled_deployment = remote_deployment.deployment.clone();
+        let mut mock_api = MockEnclaveApi::new();
+        mock_api
+            .expect_get_enclave_deployment_by_uuid()
+            .times(2)
+            .returning(move |_, _| Box::pin(std::future::ready(Ok(remote_deployment.clone()))));
+        mock_api
+            .expect_cancel_deployment()
+            .withf(|enclave_uuid, deployment_uuid| {
+                enclave_uuid == "enclave_123" && deployment_uuid == "deployment_456"
+            })
+            .times(1)
+            .returning(move |_, _| Box::pin(std::future::ready(Ok(cancelled_deployment.clone()))));
+
+        let result = verify_remote_pcrs(
+            &mock_api,
+            "enclave_123",
+            "deployment_456",
+            &local_pcrs,
+            RemotePcrMismatch::Fail,
+        )
+        .await;
+        match result {
+            Err(DeployError::RemotePcrMismatch(deployment_uuid, differences)) => {
+                assert_eq!(deployment_uuid, "deployment_456");
+                assert_eq!(
+                    differences,
+                    format!(
+                        "  PCR0: local {}, remote {}",
+                        "00".repeat(48),
+                        "ff".repeat(48)
+                    )
+                );
+            }
+            other => panic!("Expected a PCR mismatch, got {other:?}"),
+        }
+
+        assert!(verify_remote_pcrs(
+            &mock_api,
+            "enclave_123",
+            "deployment_456",
+            &local_pcrs,
+            RemotePcrMismatch::Warn,
+        )
+        .await
+        .is_ok());
+    }
 }
@@ -0,0 +1,406 @@
+use super::archive::UploadArchive;
+use super::error::DeployError;
+use crate::api;
+use crate::api::enclave::{
+    CompleteDeploymentUploadRequest, CreateEnclaveDeploymentIntentResponse, EnclaveApi,
+    MultipartUpload, UploadedPart,
+};
+use crate::progress::{get_tracker, ProgressLogger};
+use async_stream::__private::AsyncStream;
+use futures::{StreamExt as _, TryStreamExt};
+use reqwest::Body;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::AsyncRead;
+use tokio_stream::StreamExt;
+use tokio_util::codec::{BytesCodec, FramedRead};
+
+// Uploads which fail to connect or get a server error are retried with a fresh archive reader
+const MAX_UPLOAD_ATTEMPTS: u32 = 3;
+/// Most parts an archive can be uploaded in at once
+pub const MAX_UPLOAD_CONCURRENCY: u32 = 16;
+// S3 rejects parts smaller than this, other than the last
+const MIN_PART_LEN: u64 = 5 * 1024 * 1024;
+
+/// How the archive is sent to Evervault. Constrained uplinks can cap the upload's rate, and fast
+/// ones can upload parts of the archive concurrently.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UploadOptions {
+    /// Most bytes per second to send, across every part
+    pub rate_limit: Option<u64>,
+    /// Parts to upload at once
+    pub concurrency: u32,
+}
+
+impl Default for UploadOptions {
+    fn default() -> Self {
+        Self {
+            rate_limit: None,
+            concurrency: 1,
+        }
+    }
+}
+
+impl UploadOptions {
+    /// Parts to request signed urls for in the deployment intent, when the upload is split
+    pub fn parts(&self) -> Option<u32> {
+        (self.concurrency > 1).then_some(self.concurrency)
+    }
+}
+
+/// Parses a rate in bytes per second, with an optional K, M or G suffix, such as 500K or 10M
+pub fn parse_rate_limit(rate: &str) -> Result<u64, String> {
+    let trimmed = rate.trim().trim_end_matches(['B', 'b']);
+    let (digits, multiplier) = match trimmed.char_indices().last() {
+        Some((index, 'K' | 'k')) => (&trimmed[..index], 1024),
+        Some((index, 'M' | 'm')) => (&trimmed[..index], 1024 * 1024),
+        Some((index, 'G' | 'g')) => (&trimmed[..index], 1024 * 1024 * 1024),
+        _ => (trimmed, 1),
+    };
+    match digits
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .and_then(|value| value.checked_mul(multiplier))
+    {
+        Some(0) | None => Err(format!(
+            "{rate} is not a valid rate. Use bytes per second, such as 500K, 10M or 1G."
+        )),
+        Some(bytes) => Ok(bytes),
+    }
+}
+
+// Each part is at least MIN_PART_LEN, so small archives use fewer parts than were requested
+fn part_len(archive_len: u64, parts: usize) -> u64 {
+    archive_len.div_ceil(parts.max(1) as u64).max(MIN_PART_LEN)
+}
+
+// The bytes sent by an upload, shared by its parts so the progress and rate limit cover all of them
+struct Transfer {
+    progress_bar: Box<dyn ProgressLogger + Send + Sync>,
+    len: u64,
+    sent: AtomicU64,
+    started: Instant,
+    rate_limit: Option<u64>,
+}
+
+impl Transfer {
+    fn new(len: u64, rate_limit: Option<u64>) -> Arc<Self> {
+        Arc::new(Self {
+            progress_bar: get_tracker("Uploading Enclave to Evervault", Some(len)),
+            len,
+            sent: AtomicU64::new(0),
+            started: Instant::now(),
+            rate_limit,
+        })
+    }
+
+    // Retried parts are counted again, so the position is capped at the archive's length
+    async fn record(&self, bytes: u64) {
+        let sent = self.sent.fetch_add(bytes, Ordering::Relaxed) + bytes;
+        self.progress_bar.set_position(sent.min(self.len));
+        if let Some(rate_limit) = self.rate_limit {
+            let due = Duration::from_secs_f64(sent as f64 / rate_limit as f64);
+            if let Some(wait) = due.checked_sub(self.started.elapsed()) {
+                tokio::time::sleep(wait).await;
+            }
+        }
+    }
+}
+
+/// Uploads the archive to the deployment intent's signed url, or in parts when the API returned a
+/// multipart upload
+pub async fn upload_archive<T: EnclaveApi>(
+    enclave_api: &T,
+    deployment_intent: &CreateEnclaveDeploymentIntentResponse,
+    archive: &UploadArchive,
+    options: UploadOptions,
+) -> Result<(), DeployError> {
+    if let Some(multipart_upload) = deployment_intent.multipart_upload() {
+        return upload_parts(
+            enclave_api,
+            deployment_intent,
+            multipart_upload,
+            archive,
+            options,
+        )
+        .await;
+    }
+    if options.parts().is_some() {
+        log::debug!("Multipart uploads aren't supported by the API, uploading the Enclave in a single request");
+    }
+
+    let reqwest_client = api::Client::builder().build().unwrap();
+    let response = put_with_retries(
+        &reqwest_client,
+        deployment_intent.signed_url(),
+        Some(deployment_intent.upload_format().content_type()),
+        archive,
+        (0, archive.len()),
+        || Transfer::new(archive.len(), options.rate_limit),
+    )
+    .await?;
+    if !response.status().is_success() {
+        return Err(DeployError::UploadError(response.text().await?));
+    }
+    Ok(())
+}
+
+async fn upload_parts<T: EnclaveApi>(
+    enclave_api: &T,
+    deployment_intent: &CreateEnclaveDeploymentIntentResponse,
+    multipart_upload: &MultipartUpload,
+    archive: &UploadArchive,
+    options: UploadOptions,
+) -> Result<(), DeployError> {
+    let reqwest_client = api::Client::builder().build().unwrap();
+    let transfer = Transfer::new(archive.len(), options.rate_limit);
+    let part_len = part_len(archive.len(), multipart_upload.part_urls.len());
+    let mut uploads = vec![];
+    for (index, part_url) in multipart_upload.part_urls.iter().enumerate() {
+        let offset = index as u64 * part_len;
+        if offset >= archive.len() {
+            break;
+        }
+        uploads.push(upload_part(
+            &reqwest_client,
+            part_url,
+            index as u32 + 1,
+            archive,
+            (offset, part_len.min(archive.len() - offset)),
+            transfer.clone(),
+        ));
+    }
+    let parts: Vec<UploadedPart> = futures::stream::iter(uploads)
+        .buffered(options.concurrency.max(1) as usize)
+        .try_collect()
+        .await?;
+
+    enclave_api
+        .complete_deployment_upload(
+            deployment_intent.enclave_uuid(),
+            deployment_intent.deployment_uuid(),
+            CompleteDeploymentUploadRequest {
+                upload_id: multipart_upload.upload_id.clone(),
+                parts,
+            },
+        )
+        .await?;
+    Ok(())
+}
+
+async fn upload_part(
+    reqwest_client: &reqwest::Client,
+    part_url: &str,
+    part_number: u32,
+    archive: &UploadArchive,
+    range: (u64, u64),
+    transfer: Arc<Transfer>,
+) -> Result<UploadedPart, DeployError> {
+    let response = put_with_retries(reqwest_client, part_url, None, archive, range, || {
+        transfer.clone()
+    })
+    .await?;
+    if !response.status().is_success() {
+        return Err(DeployError::UploadError(response.text().await?));
+    }
+    let etag = response
+        .headers()
+        .get(reqwest::header::ETAG)
+        .and_then(|etag| etag.to_str().ok())
+        .ok_or_else(|| {
+            DeployError::UploadError(format!(
+                "Part {part_number} was uploaded, but no ETag was returned"
+            ))
+        })?;
+    Ok(UploadedPart {
+        part_number,
+        etag: etag.to_string(),
+    })
+}
+
+// Sends `len` bytes of the archive from `offset`, taking a transfer to report to for each attempt
+async fn put_with_retries<F: Fn() -> Arc<Transfer>>(
+    reqwest_client: &reqwest::Client,
+    signed_url: &str,
+    content_type: Option<&str>,
+    archive: &UploadArchive,
+    (offset, len): (u64, u64),
+    transfer: F,
+) -> Result<reqwest::Response, DeployError> {
+    let mut attempt = 1;
+    loop {
+        let upload_stream =
+            create_upload_stream(archive.part_reader(offset, len).await?, transfer());
+        let mut request = reqwest_client
+            .put(signed_url)
+            .header("Content-Length", len)
+            .body(Body::wrap_stream(upload_stream));
+        if let Some(content_type) = content_type {
+            request = request.header("Content-Type", content_type);
+        }
+        let result = request.send().await;
+        let retry_reason = match &result {
+            Ok(response) if response.status().is_server_error() => response.status().to_string(),
+            Err(e) if e.is_connect() || e.is_timeout() || e.is_request() => e.to_string(),
+            _ => return Ok(result?),
+        };
+        if attempt >= MAX_UPLOAD_ATTEMPTS {
+            return Ok(result?);
+        }
+        log::warn!(
+            "Upload attempt {attempt} of {MAX_UPLOAD_ATTEMPTS} failed ({retry_reason}), retrying..."
+        );
+        attempt += 1;
+    }
+}
+
+fn create_upload_stream<R: AsyncRead + Unpin>(
+    archive: R,
+    transfer: Arc<Transfer>,
+) -> AsyncStream<Result<bytes::BytesMut, std::io::Error>, impl core::future::Future<Output = ()>> {
+    let mut stream = FramedRead::new(archive, BytesCodec::new());
+    async_stream::stream! {
+        while let Some(bytes) = StreamExt::next(&mut stream).await {
+            if let Ok(bytes) = &bytes {
+                transfer.record(bytes.len() as u64).await;
+            }
+            yield bytes;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::api::enclave::{MockEnclaveApi, UploadFormat};
+    use crate::deploy::archive::EifDigests;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    // Accepts part uploads, responding with an ETag naming the part's path and length
+    async fn serve_parts(listener: tokio::net::TcpListener, parts: usize) -> Vec<(String, usize)> {
+        let mut received = vec![];
+        for _ in 0..parts {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = vec![];
+            let mut buffer = vec![0; 64 * 1024];
+            let header_end = loop {
+                let read = stream.read(&mut buffer).await.unwrap();
+                request.extend_from_slice(&buffer[..read]);
+                if let Some(end) = request.windows(4).position(|window| window == b"\r\n\r\n") {
+                    break end + 4;
+                }
+            };
+            let headers = String::from_utf8_lossy(&request[..header_end]).to_lowercase();
+            let path = headers.split_whitespace().nth(1).unwrap().to_string();
+            let content_length: usize = headers
+                .lines()
+                .find_map(|line| line.strip_prefix("content-length: "))
+                .unwrap()
+                .trim()
+                .parse()
+                .unwrap();
+            while request.len() < header_end + content_length {
+                let read = stream.read(&mut buffer).await.unwrap();
+                request.extend_from_slice(&buffer[..read]);
+            }
+            let response = format!(
+                "HTTP/1.1 200 OK\r\netag: \"{path}-{content_length}\"\r\ncontent-length: 0\r\nconnection: close\r\n\r\n"
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+            received.push((path, content_length));
+        }
+        received
+    }
+
+    #[tokio::test]
+    async fn test_upload_parts_completes_upload() {
+        let output_dir = tempfile::TempDir::new().unwrap();
+        let eif_len = MIN_PART_LEN as usize + 1024;
+        std::fs::write(
+            output_dir.path().join(crate::enclave::ENCLAVE_FILENAME),
+            vec![7; eif_len],
+        )
+        .unwrap();
+        let digests = EifDigests::compute(output_dir.path()).unwrap();
+        let archive = UploadArchive::new(output_dir.path(), UploadFormat::Zip, &digests).unwrap();
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = tokio::spawn(serve_parts(listener, 2));
+
+        // Three urls were returned, but the archive only fills two parts
+        let deployment_intent: CreateEnclaveDeploymentIntentResponse =
+            serde_json::from_value(serde_json::json!({
+                "signedUrl": "http://unused",
+                "enclaveUuid": "enclave_123",
+                "deploymentUuid": "deployment_123",
+                "version": 1,
+                "multipartUpload": {
+                    "uploadId": "upload_123",
+                    "partUrls": [
+                        format!("http://{address}/part1"),
+                        format!("http://{address}/part2"),
+                        format!("http://{address}/part3"),
+                    ],
+                },
+            }))
+            .unwrap();
+
+        let last_part_len = archive.len() - MIN_PART_LEN;
+        let mut mock_api = MockEnclaveApi::new();
+        mock_api
+            .expect_complete_deployment_upload()
+            .withf(move |enclave_uuid, deployment_uuid, request| {
+                enclave_uuid == "enclave_123"
+                    && deployment_uuid == "deployment_123"
+                    && request.upload_id == "upload_123"
+                    && request.parts.len() == 2
+                    && request.parts[0].part_number == 1
+                    && request.parts[0].etag == format!("\"/part1-{MIN_PART_LEN}\"")
+                    && request.parts[1].etag == format!("\"/part2-{last_part_len}\"")
+            })
+            .times(1)
+            .returning(|_, _, _| Box::pin(std::future::ready(Ok(()))));
+
+        let options = UploadOptions {
+            rate_limit: None,
+            concurrency: 2,
+        };
+        upload_archive(&mock_api, &deployment_intent, &archive, options)
+            .await
+            .unwrap();
+
+        let mut received = server.await.unwrap();
+        received.sort();
+        assert_eq!(
+            received,
+            vec![
+                ("/part1".to_string(), MIN_PART_LEN as usize),
+                ("/part2".to_string(), last_part_len as usize),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_rate_limit() {
+        assert_eq!(parse_rate_limit("2048"), Ok(2048));
+        assert_eq!(parse_rate_limit("500K"), Ok(500 * 1024));
+        assert_eq!(parse_rate_limit("10MB"), Ok(10 * 1024 * 1024));
+        assert_eq!(parse_rate_limit("1g"), Ok(1024 * 1024 * 1024));
+        assert!(parse_rate_limit("0").is_err());
+        assert!(parse_rate_limit("fast").is_err());
+        assert!(parse_rate_limit("M").is_err());
+        assert!(parse_rate_limit("20000000000G").is_err());
+    }
+
+    #[test]
+    fn test_part_len() {
+        let mb = 1024 * 1024;
+        assert_eq!(part_len(100 * mb, 4), 25 * mb);
+        assert_eq!(part_len(100 * mb + 1, 4), 25 * mb + 1);
+        // Small archives aren't split into parts smaller than S3 accepts
+        assert_eq!(part_len(8 * mb, 4), MIN_PART_LEN);
+    }
+}
@@ -0,0 +1,274 @@
+use crate::api::enclave::{EnclaveApi, EnclaveDeployment};
+use crate::deploy::{timed_operation, watch_deployment, DeployError, DEPLOY_WATCH_TIMEOUT_SECONDS};
+use crate::progress::get_tracker;
+use common::CliError;
+use serde::Serialize;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum DeploymentsError {
+    #[error("An error occurred while reading the Enclave config — {0}")]
+    EnclaveConfigError(#[from] crate::config::EnclaveConfigError),
+    #[error("No Enclave Uuid given. You can provide one by using either the --enclave-uuid flag, or using the --config flag to point to an Enclave.toml")]
+    MissingUuid,
+    #[error("Deployment {0} has already finished, so it can't be cancelled. Deploy a new version to replace it.")]
+    AlreadyFinished(String),
+    #[error("Deployment {0} has already failed, so there's nothing to cancel")]
+    AlreadyFailed(String),
+    #[error("An error occurred contacting the API — {0}")]
+    ApiError(#[from] common::api::client::ApiError),
+    #[error("There's no earlier successful deployment of Enclave {0} to roll back to")]
+    NoPreviousDeployment(String),
+    #[error("The rollback deployment failed — {0}")]
+    RollbackFailed(#[from] DeployError),
+}
+
+impl CliError for DeploymentsError {
+    fn exitcode(&self) -> exitcode::ExitCode {
+        match self {
+            Self::EnclaveConfigError(config_err) => config_err.exitcode(),
+            Self::MissingUuid => exitcode::DATAERR,
+            Self::AlreadyFinished(_) | Self::AlreadyFailed(_) => exitcode::DATAERR,
+            Self::ApiError(api_err) => api_err.exitcode(),
+            Self::NoPreviousDeployment(_) => exitcode::DATAERR,
+            Self::RollbackFailed(deploy_err) => deploy_err.exitcode(),
+        }
+    }
+}
+
+/// Cancels a deployment which is still queued, building or rolling out, so a superseded deployment
+/// doesn't hold up the ones behind it
+pub async fn cancel_deployment<T: EnclaveApi>(
+    enclave_api: &T,
+    enclave_uuid: &str,
+    deployment_uuid: &str,
+) -> Result<EnclaveDeployment, DeploymentsError> {
+    let deployment = enclave_api
+        .get_enclave_deployment_by_uuid(enclave_uuid, deployment_uuid)
+        .await?;
+    if deployment.is_cancelled() {
+        log::info!("Deployment {deployment_uuid} was already cancelled");
+        return Ok(deployment.deployment);
+    }
+    if deployment.is_finished() {
+        return Err(DeploymentsError::AlreadyFinished(
+            deployment_uuid.to_string(),
+        ));
+    }
+    if deployment.is_failed() {
+        return Err(DeploymentsError::AlreadyFailed(deployment_uuid.to_string()));
+    }
+    Ok(enclave_api
+        .cancel_deployment(enclave_uuid, deployment_uuid)
+        .await?)
+}
+
+/// The deployment a rollback replaced and the one it restored
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RollbackReport {
+    pub from_deployment_uuid: String,
+    pub to_deployment_uuid: String,
+    pub to_version: u16,
+    /// The new deployment which runs the restored version
+    pub deployment_uuid: String,
+}
+
+/// Redeploys the version of the last successful deployment before `deployment_uuid`, and waits
+/// for it to finish
+pub async fn rollback_deployment<T: EnclaveApi>(
+    enclave_api: T,
+    enclave_uuid: &str,
+    deployment_uuid: &str,
+) -> Result<RollbackReport, DeploymentsError> {
+    let enclave = enclave_api.get_enclave(enclave_uuid).await?;
+    let previous = enclave
+        .previous_successful_deployment(deployment_uuid)
+        .ok_or_else(|| DeploymentsError::NoPreviousDeployment(enclave_uuid.to_string()))?;
+    log::info!(
+        "Rolling back to deployment {} (version {})",
+        previous.deployment.uuid,
+        previous.version.version
+    );
+    let rollback = enclave_api
+        .redeploy_deployment(enclave_uuid, &previous.deployment.uuid)
+        .await?;
+
+    let progress_bar = get_tracker("Rolling back Enclave...", None);
+    let rollback_complete = timed_operation(
+        "Enclave Rollback",
+        DEPLOY_WATCH_TIMEOUT_SECONDS,
+        watch_deployment(enclave_api, enclave_uuid, rollback.uuid(), progress_bar),
+    )
+    .await??;
+    if !rollback_complete {
+        return Err(DeployError::DeploymentError.into());
+    }
+    Ok(RollbackReport {
+        from_deployment_uuid: deployment_uuid.to_string(),
+        to_deployment_uuid: previous.deployment.uuid.clone(),
+        to_version: previous.version.version,
+        deployment_uuid: rollback.uuid,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::api::enclave::{
+        BuildStatus, DeployStatus, DeploymentsForGetEnclave, EnclaveState, MockEnclaveApi,
+    };
+    use crate::test_utils::{build_get_enclave_deployment, build_get_enclave_response};
+
+    #[tokio::test]
+    async fn test_cancel_deployment() {
+        let mut mock_api = MockEnclaveApi::new();
+        mock_api
+            .expect_get_enclave_deployment_by_uuid()
+            .returning(|_, _| {
+                Box::pin(std::future::ready(Ok(build_get_enclave_deployment(
+                    BuildStatus::Pending,
+                    DeployStatus::Pending,
+                    None,
+                    None,
+                ))))
+            });
+        mock_api
+            .expect_cancel_deployment()
+            .times(1)
+            .returning(|_, _| {
+                let deployment = build_get_enclave_deployment(
+                    BuildStatus::Cancelled,
+                    DeployStatus::Pending,
+                    None,
+                    None,
+                );
+                Box::pin(std::future::ready(Ok(deployment.deployment)))
+            });
+        assert!(
+            cancel_deployment(&mock_api, "enclave_123", "deployment_456")
+                .await
+                .is_ok()
+        );
+
+        let mut mock_api = MockEnclaveApi::new();
+        mock_api
+            .expect_get_enclave_deployment_by_uuid()
+            .returning(|_, _| {
+                Box::pin(std::future::ready(Ok(build_get_enclave_deployment(
+                    BuildStatus::Ready,
+                    DeployStatus::Ready,
+                    None,
+                    Some("2024-01-01T00:00:00Z".into()),
+                ))))
+            });
+        mock_api.expect_cancel_deployment().never();
+        assert!(matches!(
+            cancel_deployment(&mock_api, "enclave_123", "deployment_456").await,
+            Err(DeploymentsError::AlreadyFinished(uuid)) if uuid == "deployment_456"
+        ));
+    }
+
+    fn finished_deployment(
+        uuid: &str,
+        version: u16,
+        build_status: BuildStatus,
+        started_at: &str,
+    ) -> DeploymentsForGetEnclave {
+        let mut deployment = build_get_enclave_deployment(
+            build_status,
+            DeployStatus::Ready,
+            Some(started_at.into()),
+            Some(started_at.into()),
+        );
+        deployment.deployment.uuid = uuid.into();
+        deployment.enclave_version.version = version;
+        DeploymentsForGetEnclave {
+            deployment: deployment.deployment,
+            version: deployment.enclave_version,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rollback_deployment() {
+        let deployments = vec![
+            finished_deployment(
+                "deployment_1",
+                1,
+                BuildStatus::Ready,
+                "2024-01-01T00:00:00Z",
+            ),
+            finished_deployment(
+                "deployment_2",
+                2,
+                BuildStatus::Ready,
+                "2024-01-02T00:00:00Z",
+            ),
+            finished_deployment(
+                "deployment_3",
+                3,
+                BuildStatus::Failed,
+                "2024-01-03T00:00:00Z",
+            ),
+            finished_deployment(
+                "deployment_4",
+                4,
+                BuildStatus::Ready,
+                "2024-01-04T00:00:00Z",
+            ),
+        ];
+        let mut mock_api = MockEnclaveApi::new();
+        mock_api.expect_get_enclave().returning(move |_| {
+            let response = build_get_enclave_response(EnclaveState::Active, deployments.clone());
+            Box::pin(std::future::ready(Ok(response)))
+        });
+        mock_api
+            .expect_redeploy_deployment()
+            .withf(|_, deployment_uuid| deployment_uuid == "deployment_2")
+            .times(1)
+            .returning(|_, _| {
+                let mut deployment = build_get_enclave_deployment(
+                    BuildStatus::Pending,
+                    DeployStatus::Pending,
+                    None,
+                    None,
+                );
+                deployment.deployment.uuid = "deployment_5".into();
+                Box::pin(std::future::ready(Ok(deployment.deployment)))
+            });
+        mock_api
+            .expect_get_enclave_deployment_by_uuid()
+            .returning(|_, _| {
+                Box::pin(std::future::ready(Ok(build_get_enclave_deployment(
+                    BuildStatus::Ready,
+                    DeployStatus::Ready,
+                    Some("2024-01-05T00:00:00Z".into()),
+                    Some("2024-01-05T00:05:00Z".into()),
+                ))))
+            });
+
+        let report = rollback_deployment(mock_api, "abc", "deployment_4")
+            .await
+            .unwrap();
+        assert_eq!(report.to_deployment_uuid, "deployment_2");
+        assert_eq!(report.to_version, 2);
+        assert_eq!(report.deployment_uuid, "deployment_5");
+
+        let mut mock_api = MockEnclaveApi::new();
+        mock_api.expect_get_enclave().returning(|_| {
+            let deployments = vec![finished_deployment(
+                "deployment_1",
+                1,
+                BuildStatus::Ready,
+                "2024-01-01T00:00:00Z",
+            )];
+            let response = build_get_enclave_response(EnclaveState::Active, deployments);
+            Box::pin(std::future::ready(Ok(response)))
+        });
+        mock_api.expect_redeploy_deployment().never();
+        assert!(matches!(
+            rollback_deployment(mock_api, "abc", "deployment_1").await,
+            Err(DeploymentsError::NoPreviousDeployment(_))
+        ));
+    }
+}
@@ -0,0 +1,153 @@
+use super::error::DescribeError;
+use crate::enclave::DescribeEif;
+use aws_nitro_enclaves_image_format::utils::{eif_reader::EifReader, get_pcrs};
+use sha2::{Digest, Sha384};
+use std::path::Path;
+
+/// Reads the measurements and metadata of an EIF directly from the file, producing the same
+/// description as `nitro-cli describe-eif` without requiring Docker.
+pub fn describe_eif_from_file(eif_path: &Path) -> Result<DescribeEif, DescribeError> {
+    let mut reader = EifReader::from_eif(eif_path.display().to_string())
+        .map_err(DescribeError::EifParseError)?;
+
+    if !reader.check_crc() {
+        return Err(DescribeError::EifParseError(
+            "EIF checksum does not match its contents".to_string(),
+        ));
+    }
+
+    // Unsigned EIFs are measured the same way, but have no PCR8 or signing certificate
+    let is_signed = reader.signature_section.is_some();
+    let measurements = get_pcrs(
+        &mut reader.image_hasher,
+        &mut reader.bootstrap_hasher,
+        &mut reader.app_hasher,
+        &mut reader.cert_hasher,
+        Sha384::new(),
+        is_signed,
+    )
+    .map_err(DescribeError::EifParseError)?;
+
+    let signing_certificate = is_signed
+        .then(|| reader.get_certificate_info(measurements.clone()))
+        .transpose()
+        .map_err(DescribeError::EifParseError)?;
+    let metadata = reader.get_metadata().ok_or_else(|| {
+        DescribeError::EifParseError("EIF does not contain a metadata section".to_string())
+    })?;
+
+    // Mirror the shape of the nitro-cli output so both paths deserialize to the same type
+    let description = serde_json::json!({
+        "Measurements": measurements,
+        "IsSigned": is_signed,
+        "SigningCertificate": signing_certificate,
+        "SignatureCheck": reader.sign_check.unwrap_or(false),
+        "Metadata": metadata.build_info,
+    });
+
+    serde_json::from_value(description)
+        .map_err(|e| DescribeError::EifParseError(format!("Unexpected EIF contents - {e}")))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use aws_nitro_enclaves_image_format::defs::{EifBuildInfo, EifIdentityInfo};
+    use aws_nitro_enclaves_image_format::utils::{EifBuilder, SignEnclaveInfo};
+    use tempfile::TempDir;
+
+    fn build_test_eif(
+        output_dir: &Path,
+        signed: bool,
+    ) -> (
+        std::path::PathBuf,
+        std::collections::BTreeMap<String, String>,
+    ) {
+        let kernel_path = output_dir.join("bzImage");
+        let ramdisk_path = output_dir.join("rootfs.cpio");
+        std::fs::write(&kernel_path, b"test-kernel").unwrap();
+        std::fs::write(&ramdisk_path, b"test-ramdisk").unwrap();
+
+        let sign_info = signed.then(|| {
+            SignEnclaveInfo::new("../../fixtures/cert.pem", "../../fixtures/key.pem").unwrap()
+        });
+        let identity = EifIdentityInfo {
+            img_name: "test".to_string(),
+            img_version: "1.0".to_string(),
+            build_info: EifBuildInfo {
+                build_time: "2024-06-12T00:00:00Z".to_string(),
+                build_tool: "test".to_string(),
+                build_tool_version: "1.0".to_string(),
+                img_os: "linux".to_string(),
+                img_kernel: "test".to_string(),
+            },
+            docker_info: serde_json::json!({}),
+            custom_info: serde_json::json!({}),
+        };
+
+        let mut builder = EifBuilder::new(
+            &kernel_path,
+            "console=ttyS0".to_string(),
+            sign_info,
+            Sha384::new(),
+            0,
+            identity,
+        );
+        builder.add_ramdisk(&ramdisk_path);
+        builder.add_ramdisk(&ramdisk_path);
+
+        let eif_path = output_dir.join("enclave.eif");
+        let mut eif_file = std::fs::File::create(&eif_path).unwrap();
+        let measurements = builder.write_to(&mut eif_file);
+        (eif_path, measurements)
+    }
+
+    #[test]
+    fn test_describe_eif_from_file() {
+        let output_dir = TempDir::new().unwrap();
+        let (eif_path, expected) = build_test_eif(output_dir.path(), true);
+
+        let description = describe_eif_from_file(&eif_path).unwrap();
+        let pcrs = description.measurements.measurements().pcrs();
+        assert_eq!(pcrs.pcr0.as_str(), expected["PCR0"]);
+        assert_eq!(pcrs.pcr1.as_str(), expected["PCR1"]);
+        assert_eq!(pcrs.pcr2.as_str(), expected["PCR2"]);
+        assert_eq!(
+            pcrs.pcr8.as_deref(),
+            expected.get("PCR8").map(String::as_str)
+        );
+        assert_eq!(
+            pcrs.pcr8.as_deref(),
+            Some(
+                crate::cert::get_cert_pcr(Path::new("../../fixtures/cert.pem"))
+                    .unwrap()
+                    .as_str()
+            )
+        );
+    }
+
+    #[test]
+    fn test_describe_unsigned_eif_from_file() {
+        let output_dir = TempDir::new().unwrap();
+        let (eif_path, expected) = build_test_eif(output_dir.path(), false);
+
+        let description = describe_eif_from_file(&eif_path).unwrap();
+        let pcrs = description.measurements.measurements().pcrs();
+        assert_eq!(pcrs.pcr0.as_str(), expected["PCR0"]);
+        assert_eq!(pcrs.pcr1.as_str(), expected["PCR1"]);
+        assert_eq!(pcrs.pcr2.as_str(), expected["PCR2"]);
+        assert_eq!(pcrs.pcr8, None);
+        assert!(!expected.contains_key("PCR8"));
+    }
+
+    #[test]
+    fn test_describe_eif_from_file_rejects_invalid_file() {
+        let output_dir = TempDir::new().unwrap();
+        let eif_path = output_dir.path().join("enclave.eif");
+        std::fs::write(&eif_path, b"not an eif").unwrap();
+        assert!(matches!(
+            describe_eif_from_file(&eif_path),
+            Err(DescribeError::EifParseError(_))
+        ));
+    }
+}
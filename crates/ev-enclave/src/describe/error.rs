@@ -9,16 +9,35 @@ pub enum DescribeError {
     DockerError(#[from] DockerError),
     #[error("Could not find eif at {0}")]
     EIFNotFound(std::path::PathBuf),
+    #[error("Failed to read Enclave image file — {0}")]
+    EifParseError(String),
     #[error(transparent)]
     EnclaveError(#[from] EnclaveError),
+    #[error("No EIFs were found in {0}")]
+    NoEifsFound(std::path::PathBuf),
+    #[error("An error occurred while reading the Enclave config — {0}")]
+    EnclaveConfigError(#[from] crate::config::EnclaveConfigError),
+    #[error("An error occurred contacting the API — {0}")]
+    ApiError(#[from] common::api::client::ApiError),
+    #[error("Enclave {0} has no finished deployments, so nothing is being served yet.")]
+    NoFinishedDeployment(String),
+    #[error("Failed to describe {file_name} — {source}")]
+    BatchEifError {
+        file_name: String,
+        source: Box<DescribeError>,
+    },
 }
 
 impl CliError for DescribeError {
     fn exitcode(&self) -> exitcode::ExitCode {
         match self {
             Self::DockerError(_) => exitcode::UNAVAILABLE,
-            Self::EIFNotFound(_) => exitcode::NOINPUT,
+            Self::EIFNotFound(_) | Self::NoEifsFound(_) => exitcode::NOINPUT,
+            Self::EifParseError(_) | Self::NoFinishedDeployment(_) => exitcode::DATAERR,
+            Self::EnclaveConfigError(inner) => inner.exitcode(),
+            Self::ApiError(inner) => inner.exitcode(),
             Self::EnclaveError(inner) => inner.exitcode(),
+            Self::BatchEifError { source, .. } => source.exitcode(),
         }
     }
 }
@@ -1,13 +1,22 @@
+mod eif;
 pub mod error;
+pub mod remote;
 
 use crate::common::resolve_output_path;
 use crate::docker::{error::DockerError, utils::verify_docker_is_running};
 use crate::enclave;
 use crate::progress::get_tracker;
 use error::DescribeError;
+use futures::StreamExt;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+// EIFs are read concurrently in a batch, bounded as each read hashes the whole file
+const MAX_CONCURRENT_DESCRIBES: usize = 4;
 
 pub fn describe_eif(
     eif_path: &str,
+    nitro_cli: &enclave::NitroCliImage,
     verbose: bool,
     no_cache: bool,
 ) -> Result<enclave::DescribeEif, DescribeError> {
@@ -18,6 +27,21 @@ pub fn describe_eif(
     let absolute_path = eif_path
         .canonicalize()
         .map_err(|_| DescribeError::EIFNotFound(eif_path.to_path_buf()))?;
+
+    match eif::describe_eif_from_file(&absolute_path) {
+        Ok(description) => return Ok(description),
+        Err(e) => log::debug!("Failed to read EIF directly, falling back to the Nitro CLI - {e}"),
+    }
+
+    describe_eif_with_nitro_cli(&absolute_path, nitro_cli, verbose, no_cache)
+}
+
+fn describe_eif_with_nitro_cli(
+    absolute_path: &Path,
+    nitro_cli: &enclave::NitroCliImage,
+    verbose: bool,
+    no_cache: bool,
+) -> Result<enclave::DescribeEif, DescribeError> {
     if !verify_docker_is_running()? {
         return Err(DockerError::DaemonNotRunning.into());
     }
@@ -26,10 +50,105 @@ pub fn describe_eif(
 
     let supplied_path: Option<&str> = None;
     let output_path = resolve_output_path(supplied_path).unwrap();
-    enclave::build_nitro_cli_image(output_path.path(), None, verbose, no_cache)?;
+    enclave::build_nitro_cli_image(output_path.path(), None, nitro_cli, verbose, no_cache)?;
 
-    let description = enclave::describe_eif(&absolute_path, verbose)?;
+    let description = enclave::describe_eif(absolute_path, verbose)?;
     describe_progress.finish_with_message("PCRs retrieved.");
 
     Ok(description)
 }
+
+fn find_eifs(dir: &Path) -> Result<Vec<PathBuf>, DescribeError> {
+    let entries = std::fs::read_dir(dir).map_err(|_| DescribeError::EIFNotFound(dir.into()))?;
+    let mut eifs: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "eif"))
+        .collect();
+    eifs.sort();
+    Ok(eifs)
+}
+
+/// Describes every EIF in a directory, keyed by file name. EIFs are read concurrently, and any
+/// which can't be read directly are then described one at a time with the Nitro CLI.
+pub async fn describe_eifs_in_dir(
+    dir: &str,
+    nitro_cli: &enclave::NitroCliImage,
+    verbose: bool,
+    no_cache: bool,
+) -> Result<BTreeMap<String, enclave::DescribeEif>, DescribeError> {
+    let dir = Path::new(dir);
+    let eifs = find_eifs(dir)?;
+    if eifs.is_empty() {
+        return Err(DescribeError::NoEifsFound(dir.to_path_buf()));
+    }
+
+    let results: Vec<(PathBuf, Result<enclave::DescribeEif, DescribeError>)> =
+        futures::stream::iter(eifs)
+            .map(|path| async move {
+                let read_path = path.clone();
+                let result =
+                    tokio::task::spawn_blocking(move || eif::describe_eif_from_file(&read_path))
+                        .await
+                        .unwrap_or_else(|e| Err(DescribeError::EifParseError(e.to_string())));
+                (path, result)
+            })
+            .buffer_unordered(MAX_CONCURRENT_DESCRIBES)
+            .collect()
+            .await;
+
+    let mut descriptions = BTreeMap::new();
+    for (path, result) in results {
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let description = match result {
+            Ok(description) => description,
+            Err(e) => {
+                log::debug!(
+                    "Failed to read {file_name} directly, falling back to the Nitro CLI - {e}"
+                );
+                let absolute_path = path
+                    .canonicalize()
+                    .map_err(|_| DescribeError::EIFNotFound(path.clone()))?;
+                describe_eif_with_nitro_cli(&absolute_path, nitro_cli, verbose, no_cache).map_err(
+                    |source| DescribeError::BatchEifError {
+                        file_name: file_name.clone(),
+                        source: Box::new(source),
+                    },
+                )?
+            }
+        };
+        descriptions.insert(file_name, description);
+    }
+    Ok(descriptions)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_describe_empty_batch() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("enclave.toml"), "").unwrap();
+        std::fs::create_dir(dir.path().join("nested.eif")).unwrap();
+        std::fs::write(dir.path().join("b.eif"), "").unwrap();
+        std::fs::write(dir.path().join("a.eif"), "").unwrap();
+        assert_eq!(
+            find_eifs(dir.path()).unwrap(),
+            vec![dir.path().join("a.eif"), dir.path().join("b.eif")]
+        );
+
+        let empty = tempfile::TempDir::new().unwrap();
+        let result = describe_eifs_in_dir(
+            &empty.path().display().to_string(),
+            &enclave::NitroCliImage::default(),
+            false,
+            false,
+        )
+        .await;
+        assert!(matches!(result, Err(DescribeError::NoEifsFound(_))));
+    }
+}
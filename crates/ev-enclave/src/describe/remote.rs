@@ -0,0 +1,118 @@
+use super::error::DescribeError;
+use crate::api::enclave::{EnclaveApi, GetEnclaveResponse};
+use crate::config::{EnclaveConfig, EnclaveConfigError};
+use crate::docker::command::NO_GIT_INFO;
+use crate::enclave::PCRs;
+use serde::Serialize;
+
+/// Where the measurements of the deployment serving an Enclave's traffic came from
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct DeploymentProvenance {
+    pub enclave_uuid: String,
+    pub deployment_uuid: String,
+    pub version: u16,
+    pub deployed_at: Option<String>,
+    pub pcrs: Option<PCRs>,
+    pub git_hash: Option<String>,
+    pub source_date_epoch: Option<String>,
+    pub data_plane_version: Option<String>,
+    pub installer_version: Option<String>,
+}
+
+impl DeploymentProvenance {
+    pub fn from_enclave(enclave: &GetEnclaveResponse) -> Option<Self> {
+        let latest = enclave.latest_finished_deployment()?;
+        let version = &latest.version;
+        Some(Self {
+            enclave_uuid: enclave.enclaves.uuid().to_string(),
+            deployment_uuid: latest.deployment.uuid().to_string(),
+            version: version.version,
+            deployed_at: latest.deployment.completed_at.clone(),
+            pcrs: version.pcrs.clone(),
+            git_hash: version
+                .git_hash
+                .clone()
+                .filter(|git_hash| !git_hash.is_empty() && git_hash != NO_GIT_INFO),
+            source_date_epoch: version.git_timestamp.clone(),
+            data_plane_version: version.data_plane_version.clone(),
+            installer_version: version.installer_version.clone(),
+        })
+    }
+}
+
+/// Finds which commit and source date epoch produced the deployment currently serving the
+/// Enclave in the config, so its attested measurements can be traced back to source
+pub async fn describe_remote<T: EnclaveApi>(
+    config_path: &str,
+    enclave_api: &T,
+) -> Result<DeploymentProvenance, DescribeError> {
+    let config = EnclaveConfig::try_from_filepath(config_path)?;
+    let enclave_uuid = config
+        .uuid
+        .ok_or_else(|| EnclaveConfigError::MissingField("uuid".into()))?;
+    let enclave = enclave_api.get_enclave(&enclave_uuid).await?;
+    DeploymentProvenance::from_enclave(&enclave)
+        .ok_or(DescribeError::NoFinishedDeployment(enclave_uuid))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_provenance_of_latest_finished_deployment() {
+        let deployment = |started_at: &str, completed: bool, git_hash: &str| {
+            serde_json::json!({
+                "uuid": format!("deployment-{started_at}"),
+                "enclaveUuid": "enclave_123",
+                "versionUuid": "version_123",
+                "signingCertUuid": "cert_123",
+                "debugMode": false,
+                "startedAt": started_at,
+                "completedAt": completed.then_some(started_at),
+                "enclaveVersion": {
+                    "uuid": "version_123",
+                    "version": 4,
+                    "buildStatus": "ready",
+                    "dataPlaneVersion": "1.4.0",
+                    "installerVersion": "abc123",
+                    "gitHash": git_hash,
+                    "gitTimestamp": "1700000000"
+                }
+            })
+        };
+        let mut enclave: GetEnclaveResponse = serde_json::from_value(serde_json::json!({
+            "uuid": "enclave_123",
+            "name": "hello",
+            "teamUuid": "team_123",
+            "appUuid": "app_123",
+            "domain": "hello.app.enclave.evervault.com",
+            "state": "active",
+            "createdAt": "2026-01-01T00:00:00Z",
+            "updatedAt": "2026-01-01T00:00:00Z",
+            "enclaveDeployments": [
+                deployment("2026-01-01T00:00:00Z", true, "older"),
+                deployment("2026-01-02T00:00:00Z", true, "deadbeef"),
+                deployment("2026-01-03T00:00:00Z", false, "in-progress"),
+            ]
+        }))
+        .unwrap();
+
+        let provenance = DeploymentProvenance::from_enclave(&enclave).unwrap();
+        assert_eq!(
+            provenance.deployment_uuid,
+            "deployment-2026-01-02T00:00:00Z"
+        );
+        assert_eq!(provenance.git_hash.as_deref(), Some("deadbeef"));
+        assert_eq!(provenance.source_date_epoch.as_deref(), Some("1700000000"));
+        assert_eq!(provenance.installer_version.as_deref(), Some("abc123"));
+
+        enclave.deployments[1].version.git_hash = Some(NO_GIT_INFO.into());
+        let provenance = DeploymentProvenance::from_enclave(&enclave).unwrap();
+        assert_eq!(provenance.git_hash, None);
+
+        enclave.deployments.retain(|d| !d.deployment.is_finished());
+        assert!(DeploymentProvenance::from_enclave(&enclave).is_none());
+    }
+}
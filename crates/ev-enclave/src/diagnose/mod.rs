@@ -0,0 +1,448 @@
+use common::table::Table;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const ALLOCATOR_SERVICE: &str = "nitro-enclaves-allocator.service";
+const ALLOCATOR_CONFIG_PATH: &str = "etc/nitro_enclaves/allocator.yaml";
+const NITRO_ENCLAVES_DEVICE: &str = "dev/nitro_enclaves";
+const VSOCK_DEVICE: &str = "dev/vsock";
+const NE_CPU_POOL_PATH: &str = "sys/module/nitro_enclaves/parameters/ne_cpus";
+const HUGEPAGES_PATH: &str = "sys/kernel/mm/hugepages";
+const PROC_MODULES_PATH: &str = "proc/modules";
+/// The oldest nitro-cli release able to run EIFs built by this CLI
+const MIN_NITRO_CLI_VERSION: semver::Version = semver::Version::new(1, 2, 0);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl std::fmt::Display for CheckStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Pass => write!(f, "pass"),
+            Self::Warn => write!(f, "warn"),
+            Self::Fail => write!(f, "fail"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct HostCheck {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+}
+
+impl HostCheck {
+    fn new(name: &'static str, status: CheckStatus, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Whether an EC2 host is ready to run Enclaves, e.g. to debug an EIF with `nitro-cli run-enclave`
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HostReport {
+    pub ready: bool,
+    pub checks: Vec<HostCheck>,
+}
+
+impl HostReport {
+    fn new(checks: Vec<HostCheck>) -> Self {
+        Self {
+            ready: checks.iter().all(|check| check.status != CheckStatus::Fail),
+            checks,
+        }
+    }
+
+    pub fn exitcode(&self) -> exitcode::ExitCode {
+        if self.ready {
+            exitcode::OK
+        } else {
+            exitcode::UNAVAILABLE
+        }
+    }
+
+    pub fn table(&self) -> Table {
+        let mut table = Table::new([
+            ("check", "CHECK"),
+            ("status", "STATUS"),
+            ("detail", "DETAIL"),
+        ]);
+        for check in self.checks.iter() {
+            table.push_row(vec![
+                check.name.to_string(),
+                check.status.to_string(),
+                check.detail.clone(),
+            ]);
+        }
+        table
+    }
+}
+
+/// Reads the host's sysfs, procfs and devices relative to `root`, which is `/` outside of tests
+pub struct HostProbe {
+    root: PathBuf,
+}
+
+impl Default for HostProbe {
+    fn default() -> Self {
+        Self {
+            root: PathBuf::from("/"),
+        }
+    }
+}
+
+impl HostProbe {
+    fn read(&self, path: &str) -> Option<String> {
+        std::fs::read_to_string(self.root.join(path)).ok()
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        self.root.join(path).exists()
+    }
+
+    pub fn diagnose(&self) -> HostReport {
+        let allocator_config = self
+            .read(ALLOCATOR_CONFIG_PATH)
+            .map(|contents| AllocatorConfig::parse(&contents));
+        HostReport::new(vec![
+            check_allocator_service(run_command("systemctl", &["is-active", ALLOCATOR_SERVICE])),
+            self.check_driver(),
+            self.check_vsock(),
+            self.check_cpu_pool(allocator_config.as_ref()),
+            check_hugepages(
+                hugepages_reserved_mib(&self.root.join(HUGEPAGES_PATH)),
+                allocator_config.as_ref(),
+            ),
+            check_nitro_cli_version(run_command("nitro-cli", &["--version"])),
+        ])
+    }
+
+    fn check_driver(&self) -> HostCheck {
+        const NAME: &str = "nitro_enclaves driver";
+        if self.exists(NITRO_ENCLAVES_DEVICE) {
+            HostCheck::new(NAME, CheckStatus::Pass, "/dev/nitro_enclaves is present")
+        } else {
+            HostCheck::new(NAME, CheckStatus::Fail, "/dev/nitro_enclaves is missing. Check Nitro Enclaves is enabled for the instance, and the aws-nitro-enclaves-cli package is installed.")
+        }
+    }
+
+    fn check_vsock(&self) -> HostCheck {
+        const NAME: &str = "vsock";
+        let module_loaded = self.read(PROC_MODULES_PATH).is_some_and(|modules| {
+            modules.lines().any(|line| {
+                line.split_whitespace()
+                    .next()
+                    .is_some_and(|module| module.contains("vsock"))
+            })
+        });
+        match (module_loaded, self.exists(VSOCK_DEVICE)) {
+            (_, true) => HostCheck::new(NAME, CheckStatus::Pass, "/dev/vsock is present"),
+            (true, false) => HostCheck::new(
+                NAME,
+                CheckStatus::Warn,
+                "A vsock module is loaded, but /dev/vsock is missing",
+            ),
+            (false, false) => HostCheck::new(
+                NAME,
+                CheckStatus::Fail,
+                "No vsock module is loaded. Run `modprobe vsock_loopback` or reinstall the Nitro Enclaves CLI.",
+            ),
+        }
+    }
+
+    fn check_cpu_pool(&self, allocator_config: Option<&AllocatorConfig>) -> HostCheck {
+        const NAME: &str = "CPU pool";
+        let Some(pool) = self
+            .read(NE_CPU_POOL_PATH)
+            .map(|pool| pool.trim().to_string())
+        else {
+            return HostCheck::new(
+                NAME,
+                CheckStatus::Fail,
+                "The nitro_enclaves CPU pool couldn't be read",
+            );
+        };
+        if pool.is_empty() {
+            return HostCheck::new(NAME, CheckStatus::Fail, "No CPUs are reserved for Enclaves. Set cpu_count in /etc/nitro_enclaves/allocator.yaml and restart the allocator.");
+        }
+        let pool_size = cpu_list_size(&pool);
+        match allocator_config.and_then(|config| config.cpu_count) {
+            Some(cpu_count) if pool_size < cpu_count => HostCheck::new(
+                NAME,
+                CheckStatus::Fail,
+                format!("{pool_size} CPUs ({pool}) are reserved, but the allocator is configured for {cpu_count}"),
+            ),
+            _ => HostCheck::new(
+                NAME,
+                CheckStatus::Pass,
+                format!("{pool_size} CPUs reserved ({pool})"),
+            ),
+        }
+    }
+}
+
+/// The memory and CPUs the allocator service reserves for Enclaves
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AllocatorConfig {
+    pub memory_mib: Option<u64>,
+    pub cpu_count: Option<usize>,
+}
+
+impl AllocatorConfig {
+    // allocator.yaml is a flat list of `key: value` pairs, so isn't worth a yaml parser
+    fn parse(contents: &str) -> Self {
+        let value = |key: &str| {
+            contents
+                .lines()
+                .filter_map(|line| line.split_once(':'))
+                .find(|(line_key, _)| line_key.trim() == key)
+                .map(|(_, value)| value.trim().to_string())
+        };
+        Self {
+            memory_mib: value("memory_mib").and_then(|value| value.parse().ok()),
+            cpu_count: value("cpu_count").and_then(|value| value.parse().ok()),
+        }
+    }
+}
+
+/// Counts the CPUs in a sysfs CPU list, e.g. `1,3-5` has 4
+fn cpu_list_size(cpu_list: &str) -> usize {
+    cpu_list
+        .split(',')
+        .filter_map(|range| match range.trim().split_once('-') {
+            Some((start, end)) => {
+                Some(end.parse::<usize>().ok()? + 1 - start.parse::<usize>().ok()?)
+            }
+            None => range.trim().parse::<usize>().ok().map(|_| 1),
+        })
+        .sum()
+}
+
+/// The memory reserved in hugepages of every size, in MiB
+fn hugepages_reserved_mib(hugepages_dir: &Path) -> Option<u64> {
+    let entries = std::fs::read_dir(hugepages_dir).ok()?;
+    let reserved_kib = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let page_size_kib: u64 = name
+                .strip_prefix("hugepages-")?
+                .strip_suffix("kB")?
+                .parse()
+                .ok()?;
+            let pages: u64 = std::fs::read_to_string(entry.path().join("nr_hugepages"))
+                .ok()?
+                .trim()
+                .parse()
+                .ok()?;
+            Some(page_size_kib * pages)
+        })
+        .sum::<u64>();
+    Some(reserved_kib / 1024)
+}
+
+fn check_hugepages(
+    reserved_mib: Option<u64>,
+    allocator_config: Option<&AllocatorConfig>,
+) -> HostCheck {
+    const NAME: &str = "hugepages";
+    let required_mib = allocator_config.and_then(|config| config.memory_mib);
+    match (reserved_mib, required_mib) {
+        (None, _) => HostCheck::new(NAME, CheckStatus::Fail, "Hugepages couldn't be read"),
+        (Some(0), _) => HostCheck::new(NAME, CheckStatus::Fail, "No hugepages are reserved for Enclaves. Set memory_mib in /etc/nitro_enclaves/allocator.yaml and restart the allocator."),
+        (Some(reserved), Some(required)) if reserved < required => HostCheck::new(
+            NAME,
+            CheckStatus::Fail,
+            format!("{reserved} MiB reserved, but the allocator is configured for {required} MiB. The host may not have enough free memory."),
+        ),
+        (Some(reserved), None) => HostCheck::new(
+            NAME,
+            CheckStatus::Warn,
+            format!("{reserved} MiB reserved, but /etc/nitro_enclaves/allocator.yaml couldn't be read to compare against"),
+        ),
+        (Some(reserved), Some(_)) => {
+            HostCheck::new(NAME, CheckStatus::Pass, format!("{reserved} MiB reserved"))
+        }
+    }
+}
+
+/// Runs a command, returning its trimmed stdout when it succeeds
+pub(crate) fn run_command(program: &str, args: &[&str]) -> Result<String, String> {
+    match Command::new(program).args(args).output() {
+        Ok(output) if output.status.success() => {
+            Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+        }
+        Ok(output) => Err(String::from_utf8_lossy(&output.stdout).trim().to_string()),
+        Err(e) => Err(format!("{program} couldn't be run - {e}")),
+    }
+}
+
+fn check_allocator_service(is_active: Result<String, String>) -> HostCheck {
+    const NAME: &str = "allocator service";
+    match is_active {
+        Ok(_) => HostCheck::new(NAME, CheckStatus::Pass, format!("{ALLOCATOR_SERVICE} is active")),
+        Err(state) if state.is_empty() => HostCheck::new(
+            NAME,
+            CheckStatus::Fail,
+            format!("{ALLOCATOR_SERVICE} is not active. Run `sudo systemctl enable --now {ALLOCATOR_SERVICE}`."),
+        ),
+        Err(state) => HostCheck::new(
+            NAME,
+            CheckStatus::Fail,
+            format!("{ALLOCATOR_SERVICE} is not active ({state}). Run `sudo systemctl enable --now {ALLOCATOR_SERVICE}`."),
+        ),
+    }
+}
+
+fn check_nitro_cli_version(version_output: Result<String, String>) -> HostCheck {
+    const NAME: &str = "nitro-cli";
+    let output = match version_output {
+        Ok(output) => output,
+        Err(e) => {
+            return HostCheck::new(
+                NAME,
+                CheckStatus::Fail,
+                format!("nitro-cli isn't installed - {e}"),
+            )
+        }
+    };
+    // e.g. "Nitro CLI 1.2.2"
+    let version = output
+        .split_whitespace()
+        .last()
+        .and_then(|version| semver::Version::parse(version.trim_start_matches('v')).ok());
+    match version {
+        Some(version) if version < MIN_NITRO_CLI_VERSION => HostCheck::new(
+            NAME,
+            CheckStatus::Fail,
+            format!("nitro-cli {version} is installed, but {MIN_NITRO_CLI_VERSION} or later is required"),
+        ),
+        Some(version) => HostCheck::new(NAME, CheckStatus::Pass, format!("nitro-cli {version}")),
+        None => HostCheck::new(
+            NAME,
+            CheckStatus::Warn,
+            format!("Couldn't parse the nitro-cli version from `{output}`"),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write(root: &Path, path: &str, contents: &str) {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+
+    #[test]
+    fn test_host_probe_reads_reserved_resources() {
+        let root = TempDir::new().unwrap();
+        write(
+            root.path(),
+            ALLOCATOR_CONFIG_PATH,
+            "---\nmemory_mib: 2048\ncpu_count: 2\n",
+        );
+        write(root.path(), NE_CPU_POOL_PATH, "1,3\n");
+        write(
+            root.path(),
+            "sys/kernel/mm/hugepages/hugepages-2048kB/nr_hugepages",
+            "512\n",
+        );
+        write(
+            root.path(),
+            "sys/kernel/mm/hugepages/hugepages-1048576kB/nr_hugepages",
+            "1\n",
+        );
+        write(
+            root.path(),
+            PROC_MODULES_PATH,
+            "vsock_loopback 16384 0 - Live 0x0\n",
+        );
+        write(root.path(), VSOCK_DEVICE, "");
+
+        let probe = HostProbe {
+            root: root.path().to_path_buf(),
+        };
+        let allocator_config = AllocatorConfig::parse(&probe.read(ALLOCATOR_CONFIG_PATH).unwrap());
+        assert_eq!(
+            allocator_config,
+            AllocatorConfig {
+                memory_mib: Some(2048),
+                cpu_count: Some(2)
+            }
+        );
+        assert_eq!(
+            probe.check_cpu_pool(Some(&allocator_config)).status,
+            CheckStatus::Pass
+        );
+        assert_eq!(probe.check_vsock().status, CheckStatus::Pass);
+        assert_eq!(probe.check_driver().status, CheckStatus::Fail);
+
+        let reserved = hugepages_reserved_mib(&root.path().join(HUGEPAGES_PATH));
+        assert_eq!(reserved, Some(2048));
+        assert_eq!(
+            check_hugepages(reserved, Some(&allocator_config)).status,
+            CheckStatus::Pass
+        );
+        let larger = AllocatorConfig {
+            memory_mib: Some(4096),
+            cpu_count: Some(4),
+        };
+        assert_eq!(
+            check_hugepages(reserved, Some(&larger)).status,
+            CheckStatus::Fail
+        );
+        assert_eq!(
+            probe.check_cpu_pool(Some(&larger)).status,
+            CheckStatus::Fail
+        );
+    }
+
+    #[test]
+    fn test_cpu_list_size() {
+        assert_eq!(cpu_list_size("1"), 1);
+        assert_eq!(cpu_list_size("1,3-5"), 4);
+        assert_eq!(cpu_list_size("0-7,9"), 9);
+    }
+
+    #[test]
+    fn test_check_nitro_cli_version() {
+        assert_eq!(
+            check_nitro_cli_version(Ok("Nitro CLI 1.2.2".into())).status,
+            CheckStatus::Pass
+        );
+        assert_eq!(
+            check_nitro_cli_version(Ok("Nitro CLI 1.1.0".into())).status,
+            CheckStatus::Fail
+        );
+        assert_eq!(
+            check_nitro_cli_version(Ok("unknown".into())).status,
+            CheckStatus::Warn
+        );
+        assert_eq!(
+            check_nitro_cli_version(Err("not found".into())).status,
+            CheckStatus::Fail
+        );
+
+        let report = HostReport::new(vec![
+            check_allocator_service(Ok("active".into())),
+            check_nitro_cli_version(Ok("Nitro CLI 1.1.0".into())),
+        ]);
+        assert!(!report.ready);
+        assert_eq!(report.exitcode(), exitcode::UNAVAILABLE);
+    }
+}
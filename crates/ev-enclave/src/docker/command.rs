@@ -1,21 +1,33 @@
 use super::error::CommandError;
+use super::platform::Platform;
+use super::remote::docker_command;
 use git2::Repository;
 use std::ffi::OsStr;
 use std::path::Path;
-use std::process::{Command, ExitStatus, Output, Stdio};
+use std::process::{ExitStatus, Output, Stdio};
 
 pub struct CommandConfig {
     verbose: bool,
     no_cache: bool,
+    platform: String,
 }
 
 impl CommandConfig {
     pub fn new(verbose: bool, no_cache: bool) -> Self {
-        Self { verbose, no_cache }
+        Self {
+            verbose,
+            no_cache,
+            platform: Platform::default().to_string(),
+        }
+    }
+
+    pub fn with_platform(mut self, platform: &Platform) -> Self {
+        self.platform = platform.to_string();
+        self
     }
 
     pub fn extra_build_args(&self) -> Vec<&OsStr> {
-        let mut args = vec!["--platform".as_ref(), "linux/amd64".as_ref()];
+        let mut args = vec!["--platform".as_ref(), self.platform.as_ref()];
         if self.no_cache {
             args.push("--no-cache".as_ref());
         }
@@ -37,7 +49,7 @@ pub fn load_image_into_local_docker_registry(
 ) -> Result<ExitStatus, CommandError> {
     let command_config = CommandConfig::new(verbose, false);
     let is_stdout_piped = atty::isnt(atty::Stream::Stdout);
-    let docker_load_result = Command::new("docker")
+    let docker_load_result = docker_command()
         .args(vec![
             "load".as_ref(),
             "--input".as_ref(),
@@ -57,7 +69,7 @@ fn docker_buildkit_enabled() -> Result<bool, CommandError> {
     use regex::Regex;
     use version_compare::Version;
     let args: Vec<&OsStr> = vec!["buildx".as_ref(), "version".as_ref()];
-    let output = Command::new("docker").args(args).output()?;
+    let output = docker_command().args(args).output()?;
 
     let version_output = String::from_utf8_lossy(&output.stdout).to_ascii_lowercase();
     let semver_regex = Regex::new(r"\d+\.\d+\.\d+")?;
@@ -71,10 +83,13 @@ fn docker_buildkit_enabled() -> Result<bool, CommandError> {
     Ok(user_version >= min_version)
 }
 
+/// Sent in place of a commit hash by deploys made outside a git repository
+pub const NO_GIT_INFO: &str = "no git info available";
+
 pub fn get_git_hash() -> String {
     match try_get_git_hash() {
         Ok(info) => info,
-        Err(_) => "no git info available".to_string(),
+        Err(_) => NO_GIT_INFO.to_string(),
     }
 }
 
@@ -113,7 +128,7 @@ pub fn build_image(
     ]
     .concat();
 
-    let command_status = Command::new("docker")
+    let command_status = docker_command()
         .args(build_image_args)
         .stdout(command_config.output_setting())
         .stderr(command_config.output_setting())
@@ -122,6 +137,7 @@ pub fn build_image(
     Ok(command_status)
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn build_image_repro(
     dockerfile_path: &std::path::Path,
     tag_name: &str,
@@ -129,8 +145,10 @@ pub fn build_image_repro(
     verbose: bool,
     timestamp: String,
     no_cache: bool,
+    docker_config_dir: Option<&Path>,
+    platform: &Platform,
 ) -> Result<ExitStatus, CommandError> {
-    let command_config = CommandConfig::new(verbose, no_cache);
+    let command_config = CommandConfig::new(verbose, no_cache).with_platform(platform);
     let build_image_args = if docker_buildkit_enabled()? {
         log::info!("Docker version is reproducible build compatible");
         [
@@ -163,7 +181,11 @@ pub fn build_image_repro(
         .concat()
     };
 
-    let command_status = Command::new("docker")
+    let mut build_command = docker_command();
+    if let Some(docker_config_dir) = docker_config_dir {
+        build_command.env("DOCKER_CONFIG", docker_config_dir);
+    }
+    let command_status = build_command
         .env("SOURCE_DATE_EPOCH", timestamp)
         .args(build_image_args)
         .stdout(command_config.output_setting())
@@ -192,7 +214,7 @@ pub fn run_image(
 
     let run_args = [run_image_args, command_line_args].concat();
 
-    let command_output = Command::new("docker")
+    let command_output = docker_command()
         .args(run_args)
         .stdout(Stdio::piped())
         .stderr(command_config.output_setting())
@@ -201,8 +223,227 @@ pub fn run_image(
     Ok(command_output)
 }
 
+/// Runs an image in a container with the given name, so it can be removed if the daemon fails to
+/// clean it up. Both stdout and stderr are captured.
+pub fn run_named_image(
+    container_name: &str,
+    image_name: &str,
+    volumes: Vec<&str>,
+    command_line_args: Vec<&OsStr>,
+) -> Result<Output, CommandError> {
+    let mut run_image_args: Vec<&OsStr> = vec![
+        "run".as_ref(),
+        "--rm".as_ref(),
+        "--name".as_ref(),
+        container_name.as_ref(),
+    ];
+    for &volume in volumes.iter() {
+        run_image_args.push("-v".as_ref());
+        run_image_args.push(volume.as_ref());
+    }
+    run_image_args.push(image_name.as_ref());
+
+    let command_output = docker_command()
+        .args([run_image_args, command_line_args].concat())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()?;
+    Ok(command_output)
+}
+
+pub fn remove_container(container_name: &str) -> Result<(), CommandError> {
+    checked_docker_command(&["rm".as_ref(), "--force".as_ref(), container_name.as_ref()])?;
+    Ok(())
+}
+
+/// Runs an image with stdin and stderr attached to the terminal and `workdir` as its working
+/// directory, returning its stdout
+pub fn run_image_in_workdir(
+    image_name: &str,
+    volumes: Vec<&str>,
+    workdir: &str,
+    command_line_args: Vec<&OsStr>,
+) -> Result<Output, CommandError> {
+    let mut run_image_args: Vec<&OsStr> = vec!["run".as_ref(), "--rm".as_ref(), "-i".as_ref()];
+    for &volume in volumes.iter() {
+        run_image_args.push("-v".as_ref());
+        run_image_args.push(volume.as_ref());
+    }
+    run_image_args.push("-w".as_ref());
+    run_image_args.push(workdir.as_ref());
+    run_image_args.push(image_name.as_ref());
+
+    let command_output = docker_command()
+        .args([run_image_args, command_line_args].concat())
+        .stdin(Stdio::inherit())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .output()?;
+    Ok(command_output)
+}
+
+// Runs a docker command which must succeed, returning its output
+fn checked_docker_command(args: &[&OsStr]) -> Result<Output, CommandError> {
+    let output = docker_command().args(args).output()?;
+    if !output.status.success() {
+        let command = args
+            .first()
+            .map(|command| command.to_string_lossy().to_string())
+            .unwrap_or_default();
+        return Err(CommandError::CommandFailed {
+            command,
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+    Ok(output)
+}
+
+/// Runs an image on a remote docker host, which can't mount local directories. Local paths are
+/// copied into the container before it starts, and container paths are copied back out once it
+/// exits successfully.
+pub fn run_image_with_copies(
+    image_name: &str,
+    volumes: Vec<&str>,
+    command_line_args: Vec<&OsStr>,
+    copy_in: &[(&Path, &str)],
+    copy_out: &[(&str, &Path)],
+    stderr: Stdio,
+) -> Result<Output, CommandError> {
+    let mut create_args: Vec<&OsStr> = vec!["create".as_ref()];
+    for &volume in volumes.iter() {
+        create_args.push("-v".as_ref());
+        create_args.push(volume.as_ref());
+    }
+    create_args.push(image_name.as_ref());
+    let create_args = [create_args, command_line_args].concat();
+    let created = checked_docker_command(&create_args)?;
+    let container_id = String::from_utf8_lossy(&created.stdout).trim().to_string();
+
+    let run_in_container = || -> Result<Output, CommandError> {
+        for (local_path, container_path) in copy_in {
+            let destination = format!("{container_id}:{container_path}");
+            checked_docker_command(&["cp".as_ref(), local_path.as_os_str(), destination.as_ref()])?;
+        }
+        let output = docker_command()
+            .args(["start", "--attach", container_id.as_str()])
+            .stdout(Stdio::piped())
+            .stderr(stderr)
+            .output()?;
+        if output.status.success() {
+            for (container_path, local_path) in copy_out {
+                let source = format!("{container_id}:{container_path}");
+                checked_docker_command(&["cp".as_ref(), source.as_ref(), local_path.as_os_str()])?;
+            }
+        }
+        Ok(output)
+    };
+    let result = run_in_container();
+
+    if let Err(e) =
+        checked_docker_command(&["rm".as_ref(), "--force".as_ref(), container_id.as_ref()])
+    {
+        log::debug!("Failed to remove container {container_id} — {e}");
+    }
+    result
+}
+
+/// Runs a shell script in the given image which prints each of the given commands that can't be
+/// found on the image's PATH, one per line.
+pub fn probe_image_commands(image_name: &str, commands: &[&str]) -> Result<Output, CommandError> {
+    let probe_script = format!(
+        "for cmd in {}; do command -v \"$cmd\" > /dev/null 2>&1 || echo \"$cmd\"; done",
+        commands.join(" ")
+    );
+
+    let command_output = docker_command()
+        .args([
+            "run",
+            "--rm",
+            "--entrypoint",
+            "/bin/sh",
+            image_name,
+            "-c",
+            probe_script.as_str(),
+        ])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()?;
+
+    Ok(command_output)
+}
+
+/// The daemon's os/arch, which is the platform docker builds images for natively
+pub fn docker_server_platform() -> Result<String, CommandError> {
+    let output = docker_command()
+        .args(["version", "--format", "{{.Server.Os}}/{{.Server.Arch}}"])
+        .output()?;
+    if !output.status.success() {
+        return Err(CommandError::CommandFailed {
+            command: "version".into(),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Size in bytes of a local image, as reported by the daemon
+pub fn image_size(image_name: &str) -> Result<u64, CommandError> {
+    let output = docker_command()
+        .args(["image", "inspect", "--format", "{{.Size}}", image_name])
+        .output()?;
+    if !output.status.success() {
+        return Err(CommandError::CommandFailed {
+            command: "image inspect".into(),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse()
+        .map_err(|_| CommandError::CommandFailed {
+            command: "image inspect".into(),
+            stderr: "Image size was not a number".into(),
+        })
+}
+
+/// Content-addressed ID of a local image, which changes whenever its layers or config do
+pub fn image_id(image_name: &str) -> Result<String, CommandError> {
+    let output = docker_command()
+        .args(["image", "inspect", "--format", "{{.Id}}", image_name])
+        .output()?;
+    if !output.status.success() {
+        return Err(CommandError::CommandFailed {
+            command: "image inspect".into(),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Fetches an image's manifest, or the index of its platform variants, from its registry
+/// without pulling it.
+pub fn inspect_image_manifest(
+    image_name: &str,
+    docker_config_dir: Option<&Path>,
+) -> Result<Vec<u8>, CommandError> {
+    let mut inspect_command = docker_command();
+    if let Some(docker_config_dir) = docker_config_dir {
+        inspect_command.env("DOCKER_CONFIG", docker_config_dir);
+    }
+    let output = inspect_command
+        .args(["manifest", "inspect", image_name])
+        .output()?;
+    if !output.status.success() {
+        return Err(CommandError::CommandFailed {
+            command: "manifest inspect".into(),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+    Ok(output.stdout)
+}
+
 pub fn docker_info() -> Result<ExitStatus, CommandError> {
-    let status = std::process::Command::new("docker")
+    let status = docker_command()
         .args(["info"])
         .stdout(std::process::Stdio::null())
         .stderr(std::process::Stdio::null())
@@ -0,0 +1,470 @@
+use super::error::CredentialError;
+use super::parse::Directive;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+const DOCKER_CONFIG_FILENAME: &str = "config.json";
+const DOCKER_HUB_REGISTRY: &str = "docker.io";
+// Docker Hub logins are keyed by the legacy index URL in the docker config.
+const DOCKER_HUB_AUTH_KEY: &str = "https://index.docker.io/v1/";
+// Credential helpers return this username when the secret is an identity token rather than a
+// password.
+const IDENTITY_TOKEN_USERNAME: &str = "<token>";
+
+// The parts of ~/.docker/config.json needed to find a registry's credentials. Everything else is
+// kept so the config can be written back out for the build.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct DockerConfig {
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    auths: BTreeMap<String, serde_json::Value>,
+    #[serde(
+        rename = "credsStore",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    creds_store: Option<String>,
+    #[serde(
+        rename = "credHelpers",
+        default,
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    cred_helpers: BTreeMap<String, String>,
+    #[serde(flatten)]
+    other: serde_json::Map<String, serde_json::Value>,
+}
+
+impl DockerConfig {
+    pub fn load(config_dir: &Path) -> Result<Option<Self>, CredentialError> {
+        let config_path = config_dir.join(DOCKER_CONFIG_FILENAME);
+        let contents = match std::fs::read(&config_path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => {
+                return Err(CredentialError::ConfigReadError(
+                    config_path.display().to_string(),
+                    e,
+                ))
+            }
+        };
+        serde_json::from_slice(&contents)
+            .map(Some)
+            .map_err(|e| CredentialError::ConfigParseError(config_path.display().to_string(), e))
+    }
+
+    // The credential helper docker would use to pull from a registry. A credsStore is only used
+    // for registries which have been logged in to, so anonymous pulls of public images don't
+    // depend on it.
+    pub fn helper_for(&self, registry: &str) -> Option<&str> {
+        let keys = registry_keys(registry);
+        keys.iter()
+            .find_map(|key| self.cred_helpers.get(*key))
+            .or_else(|| {
+                keys.iter()
+                    .any(|key| self.auths.contains_key(*key))
+                    .then_some(self.creds_store.as_ref())
+                    .flatten()
+            })
+            .map(String::as_str)
+    }
+
+    // Overrides the resolved registries only. Registries which weren't resolved, such as those
+    // chosen by build args or with no stored login, are left to the user's credsStore and
+    // credHelpers. Docker prefers a credHelpers entry, then the credsStore, over `auths`, so each
+    // resolved registry gets an empty helper to have its credentials read from `auths` instead.
+    fn with_resolved_credentials(mut self, credentials: &[(String, HelperCredentials)]) -> Self {
+        for (registry, registry_credentials) in credentials {
+            for key in registry_keys(registry) {
+                self.cred_helpers.remove(key);
+                self.auths.remove(key);
+            }
+            let key = auth_key(registry).to_string();
+            self.auths
+                .insert(key.clone(), registry_credentials.auth_entry());
+            if self.creds_store.is_some() {
+                self.cred_helpers.insert(key, String::new());
+            }
+        }
+        self
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HelperCredentials {
+    #[serde(rename = "Username")]
+    username: String,
+    #[serde(rename = "Secret")]
+    secret: String,
+}
+
+impl HelperCredentials {
+    fn auth_entry(&self) -> serde_json::Value {
+        if self.username == IDENTITY_TOKEN_USERNAME {
+            serde_json::json!({ "identitytoken": self.secret })
+        } else {
+            let auth = base64::encode(format!("{}:{}", self.username, self.secret));
+            serde_json::json!({ "auth": auth })
+        }
+    }
+}
+
+// A docker config directory holding the credentials resolved for a build. It's deleted when
+// dropped, so must be kept in scope until the build has finished.
+pub struct BuildCredentials {
+    config_dir: tempfile::TempDir,
+}
+
+impl BuildCredentials {
+    pub fn docker_config_dir(&self) -> &Path {
+        self.config_dir.path()
+    }
+}
+
+pub fn docker_config_dir() -> Option<PathBuf> {
+    if let Some(config_dir) = std::env::var_os("DOCKER_CONFIG") {
+        return Some(PathBuf::from(config_dir));
+    }
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(|home| PathBuf::from(home).join(".docker"))
+}
+
+// The registry an image is pulled from. Images without a registry host come from Docker Hub.
+pub fn registry_for_image(image: &str) -> &str {
+    match image.split_once('/') {
+        Some((host, _)) if host.contains('.') || host.contains(':') || host == "localhost" => host,
+        _ => DOCKER_HUB_REGISTRY,
+    }
+}
+
+fn registry_keys(registry: &str) -> Vec<&str> {
+    if registry == DOCKER_HUB_REGISTRY {
+        vec![DOCKER_HUB_REGISTRY, "index.docker.io", DOCKER_HUB_AUTH_KEY]
+    } else {
+        vec![registry]
+    }
+}
+
+fn auth_key(registry: &str) -> &str {
+    if registry == DOCKER_HUB_REGISTRY {
+        DOCKER_HUB_AUTH_KEY
+    } else {
+        registry
+    }
+}
+
+// The registries of the images pulled by a Dockerfile, skipping scratch, earlier build stages and
+// images chosen by build args, which can't be known until the build runs.
+pub fn base_image_registries(directives: &[Directive]) -> Vec<String> {
+    let mut stages = HashSet::new();
+    let mut registries = Vec::new();
+    for (image, alias) in directives.iter().filter_map(Directive::base_image) {
+        let is_pulled = !image.eq_ignore_ascii_case("scratch")
+            && !image.contains('$')
+            && !stages.contains(&image.to_ascii_lowercase());
+        if is_pulled {
+            let registry = registry_for_image(image).to_string();
+            if !registries.contains(&registry) {
+                registries.push(registry);
+            }
+        }
+        if let Some(alias) = alias {
+            stages.insert(alias.to_ascii_lowercase());
+        }
+    }
+    registries
+}
+
+pub fn get_helper_credentials(
+    helper: &str,
+    registry: &str,
+) -> Result<Option<HelperCredentials>, CredentialError> {
+    let helper_failed =
+        |message: String| CredentialError::HelperFailed(helper.into(), registry.into(), message);
+    let mut child = Command::new(format!("docker-credential-{helper}"))
+        .arg("get")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => {
+                CredentialError::HelperNotFound(helper.into(), registry.into())
+            }
+            _ => helper_failed(e.to_string()),
+        })?;
+    child
+        .stdin
+        .take()
+        .ok_or_else(|| helper_failed("failed to write to the helper's stdin".into()))?
+        .write_all(registry.as_bytes())
+        .map_err(|e| helper_failed(e.to_string()))?;
+    let output = child
+        .wait_with_output()
+        .map_err(|e| helper_failed(e.to_string()))?;
+
+    if !output.status.success() {
+        let message = if output.stdout.is_empty() {
+            String::from_utf8_lossy(&output.stderr)
+        } else {
+            String::from_utf8_lossy(&output.stdout)
+        };
+        // Helpers report a registry they have no login for as an error, which docker treats as
+        // an anonymous pull.
+        if message
+            .to_ascii_lowercase()
+            .contains("credentials not found")
+        {
+            return Ok(None);
+        }
+        return Err(helper_failed(message.trim().to_string()));
+    }
+    serde_json::from_slice(&output.stdout)
+        .map(Some)
+        .map_err(|e| helper_failed(format!("the helper returned invalid credentials — {e}")))
+}
+
+// Resolves credentials for the registries a build pulls its base images from through the
+// credential helpers in the user's docker config, so a missing or broken helper fails the build
+// up front with a clear error. The resolved credentials are written to a private docker config
+// for the build to use, rather than relying on a prior `docker login`.
+pub fn resolve_build_credentials(
+    directives: &[Directive],
+) -> Result<Option<BuildCredentials>, CredentialError> {
+    let Some(config_dir) = docker_config_dir() else {
+        return Ok(None);
+    };
+    let Some(config) = DockerConfig::load(&config_dir)? else {
+        return Ok(None);
+    };
+
+    let mut credentials = Vec::new();
+    for registry in base_image_registries(directives) {
+        let Some(helper) = config.helper_for(&registry) else {
+            continue;
+        };
+        log::debug!("Getting credentials for {registry} from docker-credential-{helper}");
+        if let Some(registry_credentials) = get_helper_credentials(helper, &registry)? {
+            credentials.push((registry, registry_credentials));
+        }
+    }
+    if credentials.is_empty() {
+        return Ok(None);
+    }
+
+    let config = config.with_resolved_credentials(&credentials);
+    write_build_config(&config_dir, &config)
+        .map(|config_dir| Some(BuildCredentials { config_dir }))
+        .map_err(CredentialError::ConfigWriteError)
+}
+
+fn write_build_config(
+    user_config_dir: &Path,
+    config: &DockerConfig,
+) -> Result<tempfile::TempDir, std::io::Error> {
+    let config_dir = tempfile::Builder::new()
+        .prefix("ev-docker-config")
+        .tempdir()?;
+    // Link everything else in the user's config directory, so buildx builders, CLI plugins and
+    // contexts still resolve.
+    if let Ok(entries) = std::fs::read_dir(user_config_dir) {
+        for entry in entries.flatten() {
+            if entry.file_name() != DOCKER_CONFIG_FILENAME {
+                link_config_entry(&entry.path(), &config_dir.path().join(entry.file_name()))?;
+            }
+        }
+    }
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut config_file = options.open(config_dir.path().join(DOCKER_CONFIG_FILENAME))?;
+    serde_json::to_writer_pretty(&mut config_file, config)?;
+    Ok(config_dir)
+}
+
+#[cfg(unix)]
+fn link_config_entry(source: &Path, destination: &Path) -> Result<(), std::io::Error> {
+    std::os::unix::fs::symlink(source, destination)
+}
+
+#[cfg(windows)]
+fn link_config_entry(source: &Path, destination: &Path) -> Result<(), std::io::Error> {
+    if source.is_dir() {
+        std::os::windows::fs::symlink_dir(source, destination)
+    } else {
+        std::os::windows::fs::symlink_file(source, destination)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn from(arguments: &str) -> Directive {
+        Directive::From {
+            arguments: arguments.to_string().into(),
+        }
+    }
+
+    fn config(contents: serde_json::Value) -> DockerConfig {
+        serde_json::from_value(contents).unwrap()
+    }
+
+    #[test]
+    fn test_registry_for_image() {
+        assert_eq!(registry_for_image("alpine"), "docker.io");
+        assert_eq!(registry_for_image("library/node:18"), "docker.io");
+        assert_eq!(
+            registry_for_image("123456789012.dkr.ecr.us-east-1.amazonaws.com/app:latest"),
+            "123456789012.dkr.ecr.us-east-1.amazonaws.com"
+        );
+        assert_eq!(registry_for_image("localhost:5000/app"), "localhost:5000");
+        assert_eq!(registry_for_image("localhost/app"), "localhost");
+    }
+
+    #[test]
+    fn test_base_image_registries_skip_stages_and_scratch() {
+        let directives = vec![
+            from("--platform=linux/amd64 gcr.io/project/builder:1 AS build"),
+            from("build"),
+            from("scratch"),
+            from("${BASE_IMAGE}"),
+            from("gcr.io/project/runtime:1"),
+            from("alpine:3.18"),
+        ];
+        assert_eq!(
+            base_image_registries(&directives),
+            vec!["gcr.io".to_string(), "docker.io".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_helper_for_registry() {
+        let config = config(serde_json::json!({
+            "auths": { "https://index.docker.io/v1/": {} },
+            "credsStore": "desktop",
+            "credHelpers": {
+                "123456789012.dkr.ecr.us-east-1.amazonaws.com": "ecr-login",
+                "gcr.io": "gcloud"
+            }
+        }));
+        assert_eq!(
+            config.helper_for("123456789012.dkr.ecr.us-east-1.amazonaws.com"),
+            Some("ecr-login")
+        );
+        assert_eq!(config.helper_for("gcr.io"), Some("gcloud"));
+        assert_eq!(config.helper_for("docker.io"), Some("desktop"));
+        assert_eq!(config.helper_for("quay.io"), None);
+    }
+
+    #[test]
+    fn test_resolved_credentials_replace_helpers() {
+        let config = config(serde_json::json!({
+            "auths": { "https://index.docker.io/v1/": {} },
+            "credsStore": "desktop",
+            "credHelpers": { "gcr.io": "gcloud", "quay.io": "quay" },
+            "currentContext": "default"
+        }));
+        let credentials = vec![
+            (
+                "gcr.io".to_string(),
+                HelperCredentials {
+                    username: IDENTITY_TOKEN_USERNAME.into(),
+                    secret: "token".into(),
+                },
+            ),
+            (
+                "docker.io".to_string(),
+                HelperCredentials {
+                    username: "user".into(),
+                    secret: "pass".into(),
+                },
+            ),
+        ];
+        let resolved =
+            serde_json::to_value(config.with_resolved_credentials(&credentials)).unwrap();
+        assert_eq!(
+            resolved,
+            serde_json::json!({
+                "auths": {
+                    "gcr.io": { "identitytoken": "token" },
+                    "https://index.docker.io/v1/": { "auth": "dXNlcjpwYXNz" }
+                },
+                "credsStore": "desktop",
+                "credHelpers": {
+                    "gcr.io": "",
+                    "https://index.docker.io/v1/": "",
+                    "quay.io": "quay"
+                },
+                "currentContext": "default"
+            })
+        );
+    }
+
+    #[test]
+    fn test_resolved_credentials_keep_store_for_unresolved_registries() {
+        let config = config(serde_json::json!({
+            "auths": { "quay.io": {}, "gcr.io": {} },
+            "credsStore": "desktop"
+        }));
+        let credentials = vec![(
+            "gcr.io".to_string(),
+            HelperCredentials {
+                username: "user".into(),
+                secret: "pass".into(),
+            },
+        )];
+        let resolved = config.with_resolved_credentials(&credentials);
+        assert_eq!(resolved.helper_for("quay.io"), Some("desktop"));
+        assert_eq!(
+            serde_json::to_value(resolved).unwrap(),
+            serde_json::json!({
+                "auths": {
+                    "gcr.io": { "auth": "dXNlcjpwYXNz" },
+                    "quay.io": {}
+                },
+                "credsStore": "desktop",
+                "credHelpers": { "gcr.io": "" }
+            })
+        );
+    }
+
+    #[test]
+    fn test_missing_helper_is_reported() {
+        let result = get_helper_credentials("ev-test-missing-helper", "gcr.io");
+        assert!(matches!(
+            result,
+            Err(CredentialError::HelperNotFound(helper, registry))
+                if helper == "ev-test-missing-helper" && registry == "gcr.io"
+        ));
+    }
+
+    #[test]
+    fn test_build_config_links_user_config_dir() {
+        let user_config_dir = tempfile::tempdir().unwrap();
+        std::fs::write(user_config_dir.path().join("config.json"), "{}").unwrap();
+        std::fs::create_dir(user_config_dir.path().join("cli-plugins")).unwrap();
+        let config = config(serde_json::json!({ "auths": { "gcr.io": { "auth": "abc" } } }));
+
+        let build_config_dir = write_build_config(user_config_dir.path(), &config).unwrap();
+        assert!(build_config_dir.path().join("cli-plugins").is_dir());
+        let written: serde_json::Value = serde_json::from_slice(
+            &std::fs::read(build_config_dir.path().join("config.json")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(written, serde_json::to_value(&config).unwrap());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(build_config_dir.path().join("config.json"))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+}
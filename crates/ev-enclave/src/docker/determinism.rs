@@ -0,0 +1,129 @@
+use super::format::fold_line_continuations;
+use super::parse::Directive;
+use regex::Regex;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// A directive which is likely to produce different image contents, and so different PCRs,
+/// between otherwise identical builds
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NonDeterministicPattern {
+    pub rule: &'static str,
+    pub severity: Severity,
+    pub directive: String,
+    pub hint: &'static str,
+}
+
+impl std::fmt::Display for NonDeterministicPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}] `{}` - {}", self.rule, self.directive, self.hint)
+    }
+}
+
+struct Rule {
+    name: &'static str,
+    severity: Severity,
+    pattern: &'static str,
+    // the rule doesn't apply if the command also matches this pattern
+    unless: Option<&'static str>,
+    hint: &'static str,
+}
+
+const RULES: [Rule; 3] = [
+    Rule {
+        name: "apt-unpinned",
+        severity: Severity::Warning,
+        pattern: r"\bapt(-get)?\s+update\b",
+        unless: Some(r"snapshot\.(debian\.org|ubuntu\.com)"),
+        hint: "apt-get update fetches the latest package index at build time. Point apt at a snapshot repository (e.g. snapshot.debian.org) and pin package versions.",
+    },
+    Rule {
+        name: "pip-unhashed",
+        severity: Severity::Warning,
+        pattern: r"\bpip3?\s+install\b",
+        unless: Some(r"--require-hashes"),
+        hint: "pip install resolves the latest matching versions at build time. Install from a requirements file with hashes using --require-hashes.",
+    },
+    Rule {
+        name: "remote-script",
+        severity: Severity::Error,
+        pattern: r"\b(curl|wget)\b[^|;&]*\|\s*(sudo\s+)?(ba|z|da)?sh\b",
+        unless: None,
+        hint: "Piping a remote script into a shell runs whatever the server returns at build time. Download a pinned version of the script into your build context and verify its checksum.",
+    },
+];
+
+/// Scans RUN directives for commands whose results depend on when the build is run
+pub fn find_non_deterministic_patterns(directives: &[Directive]) -> Vec<NonDeterministicPattern> {
+    let rules: Vec<(&Rule, Regex, Option<Regex>)> = RULES
+        .iter()
+        .map(|rule| {
+            (
+                rule,
+                Regex::new(rule.pattern).expect("Infallible - static regex"),
+                rule.unless
+                    .map(|unless| Regex::new(unless).expect("Infallible - static regex")),
+            )
+        })
+        .collect();
+
+    directives
+        .iter()
+        .filter_map(|directive| match directive {
+            Directive::Run(arguments) => Some(fold_line_continuations(arguments)),
+            _ => None,
+        })
+        .flat_map(|command| {
+            rules
+                .iter()
+                .filter(|(_, pattern, unless)| {
+                    pattern.is_match(&command)
+                        && !unless
+                            .as_ref()
+                            .is_some_and(|unless| unless.is_match(&command))
+                })
+                .map(|(rule, _, _)| NonDeterministicPattern {
+                    rule: rule.name,
+                    severity: rule.severity,
+                    directive: format!("RUN {command}"),
+                    hint: rule.hint,
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::docker::parse::DockerfileDecoder;
+
+    #[tokio::test]
+    async fn test_find_non_deterministic_patterns() {
+        let dockerfile = r#"FROM debian:bookworm
+RUN apt-get update && \
+    apt-get install -y curl
+RUN echo "deb http://snapshot.debian.org/archive/debian/20240101T000000Z bookworm main" > /etc/apt/sources.list && apt-get update
+RUN pip install flask
+RUN pip install --require-hashes -r requirements.txt
+RUN curl -fsSL https://example.com/install.sh | sudo bash
+RUN curl -o /tmp/install.sh https://example.com/install.sh
+"#;
+        let directives = DockerfileDecoder::decode_dockerfile_from_src(dockerfile.as_bytes())
+            .await
+            .unwrap();
+
+        let patterns = find_non_deterministic_patterns(&directives);
+        let rules: Vec<&str> = patterns.iter().map(|pattern| pattern.rule).collect();
+        assert_eq!(rules, vec!["apt-unpinned", "pip-unhashed", "remote-script"]);
+        assert_eq!(
+            patterns[0].directive,
+            "RUN apt-get update && apt-get install -y curl"
+        );
+        assert_eq!(patterns[2].severity, Severity::Error);
+    }
+}
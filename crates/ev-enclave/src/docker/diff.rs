@@ -0,0 +1,177 @@
+//! Diffs of generated Dockerfiles. Dockerfiles are short, so a quadratic longest common
+//! subsequence is used rather than a faster heuristic diff.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change<'a, T> {
+    Unchanged(&'a T),
+    Removed(&'a T),
+    Added(&'a T),
+}
+
+/// Diffs two sequences by their longest common subsequence. Where a removal and an addition are
+/// interchangeable, the removal comes first.
+pub fn diff<'a, T: PartialEq>(previous: &'a [T], next: &'a [T]) -> Vec<Change<'a, T>> {
+    let mut common = vec![vec![0usize; next.len() + 1]; previous.len() + 1];
+    for i in (0..previous.len()).rev() {
+        for j in (0..next.len()).rev() {
+            common[i][j] = if previous[i] == next[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut changes = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < previous.len() || j < next.len() {
+        if i < previous.len() && j < next.len() && previous[i] == next[j] {
+            changes.push(Change::Unchanged(&previous[i]));
+            i += 1;
+            j += 1;
+        } else if i < previous.len() && (j == next.len() || common[i + 1][j] >= common[i][j + 1]) {
+            changes.push(Change::Removed(&previous[i]));
+            i += 1;
+        } else {
+            changes.push(Change::Added(&next[j]));
+            j += 1;
+        }
+    }
+    changes
+}
+
+/// The changed lines of a line diff, prefixed with `-` when removed and `+` when added
+pub fn changed_lines<S: AsRef<str> + PartialEq>(previous: &[S], next: &[S]) -> Vec<String> {
+    diff(previous, next)
+        .into_iter()
+        .filter_map(|change| match change {
+            Change::Unchanged(_) => None,
+            Change::Removed(line) => Some(format!("- {}", line.as_ref())),
+            Change::Added(line) => Some(format!("+ {}", line.as_ref())),
+        })
+        .collect()
+}
+
+// Splits a line into words and the whitespace between them, so a line can be rebuilt exactly
+fn tokenize(line: &str) -> Vec<&str> {
+    let mut tokens = vec![];
+    let mut start = 0;
+    let mut in_whitespace = None;
+    for (index, c) in line.char_indices() {
+        let is_whitespace = c.is_whitespace();
+        if in_whitespace.is_some_and(|was_whitespace| was_whitespace != is_whitespace) {
+            tokens.push(&line[start..index]);
+            start = index;
+        }
+        in_whitespace = Some(is_whitespace);
+    }
+    if start < line.len() {
+        tokens.push(&line[start..]);
+    }
+    tokens
+}
+
+fn word_diff_line(previous: &str, next: &str) -> String {
+    let previous_tokens = tokenize(previous);
+    let next_tokens = tokenize(next);
+    let mut rendered = String::new();
+    let mut removed = String::new();
+    let mut added = String::new();
+    let flush = |rendered: &mut String, removed: &mut String, added: &mut String| {
+        if !removed.is_empty() {
+            rendered.push_str(&format!("[-{removed}-]"));
+            removed.clear();
+        }
+        if !added.is_empty() {
+            rendered.push_str(&format!("{{+{added}+}}"));
+            added.clear();
+        }
+    };
+    for change in diff(&previous_tokens, &next_tokens) {
+        match change {
+            Change::Unchanged(token) => {
+                flush(&mut rendered, &mut removed, &mut added);
+                rendered.push_str(token);
+            }
+            Change::Removed(token) => removed.push_str(token),
+            Change::Added(token) => added.push_str(token),
+        }
+    }
+    flush(&mut rendered, &mut removed, &mut added);
+    rendered
+}
+
+/// Renders a diff of two Dockerfiles in the style of `git diff --word-diff`, marking removed words
+/// with `[-...-]` and added words with `{+...+}`. Lines changed in place are diffed word by word,
+/// and unchanged lines are kept so each change can be read in context.
+pub fn word_diff(previous: &str, next: &str) -> String {
+    let previous_lines: Vec<&str> = previous.lines().collect();
+    let next_lines: Vec<&str> = next.lines().collect();
+
+    let mut rendered = vec![];
+    let mut removed: Vec<&str> = vec![];
+    let mut added: Vec<&str> = vec![];
+    let flush = |rendered: &mut Vec<String>, removed: &mut Vec<&str>, added: &mut Vec<&str>| {
+        // a run of removed lines followed by added lines is treated as lines edited in place
+        let edited = removed.len().min(added.len());
+        for (previous_line, next_line) in removed.iter().zip(added.iter()) {
+            rendered.push(word_diff_line(previous_line, next_line));
+        }
+        for line in &removed[edited..] {
+            rendered.push(format!("[-{line}-]"));
+        }
+        for line in &added[edited..] {
+            rendered.push(format!("{{+{line}+}}"));
+        }
+        removed.clear();
+        added.clear();
+    };
+    for change in diff(&previous_lines, &next_lines) {
+        match change {
+            Change::Unchanged(line) => {
+                flush(&mut rendered, &mut removed, &mut added);
+                rendered.push(line.to_string());
+            }
+            Change::Removed(line) => removed.push(line),
+            Change::Added(line) => added.push(line),
+        }
+    }
+    flush(&mut rendered, &mut removed, &mut added);
+    rendered.join("\n")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_changed_lines() {
+        let previous = [
+            "FROM alpine",
+            "RUN apk add curl",
+            "ENTRYPOINT [\"/start.sh\"]",
+        ];
+        let next = [
+            "FROM alpine",
+            "RUN apk add curl jq",
+            "ENTRYPOINT [\"/start.sh\"]",
+        ];
+        assert_eq!(
+            changed_lines(&previous, &next),
+            vec!["- RUN apk add curl", "+ RUN apk add curl jq"]
+        );
+        assert!(changed_lines(&previous, &previous).is_empty());
+    }
+
+    #[test]
+    fn test_word_diff() {
+        let previous = "FROM alpine\nRUN /opt/evervault/installer.sh 1.0.0 --quiet\nUSER root";
+        let next =
+            "FROM alpine\nRUN /opt/evervault/installer.sh 1.1.0 --quiet\nUSER root\nENV EV_TLS=1";
+        assert_eq!(
+            word_diff(previous, next),
+            "FROM alpine\nRUN /opt/evervault/installer.sh [-1.0.0-]{+1.1.0+} --quiet\nUSER root\n{+ENV EV_TLS=1+}"
+        );
+        assert_eq!(word_diff(previous, previous), previous);
+    }
+}
@@ -17,6 +17,8 @@ pub enum CommandError {
     RegexError(#[from] regex::Error),
     #[error("Failed to parse semver versions")]
     SemverParseError,
+    #[error("docker {command} failed — {stderr}")]
+    CommandFailed { command: String, stderr: String },
 }
 
 impl CliError for CommandError {
@@ -40,4 +42,48 @@ pub enum DockerError {
     RestrictedPortExposed(u16),
     #[error(transparent)]
     CommandError(#[from] CommandError),
+    #[error(transparent)]
+    CredentialError(#[from] CredentialError),
+    #[error(transparent)]
+    PlatformError(#[from] PlatformError),
+}
+
+#[derive(Debug, Error)]
+pub enum CredentialError {
+    #[error("Failed to read your docker config at {0} — {1}")]
+    ConfigReadError(String, std::io::Error),
+    #[error("Failed to parse your docker config at {0} — {1}")]
+    ConfigParseError(String, serde_json::Error),
+    #[error("Your docker config uses docker-credential-{0} for {1}, but it isn't installed. Install the credential helper and make sure it's on your PATH.")]
+    HelperNotFound(String, String),
+    #[error("docker-credential-{0} couldn't get credentials for {1} — {2}")]
+    HelperFailed(String, String, String),
+    #[error("Failed to write the docker config used for the build — {0}")]
+    ConfigWriteError(std::io::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum PlatformError {
+    #[error("Invalid platform `{0}`. Expected a platform of the form os/arch[/variant], such as linux/amd64.")]
+    InvalidPlatform(String),
+    #[error("Enclaves can only run linux/amd64 images, so they can't be built for {0}.")]
+    UnsupportedPlatform(String),
+    #[error("The final stage of your Dockerfile pins {image} to {platform}, but Enclaves run {target} images. Remove --platform from its FROM instruction, or set it to {target}.")]
+    FinalStagePlatform {
+        image: String,
+        platform: String,
+        target: String,
+    },
+    #[error("The base image {image} has no {target} variant, so it can't run in an Enclave. It's published for {available}. Use a base image, or tag, which supports {target}.")]
+    MissingPlatformVariant {
+        image: String,
+        target: String,
+        available: String,
+    },
+}
+
+impl CliError for PlatformError {
+    fn exitcode(&self) -> exitcode::ExitCode {
+        exitcode::DATAERR
+    }
 }
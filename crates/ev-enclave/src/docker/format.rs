@@ -0,0 +1,221 @@
+use super::parse::{Delimiter, Directive, EnvVar};
+use bytes::Bytes;
+
+// Rewrites a decoded directive into its canonical form: upper case instructions and keywords,
+// `KEY=value` env vars, and line continuations folded onto a single line.
+fn canonicalize_directive(directive: &Directive) -> Directive {
+    match directive {
+        Directive::Comment(content) => Directive::Comment(Bytes::from(
+            String::from_utf8_lossy(content).trim().to_string(),
+        )),
+        Directive::From { arguments } => {
+            let arguments = fold_line_continuations(arguments)
+                .split_whitespace()
+                .map(|token| {
+                    if token.eq_ignore_ascii_case("as") {
+                        "AS"
+                    } else {
+                        token
+                    }
+                })
+                .collect::<Vec<&str>>()
+                .join(" ");
+            Directive::From {
+                arguments: arguments.into(),
+            }
+        }
+        Directive::Env { vars } => {
+            Directive::new_env(vars.iter().map(canonicalize_env_var).collect())
+        }
+        Directive::Run(arguments) => Directive::new_run(fold_line_continuations(arguments)),
+        Directive::User(arguments) => Directive::new_user(fold_line_continuations(arguments)),
+        Directive::Other {
+            directive,
+            arguments,
+        } => Directive::Other {
+            directive: directive.to_ascii_uppercase(),
+            arguments: fold_line_continuations(arguments).into(),
+        },
+        _ => directive.clone(),
+    }
+}
+
+fn canonicalize_env_var(var: &EnvVar) -> EnvVar {
+    // The decoder drops the continuation character of a value spanning lines but keeps the
+    // newline, which Docker removes while keeping the whitespace around it
+    let val = var.val.replace("\r\n", "").replace('\n', "");
+    let val = match var.delim {
+        // `ENV KEY multiple words` treats the remainder of the line as the value
+        Delimiter::None if val.contains(char::is_whitespace) && !val.starts_with('"') => {
+            format!("\"{}\"", val.replace('"', "\\\""))
+        }
+        _ => val,
+    };
+    EnvVar {
+        key: var.key.clone(),
+        val,
+        delim: Delimiter::Eq,
+    }
+}
+
+// Folds continued lines onto one. Outside of quotes the whitespace around a continuation is
+// collapsed to a single space, while quoted values keep every character, as Docker's parser does.
+pub(super) fn fold_line_continuations(arguments: &[u8]) -> String {
+    let arguments = String::from_utf8_lossy(arguments);
+    let continuation =
+        regex::Regex::new(r"\\[ \t]*\r?\n").expect("Infallible - valid continuation regex");
+    let mut folded = String::new();
+    let mut quote = None;
+    for segment in continuation.split(&arguments) {
+        let segment = match quote {
+            Some(_) => segment,
+            None => {
+                let segment = segment.trim_start();
+                if segment.trim_end().is_empty() {
+                    continue;
+                }
+                folded.truncate(folded.trim_end().len());
+                if !folded.is_empty() {
+                    folded.push(' ');
+                }
+                segment
+            }
+        };
+        folded.push_str(segment);
+        quote = quote_after(segment, quote);
+    }
+    folded.truncate(folded.trim_end().len());
+    folded
+}
+
+// The quote left open at the end of `segment`, given the quote open at its start
+fn quote_after(segment: &str, mut quote: Option<char>) -> Option<char> {
+    let mut chars = segment.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some('\''), '\'') => quote = None,
+            (Some('\''), _) => {}
+            (_, '\\') => {
+                chars.next();
+            }
+            (None, '"' | '\'') => quote = Some(c),
+            (Some('"'), '"') => quote = None,
+            _ => {}
+        }
+    }
+    quote
+}
+
+/// Renders a decoded Dockerfile in canonical form, one directive per line with a blank line
+/// between build stages.
+pub fn format_dockerfile(directives: &[Directive]) -> String {
+    let mut formatted = String::new();
+    let mut in_stage = false;
+    for directive in directives {
+        if directive.is_from() {
+            if in_stage {
+                formatted.push('\n');
+            }
+            in_stage = true;
+        }
+        let line = canonicalize_directive(directive).to_string();
+        formatted.push_str(line.trim_end());
+        formatted.push('\n');
+    }
+    formatted
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::docker::parse::DockerfileDecoder;
+
+    #[tokio::test]
+    async fn test_format_dockerfile() {
+        let dockerfile = r#"#   builder stage
+from node:18 as builder
+copy . /app
+run npm ci && \
+    npm run build
+
+FROM alpine:3.18
+env GREETING hello world
+ENV A=1 B=2
+entrypoint ["node",   "server.js"]
+"#;
+        let directives = DockerfileDecoder::decode_dockerfile_from_src(dockerfile.as_bytes())
+            .await
+            .unwrap();
+
+        let expected = r#"# builder stage
+FROM node:18 AS builder
+COPY . /app
+RUN npm ci && npm run build
+
+FROM alpine:3.18
+ENV GREETING="hello world"
+ENV A=1 B=2
+ENTRYPOINT ["node", "server.js"]
+"#;
+        let formatted = format_dockerfile(&directives);
+        assert_eq!(formatted, expected);
+
+        // formatting is idempotent
+        let reformatted = format_dockerfile(
+            &DockerfileDecoder::decode_dockerfile_from_src(formatted.as_bytes())
+                .await
+                .unwrap(),
+        );
+        assert_eq!(reformatted, expected);
+    }
+
+    #[test]
+    fn test_fold_line_continuations() {
+        assert_eq!(
+            fold_line_continuations(b"npm ci && \\\n    npm run build"),
+            "npm ci && npm run build"
+        );
+        assert_eq!(
+            fold_line_continuations(b"apk add \\\n\\\n    curl"),
+            "apk add curl"
+        );
+    }
+
+    #[test]
+    fn test_fold_line_continuations_in_quotes() {
+        assert_eq!(
+            fold_line_continuations(b"echo \"first \\\n    second\" && \\\n    echo done"),
+            "echo \"first     second\" && echo done"
+        );
+        assert_eq!(
+            fold_line_continuations(b"echo 'a \\\n\\\n  b' \\\n  c"),
+            "echo 'a   b' c"
+        );
+        // an escaped quote doesn't end the quoted value
+        assert_eq!(
+            fold_line_continuations(b"echo \"say \\\"hi\\\" \\\n  there\""),
+            "echo \"say \\\"hi\\\"   there\""
+        );
+    }
+
+    #[tokio::test]
+    async fn test_format_dockerfile_quoted_multi_line_values() {
+        let dockerfile = "FROM alpine\nRUN echo \"hello \\\n    world\" > /greeting\n";
+        let directives = DockerfileDecoder::decode_dockerfile_from_src(dockerfile.as_bytes())
+            .await
+            .unwrap();
+        assert_eq!(
+            format_dockerfile(&directives),
+            "FROM alpine\nRUN echo \"hello     world\" > /greeting\n"
+        );
+
+        let dockerfile = "FROM alpine\nENV GREETING=\"hello \\\n    world\" OTHER=1\n";
+        let directives = DockerfileDecoder::decode_dockerfile_from_src(dockerfile.as_bytes())
+            .await
+            .unwrap();
+        assert_eq!(
+            format_dockerfile(&directives),
+            "FROM alpine\nENV GREETING=\"hello     world\" OTHER=1\n"
+        );
+    }
+}
@@ -0,0 +1,92 @@
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+const DOCKERFILE_EXTENSION: &str = "Dockerfile";
+
+/// Generated Dockerfiles cached per CLI version, so a Dockerfile generated by a previous release can
+/// be compared against the current one after upgrading. Entries are keyed on the enclave.toml they
+/// were generated from, so Enclaves in different directories never see each other's Dockerfiles.
+#[derive(Clone, Debug)]
+pub struct DockerfileHistory {
+    directory: PathBuf,
+}
+
+impl DockerfileHistory {
+    pub fn new(directory: PathBuf, config_path: &Path) -> Self {
+        // canonicalized so the same config is found however its path was passed
+        let config_path = config_path
+            .canonicalize()
+            .unwrap_or_else(|_| config_path.to_path_buf());
+        let key = Sha256::digest(config_path.display().to_string().as_bytes());
+        Self {
+            directory: directory.join(&hex::encode(key)[..16]),
+        }
+    }
+
+    fn entry_path(&self, cli_version: &str) -> PathBuf {
+        self.directory
+            .join(format!("{cli_version}.{DOCKERFILE_EXTENSION}"))
+    }
+
+    /// Records the Dockerfile generated by a CLI version, replacing any previous entry for it.
+    /// The cache is best effort, so failures are only logged.
+    pub fn store(&self, cli_version: &str, dockerfile: &str) {
+        let result = std::fs::create_dir_all(&self.directory)
+            .and_then(|_| std::fs::write(self.entry_path(cli_version), dockerfile));
+        if let Err(e) = result {
+            log::debug!("Failed to write to the generated Dockerfile cache - {e}");
+        }
+    }
+
+    pub fn load(&self, cli_version: &str) -> Option<String> {
+        std::fs::read_to_string(self.entry_path(cli_version)).ok()
+    }
+
+    /// The CLI versions with a cached Dockerfile, oldest first
+    pub fn versions(&self) -> Vec<String> {
+        let Ok(entries) = std::fs::read_dir(&self.directory) else {
+            return vec![];
+        };
+        let mut versions: Vec<String> = entries
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                let path = entry.path();
+                (path.extension()? == DOCKERFILE_EXTENSION)
+                    .then(|| path.file_stem()?.to_str().map(String::from))
+                    .flatten()
+            })
+            .collect();
+        versions.sort_by_cached_key(|version| {
+            version
+                .split(|c: char| !c.is_ascii_digit())
+                .map(|part| part.parse::<u64>().unwrap_or_default())
+                .collect::<Vec<_>>()
+        });
+        versions
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_dockerfile_history() {
+        let cache = tempfile::TempDir::new().unwrap();
+        let enclave = tempfile::TempDir::new().unwrap();
+        let config_path = enclave.path().join("enclave.toml");
+        std::fs::write(&config_path, "name = \"hello-enclave\"").unwrap();
+
+        let history = DockerfileHistory::new(cache.path().to_path_buf(), &config_path);
+        assert!(history.versions().is_empty());
+        history.store("1.10.0", "FROM alpine:3.19\n");
+        history.store("1.9.2", "FROM alpine:3.18\n");
+        assert_eq!(history.versions(), vec!["1.9.2", "1.10.0"]);
+        assert_eq!(history.load("1.9.2").as_deref(), Some("FROM alpine:3.18\n"));
+        assert_eq!(history.load("1.8.0"), None);
+
+        let other_config = tempfile::NamedTempFile::new().unwrap();
+        let other = DockerfileHistory::new(cache.path().to_path_buf(), other_config.path());
+        assert!(other.versions().is_empty());
+    }
+}
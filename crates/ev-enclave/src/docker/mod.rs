@@ -1,4 +1,11 @@
 pub mod command;
+pub mod credentials;
+pub mod determinism;
+pub mod diff;
 pub mod error;
+pub mod format;
+pub mod history;
 pub mod parse;
+pub mod platform;
+pub mod remote;
 pub mod utils;
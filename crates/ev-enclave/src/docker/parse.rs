@@ -342,6 +342,36 @@ impl Directive {
         }
     }
 
+    // The image a FROM directive builds on and the stage name it's given, skipping flags like
+    // --platform.
+    pub fn base_image(&self) -> Option<(&str, Option<&str>)> {
+        let Self::From { arguments } = self else {
+            return None;
+        };
+        let mut tokens = std::str::from_utf8(arguments)
+            .ok()?
+            .split_whitespace()
+            .skip_while(|token| token.starts_with("--"));
+        let image = tokens.next()?;
+        let alias = match tokens.next() {
+            Some(keyword) if keyword.eq_ignore_ascii_case("as") => tokens.next(),
+            _ => None,
+        };
+        Some((image, alias))
+    }
+
+    /// The platform given to a FROM directive with `--platform=<platform>`
+    pub fn base_image_platform(&self) -> Option<&str> {
+        let Self::From { arguments } = self else {
+            return None;
+        };
+        std::str::from_utf8(arguments)
+            .ok()?
+            .split_whitespace()
+            .take_while(|token| token.starts_with("--"))
+            .find_map(|token| token.strip_prefix("--platform="))
+    }
+
     pub fn new_entrypoint<T: Into<Vec<String>>>(mode: Mode, tokens: T) -> Self {
         Self::Entrypoint {
             mode: Some(mode),
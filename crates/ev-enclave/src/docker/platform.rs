@@ -0,0 +1,290 @@
+use super::command::{docker_server_platform, inspect_image_manifest};
+use super::error::PlatformError;
+use super::parse::Directive;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::path::Path;
+use std::str::FromStr;
+
+/// An image platform in docker's os/arch[/variant] form
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Platform {
+    os: String,
+    architecture: String,
+    variant: Option<String>,
+}
+
+/// Enclaves run on x86_64 Nitro hosts, so images are built for linux/amd64 unless told otherwise.
+impl Default for Platform {
+    fn default() -> Self {
+        Self {
+            os: "linux".into(),
+            architecture: "amd64".into(),
+            variant: None,
+        }
+    }
+}
+
+impl FromStr for Platform {
+    type Err = PlatformError;
+
+    fn from_str(platform: &str) -> Result<Self, Self::Err> {
+        let invalid = || PlatformError::InvalidPlatform(platform.to_string());
+        let lowercase = platform.trim().to_ascii_lowercase();
+        let mut parts = lowercase.split('/');
+        let (Some(os), Some(architecture)) = (parts.next(), parts.next()) else {
+            return Err(invalid());
+        };
+        let variant = parts.next();
+        if os.is_empty()
+            || architecture.is_empty()
+            || variant.is_some_and(str::is_empty)
+            || parts.next().is_some()
+        {
+            return Err(invalid());
+        }
+        Ok(Self::new(os, architecture, variant))
+    }
+}
+
+impl std::fmt::Display for Platform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.os, self.architecture)?;
+        if let Some(variant) = self.variant.as_ref() {
+            write!(f, "/{variant}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Platform {
+    // Architectures are normalised to the names docker uses in image manifests
+    fn new(os: &str, architecture: &str, variant: Option<&str>) -> Self {
+        let architecture = match architecture {
+            "x86_64" | "x86-64" => "amd64",
+            "aarch64" => "arm64",
+            architecture => architecture,
+        };
+        Self {
+            os: os.to_string(),
+            architecture: architecture.to_string(),
+            variant: variant.map(String::from),
+        }
+    }
+
+    /// Checks the platform is one the Enclave runtime can run, allowing amd64 variants such as
+    /// linux/amd64/v3.
+    pub fn validate_target(self) -> Result<Self, PlatformError> {
+        let supported = Self::default();
+        if self.os == supported.os && self.architecture == supported.architecture {
+            Ok(self)
+        } else {
+            Err(PlatformError::UnsupportedPlatform(self.to_string()))
+        }
+    }
+
+    /// Whether an image built for this platform can be used for the other. Variants are only
+    /// compared when both platforms give one.
+    pub fn matches(&self, other: &Platform) -> bool {
+        self.os == other.os
+            && self.architecture == other.architecture
+            && match (self.variant.as_ref(), other.variant.as_ref()) {
+                (Some(variant), Some(other_variant)) => variant == other_variant,
+                _ => true,
+            }
+    }
+
+    /// The platform images are built on natively, preferring the docker daemon's, which can
+    /// differ from this machine's when using a VM or remote builder.
+    pub fn builder() -> Option<Self> {
+        match docker_server_platform().map(|platform| Self::from_str(&platform)) {
+            Ok(Ok(platform)) => return Some(platform),
+            Ok(Err(e)) => log::debug!("Failed to parse the docker daemon's platform - {e}"),
+            Err(e) => log::debug!("Failed to get the docker daemon's platform - {e}"),
+        }
+        let architecture = std::env::consts::ARCH;
+        ["x86_64", "aarch64"]
+            .contains(&architecture)
+            .then(|| Self::new("linux", architecture, None))
+    }
+}
+
+#[derive(Deserialize)]
+struct ImageIndex {
+    manifests: Option<Vec<IndexEntry>>,
+}
+
+#[derive(Deserialize)]
+struct IndexEntry {
+    platform: Option<IndexPlatform>,
+}
+
+#[derive(Deserialize)]
+struct IndexPlatform {
+    os: String,
+    architecture: String,
+    variant: Option<String>,
+}
+
+/// The platforms listed by an image index, or None for a single image manifest, whose platform
+/// is only recorded in its config blob.
+pub fn manifest_platforms(manifest: &[u8]) -> Option<Vec<Platform>> {
+    let index: ImageIndex = serde_json::from_slice(manifest).ok()?;
+    let platforms = index
+        .manifests?
+        .into_iter()
+        .filter_map(|entry| entry.platform)
+        // Attestation manifests pushed by buildx are listed with an unknown platform
+        .filter(|platform| platform.os != "unknown")
+        .map(|platform| {
+            Platform::new(
+                &platform.os,
+                &platform.architecture,
+                platform.variant.as_deref(),
+            )
+        })
+        .collect();
+    Some(platforms)
+}
+
+/// Checks each base image in the Dockerfile can be pulled for the target platform, so a missing
+/// variant fails with a clear message rather than docker building an image the Enclave can't run.
+/// Images which can't be inspected are left for the build to pull.
+pub fn check_base_image_platforms(
+    directives: &[Directive],
+    target: &Platform,
+    docker_config_dir: Option<&Path>,
+) -> Result<(), PlatformError> {
+    let from_directives: Vec<&Directive> = directives
+        .iter()
+        .filter(|directive| directive.base_image().is_some())
+        .collect();
+    let mut stages = HashSet::new();
+    for (index, directive) in from_directives.iter().enumerate() {
+        let Some((image, alias)) = directive.base_image() else {
+            continue;
+        };
+        let is_final_stage = index + 1 == from_directives.len();
+        let is_earlier_stage = stages.contains(&image.to_ascii_lowercase());
+        if let Some(alias) = alias {
+            stages.insert(alias.to_ascii_lowercase());
+        }
+
+        // Stages pinned to another platform, such as $BUILDPLATFORM for cross-compiling, are
+        // only valid when their output is copied into a later stage.
+        let stage_target = match directive.base_image_platform() {
+            Some(platform) if platform.contains('$') => continue,
+            Some(platform) => Platform::from_str(platform)?,
+            None => target.clone(),
+        };
+        if !stage_target.matches(target) {
+            if is_final_stage {
+                return Err(PlatformError::FinalStagePlatform {
+                    image: image.to_string(),
+                    platform: stage_target.to_string(),
+                    target: target.to_string(),
+                });
+            }
+            continue;
+        }
+
+        let is_pulled =
+            !image.eq_ignore_ascii_case("scratch") && !image.contains('$') && !is_earlier_stage;
+        if !is_pulled {
+            continue;
+        }
+        let manifest = match inspect_image_manifest(image, docker_config_dir) {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                log::debug!("Skipping the platform check for {image} - {e}");
+                continue;
+            }
+        };
+        let Some(platforms) = manifest_platforms(&manifest) else {
+            continue;
+        };
+        if !platforms.iter().any(|platform| platform.matches(target)) {
+            let available: Vec<String> = platforms.iter().map(Platform::to_string).collect();
+            return Err(PlatformError::MissingPlatformVariant {
+                image: image.to_string(),
+                target: target.to_string(),
+                available: available.join(", "),
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn from(arguments: &str) -> Directive {
+        Directive::From {
+            arguments: arguments.to_string().into(),
+        }
+    }
+
+    #[test]
+    fn test_parse_platform() {
+        let platform = Platform::from_str("linux/x86_64").unwrap();
+        assert_eq!(platform, Platform::default());
+        assert_eq!(platform.to_string(), "linux/amd64");
+        assert_eq!(
+            Platform::from_str("Linux/aarch64/v8").unwrap().to_string(),
+            "linux/arm64/v8"
+        );
+        for invalid in ["amd64", "linux/", "linux/amd64/", "linux/amd64/v3/extra"] {
+            assert!(Platform::from_str(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_validate_target() {
+        assert!(Platform::from_str("linux/amd64/v3")
+            .unwrap()
+            .validate_target()
+            .is_ok());
+        assert!(matches!(
+            Platform::from_str("linux/arm64").unwrap().validate_target(),
+            Err(PlatformError::UnsupportedPlatform(platform)) if platform == "linux/arm64"
+        ));
+    }
+
+    #[test]
+    fn test_manifest_platforms() {
+        let index = br#"{
+            "schemaVersion": 2,
+            "mediaType": "application/vnd.oci.image.index.v1+json",
+            "manifests": [
+                {"digest": "sha256:1", "platform": {"architecture": "arm64", "os": "linux", "variant": "v8"}},
+                {"digest": "sha256:2", "platform": {"architecture": "unknown", "os": "unknown"}}
+            ]
+        }"#;
+        let platforms = manifest_platforms(index).unwrap();
+        assert_eq!(platforms, vec![Platform::new("linux", "arm64", Some("v8"))]);
+        assert!(!platforms[0].matches(&Platform::default()));
+
+        let single = br#"{"schemaVersion": 2, "config": {"digest": "sha256:3"}, "layers": []}"#;
+        assert_eq!(manifest_platforms(single), None);
+    }
+
+    #[test]
+    fn test_final_stage_pinned_to_another_platform() {
+        let directives = vec![
+            from("--platform=$BUILDPLATFORM golang:1.22 AS build"),
+            from("--platform=linux/arm64 scratch"),
+        ];
+        let result = check_base_image_platforms(&directives, &Platform::default(), None);
+        assert!(matches!(
+            result,
+            Err(PlatformError::FinalStagePlatform { platform, .. }) if platform == "linux/arm64"
+        ));
+
+        let directives = vec![
+            from("--platform=linux/arm64 scratch AS tools"),
+            from("--platform=linux/amd64 scratch"),
+        ];
+        assert!(check_base_image_platforms(&directives, &Platform::default(), None).is_ok());
+    }
+}
@@ -0,0 +1,78 @@
+use super::platform::Platform;
+use common::CliError;
+use std::process::Command;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use thiserror::Error;
+
+static REMOTE_BUILDER: OnceLock<String> = OnceLock::new();
+
+#[derive(Debug, Error)]
+pub enum RemoteBuilderError {
+    #[error("Failed to reach the docker context {context} — {reason}. Check it exists with docker context ls, and that its daemon is running.")]
+    Unreachable { context: String, reason: String },
+    #[error("The docker context {context} builds {platform} images. Remote builders must be linux/amd64 hosts, so builds match those on x86_64 machines.")]
+    UnsupportedPlatform { context: String, platform: String },
+}
+
+impl CliError for RemoteBuilderError {
+    fn exitcode(&self) -> exitcode::ExitCode {
+        match self {
+            Self::Unreachable { .. } => exitcode::UNAVAILABLE,
+            Self::UnsupportedPlatform { .. } => exitcode::CONFIG,
+        }
+    }
+}
+
+/// The docker context builds run against, if builds have been sent to a remote builder
+pub fn remote_builder() -> Option<&'static str> {
+    REMOTE_BUILDER.get().map(String::as_str)
+}
+
+/// A docker command targeting the remote builder when one is selected, or the local daemon
+pub fn docker_command() -> Command {
+    let mut command = Command::new("docker");
+    if let Some(context) = remote_builder() {
+        command.args(["--context", context]);
+    }
+    command
+}
+
+/// Sends every docker command for the rest of the process to the given docker context, once
+/// it's been checked to be a native x86_64 host. Emulated builds on other architectures can
+/// produce different images, and so different PCRs, from the same Dockerfile.
+pub fn use_remote_builder(context: &str) -> Result<(), RemoteBuilderError> {
+    let unreachable = |reason: String| RemoteBuilderError::Unreachable {
+        context: context.to_string(),
+        reason,
+    };
+    let output = Command::new("docker")
+        .args([
+            "--context",
+            context,
+            "version",
+            "--format",
+            "{{.Server.Os}}/{{.Server.Arch}}",
+        ])
+        .output()
+        .map_err(|e| unreachable(e.to_string()))?;
+    if !output.status.success() {
+        return Err(unreachable(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+
+    let platform = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    let is_amd64 =
+        Platform::from_str(&platform).is_ok_and(|platform| platform.matches(&Platform::default()));
+    if !is_amd64 {
+        return Err(RemoteBuilderError::UnsupportedPlatform {
+            context: context.to_string(),
+            platform,
+        });
+    }
+
+    log::info!("Building on the remote docker context {context} ({platform})");
+    let _ = REMOTE_BUILDER.set(context.to_string());
+    Ok(())
+}
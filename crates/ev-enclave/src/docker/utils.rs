@@ -58,6 +58,54 @@ pub fn verify_docker_is_running() -> Result<bool, super::error::DockerError> {
     Ok(exit_status.success())
 }
 
+/// Returns the commands which are missing from the given image. If the image has no `/bin/sh`, the
+/// probe can't run and `sh` is reported as the only missing command.
+pub fn find_missing_image_commands(
+    image_name: &str,
+    commands: &[&str],
+) -> Result<Vec<String>, super::error::DockerError> {
+    let output = super::command::probe_image_commands(image_name, commands)?;
+    Ok(missing_commands_from_probe(
+        image_name,
+        output.status.code(),
+        &output.stdout,
+        &output.stderr,
+    )?)
+}
+
+// docker run exits with 126 when the entrypoint can't be executed and 127 when it doesn't exist.
+// Any other failure, such as a pull or daemon error, is unrelated to the image's shell.
+fn missing_commands_from_probe(
+    image_name: &str,
+    exit_code: Option<i32>,
+    stdout: &[u8],
+    stderr: &[u8],
+) -> Result<Vec<String>, super::error::CommandError> {
+    match exit_code {
+        Some(0) => Ok(parse_missing_commands(&String::from_utf8_lossy(stdout))),
+        Some(126) | Some(127) => {
+            log::debug!(
+                "Image {image_name} can't run /bin/sh - {}",
+                String::from_utf8_lossy(stderr)
+            );
+            Ok(vec!["sh".to_string()])
+        }
+        _ => Err(super::error::CommandError::CommandFailed {
+            command: format!("run {image_name}"),
+            stderr: String::from_utf8_lossy(stderr).trim().to_string(),
+        }),
+    }
+}
+
+fn parse_missing_commands(probe_output: &str) -> Vec<String> {
+    probe_output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -70,4 +118,47 @@ mod tests {
             r##"printf "#!/bin/sh\necho hello\n" > hello-script.sh && chmod +x hello-script.sh"##
         )
     }
+
+    #[test]
+    fn test_parse_missing_commands() {
+        assert_eq!(
+            parse_missing_commands("ifconfig\n\nhostname\n"),
+            vec!["ifconfig".to_string(), "hostname".to_string()]
+        );
+        assert!(parse_missing_commands("").is_empty());
+    }
+
+    #[test]
+    fn test_missing_commands_from_probe() {
+        assert_eq!(
+            missing_commands_from_probe("user-image", Some(0), b"ifconfig\n", b"").unwrap(),
+            vec!["ifconfig".to_string()]
+        );
+        assert_eq!(
+            missing_commands_from_probe(
+                "user-image",
+                Some(127),
+                b"",
+                b"exec: \"/bin/sh\": stat /bin/sh: no such file or directory"
+            )
+            .unwrap(),
+            vec!["sh".to_string()]
+        );
+        assert_eq!(
+            missing_commands_from_probe("user-image", Some(126), b"", b"permission denied")
+                .unwrap(),
+            vec!["sh".to_string()]
+        );
+        let err = missing_commands_from_probe(
+            "user-image",
+            Some(125),
+            b"",
+            b"Cannot connect to the Docker daemon\n",
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "docker run user-image failed — Cannot connect to the Docker daemon"
+        );
+    }
 }
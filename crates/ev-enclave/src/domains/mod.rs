@@ -0,0 +1,298 @@
+use crate::api::enclave::{AddCustomDomainRequest, CustomDomain, CustomDomainStatus, EnclaveApi};
+use crate::progress::{poll_fn_and_report_status, ProgressLogger, StatusReport};
+use common::CliError;
+use std::sync::Arc;
+use thiserror::Error;
+
+// Enclaves are always served on their own domain, so it can't be added as a custom domain
+const EVERVAULT_DOMAIN_SUFFIX: &str = ".evervault.com";
+const MAX_DOMAIN_LENGTH: usize = 253;
+const MAX_LABEL_LENGTH: usize = 63;
+
+#[derive(Debug, Error)]
+pub enum DomainsError {
+    #[error("An error occurred while reading the Enclave config — {0}")]
+    EnclaveConfigError(#[from] crate::config::EnclaveConfigError),
+    #[error("No Enclave Uuid given. You can provide one by using either the --enclave-uuid flag, or using the --config flag to point to an Enclave.toml")]
+    MissingUuid,
+    #[error("Invalid domain \"{0}\". Custom domains must be a fully qualified hostname, such as api.example.com, without a protocol or path.")]
+    InvalidDomain(String),
+    #[error("{0} is an Evervault domain. Enclaves are already served on their Evervault domain, so only domains you own can be added.")]
+    EvervaultDomain(String),
+    #[error("{0} is not a custom domain of this Enclave")]
+    UnknownDomain(String),
+    #[error("Verification of {0} failed - {1}")]
+    VerificationFailed(String, String),
+    #[error("An error occurred contacting the API — {0}")]
+    ApiError(#[from] common::api::client::ApiError),
+}
+
+impl CliError for DomainsError {
+    fn exitcode(&self) -> exitcode::ExitCode {
+        match self {
+            Self::EnclaveConfigError(config_err) => config_err.exitcode(),
+            Self::MissingUuid | Self::InvalidDomain(_) | Self::EvervaultDomain(_) => {
+                exitcode::DATAERR
+            }
+            Self::UnknownDomain(_) => exitcode::NOINPUT,
+            Self::VerificationFailed(..) => exitcode::UNAVAILABLE,
+            Self::ApiError(api_err) => api_err.exitcode(),
+        }
+    }
+}
+
+fn is_valid_label(label: &str) -> bool {
+    !label.is_empty()
+        && label.len() <= MAX_LABEL_LENGTH
+        && !label.starts_with('-')
+        && !label.ends_with('-')
+        && label
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+}
+
+/// Normalizes a custom domain to lowercase without a trailing dot, checking it's a fully
+/// qualified hostname which isn't an Evervault domain
+pub fn validate_domain(domain: &str) -> Result<String, DomainsError> {
+    let normalized = domain.trim().trim_end_matches('.').to_lowercase();
+    let labels: Vec<&str> = normalized.split('.').collect();
+    let is_valid = normalized.len() <= MAX_DOMAIN_LENGTH
+        && labels.len() >= 2
+        && labels.iter().all(|label| is_valid_label(label));
+    if !is_valid {
+        return Err(DomainsError::InvalidDomain(domain.to_string()));
+    }
+    if normalized.ends_with(EVERVAULT_DOMAIN_SUFFIX) {
+        return Err(DomainsError::EvervaultDomain(normalized));
+    }
+    Ok(normalized)
+}
+
+/// The DNS records to create for a custom domain, as aligned lines to print
+pub fn format_dns_guidance(custom_domain: &CustomDomain) -> String {
+    let records = &custom_domain.dns_records;
+    if records.is_empty() {
+        return format!("No DNS records are needed for {}", custom_domain.domain);
+    }
+    let type_width = records
+        .iter()
+        .map(|r| r.record_type.len())
+        .max()
+        .unwrap_or(0);
+    let name_width = records.iter().map(|r| r.name.len()).max().unwrap_or(0);
+    let lines = records
+        .iter()
+        .map(|record| {
+            format!(
+                "  {:type_width$}  {:name_width$}  {}",
+                record.record_type, record.name, record.value
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        "Create the following DNS records with your DNS provider to serve the Enclave on {}:\n{lines}",
+        custom_domain.domain
+    )
+}
+
+/// Attaches a custom domain to an Enclave, returning the domain with the DNS records it needs
+pub async fn add_domain<T: EnclaveApi>(
+    enclave_api: &T,
+    enclave_uuid: &str,
+    domain: &str,
+) -> Result<CustomDomain, DomainsError> {
+    let domain = validate_domain(domain)?;
+    log::info!("Adding {domain} to Enclave {enclave_uuid}...");
+    Ok(enclave_api
+        .add_custom_domain(enclave_uuid, AddCustomDomainRequest::new(domain))
+        .await?)
+}
+
+pub async fn list_domains<T: EnclaveApi>(
+    enclave_api: &T,
+    enclave_uuid: &str,
+) -> Result<Vec<CustomDomain>, DomainsError> {
+    Ok(enclave_api.get_custom_domains(enclave_uuid).await?.domains)
+}
+
+/// Detaches a custom domain from an Enclave. The Enclave stops being served on it, but its DNS
+/// records are left for you to remove.
+pub async fn remove_domain<T: EnclaveApi>(
+    enclave_api: &T,
+    enclave_uuid: &str,
+    domain: &str,
+) -> Result<(), DomainsError> {
+    let domain = validate_domain(domain)?;
+    let domains = list_domains(enclave_api, enclave_uuid).await?;
+    if !domains
+        .iter()
+        .any(|custom_domain| custom_domain.domain == domain)
+    {
+        return Err(DomainsError::UnknownDomain(domain));
+    }
+    Ok(enclave_api
+        .remove_custom_domain(enclave_uuid, &domain)
+        .await?)
+}
+
+fn domain_status_report(custom_domain: &CustomDomain) -> StatusReport {
+    let domain = &custom_domain.domain;
+    match custom_domain.status {
+        CustomDomainStatus::Pending => {
+            StatusReport::update(format!("Waiting for the DNS records of {domain}..."))
+        }
+        CustomDomainStatus::Provisioning => StatusReport::update(format!(
+            "DNS records found, issuing a certificate for {domain}..."
+        )),
+        CustomDomainStatus::Active => StatusReport::complete(format!("{domain} is active")),
+        CustomDomainStatus::Failed => StatusReport::failed(format!(
+            "Verification of {domain} failed - {}",
+            failure_reason(custom_domain)
+        )),
+    }
+}
+
+fn failure_reason(custom_domain: &CustomDomain) -> String {
+    custom_domain
+        .failure_reason
+        .clone()
+        .unwrap_or_else(|| "the DNS records could not be verified".to_string())
+}
+
+/// Polls a custom domain until its DNS records are verified and it's serving the Enclave,
+/// returning the active domain
+pub async fn watch_domain<T: EnclaveApi>(
+    enclave_api: T,
+    enclave_uuid: &str,
+    domain: &str,
+    progress_bar: impl ProgressLogger,
+) -> Result<CustomDomain, DomainsError> {
+    async fn check_domain_status<T: EnclaveApi>(
+        enclave_api: Arc<T>,
+        args: Vec<String>,
+    ) -> Result<StatusReport, DomainsError> {
+        let enclave_uuid = args.first().unwrap();
+        let domain = args.get(1).unwrap();
+        let custom_domain = enclave_api.get_custom_domain(enclave_uuid, domain).await?;
+        Ok(domain_status_report(&custom_domain))
+    }
+
+    let enclave_api = Arc::new(enclave_api);
+    let domain_args = vec![enclave_uuid.to_string(), domain.to_string()];
+    poll_fn_and_report_status(
+        enclave_api.clone(),
+        domain_args,
+        check_domain_status,
+        progress_bar,
+    )
+    .await?;
+
+    let custom_domain = enclave_api.get_custom_domain(enclave_uuid, domain).await?;
+    if !custom_domain.is_active() {
+        return Err(DomainsError::VerificationFailed(
+            custom_domain.domain.clone(),
+            failure_reason(&custom_domain),
+        ));
+    }
+    Ok(custom_domain)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::api::enclave::{DnsRecord, MockEnclaveApi};
+    use crate::progress::NonTty;
+
+    fn custom_domain(status: CustomDomainStatus) -> CustomDomain {
+        CustomDomain {
+            domain: "api.example.com".into(),
+            status,
+            dns_records: vec![
+                DnsRecord {
+                    record_type: "CNAME".into(),
+                    name: "api.example.com".into(),
+                    value: "hello.app-123.enclave.evervault.com".into(),
+                },
+                DnsRecord {
+                    record_type: "TXT".into(),
+                    name: "_evervault.api.example.com".into(),
+                    value: "ev-verify=abc123".into(),
+                },
+            ],
+            failure_reason: None,
+            created_at: None,
+        }
+    }
+
+    #[test]
+    fn test_validate_domain() {
+        assert_eq!(
+            validate_domain("API.Example.com.").unwrap(),
+            "api.example.com"
+        );
+        for invalid in [
+            "localhost",
+            "https://api.example.com",
+            "api.example.com/path",
+            "-api.example.com",
+            "api..example.com",
+        ] {
+            assert!(matches!(
+                validate_domain(invalid),
+                Err(DomainsError::InvalidDomain(_))
+            ));
+        }
+        assert!(matches!(
+            validate_domain("hello.app-123.enclave.evervault.com"),
+            Err(DomainsError::EvervaultDomain(_))
+        ));
+    }
+
+    #[test]
+    fn test_format_dns_guidance() {
+        let guidance = format_dns_guidance(&custom_domain(CustomDomainStatus::Pending));
+        assert_eq!(
+            guidance,
+            "Create the following DNS records with your DNS provider to serve the Enclave on api.example.com:\n  CNAME  api.example.com             hello.app-123.enclave.evervault.com\n  TXT    _evervault.api.example.com  ev-verify=abc123"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_watch_domain_reports_failure() {
+        let mut mock_api = MockEnclaveApi::new();
+        mock_api.expect_get_custom_domain().returning(|_, _| {
+            let mut failed = custom_domain(CustomDomainStatus::Failed);
+            failed.failure_reason = Some("CNAME record not found".into());
+            Box::pin(std::future::ready(Ok(failed)))
+        });
+        let result = watch_domain(
+            mock_api,
+            "enclave_123",
+            "api.example.com",
+            NonTty::default(),
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(DomainsError::VerificationFailed(domain, reason))
+                if domain == "api.example.com" && reason == "CNAME record not found"
+        ));
+
+        let mut mock_api = MockEnclaveApi::new();
+        mock_api.expect_get_custom_domain().returning(|_, _| {
+            Box::pin(std::future::ready(Ok(custom_domain(
+                CustomDomainStatus::Active,
+            ))))
+        });
+        let custom_domain = watch_domain(
+            mock_api,
+            "enclave_123",
+            "api.example.com",
+            NonTty::default(),
+        )
+        .await
+        .unwrap();
+        assert!(custom_domain.is_active());
+    }
+}
@@ -0,0 +1,95 @@
+use common::CliError;
+use thiserror::Error;
+
+pub mod mtls;
+
+/// Named lists of the domains used by common third-party services, so Enclaves can allow them
+/// without copying hostnames from each provider's docs.
+const PRESETS: &[(&str, &[&str])] = &[
+    ("aws-s3", &["s3.amazonaws.com", "*.s3.amazonaws.com"]),
+    (
+        "datadog",
+        &["*.datadoghq.com", "*.datadoghq.eu", "*.ddog-gov.com"],
+    ),
+    ("openai", &["api.openai.com"]),
+    ("sendgrid", &["api.sendgrid.com"]),
+    (
+        "stripe",
+        &["api.stripe.com", "files.stripe.com", "uploads.stripe.com"],
+    ),
+];
+
+#[derive(Debug, Error)]
+pub enum EgressPresetError {
+    #[error("Unknown egress preset \"{0}\". Available presets are: {available}", available = preset_names().join(", "))]
+    UnknownPreset(String),
+    #[error("Egress presets were given, but egress is disabled. Set enabled = true in the [egress] section of your enclave.toml.")]
+    EgressDisabled,
+}
+
+impl CliError for EgressPresetError {
+    fn exitcode(&self) -> exitcode::ExitCode {
+        exitcode::DATAERR
+    }
+}
+
+pub fn preset_names() -> Vec<&'static str> {
+    PRESETS.iter().map(|(name, _)| *name).collect()
+}
+
+/// The destinations allowed by a preset, matched case-insensitively
+pub fn preset_destinations(preset: &str) -> Result<&'static [&'static str], EgressPresetError> {
+    PRESETS
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(preset.trim()))
+        .map(|(_, destinations)| *destinations)
+        .ok_or_else(|| EgressPresetError::UnknownPreset(preset.to_string()))
+}
+
+/// Expands presets into their destinations and appends them to the explicit destinations,
+/// dropping duplicates while keeping the order they were given in.
+pub fn expand_presets(
+    destinations: &[String],
+    presets: &[String],
+) -> Result<Vec<String>, EgressPresetError> {
+    let mut expanded: Vec<String> = vec![];
+    let preset_destinations = presets
+        .iter()
+        .map(|preset| preset_destinations(preset))
+        .collect::<Result<Vec<_>, _>>()?;
+    let all_destinations = destinations
+        .iter()
+        .map(String::as_str)
+        .chain(preset_destinations.into_iter().flatten().copied());
+    for destination in all_destinations {
+        if !expanded.iter().any(|existing| existing == destination) {
+            expanded.push(destination.to_string());
+        }
+    }
+    Ok(expanded)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_expand_presets() {
+        let destinations = vec!["api.stripe.com".to_string(), "*.evervault.com".to_string()];
+        let presets = vec!["Stripe".to_string(), "openai".to_string()];
+        assert_eq!(
+            expand_presets(&destinations, &presets).unwrap(),
+            vec![
+                "api.stripe.com",
+                "*.evervault.com",
+                "files.stripe.com",
+                "uploads.stripe.com",
+                "api.openai.com"
+            ]
+        );
+
+        let err = expand_presets(&[], &["stripee".to_string()]).unwrap_err();
+        assert!(matches!(&err, EgressPresetError::UnknownPreset(preset) if preset == "stripee"));
+        assert!(err.to_string().contains("aws-s3, datadog"));
+    }
+}
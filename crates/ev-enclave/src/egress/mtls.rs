@@ -0,0 +1,193 @@
+use crate::config::EgressMtlsSettings;
+use common::CliError;
+use thiserror::Error;
+use x509_parser::parse_x509_certificate;
+use x509_parser::pem::Pem;
+
+/// The secrets the data plane reads the egress client certificate chain and key from, each a
+/// base64 encoded PEM file
+pub const MTLS_CERT_ENV_VAR: &str = "EV_EGRESS_MTLS_CERT";
+pub const MTLS_KEY_ENV_VAR: &str = "EV_EGRESS_MTLS_KEY";
+
+#[derive(Debug, Error)]
+pub enum MtlsError {
+    #[error("Failed to read {path} — {source}")]
+    Read {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("{0} doesn't contain any PEM encoded certificates.")]
+    NoCertificates(String),
+    #[error("{path} contains a {label} PEM block. The cert_path in [egress.mtls] should only contain the client certificate, followed by any intermediates.")]
+    UnexpectedCertBlock { path: String, label: String },
+    #[error("Failed to parse the certificate in {path} — {reason}")]
+    InvalidCertificate { path: String, reason: String },
+    #[error("The client certificate in {0} has expired or isn't valid yet.")]
+    CertificateNotValid(String),
+    #[error("The key in {path} must be an unencrypted PKCS#8 private key, with a PRIVATE KEY PEM block — {reason}. Convert it with openssl pkcs8 -topk8 -nocrypt -in {path}.")]
+    InvalidKey { path: String, reason: String },
+    #[error("The key in {key_path} doesn't match the client certificate in {cert_path}.")]
+    KeyMismatch { cert_path: String, key_path: String },
+}
+
+impl CliError for MtlsError {
+    fn exitcode(&self) -> exitcode::ExitCode {
+        match self {
+            Self::Read { .. } => exitcode::NOINPUT,
+            _ => exitcode::DATAERR,
+        }
+    }
+}
+
+/// A client certificate chain and the key for its first certificate, checked to match before
+/// they're uploaded so a mismatch isn't only found by failed egress requests
+#[derive(Clone, Debug)]
+pub struct MtlsCredentials {
+    cert_pem: String,
+    key_pem: String,
+}
+
+impl MtlsCredentials {
+    pub fn load(settings: &EgressMtlsSettings) -> Result<Self, MtlsError> {
+        let read = |path: &str| {
+            std::fs::read(path).map_err(|source| MtlsError::Read {
+                path: path.to_string(),
+                source,
+            })
+        };
+        Self::parse(
+            &settings.cert_path,
+            &read(&settings.cert_path)?,
+            &settings.key_path,
+            &read(&settings.key_path)?,
+        )
+    }
+
+    fn parse(
+        cert_path: &str,
+        cert_contents: &[u8],
+        key_path: &str,
+        key_contents: &[u8],
+    ) -> Result<Self, MtlsError> {
+        let mut leaf_public_key = None;
+        for block in Pem::iter_from_buffer(cert_contents) {
+            let block = block.map_err(|e| MtlsError::InvalidCertificate {
+                path: cert_path.to_string(),
+                reason: e.to_string(),
+            })?;
+            if block.label != "CERTIFICATE" {
+                return Err(MtlsError::UnexpectedCertBlock {
+                    path: cert_path.to_string(),
+                    label: block.label,
+                });
+            }
+            let (_, certificate) = parse_x509_certificate(&block.contents).map_err(|e| {
+                MtlsError::InvalidCertificate {
+                    path: cert_path.to_string(),
+                    reason: e.to_string(),
+                }
+            })?;
+            if leaf_public_key.is_none() {
+                if !certificate.validity().is_valid() {
+                    return Err(MtlsError::CertificateNotValid(cert_path.to_string()));
+                }
+                leaf_public_key = Some(certificate.public_key().subject_public_key.data.to_vec());
+            }
+        }
+        let leaf_public_key =
+            leaf_public_key.ok_or_else(|| MtlsError::NoCertificates(cert_path.to_string()))?;
+
+        let key_pem =
+            String::from_utf8(key_contents.to_vec()).map_err(|e| MtlsError::InvalidKey {
+                path: key_path.to_string(),
+                reason: e.to_string(),
+            })?;
+        let key_pair = rcgen::KeyPair::from_pem(&key_pem).map_err(|e| MtlsError::InvalidKey {
+            path: key_path.to_string(),
+            reason: e.to_string(),
+        })?;
+        if key_pair.public_key_raw() != leaf_public_key.as_slice() {
+            return Err(MtlsError::KeyMismatch {
+                cert_path: cert_path.to_string(),
+                key_path: key_path.to_string(),
+            });
+        }
+
+        Ok(Self {
+            cert_pem: String::from_utf8_lossy(cert_contents).into_owned(),
+            key_pem,
+        })
+    }
+
+    /// The environment variables to add to the Enclave, base64 encoded as environment variables
+    /// can't hold the newlines in a PEM file
+    pub fn env_vars(&self) -> [(&'static str, String); 2] {
+        [
+            (MTLS_CERT_ENV_VAR, base64::encode(&self.cert_pem)),
+            (MTLS_KEY_ENV_VAR, base64::encode(&self.key_pem)),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn generate_credentials() -> (String, String) {
+        let cert = rcgen::generate_simple_self_signed(vec!["client.local".to_string()]).unwrap();
+        (
+            cert.serialize_pem().unwrap(),
+            cert.serialize_private_key_pem(),
+        )
+    }
+
+    #[test]
+    fn test_parse_credentials() {
+        let (cert_pem, key_pem) = generate_credentials();
+        let credentials = MtlsCredentials::parse(
+            "client.crt",
+            cert_pem.as_bytes(),
+            "client.key",
+            key_pem.as_bytes(),
+        )
+        .unwrap();
+        let [(cert_var, cert_value), (key_var, key_value)] = credentials.env_vars();
+        assert_eq!(cert_var, MTLS_CERT_ENV_VAR);
+        assert_eq!(base64::decode(cert_value).unwrap(), cert_pem.as_bytes());
+        assert_eq!(key_var, MTLS_KEY_ENV_VAR);
+        assert_eq!(base64::decode(key_value).unwrap(), key_pem.as_bytes());
+
+        let (_, other_key_pem) = generate_credentials();
+        assert!(matches!(
+            MtlsCredentials::parse(
+                "client.crt",
+                cert_pem.as_bytes(),
+                "other.key",
+                other_key_pem.as_bytes()
+            ),
+            Err(MtlsError::KeyMismatch { .. })
+        ));
+        assert!(matches!(
+            MtlsCredentials::parse(
+                "client.crt",
+                key_pem.as_bytes(),
+                "client.key",
+                key_pem.as_bytes()
+            ),
+            Err(MtlsError::UnexpectedCertBlock { .. })
+        ));
+        assert!(matches!(
+            MtlsCredentials::parse("client.crt", b"", "client.key", key_pem.as_bytes()),
+            Err(MtlsError::NoCertificates(_))
+        ));
+        assert!(matches!(
+            MtlsCredentials::parse(
+                "client.crt",
+                cert_pem.as_bytes(),
+                "client.key",
+                b"not a key"
+            ),
+            Err(MtlsError::InvalidKey { .. })
+        ));
+    }
+}
@@ -0,0 +1,258 @@
+use crate::progress::{format_duration, get_tracker, HumanBytes, ProgressLogger};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+// How often the EIF is checked while nitro-cli writes it
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+// The progress message is only updated when the EIF crosses a step, so plain-text logs stay short
+const PERCENT_STEP: u64 = 10;
+
+const CONVERSION_RETRIES_ENV_VAR: &str = "EV_CONVERSION_RETRIES";
+const DEFAULT_CONVERSION_RETRIES: u32 = 2;
+// Docker and daemon errors which a later attempt can get past, rather than problems with the image
+const TRANSIENT_FAILURES: &[&str] = &[
+    "cannot connect to the docker daemon",
+    "error during connect",
+    "connection refused",
+    "connection reset by peer",
+    "i/o timeout",
+    "tls handshake timeout",
+    "context deadline exceeded",
+    "unexpected eof",
+    "is already in use by container",
+    "device or resource busy",
+];
+
+/// How many times to run the conversion before giving up, from EV_CONVERSION_RETRIES
+pub fn max_attempts() -> u32 {
+    max_attempts_from(std::env::var(CONVERSION_RETRIES_ENV_VAR).ok().as_deref())
+}
+
+fn max_attempts_from(retries: Option<&str>) -> u32 {
+    retries
+        .and_then(|retries| retries.trim().parse::<u32>().ok())
+        .unwrap_or(DEFAULT_CONVERSION_RETRIES)
+        .saturating_add(1)
+}
+
+/// Whether a failed conversion's stderr shows a docker or daemon error worth retrying
+pub fn is_transient_failure(stderr: &str) -> bool {
+    let stderr = stderr.to_lowercase();
+    TRANSIENT_FAILURES
+        .iter()
+        .any(|failure| stderr.contains(failure))
+}
+
+/// Reports progress while nitro-cli converts the user image to an EIF. nitro-cli builds the
+/// ramdisks before it writes any of the EIF, so only the elapsed time is known until the EIF
+/// appears. Its growth then drives the progress and ETA, against the image's size as an estimate
+/// of the EIF's.
+pub struct ConversionProgress {
+    tracker: Option<Arc<Box<dyn ProgressLogger + Send + Sync>>>,
+    stop: Arc<AtomicBool>,
+    monitor: Option<JoinHandle<()>>,
+    started: Instant,
+}
+
+impl ConversionProgress {
+    /// `eif_path` is None when the EIF is only copied out once it's complete, as on remote
+    /// builders. Verbose conversions show nitro-cli's own output instead of a progress indicator.
+    pub fn start(eif_path: Option<PathBuf>, image_size: Option<u64>, verbose: bool) -> Self {
+        let message = match image_size {
+            Some(image_size) => format!(
+                "Converting {} docker image to EIF...",
+                HumanBytes(image_size)
+            ),
+            None => "Converting docker image to EIF...".to_string(),
+        };
+        let stop = Arc::new(AtomicBool::new(false));
+        if verbose {
+            log::info!("{message}");
+            return Self {
+                tracker: None,
+                stop,
+                monitor: None,
+                started: Instant::now(),
+            };
+        }
+        let tracker = Arc::new(get_tracker(&message, None));
+        let monitor = {
+            let (tracker, stop) = (tracker.clone(), stop.clone());
+            std::thread::spawn(move || monitor_eif(eif_path, image_size, &tracker, &stop))
+        };
+        Self {
+            tracker: Some(tracker),
+            stop,
+            monitor: Some(monitor),
+            started: Instant::now(),
+        }
+    }
+
+    /// Stops reporting, summarising the EIF's size and how long the conversion took
+    pub fn finish(mut self, eif_size: Option<u64>) {
+        self.stop_monitor();
+        let elapsed = format_duration(self.started.elapsed().as_millis() as u64);
+        let Some(eif_size) = eif_size else {
+            if let Some(tracker) = self.tracker.as_ref() {
+                tracker.finish();
+            }
+            return;
+        };
+        let message = format!("Docker image converted to a {} EIF.", HumanBytes(eif_size));
+        match self.tracker.as_ref() {
+            Some(tracker) => tracker.finish_with_message(&message),
+            None => log::info!("{message}"),
+        }
+        log::info!("EIF conversion and PCR computation took {elapsed}");
+    }
+
+    fn stop_monitor(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(monitor) = self.monitor.take() {
+            let _ = monitor.join();
+        }
+    }
+}
+
+impl Drop for ConversionProgress {
+    fn drop(&mut self) {
+        self.stop_monitor();
+    }
+}
+
+fn monitor_eif(
+    eif_path: Option<PathBuf>,
+    image_size: Option<u64>,
+    tracker: &Arc<Box<dyn ProgressLogger + Send + Sync>>,
+    stop: &AtomicBool,
+) {
+    let eif_metadata = || {
+        eif_path
+            .as_ref()
+            .and_then(|eif_path| std::fs::metadata(eif_path).ok())
+    };
+    // An EIF from a previous build is ignored until nitro-cli starts overwriting it
+    let previous_eif = eif_metadata().and_then(|metadata| metadata.modified().ok());
+    let mut first_seen = None;
+    let mut reported_step = None;
+    while !stop.load(Ordering::Relaxed) {
+        std::thread::sleep(POLL_INTERVAL);
+        let written = eif_metadata()
+            .filter(|metadata| previous_eif.is_none() || metadata.modified().ok() != previous_eif)
+            .map(|metadata| metadata.len())
+            .filter(|written| *written > 0);
+        match (written, image_size) {
+            (Some(written), Some(estimated_len)) => {
+                let (seen_at, seen_len) = *first_seen.get_or_insert((Instant::now(), written));
+                let rate = written.saturating_sub(seen_len) as f64
+                    / seen_at.elapsed().as_secs_f64().max(f64::EPSILON);
+                let status = WriteStatus::new(written, estimated_len, rate);
+                let step = status.percent / PERCENT_STEP;
+                if reported_step != Some(step) {
+                    reported_step = Some(step);
+                    tracker.set_message(&status.describe());
+                    continue;
+                }
+            }
+            (Some(written), None) if reported_step.is_none() => {
+                reported_step = Some(0);
+                tracker.set_message(&format!("Writing EIF... {} written", HumanBytes(written)));
+                continue;
+            }
+            _ => {}
+        }
+        tracker.heartbeat();
+    }
+}
+
+/// How far through writing the EIF nitro-cli is, estimated from the size of the image
+#[derive(Debug, PartialEq, Eq)]
+struct WriteStatus {
+    percent: u64,
+    estimated_len: u64,
+    remaining: Option<Duration>,
+}
+
+impl WriteStatus {
+    /// `rate` is the bytes per second the EIF has been growing at
+    fn new(written: u64, estimated_len: u64, rate: f64) -> Self {
+        // The estimate can be short, so the EIF is never reported as complete until it is
+        let percent = (written * 100)
+            .checked_div(estimated_len)
+            .unwrap_or(0)
+            .min(99);
+        let remaining = (written < estimated_len && rate > 0.0)
+            .then(|| Duration::from_secs_f64((estimated_len - written) as f64 / rate));
+        Self {
+            percent,
+            estimated_len,
+            remaining,
+        }
+    }
+
+    fn describe(&self) -> String {
+        let estimate = format!(
+            "Writing EIF... {}% of ~{}",
+            self.percent,
+            HumanBytes(self.estimated_len)
+        );
+        match self.remaining {
+            Some(remaining) => format!(
+                "{estimate}, about {} remaining",
+                format_duration(remaining.as_millis() as u64)
+            ),
+            None => format!("{estimate}, finishing"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_write_status() {
+        let mb = 1024 * 1024;
+        let status = WriteStatus::new(25 * mb, 100 * mb, 5.0 * mb as f64);
+        assert_eq!(status.percent, 25);
+        assert_eq!(status.remaining, Some(Duration::from_secs(15)));
+        assert_eq!(
+            status.describe(),
+            "Writing EIF... 25% of ~100.00 MiB, about 15.0s remaining"
+        );
+
+        // EIFs larger than the image are still being written, not complete
+        let status = WriteStatus::new(120 * mb, 100 * mb, 5.0 * mb as f64);
+        assert_eq!(status.percent, 99);
+        assert_eq!(status.remaining, None);
+        assert_eq!(
+            status.describe(),
+            "Writing EIF... 99% of ~100.00 MiB, finishing"
+        );
+
+        // No ETA until the EIF has been seen growing
+        assert_eq!(WriteStatus::new(mb, 100 * mb, 0.0).remaining, None);
+    }
+
+    #[test]
+    fn test_conversion_retries() {
+        assert_eq!(max_attempts_from(None), 3);
+        assert_eq!(max_attempts_from(Some("0")), 1);
+        assert_eq!(max_attempts_from(Some(" 5 ")), 6);
+        assert_eq!(max_attempts_from(Some("many")), 3);
+
+        assert!(is_transient_failure(
+            "docker: Cannot connect to the Docker daemon at unix:///var/run/docker.sock. Is the docker daemon running?"
+        ));
+        assert!(is_transient_failure(
+            "Error response from daemon: Conflict. The container name \"/ev-nitro-cli-conversion-1\" is already in use by container \"abc\""
+        ));
+        assert!(!is_transient_failure(
+            "[ E19 ] Image not found. Could not find the provided docker image."
+        ));
+        assert!(!is_transient_failure(""));
+    }
+}
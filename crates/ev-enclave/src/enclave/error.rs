@@ -6,6 +6,12 @@ use thiserror::Error;
 pub enum ErrorKind {
     #[error("Docker exited with code {0}")]
     BuildError(i32),
+    #[error("Nitro CLI exited with code {code} after {attempts} attempt(s) — {stderr}")]
+    ConversionError {
+        code: i32,
+        attempts: u32,
+        stderr: String,
+    },
     #[error("An error occurred while deserializing.")]
     DeserializeError(serde_json::error::Error),
     #[error(transparent)]
@@ -18,6 +24,7 @@ impl CliError for ErrorKind {
     fn exitcode(&self) -> exitcode::ExitCode {
         match self {
             Self::BuildError(inner) => *inner,
+            Self::ConversionError { code, .. } => *code,
             Self::DeserializeError(_) => exitcode::IOERR,
             Self::DockerError(inner) => inner.exitcode(),
             Self::FsError(_) => exitcode::IOERR,
@@ -45,6 +52,17 @@ impl EnclaveError {
         }
     }
 
+    pub fn new_conversion_error(code: i32, attempts: u32, stderr: String) -> Self {
+        Self {
+            kind: ErrorKind::ConversionError {
+                code,
+                attempts,
+                stderr,
+            },
+            context: None,
+        }
+    }
+
     pub fn new_fs_error() -> Self {
         Self {
             kind: ErrorKind::FsError(None),
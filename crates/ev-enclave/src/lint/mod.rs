@@ -0,0 +1,237 @@
+use crate::config::EnclaveConfig;
+use common::table::Table;
+use serde::Serialize;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Low => write!(f, "low"),
+            Self::Medium => write!(f, "medium"),
+            Self::High => write!(f, "high"),
+        }
+    }
+}
+
+/// A setting in an Enclave's config which weakens its security
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Finding {
+    pub rule: &'static str,
+    pub severity: Severity,
+    pub message: String,
+    pub remediation: &'static str,
+}
+
+impl Finding {
+    fn new(
+        rule: &'static str,
+        severity: Severity,
+        message: impl Into<String>,
+        remediation: &'static str,
+    ) -> Self {
+        Self {
+            rule,
+            severity,
+            message: message.into(),
+            remediation,
+        }
+    }
+}
+
+/// Checks an Enclave's config for risky settings, most severe first
+pub fn audit_config(config: &EnclaveConfig) -> Vec<Finding> {
+    let mut findings = vec![];
+
+    if config.debug {
+        findings.push(Finding::new(
+            "debug-mode",
+            Severity::High,
+            "Debug mode is enabled, so the Enclave's console can be read from its host and its attestation docs contain zeroed PCRs.",
+            "Set debug = false before deploying to production.",
+        ));
+    }
+
+    // The [dataplane] block takes precedence over the top-level setting
+    let api_key_auth = config
+        .dataplane
+        .as_ref()
+        .and_then(|dataplane| dataplane.api_key_auth)
+        .unwrap_or(config.api_key_auth);
+    if !api_key_auth {
+        findings.push(Finding::new(
+            "api-key-auth-disabled",
+            Severity::High,
+            "API key auth is disabled, so anyone who can reach the Enclave's domain can call it.",
+            "Set api_key_auth = true, or authenticate requests in your service.",
+        ));
+    }
+
+    if config.egress.allows_any_destination() {
+        findings.push(Finding::new(
+            "wildcard-egress",
+            Severity::High,
+            "Egress is enabled for all destinations, so the Enclave can send data anywhere.",
+            "List the domains your service calls in the destinations or presets of the [egress] section.",
+        ));
+    }
+
+    if config.attestation.is_none() {
+        findings.push(Finding::new(
+            "missing-attestation",
+            Severity::Medium,
+            "The config has no [attestation] section, so there are no PCRs for clients to verify the Enclave against.",
+            "Run ev enclave build to write the Enclave's PCRs to the config, and commit them.",
+        ));
+    }
+
+    match config.nitro_cli_image() {
+        Ok(nitro_cli) if !nitro_cli.is_pinned() => findings.push(Finding::new(
+            "unpinned-nitro-cli",
+            Severity::Low,
+            "The Nitro CLI used to build the EIF isn't pinned, so a new release can change the Enclave's PCRs.",
+            "Set nitro_cli_version, or a nitro_cli_image pinned with @sha256:<digest>, in the [build] section.",
+        )),
+        Ok(_) => {}
+        Err(e) => findings.push(Finding::new(
+            "invalid-nitro-cli",
+            Severity::Low,
+            e.to_string(),
+            "Set either nitro_cli_version or nitro_cli_image in the [build] section.",
+        )),
+    }
+
+    findings.sort_by_key(|finding| std::cmp::Reverse(finding.severity));
+    findings
+}
+
+/// Whether any finding is at least as severe as the threshold
+pub fn exceeds_threshold(findings: &[Finding], threshold: Severity) -> bool {
+    findings.iter().any(|finding| finding.severity >= threshold)
+}
+
+pub fn findings_table(findings: &[Finding]) -> Table {
+    let mut table = Table::new([
+        ("severity", "SEVERITY"),
+        ("rule", "RULE"),
+        ("finding", "FINDING"),
+        ("remediation", "REMEDIATION"),
+    ]);
+    for finding in findings {
+        table.push_row(vec![
+            finding.severity.to_string(),
+            finding.rule.to_string(),
+            finding.message.clone(),
+            finding.remediation.to_string(),
+        ]);
+    }
+    table
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn config(extra: &str) -> EnclaveConfig {
+        toml::from_str(&format!(
+            r#"
+version = 1
+name = "hello"
+uuid = "1234"
+app_uuid = "4321"
+team_uuid = "teamid"
+debug = false
+{extra}
+"#
+        ))
+        .unwrap()
+    }
+
+    const SECURE: &str = r#"
+[egress]
+enabled = true
+destinations = ["api.stripe.com", "*.evervault.com"]
+
+[build]
+nitro_cli_version = "1.2.2"
+
+[attestation]
+HashAlgorithm = "Sha384 { ... }"
+PCR0 = "000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000"
+PCR1 = "111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111111"
+PCR2 = "222222222222222222222222222222222222222222222222222222222222222222222222222222222222222222222222"
+PCR8 = "888888888888888888888888888888888888888888888888888888888888888888888888888888888888888888888888"
+"#;
+
+    fn rules(findings: &[Finding]) -> Vec<&str> {
+        findings.iter().map(|finding| finding.rule).collect()
+    }
+
+    #[test]
+    fn test_secure_config_has_no_findings() {
+        assert!(audit_config(&config(SECURE)).is_empty());
+    }
+
+    #[test]
+    fn test_insecure_config_findings_are_ordered_by_severity() {
+        let config = config(
+            r#"
+api_key_auth = false
+
+[egress]
+enabled = true
+"#,
+        );
+        let findings = audit_config(&config);
+        assert_eq!(
+            rules(&findings),
+            vec![
+                "api-key-auth-disabled",
+                "wildcard-egress",
+                "missing-attestation",
+                "unpinned-nitro-cli"
+            ]
+        );
+        assert!(exceeds_threshold(&findings, Severity::High));
+    }
+
+    #[test]
+    fn test_egress_presets_are_not_wildcard_egress() {
+        let presets = SECURE.replace(
+            r#"destinations = ["api.stripe.com", "*.evervault.com"]"#,
+            r#"presets = ["stripe"]"#,
+        );
+        assert!(audit_config(&config(&presets)).is_empty());
+    }
+
+    #[test]
+    fn test_dataplane_api_key_auth_overrides_top_level() {
+        let mut config = config(SECURE);
+        config.debug = true;
+        config.dataplane = Some(crate::config::DataPlaneSettings {
+            api_key_auth: Some(false),
+            ..Default::default()
+        });
+        assert_eq!(
+            rules(&audit_config(&config)),
+            vec!["debug-mode", "api-key-auth-disabled"]
+        );
+    }
+
+    #[test]
+    fn test_fail_on_threshold() {
+        let findings = audit_config(&config(&SECURE.replace(
+            "nitro_cli_version = \"1.2.2\"",
+            "nitro_cli_image = \"nitro-cli:latest\"",
+        )));
+        assert_eq!(rules(&findings), vec!["unpinned-nitro-cli"]);
+        assert!(exceeds_threshold(&findings, Severity::Low));
+        assert!(!exceeds_threshold(&findings, Severity::Medium));
+    }
+}
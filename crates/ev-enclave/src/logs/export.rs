@@ -0,0 +1,296 @@
+use super::LogsError;
+use crate::api::enclave::LogEvent;
+use chrono::TimeZone;
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+const CSV_HEADER: &str = "timestamp,instance_id,message";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogExportFormat {
+    Ndjson,
+    Csv,
+}
+
+impl LogExportFormat {
+    /// The format is chosen from the export file's extension
+    pub fn from_path(path: &Path) -> Result<Self, LogsError> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("ndjson" | "jsonl") => Ok(Self::Ndjson),
+            Some("csv") => Ok(Self::Csv),
+            _ => Err(LogsError::UnsupportedExportFormat(
+                path.display().to_string(),
+            )),
+        }
+    }
+}
+
+/// When to start a new export file. Rotated files are numbered from newest to oldest, e.g.
+/// logs.1.ndjson is the most recently rotated file.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RotationPolicy {
+    pub max_bytes: Option<u64>,
+    /// The number of rotated files to keep alongside the current file
+    pub max_files: usize,
+}
+
+/// Parses a size such as `10MB` into bytes. Supports B, KB, MB and GB, using powers of 1024.
+pub fn parse_rotation_size(value: &str) -> Result<u64, LogsError> {
+    let invalid = || LogsError::InvalidRotationSize(value.to_string());
+    let value = value.trim().to_ascii_uppercase();
+    let unit_start = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (amount, unit) = value.split_at(unit_start);
+    let amount: u64 = amount.parse().map_err(|_| invalid())?;
+    let multiplier = match unit.trim() {
+        "" | "B" => 1,
+        "K" | "KB" => 1024,
+        "M" | "MB" => 1024 * 1024,
+        "G" | "GB" => 1024 * 1024 * 1024,
+        _ => return Err(invalid()),
+    };
+    match amount.checked_mul(multiplier) {
+        Some(0) | None => Err(invalid()),
+        Some(bytes) => Ok(bytes),
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportedLogEvent<'a> {
+    timestamp: String,
+    instance_id: &'a str,
+    message: &'a str,
+}
+
+impl<'a> ExportedLogEvent<'a> {
+    fn new(event: &'a LogEvent) -> Self {
+        let timestamp = chrono::Utc
+            .timestamp_millis_opt(event.timestamp())
+            .single()
+            .map(|timestamp| timestamp.to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+            .unwrap_or_else(|| event.timestamp().to_string());
+        Self {
+            timestamp,
+            instance_id: event.instance_id(),
+            message: event.message(),
+        }
+    }
+
+    fn to_csv_row(&self) -> String {
+        [self.timestamp.as_str(), self.instance_id, self.message]
+            .map(csv_field)
+            .join(",")
+    }
+}
+
+// Quotes fields containing separators, quotes or line breaks, as log messages often do
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Writes log events to a file, rotating it once it reaches the policy's size
+pub struct LogExporter {
+    path: PathBuf,
+    format: LogExportFormat,
+    rotation: RotationPolicy,
+    writer: BufWriter<File>,
+    bytes_written: u64,
+    events_written: usize,
+}
+
+impl LogExporter {
+    pub fn create(path: &Path, rotation: RotationPolicy) -> Result<Self, LogsError> {
+        let format = LogExportFormat::from_path(path)?;
+        let writer = Self::open(path)?;
+        let mut exporter = Self {
+            path: path.to_path_buf(),
+            format,
+            rotation,
+            writer,
+            bytes_written: 0,
+            events_written: 0,
+        };
+        exporter.write_header()?;
+        Ok(exporter)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn events_written(&self) -> usize {
+        self.events_written
+    }
+
+    fn open(path: &Path) -> Result<BufWriter<File>, LogsError> {
+        File::create(path)
+            .map(BufWriter::new)
+            .map_err(|source| export_error(path, source))
+    }
+
+    fn write_line(&mut self, line: &str) -> Result<(), LogsError> {
+        writeln!(self.writer, "{line}").map_err(|source| export_error(&self.path, source))?;
+        self.bytes_written += line.len() as u64 + 1;
+        Ok(())
+    }
+
+    fn write_header(&mut self) -> Result<(), LogsError> {
+        match self.format {
+            LogExportFormat::Csv => self.write_line(CSV_HEADER),
+            LogExportFormat::Ndjson => Ok(()),
+        }
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let stem = self
+            .path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let extension = self
+            .path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_string())
+            .unwrap_or_default();
+        self.path
+            .with_file_name(format!("{stem}.{index}.{extension}"))
+    }
+
+    fn rotate(&mut self) -> Result<(), LogsError> {
+        self.flush()?;
+        // Renaming over the oldest rotated file drops it
+        for index in (1..=self.rotation.max_files).rev() {
+            let from = if index == 1 {
+                self.path.clone()
+            } else {
+                self.rotated_path(index - 1)
+            };
+            if from.exists() {
+                std::fs::rename(&from, self.rotated_path(index))
+                    .map_err(|source| export_error(&from, source))?;
+            }
+        }
+        self.writer = Self::open(&self.path)?;
+        self.bytes_written = 0;
+        self.write_header()
+    }
+
+    fn needs_rotation(&self) -> bool {
+        let has_events = match self.format {
+            LogExportFormat::Csv => self.bytes_written > CSV_HEADER.len() as u64 + 1,
+            LogExportFormat::Ndjson => self.bytes_written > 0,
+        };
+        has_events
+            && self
+                .rotation
+                .max_bytes
+                .is_some_and(|max_bytes| self.bytes_written >= max_bytes)
+    }
+
+    pub fn write_events(&mut self, events: &[LogEvent]) -> Result<(), LogsError> {
+        for event in events {
+            if self.needs_rotation() {
+                self.rotate()?;
+            }
+            let exported = ExportedLogEvent::new(event);
+            let line = match self.format {
+                LogExportFormat::Ndjson => serde_json::to_string(&exported)
+                    .expect("Failed to serialize log event for export"),
+                LogExportFormat::Csv => exported.to_csv_row(),
+            };
+            self.write_line(&line)?;
+            self.events_written += 1;
+        }
+        self.flush()
+    }
+
+    pub fn flush(&mut self) -> Result<(), LogsError> {
+        self.writer
+            .flush()
+            .map_err(|source| export_error(&self.path, source))
+    }
+}
+
+fn export_error(path: &Path, source: std::io::Error) -> LogsError {
+    LogsError::ExportError {
+        path: path.display().to_string(),
+        source,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn log_event(timestamp: i64, message: &str) -> LogEvent {
+        serde_json::from_value(serde_json::json!({
+            "timestamp": timestamp,
+            "message": message,
+            "ingestionTime": timestamp,
+            "instanceId": "i-0123456789abcdef",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse_rotation_size() {
+        assert_eq!(parse_rotation_size("512").unwrap(), 512);
+        assert_eq!(parse_rotation_size("10MB").unwrap(), 10 * 1024 * 1024);
+        assert_eq!(parse_rotation_size("1g").unwrap(), 1024 * 1024 * 1024);
+        for invalid in ["", "0KB", "10TB", "MB"] {
+            assert!(parse_rotation_size(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_export_csv() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("logs.csv");
+        let mut exporter = LogExporter::create(&path, RotationPolicy::default()).unwrap();
+        exporter
+            .write_events(&[log_event(1700000000123, "GET /health, 200 \"ok\"")])
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "timestamp,instance_id,message\n2023-11-14T22:13:20.123Z,i-0123456789abcdef,\"GET /health, 200 \"\"ok\"\"\"\n"
+        );
+    }
+
+    #[test]
+    fn test_export_rotation() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("logs.ndjson");
+        let rotation = RotationPolicy {
+            max_bytes: Some(1),
+            max_files: 2,
+        };
+        let mut exporter = LogExporter::create(&path, rotation).unwrap();
+        let events: Vec<_> = (0..4)
+            .map(|i| log_event(1700000000000 + i, &format!("event {i}")))
+            .collect();
+        exporter.write_events(&events).unwrap();
+        assert_eq!(exporter.events_written(), 4);
+
+        let read = |name: &str| {
+            let line = std::fs::read_to_string(dir.path().join(name)).unwrap();
+            serde_json::from_str::<serde_json::Value>(&line).unwrap()["message"].clone()
+        };
+        // Every event exceeds the size, so each gets its own file and the oldest is dropped
+        assert_eq!(read("logs.ndjson"), "event 3");
+        assert_eq!(read("logs.1.ndjson"), "event 2");
+        assert_eq!(read("logs.2.ndjson"), "event 1");
+        assert!(!dir.path().join("logs.3.ndjson").exists());
+    }
+}
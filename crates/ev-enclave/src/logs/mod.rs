@@ -2,9 +2,50 @@ use chrono::TimeZone;
 use std::fmt::Write;
 use thiserror::Error;
 
-use crate::api::enclave::{EnclaveApi, EnclaveClient};
+use crate::api::enclave::{EnclaveApi, EnclaveClient, LogEvent, LogSource};
 use common::CliError;
 
+mod export;
+pub use export::{parse_rotation_size, LogExportFormat, LogExporter, RotationPolicy};
+mod search;
+pub use search::{parse_since, search_logs, LogSearch};
+
+// Followed logs are polled for, as the API has no streaming endpoint
+const FOLLOW_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+
+const DATA_PLANE_COLOR: &str = "\x1b[36m";
+const APP_COLOR: &str = "\x1b[32m";
+const RESET_COLOR: &str = "\x1b[0m";
+
+/// Which of the Enclave's log streams to show
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum LogSourceFilter {
+    /// Logs written by the Evervault data plane
+    DataPlane,
+    /// Logs written by your service
+    App,
+    #[default]
+    All,
+}
+
+impl LogSourceFilter {
+    pub fn matches(&self, event: &LogEvent) -> bool {
+        match self {
+            Self::DataPlane => event.source() == LogSource::DataPlane,
+            Self::App => event.source() == LogSource::App,
+            Self::All => true,
+        }
+    }
+
+    pub fn filter(&self, events: &[LogEvent]) -> Vec<LogEvent> {
+        events
+            .iter()
+            .filter(|event| self.matches(event))
+            .cloned()
+            .collect()
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum LogsError {
     #[error("Could not get system time - {0}")]
@@ -19,27 +60,48 @@ pub enum LogsError {
     TimestampFormatError,
     #[error("An error occurred while paginating your log data - {0}")]
     MinusError(#[from] minus::MinusError),
+    #[error("Can't export logs to {0}. Use a file ending in .ndjson, .jsonl or .csv")]
+    UnsupportedExportFormat(String),
+    #[error("Invalid rotation size `{0}`. Expected a number of bytes, optionally followed by KB, MB or GB, e.g. 10MB")]
+    InvalidRotationSize(String),
+    #[error("Invalid search window `{0}`. Expected a number followed by s, m, h or d, e.g. 24h")]
+    InvalidSince(String),
+    #[error("Invalid search query `{0}`. Expected some text to search for")]
+    InvalidSearchQuery(String),
+    #[error("Failed to write logs to {path} - {source}")]
+    ExportError {
+        path: String,
+        source: std::io::Error,
+    },
 }
 
 impl CliError for LogsError {
     fn exitcode(&self) -> exitcode::ExitCode {
         match self {
             Self::SystemTimeError(_) => exitcode::OSERR,
+            Self::UnsupportedExportFormat(_)
+            | Self::InvalidRotationSize(_)
+            | Self::InvalidSince(_)
+            | Self::InvalidSearchQuery(_) => exitcode::USAGE,
+            Self::ExportError { .. } => exitcode::IOERR,
             _ => exitcode::SOFTWARE,
         }
     }
 }
 
-pub async fn get_logs(
+fn epoch_millis(time: std::time::SystemTime) -> Result<u128, LogsError> {
+    Ok(time.duration_since(std::time::UNIX_EPOCH)?.as_millis())
+}
+
+/// Resolves the window to fetch logs for, defaulting to the last 30 minutes
+fn resolve_window(
     start_time: Option<String>,
     end_time: Option<String>,
-    enclave_uuid: String,
-    enclave_client: EnclaveClient,
-) -> Result<(), LogsError> {
+) -> Result<(u128, u128), LogsError> {
     let now = std::time::SystemTime::now();
     let log_end_time = match end_time {
         Some(end) => end.parse::<u128>()?,
-        None => now.duration_since(std::time::UNIX_EPOCH)?.as_millis(),
+        None => epoch_millis(now)?,
     };
 
     let log_start_time = match start_time {
@@ -50,12 +112,51 @@ pub async fn get_logs(
             .ok_or(LogsError::TimeError)?
             .as_millis(),
     };
+    Ok((log_start_time, log_end_time))
+}
+
+/// Whether logs printed to stdout can be colored by their source
+pub fn use_log_colors() -> bool {
+    atty::is(atty::Stream::Stdout) && std::env::var("TERM").map_or(true, |term| term != "dumb")
+}
+
+/// Formats a log with its instance, time and source. Sources are colored when `colored` is set.
+pub fn format_log_event(event: &LogEvent, colored: bool) -> Option<String> {
+    let instance_id = event.instance_id();
+    let short_instance_id = &instance_id[instance_id.len().saturating_sub(6)..];
+    let source = match (colored, event.source()) {
+        (false, source) => source.to_string(),
+        (true, LogSource::DataPlane) => format!("{DATA_PLANE_COLOR}data-plane{RESET_COLOR}"),
+        (true, LogSource::App) => format!("{APP_COLOR}app{RESET_COLOR}"),
+    };
+    format_timestamp(event.timestamp())
+        .map(|timestamp| {
+            format!(
+                "[ Instance-{} @ {} ] [{}] {}",
+                short_instance_id,
+                timestamp,
+                source,
+                event.message()
+            )
+        })
+        .ok()
+}
+
+pub async fn get_logs(
+    start_time: Option<String>,
+    end_time: Option<String>,
+    enclave_uuid: String,
+    enclave_client: EnclaveClient,
+    source: LogSourceFilter,
+) -> Result<(), LogsError> {
+    let (log_start_time, log_end_time) = resolve_window(start_time, end_time)?;
 
     let enclave_logs = enclave_client
         .get_enclave_logs(enclave_uuid.as_str(), log_start_time, log_end_time)
         .await?;
+    let log_events = source.filter(enclave_logs.log_events());
 
-    if enclave_logs.log_events().is_empty() {
+    if log_events.is_empty() {
         log::info!("No logs found between {log_start_time} and {log_end_time}");
         return Ok(());
     }
@@ -64,27 +165,13 @@ pub async fn get_logs(
 
     output.set_prompt(format!(
         "Retrieved {} logs from {log_start_time} to {log_end_time}",
-        enclave_logs.log_events().len()
+        log_events.len()
     ))?;
 
-    enclave_logs
-        .log_events()
+    let colored = use_log_colors();
+    log_events
         .iter()
-        .filter_map(|event| {
-            let mut instance_id = event.instance_id().to_string();
-            let instance_len = instance_id.len();
-            let _ = instance_id.drain(0..instance_len - 6);
-            format_timestamp(event.timestamp())
-                .map(|timestamp| {
-                    format!(
-                        "[ Instance-{} @ {} ] {}",
-                        instance_id,
-                        timestamp,
-                        event.message()
-                    )
-                })
-                .ok()
-        })
+        .filter_map(|event| format_log_event(event, colored))
         .for_each(|log_event| {
             writeln!(output, "{}", log_event).unwrap();
         });
@@ -92,6 +179,68 @@ pub async fn get_logs(
     Ok(minus::page_all(output)?)
 }
 
+/// Writes the logs for a window to the exporter, returning the number of events written
+pub async fn export_logs(
+    start_time: Option<String>,
+    end_time: Option<String>,
+    enclave_uuid: &str,
+    enclave_client: &EnclaveClient,
+    exporter: &mut LogExporter,
+    source: LogSourceFilter,
+) -> Result<usize, LogsError> {
+    let (log_start_time, log_end_time) = resolve_window(start_time, end_time)?;
+    let enclave_logs = enclave_client
+        .get_enclave_logs(enclave_uuid, log_start_time, log_end_time)
+        .await?;
+    let log_events = source.filter(enclave_logs.log_events());
+    exporter.write_events(&log_events)?;
+    Ok(log_events.len())
+}
+
+/// Polls for new logs from the start time until interrupted, passing each batch of events to
+/// `on_events` in the order they were logged.
+pub async fn follow_logs<F>(
+    start_time: Option<String>,
+    enclave_uuid: &str,
+    enclave_client: &EnclaveClient,
+    mut on_events: F,
+) -> Result<(), LogsError>
+where
+    F: FnMut(&[LogEvent]) -> Result<(), LogsError>,
+{
+    let (start_time, _) = resolve_window(start_time, None)?;
+    // Listen for the interrupt for the whole follow, so one sent mid-request isn't missed
+    tokio::select! {
+        result = poll_logs(start_time, enclave_uuid, enclave_client, &mut on_events) => result,
+        _ = tokio::signal::ctrl_c() => Ok(()),
+    }
+}
+
+async fn poll_logs<F>(
+    mut cursor: u128,
+    enclave_uuid: &str,
+    enclave_client: &EnclaveClient,
+    on_events: &mut F,
+) -> Result<(), LogsError>
+where
+    F: FnMut(&[LogEvent]) -> Result<(), LogsError>,
+{
+    loop {
+        let now = epoch_millis(std::time::SystemTime::now())?;
+        let enclave_logs = enclave_client
+            .get_enclave_logs(enclave_uuid, cursor, now)
+            .await?;
+        let mut events = enclave_logs.log_events().clone();
+        events.sort_by_key(LogEvent::timestamp);
+        // The cursor only moves past the newest log returned, rather than to the time polled
+        if let Some(last) = events.last() {
+            cursor = last.timestamp() as u128 + 1;
+        }
+        on_events(&events)?;
+        tokio::time::sleep(FOLLOW_POLL_INTERVAL).await;
+    }
+}
+
 fn format_timestamp(epoch: i64) -> Result<String, LogsError> {
     let epoch_secs = epoch / 1000;
     let epoch_nsecs = epoch % 1000;
@@ -104,3 +253,44 @@ fn format_timestamp(epoch: i64) -> Result<String, LogsError> {
         None => Err(LogsError::TimestampFormatError),
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn log_event(source: Option<&str>) -> LogEvent {
+        serde_json::from_value(serde_json::json!({
+            "timestamp": 1700000000123i64,
+            "message": "GET /health",
+            "ingestionTime": 1700000000123i64,
+            "instanceId": "i-0123456789abcdef",
+            "source": source,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_log_source_filter() {
+        let data_plane = log_event(Some("data-plane"));
+        let app = log_event(Some("app"));
+        let untagged = log_event(None);
+        assert!(LogSourceFilter::DataPlane.matches(&data_plane));
+        assert!(!LogSourceFilter::DataPlane.matches(&untagged));
+        assert!(LogSourceFilter::App.matches(&app));
+        assert!(LogSourceFilter::App.matches(&untagged));
+        assert_eq!(
+            LogSourceFilter::All
+                .filter(&[data_plane.clone(), app.clone()])
+                .len(),
+            2
+        );
+
+        assert_eq!(
+            format_log_event(&data_plane, false).unwrap(),
+            "[ Instance-abcdef @ 2023-11-14T22:13:20Z ] [data-plane] GET /health"
+        );
+        assert!(format_log_event(&app, true)
+            .unwrap()
+            .contains(&format!("[{APP_COLOR}app{RESET_COLOR}]")));
+    }
+}
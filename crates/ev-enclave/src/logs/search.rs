@@ -0,0 +1,258 @@
+use super::{epoch_millis, LogsError};
+use crate::api::enclave::{EnclaveApi, LogEvent};
+use futures::StreamExt;
+use regex::{Regex, RegexBuilder};
+
+/// The most logs a single query returns. Chunks which hit it are split until they don't.
+const LOGS_PER_QUERY: usize = 500;
+const SEARCH_CHUNK_MILLIS: u128 = 15 * 60 * 1000;
+// Chunks this narrow aren't split further, so a burst of logs can't split a chunk forever
+const MIN_CHUNK_MILLIS: u128 = 1000;
+
+const HIGHLIGHT_START: &str = "\x1b[1;31m";
+const HIGHLIGHT_END: &str = "\x1b[0m";
+
+/// Parses how far back to search, such as `30m`, `24h` or `7d`. Supports s, m, h and d.
+pub fn parse_since(value: &str) -> Result<std::time::Duration, LogsError> {
+    let invalid = || LogsError::InvalidSince(value.to_string());
+    let value = value.trim().to_ascii_lowercase();
+    let unit_start = value
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(invalid)?;
+    let (amount, unit) = value.split_at(unit_start);
+    let amount: u64 = amount.parse().map_err(|_| invalid())?;
+    let multiplier = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return Err(invalid()),
+    };
+    match amount.checked_mul(multiplier) {
+        Some(0) | None => Err(invalid()),
+        Some(secs) => Ok(std::time::Duration::from_secs(secs)),
+    }
+}
+
+/// A case-insensitive search for text in log messages
+#[derive(Clone, Debug)]
+pub struct LogSearch {
+    pattern: Regex,
+}
+
+impl LogSearch {
+    pub fn new(query: &str) -> Result<Self, LogsError> {
+        if query.trim().is_empty() {
+            return Err(LogsError::InvalidSearchQuery(query.to_string()));
+        }
+        let pattern = RegexBuilder::new(&regex::escape(query))
+            .case_insensitive(true)
+            .build()
+            .map_err(|_| LogsError::InvalidSearchQuery(query.to_string()))?;
+        Ok(Self { pattern })
+    }
+
+    pub fn is_match(&self, event: &LogEvent) -> bool {
+        self.pattern.is_match(event.message())
+    }
+
+    /// Wraps each match in the line in bold red, for output to a terminal
+    pub fn highlight(&self, line: &str) -> String {
+        self.pattern
+            .replace_all(line, |captures: &regex::Captures| {
+                format!("{HIGHLIGHT_START}{}{HIGHLIGHT_END}", &captures[0])
+            })
+            .into_owned()
+    }
+}
+
+/// Splits the window into chunks which are each fetched with a single query
+fn chunk_window(start: u128, end: u128, chunk_millis: u128) -> Vec<(u128, u128)> {
+    let mut chunks = Vec::new();
+    let mut chunk_start = start;
+    while chunk_start < end {
+        let chunk_end = (chunk_start + chunk_millis).min(end);
+        chunks.push((chunk_start, chunk_end));
+        chunk_start = chunk_end;
+    }
+    chunks
+}
+
+/// Searches the logs from `since` ago until now, returning the matching events in the order they
+/// were logged.
+pub async fn search_logs<T: EnclaveApi + Sync>(
+    enclave_client: &T,
+    enclave_uuid: &str,
+    search: &LogSearch,
+    since: std::time::Duration,
+    concurrency: usize,
+) -> Result<Vec<LogEvent>, LogsError> {
+    let end = epoch_millis(std::time::SystemTime::now())?;
+    let start = end.saturating_sub(since.as_millis());
+    search_window(
+        enclave_client,
+        enclave_uuid,
+        search,
+        start,
+        end,
+        concurrency,
+    )
+    .await
+}
+
+/// Fetches the window in chunks, with at most `concurrency` queries in flight. Chunks which
+/// return as many logs as a query can are halved and fetched again, so none are cut off.
+async fn search_window<T: EnclaveApi + Sync>(
+    enclave_client: &T,
+    enclave_uuid: &str,
+    search: &LogSearch,
+    start: u128,
+    end: u128,
+    concurrency: usize,
+) -> Result<Vec<LogEvent>, LogsError> {
+    let mut pending = chunk_window(start, end, SEARCH_CHUNK_MILLIS);
+    let mut matches = Vec::new();
+    while !pending.is_empty() {
+        let results: Vec<_> = futures::stream::iter(pending)
+            .map(|(chunk_start, chunk_end)| async move {
+                let logs = enclave_client
+                    .get_enclave_logs(enclave_uuid, chunk_start, chunk_end)
+                    .await;
+                ((chunk_start, chunk_end), logs)
+            })
+            .buffer_unordered(concurrency.max(1))
+            .collect()
+            .await;
+
+        pending = Vec::new();
+        for ((chunk_start, chunk_end), logs) in results {
+            let logs = logs?;
+            let width = chunk_end - chunk_start;
+            if logs.log_events().len() >= LOGS_PER_QUERY && width > MIN_CHUNK_MILLIS {
+                log::debug!(
+                    "Splitting logs from {chunk_start} to {chunk_end}, as they were cut off"
+                );
+                pending.extend(chunk_window(chunk_start, chunk_end, width.div_ceil(2)));
+                continue;
+            }
+            if logs.log_events().len() >= LOGS_PER_QUERY {
+                log::warn!("Some logs from {chunk_start} to {chunk_end} may be missing, as more than {LOGS_PER_QUERY} were logged");
+            }
+            matches.extend(
+                logs.log_events()
+                    .iter()
+                    .filter(|event| search.is_match(event))
+                    .cloned(),
+            );
+        }
+    }
+
+    // Neighbouring chunks share their boundary, so events logged on it can be returned twice
+    matches.sort_by(|a, b| {
+        (a.timestamp(), a.instance_id(), a.message()).cmp(&(
+            b.timestamp(),
+            b.instance_id(),
+            b.message(),
+        ))
+    });
+    matches.dedup_by(|a, b| {
+        (a.timestamp(), a.instance_id(), a.message())
+            == (b.timestamp(), b.instance_id(), b.message())
+    });
+    Ok(matches)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::api::enclave::{EnclaveLogs, MockEnclaveApi};
+
+    fn enclave_logs(events: impl Iterator<Item = (u128, &'static str)>) -> EnclaveLogs {
+        let log_events: Vec<_> = events
+            .map(|(timestamp, message)| {
+                serde_json::json!({
+                    "timestamp": timestamp,
+                    "message": message,
+                    "ingestionTime": timestamp,
+                    "instanceId": "i-0123456789abcdef",
+                })
+            })
+            .collect();
+        serde_json::from_value(serde_json::json!({
+            "logEvents": log_events,
+            "startTime": "0",
+            "endTime": "0",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse_since() {
+        assert_eq!(parse_since("30m").unwrap().as_secs(), 30 * 60);
+        assert_eq!(parse_since("24H").unwrap().as_secs(), 24 * 60 * 60);
+        assert_eq!(parse_since("7d").unwrap().as_secs(), 7 * 24 * 60 * 60);
+        for invalid in ["", "24", "0h", "h", "1w", "-1h"] {
+            assert!(parse_since(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_log_search_highlight() {
+        let search = LogSearch::new("error").unwrap();
+        assert_eq!(
+            search.highlight("Error: connection error"),
+            format!("{HIGHLIGHT_START}Error{HIGHLIGHT_END}: connection {HIGHLIGHT_START}error{HIGHLIGHT_END}")
+        );
+        assert_eq!(search.highlight("ok"), "ok");
+        // Queries are matched as text rather than patterns
+        assert_eq!(
+            LogSearch::new("a.b").unwrap().highlight("axb"),
+            "axb".to_string()
+        );
+        assert!(LogSearch::new(" ").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_search_window_splits_full_chunks() {
+        let start = 0;
+        let end = SEARCH_CHUNK_MILLIS * 2;
+        let mut mock_api = MockEnclaveApi::new();
+        mock_api
+            .expect_get_enclave_logs()
+            .returning(move |_, chunk_start, chunk_end| {
+                // The first chunk is too busy to fetch in one query, until it's been halved
+                let logs = if (chunk_start, chunk_end) == (0, SEARCH_CHUNK_MILLIS) {
+                    enclave_logs((0..LOGS_PER_QUERY as u128).map(|i| (i, "GET /health")))
+                } else if chunk_start == 0 {
+                    enclave_logs([(5, "Error: timed out")].into_iter())
+                } else if chunk_end == end {
+                    enclave_logs([(chunk_start, "ERROR: boundary"), (end - 1, "ok")].into_iter())
+                } else {
+                    enclave_logs([(chunk_end, "ERROR: boundary")].into_iter())
+                };
+                Box::pin(std::future::ready(Ok(logs)))
+            });
+
+        let search = LogSearch::new("error").unwrap();
+        let matches = search_window(&mock_api, "enclave", &search, start, end, 2)
+            .await
+            .unwrap();
+        let found: Vec<_> = matches
+            .iter()
+            .map(|event| (event.timestamp(), event.message()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (5, "Error: timed out"),
+                (SEARCH_CHUNK_MILLIS as i64, "ERROR: boundary"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_chunk_window() {
+        assert_eq!(chunk_window(0, 25, 10), vec![(0, 10), (10, 20), (20, 25)]);
+        assert!(chunk_window(10, 10, 10).is_empty());
+    }
+}
@@ -0,0 +1,57 @@
+use crate::common::{resolve_output_path, OutputPathError};
+use crate::docker::{error::DockerError, remote::remote_builder, utils::verify_docker_is_running};
+use crate::enclave::{self, error::EnclaveError, NitroCliImage};
+use common::CliError;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum NitroError {
+    #[error("The Nitro CLI is run with the output directory mounted, which remote builders can't do. Unset the remote builder to run it against the local docker daemon.")]
+    RemoteBuilder,
+    #[error("Failed to access output directory — {0}")]
+    FailedToAccessOutputDir(#[from] OutputPathError),
+    #[error("Failed to run the Nitro CLI — {0}")]
+    DockerError(#[from] DockerError),
+    #[error(transparent)]
+    EnclaveError(#[from] EnclaveError),
+}
+
+impl CliError for NitroError {
+    fn exitcode(&self) -> exitcode::ExitCode {
+        match self {
+            Self::RemoteBuilder => exitcode::CONFIG,
+            Self::FailedToAccessOutputDir(inner) => inner.exitcode(),
+            Self::DockerError(_) => exitcode::UNAVAILABLE,
+            Self::EnclaveError(inner) => inner.exitcode(),
+        }
+    }
+}
+
+/// Runs nitro-cli with arbitrary arguments in the Nitro CLI image the CLI builds Enclaves with,
+/// so commands the CLI doesn't wrap can be used without installing nitro-cli. The output
+/// directory is mounted as the working directory, and the docker socket is shared so commands
+/// like build-enclave can read local images.
+pub fn run_nitro_cli(
+    output_dir: &str,
+    nitro_cli: &NitroCliImage,
+    nitro_cli_args: &[String],
+    verbose: bool,
+    no_cache: bool,
+) -> Result<std::process::Output, NitroError> {
+    if remote_builder().is_some() {
+        return Err(NitroError::RemoteBuilder);
+    }
+    if !verify_docker_is_running()? {
+        return Err(DockerError::DaemonNotRunning.into());
+    }
+    let mounted_dir = resolve_output_path(Some(output_dir))?;
+
+    // The image's Dockerfile is written to a temp dir, so nothing is added to the output directory
+    let image_dir = resolve_output_path(None::<&str>)?;
+    enclave::build_nitro_cli_image(image_dir.path(), None, nitro_cli, verbose, no_cache)?;
+
+    Ok(enclave::run_nitro_cli_in_dir(
+        mounted_dir.path(),
+        nitro_cli_args,
+    )?)
+}
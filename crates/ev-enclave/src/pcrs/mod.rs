@@ -0,0 +1,143 @@
+use crate::api::enclave::{EnclaveApi, GetEnclaveResponse};
+use crate::config::{EnclaveConfig, EnclaveConfigError};
+use crate::enclave::EIFMeasurements;
+use common::CliError;
+use serde::Serialize;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum PcrsError {
+    #[error("An error occurred while reading the Enclave config — {0}")]
+    EnclaveConfigError(#[from] EnclaveConfigError),
+    #[error("No PCRs found for Enclave {0}. PCRs are recorded once a deployment finishes building on Evervault.")]
+    NoDeployedPcrs(String),
+    #[error("An error occurred contacting the API — {0}")]
+    ApiError(#[from] common::api::client::ApiError),
+}
+
+impl CliError for PcrsError {
+    fn exitcode(&self) -> exitcode::ExitCode {
+        match self {
+            Self::EnclaveConfigError(config_err) => config_err.exitcode(),
+            Self::NoDeployedPcrs(_) => exitcode::DATAERR,
+            Self::ApiError(api_err) => api_err.exitcode(),
+        }
+    }
+}
+
+/// The measurements of the Enclave's most recent deployment whose build finished on Evervault,
+/// with the PCR signature it was deployed with
+pub fn latest_deployed_measurements(enclave: GetEnclaveResponse) -> Option<EIFMeasurements> {
+    enclave
+        .deployments
+        .into_iter()
+        .filter(|deployment| deployment.deployment.is_finished())
+        .filter_map(|deployment| {
+            let signature = deployment.version.pcrs_signature;
+            deployment.version.pcrs.map(|pcrs| {
+                (
+                    deployment.deployment.started_at,
+                    EIFMeasurements::new(pcrs, signature),
+                )
+            })
+        })
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(_, measurements)| measurements)
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PulledPcrs {
+    pub uuid: String,
+    pub measurements: EIFMeasurements,
+    /// Whether the attestation section of the enclave.toml was different before the pull
+    pub changed: bool,
+}
+
+/// Writes the measurements of the Enclave's latest deployment into the attestation section of
+/// its enclave.toml, so a fresh clone can pin them without rebuilding the Enclave.
+pub async fn pull_pcrs<T: EnclaveApi>(
+    config_path: &str,
+    config_output: &str,
+    enclave_api: &T,
+) -> Result<PulledPcrs, PcrsError> {
+    let mut config = EnclaveConfig::try_from_filepath(config_path)?;
+    let enclave_uuid = config
+        .uuid
+        .clone()
+        .ok_or_else(|| EnclaveConfigError::MissingField("uuid".into()))?;
+
+    let enclave = enclave_api.get_enclave(&enclave_uuid).await?;
+    let measurements = latest_deployed_measurements(enclave)
+        .ok_or_else(|| PcrsError::NoDeployedPcrs(enclave_uuid.clone()))?;
+
+    let changed = config.attestation.as_ref().is_none_or(|existing| {
+        existing.pcrs() != measurements.pcrs() || existing.signature() != measurements.signature()
+    });
+    config.set_attestation(&measurements);
+    crate::common::save_enclave_config(&config, config_output);
+
+    Ok(PulledPcrs {
+        uuid: enclave_uuid,
+        measurements,
+        changed,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn deployment(started_at: &str, completed: bool, pcr0: Option<&str>) -> serde_json::Value {
+        serde_json::json!({
+            "uuid": started_at,
+            "enclaveUuid": "enclave_123",
+            "versionUuid": "version_123",
+            "signingCertUuid": "cert_123",
+            "debugMode": false,
+            "startedAt": started_at,
+            "completedAt": completed.then_some(started_at),
+            "enclaveVersion": {
+                "uuid": "version_123",
+                "version": 1,
+                "buildStatus": "ready",
+                "pcrs": pcr0.map(|pcr0| serde_json::json!({
+                    "PCR0": pcr0.repeat(48),
+                    "PCR1": "11".repeat(48),
+                    "PCR2": "22".repeat(48),
+                    "PCR8": "88".repeat(48)
+                })),
+                "pcrsSignature": pcr0.map(|pcr0| format!("signature-{pcr0}"))
+            }
+        })
+    }
+
+    #[test]
+    fn test_latest_deployed_measurements() {
+        let enclave: GetEnclaveResponse = serde_json::from_value(serde_json::json!({
+            "uuid": "enclave_123",
+            "name": "hello",
+            "teamUuid": "team_123",
+            "appUuid": "app_123",
+            "domain": "hello.app.enclave.evervault.com",
+            "state": "active",
+            "createdAt": "2026-01-01T00:00:00Z",
+            "updatedAt": "2026-01-01T00:00:00Z",
+            "enclaveDeployments": [
+                deployment("2026-01-01T00:00:00Z", true, Some("0a")),
+                deployment("2026-01-02T00:00:00Z", true, Some("1b")),
+                deployment("2026-01-03T00:00:00Z", true, None),
+                deployment("2026-01-04T00:00:00Z", false, Some("2c")),
+            ]
+        }))
+        .unwrap();
+
+        let measurements = latest_deployed_measurements(enclave.clone()).unwrap();
+        assert_eq!(measurements.pcrs().pcr0, crate::test_utils::pcr("1b"));
+        assert_eq!(measurements.signature(), Some("signature-1b"));
+
+        let mut undeployed = enclave;
+        undeployed.deployments.clear();
+        assert!(latest_deployed_measurements(undeployed).is_none());
+    }
+}
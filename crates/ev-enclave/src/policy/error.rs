@@ -0,0 +1,29 @@
+use common::CliError;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum PolicyError {
+    #[error("Failed to read the policy file at {0} - {1}")]
+    Io(String, std::io::Error),
+    #[error("Failed to parse the policy file at {0} - {1}")]
+    Parse(String, toml::de::Error),
+    #[error("Invalid egress pattern in policy - {0}")]
+    InvalidPattern(#[from] regex::Error),
+    #[error("Failed to run the policy command `{0}` - {1}")]
+    CommandFailed(String, std::io::Error),
+    #[error("The policy command `{0}` exited unsuccessfully - {1}")]
+    CommandExited(String, String),
+    #[error("Deployment blocked by policy {0}:\n{}", .1.iter().map(|violation| format!("  - {violation}")).collect::<Vec<_>>().join("\n"))]
+    Violations(String, Vec<String>),
+}
+
+impl CliError for PolicyError {
+    fn exitcode(&self) -> exitcode::ExitCode {
+        match self {
+            Self::Io(_, _) => exitcode::IOERR,
+            Self::Parse(_, _) | Self::InvalidPattern(_) => exitcode::CONFIG,
+            Self::CommandFailed(_, _) | Self::CommandExited(_, _) => exitcode::SOFTWARE,
+            Self::Violations(_, _) => exitcode::NOPERM,
+        }
+    }
+}
@@ -0,0 +1,266 @@
+pub mod error;
+
+use crate::config::ValidatedEnclaveBuildConfig;
+use crate::enclave::EIFMeasurements;
+pub use error::PolicyError;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_POLICY_FILENAME: &str = "policy.toml";
+
+/// Deploy-time guardrails, read from a `policy.toml` file. Every rule is optional.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct Policy {
+    /// Whether Enclaves may be deployed in debug mode
+    pub allow_debug: Option<bool>,
+    /// Egress destinations the Enclave may allow, as domain patterns (e.g. `*.internal`)
+    pub allowed_egress_destinations: Option<Vec<String>>,
+    /// Maximum number of replicas the Enclave may request
+    pub max_replicas: Option<u32>,
+    /// Whether a deployment may change the Enclave's PCRs
+    pub allow_pcr_changes: Option<bool>,
+    /// External policy engine (e.g. `opa eval`). The policy input is written to its stdin as JSON,
+    /// and it should print a JSON array of violations, or one violation per line.
+    pub command: Option<String>,
+}
+
+/// Facts about a deployment which policies are evaluated against
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PolicyInput {
+    pub enclave_name: String,
+    pub enclave_uuid: String,
+    pub debug: bool,
+    pub egress_enabled: bool,
+    pub egress_destinations: Vec<String>,
+    pub desired_replicas: Option<u32>,
+    pub changed_pcrs: Vec<String>,
+    pub measurements: EIFMeasurements,
+}
+
+impl PolicyInput {
+    pub fn new(
+        config: &ValidatedEnclaveBuildConfig,
+        previous: Option<&EIFMeasurements>,
+        built: &EIFMeasurements,
+    ) -> Self {
+        let egress = config.egress();
+        let egress_destinations = egress.allowed_destinations();
+        Self {
+            enclave_name: config.enclave_name().to_string(),
+            enclave_uuid: config.enclave_uuid().to_string(),
+            debug: config.debug,
+            egress_enabled: egress.is_enabled(),
+            egress_destinations,
+            desired_replicas: config
+                .scaling
+                .as_ref()
+                .map(|scaling| scaling.desired_replicas),
+            changed_pcrs: previous
+                .map(|previous| changed_pcrs(previous, built))
+                .unwrap_or_default(),
+            measurements: built.clone(),
+        }
+    }
+}
+
+fn changed_pcrs(previous: &EIFMeasurements, built: &EIFMeasurements) -> Vec<String> {
+    previous
+        .pcrs()
+        .differences(built.pcrs())
+        .into_iter()
+        .map(|difference| difference.name.to_string())
+        .collect()
+}
+
+impl Policy {
+    pub fn parse(contents: &str, path: &str) -> Result<Self, PolicyError> {
+        toml::from_str(contents).map_err(|e| PolicyError::Parse(path.to_string(), e))
+    }
+
+    pub fn try_from_filepath(path: &Path) -> Result<Self, PolicyError> {
+        let path_str = path.display().to_string();
+        let contents =
+            std::fs::read_to_string(path).map_err(|e| PolicyError::Io(path_str.clone(), e))?;
+        Self::parse(&contents, &path_str)
+    }
+
+    /// Returns a description of each rule the deployment violates
+    pub fn evaluate(&self, input: &PolicyInput) -> Result<Vec<String>, PolicyError> {
+        let mut violations = vec![];
+
+        if input.debug && self.allow_debug == Some(false) {
+            violations.push("Enclaves may not be deployed in debug mode".to_string());
+        }
+
+        if let Some(allowed) = self.allowed_egress_destinations.as_ref() {
+            let patterns = allowed
+                .iter()
+                .map(|pattern| destination_pattern(pattern))
+                .collect::<Result<Vec<_>, _>>()?;
+            input
+                .egress_destinations
+                .iter()
+                .filter(|destination| !patterns.iter().any(|pattern| pattern.is_match(destination)))
+                .for_each(|destination| {
+                    violations.push(format!(
+                        "Egress to {destination} is not allowed. Allowed destinations: {}",
+                        allowed.join(", ")
+                    ))
+                });
+        }
+
+        if let (Some(max_replicas), Some(desired_replicas)) =
+            (self.max_replicas, input.desired_replicas)
+        {
+            if desired_replicas > max_replicas {
+                violations.push(format!(
+                    "{desired_replicas} replicas requested, but at most {max_replicas} are allowed"
+                ));
+            }
+        }
+
+        if self.allow_pcr_changes == Some(false) && !input.changed_pcrs.is_empty() {
+            violations.push(format!(
+                "PCR changes are not allowed, but {} changed",
+                input.changed_pcrs.join(", ")
+            ));
+        }
+
+        if let Some(command) = self.command.as_deref() {
+            violations.extend(run_policy_command(command, input)?);
+        }
+
+        Ok(violations)
+    }
+}
+
+fn destination_pattern(pattern: &str) -> Result<regex::Regex, regex::Error> {
+    let escaped = regex::escape(pattern).replace(r"\*", ".*");
+    regex::Regex::new(&format!("^{escaped}$"))
+}
+
+fn run_policy_command(command: &str, input: &PolicyInput) -> Result<Vec<String>, PolicyError> {
+    let input = serde_json::to_vec(input).expect("Failed to serialize policy input");
+    let output = common::shell::run_command(command, &[], Some(&input))
+        .map_err(|e| PolicyError::CommandFailed(command.to_string(), e))?;
+    if !output.status.success() {
+        return Err(PolicyError::CommandExited(
+            command.to_string(),
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+
+    Ok(parse_command_violations(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+fn parse_command_violations(output: &str) -> Vec<String> {
+    if let Ok(violations) = serde_json::from_str::<Vec<String>>(output) {
+        return violations;
+    }
+    output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Resolves the policy file to use: an explicitly given path, or a `policy.toml` alongside the
+/// Enclave config if one exists.
+pub fn resolve_policy_path(policy_path: Option<&str>, config_path: &str) -> Option<PathBuf> {
+    if let Some(policy_path) = policy_path {
+        return Some(PathBuf::from(policy_path));
+    }
+    let default_path = Path::new(config_path)
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .join(DEFAULT_POLICY_FILENAME);
+    default_path.exists().then_some(default_path)
+}
+
+/// Evaluates the policy at the given path, if any, failing if the deployment violates it.
+pub fn enforce_policy(policy_path: Option<&Path>, input: &PolicyInput) -> Result<(), PolicyError> {
+    let Some(policy_path) = policy_path else {
+        return Ok(());
+    };
+
+    let policy = Policy::try_from_filepath(policy_path)?;
+    let violations = policy.evaluate(input)?;
+    if violations.is_empty() {
+        log::info!(
+            "Deployment satisfies the policy at {}.",
+            policy_path.display()
+        );
+        return Ok(());
+    }
+    Err(PolicyError::Violations(
+        policy_path.display().to_string(),
+        violations,
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn get_input() -> PolicyInput {
+        let measurements: EIFMeasurements = serde_json::from_value(serde_json::json!({
+            "HashAlgorithm": "Sha384 { ... }",
+            "PCR0": "0".repeat(96),
+            "PCR1": "1".repeat(96),
+            "PCR2": "2".repeat(96),
+            "PCR8": "8".repeat(96)
+        }))
+        .unwrap();
+        PolicyInput {
+            enclave_name: "hello-enclave".into(),
+            enclave_uuid: "enclave_123".into(),
+            debug: true,
+            egress_enabled: true,
+            egress_destinations: vec!["api.internal".into(), "api.stripe.com".into()],
+            desired_replicas: Some(4),
+            changed_pcrs: vec!["PCR0".into()],
+            measurements,
+        }
+    }
+
+    #[test]
+    fn test_evaluate_policy() {
+        let policy = Policy::parse(
+            r#"
+allow_debug = false
+allowed_egress_destinations = ["*.internal"]
+max_replicas = 2
+allow_pcr_changes = false
+"#,
+            "policy.toml",
+        )
+        .unwrap();
+
+        let violations = policy.evaluate(&get_input()).unwrap();
+        assert_eq!(violations.len(), 4);
+        assert!(violations[1].contains("api.stripe.com"));
+
+        assert!(Policy::default().evaluate(&get_input()).unwrap().is_empty());
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn test_evaluate_policy_command() {
+        let policy = Policy {
+            command: Some(
+                r#"grep -q '"debug":true' && echo '["debug enclaves are not allowed"]' || true"#
+                    .into(),
+            ),
+            ..Default::default()
+        };
+        let violations = policy.evaluate(&get_input()).unwrap();
+        assert_eq!(
+            violations,
+            vec!["debug enclaves are not allowed".to_string()]
+        );
+    }
+}
@@ -1,11 +1,52 @@
+#[cfg(feature = "tty")]
 use atty::Stream;
+#[cfg(feature = "tty")]
 use indicatif::{ProgressBar, ProgressStyle};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use crate::api::enclave::EnclaveApi;
+use common::api::rate_limit;
 use common::CliError;
 
-const MAX_SUCCESSIVE_POLLING_ERRORS: i32 = 5; // # attempts allowed at 6s intervals
+const MAX_SUCCESSIVE_POLLING_ERRORS: i32 = 5; // # consecutive failed polls allowed
+const MIN_POLL_INTERVAL: Duration = Duration::from_secs(6);
+const MAX_POLL_INTERVAL: Duration = Duration::from_secs(30);
+const POLL_INTERVAL_GROWTH_FACTOR: u32 = 2;
 
+/// How often a plain-text progress line is written while nothing else changes
+const STATUS_LINE_INTERVAL: Duration = Duration::from_secs(30);
+/// Upload progress is written each time it crosses a multiple of this percentage
+const UPLOAD_PERCENT_STEP: u64 = 10;
+
+static JSON_EVENTS: AtomicBool = AtomicBool::new(false);
+static PROGRESS_JSON: AtomicBool = AtomicBool::new(false);
+
+/// Write progress events to stdout as newline delimited JSON, for commands run with `--json`
+pub fn enable_json_events() {
+    JSON_EVENTS.store(true, Ordering::Relaxed);
+}
+
+/// Write all progress, including status messages and upload position, to stdout as newline
+/// delimited JSON instead of drawing progress bars, for `--progress-json`
+pub fn enable_progress_json() {
+    PROGRESS_JSON.store(true, Ordering::Relaxed);
+    enable_json_events();
+}
+
+/// Progress bars are only drawn when both stdout and stderr are terminals which can redraw lines.
+/// Otherwise, e.g. in CI logs, progress is written as plain-text status lines.
+#[cfg(feature = "tty")]
+fn use_progress_bars() -> bool {
+    !PROGRESS_JSON.load(Ordering::Relaxed)
+        && atty::is(Stream::Stdout)
+        && atty::is(Stream::Stderr)
+        && std::env::var("TERM").map_or(true, |term| term != "dumb")
+}
+
+#[cfg(feature = "tty")]
 fn get_progress_bar(start_msg: &str, upload_len: Option<u64>) -> ProgressBar {
     match upload_len {
         Some(len) => {
@@ -31,12 +72,96 @@ fn get_progress_bar(start_msg: &str, upload_len: Option<u64>) -> ProgressBar {
     }
 }
 
+/// A number of bytes, displayed with binary prefixes, e.g. `1.50 MiB`
+pub struct HumanBytes(pub u64);
+
+impl std::fmt::Display for HumanBytes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        const UNITS: [&str; 6] = ["KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+        if self.0 < 1024 {
+            return write!(f, "{} B", self.0);
+        }
+        let mut size = self.0 as f64 / 1024.0;
+        let mut unit = 0;
+        while size >= 1024.0 && unit < UNITS.len() - 1 {
+            size /= 1024.0;
+            unit += 1;
+        }
+        write!(f, "{size:.2} {}", UNITS[unit])
+    }
+}
+
+#[cfg(feature = "tty")]
 #[derive(Clone)]
 struct Tty {
     progress_bar: ProgressBar,
 }
+/// Reports progress as plain-text log lines, or JSON events with `--progress-json`. Lines are
+/// written when the status changes, and periodically while it doesn't.
 #[derive(Clone)]
-pub struct NonTty;
+pub struct NonTty {
+    state: Arc<Mutex<NonTtyState>>,
+    upload_len: Option<u64>,
+}
+
+struct NonTtyState {
+    started_at: Instant,
+    last_line_at: Instant,
+    message: String,
+    reported_percent: u64,
+}
+
+impl Default for NonTty {
+    fn default() -> Self {
+        Self::new("", None)
+    }
+}
+
+impl NonTty {
+    pub fn new(first_message: &str, upload_len: Option<u64>) -> Self {
+        let now = Instant::now();
+        Self {
+            state: Arc::new(Mutex::new(NonTtyState {
+                started_at: now,
+                last_line_at: now,
+                message: first_message.to_string(),
+                reported_percent: 0,
+            })),
+            upload_len,
+        }
+    }
+
+    fn write_line(&self, state: &mut NonTtyState, line: &str, extra: serde_json::Value) {
+        state.last_line_at = Instant::now();
+        if PROGRESS_JSON.load(Ordering::Relaxed) {
+            let mut event = serde_json::json!({
+                "event": "progress",
+                "message": line,
+                "elapsedMs": state.started_at.elapsed().as_millis() as u64,
+            });
+            if let (Some(event), serde_json::Value::Object(extra)) = (event.as_object_mut(), extra)
+            {
+                event.extend(extra);
+            }
+            println!("{event}");
+        } else {
+            log::info!("{line}");
+        }
+    }
+
+    fn write_message(&self, message: &str) {
+        let mut state = self.state.lock().expect("Progress state lock poisoned");
+        state.message = message.to_string();
+        self.write_line(&mut state, message, serde_json::Value::Null);
+    }
+}
+
+/// The percentage of an upload to report, when it has crossed the next step since the last report
+fn next_upload_percent(reported_percent: u64, bytes: u64, upload_len: u64) -> Option<u64> {
+    let percent = (bytes * 100).checked_div(upload_len)?.min(100);
+    let step = percent - percent % UPLOAD_PERCENT_STEP;
+    (step > reported_percent).then_some(step)
+}
 
 impl<'a, W: ProgressLogger + ?Sized + 'a> ProgressLogger for Box<W> {
     fn set_message(&self, message: &str) {
@@ -53,14 +178,94 @@ impl<'a, W: ProgressLogger + ?Sized + 'a> ProgressLogger for Box<W> {
     fn set_position(&self, bytes: u64) {
         (**self).set_position(bytes)
     }
+
+    fn report_step(&self, step: &ProgressStep) {
+        (**self).report_step(step)
+    }
+
+    fn heartbeat(&self) {
+        (**self).heartbeat()
+    }
 }
 pub trait ProgressLogger {
     fn set_message(&self, message: &str);
     fn finish_with_message(&self, message: &str);
     fn set_position(&self, bytes: u64);
     fn finish(&self);
+    /// Called with each step of a remote operation as it starts or completes
+    fn report_step(&self, _step: &ProgressStep) {}
+    /// Called periodically while waiting on a long running operation
+    fn heartbeat(&self) {}
+}
+
+/// A step of a long running remote operation, with its timings as reported by the API
+#[derive(Clone, Debug, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgressStep {
+    pub name: String,
+    pub status: String,
+    pub started_at: Option<String>,
+    pub completed_at: Option<String>,
+    pub duration_ms: Option<u64>,
+}
+
+impl ProgressStep {
+    pub fn new(
+        name: String,
+        status: String,
+        started_at: Option<String>,
+        completed_at: Option<String>,
+    ) -> Self {
+        let parse = |timestamp: &Option<String>| {
+            timestamp
+                .as_deref()
+                .and_then(|timestamp| chrono::DateTime::parse_from_rfc3339(timestamp).ok())
+        };
+        let duration_ms = match (parse(&started_at), parse(&completed_at)) {
+            (Some(started_at), Some(completed_at)) => {
+                u64::try_from((completed_at - started_at).num_milliseconds()).ok()
+            }
+            _ => None,
+        };
+        Self {
+            name,
+            status,
+            started_at,
+            completed_at,
+            duration_ms,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self.duration_ms {
+            Some(duration_ms) => format!(
+                "{} {} in {}",
+                self.name,
+                self.status,
+                format_duration(duration_ms)
+            ),
+            None => format!("{} {}", self.name, self.status),
+        }
+    }
 }
 
+pub(crate) fn format_duration(duration_ms: u64) -> String {
+    let seconds = duration_ms / 1000;
+    if seconds >= 60 {
+        format!("{}m {}s", seconds / 60, seconds % 60)
+    } else {
+        format!("{:.1}s", duration_ms as f64 / 1000.0)
+    }
+}
+
+fn emit_json_event(step: &ProgressStep) {
+    if JSON_EVENTS.load(Ordering::Relaxed) {
+        let event = serde_json::json!({ "event": "step", "step": step });
+        println!("{event}");
+    }
+}
+
+#[cfg(feature = "tty")]
 impl ProgressLogger for Tty {
     fn set_message(&self, message: &str) {
         self.progress_bar.set_message(message.to_string());
@@ -76,21 +281,72 @@ impl ProgressLogger for Tty {
     fn set_position(&self, bytes: u64) {
         self.progress_bar.set_position(bytes);
     }
+
+    fn report_step(&self, step: &ProgressStep) {
+        // only finished steps are printed, the running step is shown in the spinner message
+        if step.completed_at.is_some() {
+            self.progress_bar.println(format!("  {}", step.describe()));
+        }
+    }
 }
 
 impl ProgressLogger for NonTty {
     fn set_message(&self, message: &str) {
-        log::info!("{message}")
+        self.write_message(message)
     }
     fn finish_with_message(&self, message: &str) {
-        log::info!("{message}")
+        let mut state = self.state.lock().expect("Progress state lock poisoned");
+        let elapsed = format_duration(state.started_at.elapsed().as_millis() as u64);
+        self.write_line(
+            &mut state,
+            &format!("{message} ({elapsed})"),
+            serde_json::json!({ "finished": true }),
+        );
     }
     fn finish(&self) {
         // no op
     }
 
-    fn set_position(&self, _bytes: u64) {
-        // no op
+    fn set_position(&self, bytes: u64) {
+        let Some(upload_len) = self.upload_len else {
+            return;
+        };
+        let mut state = self.state.lock().expect("Progress state lock poisoned");
+        let next_percent = next_upload_percent(state.reported_percent, bytes, upload_len);
+        if next_percent.is_none() && state.last_line_at.elapsed() < STATUS_LINE_INTERVAL {
+            return;
+        }
+        if let Some(percent) = next_percent {
+            state.reported_percent = percent;
+        }
+        let line = format!(
+            "{} - {}% ({} of {})",
+            state.message,
+            (bytes * 100).checked_div(upload_len).unwrap_or(100),
+            HumanBytes(bytes),
+            HumanBytes(upload_len)
+        );
+        self.write_line(
+            &mut state,
+            &line,
+            serde_json::json!({ "bytes": bytes, "totalBytes": upload_len }),
+        );
+    }
+
+    fn report_step(&self, step: &ProgressStep) {
+        if !PROGRESS_JSON.load(Ordering::Relaxed) {
+            log::info!("{}", step.describe())
+        }
+    }
+
+    fn heartbeat(&self) {
+        let mut state = self.state.lock().expect("Progress state lock poisoned");
+        if state.last_line_at.elapsed() < STATUS_LINE_INTERVAL {
+            return;
+        }
+        let elapsed = format_duration(state.started_at.elapsed().as_millis() as u64);
+        let line = format!("{} - still running after {elapsed}", state.message);
+        self.write_line(&mut state, &line, serde_json::Value::Null);
     }
 }
 
@@ -98,13 +354,14 @@ pub fn get_tracker(
     first_message: &str,
     upload_len: Option<u64>,
 ) -> Box<dyn ProgressLogger + Send + Sync> {
-    if atty::is(Stream::Stdout) {
+    #[cfg(feature = "tty")]
+    if use_progress_bars() {
         let progress_bar = get_progress_bar(first_message, upload_len);
-        Box::new(Tty { progress_bar })
-    } else {
-        log::info!("{}", first_message);
-        Box::new(NonTty {})
+        return Box::new(Tty { progress_bar });
     }
+    let tracker = NonTty::new(first_message, upload_len);
+    tracker.write_message(first_message);
+    Box::new(tracker)
 }
 
 #[derive(Clone, Debug, PartialEq, PartialOrd)]
@@ -113,6 +370,8 @@ pub enum StatusReport {
     Complete(String),
     NoOp,
     Failed(String),
+    /// A report alongside the current state of each step of the operation
+    WithSteps(Box<StatusReport>, Vec<ProgressStep>),
 }
 
 impl StatusReport {
@@ -132,14 +391,47 @@ impl StatusReport {
         Self::NoOp
     }
 
+    pub fn with_steps(self, steps: Vec<ProgressStep>) -> Self {
+        if steps.is_empty() {
+            return self;
+        }
+        Self::WithSteps(Box::new(self), steps)
+    }
+
     pub fn get_msg(&self) -> Option<String> {
         match self {
             Self::Update(msg) | Self::Complete(msg) => Some(msg.clone()),
+            Self::WithSteps(report, _) => report.get_msg(),
             _ => None,
         }
     }
 }
 
+/// Polls at the initial interval while a status is changing, and backs off exponentially while
+/// it isn't. A Retry-After from the API is always respected.
+#[derive(Debug)]
+struct PollInterval {
+    current: Duration,
+}
+
+impl PollInterval {
+    fn new() -> Self {
+        Self {
+            current: MIN_POLL_INTERVAL,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.current = MIN_POLL_INTERVAL;
+    }
+
+    fn next(&mut self, server_hint: Option<Duration>) -> Duration {
+        let interval = server_hint.map_or(self.current, |hint| hint.max(self.current));
+        self.current = (self.current * POLL_INTERVAL_GROWTH_FACTOR).min(MAX_POLL_INTERVAL);
+        interval
+    }
+}
+
 // It should be possible to resolve the lifetimes to allow this work over borrows for every value instead of cloning/heap allocating
 pub async fn poll_fn_and_report_status<T: EnclaveApi, E, F, Fut>(
     api_client: std::sync::Arc<T>,
@@ -159,15 +451,38 @@ where
             .unwrap_or(true)
     };
     let mut poll_err_count = 0;
+    let mut reported_steps: Vec<ProgressStep> = vec![];
+    let mut poll_interval = PollInterval::new();
 
     loop {
-        match poll_fn(api_client.clone(), poll_args.clone()).await {
+        let polled = poll_fn(api_client.clone(), poll_args.clone()).await;
+        // Set when a request was rate limited, which also delays the next poll
+        let retry_after_hint = rate_limit::global().take_retry_after_hint();
+        let report = match polled {
+            Ok(StatusReport::WithSteps(report, steps)) => {
+                // report each step once per status change
+                steps
+                    .iter()
+                    .filter(|step| !reported_steps.contains(step))
+                    .filter(|step| step.started_at.is_some() || step.completed_at.is_some())
+                    .for_each(|step| {
+                        emit_json_event(step);
+                        progress_bar.report_step(step);
+                        poll_interval.reset();
+                    });
+                reported_steps = steps;
+                Ok(*report)
+            }
+            report => report,
+        };
+        match report {
             Ok(StatusReport::Update(msg)) => {
                 poll_err_count = 0; // only care about tracking *consecutive* poll errors
 
                 if is_new_update(most_recent_update.as_deref(), msg.as_str()) {
                     progress_bar.set_message(&msg);
                     most_recent_update = Some(msg);
+                    poll_interval.reset();
                 }
             }
             Ok(StatusReport::Complete(msg)) => {
@@ -179,7 +494,17 @@ where
                 log::error!("{cause}");
                 return Ok(false);
             }
-            Ok(StatusReport::NoOp) => {}
+            Ok(StatusReport::NoOp) | Ok(StatusReport::WithSteps(..)) => {}
+            // Rate limits are expected during busy periods, so they don't count as failed polls
+            Err(_) if retry_after_hint.is_some() => {
+                let retry_after = retry_after_hint.unwrap_or_default();
+                progress_bar.set_message(&format!(
+                    "Rate limited by the Evervault API, checking again in {}s...",
+                    retry_after.as_secs()
+                ));
+                // shown again once polling resumes
+                most_recent_update = None;
+            }
             Err(e) => {
                 poll_err_count += 1;
 
@@ -189,6 +514,64 @@ where
                 }
             }
         };
-        tokio::time::sleep(std::time::Duration::from_millis(6000)).await;
+        progress_bar.heartbeat();
+        let interval = poll_interval.next(retry_after_hint);
+        tokio::time::sleep(interval).await;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_poll_interval_backoff() {
+        let mut poll_interval = PollInterval::new();
+        assert_eq!(poll_interval.next(None), Duration::from_secs(6));
+        assert_eq!(poll_interval.next(None), Duration::from_secs(12));
+        assert_eq!(
+            poll_interval.next(Some(Duration::from_secs(60))),
+            Duration::from_secs(60)
+        );
+        assert_eq!(poll_interval.next(None), MAX_POLL_INTERVAL);
+        assert_eq!(poll_interval.next(None), MAX_POLL_INTERVAL);
+
+        poll_interval.reset();
+        assert_eq!(
+            poll_interval.next(Some(Duration::from_secs(1))),
+            MIN_POLL_INTERVAL
+        );
+    }
+
+    #[test]
+    fn test_next_upload_percent() {
+        assert_eq!(next_upload_percent(0, 5, 100), None);
+        assert_eq!(next_upload_percent(0, 12, 100), Some(10));
+        assert_eq!(next_upload_percent(10, 19, 100), None);
+        assert_eq!(next_upload_percent(10, 57, 100), Some(50));
+        assert_eq!(next_upload_percent(50, 100, 100), Some(100));
+        assert_eq!(next_upload_percent(100, 100, 100), None);
+        assert_eq!(next_upload_percent(0, 10, 0), None);
+    }
+
+    #[test]
+    fn test_human_bytes() {
+        assert_eq!(HumanBytes(512).to_string(), "512 B");
+        assert_eq!(HumanBytes(1536).to_string(), "1.50 KiB");
+        assert_eq!(HumanBytes(100 * 1024 * 1024).to_string(), "100.00 MiB");
+        assert_eq!(HumanBytes(3 * 1024 * 1024 * 1024).to_string(), "3.00 GiB");
+    }
+
+    #[test]
+    fn test_non_tty_tracks_latest_message() {
+        let tracker = NonTty::new("Uploading Enclave to Evervault", Some(1000));
+        tracker.set_position(450);
+        assert_eq!(tracker.state.lock().unwrap().reported_percent, 40);
+        tracker.set_message("Deploying Enclave...");
+        tracker.heartbeat();
+        assert_eq!(
+            tracker.state.lock().unwrap().message,
+            "Deploying Enclave..."
+        );
     }
 }
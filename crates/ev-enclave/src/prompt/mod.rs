@@ -0,0 +1,173 @@
+use atty::Stream;
+use common::CliError;
+use std::sync::atomic::{AtomicBool, Ordering};
+use thiserror::Error;
+
+static NON_INTERACTIVE: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Error)]
+pub enum PromptError {
+    #[error("\"{0}\" needs an answer, but the CLI isn't attached to a terminal. Pass --yes or set EV_NONINTERACTIVE=true to accept prompts automatically.")]
+    NoTerminal(String),
+    #[error("\"{0}\" has no default answer, so it can't be answered automatically with --yes.")]
+    InputRequired(String),
+    #[error("\"{0}\" needs an answer, but this build of the CLI doesn't support interactive prompts. Pass --yes or set EV_NONINTERACTIVE=true to accept prompts automatically.")]
+    Unsupported(String),
+    #[error("Failed to read your answer — {0}")]
+    IoError(#[from] std::io::Error),
+}
+
+impl CliError for PromptError {
+    fn exitcode(&self) -> exitcode::ExitCode {
+        match self {
+            Self::NoTerminal(_) | Self::InputRequired(_) | Self::Unsupported(_) => exitcode::USAGE,
+            Self::IoError(_) => exitcode::IOERR,
+        }
+    }
+}
+
+/// Answer prompts automatically for `--yes`: confirmations are accepted, and other prompts use
+/// their default or fail if they have none.
+pub fn enable_non_interactive() {
+    NON_INTERACTIVE.store(true, Ordering::Relaxed);
+}
+
+pub fn is_non_interactive() -> bool {
+    NON_INTERACTIVE.load(Ordering::Relaxed)
+}
+
+/// Whether prompts can be shown to the user, which needs a terminal for both input and the prompt
+pub fn can_prompt() -> bool {
+    cfg!(feature = "tty")
+        && !is_non_interactive()
+        && atty::is(Stream::Stdin)
+        && atty::is(Stream::Stderr)
+}
+
+/// Answers a prompt without showing it when running non-interactively. Returns None when the
+/// prompt should be shown, and fails fast rather than blocking when there's no terminal to show it
+/// on.
+pub fn auto_answer<T>(prompt: &str, default: Option<T>) -> Result<Option<T>, PromptError> {
+    if is_non_interactive() {
+        return match default {
+            Some(answer) => Ok(Some(answer)),
+            None => Err(PromptError::InputRequired(prompt.to_string())),
+        };
+    }
+    if !cfg!(feature = "tty") {
+        return Err(PromptError::Unsupported(prompt.to_string()));
+    }
+    if !atty::is(Stream::Stdin) {
+        return Err(PromptError::NoTerminal(prompt.to_string()));
+    }
+    Ok(None)
+}
+
+/// Asks the user to confirm an action, which is accepted automatically with `--yes`
+pub fn confirm(prompt: &str, default: bool) -> Result<bool, PromptError> {
+    if let Some(answer) = auto_answer(prompt, Some(true))? {
+        return Ok(answer);
+    }
+    dialog::confirm(prompt, default)
+}
+
+/// Asks the user to pick one of `items`, returning its index. Uses `default` with `--yes`.
+pub fn select(prompt: &str, items: &[String], default: usize) -> Result<usize, PromptError> {
+    if let Some(answer) = auto_answer(prompt, Some(default))? {
+        return Ok(answer);
+    }
+    dialog::select(prompt, items, default)
+}
+
+/// Asks the user to pick any of `items`, which start checked when their flag is set. Returns the
+/// indices picked, and can't be answered automatically with `--yes`.
+pub fn multi_select(prompt: &str, items: &[(&str, bool)]) -> Result<Vec<usize>, PromptError> {
+    if let Some(answer) = auto_answer(prompt, None)? {
+        return Ok(answer);
+    }
+    dialog::multi_select(prompt, items)
+}
+
+/// Asks the user for a line of text, which can't be answered automatically with `--yes`
+pub fn input(prompt: &str) -> Result<String, PromptError> {
+    if let Some(answer) = auto_answer(prompt, None)? {
+        return Ok(answer);
+    }
+    dialog::input(prompt)
+}
+
+#[cfg(feature = "tty")]
+mod dialog {
+    use super::PromptError;
+    use dialoguer::{Confirm, Input, MultiSelect, Select};
+
+    pub fn confirm(prompt: &str, default: bool) -> Result<bool, PromptError> {
+        Ok(Confirm::new()
+            .with_prompt(prompt)
+            .default(default)
+            .interact()?)
+    }
+
+    pub fn select(prompt: &str, items: &[String], default: usize) -> Result<usize, PromptError> {
+        Ok(Select::new()
+            .with_prompt(prompt)
+            .items(items)
+            .default(default)
+            .interact()?)
+    }
+
+    pub fn multi_select(prompt: &str, items: &[(&str, bool)]) -> Result<Vec<usize>, PromptError> {
+        Ok(MultiSelect::new()
+            .with_prompt(prompt)
+            .report(false)
+            .max_length(6)
+            .items_checked(items)
+            .interact()?)
+    }
+
+    pub fn input(prompt: &str) -> Result<String, PromptError> {
+        Ok(Input::new().with_prompt(prompt).interact_text()?)
+    }
+}
+
+// Builds without a terminal answer prompts automatically or reject them in auto_answer, so these
+// are only reached if that changes
+#[cfg(not(feature = "tty"))]
+mod dialog {
+    use super::PromptError;
+
+    pub fn confirm(prompt: &str, _default: bool) -> Result<bool, PromptError> {
+        Err(PromptError::Unsupported(prompt.to_string()))
+    }
+
+    pub fn select(prompt: &str, _items: &[String], _default: usize) -> Result<usize, PromptError> {
+        Err(PromptError::Unsupported(prompt.to_string()))
+    }
+
+    pub fn multi_select(prompt: &str, _items: &[(&str, bool)]) -> Result<Vec<usize>, PromptError> {
+        Err(PromptError::Unsupported(prompt.to_string()))
+    }
+
+    pub fn input(prompt: &str) -> Result<String, PromptError> {
+        Err(PromptError::Unsupported(prompt.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_non_interactive_answers() {
+        enable_non_interactive();
+        assert!(!can_prompt());
+        assert!(confirm("Delete this Enclave?", false).unwrap());
+        assert_eq!(
+            auto_answer("Enclave name", Some("hello")).unwrap(),
+            Some("hello")
+        );
+        let err = auto_answer::<String>("Enclave name", None).unwrap_err();
+        assert!(matches!(err, PromptError::InputRequired(_)));
+        assert_eq!(err.exitcode(), exitcode::USAGE);
+    }
+}
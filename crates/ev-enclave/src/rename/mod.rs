@@ -0,0 +1,144 @@
+use crate::api::enclave::{EnclaveApi, RenameEnclaveRequest};
+use crate::config::{EnclaveConfig, EnclaveConfigError};
+use common::CliError;
+use serde::Serialize;
+use thiserror::Error;
+
+// Enclave names are the first label of the Enclave's domain, so they follow DNS label rules
+const MAX_ENCLAVE_NAME_LENGTH: usize = 63;
+
+#[derive(Debug, Error)]
+pub enum RenameError {
+    #[error("An error occurred while reading the Enclave config — {0}")]
+    EnclaveConfigError(#[from] EnclaveConfigError),
+    #[error("No Enclave Uuid given. You can provide one by using either the --enclave-uuid flag, or using the --config flag to point to an Enclave.toml")]
+    MissingUuid,
+    #[error("Invalid Enclave name \"{0}\". Names are used in the Enclave's domain, so they must be 1 to 63 lowercase letters, numbers or dashes, and can't start or end with a dash.")]
+    InvalidName(String),
+    #[error("The Enclave is already named {0}")]
+    SameName(String),
+    #[error("An error occurred contacting the API — {0}")]
+    ApiError(#[from] common::api::client::ApiError),
+}
+
+impl CliError for RenameError {
+    fn exitcode(&self) -> exitcode::ExitCode {
+        match self {
+            Self::EnclaveConfigError(config_err) => config_err.exitcode(),
+            Self::MissingUuid | Self::InvalidName(_) => exitcode::DATAERR,
+            Self::SameName(_) => exitcode::USAGE,
+            Self::ApiError(api_err) => api_err.exitcode(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenamedEnclave {
+    pub uuid: String,
+    pub previous_name: String,
+    pub previous_domain: String,
+    pub name: String,
+    pub domain: String,
+    /// Whether the enclave.toml was updated with the new name
+    pub config_updated: bool,
+}
+
+pub fn validate_enclave_name(name: &str) -> Result<(), RenameError> {
+    let is_valid = !name.is_empty()
+        && name.len() <= MAX_ENCLAVE_NAME_LENGTH
+        && !name.starts_with('-')
+        && !name.ends_with('-')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+    if is_valid {
+        Ok(())
+    } else {
+        Err(RenameError::InvalidName(name.to_string()))
+    }
+}
+
+/// Renames the Enclave through the API, then updates the name in the enclave.toml when it points
+/// at the same Enclave so the config doesn't drift from the dashboard.
+pub async fn rename_enclave<T: EnclaveApi>(
+    config_path: &str,
+    enclave_uuid: Option<&str>,
+    new_name: &str,
+    enclave_api: &T,
+) -> Result<RenamedEnclave, RenameError> {
+    validate_enclave_name(new_name)?;
+
+    let config = match EnclaveConfig::try_from_filepath(config_path) {
+        Ok(config) => Some(config),
+        // The config is only needed for the Enclave's uuid when one isn't given
+        Err(_) if enclave_uuid.is_some() => None,
+        Err(e) => return Err(e.into()),
+    };
+    let enclave_uuid = enclave_uuid
+        .map(String::from)
+        .or_else(|| config.as_ref().and_then(|config| config.uuid.clone()))
+        .ok_or(RenameError::MissingUuid)?;
+
+    let current = enclave_api.get_enclave(&enclave_uuid).await?.enclaves;
+    if current.name == new_name {
+        return Err(RenameError::SameName(current.name));
+    }
+
+    log::info!("Renaming Enclave {} to {new_name}...", current.name);
+    let renamed = enclave_api
+        .rename_enclave(
+            &enclave_uuid,
+            RenameEnclaveRequest {
+                name: new_name.to_string(),
+            },
+        )
+        .await?;
+
+    let config_updated = match config {
+        Some(mut config) if config.uuid.as_deref() == Some(enclave_uuid.as_str()) => {
+            config.name = renamed.name.clone();
+            crate::common::save_enclave_config(&config, config_path);
+            true
+        }
+        _ => false,
+    };
+
+    Ok(RenamedEnclave {
+        uuid: enclave_uuid,
+        previous_name: current.name,
+        previous_domain: current.domain,
+        name: renamed.name,
+        domain: renamed.domain,
+        config_updated,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_validate_enclave_name() {
+        for valid in ["payments", "payments-api-2", "a"] {
+            assert!(validate_enclave_name(valid).is_ok(), "{valid}");
+        }
+        let too_long = "a".repeat(MAX_ENCLAVE_NAME_LENGTH + 1);
+        for invalid in [
+            "",
+            "Payments",
+            "payments_api",
+            "-payments",
+            "payments-",
+            &too_long,
+        ] {
+            assert!(
+                matches!(
+                    validate_enclave_name(invalid),
+                    Err(RenameError::InvalidName(_))
+                ),
+                "{invalid}"
+            );
+        }
+    }
+}
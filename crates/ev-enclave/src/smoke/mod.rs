@@ -0,0 +1,289 @@
+use common::CliError;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+const API_KEY_HEADER: &str = "api-key";
+
+#[derive(Debug, Error)]
+pub enum SmokeError {
+    #[error("Failed to create the HTTP client for the smoke test - {0}")]
+    ClientError(#[from] reqwest::Error),
+}
+
+impl CliError for SmokeError {
+    fn exitcode(&self) -> exitcode::ExitCode {
+        match self {
+            Self::ClientError(_) => exitcode::SOFTWARE,
+        }
+    }
+}
+
+/// Requests to send to a deployed Enclave, and the response each is expected to get
+#[derive(Clone, Debug)]
+pub struct SmokeTest {
+    pub base_url: String,
+    pub path: String,
+    pub expected_status: u16,
+    pub requests: u32,
+    pub timeout: Duration,
+    pub api_key: Option<String>,
+}
+
+impl SmokeTest {
+    pub fn url(&self) -> String {
+        let path = self.path.trim_start_matches('/');
+        format!("{}/{path}", self.base_url.trim_end_matches('/'))
+    }
+}
+
+/// The outcome of one request: the status it returned, or why it got no response
+#[derive(Clone, Debug)]
+pub struct RequestOutcome {
+    pub latency: Duration,
+    pub result: Result<u16, String>,
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SmokeFailure {
+    pub request: u32,
+    pub reason: String,
+}
+
+/// Nearest-rank latency percentiles in milliseconds
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+pub struct LatencyPercentiles {
+    pub p50: u64,
+    pub p90: u64,
+    pub p99: u64,
+    pub max: u64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SmokeReport {
+    pub url: String,
+    pub requests: u32,
+    pub passed: bool,
+    pub attested: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency: Option<LatencyPercentiles>,
+    pub failures: Vec<SmokeFailure>,
+}
+
+impl SmokeReport {
+    pub fn new(
+        url: String,
+        expected_status: u16,
+        outcomes: &[RequestOutcome],
+        attested: bool,
+    ) -> Self {
+        let failures: Vec<SmokeFailure> = outcomes
+            .iter()
+            .zip(1..)
+            .filter_map(|(outcome, request)| {
+                let reason = match &outcome.result {
+                    Ok(status) if *status == expected_status => return None,
+                    Ok(status) => format!("expected status {expected_status}, got {status}"),
+                    Err(e) => e.clone(),
+                };
+                Some(SmokeFailure { request, reason })
+            })
+            .collect();
+        let latencies: Vec<u64> = outcomes
+            .iter()
+            .map(|outcome| outcome.latency.as_millis() as u64)
+            .collect();
+        Self {
+            url,
+            requests: outcomes.len() as u32,
+            passed: !outcomes.is_empty() && failures.is_empty(),
+            attested,
+            latency: latency_percentiles(&latencies),
+            failures,
+        }
+    }
+
+    pub fn exitcode(&self) -> exitcode::ExitCode {
+        if self.passed {
+            exitcode::OK
+        } else {
+            exitcode::UNAVAILABLE
+        }
+    }
+}
+
+pub fn latency_percentiles(latencies: &[u64]) -> Option<LatencyPercentiles> {
+    if latencies.is_empty() {
+        return None;
+    }
+    let mut sorted = latencies.to_vec();
+    sorted.sort_unstable();
+    let percentile = |percent: usize| {
+        let rank = (percent * sorted.len()).div_ceil(100).max(1);
+        sorted[rank - 1]
+    };
+    Some(LatencyPercentiles {
+        p50: percentile(50),
+        p90: percentile(90),
+        p99: percentile(99),
+        max: sorted[sorted.len() - 1],
+    })
+}
+
+/// Sends the smoke test's requests one at a time, so each latency is measured without contention
+pub async fn run_smoke_test(test: &SmokeTest) -> Result<Vec<RequestOutcome>, SmokeError> {
+    let client = reqwest::Client::builder().timeout(test.timeout).build()?;
+    let url = test.url();
+    let mut outcomes = Vec::with_capacity(test.requests as usize);
+    for request in 1..=test.requests {
+        let mut request_builder = client.get(&url);
+        if let Some(api_key) = test.api_key.as_ref() {
+            request_builder = request_builder.header(API_KEY_HEADER, api_key);
+        }
+        let started = Instant::now();
+        let result = request_builder
+            .send()
+            .await
+            .map(|response| response.status().as_u16())
+            .map_err(|e| e.to_string());
+        let outcome = RequestOutcome {
+            latency: started.elapsed(),
+            result,
+        };
+        log::debug!(
+            "Smoke test request {request}/{} - {:?} in {}ms",
+            test.requests,
+            outcome.result,
+            outcome.latency.as_millis()
+        );
+        outcomes.push(outcome);
+    }
+    Ok(outcomes)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn outcome(latency_ms: u64, result: Result<u16, &str>) -> RequestOutcome {
+        RequestOutcome {
+            latency: Duration::from_millis(latency_ms),
+            result: result.map_err(String::from),
+        }
+    }
+
+    #[test]
+    fn test_latency_percentiles() {
+        let latencies: Vec<u64> = (1..=100).rev().collect();
+        assert_eq!(
+            latency_percentiles(&latencies),
+            Some(LatencyPercentiles {
+                p50: 50,
+                p90: 90,
+                p99: 99,
+                max: 100
+            })
+        );
+        assert_eq!(
+            latency_percentiles(&[7]),
+            Some(LatencyPercentiles {
+                p50: 7,
+                p90: 7,
+                p99: 7,
+                max: 7
+            })
+        );
+        assert_eq!(latency_percentiles(&[]), None);
+    }
+
+    #[test]
+    fn test_report_records_failures() {
+        let outcomes = vec![
+            outcome(10, Ok(200)),
+            outcome(20, Ok(503)),
+            outcome(30, Err("operation timed out")),
+        ];
+        let report = SmokeReport::new("https://enclave/health".into(), 200, &outcomes, false);
+        assert!(!report.passed);
+        assert_eq!(report.exitcode(), exitcode::UNAVAILABLE);
+        assert_eq!(
+            report.failures,
+            vec![
+                SmokeFailure {
+                    request: 2,
+                    reason: "expected status 200, got 503".into()
+                },
+                SmokeFailure {
+                    request: 3,
+                    reason: "operation timed out".into()
+                }
+            ]
+        );
+        assert_eq!(report.latency.unwrap().max, 30);
+    }
+
+    #[test]
+    fn test_report_without_requests_fails() {
+        let report = SmokeReport::new("https://enclave/".into(), 200, &[], true);
+        assert!(!report.passed);
+        assert!(report.latency.is_none());
+    }
+
+    #[test]
+    fn test_url_joins_path() {
+        let test = SmokeTest {
+            base_url: "https://hello.app-123.enclave.evervault.com/".into(),
+            path: "/health".into(),
+            expected_status: 200,
+            requests: 1,
+            timeout: Duration::from_secs(1),
+            api_key: None,
+        };
+        assert_eq!(
+            test.url(),
+            "https://hello.app-123.enclave.evervault.com/health"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_smoke_test_sends_api_key() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            for _ in 0..2 {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buffer = vec![0; 4096];
+                let read = stream.read(&mut buffer).await.unwrap();
+                requests.push(String::from_utf8_lossy(&buffer[..read]).to_lowercase());
+                stream
+                    .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\nconnection: close\r\n\r\n")
+                    .await
+                    .unwrap();
+            }
+            requests
+        });
+
+        let test = SmokeTest {
+            base_url: format!("http://{address}"),
+            path: "health".into(),
+            expected_status: 200,
+            requests: 2,
+            timeout: Duration::from_secs(5),
+            api_key: Some("ev:key:123".into()),
+        };
+        let outcomes = run_smoke_test(&test).await.unwrap();
+        let report = SmokeReport::new(test.url(), test.expected_status, &outcomes, false);
+        assert!(report.passed);
+        assert_eq!(report.requests, 2);
+
+        let requests = server.await.unwrap();
+        assert!(requests
+            .iter()
+            .all(|request| request.starts_with("get /health ")
+                && request.contains("api-key: ev:key:123")));
+    }
+}
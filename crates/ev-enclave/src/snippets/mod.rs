@@ -0,0 +1,278 @@
+use crate::api::enclave::EnclaveApi;
+use crate::config::{EnclaveConfig, EnclaveConfigError};
+use crate::enclave::PCRs;
+use crate::pcrs::latest_deployed_measurements;
+use common::CliError;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum SnippetError {
+    #[error("An error occurred while reading the Enclave config — {0}")]
+    EnclaveConfigError(#[from] EnclaveConfigError),
+    #[error("No PCRs found for Enclave {0}. Deploy the Enclave, or build it locally to add an attestation section to the enclave.toml.")]
+    MissingPcrs(String),
+    #[error("An error occurred contacting the API — {0}")]
+    ApiError(#[from] common::api::client::ApiError),
+}
+
+impl CliError for SnippetError {
+    fn exitcode(&self) -> exitcode::ExitCode {
+        match self {
+            Self::EnclaveConfigError(config_err) => config_err.exitcode(),
+            Self::MissingPcrs(_) => exitcode::DATAERR,
+            Self::ApiError(api_err) => api_err.exitcode(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum SnippetLanguage {
+    Node,
+    Python,
+    Go,
+}
+
+/// The Enclave a client snippet connects to, and the PCRs the client pins
+#[derive(Clone, Debug)]
+pub struct SnippetTarget {
+    pub name: String,
+    pub app_uuid: String,
+    pub domain: String,
+    pub pcrs: PCRs,
+}
+
+// The PCRs of the most recent deployment whose build finished on Evervault
+async fn deployed_pcrs<T: EnclaveApi>(
+    enclave_api: &T,
+    enclave_uuid: &str,
+) -> Result<PCRs, SnippetError> {
+    let enclave = enclave_api.get_enclave(enclave_uuid).await?;
+    latest_deployed_measurements(enclave)
+        .map(|measurements| measurements.pcrs().clone())
+        .ok_or_else(|| SnippetError::MissingPcrs(enclave_uuid.to_string()))
+}
+
+impl SnippetTarget {
+    /// Uses the PCRs in the attestation section of the config, unless they're missing or
+    /// `from_api` is set, in which case the PCRs of the Enclave's latest deployment are used.
+    pub async fn resolve<T: EnclaveApi>(
+        config: &EnclaveConfig,
+        enclave_api: &T,
+        from_api: bool,
+    ) -> Result<Self, SnippetError> {
+        let enclave_uuid = config
+            .uuid
+            .clone()
+            .ok_or_else(|| EnclaveConfigError::MissingField("uuid".into()))?;
+        let pcrs = match config.attestation.as_ref() {
+            Some(measurements) if !from_api => measurements.pcrs().clone(),
+            _ => deployed_pcrs(enclave_api, &enclave_uuid).await?,
+        };
+        Ok(Self {
+            name: config.name.clone(),
+            app_uuid: config
+                .app_uuid
+                .clone()
+                .ok_or_else(|| EnclaveConfigError::MissingField("app_uuid".into()))?,
+            domain: config.get_enclave_domain()?,
+            pcrs,
+        })
+    }
+
+    pub fn render(&self, language: SnippetLanguage) -> String {
+        match language {
+            SnippetLanguage::Node => self.to_node(),
+            SnippetLanguage::Python => self.to_python(),
+            SnippetLanguage::Go => self.to_go(),
+        }
+    }
+
+    // (name, value) pairs for each PCR, leaving out PCR8 for unsigned builds
+    fn pcr_values(&self) -> Vec<(u8, &str)> {
+        let mut values = vec![
+            (0, self.pcrs.pcr0.as_str()),
+            (1, self.pcrs.pcr1.as_str()),
+            (2, self.pcrs.pcr2.as_str()),
+        ];
+        if let Some(pcr8) = self.pcrs.pcr8.as_deref() {
+            values.push((8, pcr8));
+        }
+        values
+    }
+
+    fn to_node(&self) -> String {
+        let pcrs = self
+            .pcr_values()
+            .iter()
+            .map(|(index, value)| format!("      pcr{index}: {},\n", quoted(value)))
+            .collect::<String>();
+        format!(
+            r#"// Generated by `ev enclave snippets`. Generate it again after each deploy to pin the new PCRs.
+const https = require('https');
+const Evervault = require('@evervault/sdk');
+
+const evervault = new Evervault({app_uuid}, process.env.EV_API_KEY);
+
+async function main() {{
+  // Requests to the Enclave fail unless it attests to these PCRs
+  await evervault.enableEnclaves({{
+    {name}: {{
+{pcrs}    }},
+  }});
+
+  https.get({url}, (response) => {{
+    console.log(`Enclave responded with status ${{response.statusCode}}`);
+  }});
+}}
+
+main();
+"#,
+            app_uuid = quoted(&self.app_uuid),
+            name = quoted(&self.name),
+            url = quoted(&self.url()),
+            pcrs = pcrs,
+        )
+    }
+
+    fn to_python(&self) -> String {
+        let pcrs = self
+            .pcr_values()
+            .iter()
+            .map(|(index, value)| format!("pcr_{index}={}", quoted(value)))
+            .collect::<Vec<_>>()
+            .join(",\n                ");
+        format!(
+            r#"# Generated by `ev enclave snippets`. Generate it again after each deploy to pin the new PCRs.
+import os
+
+import evervault
+
+evervault.init({app_uuid}, os.environ["EV_API_KEY"])
+
+# Requests sent with the session fail unless the Enclave attests to these PCRs
+session = evervault.enclave_requests_session(
+    {{
+        {name}: [
+            evervault.PCRs(
+                {pcrs},
+            )
+        ]
+    }}
+)
+
+response = session.get({url})
+print(f"Enclave responded with status {{response.status_code}}")
+"#,
+            app_uuid = quoted(&self.app_uuid),
+            name = quoted(&self.name),
+            url = quoted(&self.url()),
+            pcrs = pcrs,
+        )
+    }
+
+    fn to_go(&self) -> String {
+        let pcrs = self
+            .pcr_values()
+            .iter()
+            .map(|(index, value)| format!("\t\tPCR{index}: {},\n", quoted(value)))
+            .collect::<String>();
+        format!(
+            r#"// Generated by `ev enclave snippets`. Generate it again after each deploy to pin the new PCRs.
+package main
+
+import (
+	"log"
+	"os"
+
+	"github.com/evervault/evervault-go"
+)
+
+func main() {{
+	client, err := evervault.MakeClient({app_uuid}, os.Getenv("EV_API_KEY"))
+	if err != nil {{
+		log.Fatal(err)
+	}}
+
+	// Requests sent with the client fail unless the Enclave attests to these PCRs
+	enclaveClient, err := client.EnclaveClient({name}, []evervault.PCRs{{{{
+{pcrs}	}}}})
+	if err != nil {{
+		log.Fatal(err)
+	}}
+
+	response, err := enclaveClient.Get({url})
+	if err != nil {{
+		log.Fatal(err)
+	}}
+	defer response.Body.Close()
+	log.Printf("Enclave responded with status %d", response.StatusCode)
+}}
+"#,
+            app_uuid = quoted(&self.app_uuid),
+            name = quoted(&self.name),
+            url = quoted(&self.url()),
+            pcrs = pcrs,
+        )
+    }
+
+    fn url(&self) -> String {
+        format!("https://{}/", self.domain)
+    }
+}
+
+// JSON string literals are valid string literals in JavaScript, Python and Go
+fn quoted(value: &str) -> String {
+    serde_json::to_string(value).expect("Failed to quote string")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::pcr;
+
+    fn target(pcr8: Option<&str>) -> SnippetTarget {
+        SnippetTarget {
+            name: "payments-api".into(),
+            app_uuid: "app_123".into(),
+            domain: "payments-api.app-123.enclave.evervault.com".into(),
+            pcrs: PCRs {
+                pcr0: pcr("00"),
+                pcr1: pcr("11"),
+                pcr2: pcr("22"),
+                pcr8: pcr8.map(pcr),
+            },
+        }
+    }
+
+    #[test]
+    fn test_render_node() {
+        let snippet = target(Some("88")).render(SnippetLanguage::Node);
+        assert!(snippet.contains("new Evervault(\"app_123\", process.env.EV_API_KEY)"));
+        assert!(snippet.contains(&format!(
+            "    \"payments-api\": {{\n      pcr0: \"{}\",\n",
+            "00".repeat(48)
+        )));
+        assert!(snippet.contains(&format!("      pcr8: \"{}\",\n    }},\n", "88".repeat(48))));
+        assert!(
+            snippet.contains("https.get(\"https://payments-api.app-123.enclave.evervault.com/\"")
+        );
+    }
+
+    #[test]
+    fn test_render_python_and_go() {
+        let (pcr0, pcr1) = ("00".repeat(48), "11".repeat(48));
+        let snippet = target(Some("88")).render(SnippetLanguage::Python);
+        assert!(snippet.contains(&format!(
+            "pcr_0=\"{pcr0}\",\n                pcr_1=\"{pcr1}\""
+        )));
+        assert!(snippet.contains(&format!("pcr_8=\"{}\",\n            )", "88".repeat(48))));
+
+        // Unsigned builds have no PCR8 to pin
+        let snippet = target(None).render(SnippetLanguage::Go);
+        assert!(snippet.contains(&format!(
+            "client.EnclaveClient(\"payments-api\", []evervault.PCRs{{{{\n\t\tPCR0: \"{pcr0}\",\n"
+        )));
+        assert!(snippet.contains(&format!("\t\tPCR2: \"{}\",\n\t}}}})", "22".repeat(48))));
+        assert!(!snippet.contains("PCR8"));
+    }
+}
@@ -0,0 +1,284 @@
+use crate::api::enclave::{EnclaveApi, EnclaveScalingConfig, EnclaveState, GetEnclaveResponse};
+use chrono::{DateTime, Utc};
+use common::api::client::{ApiError, ApiErrorKind};
+use common::CliError;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+const STATE_DIRECTORY: &str = ".evervault";
+const STATE_FILENAME: &str = "state.json";
+
+#[derive(Debug, Error)]
+pub enum StateError {
+    #[error("Failed to write the local Enclave state to {0} - {1}")]
+    Io(String, std::io::Error),
+    #[error("Failed to serialize the local Enclave state - {0}")]
+    Serialize(#[from] serde_json::Error),
+    #[error("Failed to fetch the Enclave's state from the Evervault API - {0}")]
+    Api(#[from] ApiError),
+}
+
+impl CliError for StateError {
+    fn exitcode(&self) -> exitcode::ExitCode {
+        match self {
+            Self::Io(_, _) => exitcode::IOERR,
+            Self::Serialize(_) => exitcode::SOFTWARE,
+            Self::Api(api_err) => api_err.exitcode(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CachedDeployment {
+    pub uuid: String,
+    pub version: u16,
+    pub started_at: Option<String>,
+    pub completed_at: Option<String>,
+}
+
+/// The remote metadata of an Enclave as of the last command which fetched it
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CachedEnclave {
+    pub uuid: String,
+    pub name: String,
+    pub domain: String,
+    pub state: EnclaveState,
+    pub last_deployment: Option<CachedDeployment>,
+    pub desired_replicas: Option<u32>,
+    /// RFC 3339 timestamp of when the metadata was fetched
+    pub cached_at: String,
+}
+
+impl CachedEnclave {
+    pub fn new(enclave: &GetEnclaveResponse, scaling: Option<&EnclaveScalingConfig>) -> Self {
+        let last_deployment = enclave
+            .deployments
+            .iter()
+            .max_by(|a, b| a.deployment.started_at.cmp(&b.deployment.started_at))
+            .map(|deployment| CachedDeployment {
+                uuid: deployment.deployment.uuid.clone(),
+                version: deployment.version.version,
+                started_at: deployment.deployment.started_at.clone(),
+                completed_at: deployment.deployment.completed_at.clone(),
+            });
+        Self {
+            uuid: enclave.enclaves.uuid.clone(),
+            name: enclave.enclaves.name.clone(),
+            domain: enclave.domain().to_string(),
+            state: enclave.enclaves.state,
+            last_deployment,
+            desired_replicas: scaling.map(EnclaveScalingConfig::desired_replicas),
+            cached_at: Utc::now().to_rfc3339(),
+        }
+    }
+
+    /// How long ago the metadata was fetched, such as 5m, if the timestamp can be read
+    pub fn age(&self) -> Option<String> {
+        let cached_at = DateTime::parse_from_rfc3339(&self.cached_at).ok()?;
+        Some(format_age(Utc::now() - cached_at.with_timezone(&Utc)))
+    }
+
+    pub fn summary(&self) -> String {
+        let deployment = match self.last_deployment.as_ref() {
+            Some(deployment) => format!(
+                "version {} ({}{})",
+                deployment.version,
+                deployment.uuid,
+                if deployment.completed_at.is_some() {
+                    ""
+                } else {
+                    ", in progress"
+                }
+            ),
+            None => "none".to_string(),
+        };
+        [
+            format!("Enclave: {} ({}) - {}", self.name, self.uuid, self.state),
+            format!("Domain: https://{}", self.domain),
+            format!("Last deployment: {deployment}"),
+            format!(
+                "Desired replicas: {}",
+                self.desired_replicas
+                    .map(|replicas| replicas.to_string())
+                    .unwrap_or_else(|| "-".to_string())
+            ),
+        ]
+        .join("\n")
+    }
+}
+
+fn format_age(age: chrono::Duration) -> String {
+    let seconds = age.num_seconds().max(0);
+    match seconds {
+        0..=59 => format!("{seconds}s"),
+        60..=3599 => format!("{}m", seconds / 60),
+        3600..=86399 => format!("{}h", seconds / 3600),
+        _ => format!("{}d", seconds / 86400),
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct LocalState {
+    #[serde(default)]
+    enclaves: BTreeMap<String, CachedEnclave>,
+}
+
+/// The state file lives alongside the Enclave config, so each project caches its own Enclaves
+pub fn state_path(config_path: &str) -> PathBuf {
+    let project_dir = match Path::new(config_path).parent() {
+        Some(parent) if config_path != "-" && !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    project_dir.join(STATE_DIRECTORY).join(STATE_FILENAME)
+}
+
+// The cache is only an optimisation, so a missing or unreadable file is treated as empty
+fn load_state(path: &Path) -> LocalState {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn save_state(path: &Path, state: &LocalState) -> Result<(), StateError> {
+    let io_err = |e| StateError::Io(path.display().to_string(), e);
+    if let Some(dir) = path.parent() {
+        if !dir.exists() {
+            std::fs::create_dir_all(dir).map_err(io_err)?;
+            // keep cached metadata out of version control
+            std::fs::write(dir.join(".gitignore"), "*\n").map_err(io_err)?;
+        }
+    }
+    // written to a temporary file first, so a concurrent command never reads a partial file
+    let temp_path = path.with_extension("json.tmp");
+    std::fs::write(&temp_path, serde_json::to_vec_pretty(state)?).map_err(io_err)?;
+    std::fs::rename(&temp_path, path).map_err(io_err)
+}
+
+pub fn load_cached_enclave(config_path: &str, enclave_uuid: &str) -> Option<CachedEnclave> {
+    load_state(&state_path(config_path))
+        .enclaves
+        .remove(enclave_uuid)
+}
+
+pub fn cache_enclave(config_path: &str, enclave: CachedEnclave) -> Result<(), StateError> {
+    let path = state_path(config_path);
+    let mut state = load_state(&path);
+    state.enclaves.insert(enclave.uuid.clone(), enclave);
+    save_state(&path, &state)
+}
+
+/// Applies a change made by a command to the cached Enclave, if it's cached. Returns whether it was.
+pub fn update_cached_enclave(
+    config_path: &str,
+    enclave_uuid: &str,
+    update: impl FnOnce(&mut CachedEnclave),
+) -> Result<bool, StateError> {
+    let path = state_path(config_path);
+    let mut state = load_state(&path);
+    let Some(enclave) = state.enclaves.get_mut(enclave_uuid) else {
+        return Ok(false);
+    };
+    update(enclave);
+    save_state(&path, &state)?;
+    Ok(true)
+}
+
+/// Fetches the Enclave's metadata from the API and caches it
+pub async fn refresh_cached_enclave<T: EnclaveApi>(
+    enclave_api: &T,
+    config_path: &str,
+    enclave_uuid: &str,
+) -> Result<CachedEnclave, StateError> {
+    let (enclave, scaling) = tokio::join!(
+        enclave_api.get_enclave(enclave_uuid),
+        enclave_api.get_scaling_config(enclave_uuid)
+    );
+    let scaling = match scaling {
+        Ok(scaling) => Some(scaling),
+        Err(e) if matches!(e.kind, ApiErrorKind::NotFound) => None,
+        Err(e) => return Err(e.into()),
+    };
+    let cached = CachedEnclave::new(&enclave?, scaling.as_ref());
+    cache_enclave(config_path, cached.clone())?;
+    Ok(cached)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::api::enclave::{BuildStatus, DeployStatus, DeploymentsForGetEnclave};
+    use crate::test_utils::{build_get_enclave_deployment, build_get_enclave_response};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_state_path() {
+        assert_eq!(
+            state_path("./enclave.toml"),
+            Path::new("./.evervault/state.json")
+        );
+        assert_eq!(
+            state_path("enclave.toml"),
+            Path::new("./.evervault/state.json")
+        );
+        assert_eq!(
+            state_path("/projects/hello/enclave.toml"),
+            Path::new("/projects/hello/.evervault/state.json")
+        );
+        assert_eq!(state_path("-"), Path::new("./.evervault/state.json"));
+    }
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(chrono::Duration::seconds(-5)), "0s");
+        assert_eq!(format_age(chrono::Duration::seconds(42)), "42s");
+        assert_eq!(format_age(chrono::Duration::minutes(5)), "5m");
+        assert_eq!(format_age(chrono::Duration::hours(3)), "3h");
+        assert_eq!(format_age(chrono::Duration::days(2)), "2d");
+    }
+
+    #[test]
+    fn test_cache_enclave() {
+        let project_dir = TempDir::new().unwrap();
+        let config_path = project_dir.path().join("enclave.toml");
+        let config_path = config_path.to_str().unwrap();
+
+        let deployment = build_get_enclave_deployment(
+            BuildStatus::Ready,
+            DeployStatus::Ready,
+            Some("2024-01-01T00:00:00Z".into()),
+            Some("2024-01-01T00:05:00Z".into()),
+        );
+        let response = build_get_enclave_response(
+            EnclaveState::Active,
+            vec![DeploymentsForGetEnclave {
+                deployment: deployment.deployment,
+                version: deployment.enclave_version,
+            }],
+        );
+        let cached = CachedEnclave::new(&response, None);
+        let uuid = cached.uuid.clone();
+        assert!(cached.last_deployment.is_some());
+        assert!(load_cached_enclave(config_path, &uuid).is_none());
+        assert!(!update_cached_enclave(config_path, &uuid, |_| {}).unwrap());
+
+        cache_enclave(config_path, cached.clone()).unwrap();
+        assert_eq!(load_cached_enclave(config_path, &uuid), Some(cached));
+        assert!(project_dir.path().join(".evervault/.gitignore").exists());
+
+        assert!(update_cached_enclave(config_path, &uuid, |enclave| {
+            enclave.desired_replicas = Some(3)
+        })
+        .unwrap());
+        assert_eq!(
+            load_cached_enclave(config_path, &uuid)
+                .unwrap()
+                .desired_replicas,
+            Some(3)
+        );
+    }
+}
@@ -0,0 +1,201 @@
+use thiserror::Error;
+
+use crate::api::enclave::{EnclaveApi, EnclaveMetrics, MetricDatapoint, ReplicaMetrics};
+use common::table::Table;
+use common::CliError;
+
+pub const METRIC_WINDOWS: [&str; 5] = ["15m", "1h", "6h", "24h", "7d"];
+
+const SPARK_CHARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+#[derive(Debug, Error)]
+pub enum StatsError {
+    #[error("Error retrieving metrics - {0}")]
+    ApiError(#[from] common::api::client::ApiError),
+}
+
+impl CliError for StatsError {
+    fn exitcode(&self) -> exitcode::ExitCode {
+        match self {
+            Self::ApiError(inner) => inner.exitcode(),
+        }
+    }
+}
+
+pub async fn get_metrics<T: EnclaveApi>(
+    enclave_api: &T,
+    enclave_uuid: &str,
+    window: &str,
+) -> Result<EnclaveMetrics, StatsError> {
+    Ok(enclave_api
+        .get_enclave_metrics(enclave_uuid, window)
+        .await?)
+}
+
+fn short_instance_id(replica: &ReplicaMetrics) -> String {
+    let instance_id = replica.instance_id();
+    // Counted in chars, so an id with multi-byte characters is never sliced mid-character
+    let start = instance_id.chars().count().saturating_sub(6);
+    let suffix: String = instance_id.chars().skip(start).collect();
+    format!("Instance-{suffix}")
+}
+
+fn average(datapoints: &[MetricDatapoint]) -> Option<f64> {
+    if datapoints.is_empty() {
+        return None;
+    }
+    Some(datapoints.iter().map(MetricDatapoint::value).sum::<f64>() / datapoints.len() as f64)
+}
+
+fn maximum(datapoints: &[MetricDatapoint]) -> Option<f64> {
+    datapoints
+        .iter()
+        .map(MetricDatapoint::value)
+        .reduce(f64::max)
+}
+
+fn format_utilization(datapoints: &[MetricDatapoint]) -> String {
+    match (average(datapoints), maximum(datapoints)) {
+        (Some(avg), Some(max)) => format!("{avg:.1}% / {max:.1}%"),
+        _ => "-".to_string(),
+    }
+}
+
+/// One row per replica with average and peak utilization over the window
+pub fn metrics_table(metrics: &EnclaveMetrics) -> Table {
+    let mut table = Table::new([
+        ("instance", "INSTANCE"),
+        ("cpu", "CPU (AVG / MAX)"),
+        ("memory", "MEMORY (AVG / MAX)"),
+        ("requests", "REQUESTS"),
+        ("restarts", "RESTARTS"),
+    ]);
+    for replica in metrics.replicas() {
+        table.push_row(vec![
+            short_instance_id(replica),
+            format_utilization(replica.cpu_utilization()),
+            format_utilization(replica.memory_utilization()),
+            replica.request_count().to_string(),
+            replica.restart_count().to_string(),
+        ]);
+    }
+    table
+}
+
+pub fn sparkline(values: &[f64]) -> String {
+    let (min, max) = values
+        .iter()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), value| {
+            (min.min(*value), max.max(*value))
+        });
+    let range = max - min;
+    values
+        .iter()
+        .map(|value| {
+            let index = if range > 0.0 {
+                (((value - min) / range) * (SPARK_CHARS.len() - 1) as f64).round() as usize
+            } else {
+                0
+            };
+            SPARK_CHARS[index.min(SPARK_CHARS.len() - 1)]
+        })
+        .collect()
+}
+
+/// One row per replica with CPU and memory utilization over the window as sparklines, followed
+/// by the latest value
+pub fn sparklines_table(metrics: &EnclaveMetrics) -> Table {
+    let mut table = Table::new([
+        ("instance", "INSTANCE"),
+        ("cpu", "CPU"),
+        ("memory", "MEMORY"),
+        ("requests", "REQUESTS"),
+        ("restarts", "RESTARTS"),
+    ]);
+    let utilization = |datapoints: &[MetricDatapoint]| {
+        let values: Vec<f64> = datapoints.iter().map(MetricDatapoint::value).collect();
+        match values.last() {
+            Some(latest) => format!("{} {latest:.1}%", sparkline(&values)),
+            None => "-".to_string(),
+        }
+    };
+    for replica in metrics.replicas() {
+        table.push_row(vec![
+            short_instance_id(replica),
+            utilization(replica.cpu_utilization()),
+            utilization(replica.memory_utilization()),
+            replica.request_count().to_string(),
+            replica.restart_count().to_string(),
+        ]);
+    }
+    table
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn get_metrics() -> EnclaveMetrics {
+        serde_json::from_value(serde_json::json!({
+            "window": "1h",
+            "replicas": [{
+                "instanceId": "i-0abc123def456",
+                "cpuUtilization": [
+                    { "timestamp": 1, "value": 10.0 },
+                    { "timestamp": 2, "value": 30.0 }
+                ],
+                "memoryUtilization": [],
+                "requestCount": 1200,
+                "restartCount": 1
+            }]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_render_table() {
+        let table = metrics_table(&get_metrics()).render(true);
+        let lines: Vec<&str> = table.lines().collect();
+        assert!(lines[0].starts_with("INSTANCE"));
+        assert!(lines[1].starts_with("Instance-def456"));
+        assert!(lines[1].contains("20.0% / 30.0%"));
+        assert!(lines[1].contains("1200"));
+    }
+
+    #[test]
+    fn test_render_sparklines_table() {
+        let table = sparklines_table(&get_metrics()).render(true);
+        let lines: Vec<&str> = table.lines().collect();
+        assert!(lines[0].starts_with("INSTANCE"));
+        assert!(lines[1].starts_with("Instance-def456"));
+        assert!(lines[1].contains("▁█ 30.0%"));
+        assert!(lines[1].contains(" - "));
+    }
+
+    #[test]
+    fn test_short_instance_id() {
+        let replica = |instance_id: &str| -> ReplicaMetrics {
+            serde_json::from_value(serde_json::json!({
+                "instanceId": instance_id,
+                "cpuUtilization": [],
+                "memoryUtilization": [],
+                "requestCount": 0,
+                "restartCount": 0
+            }))
+            .unwrap()
+        };
+        assert_eq!(
+            short_instance_id(&replica("i-0abc123def456")),
+            "Instance-def456"
+        );
+        assert_eq!(short_instance_id(&replica("abc")), "Instance-abc");
+        assert_eq!(short_instance_id(&replica("i-ünïcødé")), "Instance-nïcødé");
+    }
+
+    #[test]
+    fn test_sparkline() {
+        assert_eq!(sparkline(&[0.0, 50.0, 100.0]), "▁▅█");
+        assert_eq!(sparkline(&[5.0, 5.0]), "▁▁");
+        assert_eq!(sparkline(&[]), "");
+    }
+}
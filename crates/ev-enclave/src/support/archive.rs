@@ -0,0 +1,128 @@
+use flate2::{write::GzEncoder, Compression};
+use std::io::Write;
+
+const BLOCK_SIZE: usize = 512;
+const NAME_LENGTH: usize = 100;
+
+/// A minimal writer for gzipped ustar archives of regular files. Support bundles only hold a
+/// handful of small files, so entries are written in one pass with no directories or links.
+pub struct TarGzWriter<W: Write> {
+    encoder: GzEncoder<W>,
+    mtime: u64,
+}
+
+// Numeric header fields are zero padded octal, terminated with a NUL
+fn write_octal(field: &mut [u8], value: u64) {
+    let digits = format!("{value:0width$o}", width = field.len() - 1);
+    field[..digits.len()].copy_from_slice(digits.as_bytes());
+}
+
+fn header(name: &str, size: u64, mtime: u64) -> std::io::Result<[u8; BLOCK_SIZE]> {
+    if name.len() > NAME_LENGTH {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("{name} is longer than the {NAME_LENGTH} bytes a tar entry name can hold"),
+        ));
+    }
+    let mut header = [0u8; BLOCK_SIZE];
+    header[..name.len()].copy_from_slice(name.as_bytes());
+    write_octal(&mut header[100..108], 0o644);
+    write_octal(&mut header[108..116], 0);
+    write_octal(&mut header[116..124], 0);
+    write_octal(&mut header[124..136], size);
+    write_octal(&mut header[136..148], mtime);
+    header[156] = b'0';
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+
+    // the checksum is calculated with its own field set to spaces
+    header[148..156].copy_from_slice(b"        ");
+    let checksum: u64 = header.iter().map(|byte| u64::from(*byte)).sum();
+    header[148..156].fill(0);
+    write_octal(&mut header[148..155], checksum);
+    header[155] = b' ';
+    Ok(header)
+}
+
+impl<W: Write> TarGzWriter<W> {
+    pub fn new(writer: W, mtime: u64) -> Self {
+        Self {
+            encoder: GzEncoder::new(writer, Compression::default()),
+            mtime,
+        }
+    }
+
+    pub fn append(&mut self, name: &str, contents: &[u8]) -> std::io::Result<()> {
+        self.encoder
+            .write_all(&header(name, contents.len() as u64, self.mtime)?)?;
+        self.encoder.write_all(contents)?;
+        let padding = (BLOCK_SIZE - contents.len() % BLOCK_SIZE) % BLOCK_SIZE;
+        self.encoder.write_all(&vec![0u8; padding])
+    }
+
+    /// Writes the two empty blocks which end an archive, and flushes the compressed stream
+    pub fn finish(mut self) -> std::io::Result<W> {
+        self.encoder.write_all(&[0u8; BLOCK_SIZE * 2])?;
+        self.encoder.finish()
+    }
+}
+
+#[cfg(test)]
+pub(super) fn read_entries(archive: &[u8]) -> Vec<(String, Vec<u8>)> {
+    use std::io::Read;
+    let mut tar = vec![];
+    flate2::read::GzDecoder::new(archive)
+        .read_to_end(&mut tar)
+        .unwrap();
+
+    let mut entries = vec![];
+    let mut offset = 0;
+    while offset + BLOCK_SIZE <= tar.len() && tar[offset] != 0 {
+        let header = &tar[offset..offset + BLOCK_SIZE];
+        let name_end = header[..NAME_LENGTH]
+            .iter()
+            .position(|byte| *byte == 0)
+            .unwrap_or(NAME_LENGTH);
+        let name = String::from_utf8(header[..name_end].to_vec()).unwrap();
+        let size =
+            usize::from_str_radix(std::str::from_utf8(&header[124..135]).unwrap(), 8).unwrap();
+        let stored: u64 =
+            u64::from_str_radix(std::str::from_utf8(&header[148..154]).unwrap(), 8).unwrap();
+        let mut unsummed = header.to_vec();
+        unsummed[148..156].copy_from_slice(b"        ");
+        assert_eq!(
+            stored,
+            unsummed.iter().map(|byte| u64::from(*byte)).sum::<u64>()
+        );
+
+        offset += BLOCK_SIZE;
+        entries.push((name, tar[offset..offset + size].to_vec()));
+        offset += size.div_ceil(BLOCK_SIZE) * BLOCK_SIZE;
+    }
+    entries
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_tar_gz_round_trip() {
+        let mut writer = TarGzWriter::new(vec![], 1_700_000_000);
+        writer.append("bundle/system.json", b"{}").unwrap();
+        writer.append("bundle/empty.log", b"").unwrap();
+        let large = vec![b'a'; BLOCK_SIZE + 1];
+        writer.append("bundle/large.log", &large).unwrap();
+        assert!(writer.append(&"a".repeat(NAME_LENGTH + 1), b"").is_err());
+        let archive = writer.finish().unwrap();
+
+        assert_eq!(
+            read_entries(&archive),
+            vec![
+                ("bundle/system.json".to_string(), b"{}".to_vec()),
+                ("bundle/empty.log".to_string(), vec![]),
+                ("bundle/large.log".to_string(), large),
+            ]
+        );
+    }
+}
@@ -6,8 +6,8 @@ use crate::api::enclave::{
 use crate::build::build_enclave_image_file;
 use crate::build::error::BuildError;
 use crate::common::OutputPath;
-use crate::config::{read_and_validate_config, ValidatedEnclaveBuildConfig};
-use crate::enclave::BuiltEnclave;
+use crate::config::{read_and_validate_config, BuildTimeConfig, ValidatedEnclaveBuildConfig};
+use crate::enclave::{BuiltEnclave, Pcr};
 use common::api::enclave_assets::EnclaveAssetsClient;
 
 pub async fn build_test_enclave(
@@ -15,14 +15,18 @@ pub async fn build_test_enclave(
     from_existing: Option<String>,
     reproducible: bool,
 ) -> Result<(BuiltEnclave, OutputPath), BuildError> {
+    let signing_dir = tempfile::TempDir::new().unwrap();
     let dn_string = crate::cert::DistinguishedName::default();
-    crate::cert::create_new_cert(
-        std::path::Path::new("."),
+    let (cert_path, key_path) = crate::cert::create_new_cert(
+        signing_dir.path(),
         dn_string,
         crate::cert::DesiredLifetime::default(),
     )
     .expect("Failed to gen cert in tests");
-    let build_args = get_test_build_args();
+    let build_args = get_test_build_args(&TestSigningArgs {
+        cert: cert_path.to_str().unwrap().to_string(),
+        key: key_path.to_str().unwrap().to_string(),
+    });
     let assets_client = EnclaveAssetsClient::new();
 
     let data_plane_version = assets_client.get_data_plane_version().await.unwrap();
@@ -41,13 +45,30 @@ pub async fn build_test_enclave(
         from_existing,
         reproducible,
         true,
+        None,
     )
     .await
 }
 
-fn get_test_build_args() -> ValidatedEnclaveBuildConfig {
-    let (_enclave_config, validated_config) = read_and_validate_config("./test.enclave.toml", &())
-        .expect("Testing config failed to validate");
+struct TestSigningArgs {
+    cert: String,
+    key: String,
+}
+
+impl BuildTimeConfig for TestSigningArgs {
+    fn certificate(&self) -> Option<&str> {
+        Some(&self.cert)
+    }
+
+    fn private_key(&self) -> Option<&str> {
+        Some(&self.key)
+    }
+}
+
+fn get_test_build_args(signing: &TestSigningArgs) -> ValidatedEnclaveBuildConfig {
+    let (_enclave_config, validated_config) =
+        read_and_validate_config("./test.enclave.toml", signing)
+            .expect("Testing config failed to validate");
     validated_config
 }
 
@@ -89,13 +110,19 @@ pub fn build_get_enclave_deployment(
         enclave_version: EnclaveVersion {
             uuid: "".into(),
             version: 0,
-            control_plane_img_url: Some("".into()),
+            control_plane_img_url: Some("".to_string()).into(),
             control_plane_version: Some("".into()),
             data_plane_version: None,
             build_status,
             failure_reason: None,
             started_at: started_at.clone(),
             healthcheck: None,
+            build_steps: vec![],
+            pcrs: None,
+            pcrs_signature: None,
+            installer_version: None,
+            git_hash: None,
+            git_timestamp: None,
         },
         enclave_signing_cert: EnclaveSigningCert {
             name: Some("".into()),
@@ -115,6 +142,13 @@ pub fn build_get_enclave_deployment(
             started_at,
             completed_at,
             detailed_status: Some("".into()),
+            replica_rollover: None,
         }],
+        job_execution: None,
     }
 }
+
+/// A valid PCR of a repeated byte, such as `pcr("aa")`
+pub fn pcr(byte: &str) -> Pcr {
+    Pcr::parse(&byte.repeat(48)).unwrap()
+}
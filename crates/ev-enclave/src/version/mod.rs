@@ -1,5 +1,5 @@
 use common::api::client::ApiError;
-use common::api::enclave_assets::EnclaveAssetsClient;
+use common::api::enclave_assets::{EnclaveAssetsClient, ReleaseChannel, RuntimeMajorVersion};
 use common::CliError;
 use regex::Regex;
 use std::fs;
@@ -34,18 +34,51 @@ impl CliError for VersionError {
     }
 }
 
-pub async fn get_runtime_and_installer_version(
+/// The data plane and installer versions available to builds, fetched once so each Enclave in a
+/// workspace can pick the release for its channel.
+#[derive(Clone, Debug)]
+pub enum RuntimeVersions {
+    /// Versions read from an existing Dockerfile, which are used whatever the channel
+    Existing(String, String),
+    Releases(RuntimeMajorVersion),
+}
+
+impl RuntimeVersions {
+    /// The data plane and installer versions for an Enclave on the given release channel
+    pub fn resolve(&self, channel: ReleaseChannel) -> (String, String) {
+        match self {
+            Self::Existing(data_plane_version, installer_version) => {
+                (data_plane_version.clone(), installer_version.clone())
+            }
+            Self::Releases(releases) => {
+                if !channel.is_stable() && releases.candidate.is_none() {
+                    log::info!(
+                        "There's no {channel} release of the data plane, using the stable release."
+                    );
+                }
+                let release = releases.release(channel);
+                (release.latest, release.installer)
+            }
+        }
+    }
+}
+
+pub async fn get_runtime_versions(
     from_existing: Option<String>,
-) -> Result<(String, String), VersionError> {
+) -> Result<RuntimeVersions, VersionError> {
     match from_existing {
-        Some(existing) => parse_version_from_existing_dockerfile(existing),
+        Some(existing) => {
+            let (data_plane_version, installer_version) =
+                parse_version_from_existing_dockerfile(existing)?;
+            Ok(RuntimeVersions::Existing(
+                data_plane_version,
+                installer_version,
+            ))
+        }
         None => {
             let enclave_build_assets_client = EnclaveAssetsClient::new();
-            let data_plane_version = enclave_build_assets_client.get_data_plane_version().await?;
-            let installer_version = enclave_build_assets_client
-                .get_runtime_installer_version()
-                .await?;
-            Ok((data_plane_version, installer_version))
+            let releases = enclave_build_assets_client.get_runtime_versions().await?;
+            Ok(RuntimeVersions::Releases(releases))
         }
     }
 }
@@ -0,0 +1,28 @@
+use common::CliError;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum WorkspaceError {
+    #[error("No evervault.toml workspace was found in {0} or any parent directory. Workspaces list their member Enclaves under [workspace] members = [...].")]
+    NotFound(String),
+    #[error("Failed to read the workspace file at {0} - {1}")]
+    Io(String, std::io::Error),
+    #[error("Failed to parse the workspace file at {0} - {1}")]
+    Parse(String, toml::de::Error),
+    #[error("The workspace member {0} has no enclave.toml")]
+    MissingMember(String),
+    #[error("Failed to read the config of workspace member {0} - {1}")]
+    InvalidMember(String, String),
+    #[error("The workspace has no members")]
+    NoMembers,
+}
+
+impl CliError for WorkspaceError {
+    fn exitcode(&self) -> exitcode::ExitCode {
+        match self {
+            Self::NotFound(_) | Self::MissingMember(_) => exitcode::NOINPUT,
+            Self::Io(_, _) => exitcode::IOERR,
+            Self::Parse(_, _) | Self::InvalidMember(_, _) | Self::NoMembers => exitcode::CONFIG,
+        }
+    }
+}
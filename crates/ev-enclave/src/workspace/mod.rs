@@ -0,0 +1,288 @@
+pub mod error;
+
+use crate::config::EnclaveConfig;
+use common::table::Table;
+pub use error::WorkspaceError;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+pub const WORKSPACE_FILENAME: &str = "evervault.toml";
+const MEMBER_CONFIG_FILENAME: &str = "enclave.toml";
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+struct WorkspaceFile {
+    workspace: WorkspaceSettings,
+}
+
+/// The `[workspace]` section of an evervault.toml, which groups the Enclaves in a repo
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct WorkspaceSettings {
+    /// Paths to member Enclaves, relative to the workspace root. Each is a directory containing an
+    /// enclave.toml, the path of an Enclave config, or a directory ending in `/*` whose
+    /// subdirectories with an enclave.toml are all members.
+    pub members: Vec<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct WorkspaceMember {
+    /// The member's directory, relative to the workspace root
+    pub path: String,
+    pub config_path: PathBuf,
+    pub directory: PathBuf,
+    pub config: EnclaveConfig,
+}
+
+impl WorkspaceMember {
+    fn load(root: &Path, config_path: PathBuf) -> Result<Self, WorkspaceError> {
+        let config_path_str = config_path.display().to_string();
+        let config = EnclaveConfig::try_from_filepath(&config_path_str)
+            .map_err(|e| WorkspaceError::InvalidMember(config_path_str, e.to_string()))?;
+        let directory = config_path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| root.to_path_buf());
+        let path = directory
+            .strip_prefix(root)
+            .unwrap_or(&directory)
+            .display()
+            .to_string();
+        Ok(Self {
+            path: if path.is_empty() { ".".into() } else { path },
+            config_path,
+            directory,
+            config,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        self.config.name()
+    }
+
+    /// Resolves a path from the member's enclave.toml, which is relative to the member's directory
+    /// rather than wherever the CLI was run from
+    pub fn resolve_path(&self, path: &str) -> String {
+        let path = Path::new(path);
+        if path.is_absolute() {
+            path.display().to_string()
+        } else {
+            self.directory.join(path).display().to_string()
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Workspace {
+    root: PathBuf,
+    members: Vec<WorkspaceMember>,
+}
+
+impl Workspace {
+    /// Finds the nearest evervault.toml in `start` or its parent directories, as cargo does
+    pub fn discover(start: &Path) -> Result<Self, WorkspaceError> {
+        start
+            .ancestors()
+            .map(|directory| directory.join(WORKSPACE_FILENAME))
+            .find(|path| path.is_file())
+            .ok_or_else(|| WorkspaceError::NotFound(start.display().to_string()))
+            .and_then(|path| Self::try_from_filepath(&path))
+    }
+
+    pub fn try_from_filepath(path: &Path) -> Result<Self, WorkspaceError> {
+        let path_str = path.display().to_string();
+        let contents =
+            std::fs::read_to_string(path).map_err(|e| WorkspaceError::Io(path_str.clone(), e))?;
+        let workspace_file: WorkspaceFile =
+            toml::from_str(&contents).map_err(|e| WorkspaceError::Parse(path_str, e))?;
+        let root = path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| PathBuf::from("."));
+
+        let mut config_paths: Vec<PathBuf> = vec![];
+        for member in workspace_file.workspace.members.iter() {
+            for config_path in expand_member(&root, member)? {
+                if !config_paths.contains(&config_path) {
+                    config_paths.push(config_path);
+                }
+            }
+        }
+        if config_paths.is_empty() {
+            return Err(WorkspaceError::NoMembers);
+        }
+
+        let members = config_paths
+            .into_iter()
+            .map(|config_path| WorkspaceMember::load(&root, config_path))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { root, members })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn members(&self) -> &[WorkspaceMember] {
+        &self.members
+    }
+}
+
+fn expand_member(root: &Path, member: &str) -> Result<Vec<PathBuf>, WorkspaceError> {
+    if let Some(parent) = member.strip_suffix("/*") {
+        let parent = root.join(parent);
+        let entries = std::fs::read_dir(&parent)
+            .map_err(|e| WorkspaceError::Io(parent.display().to_string(), e))?;
+        let mut config_paths: Vec<PathBuf> = entries
+            .filter_map(Result::ok)
+            .map(|entry| entry.path().join(MEMBER_CONFIG_FILENAME))
+            .filter(|config_path| config_path.is_file())
+            .collect();
+        config_paths.sort();
+        return Ok(config_paths);
+    }
+
+    let path = root.join(member);
+    let config_path = if path.is_dir() {
+        path.join(MEMBER_CONFIG_FILENAME)
+    } else {
+        path
+    };
+    if config_path.is_file() {
+        Ok(vec![config_path])
+    } else {
+        Err(WorkspaceError::MissingMember(member.to_string()))
+    }
+}
+
+/// The outcome of running a command against one workspace member
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MemberReport {
+    pub member: String,
+    pub enclave_name: String,
+    pub exit_code: exitcode::ExitCode,
+    pub elapsed_seconds: u64,
+    /// e.g. the Enclave's domain once deployed, or its PCR0 once built
+    pub detail: Option<String>,
+}
+
+impl MemberReport {
+    pub fn succeeded(&self) -> bool {
+        self.exit_code == exitcode::OK
+    }
+}
+
+/// The exit code for a run across the workspace, which is that of the first failed member
+pub fn combined_exitcode(reports: &[MemberReport]) -> exitcode::ExitCode {
+    reports
+        .iter()
+        .find(|report| !report.succeeded())
+        .map(|report| report.exit_code)
+        .unwrap_or(exitcode::OK)
+}
+
+pub fn summary_table(reports: &[MemberReport]) -> Table {
+    let mut table = Table::new([
+        ("member", "MEMBER"),
+        ("name", "ENCLAVE"),
+        ("status", "STATUS"),
+        ("elapsed", "ELAPSED (s)"),
+        ("detail", "DETAIL"),
+    ]);
+    for report in reports {
+        table.push_row(vec![
+            report.member.clone(),
+            report.enclave_name.clone(),
+            if report.succeeded() {
+                "ok".to_string()
+            } else {
+                format!("failed ({})", report.exit_code)
+            },
+            report.elapsed_seconds.to_string(),
+            report.detail.clone().unwrap_or_else(|| "-".to_string()),
+        ]);
+    }
+    table
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write_member(root: &Path, directory: &str, name: &str) {
+        let directory = root.join(directory);
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(
+            directory.join(MEMBER_CONFIG_FILENAME),
+            format!("version = 1\nname = \"{name}\"\ndebug = false\n\n[egress]\nenabled = false\n"),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_discover_workspace_members() {
+        let root = TempDir::new().unwrap();
+        write_member(root.path(), "payments", "payments");
+        write_member(root.path(), "services/auth", "auth");
+        write_member(root.path(), "services/keys", "keys");
+        std::fs::create_dir_all(root.path().join("services/docs")).unwrap();
+        std::fs::write(
+            root.path().join(WORKSPACE_FILENAME),
+            "[workspace]\nmembers = [\"payments\", \"services/*\", \"services/auth/enclave.toml\"]\n",
+        )
+        .unwrap();
+
+        let workspace = Workspace::discover(&root.path().join("services/auth")).unwrap();
+        let names: Vec<&str> = workspace
+            .members()
+            .iter()
+            .map(|member| member.name())
+            .collect();
+        assert_eq!(names, vec!["payments", "auth", "keys"]);
+
+        let auth = &workspace.members()[1];
+        assert_eq!(auth.path, "services/auth");
+        assert_eq!(
+            auth.resolve_path("./Dockerfile"),
+            root.path()
+                .join("services/auth/./Dockerfile")
+                .display()
+                .to_string()
+        );
+        assert_eq!(auth.resolve_path("/etc/cert.pem"), "/etc/cert.pem");
+    }
+
+    #[test]
+    fn test_missing_workspace_member() {
+        let root = TempDir::new().unwrap();
+        std::fs::write(
+            root.path().join(WORKSPACE_FILENAME),
+            "[workspace]\nmembers = [\"missing\"]\n",
+        )
+        .unwrap();
+        assert!(matches!(
+            Workspace::try_from_filepath(&root.path().join(WORKSPACE_FILENAME)),
+            Err(WorkspaceError::MissingMember(_))
+        ));
+    }
+
+    #[test]
+    fn test_combined_exitcode() {
+        let report = |exit_code| MemberReport {
+            member: "payments".into(),
+            enclave_name: "payments".into(),
+            exit_code,
+            elapsed_seconds: 12,
+            detail: None,
+        };
+        assert_eq!(
+            combined_exitcode(&[report(exitcode::OK), report(exitcode::OK)]),
+            exitcode::OK
+        );
+        let reports = [report(exitcode::OK), report(exitcode::DATAERR)];
+        assert_eq!(combined_exitcode(&reports), exitcode::DATAERR);
+        assert!(summary_table(&reports)
+            .render(false)
+            .contains("failed (65)"));
+    }
+}
@@ -0,0 +1,201 @@
+use common::api::client::{ApiClient, ApiError, ApiErrorKind, ApiResult, HandleResponse};
+use common::api::rate_limit::RateLimitedRequest;
+use common::api::AuthMode;
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How long a cached response is served without revalidating it with the API
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Deserialize, Serialize)]
+struct CachedResponse {
+    stored_at: u64,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    body: String,
+}
+
+/// A short-lived on-disk cache for read-only GET endpoints. Fresh entries are served without a
+/// request, and stale entries are revalidated using their `ETag` and `Last-Modified` headers.
+#[derive(Clone, Debug)]
+pub struct ResponseCache {
+    directory: PathBuf,
+    ttl: Duration,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+// Responses are keyed on the credentials used, so switching apps never serves another app's data
+fn auth_identity(auth: &AuthMode) -> String {
+    match auth {
+        AuthMode::NoAuth => String::new(),
+        AuthMode::ApiKey(api_key) => format!("api-key:{api_key}"),
+        AuthMode::BearerAuth(token) => format!("bearer:{}", token.access_token()),
+        AuthMode::BasicAuth((app_uuid, api_key)) => format!("basic:{app_uuid}:{api_key}"),
+    }
+}
+
+fn parse_body<T: DeserializeOwned>(body: &str) -> ApiResult<T> {
+    serde_json::from_str(body).map_err(|e| ApiError::new(ApiErrorKind::ParsingError(e.to_string())))
+}
+
+impl ResponseCache {
+    pub fn new(directory: PathBuf) -> Self {
+        Self {
+            directory,
+            ttl: DEFAULT_CACHE_TTL,
+        }
+    }
+
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    fn entry_path(&self, auth: &AuthMode, url: &str) -> PathBuf {
+        let key = Sha256::digest(format!("{}\n{url}", auth_identity(auth)).as_bytes());
+        self.directory.join(format!("{}.json", hex::encode(key)))
+    }
+
+    fn read(&self, path: &PathBuf) -> Option<CachedResponse> {
+        let contents = std::fs::read_to_string(path).ok()?;
+        serde_json::from_str(&contents).ok()
+    }
+
+    fn write(&self, path: &PathBuf, entry: &CachedResponse) {
+        let result = std::fs::create_dir_all(&self.directory).and_then(|_| {
+            let contents = serde_json::to_vec(entry).expect("Failed to serialize cached response");
+            // Cached responses can contain secrets, so only the user can read them
+            let mut options = std::fs::OpenOptions::new();
+            options.write(true).create(true).truncate(true);
+            #[cfg(unix)]
+            std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+            let mut file = options.open(path)?;
+            // The mode only applies to new files, so also restrict entries written by older versions
+            #[cfg(unix)]
+            file.set_permissions(std::os::unix::fs::PermissionsExt::from_mode(0o600))?;
+            file.write_all(&contents)
+        });
+        if let Err(e) = result {
+            log::debug!("Failed to write to the API response cache - {e}");
+        }
+    }
+
+    fn is_fresh(&self, entry: &CachedResponse) -> bool {
+        now().saturating_sub(entry.stored_at) < self.ttl.as_secs()
+    }
+
+    pub async fn get_json<T: DeserializeOwned, C: ApiClient + Sync>(
+        &self,
+        client: &C,
+        url: &str,
+    ) -> ApiResult<T> {
+        let path = self.entry_path(client.auth(), url);
+        let cached = self.read(&path);
+
+        if let Some(entry) = cached.as_ref().filter(|entry| self.is_fresh(entry)) {
+            if let Ok(value) = parse_body(&entry.body) {
+                log::debug!("Serving {url} from the API response cache");
+                return Ok(value);
+            }
+        }
+
+        let mut request = client.get(url);
+        if let Some(entry) = cached.as_ref() {
+            if let Some(etag) = entry.etag.as_deref() {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = entry.last_modified.as_deref() {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+
+        match request.send_rate_limited().await {
+            Ok(res) if res.status() == StatusCode::NOT_MODIFIED && cached.is_some() => {
+                let mut entry = cached.unwrap();
+                log::debug!("{url} has not been modified, reusing the cached response");
+                entry.stored_at = now();
+                self.write(&path, &entry);
+                parse_body(&entry.body)
+            }
+            Ok(res) if res.status().is_success() => {
+                let header = |name| {
+                    res.headers()
+                        .get(name)
+                        .and_then(|value| value.to_str().ok())
+                        .map(String::from)
+                };
+                let (etag, last_modified) = (header(ETAG), header(LAST_MODIFIED));
+                let body = res
+                    .text()
+                    .await
+                    .map_err(|e| ApiError::new(ApiErrorKind::ParsingError(e.to_string())))?;
+                let value = parse_body(&body)?;
+                self.write(
+                    &path,
+                    &CachedResponse {
+                        stored_at: now(),
+                        etag,
+                        last_modified,
+                        body,
+                    },
+                );
+                Ok(value)
+            }
+            result => result.handle_json_response().await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_cache_entries() {
+        let directory = TempDir::new().unwrap();
+        let cache = ResponseCache::new(directory.path().to_path_buf());
+        let auth = AuthMode::ApiKey("key_1".into());
+        let url = "https://api.evervault.com/enclaves/";
+
+        let path = cache.entry_path(&auth, url);
+        assert_ne!(
+            path,
+            cache.entry_path(&AuthMode::ApiKey("key_2".into()), url)
+        );
+        assert!(cache.read(&path).is_none());
+
+        let entry = CachedResponse {
+            stored_at: now(),
+            etag: Some("\"abc\"".into()),
+            last_modified: None,
+            body: r#"{"data":[]}"#.into(),
+        };
+        cache.write(&path, &entry);
+        let cached = cache.read(&path).unwrap();
+        assert_eq!(cached.etag.as_deref(), Some("\"abc\""));
+        assert!(cache.is_fresh(&cached));
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let expired = cache.with_ttl(Duration::from_secs(0));
+        assert!(!expired.is_fresh(&cached));
+    }
+}
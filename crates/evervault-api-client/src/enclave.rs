@@ -0,0 +1,2310 @@
+use crate::cache::ResponseCache;
+
+use common::api::client::{ApiClient, ApiClientError, ApiResult, GenericApiClient, HandleResponse};
+use common::api::compat::Deprecated;
+use common::api::rate_limit::RateLimitedRequest;
+use common::api::AuthMode;
+use common::enclave::types::PCRs;
+use common::CliError;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[cfg(any(test, feature = "mock"))]
+use mockall::automock;
+
+#[derive(Clone)]
+pub struct EnclaveClient {
+    inner: GenericApiClient,
+    cache: Option<ResponseCache>,
+}
+
+impl ApiClient for EnclaveClient {
+    fn auth(&self) -> &AuthMode {
+        self.inner.auth()
+    }
+
+    fn update_auth(&mut self, auth: AuthMode) -> Result<(), ApiClientError> {
+        self.inner.update_auth(auth)
+    }
+
+    fn client(&self) -> &Client {
+        self.inner.client()
+    }
+
+    fn base_url(&self) -> String {
+        let api_base = self.inner.base_url();
+        format!("{}/enclaves", api_base)
+    }
+
+    fn accept(&self) -> String {
+        format!(
+            "application/json;version={}",
+            env!("ENCLAVE_RUNTIME_VERSION")
+        )
+    }
+}
+
+#[async_trait::async_trait]
+#[cfg_attr(any(test, feature = "mock"), automock)]
+pub trait EnclaveApi {
+    async fn create_enclave(
+        &self,
+        enclave_create_payload: CreateEnclaveRequest,
+    ) -> ApiResult<Enclave>;
+    async fn create_enclave_deployment_intent(
+        &self,
+        enclave_uuid: &str,
+        payload: CreateEnclaveDeploymentIntentRequest,
+    ) -> ApiResult<CreateEnclaveDeploymentIntentResponse>;
+    async fn complete_deployment_upload(
+        &self,
+        enclave_uuid: &str,
+        deployment_uuid: &str,
+        payload: CompleteDeploymentUploadRequest,
+    ) -> ApiResult<()>;
+    async fn create_enclave_signing_cert_ref(
+        &self,
+        payload: CreateEnclaveSigningCertRefRequest,
+    ) -> ApiResult<CreateEnclaveSigningCertRefResponse>;
+    async fn get_enclaves(&self) -> ApiResult<GetEnclavesResponse>;
+    async fn get_enclave(&self, enclave_uuid: &str) -> ApiResult<GetEnclaveResponse>;
+    async fn get_app_keys(&self, team_uuid: &str, app_uuid: &str) -> ApiResult<GetKeysResponse>;
+    async fn add_env_var(&self, enclave_uuid: String, payload: AddSecretRequest) -> ApiResult<()>;
+    async fn delete_env_var(&self, enclave_uuid: String, name: String) -> ApiResult<()>;
+    async fn get_enclave_env(&self, enclave_uuid: String) -> ApiResult<EnclaveEnv>;
+    async fn get_enclave_env_history(&self, enclave_uuid: &str) -> ApiResult<EnclaveEnvHistory>;
+    async fn sync_env_vars(
+        &self,
+        enclave_uuid: String,
+        payload: SyncSecretsRequest,
+    ) -> ApiResult<EnclaveEnv>;
+    async fn get_enclave_deployment_by_uuid(
+        &self,
+        enclave_uuid: &str,
+        deployment_uuid: &str,
+    ) -> ApiResult<GetEnclaveDeploymentResponse>;
+    async fn get_deployment_queue_position(
+        &self,
+        enclave_uuid: &str,
+        deployment_uuid: &str,
+    ) -> ApiResult<DeploymentQueuePosition>;
+    async fn cancel_deployment(
+        &self,
+        enclave_uuid: &str,
+        deployment_uuid: &str,
+    ) -> ApiResult<EnclaveDeployment>;
+    /// Starts a new deployment of the version an earlier deployment ran
+    async fn redeploy_deployment(
+        &self,
+        enclave_uuid: &str,
+        deployment_uuid: &str,
+    ) -> ApiResult<EnclaveDeployment>;
+    async fn get_signing_certs(&self) -> ApiResult<GetSigningCertsResponse>;
+    async fn update_enclave_locked_signing_certs(
+        &self,
+        enclave_uuid: &str,
+        payload: UpdateLockedEnclaveSigningCertRequest,
+    ) -> ApiResult<Vec<EnclaveToSigningCert>>;
+    async fn get_enclave_locked_signing_certs(
+        &self,
+        enclave_uuid: &str,
+    ) -> ApiResult<Vec<EnclaveSigningCert>>;
+    async fn get_enclave_cert_by_uuid(&self, cert_uuid: &str) -> ApiResult<EnclaveSigningCert>;
+    async fn get_enclave_logs(
+        &self,
+        enclave_uuid: &str,
+        start_time: u128,
+        end_time: u128,
+    ) -> ApiResult<EnclaveLogs>;
+    async fn delete_enclave(&self, enclave_uuid: &str) -> ApiResult<DeleteEnclaveResponse>;
+    async fn restart_enclave(&self, enclave_uuid: &str) -> ApiResult<EnclaveDeployment>;
+    async fn rename_enclave(
+        &self,
+        enclave_uuid: &str,
+        payload: RenameEnclaveRequest,
+    ) -> ApiResult<Enclave>;
+    async fn run_job(&self, enclave_uuid: &str) -> ApiResult<JobExecution>;
+    async fn get_job_execution(
+        &self,
+        enclave_uuid: &str,
+        job_uuid: &str,
+    ) -> ApiResult<JobExecution>;
+    async fn get_scaling_config(&self, enclave_uuid: &str) -> ApiResult<EnclaveScalingConfig>;
+    async fn get_enclave_sizes(&self) -> ApiResult<EnclaveSizes>;
+    async fn get_enclave_metrics(
+        &self,
+        enclave_uuid: &str,
+        window: &str,
+    ) -> ApiResult<EnclaveMetrics>;
+    async fn update_scaling_config(
+        &self,
+        enclave_uuid: &str,
+        update_scaling_config_request: UpdateEnclaveScalingConfigRequest,
+    ) -> ApiResult<EnclaveScalingConfig>;
+    async fn get_custom_domains(&self, enclave_uuid: &str) -> ApiResult<GetCustomDomainsResponse>;
+    async fn get_custom_domain(&self, enclave_uuid: &str, domain: &str) -> ApiResult<CustomDomain>;
+    async fn add_custom_domain(
+        &self,
+        enclave_uuid: &str,
+        payload: AddCustomDomainRequest,
+    ) -> ApiResult<CustomDomain>;
+    async fn remove_custom_domain(&self, enclave_uuid: &str, domain: &str) -> ApiResult<()>;
+}
+
+impl EnclaveClient {
+    pub fn new(auth_mode: AuthMode) -> Self {
+        Self {
+            inner: GenericApiClient::from(auth_mode),
+            cache: None,
+        }
+    }
+
+    /// Serve read-only requests for Enclaves from a short-lived on-disk cache. Only for commands
+    /// which display data; deploys must read the Enclave from a client without a cache.
+    pub fn with_response_cache(mut self, cache: ResponseCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl EnclaveApi for EnclaveClient {
+    async fn create_enclave(
+        &self,
+        enclave_create_payload: CreateEnclaveRequest,
+    ) -> ApiResult<Enclave> {
+        let create_enclave_url = format!("{}/", self.base_url());
+        self.post(&create_enclave_url)
+            .json(&enclave_create_payload)
+            .send_rate_limited()
+            .await
+            .handle_json_response()
+            .await
+    }
+
+    async fn create_enclave_deployment_intent(
+        &self,
+        enclave_uuid: &str,
+        payload: CreateEnclaveDeploymentIntentRequest,
+    ) -> ApiResult<CreateEnclaveDeploymentIntentResponse> {
+        let deployment_intent_url = format!("{}/{}/credentials", self.base_url(), enclave_uuid);
+        self.post(&deployment_intent_url)
+            .json(&payload)
+            .send_rate_limited()
+            .await
+            .handle_json_response()
+            .await
+    }
+
+    async fn complete_deployment_upload(
+        &self,
+        enclave_uuid: &str,
+        deployment_uuid: &str,
+        payload: CompleteDeploymentUploadRequest,
+    ) -> ApiResult<()> {
+        let complete_upload_url = format!(
+            "{}/{}/deployments/{}/upload",
+            self.base_url(),
+            enclave_uuid,
+            deployment_uuid
+        );
+        self.post(&complete_upload_url)
+            .json(&payload)
+            .send_rate_limited()
+            .await
+            .handle_no_op_response()
+    }
+
+    async fn create_enclave_signing_cert_ref(
+        &self,
+        payload: CreateEnclaveSigningCertRefRequest,
+    ) -> ApiResult<CreateEnclaveSigningCertRefResponse> {
+        let signing_cert_url = format!("{}/signing/certs", self.base_url());
+        self.post(&signing_cert_url)
+            .json(&payload)
+            .send_rate_limited()
+            .await
+            .handle_json_response()
+            .await
+    }
+
+    async fn get_enclaves(&self) -> ApiResult<GetEnclavesResponse> {
+        let get_enclaves_url = format!("{}/", self.base_url());
+        if let Some(cache) = self.cache.as_ref() {
+            return cache.get_json(self, &get_enclaves_url).await;
+        }
+        self.get(&get_enclaves_url)
+            .send_rate_limited()
+            .await
+            .handle_json_response()
+            .await
+    }
+
+    async fn get_enclave(&self, enclave_uuid: &str) -> ApiResult<GetEnclaveResponse> {
+        let get_enclave_url = format!("{}/{}", self.base_url(), enclave_uuid);
+        if let Some(cache) = self.cache.as_ref() {
+            return cache.get_json(self, &get_enclave_url).await;
+        }
+        self.get(&get_enclave_url)
+            .send_rate_limited()
+            .await
+            .handle_json_response()
+            .await
+    }
+
+    async fn get_app_keys(&self, team_uuid: &str, app_uuid: &str) -> ApiResult<GetKeysResponse> {
+        let get_enclave_url = format!("{}/{}/apps/{}", self.keys_url(), team_uuid, app_uuid);
+        self.get(&get_enclave_url)
+            .send_rate_limited()
+            .await
+            .handle_json_response()
+            .await
+    }
+
+    async fn add_env_var(&self, enclave_uuid: String, payload: AddSecretRequest) -> ApiResult<()> {
+        let add_env_url = format!("{}/{}/secrets", self.base_url(), enclave_uuid);
+        self.put(&add_env_url)
+            .json(&payload)
+            .send_rate_limited()
+            .await
+            .handle_no_op_response()
+    }
+
+    async fn delete_env_var(&self, enclave_uuid: String, name: String) -> ApiResult<()> {
+        let delete_env_url = format!("{}/{}/secrets/{}", self.base_url(), enclave_uuid, name);
+        self.delete(&delete_env_url)
+            .send_rate_limited()
+            .await
+            .handle_no_op_response()
+    }
+
+    async fn get_enclave_env(&self, enclave_uuid: String) -> ApiResult<EnclaveEnv> {
+        let get_env_url = format!("{}/{}/secrets", self.base_url(), enclave_uuid);
+        self.get(&get_env_url)
+            .send_rate_limited()
+            .await
+            .handle_json_response()
+            .await
+    }
+
+    async fn get_enclave_env_history(&self, enclave_uuid: &str) -> ApiResult<EnclaveEnvHistory> {
+        let get_history_url = format!("{}/{}/secrets/history", self.base_url(), enclave_uuid);
+        self.get(&get_history_url)
+            .send_rate_limited()
+            .await
+            .handle_json_response()
+            .await
+    }
+
+    async fn sync_env_vars(
+        &self,
+        enclave_uuid: String,
+        payload: SyncSecretsRequest,
+    ) -> ApiResult<EnclaveEnv> {
+        let sync_env_url = format!("{}/{}/secrets/sync", self.base_url(), enclave_uuid);
+        self.put(&sync_env_url)
+            .json(&payload)
+            .send_rate_limited()
+            .await
+            .handle_json_response()
+            .await
+    }
+
+    async fn get_enclave_deployment_by_uuid(
+        &self,
+        enclave_uuid: &str,
+        deployment_uuid: &str,
+    ) -> ApiResult<GetEnclaveDeploymentResponse> {
+        let get_enclave_url = format!(
+            "{}/{}/deployments/{}",
+            self.base_url(),
+            enclave_uuid,
+            deployment_uuid
+        );
+        self.get(&get_enclave_url)
+            .send_rate_limited()
+            .await
+            .handle_json_response()
+            .await
+    }
+
+    async fn get_deployment_queue_position(
+        &self,
+        enclave_uuid: &str,
+        deployment_uuid: &str,
+    ) -> ApiResult<DeploymentQueuePosition> {
+        let queue_url = format!(
+            "{}/{}/deployments/{}/queue",
+            self.base_url(),
+            enclave_uuid,
+            deployment_uuid
+        );
+        self.get(&queue_url)
+            .send_rate_limited()
+            .await
+            .handle_json_response()
+            .await
+    }
+
+    async fn cancel_deployment(
+        &self,
+        enclave_uuid: &str,
+        deployment_uuid: &str,
+    ) -> ApiResult<EnclaveDeployment> {
+        let cancel_url = format!(
+            "{}/{}/deployments/{}/cancel",
+            self.base_url(),
+            enclave_uuid,
+            deployment_uuid
+        );
+        self.post(&cancel_url)
+            .send_rate_limited()
+            .await
+            .handle_json_response()
+            .await
+    }
+
+    async fn redeploy_deployment(
+        &self,
+        enclave_uuid: &str,
+        deployment_uuid: &str,
+    ) -> ApiResult<EnclaveDeployment> {
+        let redeploy_url = format!(
+            "{}/{}/deployments/{}/redeploy",
+            self.base_url(),
+            enclave_uuid,
+            deployment_uuid
+        );
+        self.post(&redeploy_url)
+            .send_rate_limited()
+            .await
+            .handle_json_response()
+            .await
+    }
+
+    async fn get_signing_certs(&self) -> ApiResult<GetSigningCertsResponse> {
+        let get_certs_url = format!("{}/signing/certs", self.base_url(),);
+        self.get(&get_certs_url)
+            .send_rate_limited()
+            .await
+            .handle_json_response()
+            .await
+    }
+
+    async fn update_enclave_locked_signing_certs(
+        &self,
+        enclave_uuid: &str,
+        payload: UpdateLockedEnclaveSigningCertRequest,
+    ) -> ApiResult<Vec<EnclaveToSigningCert>> {
+        let get_enclave_lock_certs_url =
+            format!("{}/{}/signing/certs", self.base_url(), enclave_uuid);
+        self.put(&get_enclave_lock_certs_url)
+            .json(&payload)
+            .send_rate_limited()
+            .await
+            .handle_json_response()
+            .await
+    }
+
+    async fn get_enclave_locked_signing_certs(
+        &self,
+        enclave_uuid: &str,
+    ) -> ApiResult<Vec<EnclaveSigningCert>> {
+        let get_enclave_lock_certs_url =
+            format!("{}/{}/signing/certs", self.base_url(), enclave_uuid);
+        self.get(&get_enclave_lock_certs_url)
+            .send_rate_limited()
+            .await
+            .handle_json_response()
+            .await
+    }
+
+    async fn get_enclave_cert_by_uuid(&self, cert_uuid: &str) -> ApiResult<EnclaveSigningCert> {
+        let get_cert_url = format!("{}/signing/certs/{}", self.base_url(), cert_uuid);
+        self.get(&get_cert_url)
+            .send_rate_limited()
+            .await
+            .handle_json_response()
+            .await
+    }
+
+    async fn get_enclave_logs(
+        &self,
+        enclave_uuid: &str,
+        start_time: u128,
+        end_time: u128,
+    ) -> ApiResult<EnclaveLogs> {
+        let get_logs_url = format!(
+            "{}/{}/logs?startTime={start_time}&endTime={end_time}",
+            self.base_url(),
+            enclave_uuid
+        );
+
+        self.get(&get_logs_url)
+            .send_rate_limited()
+            .await
+            .handle_json_response()
+            .await
+    }
+
+    async fn delete_enclave(&self, enclave_uuid: &str) -> ApiResult<DeleteEnclaveResponse> {
+        let delete_enclave_url = format!("{}/{}", self.base_url(), enclave_uuid);
+        self.delete(&delete_enclave_url)
+            .send_rate_limited()
+            .await
+            .handle_json_response()
+            .await
+    }
+
+    async fn restart_enclave(&self, enclave_uuid: &str) -> ApiResult<EnclaveDeployment> {
+        let patch_enclave_url = format!("{}/{}", self.base_url(), enclave_uuid);
+        self.patch(&patch_enclave_url)
+            .send_rate_limited()
+            .await
+            .handle_json_response()
+            .await
+    }
+
+    async fn rename_enclave(
+        &self,
+        enclave_uuid: &str,
+        payload: RenameEnclaveRequest,
+    ) -> ApiResult<Enclave> {
+        let rename_enclave_url = format!("{}/{}/name", self.base_url(), enclave_uuid);
+        self.put(&rename_enclave_url)
+            .json(&payload)
+            .send_rate_limited()
+            .await
+            .handle_json_response()
+            .await
+    }
+
+    async fn run_job(&self, enclave_uuid: &str) -> ApiResult<JobExecution> {
+        let run_job_url = format!("{}/{}/jobs", self.base_url(), enclave_uuid);
+        self.post(&run_job_url)
+            .send_rate_limited()
+            .await
+            .handle_json_response()
+            .await
+    }
+
+    async fn get_job_execution(
+        &self,
+        enclave_uuid: &str,
+        job_uuid: &str,
+    ) -> ApiResult<JobExecution> {
+        let job_url = format!("{}/{}/jobs/{}", self.base_url(), enclave_uuid, job_uuid);
+        self.get(&job_url)
+            .send_rate_limited()
+            .await
+            .handle_json_response()
+            .await
+    }
+
+    async fn get_scaling_config(&self, enclave_uuid: &str) -> ApiResult<EnclaveScalingConfig> {
+        let enclave_scaling_url = format!("{}/{}/scale", self.base_url(), enclave_uuid);
+        self.get(&enclave_scaling_url)
+            .send_rate_limited()
+            .await
+            .handle_json_response()
+            .await
+    }
+
+    async fn get_enclave_sizes(&self) -> ApiResult<EnclaveSizes> {
+        let sizes_url = format!("{}/sizes", self.base_url());
+        self.get(&sizes_url)
+            .send_rate_limited()
+            .await
+            .handle_json_response()
+            .await
+    }
+
+    async fn get_enclave_metrics(
+        &self,
+        enclave_uuid: &str,
+        window: &str,
+    ) -> ApiResult<EnclaveMetrics> {
+        let enclave_metrics_url = format!(
+            "{}/{}/metrics?window={window}",
+            self.base_url(),
+            enclave_uuid
+        );
+        self.get(&enclave_metrics_url)
+            .send_rate_limited()
+            .await
+            .handle_json_response()
+            .await
+    }
+
+    async fn update_scaling_config(
+        &self,
+        enclave_uuid: &str,
+        update_scaling_config_request: UpdateEnclaveScalingConfigRequest,
+    ) -> ApiResult<EnclaveScalingConfig> {
+        let enclave_scaling_url = format!("{}/{}/scale", self.base_url(), enclave_uuid);
+        self.put(&enclave_scaling_url)
+            .json(&update_scaling_config_request)
+            .send_rate_limited()
+            .await
+            .handle_json_response()
+            .await
+    }
+    async fn get_custom_domains(&self, enclave_uuid: &str) -> ApiResult<GetCustomDomainsResponse> {
+        let domains_url = format!("{}/{}/domains", self.base_url(), enclave_uuid);
+        self.get(&domains_url)
+            .send_rate_limited()
+            .await
+            .handle_json_response()
+            .await
+    }
+
+    async fn get_custom_domain(&self, enclave_uuid: &str, domain: &str) -> ApiResult<CustomDomain> {
+        let domain_url = format!("{}/{}/domains/{}", self.base_url(), enclave_uuid, domain);
+        self.get(&domain_url)
+            .send_rate_limited()
+            .await
+            .handle_json_response()
+            .await
+    }
+
+    async fn add_custom_domain(
+        &self,
+        enclave_uuid: &str,
+        payload: AddCustomDomainRequest,
+    ) -> ApiResult<CustomDomain> {
+        let domains_url = format!("{}/{}/domains", self.base_url(), enclave_uuid);
+        self.post(&domains_url)
+            .json(&payload)
+            .send_rate_limited()
+            .await
+            .handle_json_response()
+            .await
+    }
+
+    async fn remove_custom_domain(&self, enclave_uuid: &str, domain: &str) -> ApiResult<()> {
+        let domain_url = format!("{}/{}/domains/{}", self.base_url(), enclave_uuid, domain);
+        self.delete(&domain_url)
+            .send_rate_limited()
+            .await
+            .handle_no_op_response()
+    }
+}
+
+/// The protocol spoken by the service in the Enclave. Raw TCP services must disable TLS
+/// termination, as the data plane can't inspect their traffic.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum NetworkProtocol {
+    #[default]
+    Http,
+    Tcp,
+}
+
+impl NetworkProtocol {
+    pub fn is_http(&self) -> bool {
+        matches!(self, Self::Http)
+    }
+}
+
+impl std::fmt::Display for NetworkProtocol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Http => write!(f, "http"),
+            Self::Tcp => write!(f, "tcp"),
+        }
+    }
+}
+
+/// Whether the Enclave serves requests until it's stopped, or runs the user's process once
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EnclaveType {
+    #[default]
+    Service,
+    /// The Enclave stops when the user's process exits, instead of restarting it
+    Job,
+}
+
+impl EnclaveType {
+    pub fn is_service(&self) -> bool {
+        matches!(self, Self::Service)
+    }
+
+    pub fn is_job(&self) -> bool {
+        matches!(self, Self::Job)
+    }
+}
+
+impl std::fmt::Display for EnclaveType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Service => write!(f, "service"),
+            Self::Job => write!(f, "job"),
+        }
+    }
+}
+
+/// What happens when the user's service exits. When unset in the enclave.toml, runit restarts the
+/// service whenever it exits, and the other supervisors stop the Enclave.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartPolicy {
+    #[default]
+    Always,
+    /// Restart the service if it exits with a non-zero code or is killed, and stop the Enclave if
+    /// it exits cleanly
+    OnFailure,
+    /// Stop the Enclave when the service exits
+    Never,
+}
+
+impl RestartPolicy {
+    pub fn is_always(&self) -> bool {
+        matches!(self, Self::Always)
+    }
+}
+
+impl std::fmt::Display for RestartPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Always => write!(f, "always"),
+            Self::OnFailure => write!(f, "on-failure"),
+            Self::Never => write!(f, "never"),
+        }
+    }
+}
+
+/// Named resource reservations for each of an Enclave's instances. The cpus and memory a size
+/// reserves are defined by the API, and listed with `ev enclave sizes list`.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum EnclaveSize {
+    Small,
+    Medium,
+    Large,
+}
+
+impl std::fmt::Display for EnclaveSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Small => write!(f, "small"),
+            Self::Medium => write!(f, "medium"),
+            Self::Large => write!(f, "large"),
+        }
+    }
+}
+
+/// The content digests of the data plane and installer a build added to the Enclave. Version tags
+/// can be republished, so the digests identify exactly what was built.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuntimeDigests {
+    /// The data plane is published per feature set, e.g. egress-enabled/tls-termination-enabled
+    pub data_plane_variant: String,
+    pub data_plane: String,
+    pub installer: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VersionMetadata {
+    installer_version: String,
+    git_hash: String,
+    data_plane_version: String,
+    git_timestamp: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data_plane_digest: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    installer_digest: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateEnclaveDeploymentIntentRequest {
+    #[serde(flatten)]
+    pcrs: PCRs,
+    debug_mode: bool,
+    trusted_headers: Vec<String>,
+    egress_enabled: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    egress_domains: Option<Vec<String>>,
+    eif_size_bytes: u64,
+    /// Digests of the whole EIF, so the API can verify the upload before building from it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    eif_sha256: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    eif_sha384: Option<String>,
+    not_before: String,
+    not_after: String,
+    metadata: VersionMetadata,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    healthcheck: Option<String>,
+    #[serde(skip_serializing_if = "NetworkProtocol::is_http")]
+    protocol: NetworkProtocol,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    desired_replicas: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pcrs_signature: Option<String>,
+    supported_upload_formats: Vec<UploadFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signing_rotation: Option<SigningRotation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    strategy: Option<DeployStrategy>,
+    #[serde(rename = "type", skip_serializing_if = "EnclaveType::is_service")]
+    enclave_type: EnclaveType,
+    /// Left out for the default policy, so older API versions accept the intent
+    #[serde(default, skip_serializing_if = "RestartPolicy::is_always")]
+    restart_policy: RestartPolicy,
+    /// Parts to upload the archive in concurrently, when the API supports multipart uploads
+    #[serde(skip_serializing_if = "Option::is_none")]
+    upload_parts: Option<u32>,
+    /// The resources of the config's size, resolved by the API before the build
+    #[serde(skip_serializing_if = "Option::is_none")]
+    resources: Option<DeploymentResources>,
+}
+
+/// The resources reserved for each instance of a deployment
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct DeploymentResources {
+    size: String,
+    cpus: u32,
+    memory_mib: u64,
+}
+
+impl From<&EnclaveSizeDefinition> for DeploymentResources {
+    fn from(definition: &EnclaveSizeDefinition) -> Self {
+        Self {
+            size: definition.size.clone(),
+            cpus: definition.cpus,
+            memory_mib: definition.memory_mib,
+        }
+    }
+}
+
+/// Metadata about an upcoming signing key rotation, allowing clients to pre-trust the PCR8 of
+/// the next signing cert before it is used
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SigningRotation {
+    pub next_pcr8: String,
+    pub not_before: String,
+    pub not_after: String,
+}
+
+/// Archive formats the CLI can upload an EIF in. The API selects one from the formats advertised
+/// in the deployment intent, older API versions which don't return a format expect a zip.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum UploadFormat {
+    #[default]
+    Zip,
+    Zstd,
+}
+
+/// How the API replaces the replicas of the current deployment. When unset, the API's default
+/// strategy is used, which stops the old replicas before starting the new ones.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[serde(rename_all = "kebab-case")]
+pub enum DeployStrategy {
+    /// Replace replicas one at a time, draining each old replica once a new one is healthy
+    Rolling,
+    /// Start a full set of new replicas alongside the old ones, then switch traffic over at once
+    BlueGreen,
+}
+
+impl std::fmt::Display for DeployStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Rolling => write!(f, "rolling"),
+            Self::BlueGreen => write!(f, "blue-green"),
+        }
+    }
+}
+
+impl UploadFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Zip => "application/zip",
+            Self::Zstd => "application/zstd",
+        }
+    }
+}
+
+/// Deployments larger than this many replicas are almost certainly a typo, such as an extra zero
+pub const MAX_DESIRED_REPLICAS: u32 = 100;
+
+#[derive(Debug, Error)]
+pub enum DeploymentIntentError {
+    #[error("The EIF is empty, so it can't be deployed. Rebuild the Enclave and try again.")]
+    EmptyEif,
+    #[error("The {0} version for the deployment is missing.")]
+    MissingVersion(&'static str),
+    #[error(
+        "Enclaves can run between 1 and {MAX_DESIRED_REPLICAS} replicas, but {0} were requested."
+    )]
+    InvalidReplicaCount(u32),
+}
+
+impl CliError for DeploymentIntentError {
+    fn exitcode(&self) -> exitcode::ExitCode {
+        match self {
+            Self::EmptyEif | Self::MissingVersion(_) => exitcode::DATAERR,
+            Self::InvalidReplicaCount(_) => exitcode::CONFIG,
+        }
+    }
+}
+
+/// The settings of the Enclave being deployed, which are sent with each deployment intent
+#[derive(Clone, Debug, Default)]
+pub struct DeploymentSettings {
+    pub debug_mode: bool,
+    pub egress_enabled: bool,
+    pub egress_domains: Option<Vec<String>>,
+    pub trusted_headers: Vec<String>,
+    /// The validity period of the signing cert
+    pub not_before: String,
+    pub not_after: String,
+    pub signing_rotation: Option<SigningRotation>,
+    pub healthcheck: Option<String>,
+    pub protocol: NetworkProtocol,
+    pub desired_replicas: Option<u32>,
+    pub enclave_type: EnclaveType,
+    pub restart_policy: RestartPolicy,
+}
+
+impl CreateEnclaveDeploymentIntentRequest {
+    /// Starts a deployment intent for an EIF built with the given settings and PCRs
+    pub fn builder(
+        settings: impl Into<DeploymentSettings>,
+        pcrs: &PCRs,
+    ) -> DeploymentIntentBuilder<'_> {
+        let settings = settings.into();
+        DeploymentIntentBuilder {
+            desired_replicas: settings.desired_replicas,
+            settings,
+            pcrs,
+            eif_size_bytes: 0,
+            eif_digests: None,
+            data_plane_version: String::new(),
+            installer_version: String::new(),
+            git_hash: String::new(),
+            git_timestamp: String::new(),
+            pcrs_signature: None,
+            runtime_digests: None,
+            strategy: None,
+            upload_parts: None,
+            resources: None,
+        }
+    }
+}
+
+/// Assembles a deployment intent, checking it's one the API can act on before it's sent
+#[derive(Clone, Debug)]
+pub struct DeploymentIntentBuilder<'a> {
+    settings: DeploymentSettings,
+    pcrs: &'a PCRs,
+    eif_size_bytes: u64,
+    eif_digests: Option<(String, String)>,
+    data_plane_version: String,
+    installer_version: String,
+    git_hash: String,
+    git_timestamp: String,
+    desired_replicas: Option<u32>,
+    pcrs_signature: Option<String>,
+    runtime_digests: Option<RuntimeDigests>,
+    strategy: Option<DeployStrategy>,
+    upload_parts: Option<u32>,
+    resources: Option<DeploymentResources>,
+}
+
+impl DeploymentIntentBuilder<'_> {
+    pub fn eif_size_bytes(mut self, eif_size_bytes: u64) -> Self {
+        self.eif_size_bytes = eif_size_bytes;
+        self
+    }
+
+    /// Includes the SHA-256 and SHA-384 digests of the EIF being uploaded
+    pub fn eif_digests(mut self, sha256: &str, sha384: &str) -> Self {
+        self.eif_digests = Some((sha256.to_string(), sha384.to_string()));
+        self
+    }
+
+    pub fn runtime_versions(
+        mut self,
+        data_plane_version: impl Into<String>,
+        installer_version: impl Into<String>,
+    ) -> Self {
+        self.data_plane_version = data_plane_version.into();
+        self.installer_version = installer_version.into();
+        self
+    }
+
+    /// Includes the digests of the data plane and installer the EIF was built with
+    pub fn runtime_digests(mut self, digests: Option<&RuntimeDigests>) -> Self {
+        self.runtime_digests = digests.cloned();
+        self
+    }
+
+    pub fn git_metadata(mut self, git_hash: String, git_timestamp: String) -> Self {
+        self.git_hash = git_hash;
+        self.git_timestamp = git_timestamp;
+        self
+    }
+
+    /// Overrides the replicas from the deployment settings
+    pub fn desired_replicas(mut self, desired_replicas: Option<u32>) -> Self {
+        self.desired_replicas = desired_replicas;
+        self
+    }
+
+    pub fn pcrs_signature(mut self, pcrs_signature: Option<String>) -> Self {
+        self.pcrs_signature = pcrs_signature;
+        self
+    }
+
+    pub fn strategy(mut self, strategy: Option<DeployStrategy>) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Requests signed urls to upload the archive in this many parts, rather than a single PUT
+    pub fn upload_parts(mut self, upload_parts: Option<u32>) -> Self {
+        self.upload_parts = upload_parts;
+        self
+    }
+
+    /// Reserves the cpus and memory of a size preset for each instance
+    pub fn resources(mut self, resources: Option<&EnclaveSizeDefinition>) -> Self {
+        self.resources = resources.map(DeploymentResources::from);
+        self
+    }
+
+    pub fn build(self) -> Result<CreateEnclaveDeploymentIntentRequest, DeploymentIntentError> {
+        if self.eif_size_bytes == 0 {
+            return Err(DeploymentIntentError::EmptyEif);
+        }
+        if self.data_plane_version.trim().is_empty() {
+            return Err(DeploymentIntentError::MissingVersion("data plane"));
+        }
+        if self.installer_version.trim().is_empty() {
+            return Err(DeploymentIntentError::MissingVersion("installer"));
+        }
+        if let Some(replicas) = self
+            .desired_replicas
+            .filter(|replicas| !(1..=MAX_DESIRED_REPLICAS).contains(replicas))
+        {
+            return Err(DeploymentIntentError::InvalidReplicaCount(replicas));
+        }
+
+        let settings = self.settings;
+        Ok(CreateEnclaveDeploymentIntentRequest {
+            pcrs: self.pcrs.clone(),
+            debug_mode: settings.debug_mode,
+            egress_enabled: settings.egress_enabled,
+            egress_domains: settings.egress_domains,
+            trusted_headers: settings.trusted_headers,
+            eif_size_bytes: self.eif_size_bytes,
+            eif_sha256: self.eif_digests.as_ref().map(|(sha256, _)| sha256.clone()),
+            eif_sha384: self.eif_digests.map(|(_, sha384)| sha384),
+            not_before: settings.not_before,
+            not_after: settings.not_after,
+            metadata: VersionMetadata {
+                git_hash: self.git_hash,
+                installer_version: self.installer_version,
+                data_plane_version: self.data_plane_version,
+                git_timestamp: self.git_timestamp,
+                data_plane_digest: self
+                    .runtime_digests
+                    .as_ref()
+                    .map(|digests| digests.data_plane.clone()),
+                installer_digest: self
+                    .runtime_digests
+                    .as_ref()
+                    .map(|digests| digests.installer.clone()),
+            },
+            healthcheck: settings.healthcheck,
+            protocol: settings.protocol,
+            desired_replicas: self.desired_replicas,
+            pcrs_signature: self.pcrs_signature,
+            supported_upload_formats: vec![UploadFormat::Zstd, UploadFormat::Zip],
+            signing_rotation: settings.signing_rotation,
+            strategy: self.strategy,
+            enclave_type: settings.enclave_type,
+            restart_policy: settings.restart_policy,
+            upload_parts: self.upload_parts,
+            resources: self.resources,
+        })
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateEnclaveSigningCertRefRequest {
+    cert_hash: String,
+    name: String,
+    not_before: String,
+    not_after: String,
+    /// PEM encoded public certificate, stored so the platform can audit which certs signed a deployment
+    #[serde(skip_serializing_if = "Option::is_none")]
+    certificate: Option<String>,
+}
+
+impl CreateEnclaveSigningCertRefRequest {
+    pub fn new(cert_hash: String, name: String, not_before: String, not_after: String) -> Self {
+        Self {
+            cert_hash,
+            name,
+            not_before,
+            not_after,
+            certificate: None,
+        }
+    }
+
+    pub fn with_certificate(mut self, certificate: String) -> Self {
+        self.certificate = Some(certificate);
+        self
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateLockedEnclaveSigningCertRequest {
+    cert_uuids: Vec<String>,
+}
+
+impl UpdateLockedEnclaveSigningCertRequest {
+    pub fn new(cert_uuids: Vec<String>) -> Self {
+        Self { cert_uuids }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateEnclaveRequest {
+    name: String,
+    is_time_bound: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenameEnclaveRequest {
+    pub name: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddSecretRequest {
+    pub name: String,
+    pub secret: String,
+}
+
+/// Replaces the full set of an Enclave's environment variables
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncSecretsRequest {
+    pub secrets: Vec<AddSecretRequest>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnclaveSecrets {
+    pub name: String,
+    pub secret: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct EnclaveEnv {
+    pub secrets: Vec<Secret>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Secret {
+    pub name: String,
+    pub secret: String,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum EnvChangeAction {
+    Added,
+    Updated,
+    Deleted,
+}
+
+impl std::fmt::Display for EnvChangeAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Added => write!(f, "added"),
+            Self::Updated => write!(f, "updated"),
+            Self::Deleted => write!(f, "deleted"),
+        }
+    }
+}
+
+/// A change to one of an Enclave's environment variables. Values aren't included, so history can
+/// be shared without exposing them.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvChange {
+    pub name: String,
+    pub action: EnvChangeAction,
+    #[serde(default)]
+    pub is_secret: bool,
+    pub changed_by: Option<String>,
+    pub changed_at: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct EnclaveEnvHistory {
+    pub changes: Vec<EnvChange>,
+}
+
+impl CreateEnclaveRequest {
+    pub fn new(enclave_name: String, is_time_bound: bool) -> Self {
+        Self {
+            name: enclave_name,
+            is_time_bound,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateEnclaveDeploymentIntentResponse {
+    signed_url: String,
+    enclave_uuid: String,
+    deployment_uuid: String,
+    version: u32,
+    #[serde(default)]
+    upload_format: UploadFormat,
+    /// Only returned when parts were requested, by API versions which support multipart uploads
+    #[serde(default)]
+    multipart_upload: Option<MultipartUpload>,
+}
+
+/// A multipart upload of the deployment's archive, with a signed url for each part in order
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MultipartUpload {
+    pub upload_id: String,
+    pub part_urls: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadedPart {
+    pub part_number: u32,
+    pub etag: String,
+}
+
+/// Completes a multipart upload, once every part has been uploaded
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompleteDeploymentUploadRequest {
+    pub upload_id: String,
+    pub parts: Vec<UploadedPart>,
+}
+
+impl CreateEnclaveDeploymentIntentResponse {
+    pub fn signed_url(&self) -> &str {
+        &self.signed_url
+    }
+
+    pub fn enclave_uuid(&self) -> &str {
+        &self.enclave_uuid
+    }
+
+    pub fn deployment_uuid(&self) -> &str {
+        &self.deployment_uuid
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn upload_format(&self) -> UploadFormat {
+        self.upload_format
+    }
+
+    pub fn multipart_upload(&self) -> Option<&MultipartUpload> {
+        self.multipart_upload.as_ref()
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateEnclaveSigningCertRefResponse {
+    cert_hash: String,
+    not_before: String,
+    not_after: String,
+    name: String,
+    uuid: String,
+}
+
+impl CreateEnclaveSigningCertRefResponse {
+    pub fn cert_hash(&self) -> &str {
+        &self.cert_hash
+    }
+
+    pub fn not_before(&self) -> &str {
+        &self.not_before
+    }
+
+    pub fn not_after(&self) -> &str {
+        &self.not_after
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn uuid(&self) -> &str {
+        &self.uuid
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnclaveToSigningCert {
+    pub enclave_uuid: String,
+    pub signing_cert_uuid: String,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum EnclaveState {
+    Pending,
+    Active,
+    Deleting,
+    Deleted,
+}
+
+impl std::fmt::Display for EnclaveState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Pending => write!(f, "pending"),
+            Self::Active => write!(f, "active"),
+            Self::Deleting => write!(f, "deleting"),
+            Self::Deleted => write!(f, "deleted"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Enclave {
+    pub uuid: String,
+    pub name: String,
+    pub team_uuid: String,
+    pub app_uuid: String,
+    pub domain: String,
+    pub state: EnclaveState,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl Enclave {
+    pub fn uuid(&self) -> &str {
+        &self.uuid
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn app_uuid(&self) -> &str {
+        &self.app_uuid
+    }
+
+    pub fn team_uuid(&self) -> &str {
+        &self.team_uuid
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnclaveDeployment {
+    pub uuid: String,
+    pub enclave_uuid: String,
+    pub version_uuid: String,
+    pub signing_cert_uuid: String,
+    pub debug_mode: bool,
+    pub started_at: Option<String>,
+    pub completed_at: Option<String>,
+}
+
+impl EnclaveDeployment {
+    pub fn is_finished(&self) -> bool {
+        self.completed_at.is_some()
+    }
+
+    pub fn enclave_uuid(&self) -> &str {
+        &self.enclave_uuid
+    }
+
+    pub fn uuid(&self) -> &str {
+        &self.uuid
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BuildStatus {
+    Pending,
+    Building,
+    Ready,
+    Failed,
+    /// The deployment was cancelled before its build finished
+    Cancelled,
+}
+
+impl std::fmt::Display for BuildStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Pending => write!(f, "pending"),
+            Self::Building => write!(f, "building"),
+            Self::Ready => write!(f, "ready"),
+            Self::Failed => write!(f, "failed"),
+            Self::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnclaveVersion {
+    pub uuid: String,
+    pub version: u16,
+    #[serde(default)]
+    pub control_plane_img_url: Deprecated<String>,
+    pub control_plane_version: Option<String>,
+    pub data_plane_version: Option<String>,
+    pub build_status: BuildStatus,
+    pub failure_reason: Option<String>,
+    pub started_at: Option<String>,
+    pub healthcheck: Option<String>,
+    #[serde(default)]
+    pub build_steps: Vec<BuildStep>,
+    /// Measurements of the EIF built on Evervault, once the build has finished
+    #[serde(default)]
+    pub pcrs: Option<PCRs>,
+    /// Signature over the PCRs, when the deployment was made with signed PCRs
+    #[serde(default)]
+    pub pcrs_signature: Option<String>,
+    #[serde(default)]
+    pub installer_version: Option<String>,
+    /// The commit the EIF was built from, as sent by the CLI when the version was deployed
+    #[serde(default)]
+    pub git_hash: Option<String>,
+    /// The SOURCE_DATE_EPOCH the EIF was built with
+    #[serde(default)]
+    pub git_timestamp: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BuildStepStatus {
+    Pending,
+    Running,
+    Complete,
+    Failed,
+}
+
+impl std::fmt::Display for BuildStepStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let status = match self {
+            Self::Pending => "pending",
+            Self::Running => "running",
+            Self::Complete => "complete",
+            Self::Failed => "failed",
+        };
+        write!(f, "{status}")
+    }
+}
+
+/// A stage of the remote build (e.g. queue, fetch, build, convert, sign), as reported by the API
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildStep {
+    pub name: String,
+    pub status: BuildStepStatus,
+    pub started_at: Option<String>,
+    pub completed_at: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, PartialOrd)]
+#[serde(rename_all = "camelCase")]
+pub struct EnclaveSigningCert {
+    pub name: Option<String>,
+    pub uuid: String,
+    pub app_uuid: String,
+    pub cert_hash: String,
+    pub not_before: Option<String>,
+    pub not_after: Option<String>,
+}
+
+impl EnclaveSigningCert {
+    pub fn new(
+        name: Option<String>,
+        uuid: String,
+        app_uuid: String,
+        cert_hash: String,
+        not_before: Option<String>,
+        not_after: Option<String>,
+    ) -> Self {
+        Self {
+            name,
+            uuid,
+            app_uuid,
+            cert_hash,
+            not_before,
+            not_after,
+        }
+    }
+
+    pub fn uuid(&self) -> &str {
+        &self.uuid
+    }
+
+    pub fn app_uuid(&self) -> &str {
+        &self.app_uuid
+    }
+
+    pub fn cert_hash(&self) -> &str {
+        &self.cert_hash
+    }
+
+    pub fn not_before(&self) -> Option<String> {
+        self.not_before.clone()
+    }
+
+    pub fn not_after(&self) -> Option<String> {
+        self.not_after.clone()
+    }
+
+    pub fn name(&self) -> Option<String> {
+        self.name.clone()
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DeployStatus {
+    Pending,
+    Deploying,
+    Ready,
+    Failed,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnclaveRegionalDeployment {
+    pub uuid: String,
+    pub deployment_uuid: String,
+    pub deployment_order: u16,
+    pub region: String,
+    pub failure_reason: Option<String>,
+    pub deploy_status: DeployStatus,
+    // started_at should be required, but is being returned as null sometimes
+    // should revert this to just String after API fix
+    pub started_at: Option<String>,
+    pub completed_at: Option<String>,
+    pub detailed_status: Option<String>,
+    /// Progress replacing the old replicas, reported for rolling and blue-green deployments
+    #[serde(default)]
+    pub replica_rollover: Option<ReplicaRollover>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplicaRollover {
+    pub desired_replicas: u32,
+    pub new_replicas_ready: u32,
+    pub old_replicas_remaining: u32,
+}
+
+impl ReplicaRollover {
+    pub fn describe(&self) -> String {
+        format!(
+            "Rolling over replicas - {} of {} new replicas ready, {} old {} still serving traffic",
+            self.new_replicas_ready,
+            self.desired_replicas,
+            self.old_replicas_remaining,
+            if self.old_replicas_remaining == 1 {
+                "replica"
+            } else {
+                "replicas"
+            }
+        )
+    }
+}
+
+impl EnclaveRegionalDeployment {
+    pub fn is_failed(&self) -> bool {
+        self.deploy_status == DeployStatus::Failed
+    }
+
+    pub fn get_failure_reason(&self) -> String {
+        self.failure_reason
+            .clone()
+            .unwrap_or_else(|| String::from("An unknown error occurred during deployment."))
+    }
+
+    pub fn get_detailed_status(&self) -> String {
+        self.detailed_status
+            .clone()
+            .unwrap_or_else(|| String::from("Starting deployment."))
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetEnclavesResponse {
+    enclaves: Vec<Enclave>,
+}
+
+impl GetEnclavesResponse {
+    pub fn enclaves(&self) -> &Vec<Enclave> {
+        self.enclaves.as_ref()
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeploymentsForGetEnclave {
+    #[serde(flatten)]
+    pub deployment: EnclaveDeployment,
+    #[serde(rename = "enclaveVersion")]
+    pub version: EnclaveVersion,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetEnclaveResponse {
+    #[serde(flatten)]
+    pub enclaves: Enclave,
+    #[serde(rename = "enclaveDeployments")]
+    pub deployments: Vec<DeploymentsForGetEnclave>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetKeysResponse {
+    pub ecdh_p256_key_uncompressed: String,
+    pub ecdh_p256_key: String,
+    pub ecdh_key: String,
+}
+
+impl GetEnclaveResponse {
+    pub fn is_deleted(&self) -> bool {
+        self.enclaves.state == EnclaveState::Deleted
+    }
+
+    pub fn domain(&self) -> &str {
+        self.enclaves.domain.as_str()
+    }
+
+    /// The most recently started deployment which has finished, and so is serving traffic
+    pub fn latest_finished_deployment(&self) -> Option<&DeploymentsForGetEnclave> {
+        self.deployments
+            .iter()
+            .filter(|deployment| deployment.deployment.is_finished())
+            .max_by(|a, b| a.deployment.started_at.cmp(&b.deployment.started_at))
+    }
+
+    /// The most recent deployment other than `deployment_uuid` which finished with a successful
+    /// build, which is what a rollback from `deployment_uuid` returns to
+    pub fn previous_successful_deployment(
+        &self,
+        deployment_uuid: &str,
+    ) -> Option<&DeploymentsForGetEnclave> {
+        self.deployments
+            .iter()
+            .filter(|deployment| {
+                deployment.deployment.uuid != deployment_uuid
+                    && deployment.deployment.is_finished()
+                    && matches!(deployment.version.build_status, BuildStatus::Ready)
+            })
+            .max_by(|a, b| a.deployment.started_at.cmp(&b.deployment.started_at))
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetEnclaveDeploymentResponse {
+    #[serde(flatten)]
+    pub deployment: EnclaveDeployment,
+    pub enclave_version: EnclaveVersion,
+    pub enclave_signing_cert: EnclaveSigningCert,
+    pub enclave_regional_deployments: Vec<EnclaveRegionalDeployment>,
+    /// The execution started by deploying a job Enclave
+    #[serde(default)]
+    pub job_execution: Option<JobExecution>,
+}
+
+impl GetEnclaveDeploymentResponse {
+    pub fn is_built(&self) -> bool {
+        matches!(self.enclave_version.build_status, BuildStatus::Ready)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.deployment.is_finished()
+    }
+
+    pub fn is_queued(&self) -> bool {
+        matches!(self.enclave_version.build_status, BuildStatus::Pending)
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self.enclave_version.build_status, BuildStatus::Cancelled)
+    }
+
+    //TODO: Handle multi region deployment failures
+    pub fn is_failed(&self) -> bool {
+        let build_failed = matches!(self.enclave_version.build_status, BuildStatus::Failed);
+        build_failed
+            || self
+                .enclave_regional_deployments
+                .first()
+                .map(|depl| depl.is_failed())
+                .unwrap_or_default()
+    }
+
+    pub fn get_failure_reason(&self) -> Option<String> {
+        self.enclave_version.failure_reason.clone().or_else(|| {
+            self.enclave_regional_deployments
+                .first()
+                .map(|depl| depl.get_failure_reason())
+        })
+    }
+
+    pub fn get_detailed_status(&self) -> Option<String> {
+        self.enclave_regional_deployments
+            .first()
+            .map(|depl| depl.get_detailed_status())
+    }
+
+    pub fn get_replica_rollover(&self) -> Option<&ReplicaRollover> {
+        self.enclave_regional_deployments
+            .first()
+            .and_then(|depl| depl.replica_rollover.as_ref())
+    }
+}
+
+/// Where a pending deployment is in the queue for remote builds
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct DeploymentQueuePosition {
+    /// 1-indexed position in the queue, or none once the deployment has left it
+    pub position: Option<u32>,
+    pub queue_length: u32,
+    /// An earlier deployment of the same Enclave which has to finish first, as an Enclave's
+    /// deployments are built in order
+    #[serde(default)]
+    pub blocking_deployment_uuid: Option<String>,
+}
+
+impl DeploymentQueuePosition {
+    pub fn describe(&self) -> Option<String> {
+        let position = self.position?;
+        let queued = format!(
+            "Waiting for a remote build - position {position} of {} in the queue",
+            self.queue_length
+        );
+        Some(match self.blocking_deployment_uuid.as_deref() {
+            Some(blocking_uuid) => format!(
+                "{queued}, behind deployment {blocking_uuid} of this Enclave. Cancel it with ev enclave deployments cancel {blocking_uuid} if it's been superseded..."
+            ),
+            None => format!("{queued}..."),
+        })
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddCustomDomainRequest {
+    domain: String,
+}
+
+impl AddCustomDomainRequest {
+    pub fn new(domain: String) -> Self {
+        Self { domain }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CustomDomainStatus {
+    /// Waiting for the DNS records to be created
+    Pending,
+    /// The DNS records were found, and a certificate is being issued for the domain
+    Provisioning,
+    Active,
+    Failed,
+}
+
+impl std::fmt::Display for CustomDomainStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let status = match self {
+            Self::Pending => "pending",
+            Self::Provisioning => "provisioning",
+            Self::Active => "active",
+            Self::Failed => "failed",
+        };
+        write!(f, "{status}")
+    }
+}
+
+/// A DNS record which must be created for a custom domain to be verified and served
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct DnsRecord {
+    #[serde(rename = "type")]
+    pub record_type: String,
+    pub name: String,
+    pub value: String,
+}
+
+/// A domain the Enclave is served on, alongside its Evervault domain
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomDomain {
+    pub domain: String,
+    pub status: CustomDomainStatus,
+    #[serde(default)]
+    pub dns_records: Vec<DnsRecord>,
+    pub failure_reason: Option<String>,
+    pub created_at: Option<String>,
+}
+
+impl CustomDomain {
+    pub fn is_active(&self) -> bool {
+        self.status == CustomDomainStatus::Active
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetCustomDomainsResponse {
+    pub domains: Vec<CustomDomain>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Pending,
+    Running,
+    Succeeded,
+    Failed,
+}
+
+/// A single run of a job Enclave, from boot until its process exits
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobExecution {
+    pub uuid: String,
+    pub enclave_uuid: String,
+    pub status: JobStatus,
+    pub exit_code: Option<i32>,
+    pub failure_reason: Option<String>,
+    pub started_at: Option<String>,
+    pub completed_at: Option<String>,
+}
+
+impl JobExecution {
+    pub fn is_finished(&self) -> bool {
+        matches!(self.status, JobStatus::Succeeded | JobStatus::Failed)
+    }
+
+    pub fn is_failed(&self) -> bool {
+        self.status == JobStatus::Failed
+    }
+
+    pub fn get_failure_reason(&self) -> String {
+        match (&self.failure_reason, self.exit_code) {
+            (Some(reason), _) => reason.clone(),
+            (None, Some(exit_code)) => format!("The job exited with code {exit_code}"),
+            (None, None) => String::from("An unknown error occurred while running the job."),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetSigningCertsResponse {
+    pub certs: Vec<EnclaveSigningCert>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnclaveLogs {
+    log_events: Vec<LogEvent>,
+    next_token: Option<String>,
+    start_time: String,
+    end_time: String,
+}
+
+impl EnclaveLogs {
+    pub fn start_time(&self) -> &str {
+        &self.start_time
+    }
+
+    pub fn end_time(&self) -> &str {
+        &self.end_time
+    }
+
+    pub fn log_events(&self) -> &Vec<LogEvent> {
+        &self.log_events
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnclaveMetrics {
+    window: String,
+    replicas: Vec<ReplicaMetrics>,
+}
+
+impl EnclaveMetrics {
+    pub fn window(&self) -> &str {
+        &self.window
+    }
+
+    pub fn replicas(&self) -> &[ReplicaMetrics] {
+        &self.replicas
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplicaMetrics {
+    instance_id: String,
+    /// CPU utilization as a percentage of the Enclave's allocated vCPUs
+    cpu_utilization: Vec<MetricDatapoint>,
+    /// Memory utilization as a percentage of the Enclave's allocated memory
+    memory_utilization: Vec<MetricDatapoint>,
+    request_count: u64,
+    restart_count: u32,
+}
+
+impl ReplicaMetrics {
+    pub fn instance_id(&self) -> &str {
+        &self.instance_id
+    }
+
+    pub fn cpu_utilization(&self) -> &[MetricDatapoint] {
+        &self.cpu_utilization
+    }
+
+    pub fn memory_utilization(&self) -> &[MetricDatapoint] {
+        &self.memory_utilization
+    }
+
+    pub fn request_count(&self) -> u64 {
+        self.request_count
+    }
+
+    pub fn restart_count(&self) -> u32 {
+        self.restart_count
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetricDatapoint {
+    timestamp: i64,
+    value: f64,
+}
+
+impl MetricDatapoint {
+    pub fn timestamp(&self) -> i64 {
+        self.timestamp
+    }
+
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogEvent {
+    timestamp: i64,
+    message: String,
+    ingestion_time: i64,
+    instance_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    source: Option<String>,
+}
+
+/// The process within the Enclave which wrote a log
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LogSource {
+    DataPlane,
+    App,
+}
+
+impl std::fmt::Display for LogSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::DataPlane => write!(f, "data-plane"),
+            Self::App => write!(f, "app"),
+        }
+    }
+}
+
+impl LogEvent {
+    /// The stream the API read the log from. Logs recorded before sources were tagged, or from
+    /// streams this client doesn't know, are attributed to the app.
+    pub fn source(&self) -> LogSource {
+        match self.source.as_deref() {
+            Some("data-plane" | "dataplane" | "data_plane") => LogSource::DataPlane,
+            _ => LogSource::App,
+        }
+    }
+
+    pub fn timestamp(&self) -> i64 {
+        self.timestamp
+    }
+
+    pub fn message(&self) -> &str {
+        self.message.as_str()
+    }
+
+    pub fn instance_id(&self) -> &str {
+        self.instance_id.as_str()
+    }
+}
+
+pub type DeleteEnclaveResponse = Enclave;
+
+/// The resources a size preset reserves for each of an Enclave's instances. Sizes are kept as
+/// strings, so sizes added to the API can be listed by older CLIs.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct EnclaveSizeDefinition {
+    pub size: String,
+    pub cpus: u32,
+    pub memory_mib: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct EnclaveSizes {
+    pub sizes: Vec<EnclaveSizeDefinition>,
+}
+
+impl EnclaveSizes {
+    pub fn resolve(&self, size: EnclaveSize) -> Option<&EnclaveSizeDefinition> {
+        let name = size.to_string();
+        self.sizes.iter().find(|definition| definition.size == name)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct EnclaveScalingConfig {
+    limits: ScalingLimits,
+    config: ScalingConfig,
+}
+
+impl EnclaveScalingConfig {
+    pub fn max_instances(&self) -> u32 {
+        self.limits.max_instances
+    }
+
+    pub fn available_instances(&self) -> u32 {
+        self.limits.available_instances
+    }
+
+    pub fn desired_replicas(&self) -> u32 {
+        self.config.desired_replicas
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScalingLimits {
+    max_instances: u32,
+    available_instances: u32,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScalingConfig {
+    desired_replicas: u32,
+}
+
+impl std::convert::From<u32> for ScalingConfig {
+    fn from(value: u32) -> Self {
+        Self {
+            desired_replicas: value,
+        }
+    }
+}
+
+pub type UpdateEnclaveScalingConfigRequest = ScalingConfig;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn test_pcrs() -> PCRs {
+        let pcr = |byte: &str| byte.repeat(48).parse().unwrap();
+        PCRs {
+            pcr0: pcr("00"),
+            pcr1: pcr("11"),
+            pcr2: pcr("22"),
+            pcr8: None,
+        }
+    }
+
+    #[test]
+    fn test_deployment_intent_builder() {
+        let settings = DeploymentSettings::default();
+        let pcrs = test_pcrs();
+        let digests = RuntimeDigests {
+            data_plane_variant: "egress-disabled/tls-termination-enabled".into(),
+            data_plane: "sha256:aaaa".into(),
+            installer: "sha256:bbbb".into(),
+        };
+        let intent = CreateEnclaveDeploymentIntentRequest::builder(settings.clone(), &pcrs)
+            .eif_size_bytes(1024)
+            .runtime_versions("1.2.0", "abc123")
+            .runtime_digests(Some(&digests))
+            .git_metadata("deadbeef".into(), "0".into())
+            .desired_replicas(Some(3))
+            .build()
+            .unwrap();
+        assert_eq!(intent.eif_size_bytes, 1024);
+        assert_eq!(intent.metadata.data_plane_version, "1.2.0");
+        assert_eq!(intent.metadata.installer_version, "abc123");
+        assert_eq!(
+            intent.metadata.installer_digest.as_deref(),
+            Some("sha256:bbbb")
+        );
+        assert_eq!(intent.metadata.git_hash, "deadbeef");
+        assert_eq!(intent.desired_replicas, Some(3));
+        assert!(intent.strategy.is_none());
+        let serialized = serde_json::to_value(&intent).unwrap();
+        assert!(serialized.get("restartPolicy").is_none());
+        assert!(serialized.get("eifSha256").is_none());
+
+        let settings = DeploymentSettings {
+            restart_policy: RestartPolicy::OnFailure,
+            ..settings
+        };
+        let intent = CreateEnclaveDeploymentIntentRequest::builder(settings, &pcrs)
+            .eif_size_bytes(1024)
+            .eif_digests("aaaa", "bbbb")
+            .runtime_versions("1.2.0", "abc123")
+            .build()
+            .unwrap();
+        let serialized = serde_json::to_value(&intent).unwrap();
+        assert_eq!(serialized["restartPolicy"], "on-failure");
+        assert_eq!(serialized["eifSha256"], "aaaa");
+        assert_eq!(serialized["eifSha384"], "bbbb");
+        assert!(serialized.get("resources").is_none());
+    }
+
+    #[test]
+    fn test_enclave_size_resources() {
+        let sizes: EnclaveSizes = serde_json::from_str(
+            r#"{"sizes":[{"size":"small","cpus":2,"memoryMib":4096},{"size":"xlarge","cpus":16,"memoryMib":65536,"description":"Dedicated host"}]}"#,
+        )
+        .unwrap();
+        assert!(sizes.resolve(EnclaveSize::Medium).is_none());
+        let small = sizes.resolve(EnclaveSize::Small).unwrap();
+        assert_eq!((small.cpus, small.memory_mib), (2, 4096));
+
+        let pcrs = test_pcrs();
+        let intent =
+            CreateEnclaveDeploymentIntentRequest::builder(DeploymentSettings::default(), &pcrs)
+                .eif_size_bytes(1024)
+                .runtime_versions("1.2.0", "abc123")
+                .resources(Some(small))
+                .build()
+                .unwrap();
+        let serialized = serde_json::to_value(&intent).unwrap();
+        assert_eq!(
+            serialized["resources"],
+            serde_json::json!({"size": "small", "cpus": 2, "memoryMib": 4096})
+        );
+    }
+
+    #[test]
+    fn test_deployment_intent_builder_validation() {
+        let pcrs = test_pcrs();
+        let builder = || {
+            CreateEnclaveDeploymentIntentRequest::builder(DeploymentSettings::default(), &pcrs)
+                .eif_size_bytes(1024)
+                .runtime_versions("1.2.0", "abc123")
+        };
+
+        assert!(matches!(
+            builder().eif_size_bytes(0).build(),
+            Err(DeploymentIntentError::EmptyEif)
+        ));
+        assert!(matches!(
+            builder().runtime_versions("1.2.0", " ").build(),
+            Err(DeploymentIntentError::MissingVersion("installer"))
+        ));
+        assert!(matches!(
+            builder().runtime_versions("", "abc123").build(),
+            Err(DeploymentIntentError::MissingVersion("data plane"))
+        ));
+        for replicas in [0, MAX_DESIRED_REPLICAS + 1] {
+            assert!(matches!(
+                builder().desired_replicas(Some(replicas)).build(),
+                Err(DeploymentIntentError::InvalidReplicaCount(count)) if count == replicas
+            ));
+        }
+        assert!(builder()
+            .desired_replicas(Some(MAX_DESIRED_REPLICAS))
+            .build()
+            .is_ok());
+        assert!(builder().desired_replicas(None).build().is_ok());
+    }
+
+    fn get_testing_deployment() -> EnclaveDeployment {
+        EnclaveDeployment {
+            uuid: "abc".to_string(),
+            enclave_uuid: "def".to_string(),
+            version_uuid: "ghi".to_string(),
+            signing_cert_uuid: "jkl".to_string(),
+            debug_mode: false,
+            started_at: None,
+            completed_at: None,
+        }
+    }
+
+    fn get_testing_version() -> EnclaveVersion {
+        EnclaveVersion {
+            uuid: "abc".to_string(),
+            version: 1,
+            control_plane_img_url: Some("control-plane.com".to_string()).into(),
+            control_plane_version: Some("1.0.0".to_string()),
+            data_plane_version: Some("1.0.0".to_string()),
+            build_status: BuildStatus::Ready,
+            failure_reason: None,
+            started_at: None,
+            healthcheck: None,
+            build_steps: vec![],
+            pcrs: None,
+            pcrs_signature: None,
+            installer_version: None,
+            git_hash: None,
+            git_timestamp: None,
+        }
+    }
+
+    fn get_testing_cert() -> EnclaveSigningCert {
+        EnclaveSigningCert {
+            name: Some("abc".to_string()),
+            uuid: "abc".to_string(),
+            app_uuid: "def".to_string(),
+            cert_hash: "ghi".to_string(),
+            not_before: None,
+            not_after: None,
+        }
+    }
+
+    #[test]
+    fn test_empty_regional_deployments() {
+        let deployment = get_testing_deployment();
+        let version = get_testing_version();
+        let cert = get_testing_cert();
+        let deployment_with_empty_regional = GetEnclaveDeploymentResponse {
+            deployment,
+            enclave_version: version,
+            enclave_signing_cert: cert,
+            enclave_regional_deployments: vec![],
+            job_execution: None,
+        };
+
+        assert!(deployment_with_empty_regional
+            .get_detailed_status()
+            .is_none());
+        assert!(deployment_with_empty_regional
+            .get_failure_reason()
+            .is_none());
+        assert!(!deployment_with_empty_regional.is_failed());
+    }
+
+    #[test]
+    fn test_populated_regional_deployments() {
+        let deployment = get_testing_deployment();
+        let version = get_testing_version();
+        let cert = get_testing_cert();
+
+        let failure_reason = "An error occurred provisioning your TEE".to_string();
+        let detailed_failure_reason = "Insufficient capacity".to_string();
+        let deployment_with_regional = GetEnclaveDeploymentResponse {
+            deployment,
+            enclave_version: version,
+            enclave_signing_cert: cert,
+            enclave_regional_deployments: vec![EnclaveRegionalDeployment {
+                uuid: "abc".to_string(),
+                deployment_uuid: "def".to_string(),
+                deployment_order: 1,
+                region: "us-east-1".to_string(),
+                failure_reason: Some(failure_reason.clone()),
+                deploy_status: DeployStatus::Failed,
+                started_at: None,
+                completed_at: None,
+                detailed_status: Some(detailed_failure_reason.clone()),
+                replica_rollover: None,
+            }],
+            job_execution: None,
+        };
+
+        assert!(deployment_with_regional.is_failed());
+        assert_eq!(
+            deployment_with_regional.get_failure_reason(),
+            Some(failure_reason)
+        );
+        assert_eq!(
+            deployment_with_regional.get_detailed_status(),
+            Some(detailed_failure_reason)
+        );
+    }
+
+    #[test]
+    fn test_deployment_intent_upload_format_defaults_to_zip() {
+        let legacy_intent: CreateEnclaveDeploymentIntentResponse = serde_json::from_str(
+            r#"{"signedUrl":"https://s3","enclaveUuid":"abc","deploymentUuid":"def","version":1}"#,
+        )
+        .unwrap();
+        assert_eq!(legacy_intent.upload_format(), UploadFormat::Zip);
+
+        let zstd_intent: CreateEnclaveDeploymentIntentResponse = serde_json::from_str(
+            r#"{"signedUrl":"https://s3","enclaveUuid":"abc","deploymentUuid":"def","version":1,"uploadFormat":"zstd"}"#,
+        )
+        .unwrap();
+        assert_eq!(zstd_intent.upload_format(), UploadFormat::Zstd);
+    }
+
+    #[test]
+    fn test_replica_rollover_deserializes_from_regional_deployment() {
+        let regional: EnclaveRegionalDeployment = serde_json::from_str(
+            r#"{"uuid":"abc","deploymentUuid":"def","deploymentOrder":1,"region":"us-east-1","deployStatus":"deploying","replicaRollover":{"desiredReplicas":3,"newReplicasReady":2,"oldReplicasRemaining":1}}"#,
+        )
+        .unwrap();
+        let rollover = regional.replica_rollover.unwrap();
+        assert_eq!(
+            rollover.describe(),
+            "Rolling over replicas - 2 of 3 new replicas ready, 1 old replica still serving traffic"
+        );
+        assert_eq!(
+            serde_json::to_value(DeployStrategy::BlueGreen).unwrap(),
+            "blue-green"
+        );
+    }
+}
@@ -0,0 +1,11 @@
+//! Typed client for the Evervault Enclaves API, shared by the CLI and other tools which manage
+//! Enclaves.
+//!
+//! The crate follows semantic versioning: removing or changing the type of a public item, or
+//! adding a method to [`enclave::EnclaveApi`], is a breaking change. Fields added to responses
+//! are optional until the next major version, so older API versions can still be parsed. See
+//! CHANGELOG.md for the changes in each version.
+pub mod cache;
+pub mod enclave;
+
+pub use reqwest::Client;